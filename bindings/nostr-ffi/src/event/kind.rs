@@ -164,6 +164,10 @@ pub enum KindEnum {
     ///
     /// <https://github.com/nostr-protocol/nips/blob/master/51.md>
     Emojis,
+    /// Inbox Relays (NIP17)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/17.md>
+    InboxRelays,
     /// Follow Sets
     ///
     /// <https://github.com/nostr-protocol/nips/blob/master/51.md>
@@ -220,6 +224,40 @@ pub enum KindEnum {
     GiftWrap,
     /// GiftWrapped Sealed Direct message
     SealedDirect,
+    /// Group Add User (NIP29)
+    GroupAddUser,
+    /// Group Remove User (NIP29)
+    GroupRemoveUser,
+    /// Group Edit Metadata (NIP29)
+    GroupEditMetadata,
+    /// Group Add Permission (NIP29)
+    GroupAddPermission,
+    /// Group Remove Permission (NIP29)
+    GroupRemovePermission,
+    /// Group Delete Event (NIP29)
+    GroupDeleteEvent,
+    /// Group Edit Status (NIP29)
+    GroupEditStatus,
+    /// Group Create (NIP29)
+    GroupCreate,
+    /// Group Join Request (NIP29)
+    GroupJoinRequest,
+    /// Group Leave Request (NIP29)
+    GroupLeaveRequest,
+    /// Group Metadata (NIP29)
+    GroupMetadata,
+    /// Group Admins (NIP29)
+    GroupAdmins,
+    /// Group Members (NIP29)
+    GroupMembers,
+    /// Date-Based Calendar Event (NIP52)
+    DateBasedCalendarEvent,
+    /// Time-Based Calendar Event (NIP52)
+    TimeBasedCalendarEvent,
+    /// Calendar (NIP52)
+    Calendar,
+    /// Calendar Event RSVP (NIP52)
+    CalendarEventRsvp,
     /// Long-form Text Note (NIP23)
     LongFormTextNote,
     /// Application-specific Data (NIP78)
@@ -296,6 +334,7 @@ impl From<nostr::Kind> for KindEnum {
             nostr::Kind::SimpleGroups => Self::SimpleGroups,
             nostr::Kind::Interests => Self::Interests,
             nostr::Kind::Emojis => Self::Emojis,
+            nostr::Kind::InboxRelays => Self::InboxRelays,
             nostr::Kind::FollowSets => Self::FollowSets,
             nostr::Kind::RelaySets => Self::RelaySets,
             nostr::Kind::BookmarkSets => Self::BookmarkSets,
@@ -316,6 +355,23 @@ impl From<nostr::Kind> for KindEnum {
             nostr::Kind::Seal => Self::Seal,
             nostr::Kind::GiftWrap => Self::GiftWrap,
             nostr::Kind::SealedDirect => Self::SealedDirect,
+            nostr::Kind::GroupAddUser => Self::GroupAddUser,
+            nostr::Kind::GroupRemoveUser => Self::GroupRemoveUser,
+            nostr::Kind::GroupEditMetadata => Self::GroupEditMetadata,
+            nostr::Kind::GroupAddPermission => Self::GroupAddPermission,
+            nostr::Kind::GroupRemovePermission => Self::GroupRemovePermission,
+            nostr::Kind::GroupDeleteEvent => Self::GroupDeleteEvent,
+            nostr::Kind::GroupEditStatus => Self::GroupEditStatus,
+            nostr::Kind::GroupCreate => Self::GroupCreate,
+            nostr::Kind::GroupJoinRequest => Self::GroupJoinRequest,
+            nostr::Kind::GroupLeaveRequest => Self::GroupLeaveRequest,
+            nostr::Kind::GroupMetadata => Self::GroupMetadata,
+            nostr::Kind::GroupAdmins => Self::GroupAdmins,
+            nostr::Kind::GroupMembers => Self::GroupMembers,
+            nostr::Kind::DateBasedCalendarEvent => Self::DateBasedCalendarEvent,
+            nostr::Kind::TimeBasedCalendarEvent => Self::TimeBasedCalendarEvent,
+            nostr::Kind::Calendar => Self::Calendar,
+            nostr::Kind::CalendarEventRsvp => Self::CalendarEventRsvp,
             nostr::Kind::LongFormTextNote => Self::LongFormTextNote,
             nostr::Kind::ApplicationSpecificData => Self::ApplicationSpecificData,
             nostr::Kind::FileMetadata => Self::FileMetadata,
@@ -373,6 +429,7 @@ impl From<KindEnum> for nostr::Kind {
             KindEnum::SimpleGroups => Self::SimpleGroups,
             KindEnum::Interests => Self::Interests,
             KindEnum::Emojis => Self::Emojis,
+            KindEnum::InboxRelays => Self::InboxRelays,
             KindEnum::FollowSets => Self::FollowSets,
             KindEnum::RelaySets => Self::RelaySets,
             KindEnum::BookmarkSets => Self::BookmarkSets,
@@ -393,6 +450,23 @@ impl From<KindEnum> for nostr::Kind {
             KindEnum::Seal => Self::Seal,
             KindEnum::GiftWrap => Self::GiftWrap,
             KindEnum::SealedDirect => Self::SealedDirect,
+            KindEnum::GroupAddUser => Self::GroupAddUser,
+            KindEnum::GroupRemoveUser => Self::GroupRemoveUser,
+            KindEnum::GroupEditMetadata => Self::GroupEditMetadata,
+            KindEnum::GroupAddPermission => Self::GroupAddPermission,
+            KindEnum::GroupRemovePermission => Self::GroupRemovePermission,
+            KindEnum::GroupDeleteEvent => Self::GroupDeleteEvent,
+            KindEnum::GroupEditStatus => Self::GroupEditStatus,
+            KindEnum::GroupCreate => Self::GroupCreate,
+            KindEnum::GroupJoinRequest => Self::GroupJoinRequest,
+            KindEnum::GroupLeaveRequest => Self::GroupLeaveRequest,
+            KindEnum::GroupMetadata => Self::GroupMetadata,
+            KindEnum::GroupAdmins => Self::GroupAdmins,
+            KindEnum::GroupMembers => Self::GroupMembers,
+            KindEnum::DateBasedCalendarEvent => Self::DateBasedCalendarEvent,
+            KindEnum::TimeBasedCalendarEvent => Self::TimeBasedCalendarEvent,
+            KindEnum::Calendar => Self::Calendar,
+            KindEnum::CalendarEventRsvp => Self::CalendarEventRsvp,
             KindEnum::LongFormTextNote => Self::LongFormTextNote,
             KindEnum::ApplicationSpecificData => Self::ApplicationSpecificData,
             KindEnum::FileMetadata => Self::FileMetadata,
@@ -221,6 +221,12 @@ pub enum TagKind {
     Ends,
     /// Status
     Status,
+    /// Start timezone (NIP52)
+    StartTzid,
+    /// End timezone (NIP52)
+    EndTzid,
+    /// Location (NIP52)
+    Location,
     /// Current participants
     CurrentParticipants,
     /// Total participants
@@ -277,6 +283,9 @@ impl From<tag::TagKind> for TagKind {
             tag::TagKind::Starts => Self::Starts,
             tag::TagKind::Ends => Self::Ends,
             tag::TagKind::Status => Self::Status,
+            tag::TagKind::StartTzid => Self::StartTzid,
+            tag::TagKind::EndTzid => Self::EndTzid,
+            tag::TagKind::Location => Self::Location,
             tag::TagKind::CurrentParticipants => Self::CurrentParticipants,
             tag::TagKind::TotalParticipants => Self::TotalParticipants,
             tag::TagKind::Method => Self::Method,
@@ -326,6 +335,9 @@ impl From<TagKind> for tag::TagKind {
             TagKind::Starts => Self::Starts,
             TagKind::Ends => Self::Ends,
             TagKind::Status => Self::Status,
+            TagKind::StartTzid => Self::StartTzid,
+            TagKind::EndTzid => Self::EndTzid,
+            TagKind::Location => Self::Location,
             TagKind::CurrentParticipants => Self::CurrentParticipants,
             TagKind::TotalParticipants => Self::TotalParticipants,
             TagKind::Method => Self::Method,
@@ -389,6 +401,9 @@ pub enum TagEnum {
     Identifier {
         identifier: String,
     },
+    Group {
+        group_id: String,
+    },
     ExternalIdentityTag {
         identity: Identity,
     },
@@ -596,6 +611,7 @@ impl From<tag::Tag> for TagEnum {
             tag::Tag::Hashtag(t) => Self::Hashtag { hashtag: t },
             tag::Tag::Geohash(g) => Self::Geohash { geohash: g },
             tag::Tag::Identifier(d) => Self::Identifier { identifier: d },
+            tag::Tag::Group(h) => Self::Group { group_id: h },
             tag::Tag::A {
                 coordinate,
                 relay_url,
@@ -767,6 +783,7 @@ impl TryFrom<TagEnum> for tag::Tag {
             TagEnum::Hashtag { hashtag } => Ok(Self::Hashtag(hashtag)),
             TagEnum::Geohash { geohash } => Ok(Self::Geohash(geohash)),
             TagEnum::Identifier { identifier } => Ok(Self::Identifier(identifier)),
+            TagEnum::Group { group_id } => Ok(Self::Group(group_id)),
             TagEnum::ExternalIdentityTag { identity } => {
                 Ok(Self::ExternalIdentity(identity.into()))
             }
@@ -12,7 +12,7 @@ client.add_relay("wss://relay.damus.io")
 client.connect()
 
 event = EventBuilder.text_note("Hello from Rust Nostr Python bindings!", []).to_event(keys)
-event_id = client.send_event(event)
+event_id = client.send_event(event).id()
 print("Event sent:")
 print(f" hex:    {event_id.to_hex()}")
 print(f" bech32: {event_id.to_bech32()}")
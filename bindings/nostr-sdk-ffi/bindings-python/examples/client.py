@@ -37,7 +37,7 @@ client.set_metadata(Metadata().set_name("Testing Rust Nostr"))
 custom_keys = Keys.generate() 
 print("Mining a POW text note...")
 event = EventBuilder.text_note("Hello from Rust Nostr Python bindings!", []).to_pow_event(custom_keys, 20)
-event_id = client.send_event(event)
+event_id = client.send_event(event).id()
 print("Event sent:")
 print(f" hex:    {event_id.to_hex()}")
 print(f" bech32: {event_id.to_bech32()}")
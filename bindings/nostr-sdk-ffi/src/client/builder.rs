@@ -7,6 +7,7 @@ use std::sync::Arc;
 
 use nostr_ffi::helper::unwrap_or_clone_arc;
 use nostr_sdk::database::DynNostrDatabase;
+use nostr_sdk::signer::DynNostrSigner;
 use nostr_sdk::zapper::DynNostrZapper;
 use uniffi::Object;
 
@@ -36,7 +37,7 @@ impl ClientBuilder {
     }
 
     pub fn signer(self: Arc<Self>, signer: Arc<NostrSigner>) -> Self {
-        let signer: nostr_sdk::NostrSigner = signer.as_ref().deref().clone();
+        let signer: Arc<DynNostrSigner> = signer.as_ref().deref().clone();
         let mut builder = unwrap_or_clone_arc(self);
         builder.inner = builder.inner.signer(signer);
         builder
@@ -27,6 +27,7 @@ pub use self::options::Options;
 pub use self::signer::NostrSigner;
 use self::zapper::{ZapDetails, ZapEntity};
 use crate::error::Result;
+use crate::pool::PublishReport;
 use crate::relay::options::{NegentropyOptions, SubscribeAutoCloseOptions};
 use crate::relay::RelayOptions;
 use crate::{HandleNotification, NostrDatabase, Relay};
@@ -225,6 +226,54 @@ impl Client {
         })
     }
 
+    /// Subscribe to filters to specific relays
+    ///
+    /// ### Auto-closing subscription
+    ///
+    /// It's possible to automatically close a subscription by configuring the `SubscribeAutoCloseOptions`.
+    pub fn subscribe_to(
+        &self,
+        urls: Vec<String>,
+        filters: Vec<Arc<Filter>>,
+        opts: Option<Arc<SubscribeAutoCloseOptions>>,
+    ) -> Result<String> {
+        let filters = filters
+            .into_iter()
+            .map(|f| f.as_ref().deref().clone())
+            .collect();
+        block_on(async move {
+            Ok(self
+                .inner
+                .subscribe_to(urls, filters, opts.map(|o| **o))
+                .await?
+                .to_string())
+        })
+    }
+
+    /// Subscribe to filters with custom subscription ID to specific relays
+    ///
+    /// ### Auto-closing subscription
+    ///
+    /// It's possible to automatically close a subscription by configuring the `SubscribeAutoCloseOptions`.
+    pub fn subscribe_with_id_to(
+        &self,
+        urls: Vec<String>,
+        id: String,
+        filters: Vec<Arc<Filter>>,
+        opts: Option<Arc<SubscribeAutoCloseOptions>>,
+    ) -> Result<()> {
+        let filters = filters
+            .into_iter()
+            .map(|f| f.as_ref().deref().clone())
+            .collect();
+        block_on(async move {
+            Ok(self
+                .inner
+                .subscribe_with_id_to(urls, SubscriptionId::new(id), filters, opts.map(|o| **o))
+                .await?)
+        })
+    }
+
     pub fn unsubscribe(&self, subscription_id: String) {
         block_on(async move {
             self.inner
@@ -296,7 +345,7 @@ impl Client {
         })
     }
 
-    pub fn send_event(&self, event: Arc<Event>) -> Result<Arc<EventId>> {
+    pub fn send_event(&self, event: Arc<Event>) -> Result<Arc<PublishReport>> {
         block_on(async move {
             Ok(Arc::new(
                 self.inner
@@ -307,7 +356,11 @@ impl Client {
         })
     }
 
-    pub fn send_event_to(&self, urls: Vec<String>, event: Arc<Event>) -> Result<Arc<EventId>> {
+    pub fn send_event_to(
+        &self,
+        urls: Vec<String>,
+        event: Arc<Event>,
+    ) -> Result<Arc<PublishReport>> {
         block_on(async move {
             Ok(Arc::new(
                 self.inner
@@ -333,7 +386,7 @@ impl Client {
     /// Take an [`EventBuilder`], sign it by using the [`NostrSigner`] and broadcast to all relays.
     ///
     /// Rise an error if the [`NostrSigner`] is not set.
-    pub fn send_event_builder(&self, builder: Arc<EventBuilder>) -> Result<Arc<EventId>> {
+    pub fn send_event_builder(&self, builder: Arc<EventBuilder>) -> Result<Arc<PublishReport>> {
         block_on(async move {
             Ok(Arc::new(
                 self.inner
@@ -351,7 +404,7 @@ impl Client {
         &self,
         urls: Vec<String>,
         builder: Arc<EventBuilder>,
-    ) -> Result<Arc<EventId>> {
+    ) -> Result<Arc<PublishReport>> {
         block_on(async move {
             Ok(Arc::new(
                 self.inner
@@ -490,6 +543,25 @@ impl Client {
         })
     }
 
+    /// Send Private Direct message to a chat room (NIP17)
+    ///
+    /// The message is gift wrapped to every receiver and to the user, and sent to their inbox relays.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/17.md>
+    pub fn send_private_msg(
+        &self,
+        receivers: Vec<Arc<PublicKey>>,
+        message: String,
+        subject: Option<String>,
+    ) -> Result<()> {
+        block_on(async move {
+            self.inner
+                .send_private_msg(receivers.into_iter().map(|p| **p), message, subject)
+                .await?;
+            Ok(())
+        })
+    }
+
     pub fn file_metadata(
         &self,
         description: String,
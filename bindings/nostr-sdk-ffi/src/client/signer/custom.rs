@@ -0,0 +1,115 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use nostr_ffi::{Event, PublicKey, UnsignedEvent};
+
+use crate::error::Result;
+
+#[uniffi::export(callback_interface)]
+pub trait CustomNostrSigner: Send + Sync + Debug {
+    /// Name of backend
+    fn backend(&self) -> String;
+
+    /// Get signer public key
+    fn public_key(&self) -> Result<Arc<PublicKey>>;
+
+    /// Sign an unsigned event
+    fn sign_event(&self, unsigned: Arc<UnsignedEvent>) -> Result<Arc<Event>>;
+
+    /// NIP04 encrypt
+    fn nip04_encrypt(&self, public_key: Arc<PublicKey>, content: String) -> Result<String>;
+
+    /// NIP04 decrypt
+    fn nip04_decrypt(
+        &self,
+        public_key: Arc<PublicKey>,
+        encrypted_content: String,
+    ) -> Result<String>;
+
+    /// NIP44 encrypt
+    fn nip44_encrypt(&self, public_key: Arc<PublicKey>, content: String) -> Result<String>;
+
+    /// NIP44 decrypt
+    fn nip44_decrypt(&self, public_key: Arc<PublicKey>, payload: String) -> Result<String>;
+}
+
+#[derive(Debug)]
+pub(super) struct IntermediateCustomNostrSigner {
+    pub(super) inner: Box<dyn CustomNostrSigner>,
+}
+
+mod inner {
+    use std::ops::Deref;
+    use std::sync::Arc;
+
+    use nostr_sdk::prelude::*;
+    use nostr_sdk::signer::{SignerBackend, SignerError};
+
+    use super::IntermediateCustomNostrSigner;
+
+    #[async_trait]
+    impl NostrSigner for IntermediateCustomNostrSigner {
+        type Err = SignerError;
+
+        fn backend(&self) -> SignerBackend {
+            SignerBackend::Custom(self.inner.backend())
+        }
+
+        async fn public_key(&self) -> Result<PublicKey, Self::Err> {
+            let public_key = self.inner.public_key().map_err(SignerError::backend)?;
+            Ok(**public_key)
+        }
+
+        async fn sign_event(&self, unsigned: UnsignedEvent) -> Result<Event, Self::Err> {
+            let event = self
+                .inner
+                .sign_event(Arc::new(unsigned.into()))
+                .map_err(SignerError::backend)?;
+            Ok(event.as_ref().deref().clone())
+        }
+
+        async fn nip04_encrypt(
+            &self,
+            public_key: PublicKey,
+            content: String,
+        ) -> Result<String, Self::Err> {
+            self.inner
+                .nip04_encrypt(Arc::new(public_key.into()), content)
+                .map_err(SignerError::backend)
+        }
+
+        async fn nip04_decrypt(
+            &self,
+            public_key: PublicKey,
+            encrypted_content: String,
+        ) -> Result<String, Self::Err> {
+            self.inner
+                .nip04_decrypt(Arc::new(public_key.into()), encrypted_content)
+                .map_err(SignerError::backend)
+        }
+
+        async fn nip44_encrypt(
+            &self,
+            public_key: PublicKey,
+            content: String,
+        ) -> Result<String, Self::Err> {
+            self.inner
+                .nip44_encrypt(Arc::new(public_key.into()), content)
+                .map_err(SignerError::backend)
+        }
+
+        async fn nip44_decrypt(
+            &self,
+            public_key: PublicKey,
+            payload: String,
+        ) -> Result<String, Self::Err> {
+            self.inner
+                .nip44_decrypt(Arc::new(public_key.into()), payload)
+                .map_err(SignerError::backend)
+        }
+    }
+}
@@ -3,31 +3,35 @@
 // Distributed under the MIT software license
 
 use std::ops::Deref;
+use std::sync::Arc;
 
 use nostr_ffi::{Event, EventBuilder, Keys, PublicKey, UnsignedEvent};
-use nostr_sdk::{block_on, signer};
+use nostr_sdk::block_on;
+use nostr_sdk::signer::{DynNostrSigner, IntoNostrSigner};
 use uniffi::Object;
 
+pub mod custom;
 pub mod nip46;
 
+use self::custom::{CustomNostrSigner, IntermediateCustomNostrSigner};
 use self::nip46::Nip46Signer;
 use crate::error::Result;
 
 #[derive(Object)]
 pub struct NostrSigner {
-    inner: signer::NostrSigner,
+    inner: Arc<DynNostrSigner>,
 }
 
 impl Deref for NostrSigner {
-    type Target = signer::NostrSigner;
+    type Target = Arc<DynNostrSigner>;
 
     fn deref(&self) -> &Self::Target {
         &self.inner
     }
 }
 
-impl From<signer::NostrSigner> for NostrSigner {
-    fn from(inner: signer::NostrSigner) -> Self {
+impl From<Arc<DynNostrSigner>> for NostrSigner {
+    fn from(inner: Arc<DynNostrSigner>) -> Self {
         Self { inner }
     }
 }
@@ -37,17 +41,31 @@ impl NostrSigner {
     #[uniffi::constructor]
     pub fn keys(keys: &Keys) -> Self {
         Self {
-            inner: signer::NostrSigner::Keys(keys.deref().clone()),
+            inner: keys.deref().clone().into_nostr_signer(),
         }
     }
 
     #[uniffi::constructor]
     pub fn nip46(nip46: &Nip46Signer) -> Self {
         Self {
-            inner: signer::NostrSigner::nip46(nip46.deref().clone()),
+            inner: nip46.deref().clone().into_nostr_signer(),
         }
     }
 
+    #[uniffi::constructor]
+    pub fn custom(signer: Box<dyn CustomNostrSigner>) -> Self {
+        let intermediate = IntermediateCustomNostrSigner { inner: signer };
+
+        Self {
+            inner: intermediate.into_nostr_signer(),
+        }
+    }
+
+    /// Name of the signer backend
+    pub fn backend(&self) -> String {
+        self.inner.backend().to_string()
+    }
+
     /// Get signer public key
     pub fn public_key(&self) -> Result<PublicKey> {
         block_on(async move { Ok(self.inner.public_key().await?.into()) })
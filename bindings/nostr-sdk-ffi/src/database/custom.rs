@@ -204,6 +204,22 @@ mod inner {
                 .collect())
         }
 
+        async fn outbox_add(&self, _event: &Event, _relays: HashSet<Url>) -> Result<(), Self::Err> {
+            Err(DatabaseError::NotSupported)
+        }
+
+        async fn outbox_remove(
+            &self,
+            _event_id: EventId,
+            _relay_url: Url,
+        ) -> Result<(), Self::Err> {
+            Err(DatabaseError::NotSupported)
+        }
+
+        async fn outbox(&self, _relay_url: Url) -> Result<Vec<Event>, Self::Err> {
+            Err(DatabaseError::NotSupported)
+        }
+
         async fn delete(&self, filter: Filter) -> Result<(), Self::Err> {
             self.inner
                 .delete(Arc::new(filter.into()))
@@ -80,8 +80,8 @@ impl From<nostr_sdk::SQLiteError> for NostrSdkError {
     }
 }
 
-impl From<nostr_sdk::signer::Error> for NostrSdkError {
-    fn from(e: nostr_sdk::signer::Error) -> NostrSdkError {
+impl From<nostr_sdk::signer::SignerError> for NostrSdkError {
+    fn from(e: nostr_sdk::signer::SignerError) -> NostrSdkError {
         Self::Generic(e.to_string())
     }
 }
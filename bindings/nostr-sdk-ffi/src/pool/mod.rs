@@ -8,11 +8,14 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use async_utility::thread;
-use nostr_ffi::{ClientMessage, Event, EventId, Filter};
+use nostr_ffi::{ClientMessage, Event, Filter};
 use nostr_sdk::database::DynNostrDatabase;
 use nostr_sdk::{block_on, spawn_blocking, RelayPoolOptions, SubscriptionId};
 use uniffi::Object;
 
+pub mod report;
+
+pub use self::report::{PublishOutcome, PublishReport};
 use crate::error::Result;
 use crate::negentropy::NegentropyItem;
 use crate::relay::options::{FilterOptions, NegentropyOptions};
@@ -185,7 +188,7 @@ impl RelayPool {
     }
 
     /// Send event to **all connected relays** and wait for `OK` message
-    pub fn send_event(&self, event: &Event, opts: &RelaySendOptions) -> Result<Arc<EventId>> {
+    pub fn send_event(&self, event: &Event, opts: &RelaySendOptions) -> Result<Arc<PublishReport>> {
         block_on(async move {
             Ok(Arc::new(
                 self.inner
@@ -197,12 +200,20 @@ impl RelayPool {
     }
 
     /// Send multiple `Event` at once to **all connected relays** and wait for `OK` message
-    pub fn batch_event(&self, events: Vec<Arc<Event>>, opts: &RelaySendOptions) -> Result<()> {
+    pub fn batch_event(
+        &self,
+        events: Vec<Arc<Event>>,
+        opts: &RelaySendOptions,
+    ) -> Result<Arc<PublishReport>> {
         let events = events
             .into_iter()
             .map(|e| e.as_ref().deref().clone())
             .collect();
-        block_on(async move { Ok(self.inner.batch_event(events, **opts).await?) })
+        block_on(async move {
+            Ok(Arc::new(
+                self.inner.batch_event(events, **opts).await?.into(),
+            ))
+        })
     }
 
     /// Send event to **specific relays** and wait for `OK` message
@@ -211,7 +222,7 @@ impl RelayPool {
         urls: Vec<String>,
         event: &Event,
         opts: &RelaySendOptions,
-    ) -> Result<Arc<EventId>> {
+    ) -> Result<Arc<PublishReport>> {
         block_on(async move {
             Ok(Arc::new(
                 self.inner
@@ -228,12 +239,19 @@ impl RelayPool {
         urls: Vec<String>,
         events: Vec<Arc<Event>>,
         opts: &RelaySendOptions,
-    ) -> Result<()> {
+    ) -> Result<Arc<PublishReport>> {
         let events = events
             .into_iter()
             .map(|e| e.as_ref().deref().clone())
             .collect();
-        block_on(async move { Ok(self.inner.batch_event_to(urls, events, **opts).await?) })
+        block_on(async move {
+            Ok(Arc::new(
+                self.inner
+                    .batch_event_to(urls, events, **opts)
+                    .await?
+                    .into(),
+            ))
+        })
     }
 
     /// Subscribe to filters
@@ -0,0 +1,117 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use nostr_ffi::EventId;
+use nostr_sdk::{pool, Url};
+use uniffi::{Enum, Object};
+
+use crate::error::Result;
+
+/// Outcome of publishing an event to a relay
+#[derive(Enum)]
+pub enum PublishOutcome {
+    /// Accepted by relay
+    Accepted {
+        /// Machine-readable prefix (ex. `duplicate`)
+        reason: Option<String>,
+        /// `OK` message
+        message: String,
+    },
+    /// Rejected by relay
+    Rejected {
+        /// Machine-readable prefix (ex. `blocked`, `pow`, `auth-required`)
+        reason: Option<String>,
+        /// `OK` message
+        message: String,
+    },
+    /// `OK` message not received in time
+    Timeout,
+    /// Event not sent or relay disconnected before the `OK` message
+    Failed { error: String },
+}
+
+impl From<&pool::PublishOutcome> for PublishOutcome {
+    fn from(value: &pool::PublishOutcome) -> Self {
+        match value {
+            pool::PublishOutcome::Accepted { reason, message } => Self::Accepted {
+                reason: reason.as_ref().map(|r| r.to_string()),
+                message: message.clone(),
+            },
+            pool::PublishOutcome::Rejected { reason, message } => Self::Rejected {
+                reason: reason.as_ref().map(|r| r.to_string()),
+                message: message.clone(),
+            },
+            pool::PublishOutcome::Timeout => Self::Timeout,
+            pool::PublishOutcome::Failed(error) => Self::Failed {
+                error: error.clone(),
+            },
+        }
+    }
+}
+
+/// Per-relay outcome of publishing events
+#[derive(Object)]
+pub struct PublishReport {
+    inner: pool::PublishReport,
+}
+
+impl From<pool::PublishReport> for PublishReport {
+    fn from(inner: pool::PublishReport) -> Self {
+        Self { inner }
+    }
+}
+
+#[uniffi::export]
+impl PublishReport {
+    /// Event ID (the first one, if many events have been sent)
+    pub fn id(&self) -> Arc<EventId> {
+        Arc::new(self.inner.id().into())
+    }
+
+    /// Event IDs
+    pub fn ids(&self) -> Vec<Arc<EventId>> {
+        self.inner
+            .ids()
+            .iter()
+            .map(|id| Arc::new((*id).into()))
+            .collect()
+    }
+
+    /// Outcome of the event for the relay
+    pub fn outcome(&self, url: String, id: &EventId) -> Result<Option<PublishOutcome>> {
+        let url: Url = Url::parse(&url)?;
+        Ok(self.inner.outcome(&url, id).map(|o| o.into()))
+    }
+
+    /// Relays that accepted the event
+    pub fn accepted(&self, id: &EventId) -> Vec<String> {
+        self.inner
+            .accepted(id)
+            .into_iter()
+            .map(|url| url.to_string())
+            .collect()
+    }
+
+    /// Relays that not accepted the event (rejected, timeout or failed), with the outcome
+    pub fn not_accepted(&self, id: &EventId) -> HashMap<String, PublishOutcome> {
+        self.inner
+            .not_accepted(id)
+            .into_iter()
+            .map(|(url, outcome)| (url.to_string(), outcome.into()))
+            .collect()
+    }
+
+    /// Check if at least one relay accepted at least one event
+    pub fn is_published(&self) -> bool {
+        self.inner.is_published()
+    }
+
+    /// Check if every event has been accepted by at least one relay
+    pub fn is_success(&self) -> bool {
+        self.inner.is_success()
+    }
+}
@@ -20,6 +20,8 @@ pub enum RelayStatus {
     Stopped,
     /// Relay completely disconnected
     Terminated,
+    /// Relay given up after too many failed connection attempts
+    Dead,
 }
 
 impl From<nostr_sdk::RelayStatus> for RelayStatus {
@@ -32,6 +34,7 @@ impl From<nostr_sdk::RelayStatus> for RelayStatus {
             nostr_sdk::RelayStatus::Disconnected => Self::Disconnected,
             nostr_sdk::RelayStatus::Stopped => Self::Stopped,
             nostr_sdk::RelayStatus::Terminated => Self::Terminated,
+            nostr_sdk::RelayStatus::Dead => Self::Dead,
         }
     }
 }
@@ -27,6 +27,7 @@ use crate::abortable::JsAbortHandle;
 use crate::database::JsNostrDatabase;
 use crate::duration::JsDuration;
 use crate::relay::options::{JsNegentropyOptions, JsSubscribeAutoCloseOptions};
+use crate::relay::publish::JsPublishReport;
 use crate::relay::{JsRelay, JsRelayArray};
 
 #[wasm_bindgen(js_name = Client)]
@@ -276,12 +277,12 @@ impl JsClient {
     /// This method will wait for the `OK` message from the relay.
     /// If you not want to wait for the `OK` message, use `sendMsg` method instead.
     #[wasm_bindgen(js_name = sendEvent)]
-    pub async fn send_event(&self, event: &JsEvent) -> Result<JsEventId> {
+    pub async fn send_event(&self, event: &JsEvent) -> Result<JsPublishReport> {
         self.inner
             .send_event(event.deref().clone())
             .await
             .map_err(into_err)
-            .map(|id| id.into())
+            .map(|report| report.into())
     }
 
     /// Send event to specific relay
@@ -289,12 +290,16 @@ impl JsClient {
     /// This method will wait for the `OK` message from the relay.
     /// If you not want to wait for the `OK` message, use `sendMsgTo` method instead.
     #[wasm_bindgen(js_name = sendEventTo)]
-    pub async fn send_event_to(&self, urls: Vec<String>, event: &JsEvent) -> Result<JsEventId> {
+    pub async fn send_event_to(
+        &self,
+        urls: Vec<String>,
+        event: &JsEvent,
+    ) -> Result<JsPublishReport> {
         self.inner
             .send_event_to(urls, event.deref().clone())
             .await
             .map_err(into_err)
-            .map(|id| id.into())
+            .map(|report| report.into())
     }
 
     /// Signs the `EventBuilder` into an `Event` using the `NostrSigner`
@@ -311,12 +316,12 @@ impl JsClient {
     ///
     /// Rise an error if the [`NostrSigner`] is not set.
     #[wasm_bindgen(js_name = sendEventBuilder)]
-    pub async fn send_event_builder(&self, builder: &JsEventBuilder) -> Result<JsEventId> {
+    pub async fn send_event_builder(&self, builder: &JsEventBuilder) -> Result<JsPublishReport> {
         self.inner
             .send_event_builder(builder.deref().clone())
             .await
             .map_err(into_err)
-            .map(|id| id.into())
+            .map(|report| report.into())
     }
 
     /// Take an [`EventBuilder`], sign it by using the [`NostrSigner`] and broadcast to specific relays.
@@ -327,12 +332,12 @@ impl JsClient {
         &self,
         urls: Vec<String>,
         builder: &JsEventBuilder,
-    ) -> Result<JsEventId> {
+    ) -> Result<JsPublishReport> {
         self.inner
             .send_event_builder_to(urls, builder.deref().clone())
             .await
             .map_err(into_err)
-            .map(|id| id.into())
+            .map(|report| report.into())
     }
 
     /// Update metadata
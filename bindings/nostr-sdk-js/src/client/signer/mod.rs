@@ -3,12 +3,13 @@
 // Distributed under the MIT software license
 
 use std::ops::Deref;
+use std::sync::Arc;
 
 use nostr_js::error::{into_err, Result};
 use nostr_js::event::{JsEvent, JsEventBuilder, JsUnsignedEvent};
 use nostr_js::key::{JsKeys, JsPublicKey};
 use nostr_js::nips::nip07::JsNip07Signer;
-use nostr_sdk::NostrSigner;
+use nostr_sdk::signer::{DynNostrSigner, IntoNostrSigner};
 use wasm_bindgen::prelude::*;
 
 pub mod nip46;
@@ -17,19 +18,19 @@ use self::nip46::JsNip46Signer;
 
 #[wasm_bindgen(js_name = NostrSigner)]
 pub struct JsNostrSigner {
-    inner: nostr_sdk::NostrSigner,
+    inner: Arc<DynNostrSigner>,
 }
 
 impl Deref for JsNostrSigner {
-    type Target = NostrSigner;
+    type Target = Arc<DynNostrSigner>;
 
     fn deref(&self) -> &Self::Target {
         &self.inner
     }
 }
 
-impl From<NostrSigner> for JsNostrSigner {
-    fn from(inner: NostrSigner) -> Self {
+impl From<Arc<DynNostrSigner>> for JsNostrSigner {
+    fn from(inner: Arc<DynNostrSigner>) -> Self {
         Self { inner }
     }
 }
@@ -39,21 +40,21 @@ impl JsNostrSigner {
     /// Private keys
     pub fn keys(keys: &JsKeys) -> Self {
         Self {
-            inner: NostrSigner::Keys(keys.deref().clone()),
+            inner: keys.deref().clone().into_nostr_signer(),
         }
     }
 
     /// NIP07
     pub fn nip07(signer: &JsNip07Signer) -> Self {
         Self {
-            inner: NostrSigner::NIP07(signer.deref().clone()),
+            inner: signer.deref().clone().into_nostr_signer(),
         }
     }
 
     /// NIP46
     pub fn nip46(signer: &JsNip46Signer) -> Self {
         Self {
-            inner: NostrSigner::nip46(signer.deref().clone()),
+            inner: signer.deref().clone().into_nostr_signer(),
         }
     }
 
@@ -9,6 +9,7 @@ use wasm_bindgen::prelude::*;
 pub mod flags;
 pub mod limits;
 pub mod options;
+pub mod publish;
 
 use self::flags::JsAtomicRelayServiceFlags;
 
@@ -45,6 +46,8 @@ pub enum JsRelayStatus {
     Stopped,
     /// Relay completely disconnected
     Terminated,
+    /// Relay given up after too many failed connection attempts
+    Dead,
 }
 
 impl From<RelayStatus> for JsRelayStatus {
@@ -57,6 +60,7 @@ impl From<RelayStatus> for JsRelayStatus {
             RelayStatus::Disconnected => Self::Disconnected,
             RelayStatus::Stopped => Self::Stopped,
             RelayStatus::Terminated => Self::Terminated,
+            RelayStatus::Dead => Self::Dead,
         }
     }
 }
@@ -0,0 +1,124 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+use js_sys::Array;
+use nostr_js::error::{into_err, Result};
+use nostr_js::event::JsEventId;
+use nostr_sdk::prelude::*;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(typescript_type = "EventId[]")]
+    pub type JsEventIdArray;
+}
+
+/// Outcome of publishing an event to a relay
+#[wasm_bindgen(js_name = PublishOutcome)]
+pub struct JsPublishOutcome {
+    inner: PublishOutcome,
+}
+
+impl From<PublishOutcome> for JsPublishOutcome {
+    fn from(inner: PublishOutcome) -> Self {
+        Self { inner }
+    }
+}
+
+#[wasm_bindgen(js_class = PublishOutcome)]
+impl JsPublishOutcome {
+    /// Check if the event has been accepted by relay
+    #[wasm_bindgen(js_name = isAccepted)]
+    pub fn is_accepted(&self) -> bool {
+        self.inner.is_accepted()
+    }
+
+    /// Check if the `OK` message has not been received in time
+    #[wasm_bindgen(js_name = isTimeout)]
+    pub fn is_timeout(&self) -> bool {
+        matches!(self.inner, PublishOutcome::Timeout)
+    }
+
+    /// Machine-readable prefix of the `OK` message (ex. `duplicate`, `blocked`, `auth-required`)
+    pub fn reason(&self) -> Option<String> {
+        self.inner.reason().map(|r| r.to_string())
+    }
+
+    /// Message (`OK` message or error)
+    pub fn message(&self) -> Option<String> {
+        self.inner.message().map(|m| m.to_string())
+    }
+}
+
+/// Per-relay outcome of publishing events
+#[wasm_bindgen(js_name = PublishReport)]
+pub struct JsPublishReport {
+    inner: PublishReport,
+}
+
+impl From<PublishReport> for JsPublishReport {
+    fn from(inner: PublishReport) -> Self {
+        Self { inner }
+    }
+}
+
+#[wasm_bindgen(js_class = PublishReport)]
+impl JsPublishReport {
+    /// Event ID (the first one, if many events have been sent)
+    pub fn id(&self) -> JsEventId {
+        self.inner.id().into()
+    }
+
+    /// Event IDs
+    pub fn ids(&self) -> JsEventIdArray {
+        self.inner
+            .ids()
+            .iter()
+            .map(|id| {
+                let id: JsEventId = (*id).into();
+                JsValue::from(id)
+            })
+            .collect::<Array>()
+            .unchecked_into()
+    }
+
+    /// Outcome of the event for the relay
+    pub fn outcome(&self, url: &str, id: &JsEventId) -> Result<Option<JsPublishOutcome>> {
+        let url: Url = Url::parse(url).map_err(into_err)?;
+        Ok(self.inner.outcome(&url, id).cloned().map(|o| o.into()))
+    }
+
+    /// Relays that accepted the event
+    pub fn accepted(&self, id: &JsEventId) -> Vec<String> {
+        self.inner
+            .accepted(id)
+            .into_iter()
+            .map(|url| url.to_string())
+            .collect()
+    }
+
+    /// Relays that not accepted the event (rejected, timeout or failed)
+    ///
+    /// Use `outcome` to get why.
+    #[wasm_bindgen(js_name = notAccepted)]
+    pub fn not_accepted(&self, id: &JsEventId) -> Vec<String> {
+        self.inner
+            .not_accepted(id)
+            .into_keys()
+            .map(|url| url.to_string())
+            .collect()
+    }
+
+    /// Check if at least one relay accepted at least one event
+    #[wasm_bindgen(js_name = isPublished)]
+    pub fn is_published(&self) -> bool {
+        self.inner.is_published()
+    }
+
+    /// Check if every event has been accepted by at least one relay
+    #[wasm_bindgen(js_name = isSuccess)]
+    pub fn is_success(&self) -> bool {
+        self.inner.is_success()
+    }
+}
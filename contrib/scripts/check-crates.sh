@@ -29,6 +29,7 @@ buildargs=(
     "-p nostr --no-default-features --features alloc,all-nips"
     "-p nostr --features blocking"
     "-p nostr-database"
+    "-p nostr-relay-builder"
     "-p nostr-zapper"
     "-p nostr-sdk"
     "-p nostr-sdk --no-default-features"
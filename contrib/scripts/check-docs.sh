@@ -6,6 +6,7 @@ set -e
 buildargs=(
     "-p nostr"
     "-p nostr-database"
+    "-p nostr-relay-builder"
     "-p nostr-relay-pool"
     "-p nostr-signer"
     "-p nostr-zapper"
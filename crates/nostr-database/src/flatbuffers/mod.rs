@@ -132,7 +132,8 @@ impl FlatBufferDecode for TempEvent {
         let created_at = ev.created_at();
         let kind = ev.kind();
         let tags = ev.tags().ok_or(Error::NotFound)?;
-        Ok(Self::new(id, pubkey, created_at, kind, tags))
+        let content = ev.content().ok_or(Error::NotFound)?;
+        Ok(Self::new(id, pubkey, created_at, kind, tags, content))
     }
 }
 
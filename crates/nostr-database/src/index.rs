@@ -17,6 +17,7 @@ use nostr::{
 use thiserror::Error;
 use tokio::sync::RwLock;
 
+use crate::search::SearchIndex;
 use crate::tag_indexes::{hash, TagIndexValues, TagIndexes, TAG_INDEX_VALUE_SIZE};
 #[cfg(feature = "flatbuf")]
 use crate::temp::TempEvent;
@@ -176,7 +177,7 @@ pub enum EventOrTempEvent<'a> {
     Event(&'a Event),
     EventOwned(Box<Event>),
     #[cfg(feature = "flatbuf")]
-    Temp(TempEvent),
+    Temp(Box<TempEvent>),
 }
 
 impl<'a> From<Event> for EventOrTempEvent<'a> {
@@ -194,7 +195,7 @@ impl<'a> From<&'a Event> for EventOrTempEvent<'a> {
 #[cfg(feature = "flatbuf")]
 impl<'a> From<TempEvent> for EventOrTempEvent<'a> {
     fn from(value: TempEvent) -> Self {
-        Self::Temp(value)
+        Self::Temp(Box::new(value))
     }
 }
 
@@ -235,6 +236,15 @@ impl<'a> EventOrTempEvent<'a> {
         }
     }
 
+    fn content(&self) -> &str {
+        match self {
+            Self::Event(e) => e.content(),
+            Self::EventOwned(e) => e.content(),
+            #[cfg(feature = "flatbuf")]
+            Self::Temp(r) => &r.content,
+        }
+    }
+
     fn tags(self) -> TagIndexes {
         match self {
             Self::Event(e) => TagIndexes::from(e.iter_tags()),
@@ -392,7 +402,11 @@ pub struct EventIndexResult {
 
 enum InternalQueryResult<'a> {
     All,
-    Set(BTreeSet<&'a ArcEventIndex>),
+    Set {
+        events: BTreeSet<&'a ArcEventIndex>,
+        /// Relevance of the events matched by a `search` filter
+        scores: HashMap<EventId, f64>,
+    },
 }
 
 /// Database Indexes
@@ -405,6 +419,7 @@ struct InternalDatabaseIndexes {
         HashMap<(Kind, PublicKeyPrefix, [u8; TAG_INDEX_VALUE_SIZE]), ArcEventIndex>,
     deleted_ids: HashSet<EventId>,
     deleted_coordinates: HashMap<Coordinate, Timestamp>,
+    search_index: SearchIndex,
 }
 
 impl InternalDatabaseIndexes {
@@ -534,6 +549,8 @@ impl InternalDatabaseIndexes {
 
         // Insert event
         if should_insert {
+            self.search_index.index(event_id, kind, event.content());
+
             let e: ArcEventIndex = Arc::new(EventIndex {
                 created_at,
                 event_id,
@@ -576,6 +593,8 @@ impl InternalDatabaseIndexes {
     fn discard_events(&mut self, ids: &HashSet<EventId>) {
         if !ids.is_empty() {
             for id in ids.iter() {
+                self.search_index.remove(id);
+
                 if let Some(ev) = self.ids_index.remove(id) {
                     self.index.remove(&ev);
 
@@ -700,6 +719,7 @@ impl InternalDatabaseIndexes {
         I: IntoIterator<Item = Filter>,
     {
         let mut matching_ids: BTreeSet<&ArcEventIndex> = BTreeSet::new();
+        let mut scores: HashMap<EventId, f64> = HashMap::new();
 
         for filter in filters.into_iter() {
             if filter.is_empty() {
@@ -714,6 +734,43 @@ impl InternalDatabaseIndexes {
 
             let limit: Option<usize> = filter.limit;
 
+            // Full-text search: keep the most relevant events
+            if let Some(found) = filter
+                .search
+                .as_deref()
+                .and_then(|query| self.search_index.search(query))
+            {
+                let filter: FilterIndex = filter.into();
+                let mut evs: Vec<(&ArcEventIndex, f64)> = found
+                    .into_iter()
+                    .filter_map(|(id, score)| {
+                        let ev: &ArcEventIndex = self.ids_index.get(&id)?;
+                        if filter.match_event(ev) {
+                            Some((ev, score))
+                        } else {
+                            None
+                        }
+                    })
+                    .collect();
+                evs.sort_by(|(a, a_score), (b, b_score)| {
+                    b_score.total_cmp(a_score).then_with(|| a.cmp(b))
+                });
+
+                if let Some(limit) = limit {
+                    evs.truncate(limit);
+                }
+
+                for (ev, score) in evs.into_iter() {
+                    scores
+                        .entry(ev.event_id)
+                        .and_modify(|s| *s = s.max(score))
+                        .or_insert(score);
+                    matching_ids.insert(ev);
+                }
+
+                continue;
+            }
+
             let evs: Box<dyn Iterator<Item = &ArcEventIndex>> = match QueryPattern::from(filter) {
                 QueryPattern::KindAuthor(params) => self.internal_query_by_kind_and_author(params),
                 QueryPattern::ParamReplaceable(params) => {
@@ -732,7 +789,10 @@ impl InternalDatabaseIndexes {
             }
         }
 
-        InternalQueryResult::Set(matching_ids)
+        InternalQueryResult::Set {
+            events: matching_ids,
+            scores,
+        }
     }
 
     /// Query
@@ -746,10 +806,23 @@ impl InternalDatabaseIndexes {
                 Order::Asc => self.index.iter().map(|ev| ev.event_id).rev().collect(),
                 Order::Desc => self.index.iter().map(|ev| ev.event_id).collect(),
             },
-            InternalQueryResult::Set(set) => match order {
-                Order::Asc => set.into_iter().map(|ev| ev.event_id).rev().collect(),
-                Order::Desc => set.into_iter().map(|ev| ev.event_id).collect(),
-            },
+            InternalQueryResult::Set { events, scores } => {
+                let mut events: Vec<&ArcEventIndex> = events.into_iter().collect();
+
+                // Sort by relevance (stable sort: same relevance are still sorted by timestamp)
+                if !scores.is_empty() {
+                    events.sort_by(|a, b| {
+                        let a: f64 = scores.get(&a.event_id).copied().unwrap_or_default();
+                        let b: f64 = scores.get(&b.event_id).copied().unwrap_or_default();
+                        b.total_cmp(&a)
+                    });
+                }
+
+                match order {
+                    Order::Asc => events.into_iter().map(|ev| ev.event_id).rev().collect(),
+                    Order::Desc => events.into_iter().map(|ev| ev.event_id).collect(),
+                }
+            }
         }
     }
 
@@ -761,7 +834,7 @@ impl InternalDatabaseIndexes {
     {
         match self.internal_query(filters) {
             InternalQueryResult::All => self.index.len(),
-            InternalQueryResult::Set(set) => set.len(),
+            InternalQueryResult::Set { events, .. } => events.len(),
         }
     }
 
@@ -773,7 +846,7 @@ impl InternalDatabaseIndexes {
                 .iter()
                 .map(|ev| (ev.event_id, ev.created_at))
                 .collect(),
-            InternalQueryResult::Set(set) => set
+            InternalQueryResult::Set { events, .. } => events
                 .into_iter()
                 .map(|ev| (ev.event_id, ev.created_at))
                 .collect(),
@@ -804,8 +877,8 @@ impl InternalDatabaseIndexes {
                 self.clear();
                 None
             }
-            InternalQueryResult::Set(set) => {
-                let ids: HashSet<EventId> = set.into_iter().map(|ev| ev.event_id).collect();
+            InternalQueryResult::Set { events, .. } => {
+                let ids: HashSet<EventId> = events.into_iter().map(|ev| ev.event_id).collect();
                 self.discard_events(&ids);
                 Some(ids)
             }
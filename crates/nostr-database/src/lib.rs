@@ -22,6 +22,7 @@ pub mod flatbuffers;
 pub mod index;
 pub mod memory;
 pub mod profile;
+pub mod search;
 mod tag_indexes;
 #[cfg(feature = "flatbuf")]
 mod temp;
@@ -174,6 +175,29 @@ pub trait NostrDatabase: AsyncTraitDeps {
         filter: Filter,
     ) -> Result<Vec<(EventId, Timestamp)>, Self::Err>;
 
+    /// Add [`Event`] to the outbox of relays
+    ///
+    /// The event is kept until removed with [`NostrDatabase::outbox_remove`] (ex. delivered to the relay).
+    ///
+    /// By default return [`DatabaseError::NotSupported`]: the backends with an outbox must override the `outbox*` methods.
+    async fn outbox_add(&self, _event: &Event, _relays: HashSet<Url>) -> Result<(), Self::Err> {
+        Err(DatabaseError::NotSupported.into())
+    }
+
+    /// Remove [`EventId`] from the outbox of relay
+    ///
+    /// By default return [`DatabaseError::NotSupported`].
+    async fn outbox_remove(&self, _event_id: EventId, _relay_url: Url) -> Result<(), Self::Err> {
+        Err(DatabaseError::NotSupported.into())
+    }
+
+    /// Get events in the outbox of relay, oldest first
+    ///
+    /// By default return [`DatabaseError::NotSupported`].
+    async fn outbox(&self, _relay_url: Url) -> Result<Vec<Event>, Self::Err> {
+        Err(DatabaseError::NotSupported.into())
+    }
+
     /// Delete all events that match the [Filter]
     async fn delete(&self, filter: Filter) -> Result<(), Self::Err>;
 
@@ -366,6 +390,21 @@ impl<T: NostrDatabase> NostrDatabase for EraseNostrDatabaseError<T> {
         self.0.negentropy_items(filter).await.map_err(Into::into)
     }
 
+    async fn outbox_add(&self, event: &Event, relays: HashSet<Url>) -> Result<(), Self::Err> {
+        self.0.outbox_add(event, relays).await.map_err(Into::into)
+    }
+
+    async fn outbox_remove(&self, event_id: EventId, relay_url: Url) -> Result<(), Self::Err> {
+        self.0
+            .outbox_remove(event_id, relay_url)
+            .await
+            .map_err(Into::into)
+    }
+
+    async fn outbox(&self, relay_url: Url) -> Result<Vec<Event>, Self::Err> {
+        self.0.outbox(relay_url).await.map_err(Into::into)
+    }
+
     async fn delete(&self, filter: Filter) -> Result<(), Self::Err> {
         self.0.delete(filter).await.map_err(Into::into)
     }
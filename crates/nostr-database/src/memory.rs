@@ -4,7 +4,7 @@
 
 //! Memory (RAM) Storage backend for Nostr apps
 
-use std::collections::{BTreeSet, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::hash::Hash;
 use std::num::NonZeroUsize;
 use std::sync::Arc;
@@ -50,6 +50,7 @@ pub struct MemoryDatabase {
     opts: MemoryDatabaseOptions,
     seen_event_ids: Arc<Mutex<LruCache<EventId, HashSet<Url>>>>,
     events: Arc<Mutex<LruCache<EventId, Event>>>,
+    outbox: Arc<Mutex<HashMap<Url, HashMap<EventId, Event>>>>,
     indexes: DatabaseIndexes,
 }
 
@@ -71,6 +72,7 @@ impl MemoryDatabase {
             opts,
             seen_event_ids: Arc::new(Mutex::new(new_lru_cache(opts.max_events))),
             events: Arc::new(Mutex::new(new_lru_cache(opts.max_events))),
+            outbox: Arc::new(Mutex::new(HashMap::new())),
             indexes: DatabaseIndexes::new(),
         }
     }
@@ -248,6 +250,38 @@ impl NostrDatabase for MemoryDatabase {
         }
     }
 
+    async fn outbox_add(&self, event: &Event, relays: HashSet<Url>) -> Result<(), Self::Err> {
+        let mut outbox = self.outbox.lock().await;
+        for relay_url in relays.into_iter() {
+            outbox
+                .entry(relay_url)
+                .or_default()
+                .insert(event.id(), event.clone());
+        }
+        Ok(())
+    }
+
+    async fn outbox_remove(&self, event_id: EventId, relay_url: Url) -> Result<(), Self::Err> {
+        let mut outbox = self.outbox.lock().await;
+        if let Some(events) = outbox.get_mut(&relay_url) {
+            events.remove(&event_id);
+            if events.is_empty() {
+                outbox.remove(&relay_url);
+            }
+        }
+        Ok(())
+    }
+
+    async fn outbox(&self, relay_url: Url) -> Result<Vec<Event>, Self::Err> {
+        let outbox = self.outbox.lock().await;
+        let mut events: Vec<Event> = outbox
+            .get(&relay_url)
+            .map(|events| events.values().cloned().collect())
+            .unwrap_or_default();
+        events.sort_by_key(|e| e.created_at());
+        Ok(events)
+    }
+
     async fn delete(&self, filter: Filter) -> Result<(), Self::Err> {
         let mut events = self.events.lock().await;
 
@@ -274,6 +308,8 @@ impl NostrDatabase for MemoryDatabase {
         seen_event_ids.clear();
         let mut events = self.events.lock().await;
         events.clear();
+        let mut outbox = self.outbox.lock().await;
+        outbox.clear();
         Ok(())
     }
 }
@@ -0,0 +1,195 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Full-text search (NIP-50)
+//!
+//! <https://github.com/nostr-protocol/nips/blob/master/50.md>
+
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap};
+
+use nostr::{EventId, JsonUtil, Kind, Metadata};
+
+/// NIP-50 extensions (`key:value`) that are not part of the search terms
+const EXTENSIONS: [&str; 5] = ["include", "domain", "language", "sentiment", "nsfw"];
+
+/// Split text into lowercase alphanumeric tokens
+pub fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(|token| token.to_lowercase())
+}
+
+/// Get the search terms of a NIP-50 query (`key:value` extensions are skipped)
+pub fn query_tokens(query: &str) -> BTreeSet<String> {
+    query
+        .split_whitespace()
+        .filter(|word| match word.split_once(':') {
+            Some((key, _)) => !EXTENSIONS.contains(&key),
+            None => true,
+        })
+        .flat_map(tokenize)
+        .collect()
+}
+
+/// Get the searchable text of an event
+///
+/// Return `None` if the event must not be indexed (i.e. encrypted content).
+pub fn searchable_text(kind: Kind, content: &str) -> Option<Cow<'_, str>> {
+    match kind {
+        Kind::EncryptedDirectMessage | Kind::Seal | Kind::GiftWrap => None,
+        Kind::Metadata => {
+            let metadata = Metadata::from_json(content).ok()?;
+            let text: String = [
+                metadata.name,
+                metadata.display_name,
+                metadata.about,
+                metadata.nip05,
+            ]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<String>>()
+            .join(" ");
+            Some(Cow::Owned(text))
+        }
+        _ if content.is_empty() => None,
+        _ => Some(Cow::Borrowed(content)),
+    }
+}
+
+/// Relevance of a single search term
+///
+/// `tf` is the number of occurrences of the term in the event,
+/// `df` the number of events that contain the term.
+#[inline]
+pub fn term_score(tf: u32, df: usize) -> f64 {
+    tf as f64 * (1.0 + 1.0 / df.max(1) as f64).ln()
+}
+
+/// Count token occurrences
+pub fn term_frequencies(text: &str) -> HashMap<String, u32> {
+    let mut frequencies: HashMap<String, u32> = HashMap::new();
+    for token in tokenize(text) {
+        *frequencies.entry(token).or_default() += 1;
+    }
+    frequencies
+}
+
+/// Tokenizing inverted index
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SearchIndex {
+    /// Token -> event ID -> occurrences
+    postings: HashMap<String, HashMap<EventId, u32>>,
+    /// Event ID -> tokens
+    documents: HashMap<EventId, Vec<String>>,
+}
+
+impl SearchIndex {
+    /// Index event content
+    pub fn index(&mut self, event_id: EventId, kind: Kind, content: &str) {
+        if let Some(text) = searchable_text(kind, content) {
+            let frequencies = term_frequencies(&text);
+            let mut tokens: Vec<String> = Vec::with_capacity(frequencies.len());
+            for (token, tf) in frequencies.into_iter() {
+                self.postings
+                    .entry(token.clone())
+                    .or_default()
+                    .insert(event_id, tf);
+                tokens.push(token);
+            }
+            if !tokens.is_empty() {
+                self.documents.insert(event_id, tokens);
+            }
+        }
+    }
+
+    /// Remove event from index
+    pub fn remove(&mut self, event_id: &EventId) {
+        if let Some(tokens) = self.documents.remove(event_id) {
+            for token in tokens.into_iter() {
+                if let Some(ids) = self.postings.get_mut(&token) {
+                    ids.remove(event_id);
+                    if ids.is_empty() {
+                        self.postings.remove(&token);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Get the events that contain **all** the search terms, with their relevance
+    ///
+    /// Return `None` if the query has no search terms.
+    pub fn search(&self, query: &str) -> Option<HashMap<EventId, f64>> {
+        let tokens: BTreeSet<String> = query_tokens(query);
+        if tokens.is_empty() {
+            return None;
+        }
+
+        let mut postings: Vec<&HashMap<EventId, u32>> = Vec::with_capacity(tokens.len());
+        for token in tokens.iter() {
+            match self.postings.get(token) {
+                Some(ids) => postings.push(ids),
+                None => return Some(HashMap::new()),
+            }
+        }
+
+        // Start from the rarest term
+        postings.sort_by_key(|ids| ids.len());
+
+        let first: &HashMap<EventId, u32> = postings.first()?;
+        Some(
+            first
+                .keys()
+                .filter_map(|id| {
+                    let mut score: f64 = 0.0;
+                    for ids in postings.iter() {
+                        score += term_score(*ids.get(id)?, ids.len());
+                    }
+                    Some((*id, score))
+                })
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_tokens() {
+        let tokens = query_tokens("Nostr, Bitcoin! language:en include:spam");
+        assert_eq!(
+            tokens.into_iter().collect::<Vec<_>>(),
+            vec![String::from("bitcoin"), String::from("nostr")]
+        );
+    }
+
+    #[test]
+    fn test_search_index() {
+        let id1 = EventId::all_zeros();
+        let id2 = EventId::from_slice(&[1u8; 32]).unwrap();
+
+        let mut index = SearchIndex::default();
+        index.index(id1, Kind::TextNote, "Hello nostr");
+        index.index(id2, Kind::TextNote, "nostr nostr, the best protocol");
+        index.index(id2, Kind::EncryptedDirectMessage, "hidden");
+
+        assert!(index.search("include:spam").is_none());
+
+        let res = index.search("NOSTR").unwrap();
+        assert_eq!(res.len(), 2);
+        assert!(res[&id2] > res[&id1]);
+
+        let res = index.search("hello nostr").unwrap();
+        assert_eq!(res.len(), 1);
+        assert!(res.contains_key(&id1));
+
+        assert!(index.search("hidden").unwrap().is_empty());
+
+        index.remove(&id1);
+        assert!(index.search("hello").unwrap().is_empty());
+    }
+}
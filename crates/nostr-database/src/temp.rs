@@ -27,6 +27,7 @@ pub struct TempEvent {
     pub identifier: Option<[u8; TAG_INDEX_VALUE_SIZE]>,
     pub event_ids: Vec<EventId>,
     pub coordinates: Vec<Coordinate>,
+    pub content: String,
 }
 
 impl PartialOrd for TempEvent {
@@ -52,6 +53,7 @@ impl TempEvent {
         created_at: u64,
         kind: u64,
         tags: Vector<'a, ForwardsUOffset<StringVector<'a>>>,
+        content: &str,
     ) -> Self {
         Self {
             id,
@@ -63,6 +65,7 @@ impl TempEvent {
             event_ids: extract_event_ids(&tags),
             coordinates: extract_coordinates(&tags),
             tags: TagIndexes::from_flatb(tags),
+            content: content.to_string(),
         }
     }
 
@@ -147,6 +150,7 @@ mod tests {
             identifier: None,
             event_ids: Vec::new(),
             coordinates: Vec::new(),
+            content: String::new(),
         };
         let now = Timestamp::now();
         assert!(raw.is_expired(&now));
@@ -167,6 +171,7 @@ mod tests {
             identifier: None,
             event_ids: Vec::new(),
             coordinates: Vec::new(),
+            content: String::new(),
         };
 
         assert!(!raw.is_expired(&now));
@@ -21,7 +21,7 @@ pub extern crate nostr_database as database;
 #[cfg(target_arch = "wasm32")]
 use async_trait::async_trait;
 use indexed_db_futures::request::{IdbOpenDbRequestLike, OpenDbRequest};
-use indexed_db_futures::web_sys::IdbTransactionMode;
+use indexed_db_futures::web_sys::{DomException, IdbKeyRange, IdbTransactionMode};
 use indexed_db_futures::{IdbDatabase, IdbQuerySource, IdbVersionChangeEvent};
 use nostr::nips::nip01::Coordinate;
 use nostr::util::hex;
@@ -33,16 +33,17 @@ use nostr_database::{
     FlatBufferEncode, Order, TempEvent,
 };
 use tokio::sync::Mutex;
-use wasm_bindgen::JsValue;
+use wasm_bindgen::{JsCast, JsValue};
 
 mod error;
 
 pub use self::error::IndexedDBError;
 
-const CURRENT_DB_VERSION: u32 = 2;
+const CURRENT_DB_VERSION: u32 = 3;
 const EVENTS_CF: &str = "events";
 const EVENTS_SEEN_BY_RELAYS_CF: &str = "event-seen-by-relays";
-const ALL_STORES: [&str; 2] = [EVENTS_CF, EVENTS_SEEN_BY_RELAYS_CF];
+const OUTBOX_CF: &str = "outbox";
+const ALL_STORES: [&str; 3] = [EVENTS_CF, EVENTS_SEEN_BY_RELAYS_CF, OUTBOX_CF];
 
 /// Helper struct for upgrading the inner DB.
 #[derive(Debug, Clone, Default)]
@@ -110,11 +111,11 @@ impl WebDatabase {
                 self.apply_migration(CURRENT_DB_VERSION, migration).await?;
                 tracing::info!("Database schemas initialized.");
             } else {
-                /* if old_version < 3 {
+                if old_version < 3 {
                     self.migrate_to_v3().await?;
                 }
 
-                if old_version < 4 {} */
+                /* if old_version < 4 {} */
             }
 
             self.db.close();
@@ -139,6 +140,16 @@ impl WebDatabase {
         Ok(())
     }
 
+    async fn migrate_to_v3(&mut self) -> Result<(), IndexedDBError> {
+        let migration = OngoingMigration {
+            create_stores: [OUTBOX_CF].into_iter().collect(),
+            ..Default::default()
+        };
+        self.apply_migration(3, migration).await?;
+        tracing::info!("Database migrated to v3");
+        Ok(())
+    }
+
     async fn apply_migration(
         &mut self,
         version: u32,
@@ -460,6 +471,54 @@ impl_nostr_database!({
         Ok(self.indexes.negentropy_items(filter).await)
     }
 
+    async fn outbox_add(&self, event: &Event, relays: HashSet<Url>) -> Result<(), IndexedDBError> {
+        let mut fbb = self.fbb.lock().await;
+        let tx = self
+            .db
+            .transaction_on_one_with_mode(OUTBOX_CF, IdbTransactionMode::Readwrite)?;
+        let store = tx.object_store(OUTBOX_CF)?;
+        let value = JsValue::from(hex::encode(event.encode(&mut fbb)));
+        for relay_url in relays.iter() {
+            let key = JsValue::from(outbox_key(relay_url, &event.id()));
+            store.put_key_val(&key, &value)?;
+        }
+        tx.await.into_result()?;
+        Ok(())
+    }
+
+    async fn outbox_remove(&self, event_id: EventId, relay_url: Url) -> Result<(), IndexedDBError> {
+        let tx = self
+            .db
+            .transaction_on_one_with_mode(OUTBOX_CF, IdbTransactionMode::Readwrite)?;
+        let store = tx.object_store(OUTBOX_CF)?;
+        let key = JsValue::from(outbox_key(&relay_url, &event_id));
+        store.delete(&key)?.await?;
+        Ok(())
+    }
+
+    async fn outbox(&self, relay_url: Url) -> Result<Vec<Event>, IndexedDBError> {
+        let tx = self
+            .db
+            .transaction_on_one_with_mode(OUTBOX_CF, IdbTransactionMode::Readonly)?;
+        let store = tx.object_store(OUTBOX_CF)?;
+
+        // Keys from `<relay url> ` (included) to `<relay url>!` (excluded)
+        let lower = JsValue::from(format!("{relay_url} "));
+        let upper = JsValue::from(format!("{relay_url}!"));
+        let range = IdbKeyRange::bound_with_lower_open_and_upper_open(&lower, &upper, false, true)
+            .map_err(|e| IndexedDBError::from(e.unchecked_into::<DomException>()))?;
+
+        let mut events: Vec<Event> = Vec::new();
+        for jsvalue in store.get_all_with_key(&range)?.await?.iter() {
+            let event_hex = jsvalue.as_string().ok_or(DatabaseError::NotFound)?;
+            let bytes = hex::decode(event_hex).map_err(DatabaseError::backend)?;
+            events.push(Event::decode(&bytes).map_err(DatabaseError::backend)?);
+        }
+
+        events.sort_by_key(|e| e.created_at());
+        Ok(events)
+    }
+
     async fn delete(&self, filter: Filter) -> Result<(), IndexedDBError> {
         let tx = self
             .db
@@ -495,3 +554,8 @@ impl_nostr_database!({
         Ok(())
     }
 });
+
+/// Outbox key: `<relay url> <event id>`
+fn outbox_key(relay_url: &Url, event_id: &EventId) -> String {
+    format!("{relay_url} {}", event_id.to_hex())
+}
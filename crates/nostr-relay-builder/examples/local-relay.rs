@@ -0,0 +1,21 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+use nostr_relay_builder::prelude::*;
+
+#[tokio::main]
+async fn main() {
+    tracing_subscriber::fmt::init();
+
+    let builder = RelayBuilder::default().max_subscriptions(100);
+
+    let relay = LocalRelay::run(builder).await.unwrap();
+
+    println!("Url: {}", relay.url());
+
+    // Keep up the program
+    tokio::signal::ctrl_c().await.unwrap();
+
+    relay.shutdown();
+}
@@ -0,0 +1,163 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Relay builder
+
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use nostr::Url;
+use nostr_database::{DynNostrDatabase, IntoNostrDatabase, MemoryDatabase, MemoryDatabaseOptions};
+
+/// NIP42 mode
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum RelayBuilderNip42Mode {
+    /// Require authentication for reading
+    Read,
+    /// Require authentication for writing
+    Write,
+    /// Require authentication for both reading and writing
+    #[default]
+    Both,
+}
+
+impl RelayBuilderNip42Mode {
+    /// Check if is [`RelayBuilderNip42Mode::Read`] or [`RelayBuilderNip42Mode::Both`]
+    pub fn is_read(&self) -> bool {
+        matches!(self, Self::Read | Self::Both)
+    }
+
+    /// Check if is [`RelayBuilderNip42Mode::Write`] or [`RelayBuilderNip42Mode::Both`]
+    pub fn is_write(&self) -> bool {
+        matches!(self, Self::Write | Self::Both)
+    }
+}
+
+/// NIP42 options
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct RelayBuilderNip42 {
+    /// Mode
+    pub mode: RelayBuilderNip42Mode,
+}
+
+/// Relay builder
+#[derive(Clone)]
+pub struct RelayBuilder {
+    /// IP address (default: `127.0.0.1`)
+    pub addr: Option<IpAddr>,
+    /// Port (default: random)
+    pub port: Option<u16>,
+    /// Public URL (ex. `wss://relay.example.com`), checked in the NIP42 `relay` tag
+    ///
+    /// If not set, the `relay` tag is checked against the listening address
+    /// (only the port if the relay listens on all interfaces).
+    pub url: Option<Url>,
+    /// Database
+    pub database: Arc<DynNostrDatabase>,
+    /// Max subscriptions per connection
+    pub max_subscriptions: Option<usize>,
+    /// Max filters per `REQ` or `COUNT`
+    pub max_filters: Option<usize>,
+    /// Max `limit` allowed for a filter
+    pub max_limit: Option<usize>,
+    /// NIP42 authentication
+    pub nip42: Option<RelayBuilderNip42>,
+}
+
+impl fmt::Debug for RelayBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RelayBuilder")
+            .field("addr", &self.addr)
+            .field("port", &self.port)
+            .field("url", &self.url)
+            .field("backend", &self.database.backend())
+            .field("max_subscriptions", &self.max_subscriptions)
+            .field("max_filters", &self.max_filters)
+            .field("max_limit", &self.max_limit)
+            .field("nip42", &self.nip42)
+            .finish()
+    }
+}
+
+impl Default for RelayBuilder {
+    fn default() -> Self {
+        let opts = MemoryDatabaseOptions {
+            events: true,
+            max_events: None,
+        };
+        Self {
+            addr: None,
+            port: None,
+            url: None,
+            database: MemoryDatabase::with_opts(opts).into_nostr_database(),
+            max_subscriptions: None,
+            max_filters: None,
+            max_limit: None,
+            nip42: None,
+        }
+    }
+}
+
+impl RelayBuilder {
+    /// New default relay builder
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set IP address
+    pub fn addr(mut self, addr: IpAddr) -> Self {
+        self.addr = Some(addr);
+        self
+    }
+
+    /// Set port
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    /// Set public URL (ex. `wss://relay.example.com`), checked in the NIP42 `relay` tag
+    ///
+    /// Useful if the relay is behind a reverse proxy or listens on all interfaces.
+    pub fn url(mut self, url: Url) -> Self {
+        self.url = Some(url);
+        self
+    }
+
+    /// Set database
+    ///
+    /// The database must store events (i.e. a [`MemoryDatabase`] with `events` option disabled will not work).
+    pub fn database<D>(mut self, database: D) -> Self
+    where
+        D: IntoNostrDatabase,
+    {
+        self.database = database.into_nostr_database();
+        self
+    }
+
+    /// Set max subscriptions per connection
+    pub fn max_subscriptions(mut self, max: usize) -> Self {
+        self.max_subscriptions = Some(max);
+        self
+    }
+
+    /// Set max filters per `REQ` or `COUNT`
+    pub fn max_filters(mut self, max: usize) -> Self {
+        self.max_filters = Some(max);
+        self
+    }
+
+    /// Set max `limit` allowed for a filter
+    pub fn max_limit(mut self, max: usize) -> Self {
+        self.max_limit = Some(max);
+        self
+    }
+
+    /// Require NIP42 authentication
+    pub fn nip42(mut self, opts: RelayBuilderNip42) -> Self {
+        self.nip42 = Some(opts);
+        self
+    }
+}
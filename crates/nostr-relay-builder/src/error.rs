@@ -0,0 +1,34 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Relay builder error
+
+use nostr::message::MessageHandleError;
+use nostr::negentropy;
+use nostr_database::DatabaseError;
+use thiserror::Error;
+use tokio_tungstenite::tungstenite;
+
+/// Relay builder error
+#[derive(Debug, Error)]
+pub enum Error {
+    /// I/O error
+    #[error(transparent)]
+    IO(#[from] std::io::Error),
+    /// WebSocket error
+    #[error(transparent)]
+    WebSocket(#[from] tungstenite::Error),
+    /// Url parse error
+    #[error("impossible to parse URL: {0}")]
+    Url(#[from] nostr::types::url::ParseError),
+    /// Message handler error
+    #[error(transparent)]
+    MessageHandler(#[from] MessageHandleError),
+    /// Negentropy error
+    #[error(transparent)]
+    Negentropy(#[from] negentropy::Error),
+    /// Database error
+    #[error(transparent)]
+    Database(#[from] DatabaseError),
+}
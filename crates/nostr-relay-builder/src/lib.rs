@@ -0,0 +1,18 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Nostr Relay Builder
+
+#![forbid(unsafe_code)]
+#![warn(missing_docs)]
+#![warn(rustdoc::bare_urls)]
+
+pub mod builder;
+pub mod error;
+pub mod local;
+pub mod prelude;
+
+pub use self::builder::{RelayBuilder, RelayBuilderNip42, RelayBuilderNip42Mode};
+pub use self::error::Error;
+pub use self::local::LocalRelay;
@@ -0,0 +1,731 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Internal local relay
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+
+use atomic_destructor::AtomicDestroyer;
+use futures_util::stream::SplitSink;
+use futures_util::{SinkExt, StreamExt};
+use nostr::message::relay::NegentropyErrorCode;
+use nostr::negentropy::{Bytes, Negentropy};
+use nostr::secp256k1::rand::rngs::OsRng;
+use nostr::secp256k1::rand::Rng;
+use nostr::types::url::Host;
+use nostr::util::hex;
+use nostr::{
+    ClientMessage, Event, Filter, JsonUtil, Kind, RelayMessage, SubscriptionId, Tag, Timestamp, Url,
+};
+use nostr_database::{DynNostrDatabase, Order};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+
+use super::session::Session;
+use crate::builder::{RelayBuilder, RelayBuilderNip42};
+use crate::error::Error;
+
+/// Max allowed difference (in secs) between the `created_at` of an auth event and now
+const AUTH_MAX_TIME_DIFF: u64 = 600;
+
+type WsTx = SplitSink<WebSocketStream<TcpStream>, Message>;
+
+#[derive(Debug, Clone)]
+pub(super) struct InternalLocalRelay {
+    addr: SocketAddr,
+    /// Public URL, checked in the NIP42 `relay` tag
+    public_url: Option<Url>,
+    database: Arc<DynNostrDatabase>,
+    max_subscriptions: Option<usize>,
+    max_filters: Option<usize>,
+    max_limit: Option<usize>,
+    nip42: Option<RelayBuilderNip42>,
+    new_event: broadcast::Sender<Event>,
+    shutdown: broadcast::Sender<()>,
+}
+
+impl AtomicDestroyer for InternalLocalRelay {
+    fn name(&self) -> Option<String> {
+        Some(format!("Local relay {}", self.addr))
+    }
+
+    fn on_destroy(&self) {
+        self.shutdown();
+    }
+}
+
+impl InternalLocalRelay {
+    pub async fn run(builder: RelayBuilder) -> Result<Self, Error> {
+        let ip: IpAddr = builder.addr.unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST));
+        let port: u16 = builder.port.unwrap_or(0);
+
+        let listener: TcpListener = TcpListener::bind(SocketAddr::new(ip, port)).await?;
+        let addr: SocketAddr = listener.local_addr()?;
+
+        let (new_event, ..) = broadcast::channel::<Event>(1024);
+        let (shutdown, ..) = broadcast::channel::<()>(1);
+
+        let relay = Self {
+            addr,
+            public_url: builder.url,
+            database: builder.database,
+            max_subscriptions: builder.max_subscriptions,
+            max_filters: builder.max_filters,
+            max_limit: builder.max_limit,
+            nip42: builder.nip42,
+            new_event,
+            shutdown,
+        };
+
+        tracing::info!("Local relay listening on {addr}");
+
+        let r = relay.clone();
+        let mut shutdown = relay.shutdown.subscribe();
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    res = listener.accept() => match res {
+                        Ok((stream, addr)) => {
+                            let r = r.clone();
+                            tokio::spawn(async move {
+                                if let Err(e) = r.handle_connection(stream, addr).await {
+                                    tracing::error!("Connection with {addr} terminated: {e}");
+                                }
+                            });
+                        }
+                        Err(e) => tracing::error!("Impossible to accept connection: {e}"),
+                    },
+                    _ = shutdown.recv() => break,
+                }
+            }
+
+            tracing::info!("Local relay {} stopped", r.addr);
+        });
+
+        Ok(relay)
+    }
+
+    pub fn url(&self) -> Url {
+        // Unwrap is safe: `ws://<ip>:<port>` is always a valid URL
+        Url::parse(&format!("ws://{}", self.addr)).expect("Invalid relay url")
+    }
+
+    pub fn shutdown(&self) {
+        let _ = self.shutdown.send(());
+    }
+
+    async fn handle_connection(&self, stream: TcpStream, addr: SocketAddr) -> Result<(), Error> {
+        let ws_stream = tokio_tungstenite::accept_async(stream).await?;
+        tracing::debug!("WebSocket connection established with {addr}");
+
+        let (mut tx, mut rx) = ws_stream.split();
+
+        let mut session = Session::default();
+        let mut new_event = self.new_event.subscribe();
+        let mut shutdown = self.shutdown.subscribe();
+
+        // Send NIP42 challenge
+        if self.nip42.is_some() {
+            let challenge: String = generate_challenge();
+            session.nip42.challenge = Some(challenge.clone());
+            send_msg(&mut tx, RelayMessage::auth(challenge)).await?;
+        }
+
+        loop {
+            tokio::select! {
+                msg = rx.next() => match msg {
+                    Some(Ok(Message::Text(json))) => {
+                        tracing::trace!("Received message from {addr}: {json}");
+                        match ClientMessage::from_json(json) {
+                            Ok(msg) => self.handle_client_msg(&mut session, &mut tx, msg).await?,
+                            Err(e) => {
+                                send_msg(&mut tx, RelayMessage::notice(format!("error: {e}"))).await?
+                            }
+                        }
+                    }
+                    Some(Ok(Message::Binary(..))) => {
+                        let msg = RelayMessage::notice("error: binary messages are not supported");
+                        send_msg(&mut tx, msg).await?;
+                    }
+                    Some(Ok(Message::Close(..))) | None => break,
+                    // Ping/Pong are handled by tungstenite
+                    Some(Ok(..)) => {}
+                    Some(Err(e)) => return Err(e.into()),
+                },
+                event = new_event.recv() => {
+                    if let Ok(event) = event {
+                        for (subscription_id, filters) in session.subscriptions.iter() {
+                            if filters.iter().any(|f| f.match_event(&event)) {
+                                let msg = RelayMessage::event(subscription_id.clone(), event.clone());
+                                send_msg(&mut tx, msg).await?;
+                            }
+                        }
+                    }
+                }
+                _ = shutdown.recv() => break,
+            }
+        }
+
+        tracing::debug!("WebSocket connection with {addr} terminated");
+
+        Ok(())
+    }
+
+    async fn handle_client_msg(
+        &self,
+        session: &mut Session,
+        tx: &mut WsTx,
+        msg: ClientMessage,
+    ) -> Result<(), Error> {
+        match msg {
+            ClientMessage::Event(event) => {
+                let event_id = event.id();
+
+                // Check NIP42
+                if let Some(nip42) = &self.nip42 {
+                    if nip42.mode.is_write() && !session.nip42.is_authenticated() {
+                        let msg = RelayMessage::ok(
+                            event_id,
+                            false,
+                            "auth-required: you must be authenticated to publish events",
+                        );
+                        return send_msg(tx, msg).await;
+                    }
+                }
+
+                if event.verify().is_err() {
+                    let msg = RelayMessage::ok(event_id, false, "invalid: event id or signature");
+                    return send_msg(tx, msg).await;
+                }
+
+                if event.is_expired() {
+                    let msg = RelayMessage::ok(event_id, false, "invalid: event expired");
+                    return send_msg(tx, msg).await;
+                }
+
+                // Ephemeral events are never stored
+                if event.is_ephemeral() {
+                    send_msg(tx, RelayMessage::ok(event_id, true, "")).await?;
+                    let _ = self.new_event.send(*event);
+                    return Ok(());
+                }
+
+                if self.database.has_event_id_been_deleted(&event_id).await? {
+                    let msg = RelayMessage::ok(event_id, false, "blocked: this event is deleted");
+                    return send_msg(tx, msg).await;
+                }
+
+                if self
+                    .database
+                    .has_event_already_been_saved(&event_id)
+                    .await?
+                {
+                    let msg =
+                        RelayMessage::ok(event_id, true, "duplicate: already have this event");
+                    return send_msg(tx, msg).await;
+                }
+
+                // Replaceable, parameterized replaceable and deletion events are handled by the database
+                match self.database.save_event(&event).await {
+                    Ok(true) => {
+                        send_msg(tx, RelayMessage::ok(event_id, true, "")).await?;
+                        let _ = self.new_event.send(*event);
+                        Ok(())
+                    }
+                    Ok(false) => {
+                        let msg = RelayMessage::ok(
+                            event_id,
+                            false,
+                            "blocked: event deleted or replaced by a newer one",
+                        );
+                        send_msg(tx, msg).await
+                    }
+                    Err(e) => {
+                        tracing::error!("Impossible to save event {event_id}: {e}");
+                        let msg = RelayMessage::ok(event_id, false, format!("error: {e}"));
+                        send_msg(tx, msg).await
+                    }
+                }
+            }
+            ClientMessage::Req {
+                subscription_id,
+                filters,
+            } => {
+                if let Some(message) = self.check_filters(session, &subscription_id, &filters) {
+                    return send_msg(tx, RelayMessage::closed(subscription_id, message)).await;
+                }
+
+                let filters: Vec<Filter> = self.apply_max_limit(filters);
+
+                match self.database.query(filters.clone(), Order::Desc).await {
+                    Ok(events) => {
+                        session
+                            .subscriptions
+                            .insert(subscription_id.clone(), filters);
+
+                        for event in events.into_iter() {
+                            send_msg(tx, RelayMessage::event(subscription_id.clone(), event))
+                                .await?;
+                        }
+
+                        send_msg(tx, RelayMessage::eose(subscription_id)).await
+                    }
+                    Err(e) => {
+                        let msg = RelayMessage::closed(subscription_id, format!("error: {e}"));
+                        send_msg(tx, msg).await
+                    }
+                }
+            }
+            ClientMessage::Count {
+                subscription_id,
+                filters,
+            } => {
+                if let Some(message) = self.check_filters(session, &subscription_id, &filters) {
+                    return send_msg(tx, RelayMessage::closed(subscription_id, message)).await;
+                }
+
+                match self.database.count(filters).await {
+                    Ok(count) => send_msg(tx, RelayMessage::count(subscription_id, count)).await,
+                    Err(e) => {
+                        let msg = RelayMessage::closed(subscription_id, format!("error: {e}"));
+                        send_msg(tx, msg).await
+                    }
+                }
+            }
+            ClientMessage::Close(subscription_id) => {
+                session.subscriptions.remove(&subscription_id);
+                Ok(())
+            }
+            ClientMessage::Auth(event) => {
+                let event_id = event.id();
+                let challenge: Option<&str> = session.nip42.challenge.as_deref();
+                match verify_auth_event(&self.addr, self.public_url.as_ref(), challenge, &event) {
+                    Ok(()) => {
+                        session.nip42.public_key = Some(event.author());
+                        send_msg(tx, RelayMessage::ok(event_id, true, "")).await
+                    }
+                    Err(e) => {
+                        let msg = RelayMessage::ok(event_id, false, format!("invalid: {e}"));
+                        send_msg(tx, msg).await
+                    }
+                }
+            }
+            ClientMessage::NegOpen {
+                subscription_id,
+                filter,
+                id_size,
+                initial_message,
+            } => {
+                if let Some(nip42) = &self.nip42 {
+                    if nip42.mode.is_read() && !session.nip42.is_authenticated() {
+                        let msg = RelayMessage::NegErr {
+                            subscription_id,
+                            code: NegentropyErrorCode::Other(String::from(
+                                "auth-required: you must be authenticated to reconcile",
+                            )),
+                        };
+                        return send_msg(tx, msg).await;
+                    }
+                }
+
+                match self
+                    .negentropy_open(*filter, id_size, initial_message)
+                    .await
+                {
+                    Ok((negentropy, message)) => {
+                        session
+                            .negentropy_subscription
+                            .insert(subscription_id.clone(), negentropy);
+                        send_msg(
+                            tx,
+                            RelayMessage::NegMsg {
+                                subscription_id,
+                                message,
+                            },
+                        )
+                        .await
+                    }
+                    Err(e) => {
+                        let msg = RelayMessage::NegErr {
+                            subscription_id,
+                            code: NegentropyErrorCode::Other(e.to_string()),
+                        };
+                        send_msg(tx, msg).await
+                    }
+                }
+            }
+            ClientMessage::NegMsg {
+                subscription_id,
+                message,
+            } => match session.negentropy_subscription.get_mut(&subscription_id) {
+                Some(negentropy) => {
+                    let res = Bytes::from_hex(message).and_then(|b| negentropy.reconcile(&b));
+                    let msg = match res {
+                        Ok(bytes) => RelayMessage::NegMsg {
+                            subscription_id,
+                            message: bytes.to_hex(),
+                        },
+                        Err(e) => RelayMessage::NegErr {
+                            subscription_id,
+                            code: NegentropyErrorCode::Other(e.to_string()),
+                        },
+                    };
+                    send_msg(tx, msg).await
+                }
+                None => {
+                    let msg = RelayMessage::NegErr {
+                        subscription_id,
+                        code: NegentropyErrorCode::Closed,
+                    };
+                    send_msg(tx, msg).await
+                }
+            },
+            ClientMessage::NegClose { subscription_id } => {
+                session.negentropy_subscription.remove(&subscription_id);
+                Ok(())
+            }
+        }
+    }
+
+    /// Check NIP42 and limits: return the `CLOSED` message if filters are rejected
+    fn check_filters(
+        &self,
+        session: &Session,
+        subscription_id: &SubscriptionId,
+        filters: &[Filter],
+    ) -> Option<&'static str> {
+        if let Some(nip42) = &self.nip42 {
+            if nip42.mode.is_read() && !session.nip42.is_authenticated() {
+                return Some("auth-required: you must be authenticated to read");
+            }
+        }
+
+        if let Some(max) = self.max_subscriptions {
+            if !session.subscriptions.contains_key(subscription_id)
+                && session.subscriptions.len() >= max
+            {
+                return Some("rate-limited: too many subscriptions");
+            }
+        }
+
+        if let Some(max) = self.max_filters {
+            if filters.len() > max {
+                return Some("error: too many filters");
+            }
+        }
+
+        None
+    }
+
+    fn apply_max_limit(&self, filters: Vec<Filter>) -> Vec<Filter> {
+        match self.max_limit {
+            Some(max) => filters
+                .into_iter()
+                .map(|mut f| {
+                    f.limit = Some(f.limit.map_or(max, |l| l.min(max)));
+                    f
+                })
+                .collect(),
+            None => filters,
+        }
+    }
+
+    async fn negentropy_open(
+        &self,
+        filter: Filter,
+        id_size: u8,
+        initial_message: String,
+    ) -> Result<(Negentropy, String), Error> {
+        let items = self.database.negentropy_items(filter).await?;
+
+        let mut negentropy = Negentropy::new(id_size as usize, None)?;
+        for (id, timestamp) in items.into_iter() {
+            negentropy.add_item(timestamp.as_u64(), Bytes::from_slice(id.as_bytes()))?;
+        }
+        negentropy.seal()?;
+
+        let bytes: Bytes = Bytes::from_hex(initial_message)?;
+        let message: Bytes = negentropy.reconcile(&bytes)?;
+        Ok((negentropy, message.to_hex()))
+    }
+}
+
+async fn send_msg(tx: &mut WsTx, msg: RelayMessage) -> Result<(), Error> {
+    tx.send(Message::Text(msg.as_json())).await?;
+    Ok(())
+}
+
+fn generate_challenge() -> String {
+    let mut rng = OsRng;
+    let bytes: [u8; 32] = rng.gen();
+    hex::encode(bytes)
+}
+
+fn verify_auth_event(
+    addr: &SocketAddr,
+    public_url: Option<&Url>,
+    challenge: Option<&str>,
+    event: &Event,
+) -> Result<(), &'static str> {
+    let challenge: &str = challenge.ok_or("no challenge was sent")?;
+
+    if event.kind() != Kind::Authentication {
+        return Err("wrong event kind");
+    }
+
+    let now: Timestamp = Timestamp::now();
+    let created_at: u64 = event.created_at().as_u64();
+    if now.as_u64().abs_diff(created_at) > AUTH_MAX_TIME_DIFF {
+        return Err("created_at too far from current time");
+    }
+
+    let match_challenge: bool = event
+        .tags()
+        .iter()
+        .any(|t| matches!(t, Tag::Challenge(c) if c == challenge));
+    if !match_challenge {
+        return Err("challenge not match");
+    }
+
+    let match_relay: bool = event.tags().iter().any(|t| match t {
+        Tag::Relay(url) => match Url::try_from(url.clone()) {
+            Ok(url) => match public_url {
+                Some(public_url) => match_public_url(public_url, &url),
+                None => match_relay_url(addr, &url),
+            },
+            Err(..) => false,
+        },
+        _ => false,
+    });
+    if !match_relay {
+        return Err("relay not match");
+    }
+
+    event.verify().map_err(|_| "event id or signature")
+}
+
+/// Check if the URL is the public URL of the relay
+///
+/// The default port of the scheme and the trailing slash are ignored.
+fn match_public_url(public_url: &Url, url: &Url) -> bool {
+    public_url.scheme() == url.scheme()
+        && public_url.host() == url.host()
+        && public_url.port_or_known_default() == url.port_or_known_default()
+        && public_url.path().trim_end_matches('/') == url.path().trim_end_matches('/')
+}
+
+/// Check if the URL points to the listening address of the relay
+///
+/// `localhost` matches the loopback addresses and, if the relay listen on all interfaces, only the port is checked.
+fn match_relay_url(addr: &SocketAddr, url: &Url) -> bool {
+    if url.port_or_known_default() != Some(addr.port()) {
+        return false;
+    }
+
+    if addr.ip().is_unspecified() {
+        return true;
+    }
+
+    match url.host() {
+        Some(Host::Ipv4(ip)) => addr.ip() == IpAddr::V4(ip),
+        Some(Host::Ipv6(ip)) => addr.ip() == IpAddr::V6(ip),
+        Some(Host::Domain(domain)) => domain == "localhost" && addr.ip().is_loopback(),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::{EventBuilder, Keys};
+    use tokio_tungstenite::connect_async;
+
+    use super::*;
+    use crate::builder::RelayBuilderNip42Mode;
+    use crate::LocalRelay;
+
+    fn auth_event(keys: &Keys, challenge: &str, relay_url: &str) -> Event {
+        EventBuilder::auth(challenge, Url::parse(relay_url).unwrap())
+            .to_event(keys)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_match_relay_url() {
+        let addr: SocketAddr = "127.0.0.1:7777".parse().unwrap();
+        assert!(match_relay_url(
+            &addr,
+            &Url::parse("ws://127.0.0.1:7777").unwrap()
+        ));
+        assert!(match_relay_url(
+            &addr,
+            &Url::parse("ws://localhost:7777/").unwrap()
+        ));
+        assert!(!match_relay_url(
+            &addr,
+            &Url::parse("ws://127.0.0.1:7778").unwrap()
+        ));
+        assert!(!match_relay_url(
+            &addr,
+            &Url::parse("ws://127.0.0.2:7777").unwrap()
+        ));
+        assert!(!match_relay_url(
+            &addr,
+            &Url::parse("wss://relay.example.com").unwrap()
+        ));
+
+        let addr: SocketAddr = "0.0.0.0:443".parse().unwrap();
+        assert!(match_relay_url(
+            &addr,
+            &Url::parse("wss://relay.example.com").unwrap()
+        ));
+        assert!(!match_relay_url(
+            &addr,
+            &Url::parse("ws://relay.example.com").unwrap()
+        ));
+    }
+
+    #[test]
+    fn test_match_public_url() {
+        let public_url = Url::parse("wss://relay.example.com").unwrap();
+        assert!(match_public_url(
+            &public_url,
+            &Url::parse("wss://relay.example.com/").unwrap()
+        ));
+        assert!(match_public_url(
+            &public_url,
+            &Url::parse("wss://RELAY.example.com:443").unwrap()
+        ));
+        assert!(!match_public_url(
+            &public_url,
+            &Url::parse("wss://other.example.com").unwrap()
+        ));
+        assert!(!match_public_url(
+            &public_url,
+            &Url::parse("ws://relay.example.com").unwrap()
+        ));
+        assert!(!match_public_url(
+            &public_url,
+            &Url::parse("wss://relay.example.com/nostr").unwrap()
+        ));
+    }
+
+    #[test]
+    fn test_verify_auth_event() {
+        let keys = Keys::generate();
+        let addr: SocketAddr = "127.0.0.1:7777".parse().unwrap();
+        let relay_url: &str = "ws://127.0.0.1:7777";
+
+        let event = auth_event(&keys, "challenge", relay_url);
+        assert_eq!(
+            verify_auth_event(&addr, None, Some("challenge"), &event),
+            Ok(())
+        );
+        assert_eq!(
+            verify_auth_event(&addr, None, None, &event),
+            Err("no challenge was sent")
+        );
+        assert_eq!(
+            verify_auth_event(&addr, None, Some("other"), &event),
+            Err("challenge not match")
+        );
+
+        let event = auth_event(&keys, "challenge", "wss://relay.example.com");
+        assert_eq!(
+            verify_auth_event(&addr, None, Some("challenge"), &event),
+            Err("relay not match")
+        );
+
+        // Behind a reverse proxy: only the public URL matches
+        let public_url = Url::parse("wss://relay.example.com").unwrap();
+        assert_eq!(
+            verify_auth_event(&addr, Some(&public_url), Some("challenge"), &event),
+            Ok(())
+        );
+        let event = auth_event(&keys, "challenge", relay_url);
+        assert_eq!(
+            verify_auth_event(&addr, Some(&public_url), Some("challenge"), &event),
+            Err("relay not match")
+        );
+
+        let event = EventBuilder::new(
+            Kind::Authentication,
+            "",
+            [Tag::Challenge(String::from("challenge"))],
+        )
+        .to_event(&keys)
+        .unwrap();
+        assert_eq!(
+            verify_auth_event(&addr, None, Some("challenge"), &event),
+            Err("relay not match")
+        );
+
+        let event = EventBuilder::text_note("challenge", [])
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(
+            verify_auth_event(&addr, None, Some("challenge"), &event),
+            Err("wrong event kind")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_auth() {
+        let builder = RelayBuilder::default().nip42(RelayBuilderNip42 {
+            mode: RelayBuilderNip42Mode::Write,
+        });
+        let relay = LocalRelay::run(builder).await.unwrap();
+        let relay_url: Url = relay.url();
+
+        let (mut ws, _) = connect_async(relay_url.as_str()).await.unwrap();
+        let challenge: String = match ws.next().await {
+            Some(Ok(Message::Text(json))) => match RelayMessage::from_json(json).unwrap() {
+                RelayMessage::Auth { challenge } => challenge,
+                msg => panic!("Unexpected message: {msg:?}"),
+            },
+            msg => panic!("Unexpected message: {msg:?}"),
+        };
+
+        let keys = Keys::generate();
+        let event = EventBuilder::text_note("Hello", [])
+            .to_event(&keys)
+            .unwrap();
+        let cases: Vec<(ClientMessage, bool, &str)> = vec![
+            (
+                ClientMessage::event(event.clone()),
+                false,
+                "auth-required: you must be authenticated to publish events",
+            ),
+            (
+                ClientMessage::auth(auth_event(&keys, &challenge, "ws://127.0.0.1:1")),
+                false,
+                "invalid: relay not match",
+            ),
+            (
+                ClientMessage::auth(auth_event(&keys, &challenge, relay_url.as_str())),
+                true,
+                "",
+            ),
+            (ClientMessage::event(event), true, ""),
+        ];
+
+        for (msg, expected_status, expected_message) in cases.into_iter() {
+            ws.send(Message::Text(msg.as_json())).await.unwrap();
+            match ws.next().await {
+                Some(Ok(Message::Text(json))) => match RelayMessage::from_json(json).unwrap() {
+                    RelayMessage::Ok {
+                        status, message, ..
+                    } => {
+                        assert_eq!(status, expected_status);
+                        assert_eq!(message, expected_message);
+                    }
+                    msg => panic!("Unexpected message: {msg:?}"),
+                },
+                msg => panic!("Unexpected message: {msg:?}"),
+            }
+        }
+    }
+}
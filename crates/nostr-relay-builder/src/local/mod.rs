@@ -0,0 +1,42 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Local relay
+
+use atomic_destructor::AtomicDestructor;
+use nostr::Url;
+
+mod internal;
+mod session;
+
+use self::internal::InternalLocalRelay;
+use crate::builder::RelayBuilder;
+use crate::error::Error;
+
+/// Local relay
+///
+/// The relay is automatically shutdown when all the clones are dropped.
+#[derive(Debug, Clone)]
+pub struct LocalRelay {
+    inner: AtomicDestructor<InternalLocalRelay>,
+}
+
+impl LocalRelay {
+    /// Bind the socket and start serving connections
+    pub async fn run(builder: RelayBuilder) -> Result<Self, Error> {
+        Ok(Self {
+            inner: AtomicDestructor::new(InternalLocalRelay::run(builder).await?),
+        })
+    }
+
+    /// Get relay url
+    pub fn url(&self) -> Url {
+        self.inner.url()
+    }
+
+    /// Shutdown relay
+    pub fn shutdown(&self) {
+        self.inner.shutdown();
+    }
+}
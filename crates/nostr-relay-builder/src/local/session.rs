@@ -0,0 +1,33 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Connection session
+
+use std::collections::HashMap;
+
+use nostr::negentropy::Negentropy;
+use nostr::{Filter, PublicKey, SubscriptionId};
+
+#[derive(Debug, Default)]
+pub(super) struct Nip42Session {
+    /// Last sent challenge
+    pub challenge: Option<String>,
+    /// Authenticated public key
+    pub public_key: Option<PublicKey>,
+}
+
+impl Nip42Session {
+    #[inline]
+    pub fn is_authenticated(&self) -> bool {
+        self.public_key.is_some()
+    }
+}
+
+/// State of a single client connection
+#[derive(Default)]
+pub(super) struct Session {
+    pub subscriptions: HashMap<SubscriptionId, Vec<Filter>>,
+    pub negentropy_subscription: HashMap<SubscriptionId, Negentropy>,
+    pub nip42: Nip42Session,
+}
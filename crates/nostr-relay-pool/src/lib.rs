@@ -10,17 +10,28 @@
 #![allow(unknown_lints)]
 #![allow(clippy::arc_with_non_send_sync)]
 
+#[cfg(test)]
+mod mock;
 pub mod pool;
 pub mod prelude;
 pub mod relay;
 
 pub use self::pool::options::RelayPoolOptions;
+pub use self::pool::report::PublishReport;
 pub use self::pool::{RelayPool, RelayPoolNotification};
+pub use self::relay::auth::{AuthenticationError, RelayAuthenticator};
 pub use self::relay::flags::{AtomicRelayServiceFlags, RelayServiceFlags};
 pub use self::relay::limits::RelayLimits;
 pub use self::relay::options::{
-    FilterOptions, NegentropyDirection, NegentropyOptions, RelayOptions, RelaySendOptions,
-    SubscribeAutoCloseOptions, SubscribeOptions,
+    FilterOptions, NegentropyDirection, NegentropyOptions, PaginationOptions, RateLimit,
+    ReconnectPolicy, RelayOptions, RelaySendOptions, SubscribeAutoCloseOptions, SubscribeOptions,
 };
+pub use self::relay::publish::PublishOutcome;
+pub use self::relay::score::RelayScore;
 pub use self::relay::stats::RelayConnectionStats;
+pub use self::relay::stream::{EventStream, SubscriptionItem, SubscriptionStream};
+pub use self::relay::transport::{
+    DefaultWebSocketTransport, DuplexConnection, DuplexListener, DuplexTransport, TransportError,
+    WebSocketTransport,
+};
 pub use self::relay::{Relay, RelayNotification, RelayStatus};
@@ -0,0 +1,73 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Mock relays for tests
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use nostr::{ClientMessage, RelayMessage, SubscriptionId, Url};
+
+use crate::{DuplexConnection, DuplexTransport, RelayOptions, RelayPool};
+
+/// Spawn a mock relay, answering every [`ClientMessage`] of every connection with the returned messages
+///
+/// The handler receive the URL requested by the client, to play many relays with the same transport.
+pub(crate) fn spawn<F>(handler: F) -> DuplexTransport
+where
+    F: Fn(&Url, ClientMessage) -> Vec<RelayMessage> + Send + Sync + 'static,
+{
+    let (transport, mut listener) = DuplexTransport::new();
+    let handler = Arc::new(handler);
+    tokio::spawn(async move {
+        while let Some(mut connection) = listener.accept().await {
+            let handler = handler.clone();
+            tokio::spawn(async move {
+                while let Some(msg) = connection.recv().await {
+                    for msg in handler(connection.url(), msg) {
+                        if connection.send(msg).is_err() {
+                            return;
+                        }
+                    }
+                }
+            });
+        }
+    });
+    transport
+}
+
+/// Pool connected to the relays, all using the same transport
+pub(crate) async fn pool<I>(transport: DuplexTransport, urls: I) -> RelayPool
+where
+    I: IntoIterator<Item = &'static str>,
+{
+    let pool = RelayPool::default();
+    add_relays(&pool, transport, urls).await;
+    pool
+}
+
+/// Add the relays to the pool, using the same transport, and connect them
+pub(crate) async fn add_relays<I>(pool: &RelayPool, transport: DuplexTransport, urls: I)
+where
+    I: IntoIterator<Item = &'static str>,
+{
+    for url in urls.into_iter() {
+        let opts = RelayOptions::new().transport(transport.clone());
+        pool.add_relay(url, opts).await.unwrap();
+    }
+    pool.connect(Some(Duration::from_secs(1))).await;
+}
+
+/// Wait for the next `REQ` of the connection, ignoring the other messages
+pub(crate) async fn recv_req(connection: &mut DuplexConnection) -> SubscriptionId {
+    loop {
+        match connection.recv().await {
+            Some(ClientMessage::Req {
+                subscription_id, ..
+            }) => return subscription_id,
+            Some(..) => (),
+            None => panic!("Connection closed"),
+        }
+    }
+}
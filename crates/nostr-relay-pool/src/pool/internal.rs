@@ -6,23 +6,39 @@
 
 use std::collections::{BTreeSet, HashMap, HashSet};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, RwLock as StdRwLock, Weak};
 use std::time::Duration;
 
+use async_utility::futures_util::future::{self, Either};
 use async_utility::{thread, time};
 use atomic_destructor::AtomicDestroyer;
 use nostr::message::MessageHandleError;
 use nostr::{ClientMessage, Event, EventId, Filter, SubscriptionId, Timestamp, TryIntoUrl, Url};
 use nostr_database::{DatabaseError, DynNostrDatabase, IntoNostrDatabase, Order};
 use thiserror::Error;
+use tokio::sync::broadcast::error::RecvError;
 use tokio::sync::{broadcast, Mutex, RwLock};
 
 use super::options::RelayPoolOptions;
+use super::outbox::{self, Attempts, Backoff, Delivery};
+use super::report::{self, PublishReport};
 use super::RelayPoolNotification;
-use crate::relay::options::{FilterOptions, NegentropyOptions, RelayOptions, RelaySendOptions};
-use crate::relay::{Error as RelayError, Relay};
+use crate::relay::auth::SharedAuthenticator;
+use crate::relay::options::{
+    FilterOptions, NegentropyOptions, PaginationOptions, RelayOptions, RelaySendOptions,
+};
+use crate::relay::stream::{EventStream, SubscriptionSink, SubscriptionStream};
+use crate::relay::{
+    Error as RelayError, PublishOutcome, Relay, RelayAuthenticator, RelayConnectionStats,
+    RelayScore, RelayStatus,
+};
 use crate::SubscribeOptions;
 
+/// Interval between the updates of the relay states by score
+const SCORING_INTERVAL: Duration = Duration::from_secs(10);
+/// Max number of disabled relays used when all the relays are disabled
+const MAX_DISABLED_FALLBACK: usize = 3;
+
 /// [`RelayPool`](super::RelayPool) error
 #[derive(Debug, Error)]
 pub enum Error {
@@ -53,9 +69,9 @@ pub enum Error {
     /// Msgs not sent
     #[error("messages not sent")]
     MsgsNotSent,
-    /// Event/s not published
+    /// Event/s not published (not accepted by any relay)
     #[error("event/s not published")]
-    EventNotPublished,
+    EventNotPublished(PublishReport),
     /// Relay not found
     #[error("relay not found")]
     RelayNotFound,
@@ -64,13 +80,39 @@ pub enum Error {
     Handler(String),
 }
 
+/// Pool subscription
+#[derive(Debug, Clone)]
+struct PoolSubscription {
+    filters: Vec<Filter>,
+    /// Targeted relays (`None` means all the relays)
+    relays: Option<HashSet<Url>>,
+}
+
+impl PoolSubscription {
+    fn is_for(&self, url: &Url) -> bool {
+        match &self.relays {
+            Some(relays) => relays.contains(url),
+            None => true,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct InternalRelayPool {
     database: Arc<DynNostrDatabase>,
     relays: Arc<RwLock<HashMap<Url, Relay>>>,
     notification_sender: broadcast::Sender<RelayPoolNotification>,
-    subscriptions: Arc<RwLock<HashMap<SubscriptionId, Vec<Filter>>>>,
-    // opts: RelayPoolOptions,
+    subscriptions: Arc<RwLock<HashMap<SubscriptionId, PoolSubscription>>>,
+    /// Subscription streams, for the relays connected after the subscription
+    streams: Arc<RwLock<HashMap<SubscriptionId, Weak<SubscriptionSink>>>>,
+    /// Streams of the subscriptions without relays, kept open until a relay is connected
+    pending_streams: Arc<RwLock<HashMap<SubscriptionId, Arc<SubscriptionSink>>>>,
+    authenticator: SharedAuthenticator,
+    outbox_backoff: Arc<Mutex<HashMap<Url, Backoff>>>,
+    outbox_attempts: Arc<Mutex<HashMap<Url, Attempts>>>,
+    outbox_running: Arc<AtomicBool>,
+    scoring_running: Arc<AtomicBool>,
+    opts: RelayPoolOptions,
 }
 
 impl AtomicDestroyer for InternalRelayPool {
@@ -100,7 +142,14 @@ impl InternalRelayPool {
             relays: Arc::new(RwLock::new(HashMap::new())),
             notification_sender,
             subscriptions: Arc::new(RwLock::new(HashMap::new())),
-            //opts,
+            streams: Arc::new(RwLock::new(HashMap::new())),
+            pending_streams: Arc::new(RwLock::new(HashMap::new())),
+            authenticator: Arc::new(StdRwLock::new(None)),
+            outbox_backoff: Arc::new(Mutex::new(HashMap::new())),
+            outbox_attempts: Arc::new(Mutex::new(HashMap::new())),
+            outbox_running: Arc::new(AtomicBool::new(false)),
+            scoring_running: Arc::new(AtomicBool::new(false)),
+            opts,
         }
     }
 
@@ -116,9 +165,16 @@ impl InternalRelayPool {
     }
 
     pub async fn shutdown(&self) -> Result<(), Error> {
+        // Stop outbox and scoring
+        self.outbox_running.store(false, Ordering::SeqCst);
+        self.scoring_running.store(false, Ordering::SeqCst);
+
         // Disconnect all relays
         self.disconnect().await?;
 
+        // Close the streams waiting for relays
+        self.pending_streams.write().await.clear();
+
         // Send shutdown notification
         time::timeout(Some(Duration::from_secs(1)), async move {
             let _ = self
@@ -140,11 +196,172 @@ impl InternalRelayPool {
         self.database.clone()
     }
 
+    pub fn set_authenticator(&self, authenticator: Option<Arc<dyn RelayAuthenticator>>) {
+        match self.authenticator.write() {
+            Ok(mut a) => *a = authenticator,
+            Err(e) => *e.into_inner() = authenticator,
+        }
+    }
+
     pub async fn relays(&self) -> HashMap<Url, Relay> {
         let relays = self.relays.read().await;
         relays.clone()
     }
 
+    /// Relays for reads (not targeted to specific relays)
+    pub async fn read_relays(&self) -> HashMap<Url, Relay> {
+        self.select_relays(false).await
+    }
+
+    /// Relays for writes (not targeted to specific relays)
+    pub async fn write_relays(&self) -> HashMap<Url, Relay> {
+        self.select_relays(true).await
+    }
+
+    /// Select relays by score, if scoring is enabled
+    ///
+    /// Skip the relays without the `READ`/`WRITE` flag and the disabled ones.
+    /// The demoted relays are returned only if no healthy relay is available
+    /// and the best disabled relays only if all the relays are disabled.
+    async fn select_relays(&self, write: bool) -> HashMap<Url, Relay> {
+        let relays: HashMap<Url, Relay> = self.relays().await;
+
+        if !self.opts.scoring {
+            return relays;
+        }
+
+        let now: Timestamp = Timestamp::now();
+        let mut healthy: HashMap<Url, Relay> = HashMap::new();
+        let mut demoted: HashMap<Url, Relay> = HashMap::new();
+        let mut disabled: Vec<(f64, Url, Relay)> = Vec::new();
+
+        for (url, relay) in relays.into_iter() {
+            let flags = relay.flags();
+            if (write && !flags.has_write()) || (!write && !flags.has_read()) {
+                continue;
+            }
+
+            let score: RelayScore = relay.score().await;
+            let (value, disabled_until) = if write {
+                (score.write, score.write_disabled_until)
+            } else {
+                (score.read, score.read_disabled_until)
+            };
+
+            // Still disabled or waiting to be disabled by the scoring task
+            let is_disabled: bool = match disabled_until {
+                Some(until) => now < until,
+                None => value < self.opts.scoring_disable_threshold,
+            };
+
+            if is_disabled {
+                disabled.push((value, url, relay));
+            } else if value < self.opts.scoring_demote_threshold {
+                demoted.insert(url, relay);
+            } else {
+                healthy.insert(url, relay);
+            }
+        }
+
+        if !healthy.is_empty() {
+            return healthy;
+        }
+
+        if !demoted.is_empty() {
+            return demoted;
+        }
+
+        // All disabled: better the best ones than nothing
+        disabled.sort_by(|a, b| b.0.total_cmp(&a.0));
+        disabled
+            .into_iter()
+            .take(MAX_DISABLED_FALLBACK)
+            .map(|(_, url, relay)| (url, relay))
+            .collect()
+    }
+
+    /// Disable the relays with a low score and give a new chance to the ones with expired disable time
+    pub(super) async fn update_relay_states(&self) {
+        let now: Timestamp = Timestamp::now();
+        for (url, relay) in self.relays().await.into_iter() {
+            let flags = relay.flags();
+            if flags.has_read() {
+                self.update_relay_state(&url, &relay, now, false).await;
+            }
+            if flags.has_write() {
+                self.update_relay_state(&url, &relay, now, true).await;
+            }
+        }
+    }
+
+    async fn update_relay_state(&self, url: &Url, relay: &Relay, now: Timestamp, write: bool) {
+        let service: &str = if write { "writes" } else { "reads" };
+        let stats: RelayConnectionStats = relay.stats();
+        let disabled_until: Option<Timestamp> = if write {
+            stats.write_disabled_until()
+        } else {
+            stats.read_disabled_until()
+        };
+
+        // Check if disabled or, if expired, give a new chance
+        if let Some(until) = disabled_until {
+            if now < until {
+                return;
+            }
+
+            tracing::info!("Re-enabling {url} for {service}");
+            if write {
+                stats.set_write_disabled_until(None);
+                stats.reset_write_score();
+            } else {
+                stats.set_read_disabled_until(None);
+                stats.reset_read_score().await;
+            }
+        }
+
+        let score: RelayScore = relay.score().await;
+        let score: f64 = if write { score.write } else { score.read };
+
+        if score < self.opts.scoring_disable_threshold {
+            tracing::warn!("Disabling {url} for {service}: score={score:.2}");
+            let until: Timestamp = now + self.opts.scoring_disable_duration;
+            if write {
+                stats.set_write_disabled_until(Some(until));
+            } else {
+                stats.set_read_disabled_until(Some(until));
+            }
+        }
+    }
+
+    fn spawn_scoring(&self) {
+        if !self.opts.scoring || self.scoring_running.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let pool = self.clone();
+        let res = thread::spawn(async move {
+            while pool.scoring_running.load(Ordering::SeqCst) {
+                pool.update_relay_states().await;
+                thread::sleep(SCORING_INTERVAL).await;
+            }
+
+            tracing::debug!("Scoring stopped");
+        });
+
+        if let Err(e) = res {
+            tracing::error!("Impossible to spawn scoring thread: {e}");
+            self.scoring_running.store(false, Ordering::SeqCst);
+        }
+    }
+
+    pub async fn scores(&self) -> HashMap<Url, RelayScore> {
+        let mut scores: HashMap<Url, RelayScore> = HashMap::new();
+        for (url, relay) in self.relays().await.into_iter() {
+            scores.insert(url, relay.score().await);
+        }
+        scores
+    }
+
     async fn internal_relay(&self, url: &Url) -> Result<Relay, Error> {
         let relays = self.relays.read().await;
         relays.get(url).cloned().ok_or(Error::RelayNotFound)
@@ -160,20 +377,33 @@ impl InternalRelayPool {
     }
 
     pub async fn subscriptions(&self) -> HashMap<SubscriptionId, Vec<Filter>> {
-        self.subscriptions.read().await.clone()
+        let subscriptions = self.subscriptions.read().await;
+        subscriptions
+            .iter()
+            .map(|(id, subscription)| (id.clone(), subscription.filters.clone()))
+            .collect()
     }
 
     pub async fn subscription(&self, id: &SubscriptionId) -> Option<Vec<Filter>> {
         let subscriptions = self.subscriptions.read().await;
-        subscriptions.get(id).cloned()
+        subscriptions
+            .get(id)
+            .map(|subscription| subscription.filters.clone())
     }
 
-    async fn update_subscription(&self, id: SubscriptionId, filters: Vec<Filter>) {
+    pub async fn subscription_relays(&self, id: &SubscriptionId) -> Option<HashSet<Url>> {
+        let subscriptions = self.subscriptions.read().await;
+        subscriptions.get(id)?.relays.clone()
+    }
+
+    async fn update_subscription(
+        &self,
+        id: SubscriptionId,
+        filters: Vec<Filter>,
+        relays: Option<HashSet<Url>>,
+    ) {
         let mut subscriptions = self.subscriptions.write().await;
-        subscriptions
-            .entry(id)
-            .and_modify(|f| *f = filters.clone())
-            .or_insert(filters);
+        subscriptions.insert(id, PoolSubscription { filters, relays });
     }
 
     pub(crate) async fn remove_subscription(&self, id: &SubscriptionId) {
@@ -194,7 +424,12 @@ impl InternalRelayPool {
         let url: Url = url.try_into_url()?;
         let mut relays = self.relays.write().await;
         if !relays.contains_key(&url) {
-            let relay = Relay::custom(url, self.database.clone(), opts);
+            let relay = Relay::with_authenticator(
+                url,
+                self.database.clone(),
+                opts,
+                self.authenticator.clone(),
+            );
             relay
                 .set_notification_sender(Some(self.notification_sender.clone()))
                 .await;
@@ -330,8 +565,12 @@ impl InternalRelayPool {
         Ok(())
     }
 
-    pub async fn send_event(&self, event: Event, opts: RelaySendOptions) -> Result<EventId, Error> {
-        let relays: HashMap<Url, Relay> = self.relays().await;
+    pub async fn send_event(
+        &self,
+        event: Event,
+        opts: RelaySendOptions,
+    ) -> Result<PublishReport, Error> {
+        let relays: HashMap<Url, Relay> = self.write_relays().await;
         self.send_event_to(relays.into_keys(), event, opts).await
     }
 
@@ -339,8 +578,8 @@ impl InternalRelayPool {
         &self,
         events: Vec<Event>,
         opts: RelaySendOptions,
-    ) -> Result<(), Error> {
-        let relays = self.relays().await;
+    ) -> Result<PublishReport, Error> {
+        let relays = self.write_relays().await;
         self.batch_event_to(relays.into_keys(), events, opts).await
     }
 
@@ -349,15 +588,13 @@ impl InternalRelayPool {
         urls: I,
         event: Event,
         opts: RelaySendOptions,
-    ) -> Result<EventId, Error>
+    ) -> Result<PublishReport, Error>
     where
         I: IntoIterator<Item = U>,
         U: TryIntoUrl,
         Error: From<<U as TryIntoUrl>::Err>,
     {
-        let event_id: EventId = event.id;
-        self.batch_event_to(urls, vec![event], opts).await?;
-        Ok(event_id)
+        self.batch_event_to(urls, vec![event], opts).await
     }
 
     pub async fn batch_event_to<I, U>(
@@ -365,12 +602,16 @@ impl InternalRelayPool {
         urls: I,
         events: Vec<Event>,
         opts: RelaySendOptions,
-    ) -> Result<(), Error>
+    ) -> Result<PublishReport, Error>
     where
         I: IntoIterator<Item = U>,
         U: TryIntoUrl,
         Error: From<<U as TryIntoUrl>::Err>,
     {
+        if events.is_empty() {
+            return Err(Error::Relay(RelayError::BatchEventEmpty));
+        }
+
         // Compose URLs
         let urls: HashSet<Url> = urls
             .into_iter()
@@ -394,44 +635,257 @@ impl InternalRelayPool {
             return Err(Error::NoRelays);
         }
 
+        // Check if urls set contains ONLY already added relays
+        if !urls.iter().all(|url| relays.contains_key(url)) {
+            return Err(Error::RelayNotFound);
+        }
+
+        // Add events to the outbox before sending them
+        let outbox_ids: Vec<EventId> = self.outbox_add(&events, &urls).await;
+
+        let ids: Vec<EventId> = events.iter().map(|e| e.id()).collect();
+        let mut report: PublishReport = PublishReport::new(ids.clone());
+
         // If passed only 1 url, not use threads
         if urls.len() == 1 {
             let url: Url = urls.into_iter().next().ok_or(Error::RelayNotFound)?;
             let relay: &Relay = relays.get(&url).ok_or(Error::RelayNotFound)?;
-            relay.batch_event(events, opts).await?;
+            let res = relay.publish(events, opts).await;
+            self.outbox_handle(&url, &outbox_ids, &res, false).await;
+            report.insert(url, report::outcomes(&ids, res));
         } else {
-            // Check if urls set contains ONLY already added relays
-            if !urls.iter().all(|url| relays.contains_key(url)) {
-                return Err(Error::RelayNotFound);
-            }
-
-            let sent_to_at_least_one_relay: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
             let mut handles = Vec::with_capacity(urls.len());
 
             for (url, relay) in relays.into_iter().filter(|(url, ..)| urls.contains(url)) {
+                let pool = self.clone();
                 let events = events.clone();
-                let sent = sent_to_at_least_one_relay.clone();
+                let ids = ids.clone();
+                let outbox_ids = outbox_ids.clone();
                 let handle = thread::spawn(async move {
-                    match relay.batch_event(events, opts).await {
-                        Ok(_) => {
-                            sent.store(true, Ordering::SeqCst);
-                        }
-                        Err(e) => tracing::error!("Impossible to send event to {url}: {e}"),
+                    let res = relay.publish(events, opts).await;
+                    pool.outbox_handle(&url, &outbox_ids, &res, false).await;
+                    if let Err(e) = &res {
+                        tracing::error!("Impossible to send event to {url}: {e}");
                     }
+                    (url, report::outcomes(&ids, res))
                 })?;
                 handles.push(handle);
             }
 
             for handle in handles.into_iter() {
-                handle.join().await?;
+                let (url, outcomes) = handle.join().await?;
+                report.insert(url, outcomes);
             }
+        }
 
-            if !sent_to_at_least_one_relay.load(Ordering::SeqCst) {
-                return Err(Error::EventNotPublished);
+        if !report.is_published() {
+            return Err(Error::EventNotPublished(report));
+        }
+
+        Ok(report)
+    }
+
+    /// Add events to the outbox of the relays (if enabled)
+    ///
+    /// Return the IDs of the events added to the outbox (ephemeral events are skipped).
+    async fn outbox_add(&self, events: &[Event], urls: &HashSet<Url>) -> Vec<EventId> {
+        if !self.opts.outbox {
+            return Vec::new();
+        }
+
+        let mut ids: Vec<EventId> = Vec::with_capacity(events.len());
+        for event in events.iter().filter(|e| !e.is_ephemeral()) {
+            match self.database.outbox_add(event, urls.clone()).await {
+                Ok(()) => ids.push(event.id()),
+                Err(DatabaseError::NotSupported) => {
+                    tracing::debug!("Outbox not supported by the database, skipping it");
+                    break;
+                }
+                Err(e) => tracing::error!("Impossible to add event {} to outbox: {e}", event.id()),
             }
         }
+        ids
+    }
 
-        Ok(())
+    /// Remove the delivered or rejected events from the outbox of the relay and schedule the next retry
+    ///
+    /// The events not delivered after the max attempts are rejected.
+    async fn outbox_handle(
+        &self,
+        url: &Url,
+        ids: &[EventId],
+        res: &Result<HashMap<EventId, PublishOutcome>, RelayError>,
+        notify: bool,
+    ) {
+        if ids.is_empty() {
+            return;
+        }
+
+        let mut retry: bool = false;
+        let mut done: Vec<(EventId, RelayPoolNotification)> = Vec::new();
+
+        {
+            let max_attempts: u32 = self.opts.outbox_max_attempts;
+            let mut attempts = self.outbox_attempts.lock().await;
+            let relay_attempts: &mut Attempts = attempts.entry(url.clone()).or_default();
+
+            for (event_id, delivery) in outbox::deliveries(ids, res).into_iter() {
+                let notification: RelayPoolNotification = match delivery {
+                    Delivery::Delivered => RelayPoolNotification::EventDelivered {
+                        relay_url: url.clone(),
+                        event_id,
+                    },
+                    Delivery::Rejected(message) => RelayPoolNotification::EventRejected {
+                        relay_url: url.clone(),
+                        event_id,
+                        message,
+                    },
+                    Delivery::Retry => {
+                        if relay_attempts.failed(event_id, max_attempts) {
+                            retry = true;
+                            continue;
+                        }
+
+                        RelayPoolNotification::EventRejected {
+                            relay_url: url.clone(),
+                            event_id,
+                            message: format!("not delivered after {max_attempts} attempts"),
+                        }
+                    }
+                };
+                relay_attempts.remove(&event_id);
+                done.push((event_id, notification));
+            }
+
+            if relay_attempts.is_empty() {
+                attempts.remove(url);
+            }
+        }
+
+        for (event_id, notification) in done.into_iter() {
+            if let Err(e) = self.database.outbox_remove(event_id, url.clone()).await {
+                tracing::error!("Impossible to remove event {event_id} from {url} outbox: {e}");
+            }
+
+            if notify {
+                let _ = self.notification_sender.send(notification);
+            }
+        }
+
+        let mut backoff = self.outbox_backoff.lock().await;
+        if retry {
+            let attempts: u32 = backoff.get(url).map_or(0, |b| b.attempts());
+            let next = Backoff::failed(attempts, self.opts.outbox_retry_interval, Timestamp::now());
+            backoff.insert(url.clone(), next);
+        } else {
+            backoff.remove(url);
+        }
+    }
+
+    /// Send again the events in the outbox of the relay
+    async fn outbox_retry(&self, url: &Url) {
+        let events: Vec<Event> = match self.database.outbox(url.clone()).await {
+            Ok(events) => events,
+            Err(DatabaseError::NotSupported) => return,
+            Err(e) => {
+                tracing::error!("Impossible to get {url} outbox: {e}");
+                return;
+            }
+        };
+
+        if events.is_empty() {
+            self.outbox_backoff.lock().await.remove(url);
+            return;
+        }
+
+        // Check if relay is still in the pool and connected
+        let relay: Relay = match self.internal_relay(url).await {
+            Ok(relay) => relay,
+            Err(..) => return,
+        };
+
+        if !relay.is_connected().await {
+            return;
+        }
+
+        // Don't send expired events
+        let (expired, events): (Vec<Event>, Vec<Event>) =
+            events.into_iter().partition(|e| e.is_expired());
+        if !expired.is_empty() {
+            let ids: Vec<EventId> = expired.iter().map(|e| e.id()).collect();
+            let res = Ok(ids
+                .iter()
+                .map(|id| {
+                    (
+                        *id,
+                        PublishOutcome::from_ok(false, "invalid: event expired"),
+                    )
+                })
+                .collect());
+            self.outbox_handle(url, &ids, &res, true).await;
+        }
+
+        if !events.is_empty() {
+            tracing::debug!("Sending {} outbox events to {url}", events.len());
+            let ids: Vec<EventId> = events.iter().map(|e| e.id()).collect();
+            let res = relay.publish(events, RelaySendOptions::new()).await;
+            self.outbox_handle(url, &ids, &res, true).await;
+        }
+    }
+
+    /// Retry outbox events when the relays reconnect and when the backoff expires
+    fn spawn_outbox(&self) {
+        if !self.opts.outbox || self.outbox_running.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let pool = self.clone();
+        let res = thread::spawn(async move {
+            let mut notifications = pool.notifications();
+            let interval: Duration = pool.opts.outbox_retry_interval;
+
+            while pool.outbox_running.load(Ordering::SeqCst) {
+                match time::timeout(Some(interval), notifications.recv()).await {
+                    Some(Ok(RelayPoolNotification::RelayStatus {
+                        relay_url,
+                        status: RelayStatus::Connected,
+                    })) => {
+                        let pool = pool.clone();
+                        let _ = thread::spawn(async move {
+                            pool.outbox_retry(&relay_url).await;
+                        });
+                    }
+                    Some(Ok(..)) | Some(Err(RecvError::Lagged(..))) | None => {}
+                    Some(Err(RecvError::Closed)) => break,
+                }
+
+                // Retry relays with expired backoff
+                let now = Timestamp::now();
+                let due: Vec<Url> = pool
+                    .outbox_backoff
+                    .lock()
+                    .await
+                    .iter()
+                    .filter(|(_, b)| b.is_due(now))
+                    .map(|(url, _)| url.clone())
+                    .collect();
+                for url in due.into_iter() {
+                    pool.outbox_retry(&url).await;
+                }
+            }
+
+            pool.outbox_running.store(false, Ordering::SeqCst);
+            tracing::debug!("Outbox stopped");
+        });
+
+        if let Err(e) = res {
+            tracing::error!("Impossible to spawn outbox thread: {e}");
+            self.outbox_running.store(false, Ordering::SeqCst);
+        }
+    }
+
+    pub async fn outbox(&self, url: Url) -> Result<Vec<Event>, Error> {
+        Ok(self.database.outbox(url).await?)
     }
 
     pub async fn subscribe(&self, filters: Vec<Filter>, opts: SubscribeOptions) -> SubscriptionId {
@@ -447,12 +901,13 @@ impl InternalRelayPool {
         opts: SubscribeOptions,
     ) {
         // Get relays
-        let relays = self.relays().await;
+        let relays = self.read_relays().await;
 
         // Check if isn't auto-closing subscription
         if !opts.is_auto_closing() {
             // Update pool subscriptions
-            self.update_subscription(id.clone(), filters.clone()).await;
+            self.update_subscription(id.clone(), filters.clone(), None)
+                .await;
         }
 
         // Subscribe
@@ -466,9 +921,153 @@ impl InternalRelayPool {
         }
     }
 
+    pub async fn subscribe_with_id_to<I, U>(
+        &self,
+        urls: I,
+        id: SubscriptionId,
+        filters: Vec<Filter>,
+        opts: SubscribeOptions,
+    ) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = U>,
+        U: TryIntoUrl,
+        Error: From<<U as TryIntoUrl>::Err>,
+    {
+        // Compose URLs
+        let urls: HashSet<Url> = urls
+            .into_iter()
+            .map(|u| u.try_into_url())
+            .collect::<Result<_, _>>()?;
+
+        // Check if urls set isn't empty
+        if urls.is_empty() {
+            return Err(Error::NoRelaysSpecified);
+        }
+
+        // Check if urls set contains ONLY already added relays
+        let relays: HashMap<Url, Relay> = self.relays().await;
+        if !urls.iter().all(|url| relays.contains_key(url)) {
+            return Err(Error::RelayNotFound);
+        }
+
+        let auto_closing: bool = opts.is_auto_closing();
+
+        // Check if isn't auto-closing subscription
+        if !auto_closing {
+            // Update pool subscriptions
+            self.update_subscription(id.clone(), filters.clone(), Some(urls.clone()))
+                .await;
+        }
+
+        // Subscribe
+        for url in urls.into_iter() {
+            if let Some(relay) = relays.get(&url) {
+                // Save the subscription in the relay before sending the REQ,
+                // so it's sent again at reconnection also if the relay is disconnected now
+                if !auto_closing {
+                    relay
+                        .inner
+                        .update_subscription(id.clone(), filters.clone())
+                        .await;
+                }
+
+                if let Err(e) = relay
+                    .subscribe_with_id(id.clone(), filters.clone(), opts)
+                    .await
+                {
+                    tracing::error!("Impossible to subscribe to {url}: {e}");
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn subscribe_stream_with_id(
+        &self,
+        id: SubscriptionId,
+        filters: Vec<Filter>,
+        opts: SubscribeOptions,
+    ) -> SubscriptionStream {
+        // Get relays
+        let relays = self.read_relays().await;
+
+        let (sink, stream) = SubscriptionSink::new(id.clone());
+
+        // Check if isn't auto-closing subscription
+        if !opts.is_auto_closing() {
+            // Update pool subscriptions
+            self.update_subscription(id.clone(), filters.clone(), None)
+                .await;
+            let mut streams = self.streams.write().await;
+            streams.insert(id.clone(), Arc::downgrade(&sink));
+
+            // No relays: wait for the relays connected later
+            if relays.is_empty() {
+                let mut pending_streams = self.pending_streams.write().await;
+                pending_streams.insert(id.clone(), sink.clone());
+            }
+        }
+
+        // Subscribe
+        let targets = relays.into_values().map(|relay| (relay, filters.clone()));
+        self.subscribe_stream_to_relays(id, targets, sink, opts)
+            .await;
+
+        stream
+    }
+
+    pub async fn subscribe_stream_targeted(
+        &self,
+        id: SubscriptionId,
+        targets: HashMap<Url, Vec<Filter>>,
+        opts: SubscribeOptions,
+    ) -> SubscriptionStream {
+        // Get relays
+        let relays = self.relays().await;
+
+        let targets = targets
+            .into_iter()
+            .filter_map(|(url, filters)| match relays.get(&url) {
+                Some(relay) => Some((relay.clone(), filters)),
+                None => {
+                    tracing::error!("Impossible to subscribe to {url}: relay not found");
+                    None
+                }
+            });
+
+        let (sink, stream) = SubscriptionSink::new(id.clone());
+        self.subscribe_stream_to_relays(id, targets, sink, opts)
+            .await;
+        stream
+    }
+
+    /// Register the [`SubscriptionSink`] in the relays and subscribe
+    ///
+    /// The stream ends when all the relays have dropped the sink.
+    async fn subscribe_stream_to_relays<I>(
+        &self,
+        id: SubscriptionId,
+        targets: I,
+        sink: Arc<SubscriptionSink>,
+        opts: SubscribeOptions,
+    ) where
+        I: IntoIterator<Item = (Relay, Vec<Filter>)>,
+    {
+        for (relay, filters) in targets.into_iter() {
+            relay.inner.add_stream(id.clone(), sink.clone()).await;
+            if let Err(e) = relay.subscribe_with_id(id.clone(), filters, opts).await {
+                relay.inner.remove_stream(&id).await;
+                tracing::error!("{e}");
+            }
+        }
+    }
+
     pub async fn unsubscribe(&self, id: SubscriptionId, opts: RelaySendOptions) {
         let relays = self.relays().await;
         self.remove_subscription(&id).await;
+        self.streams.write().await.remove(&id);
+        self.pending_streams.write().await.remove(&id);
         for relay in relays.values() {
             if let Err(e) = relay.unsubscribe(id.clone(), opts).await {
                 tracing::error!("{e}");
@@ -479,6 +1078,8 @@ impl InternalRelayPool {
     pub async fn unsubscribe_all(&self, opts: RelaySendOptions) {
         let relays = self.relays().await;
         self.remove_all_subscriptions().await;
+        self.streams.write().await.clear();
+        self.pending_streams.write().await.clear();
         for relay in relays.values() {
             if let Err(e) = relay.unsubscribe_all(opts).await {
                 tracing::error!("{e}");
@@ -535,9 +1136,10 @@ impl InternalRelayPool {
                 let filters = filters.clone();
                 let ids = ids.clone();
                 let events = events.clone();
+                let id: SubscriptionId = SubscriptionId::generate();
                 let handle = thread::spawn(async move {
                     if let Err(e) = relay
-                        .get_events_of_with_callback(filters, timeout, opts, |event| async {
+                        .get_events_of_with_callback(id, filters, timeout, opts, |event| async {
                             let mut ids = ids.lock().await;
                             if !ids.contains(&event.id()) {
                                 let mut events = events.lock().await;
@@ -569,6 +1171,9 @@ impl InternalRelayPool {
     }
 
     pub async fn connect(&self, connection_timeout: Option<Duration>) {
+        self.spawn_outbox();
+        self.spawn_scoring();
+
         let relays: HashMap<Url, Relay> = self.relays().await;
 
         if connection_timeout.is_some() {
@@ -602,10 +1207,141 @@ impl InternalRelayPool {
         Ok(())
     }
 
+    pub async fn stream_events_from<I, U>(
+        &self,
+        urls: I,
+        filters: Vec<Filter>,
+        timeout: Duration,
+        opts: FilterOptions,
+    ) -> Result<EventStream, Error>
+    where
+        I: IntoIterator<Item = U>,
+        U: TryIntoUrl,
+        Error: From<<U as TryIntoUrl>::Err>,
+    {
+        let targets: HashMap<Url, Vec<Filter>> = urls
+            .into_iter()
+            .map(|u| Ok((u.try_into_url()?, filters.clone())))
+            .collect::<Result<_, Error>>()?;
+
+        // No relays: stream only the stored events
+        if targets.is_empty() {
+            let (tx, stream) = EventStream::new();
+            let stored_events: Vec<Event> = self.database.query(filters, Order::Desc).await?;
+            thread::spawn(async move {
+                for event in stored_events.into_iter() {
+                    if tx.send(event).await.is_err() {
+                        break;
+                    }
+                }
+            })?;
+            return Ok(stream);
+        }
+
+        self.stream_events_targeted(targets, timeout, opts).await
+    }
+
+    pub async fn stream_events_targeted(
+        &self,
+        targets: HashMap<Url, Vec<Filter>>,
+        timeout: Duration,
+        opts: FilterOptions,
+    ) -> Result<EventStream, Error> {
+        let relays: HashMap<Url, Relay> = self.relays().await;
+
+        // Check if targets contains ONLY already added relays
+        if !targets.keys().all(|url| relays.contains_key(url)) {
+            return Err(Error::RelayNotFound);
+        }
+
+        let (tx, stream) = EventStream::new();
+
+        // Send stored events first
+        // The IDs are locked until all the stored events have been sent, so the relay events wait for them.
+        let filters: Vec<Filter> = targets.values().flatten().cloned().collect();
+        let stored_events: Vec<Event> = self
+            .database
+            .query(filters, Order::Desc)
+            .await
+            .unwrap_or_default();
+        let ids: Arc<Mutex<HashSet<EventId>>> =
+            Arc::new(Mutex::new(stored_events.iter().map(|e| e.id()).collect()));
+        let stored_ids = ids.clone().lock_owned().await;
+        let stored_tx = tx.clone();
+        thread::spawn(async move {
+            let _ids = stored_ids;
+            for event in stored_events.into_iter() {
+                if stored_tx.send(event).await.is_err() {
+                    break;
+                }
+            }
+        })?;
+
+        // Start queries
+        for (url, filters) in targets.into_iter() {
+            let relay: Relay = relays.get(&url).cloned().ok_or(Error::RelayNotFound)?;
+            let ids = ids.clone();
+            let tx = tx.clone();
+            let id: SubscriptionId = SubscriptionId::generate();
+            thread::spawn(async move {
+                let query = relay.get_events_of_with_callback(
+                    id.clone(),
+                    filters,
+                    timeout,
+                    opts,
+                    |event| async {
+                        let is_new: bool = ids.lock().await.insert(event.id());
+                        if is_new {
+                            // Wait for the consumer: only this query is stalled, not the relay
+                            let _ = tx.send(event).await;
+                        }
+                    },
+                );
+
+                // Stop when the stream is dropped
+                match future::select(Box::pin(query), Box::pin(tx.closed())).await {
+                    Either::Left((Err(e), ..)) => {
+                        tracing::error!("Failed to get events from {url}: {e}")
+                    }
+                    Either::Left((Ok(()), ..)) => (),
+                    Either::Right(..) => {
+                        tracing::debug!("Event stream dropped, closing query to {url}");
+                        let opts = RelaySendOptions::new().skip_send_confirmation(true);
+                        if let Err(e) = relay.unsubscribe(id, opts).await {
+                            tracing::error!("Failed to close query to {url}: {e}");
+                        }
+                    }
+                }
+            })?;
+        }
+
+        Ok(stream)
+    }
+
     pub(crate) async fn connect_relay(&self, relay: &Relay, connection_timeout: Option<Duration>) {
-        let subscriptions = self.subscriptions().await;
-        for (id, filters) in subscriptions.into_iter() {
-            relay.inner.update_subscription(id, filters).await;
+        self.spawn_outbox();
+        self.spawn_scoring();
+
+        if relay.opts().get_inherit_subscriptions() {
+            let url: Url = relay.url();
+            let subscriptions = self.subscriptions.read().await.clone();
+            let streams = self.streams.read().await.clone();
+            for (id, subscription) in subscriptions.into_iter() {
+                // Skip the subscriptions targeted to other relays
+                if !subscription.is_for(&url) {
+                    continue;
+                }
+
+                let pending: Option<Arc<SubscriptionSink>> =
+                    self.pending_streams.write().await.remove(&id);
+                if let Some(sink) = pending.or_else(|| streams.get(&id).and_then(Weak::upgrade)) {
+                    relay.inner.add_stream(id.clone(), sink).await;
+                }
+                relay
+                    .inner
+                    .update_subscription(id, subscription.filters)
+                    .await;
+            }
         }
         relay.connect(connection_timeout).await;
     }
@@ -616,6 +1352,46 @@ impl InternalRelayPool {
         self.reconcile_with_items(filter, items, opts).await
     }
 
+    pub async fn paginate(
+        &self,
+        filters: Vec<Filter>,
+        opts: PaginationOptions,
+    ) -> Result<(), Error> {
+        let relays = self.read_relays().await;
+
+        if relays.is_empty() {
+            return Err(Error::NoRelays);
+        }
+
+        let mut handles = Vec::new();
+        for (url, relay) in relays.into_iter() {
+            let filters = filters.clone();
+            let handle = thread::spawn(async move {
+                let res = relay.paginate(filters, opts).await;
+                if let Err(e) = &res {
+                    tracing::error!("Failed to paginate events from {url}: {e}");
+                }
+                res
+            })?;
+            handles.push(handle);
+        }
+
+        // Fail only if no relay succeeded
+        let mut last_error: Option<RelayError> = None;
+        let mut succeeded: bool = false;
+        for handle in handles.into_iter() {
+            match handle.join().await? {
+                Ok(()) => succeeded = true,
+                Err(e) => last_error = Some(e),
+            }
+        }
+
+        match last_error {
+            Some(e) if !succeeded => Err(Error::Relay(e)),
+            _ => Ok(()),
+        }
+    }
+
     pub async fn reconcile_with_items(
         &self,
         filter: Filter,
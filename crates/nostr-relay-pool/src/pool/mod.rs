@@ -4,7 +4,7 @@
 
 //! Relay Pool
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::future::Future;
 use std::sync::Arc;
 use std::time::Duration;
@@ -19,12 +19,19 @@ use tokio::sync::broadcast;
 
 mod internal;
 pub mod options;
+mod outbox;
+pub mod report;
 
 pub use self::internal::Error;
 use self::internal::InternalRelayPool;
 pub use self::options::RelayPoolOptions;
-use crate::relay::options::{FilterOptions, NegentropyOptions, RelayOptions, RelaySendOptions};
-use crate::relay::{Relay, RelayStatus};
+pub use self::report::PublishReport;
+use crate::relay::options::{
+    FilterOptions, NegentropyOptions, PaginationOptions, RelayOptions, RelaySendOptions,
+};
+use crate::relay::{
+    EventStream, Relay, RelayAuthenticator, RelayScore, RelayStatus, SubscriptionStream,
+};
 use crate::SubscribeOptions;
 
 /// Relay Pool Notification
@@ -53,6 +60,34 @@ pub enum RelayPoolNotification {
         /// Relay Status
         status: RelayStatus,
     },
+    /// Authenticated to relay (NIP-42)
+    Authenticated {
+        /// Relay url
+        relay_url: Url,
+    },
+    /// Authentication to relay failed or not allowed (NIP-42)
+    AuthenticationFailed {
+        /// Relay url
+        relay_url: Url,
+        /// Error message
+        message: String,
+    },
+    /// Outbox event delivered to relay, after a retry
+    EventDelivered {
+        /// Relay url
+        relay_url: Url,
+        /// Event ID
+        event_id: EventId,
+    },
+    /// Outbox event rejected by relay (or not delivered after the max attempts), after a retry
+    EventRejected {
+        /// Relay url
+        relay_url: Url,
+        /// Event ID
+        event_id: EventId,
+        /// Reason
+        message: String,
+    },
     /// Stop
     Stop,
     /// Shutdown
@@ -109,11 +144,34 @@ impl RelayPool {
         self.inner.database()
     }
 
+    /// Get events not yet delivered to the relay (oldest first)
+    ///
+    /// Require [`RelayPoolOptions::outbox`].
+    /// Events sent with [`RelayPool::send_msg`] or [`RelayPool::batch_msg`] aren't added to the outbox.
+    pub async fn outbox<U>(&self, url: U) -> Result<Vec<Event>, Error>
+    where
+        U: TryIntoUrl,
+        Error: From<<U as TryIntoUrl>::Err>,
+    {
+        let url: Url = url.try_into_url()?;
+        self.inner.outbox(url).await
+    }
+
+    /// Set the [`RelayAuthenticator`] used by relays with automatic authentication enabled
+    pub fn set_authenticator(&self, authenticator: Option<Arc<dyn RelayAuthenticator>>) {
+        self.inner.set_authenticator(authenticator)
+    }
+
     /// Get relays
     pub async fn relays(&self) -> HashMap<Url, Relay> {
         self.inner.relays().await
     }
 
+    /// Get the [`RelayScore`] of relays
+    pub async fn scores(&self) -> HashMap<Url, RelayScore> {
+        self.inner.scores().await
+    }
+
     /// Get [`Relay`]
     pub async fn relay<U>(&self, url: U) -> Result<Relay, Error>
     where
@@ -156,6 +214,15 @@ impl RelayPool {
         self.inner.disconnect().await
     }
 
+    /// Handle a network connectivity change signaled by the app
+    ///
+    /// See [`Relay::connectivity_changed`].
+    pub async fn connectivity_changed(&self, online: bool) {
+        for relay in self.relays().await.into_values() {
+            relay.connectivity_changed(online).await;
+        }
+    }
+
     /// Connect to relay
     pub async fn connect_relay<U>(
         &self,
@@ -181,6 +248,13 @@ impl RelayPool {
         self.inner.subscription(id).await
     }
 
+    /// Get the relays targeted by a subscription
+    ///
+    /// Return `None` if the subscription doesn't exist or isn't targeted to specific relays.
+    pub async fn subscription_relays(&self, id: &SubscriptionId) -> Option<HashSet<Url>> {
+        self.inner.subscription_relays(id).await
+    }
+
     /// Send client message to all connected relays
     pub async fn send_msg(&self, msg: ClientMessage, opts: RelaySendOptions) -> Result<(), Error> {
         self.inner.send_msg(msg, opts).await
@@ -230,16 +304,25 @@ impl RelayPool {
     }
 
     /// Send event to **all connected relays** and wait for `OK` message
-    pub async fn send_event(&self, event: Event, opts: RelaySendOptions) -> Result<EventId, Error> {
+    ///
+    /// Return the [`PublishReport`] with the outcome of every relay.
+    /// Rise [`Error::EventNotPublished`] if the event has not been accepted by any relay.
+    pub async fn send_event(
+        &self,
+        event: Event,
+        opts: RelaySendOptions,
+    ) -> Result<PublishReport, Error> {
         self.inner.send_event(event, opts).await
     }
 
     /// Send multiple [`Event`] at once to **all connected relays** and wait for `OK` message
+    ///
+    /// Return the [`PublishReport`] with the outcome of every event for every relay.
     pub async fn batch_event(
         &self,
         events: Vec<Event>,
         opts: RelaySendOptions,
-    ) -> Result<(), Error> {
+    ) -> Result<PublishReport, Error> {
         self.inner.batch_event(events, opts).await
     }
 
@@ -249,7 +332,7 @@ impl RelayPool {
         urls: I,
         event: Event,
         opts: RelaySendOptions,
-    ) -> Result<EventId, Error>
+    ) -> Result<PublishReport, Error>
     where
         I: IntoIterator<Item = U>,
         U: TryIntoUrl,
@@ -264,7 +347,7 @@ impl RelayPool {
         urls: I,
         events: Vec<Event>,
         opts: RelaySendOptions,
-    ) -> Result<(), Error>
+    ) -> Result<PublishReport, Error>
     where
         I: IntoIterator<Item = U>,
         U: TryIntoUrl,
@@ -300,6 +383,98 @@ impl RelayPool {
         self.inner.subscribe_with_id(id, filters, opts).await
     }
 
+    /// Subscribe to filters to **specific relays**
+    ///
+    /// The subscription is sent again to the targeted relays when they reconnect
+    /// and it's inherited only by relays with the same URLs (see [`RelayOptions::inherit_subscriptions`]).
+    ///
+    /// ### Auto-closing subscription
+    ///
+    /// It's possible to automatically close a subscription by configuring the [SubscribeOptions].
+    ///
+    /// Note: auto-closing subscriptions aren't saved in subscriptions map!
+    pub async fn subscribe_to<I, U>(
+        &self,
+        urls: I,
+        filters: Vec<Filter>,
+        opts: SubscribeOptions,
+    ) -> Result<SubscriptionId, Error>
+    where
+        I: IntoIterator<Item = U>,
+        U: TryIntoUrl,
+        Error: From<<U as TryIntoUrl>::Err>,
+    {
+        let id: SubscriptionId = SubscriptionId::generate();
+        self.inner
+            .subscribe_with_id_to(urls, id.clone(), filters, opts)
+            .await?;
+        Ok(id)
+    }
+
+    /// Subscribe to filters with custom [SubscriptionId] to **specific relays**
+    ///
+    /// ### Auto-closing subscription
+    ///
+    /// It's possible to automatically close a subscription by configuring the [SubscribeOptions].
+    ///
+    /// Note: auto-closing subscriptions aren't saved in subscriptions map!
+    pub async fn subscribe_with_id_to<I, U>(
+        &self,
+        urls: I,
+        id: SubscriptionId,
+        filters: Vec<Filter>,
+        opts: SubscribeOptions,
+    ) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = U>,
+        U: TryIntoUrl,
+        Error: From<<U as TryIntoUrl>::Err>,
+    {
+        self.inner
+            .subscribe_with_id_to(urls, id, filters, opts)
+            .await
+    }
+
+    /// Subscribe to filters and get the [`SubscriptionStream`] of the subscription
+    ///
+    /// Events are deduplicated across relays.
+    /// The stream ends when the subscription is closed (unsubscribed, auto-closed or closed by all the relays).
+    /// Without relays, the stream waits for the relays connected later.
+    pub async fn subscribe_stream(
+        &self,
+        filters: Vec<Filter>,
+        opts: SubscribeOptions,
+    ) -> SubscriptionStream {
+        self.inner
+            .subscribe_stream_with_id(SubscriptionId::generate(), filters, opts)
+            .await
+    }
+
+    /// Subscribe to filters with custom [SubscriptionId] and get the [`SubscriptionStream`] of the subscription
+    pub async fn subscribe_stream_with_id(
+        &self,
+        id: SubscriptionId,
+        filters: Vec<Filter>,
+        opts: SubscribeOptions,
+    ) -> SubscriptionStream {
+        self.inner.subscribe_stream_with_id(id, filters, opts).await
+    }
+
+    /// Subscribe to different filters for each relay and get the [`SubscriptionStream`] of the subscription
+    ///
+    /// Targeted subscriptions aren't saved in subscriptions map.
+    /// Relays not in the pool are skipped.
+    pub async fn subscribe_stream_targeted(
+        &self,
+        id: SubscriptionId,
+        targets: HashMap<Url, Vec<Filter>>,
+        opts: SubscribeOptions,
+    ) -> SubscriptionStream {
+        self.inner
+            .subscribe_stream_targeted(id, targets, opts)
+            .await
+    }
+
     /// Unsubscribe from subscription
     pub async fn unsubscribe(&self, id: SubscriptionId, opts: RelaySendOptions) {
         self.inner.unsubscribe(id, opts).await
@@ -319,7 +494,7 @@ impl RelayPool {
         timeout: Duration,
         opts: FilterOptions,
     ) -> Result<Vec<Event>, Error> {
-        let relays = self.relays().await;
+        let relays = self.inner.read_relays().await;
         self.get_events_from(relays.into_keys(), filters, timeout, opts)
             .await
     }
@@ -346,6 +521,69 @@ impl RelayPool {
             .await
     }
 
+    /// Stream events of filters
+    ///
+    /// Stream the events of **local database** first, then the events of **relays**, deduplicated.
+    /// The events of relays are sent as soon as received, without any ordering.
+    ///
+    /// Dropping the stream stops the queries.
+    pub async fn stream_events_of(
+        &self,
+        filters: Vec<Filter>,
+        timeout: Duration,
+        opts: FilterOptions,
+    ) -> Result<EventStream, Error> {
+        let relays = self.inner.read_relays().await;
+        self.stream_events_from(relays.into_keys(), filters, timeout, opts)
+            .await
+    }
+
+    /// Stream events of filters from **specific relays**
+    ///
+    /// If no relay is specified, will be streamed only the events of database.
+    pub async fn stream_events_from<I, U>(
+        &self,
+        urls: I,
+        filters: Vec<Filter>,
+        timeout: Duration,
+        opts: FilterOptions,
+    ) -> Result<EventStream, Error>
+    where
+        I: IntoIterator<Item = U>,
+        U: TryIntoUrl,
+        Error: From<<U as TryIntoUrl>::Err>,
+    {
+        self.inner
+            .stream_events_from(urls, filters, timeout, opts)
+            .await
+    }
+
+    /// Stream events of different filters for each relay
+    pub async fn stream_events_targeted(
+        &self,
+        targets: HashMap<Url, Vec<Filter>>,
+        timeout: Duration,
+        opts: FilterOptions,
+    ) -> Result<EventStream, Error> {
+        self.inner
+            .stream_events_targeted(targets, timeout, opts)
+            .await
+    }
+
+    /// Get all the events of filters from all relays, page by page
+    ///
+    /// Every page move `until` back to the oldest event received, until the relay return nothing new.
+    /// The `limit` of filters is replaced by the page size.
+    ///
+    /// Events are saved into the database.
+    pub async fn paginate(
+        &self,
+        filters: Vec<Filter>,
+        opts: PaginationOptions,
+    ) -> Result<(), Error> {
+        self.inner.paginate(filters, opts).await
+    }
+
     /// Negentropy reconciliation
     pub async fn reconcile(&self, filter: Filter, opts: NegentropyOptions) -> Result<(), Error> {
         self.inner.reconcile(filter, opts).await
@@ -381,3 +619,141 @@ impl RelayPool {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use async_utility::time;
+
+    use super::*;
+    use crate::mock::recv_req;
+    use crate::{DuplexConnection, DuplexTransport, ReconnectPolicy};
+
+    async fn no_req(connection: &mut DuplexConnection) {
+        let res = time::timeout(Some(Duration::from_millis(200)), recv_req(connection)).await;
+        assert!(res.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_to() {
+        let (transport, mut listener) = DuplexTransport::new();
+
+        let pool = RelayPool::default();
+        let relay1 = Url::parse("ws://relay1.local").unwrap();
+        let relay2 = Url::parse("ws://relay2.local").unwrap();
+        let opts = RelayOptions::new()
+            .transport(transport.clone())
+            .reconnect_policy(ReconnectPolicy::new().initial_delay(Duration::from_millis(100)));
+        pool.add_relay(relay1.clone(), opts).await.unwrap();
+        let opts = RelayOptions::new().transport(transport.clone());
+        pool.add_relay(relay2.clone(), opts).await.unwrap();
+        pool.connect(Some(Duration::from_secs(1))).await;
+
+        let mut connections: HashMap<Url, DuplexConnection> = HashMap::new();
+        for _ in 0..2 {
+            let connection = listener.accept().await.unwrap();
+            connections.insert(connection.url().clone(), connection);
+        }
+        let mut conn1 = connections.remove(&relay1).unwrap();
+        let mut conn2 = connections.remove(&relay2).unwrap();
+
+        // Unknown relay
+        let unknown = Url::parse("ws://unknown.local").unwrap();
+        let res = pool
+            .subscribe_to([unknown], vec![Filter::new()], SubscribeOptions::default())
+            .await;
+        assert!(matches!(res, Err(Error::RelayNotFound)));
+
+        // Targeted subscription
+        let targeted = pool
+            .subscribe_to([&relay1], vec![Filter::new()], SubscribeOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(
+            pool.subscription_relays(&targeted).await,
+            Some(HashSet::from([relay1.clone()]))
+        );
+        assert_eq!(recv_req(&mut conn1).await, targeted);
+        no_req(&mut conn2).await;
+
+        let all = pool
+            .subscribe(vec![Filter::new()], SubscribeOptions::default())
+            .await;
+        assert_eq!(pool.subscription_relays(&all).await, None);
+        assert_eq!(recv_req(&mut conn1).await, all);
+        assert_eq!(recv_req(&mut conn2).await, all);
+
+        // Relay added later inherits only the matching subscriptions
+        let relay3 = Url::parse("ws://relay3.local").unwrap();
+        let opts = RelayOptions::new().transport(transport.clone());
+        pool.add_relay(relay3.clone(), opts).await.unwrap();
+        pool.connect_relay(relay3, Some(Duration::from_secs(1)))
+            .await
+            .unwrap();
+        let mut conn3 = listener.accept().await.unwrap();
+        assert_eq!(recv_req(&mut conn3).await, all);
+        no_req(&mut conn3).await;
+
+        // Relay added later without inheritance
+        let relay4 = Url::parse("ws://relay4.local").unwrap();
+        let opts = RelayOptions::new()
+            .transport(transport)
+            .inherit_subscriptions(false);
+        pool.add_relay(relay4.clone(), opts).await.unwrap();
+        pool.connect_relay(relay4, Some(Duration::from_secs(1)))
+            .await
+            .unwrap();
+        let mut conn4 = listener.accept().await.unwrap();
+        no_req(&mut conn4).await;
+
+        // Targeted subscription is sent again at reconnection
+        drop(conn1);
+        let mut conn1 = listener.accept().await.unwrap();
+        assert_eq!(conn1.url(), &relay1);
+        let ids = HashSet::from([recv_req(&mut conn1).await, recv_req(&mut conn1).await]);
+        assert_eq!(ids, HashSet::from([targeted, all]));
+    }
+
+    #[tokio::test]
+    async fn test_scoring() {
+        let pool = RelayPool::new(RelayPoolOptions::new().scoring(true));
+        let urls: Vec<Url> = (0..4)
+            .map(|i| Url::parse(&format!("ws://relay{i}.local")).unwrap())
+            .collect();
+
+        // Relay `i` accepted `i` events out of 20: all under the disable threshold
+        for (i, url) in urls.iter().enumerate() {
+            pool.add_relay(url, RelayOptions::new()).await.unwrap();
+            let stats = pool.relay(url).await.unwrap().stats();
+            for j in 0..20 {
+                stats.new_ok(j < i);
+            }
+            stats.new_req(SubscriptionId::generate());
+        }
+
+        pool.inner.update_relay_states().await;
+
+        // All disabled for writes: use the best ones
+        let relays = pool.inner.write_relays().await;
+        assert_eq!(
+            relays.into_keys().collect::<HashSet<_>>(),
+            urls[1..].iter().cloned().collect()
+        );
+
+        // Reads not affected
+        assert_eq!(pool.inner.read_relays().await.len(), urls.len());
+
+        // New chance: only the write counters are reset
+        for url in urls.iter() {
+            let stats = pool.relay(url).await.unwrap().stats();
+            stats.set_write_disabled_until(Some(Timestamp::now() - Duration::from_secs(1)));
+        }
+        pool.inner.update_relay_states().await;
+        for url in urls.iter() {
+            let stats = pool.relay(url).await.unwrap().stats();
+            assert_eq!(stats.write_disabled_until(), None);
+            assert_eq!(stats.events_rejected(), 0);
+            assert_eq!(stats.reqs(), 1);
+        }
+        assert_eq!(pool.inner.write_relays().await.len(), urls.len());
+    }
+}
@@ -4,16 +4,32 @@
 
 //! Pool options
 
+use std::time::Duration;
+
 /// Relay Pool Options
 #[derive(Debug, Clone, Copy)]
 pub struct RelayPoolOptions {
     pub(super) notification_channel_size: usize,
+    pub(super) outbox: bool,
+    pub(super) outbox_retry_interval: Duration,
+    pub(super) outbox_max_attempts: u32,
+    pub(super) scoring: bool,
+    pub(super) scoring_demote_threshold: f64,
+    pub(super) scoring_disable_threshold: f64,
+    pub(super) scoring_disable_duration: Duration,
 }
 
 impl Default for RelayPoolOptions {
     fn default() -> Self {
         Self {
             notification_channel_size: 4096,
+            outbox: false,
+            outbox_retry_interval: Duration::from_secs(10),
+            outbox_max_attempts: 10,
+            scoring: false,
+            scoring_demote_threshold: 0.5,
+            scoring_disable_threshold: 0.2,
+            scoring_disable_duration: Duration::from_secs(600),
         }
     }
 }
@@ -29,4 +45,62 @@ impl RelayPoolOptions {
         self.notification_channel_size = size;
         self
     }
+
+    /// Outbox (default: false)
+    ///
+    /// Before being sent, the events are added to the outbox of the relays, in the database.
+    /// The events not delivered to a relay are sent again when the relay reconnects and,
+    /// while the relay is connected, with exponential backoff, up to the max attempts.
+    pub fn outbox(mut self, enable: bool) -> Self {
+        self.outbox = enable;
+        self
+    }
+
+    /// Min interval between outbox retries (default: 10 secs)
+    ///
+    /// The interval is doubled at every failed retry, up to 1 hour.
+    pub fn outbox_retry_interval(mut self, interval: Duration) -> Self {
+        self.outbox_retry_interval = interval;
+        self
+    }
+
+    /// Max delivery attempts of an outbox event to a relay (default: 10)
+    ///
+    /// After the max attempts, the event is removed from the outbox of the relay
+    /// and a [`RelayPoolNotification::EventRejected`](crate::RelayPoolNotification::EventRejected) is sent.
+    pub fn outbox_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.outbox_max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Relay selection by score (default: false)
+    ///
+    /// Reads and writes not targeted to specific relays use only the relays with the `READ`/`WRITE` flag
+    /// and skip the disabled ones. The demoted relays are used only if no healthy relay is available
+    /// and the best disabled relays only if all the relays are disabled.
+    /// See [`RelayScore`](crate::relay::RelayScore).
+    pub fn scoring(mut self, enable: bool) -> Self {
+        self.scoring = enable;
+        self
+    }
+
+    /// Score under which a relay is demoted (default: 0.5)
+    pub fn scoring_demote_threshold(mut self, threshold: f64) -> Self {
+        self.scoring_demote_threshold = threshold;
+        self
+    }
+
+    /// Score under which a relay is temporarily disabled (default: 0.2)
+    pub fn scoring_disable_threshold(mut self, threshold: f64) -> Self {
+        self.scoring_disable_threshold = threshold;
+        self
+    }
+
+    /// How long a relay stay disabled (default: 10 min)
+    ///
+    /// Then the score counters of the disabled service (reads or writes) are reset, to give the relay a new chance.
+    pub fn scoring_disable_duration(mut self, duration: Duration) -> Self {
+        self.scoring_disable_duration = duration;
+        self
+    }
 }
@@ -0,0 +1,246 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Outbox
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use nostr::message::relay::MachineReadablePrefix;
+use nostr::{EventId, Timestamp};
+
+use crate::relay::{Error as RelayError, PublishOutcome};
+
+/// Max interval between retries
+const MAX_RETRY_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Delivery of an event to a relay
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum Delivery {
+    /// Accepted by the relay
+    Delivered,
+    /// Rejected by the relay, don't retry
+    Rejected(String),
+    /// Not delivered, retry later (ex. relay disconnected, timeout, rate limited)
+    Retry,
+}
+
+impl Delivery {
+    fn from_outcome(outcome: &PublishOutcome) -> Self {
+        match outcome {
+            PublishOutcome::Accepted { .. } => Self::Delivered,
+            PublishOutcome::Rejected { reason, message } => match reason {
+                Some(MachineReadablePrefix::Duplicate) => Self::Delivered,
+                // Temporary
+                Some(
+                    MachineReadablePrefix::RateLimited
+                    | MachineReadablePrefix::Error
+                    | MachineReadablePrefix::AuthRequired,
+                ) => Self::Retry,
+                // Rejected for good (also without or with unknown prefix)
+                _ => Self::Rejected(message.clone()),
+            },
+            PublishOutcome::Timeout | PublishOutcome::Failed(..) => Self::Retry,
+        }
+    }
+
+    fn from_error(e: &RelayError) -> Self {
+        match e {
+            RelayError::WriteDisabled
+            | RelayError::Event(..)
+            | RelayError::EventExpired
+            | RelayError::EventTooLarge { .. }
+            | RelayError::TooManyTags { .. }
+            | RelayError::MessageTooLarge { .. }
+            | RelayError::PowDifficultyTooLow { .. }
+            | RelayError::PaymentRequired => Self::Rejected(e.to_string()),
+            _ => Self::Retry,
+        }
+    }
+}
+
+/// Get the delivery of each event from the result of
+/// [`Relay::publish`](crate::relay::Relay::publish)
+pub(super) fn deliveries(
+    ids: &[EventId],
+    res: &Result<HashMap<EventId, PublishOutcome>, RelayError>,
+) -> Vec<(EventId, Delivery)> {
+    ids.iter()
+        .map(|id| {
+            let delivery: Delivery = match res {
+                Ok(outcomes) => outcomes
+                    .get(id)
+                    .map_or(Delivery::Retry, Delivery::from_outcome),
+                Err(e) => Delivery::from_error(e),
+            };
+            (*id, delivery)
+        })
+        .collect()
+}
+
+/// Outbox retries of a relay
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Backoff {
+    attempts: u32,
+    next: Timestamp,
+}
+
+impl Backoff {
+    /// Schedule next retry after a failed attempt
+    pub fn failed(attempts: u32, min_interval: Duration, now: Timestamp) -> Self {
+        let interval: Duration = min_interval
+            .checked_mul(2u32.saturating_pow(attempts))
+            .map_or(MAX_RETRY_INTERVAL, |i| i.min(MAX_RETRY_INTERVAL));
+        Self {
+            attempts: attempts.saturating_add(1),
+            next: now + interval,
+        }
+    }
+
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    pub fn is_due(&self, now: Timestamp) -> bool {
+        now >= self.next
+    }
+}
+
+/// Failed delivery attempts of the outbox events of a relay
+#[derive(Debug, Clone, Default)]
+pub(super) struct Attempts {
+    events: HashMap<EventId, u32>,
+}
+
+impl Attempts {
+    /// Count a failed attempt
+    ///
+    /// Return `false` if the event reached the max attempts and must not be retried.
+    pub fn failed(&mut self, id: EventId, max_attempts: u32) -> bool {
+        let attempts: &mut u32 = self.events.entry(id).or_default();
+        *attempts = attempts.saturating_add(1);
+        if *attempts >= max_attempts {
+            self.events.remove(&id);
+            false
+        } else {
+            true
+        }
+    }
+
+    pub fn remove(&mut self, id: &EventId) {
+        self.events.remove(id);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deliveries() {
+        let a = EventId::all_zeros();
+        let b = EventId::from_slice(&[1u8; 32]).unwrap();
+        let c = EventId::from_slice(&[2u8; 32]).unwrap();
+        let ids = [a, b, c];
+
+        let res = Ok(HashMap::from([
+            (a, PublishOutcome::from_ok(true, "")),
+            (b, PublishOutcome::from_ok(false, "blocked: you are banned")),
+            (c, PublishOutcome::from_ok(false, "rate-limited: slow down")),
+        ]));
+        assert_eq!(
+            deliveries(&ids, &res),
+            vec![
+                (a, Delivery::Delivered),
+                (
+                    b,
+                    Delivery::Rejected(String::from("blocked: you are banned"))
+                ),
+                (c, Delivery::Retry),
+            ]
+        );
+
+        let res = Ok(HashMap::from([(
+            a,
+            PublishOutcome::from_ok(false, "duplicate: already have this event"),
+        )]));
+        assert_eq!(deliveries(&[a], &res), vec![(a, Delivery::Delivered)]);
+
+        // Timeout
+        let res = Ok(HashMap::from([(a, PublishOutcome::Timeout)]));
+        assert_eq!(deliveries(&[a], &res), vec![(a, Delivery::Retry)]);
+
+        let res = Err(RelayError::NotConnected);
+        assert_eq!(deliveries(&[a], &res), vec![(a, Delivery::Retry)]);
+
+        // Without or with unknown prefix
+        let res = Ok(HashMap::from([
+            (a, PublishOutcome::from_ok(false, "not allowed")),
+            (b, PublishOutcome::from_ok(false, "unknown: not allowed")),
+        ]));
+        assert_eq!(
+            deliveries(&[a, b], &res),
+            vec![
+                (a, Delivery::Rejected(String::from("not allowed"))),
+                (b, Delivery::Rejected(String::from("unknown: not allowed"))),
+            ]
+        );
+
+        // Permanent errors
+        let res = Err(RelayError::PaymentRequired);
+        assert_eq!(
+            deliveries(&[a], &res),
+            vec![(
+                a,
+                Delivery::Rejected(String::from("relay requires payment"))
+            )]
+        );
+        let res = Err(RelayError::MessageTooLarge {
+            size: 10,
+            max_size: 5,
+        });
+        assert!(matches!(
+            deliveries(&[a], &res)[0].1,
+            Delivery::Rejected(..)
+        ));
+    }
+
+    #[test]
+    fn test_attempts() {
+        let a = EventId::all_zeros();
+        let b = EventId::from_slice(&[1u8; 32]).unwrap();
+
+        let mut attempts = Attempts::default();
+        assert!(attempts.failed(a, 3));
+        assert!(attempts.failed(b, 3));
+        assert!(attempts.failed(a, 3));
+        assert!(!attempts.failed(a, 3));
+
+        attempts.remove(&b);
+        assert!(attempts.is_empty());
+    }
+
+    #[test]
+    fn test_backoff() {
+        let now = Timestamp::from(1_700_000_000);
+        let interval = Duration::from_secs(10);
+
+        let backoff = Backoff::failed(0, interval, now);
+        assert_eq!(backoff.attempts(), 1);
+        assert!(!backoff.is_due(now + Duration::from_secs(9)));
+        assert!(backoff.is_due(now + Duration::from_secs(10)));
+
+        let backoff = Backoff::failed(backoff.attempts(), interval, now);
+        assert!(!backoff.is_due(now + Duration::from_secs(19)));
+        assert!(backoff.is_due(now + Duration::from_secs(20)));
+
+        // Capped
+        let backoff = Backoff::failed(40, interval, now);
+        assert!(backoff.is_due(now + MAX_RETRY_INTERVAL));
+    }
+}
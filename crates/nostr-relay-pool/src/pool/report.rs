@@ -0,0 +1,163 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Publish report
+
+use std::collections::HashMap;
+
+use nostr::{EventId, Url};
+
+use crate::relay::{Error as RelayError, PublishOutcome};
+
+/// Per-relay outcome of publishing events
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublishReport {
+    /// Never empty
+    ids: Vec<EventId>,
+    relays: HashMap<Url, HashMap<EventId, PublishOutcome>>,
+}
+
+impl PublishReport {
+    pub(crate) fn new(ids: Vec<EventId>) -> Self {
+        Self {
+            ids,
+            relays: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn insert(&mut self, url: Url, outcomes: HashMap<EventId, PublishOutcome>) {
+        self.relays.insert(url, outcomes);
+    }
+
+    /// Event ID (the first one, if many events have been sent)
+    pub fn id(&self) -> EventId {
+        self.ids[0]
+    }
+
+    /// Event IDs
+    pub fn ids(&self) -> &[EventId] {
+        &self.ids
+    }
+
+    /// Outcomes of every relay
+    pub fn relays(&self) -> &HashMap<Url, HashMap<EventId, PublishOutcome>> {
+        &self.relays
+    }
+
+    /// Outcome of the event for the relay
+    pub fn outcome(&self, url: &Url, id: &EventId) -> Option<&PublishOutcome> {
+        self.relays.get(url)?.get(id)
+    }
+
+    /// Relays that accepted the event
+    pub fn accepted(&self, id: &EventId) -> Vec<&Url> {
+        self.relays
+            .iter()
+            .filter(|(_, outcomes)| outcomes.get(id).map_or(false, |o| o.is_accepted()))
+            .map(|(url, _)| url)
+            .collect()
+    }
+
+    /// Relays that not accepted the event (rejected, timeout or failed), with the outcome
+    pub fn not_accepted(&self, id: &EventId) -> HashMap<&Url, &PublishOutcome> {
+        self.relays
+            .iter()
+            .filter_map(|(url, outcomes)| {
+                let outcome: &PublishOutcome = outcomes.get(id)?;
+                if outcome.is_accepted() {
+                    None
+                } else {
+                    Some((url, outcome))
+                }
+            })
+            .collect()
+    }
+
+    /// Check if at least one relay accepted at least one event
+    pub fn is_published(&self) -> bool {
+        self.relays
+            .values()
+            .any(|outcomes| outcomes.values().any(|o| o.is_accepted()))
+    }
+
+    /// Check if every event has been accepted by at least one relay
+    pub fn is_success(&self) -> bool {
+        self.ids.iter().all(|id| !self.accepted(id).is_empty())
+    }
+}
+
+/// Outcomes of a relay (every event failed if not sent)
+pub(super) fn outcomes(
+    ids: &[EventId],
+    res: Result<HashMap<EventId, PublishOutcome>, RelayError>,
+) -> HashMap<EventId, PublishOutcome> {
+    match res {
+        Ok(outcomes) => outcomes,
+        Err(e) => ids
+            .iter()
+            .map(|id| (*id, PublishOutcome::Failed(e.to_string())))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::message::relay::MachineReadablePrefix;
+    use nostr::{ClientMessage, EventBuilder, Keys, RelayMessage};
+
+    use super::*;
+    use crate::pool::Error;
+    use crate::{mock, RelaySendOptions};
+
+    #[tokio::test]
+    async fn test_publish_report() {
+        // Relay 1 accept the events, relay 2 reject them
+        let transport = mock::spawn(|url, msg| match msg {
+            ClientMessage::Event(event) => {
+                if url.as_str().contains("relay1") {
+                    vec![RelayMessage::ok(event.id(), true, "")]
+                } else {
+                    vec![RelayMessage::ok(
+                        event.id(),
+                        false,
+                        "blocked: you are banned",
+                    )]
+                }
+            }
+            _ => Vec::new(),
+        });
+        let pool = mock::pool(transport, ["ws://relay1.local", "ws://relay2.local"]).await;
+        let relay1 = Url::parse("ws://relay1.local").unwrap();
+        let relay2 = Url::parse("ws://relay2.local").unwrap();
+
+        let keys = Keys::generate();
+        let event = EventBuilder::text_note("Hello", [])
+            .to_event(&keys)
+            .unwrap();
+        let report = pool
+            .send_event(event.clone(), RelaySendOptions::new())
+            .await
+            .unwrap();
+
+        assert_eq!(report.id(), event.id());
+        assert!(report.is_success());
+        assert_eq!(report.accepted(&event.id()), vec![&relay1]);
+
+        let outcome = report.outcome(&relay2, &event.id()).unwrap();
+        assert!(!outcome.is_accepted());
+        assert_eq!(outcome.reason(), Some(&MachineReadablePrefix::Blocked));
+        assert_eq!(report.not_accepted(&event.id()).len(), 1);
+
+        // Not accepted by any relay
+        pool.remove_relay(relay1).await.unwrap();
+        let event = EventBuilder::text_note("Hi", []).to_event(&keys).unwrap();
+        match pool.send_event(event, RelaySendOptions::new()).await {
+            Err(Error::EventNotPublished(report)) => {
+                assert!(!report.is_published());
+                assert_eq!(report.relays().len(), 1);
+            }
+            res => panic!("Unexpected result: {res:?}"),
+        }
+    }
+}
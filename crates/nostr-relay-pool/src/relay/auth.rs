@@ -0,0 +1,75 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Relay authentication (NIP-42)
+//!
+//! <https://github.com/nostr-protocol/nips/blob/master/42.md>
+
+use std::sync::{Arc, RwLock};
+
+use async_trait::async_trait;
+use nostr::{Event, Url};
+use nostr_database::AsyncTraitDeps;
+use thiserror::Error;
+
+/// Prefix used by relays to reject messages that require authentication
+const AUTH_REQUIRED_PREFIX: &str = "auth-required:";
+
+/// Authentication error
+#[derive(Debug, Error)]
+pub enum AuthenticationError {
+    /// Signer error
+    #[error(transparent)]
+    Signer(Box<dyn std::error::Error + Send + Sync>),
+    /// Authenticator not configured
+    #[error("authenticator not configured")]
+    AuthenticatorNotConfigured,
+    /// Authentication not allowed by policy
+    #[error("authentication not allowed")]
+    NotAllowed,
+    /// Authentication rejected by relay
+    #[error("authentication rejected: {0}")]
+    Rejected(String),
+}
+
+impl AuthenticationError {
+    /// Create a new signer error
+    ///
+    /// Shorthand for `Error::Signer(Box::new(error))`.
+    #[inline]
+    pub fn signer<E>(error: E) -> Self
+    where
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        Self::Signer(Box::new(error))
+    }
+}
+
+/// Relay authenticator
+///
+/// Used by relays with automatic authentication enabled, to answer `AUTH` challenges.
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait RelayAuthenticator: AsyncTraitDeps {
+    /// Check if the authentication with the relay is allowed (default: `true`)
+    async fn allow(&self, relay_url: &Url) -> bool {
+        let _ = relay_url;
+        true
+    }
+
+    /// Build and sign the `kind:22242` authentication event
+    async fn authenticate(
+        &self,
+        challenge: String,
+        relay_url: Url,
+    ) -> Result<Event, AuthenticationError>;
+}
+
+/// Authenticator shared between the pool and its relays
+pub(crate) type SharedAuthenticator = Arc<RwLock<Option<Arc<dyn RelayAuthenticator>>>>;
+
+/// Check if the `CLOSED` or `OK` message has been rejected because authentication is required
+pub(crate) fn is_auth_required(message: &str) -> bool {
+    message.starts_with(AUTH_REQUIRED_PREFIX)
+}
@@ -8,9 +8,11 @@ use std::cmp;
 use std::collections::{BTreeSet, HashMap, HashSet};
 #[cfg(not(target_arch = "wasm32"))]
 use std::net::SocketAddr;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
 
 #[cfg(not(target_arch = "wasm32"))]
 use async_utility::futures_util::stream::AbortHandle;
@@ -23,7 +25,7 @@ use nostr::message::MessageHandleError;
 use nostr::negentropy::{self, Bytes, Negentropy};
 use nostr::nips::nip01::Coordinate;
 #[cfg(feature = "nip11")]
-use nostr::nips::nip11::RelayInformationDocument;
+use nostr::nips::nip11::{Limitation, RelayInformationDocument};
 use nostr::secp256k1::rand::{self, Rng};
 use nostr::{
     event, ClientMessage, Event, EventId, Filter, JsonUtil, Keys, MissingPartialEvent,
@@ -31,23 +33,37 @@ use nostr::{
 };
 use nostr_database::{DatabaseError, DynNostrDatabase, Order};
 use thiserror::Error;
-use tokio::sync::mpsc::{self, Receiver, Sender};
-use tokio::sync::{broadcast, oneshot, Mutex, RwLock};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::oneshot::error::TryRecvError;
+use tokio::sync::{broadcast, oneshot, Mutex, Notify, RwLock};
 
+use super::auth::{self, AuthenticationError, RelayAuthenticator, SharedAuthenticator};
 use super::flags::AtomicRelayServiceFlags;
+#[cfg(feature = "nip11")]
+use super::limitation::{self, RequestShaper};
 use super::options::{
-    FilterOptions, NegentropyOptions, RelayOptions, RelaySendOptions, SubscribeAutoCloseOptions,
-    SubscribeOptions, MAX_ADJ_RETRY_SEC, MIN_RETRY_SEC, NEGENTROPY_BATCH_SIZE_DOWN,
-    NEGENTROPY_HIGH_WATER_UP, NEGENTROPY_LOW_WATER_UP,
+    FilterOptions, NegentropyOptions, PaginationOptions, ReconnectPolicy, RelayOptions,
+    RelaySendOptions, SubscribeAutoCloseOptions, SubscribeOptions, DEFAULT_SEND_TIMEOUT,
+    MAX_ADJ_RETRY_SEC, MIN_RETRY_SEC, NEGENTROPY_BATCH_SIZE_DOWN, NEGENTROPY_HIGH_WATER_UP,
+    NEGENTROPY_LOW_WATER_UP,
 };
+use super::publish::PublishOutcome;
+use super::queue::{Priority, SendQueue};
+#[cfg(not(target_arch = "wasm32"))]
+use super::ratelimit::{self, RateLimiter};
 use super::stats::RelayConnectionStats;
+use super::stream::{SubscriptionItem, SubscriptionSink};
+use super::transport::{DefaultWebSocketTransport, WebSocketTransport};
 use super::{RelayNotification, RelayStatus};
 use crate::pool::RelayPoolNotification;
 
-type Message = (RelayEvent, Option<oneshot::Sender<bool>>);
+/// Queued relay event, with the acknowledgement of each message of the batch (if requested)
+type Message = (RelayEvent, Vec<oneshot::Sender<bool>>);
 
 const MIN_ATTEMPTS: usize = 1;
 const MIN_UPTIME: f64 = 0.90;
+/// Min duration (in secs) of a connection to reset the reconnection backoff
+const MIN_STABLE_CONNECTION_SECS: u64 = 30;
 #[cfg(not(target_arch = "wasm32"))]
 const PING_INTERVAL: u64 = 55;
 
@@ -72,6 +88,9 @@ pub enum Error {
     /// Thread error
     #[error(transparent)]
     Thread(#[from] thread::Error),
+    /// Authentication error
+    #[error(transparent)]
+    Authentication(#[from] AuthenticationError),
     /// Message response timeout
     #[error("recv message response timeout")]
     RecvTimeout,
@@ -116,6 +135,9 @@ pub enum Error {
     /// Filters empty
     #[error("filters empty")]
     FiltersEmpty,
+    /// Subscription stream not consumed fast enough
+    #[error("subscription stream lagged: {0} items dropped")]
+    Lagged(u64),
     /// Reconciliation error
     #[error("negentropy reconciliation error: {0}")]
     NegentropyReconciliation(NegentropyErrorCode),
@@ -158,6 +180,20 @@ pub enum Error {
         /// Min. difficulty
         min: u8,
     },
+    /// Message too large for relay (NIP-11)
+    #[error("Message too large for relay: size={size}, max_size={max_size}")]
+    MessageTooLarge {
+        /// Message size
+        size: usize,
+        /// Max message size
+        max_size: usize,
+    },
+    /// Relay requires authentication (NIP-11)
+    #[error("relay requires authentication")]
+    AuthenticationRequired,
+    /// Relay requires payment (NIP-11)
+    #[error("relay requires payment")]
+    PaymentRequired,
 }
 
 /// Relay event
@@ -185,16 +221,34 @@ pub(crate) struct InternalRelay {
     status: Arc<RwLock<RelayStatus>>,
     #[cfg(feature = "nip11")]
     document: Arc<RwLock<RelayInformationDocument>>,
+    #[cfg(feature = "nip11")]
+    shaper: Arc<Mutex<RequestShaper>>,
     opts: RelayOptions,
     stats: RelayConnectionStats,
     database: Arc<DynNostrDatabase>,
     scheduled_for_stop: Arc<AtomicBool>,
     scheduled_for_termination: Arc<AtomicBool>,
-    relay_sender: Sender<Message>,
-    relay_receiver: Arc<Mutex<Receiver<Message>>>,
+    /// Consecutive failed connection attempts
+    failures: Arc<AtomicUsize>,
+    /// Network connectivity signaled by the host app
+    online: Arc<AtomicBool>,
+    /// Wake up the auto connect loop
+    wake: Arc<Notify>,
+    send_queue: Arc<SendQueue<Message>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    rate_limiter: Arc<Mutex<RateLimiter>>,
     pub(super) internal_notification_sender: broadcast::Sender<RelayNotification>,
     external_notification_sender: Arc<RwLock<Option<broadcast::Sender<RelayPoolNotification>>>>,
     subscriptions: Arc<RwLock<HashMap<SubscriptionId, Vec<Filter>>>>,
+    streams: Arc<RwLock<HashMap<SubscriptionId, Arc<SubscriptionSink>>>>,
+    authenticator: SharedAuthenticator,
+    authenticated: Arc<AtomicBool>,
+    /// Last authentication attempt failed or not allowed
+    authentication_failed: Arc<AtomicBool>,
+    /// Subscriptions closed by relay because authentication is required
+    auth_pending_subscriptions: Arc<Mutex<HashSet<SubscriptionId>>>,
+    /// Auto-closing subscriptions not yet closed
+    auto_closing_subscriptions: Arc<Mutex<HashSet<SubscriptionId>>>,
 }
 
 impl AtomicDestroyer for InternalRelay {
@@ -213,25 +267,43 @@ impl AtomicDestroyer for InternalRelay {
 }
 
 impl InternalRelay {
-    pub fn new(url: Url, database: Arc<DynNostrDatabase>, opts: RelayOptions) -> Self {
-        let (relay_sender, relay_receiver) = mpsc::channel::<Message>(1024);
+    pub fn new(
+        url: Url,
+        database: Arc<DynNostrDatabase>,
+        opts: RelayOptions,
+        authenticator: SharedAuthenticator,
+    ) -> Self {
         let (relay_notification_sender, ..) = broadcast::channel::<RelayNotification>(2048);
+        #[cfg(not(target_arch = "wasm32"))]
+        let rate_limiter = RateLimiter::new(opts.req_rate_limit, opts.event_rate_limit);
 
         Self {
             url,
             status: Arc::new(RwLock::new(RelayStatus::Initialized)),
             #[cfg(feature = "nip11")]
             document: Arc::new(RwLock::new(RelayInformationDocument::new())),
+            #[cfg(feature = "nip11")]
+            shaper: Arc::new(Mutex::new(RequestShaper::default())),
             opts,
             stats: RelayConnectionStats::new(),
             database,
             scheduled_for_stop: Arc::new(AtomicBool::new(false)),
             scheduled_for_termination: Arc::new(AtomicBool::new(false)),
-            relay_sender,
-            relay_receiver: Arc::new(Mutex::new(relay_receiver)),
+            failures: Arc::new(AtomicUsize::new(0)),
+            online: Arc::new(AtomicBool::new(true)),
+            wake: Arc::new(Notify::new()),
+            send_queue: Arc::new(SendQueue::new(1024)),
+            #[cfg(not(target_arch = "wasm32"))]
+            rate_limiter: Arc::new(Mutex::new(rate_limiter)),
             internal_notification_sender: relay_notification_sender,
             external_notification_sender: Arc::new(RwLock::new(None)),
             subscriptions: Arc::new(RwLock::new(HashMap::new())),
+            streams: Arc::new(RwLock::new(HashMap::new())),
+            authenticator,
+            authenticated: Arc::new(AtomicBool::new(false)),
+            authentication_failed: Arc::new(AtomicBool::new(false)),
+            auth_pending_subscriptions: Arc::new(Mutex::new(HashSet::new())),
+            auto_closing_subscriptions: Arc::new(Mutex::new(HashSet::new())),
         }
     }
 
@@ -244,6 +316,19 @@ impl InternalRelay {
         self.opts.proxy
     }
 
+    fn transport(&self) -> Arc<dyn WebSocketTransport> {
+        match &self.opts.transport {
+            Some(transport) => transport.clone(),
+            None => {
+                #[cfg(not(target_arch = "wasm32"))]
+                let transport = DefaultWebSocketTransport::new().proxy(self.proxy());
+                #[cfg(target_arch = "wasm32")]
+                let transport = DefaultWebSocketTransport::new();
+                Arc::new(transport)
+            }
+        }
+    }
+
     pub async fn status(&self) -> RelayStatus {
         let status = self.status.read().await;
         *status
@@ -279,6 +364,12 @@ impl InternalRelay {
         *d = document;
     }
 
+    #[cfg(feature = "nip11")]
+    async fn limitation(&self) -> Limitation {
+        let document = self.document.read().await;
+        document.limitation.clone().unwrap_or_default()
+    }
+
     pub async fn subscriptions(&self) -> HashMap<SubscriptionId, Vec<Filter>> {
         let subscription = self.subscriptions.read().await;
         subscription.clone()
@@ -302,6 +393,21 @@ impl InternalRelay {
         subscriptions.remove(id);
     }
 
+    async fn stream(&self, id: &SubscriptionId) -> Option<Arc<SubscriptionSink>> {
+        let streams = self.streams.read().await;
+        streams.get(id).filter(|sink| !sink.is_closed()).cloned()
+    }
+
+    pub(crate) async fn add_stream(&self, id: SubscriptionId, sink: Arc<SubscriptionSink>) {
+        let mut streams = self.streams.write().await;
+        streams.insert(id, sink);
+    }
+
+    pub(crate) async fn remove_stream(&self, id: &SubscriptionId) {
+        let mut streams = self.streams.write().await;
+        streams.remove(id);
+    }
+
     pub fn opts(&self) -> RelayOptions {
         self.opts.clone()
     }
@@ -310,8 +416,57 @@ impl InternalRelay {
         self.stats.clone()
     }
 
+    pub fn is_authenticated(&self) -> bool {
+        self.authenticated.load(Ordering::SeqCst)
+    }
+
+    /// Check if messages rejected with `auth-required` must be sent again after authentication
+    ///
+    /// If already authenticated, the rejection is final.
+    fn is_auth_retry_enabled(&self) -> bool {
+        self.opts.get_automatic_authentication()
+            && !self.is_authenticated()
+            && !self.authentication_failed.load(Ordering::SeqCst)
+            && self.authenticator().is_some()
+    }
+
+    fn authenticator(&self) -> Option<Arc<dyn RelayAuthenticator>> {
+        match self.authenticator.read() {
+            Ok(authenticator) => authenticator.clone(),
+            Err(e) => e.into_inner().clone(),
+        }
+    }
+
+    pub fn set_authenticator(&self, authenticator: Option<Arc<dyn RelayAuthenticator>>) {
+        match self.authenticator.write() {
+            Ok(mut a) => *a = authenticator,
+            Err(e) => *e.into_inner() = authenticator,
+        }
+    }
+
     pub fn queue(&self) -> usize {
-        self.relay_sender.max_capacity() - self.relay_sender.capacity()
+        self.send_queue.len()
+    }
+
+    fn is_online(&self) -> bool {
+        self.online.load(Ordering::SeqCst)
+    }
+
+    /// Handle a connectivity change signaled by the host app
+    ///
+    /// When offline, the connection attempts are paused.
+    /// When back online, the backoff is reset, the auto connect loop is woken up and the dead relay is revived.
+    pub async fn connectivity_changed(&self, online: bool) {
+        self.online.store(online, Ordering::SeqCst);
+
+        if online {
+            self.failures.store(0, Ordering::SeqCst);
+            if self.status().await == RelayStatus::Dead {
+                self.connect(None).await;
+            } else {
+                self.wake.notify_one();
+            }
+        }
     }
 
     fn is_scheduled_for_stop(&self) -> bool {
@@ -363,6 +518,15 @@ impl InternalRelay {
                     relay_url: self.url(),
                     status,
                 },
+                RelayNotification::Authenticated => RelayPoolNotification::Authenticated {
+                    relay_url: self.url(),
+                },
+                RelayNotification::AuthenticationFailed { message } => {
+                    RelayPoolNotification::AuthenticationFailed {
+                        relay_url: self.url(),
+                        message,
+                    }
+                }
                 RelayNotification::Shutdown => RelayPoolNotification::Shutdown,
                 RelayNotification::Stop => RelayPoolNotification::Stop,
             };
@@ -376,9 +540,13 @@ impl InternalRelay {
         self.schedule_for_stop(false);
         self.schedule_for_termination(false);
 
-        if let RelayStatus::Initialized | RelayStatus::Stopped | RelayStatus::Terminated =
-            self.status().await
+        if let RelayStatus::Initialized
+        | RelayStatus::Stopped
+        | RelayStatus::Terminated
+        | RelayStatus::Dead = self.status().await
         {
+            self.failures.store(0, Ordering::SeqCst);
+
             if self.opts.get_reconnect() {
                 if connection_timeout.is_some() {
                     self.try_connect(connection_timeout).await
@@ -399,7 +567,7 @@ impl InternalRelay {
                                 "{} messages queued for {} (capacity: {})",
                                 queue,
                                 relay.url(),
-                                relay.relay_sender.capacity()
+                                relay.send_queue.capacity() - queue
                             );
                         }
 
@@ -423,20 +591,62 @@ impl InternalRelay {
                             break;
                         }
 
+                        // Give up
+                        if let Some(ReconnectPolicy {
+                            max_attempts: Some(max_attempts),
+                            ..
+                        }) = relay.opts.reconnect_policy
+                        {
+                            let failures: usize = relay.failures.load(Ordering::SeqCst);
+                            if failures >= max_attempts
+                                && relay.status().await == RelayStatus::Disconnected
+                            {
+                                relay.set_status(RelayStatus::Dead).await;
+                                tracing::warn!(
+                                    "Giving up connecting to {} after {failures} attempts",
+                                    relay.url
+                                );
+                                break;
+                            }
+                        }
+
                         // Check status
                         match relay.status().await {
                             RelayStatus::Initialized
                             | RelayStatus::Pending
                             | RelayStatus::Disconnected => {
-                                relay.try_connect(connection_timeout).await
+                                // Wait for connectivity
+                                if relay.is_online() {
+                                    relay.try_connect(connection_timeout).await
+                                }
                             }
-                            RelayStatus::Stopped | RelayStatus::Terminated => {
+                            RelayStatus::Stopped | RelayStatus::Terminated | RelayStatus::Dead => {
                                 tracing::debug!("Auto connect loop terminated for {}", relay.url);
                                 break;
                             }
                             _ => (),
                         };
 
+                        if let Some(policy) = relay.opts.reconnect_policy {
+                            let failures: usize = relay.failures.load(Ordering::SeqCst);
+                            let delay: Duration = policy.delay(failures.saturating_sub(1));
+                            let delay: Duration =
+                                policy.apply_jitter(delay, &mut rand::thread_rng());
+                            tracing::trace!("{} retry time set to {delay:?}", relay.url);
+
+                            // Sleep or wake up on connectivity change
+                            if time::timeout(Some(delay), relay.wake.notified())
+                                .await
+                                .is_some()
+                            {
+                                // Spread the reconnections
+                                let delay: Duration = policy.wake_up_delay(&mut rand::thread_rng());
+                                thread::sleep(delay).await;
+                            }
+
+                            continue;
+                        }
+
                         let retry_sec: u64 = if relay.opts.get_adjust_retry_sec() {
                             let var: u64 =
                                 relay.stats.attempts().saturating_sub(relay.stats.success()) as u64;
@@ -453,7 +663,10 @@ impl InternalRelay {
                         };
 
                         tracing::trace!("{} retry time set to {retry_sec} secs", relay.url);
-                        thread::sleep(Duration::from_secs(retry_sec)).await;
+
+                        // Sleep or wake up on connectivity change
+                        time::timeout(Some(Duration::from_secs(retry_sec)), relay.wake.notified())
+                            .await;
                     }
                 });
             } else if connection_timeout.is_some() {
@@ -501,14 +714,20 @@ impl InternalRelay {
             // First attempt, use external timeout
             connection_timeout
         };
-        #[cfg(not(target_arch = "wasm32"))]
-        let connection = async_wsocket::native::connect(&self.url, self.proxy(), timeout).await;
-        #[cfg(target_arch = "wasm32")]
-        let connection = async_wsocket::wasm::connect(&self.url, timeout).await;
+        let transport: Arc<dyn WebSocketTransport> = self.transport();
 
         // Connect
-        match connection {
+        match transport.connect(&self.url, timeout).await {
             Ok((mut ws_tx, mut ws_rx)) => {
+                // Authentication is bound to the connection
+                self.authenticated.store(false, Ordering::SeqCst);
+                self.authentication_failed.store(false, Ordering::SeqCst);
+                self.auth_pending_subscriptions.lock().await.clear();
+
+                // Subscriptions are bound to the connection
+                #[cfg(feature = "nip11")]
+                self.shaper.lock().await.reset();
+
                 self.set_status(RelayStatus::Connected).await;
                 tracing::info!("Connected to {}", url);
 
@@ -517,8 +736,9 @@ impl InternalRelay {
                 #[cfg(not(target_arch = "wasm32"))]
                 let ping_abort_handle: Option<AbortHandle> = {
                     let relay = self.clone();
+                    let support_ping: bool = transport.support_ping();
                     thread::abortable(async move {
-                        if relay.opts.flags.has_ping() {
+                        if relay.opts.flags.has_ping() && support_ping {
                             tracing::debug!("Relay Ping Thread Started");
 
                             loop {
@@ -533,9 +753,7 @@ impl InternalRelay {
                                 relay.stats.ping.set_last_nonce(nonce);
                                 relay.stats.ping.set_replied(false);
 
-                                if let Err(e) =
-                                    relay.send_relay_event(RelayEvent::Ping { nonce }, None)
-                                {
+                                if let Err(e) = relay.send_relay_event(RelayEvent::Ping { nonce }) {
                                     tracing::error!("Impossible to ping {}: {e}", relay.url);
                                     break;
                                 };
@@ -556,10 +774,74 @@ impl InternalRelay {
                 let relay = self.clone();
                 let _ = thread::spawn(async move {
                     tracing::debug!("Relay Event Thread Started");
-                    let mut rx = relay.relay_receiver.lock().await;
-                    while let Some((relay_event, oneshot_sender)) = rx.recv().await {
+                    let _consumer = relay.send_queue.consumer().await;
+                    loop {
+                        // Take the next message of a priority with available tokens (rate limit)
+                        #[cfg(not(target_arch = "wasm32"))]
+                        let (priority, (relay_event, mut acks)) = {
+                            let popped = {
+                                let mut limiter = relay.rate_limiter.lock().await;
+                                let now = Instant::now();
+                                relay
+                                    .send_queue
+                                    .pop_available(|priority| limiter.available(priority, now))
+                            };
+                            match popped {
+                                Ok(item) => item,
+                                Err(wait) => {
+                                    // Wait for tokens or for a new message
+                                    match wait {
+                                        Some(wait) => {
+                                            time::timeout(Some(wait), relay.send_queue.notified())
+                                                .await;
+                                        }
+                                        None => relay.send_queue.notified().await,
+                                    }
+                                    continue;
+                                }
+                            }
+                        };
+                        #[cfg(target_arch = "wasm32")]
+                        let (_, (relay_event, acks)) = relay.send_queue.recv().await;
+
                         match relay_event {
-                            RelayEvent::Batch(msgs) => {
+                            #[cfg_attr(target_arch = "wasm32", allow(unused_mut))]
+                            RelayEvent::Batch(mut msgs) => {
+                                // Rate limit: send only the messages allowed now and queue the others
+                                #[cfg(not(target_arch = "wasm32"))]
+                                {
+                                    let taken: Result<usize, Duration> = {
+                                        let mut limiter = relay.rate_limiter.lock().await;
+                                        limiter.take(priority, msgs.len(), Instant::now())
+                                    };
+                                    let len: usize = taken.unwrap_or_default();
+                                    if len < msgs.len() {
+                                        let rest: Vec<ClientMessage> = msgs.split_off(len);
+                                        let rest_acks: Vec<oneshot::Sender<bool>> =
+                                            acks.split_off(cmp::min(len, acks.len()));
+                                        relay.send_queue.push_front(
+                                            priority,
+                                            (RelayEvent::Batch(rest), rest_acks),
+                                        );
+                                    }
+                                    if msgs.is_empty() {
+                                        continue;
+                                    }
+                                }
+
+                                // Update stats
+                                for msg in msgs.iter() {
+                                    match msg {
+                                        ClientMessage::Req {
+                                            subscription_id, ..
+                                        } => relay.stats.new_req(subscription_id.clone()),
+                                        ClientMessage::Close(subscription_id) => {
+                                            relay.stats.subscription_closed(subscription_id)
+                                        }
+                                        _ => (),
+                                    }
+                                }
+
                                 let msgs: Vec<String> =
                                     msgs.into_iter().map(|msg| msg.as_json()).collect();
                                 let size: usize = msgs.iter().map(|msg| msg.as_bytes().len()).sum();
@@ -584,13 +866,10 @@ impl InternalRelay {
                                 match ws_tx.send_all(&mut stream).await {
                                     Ok(_) => {
                                         relay.stats.add_bytes_sent(size);
-                                        if let Some(sender) = oneshot_sender {
-                                            if let Err(e) = sender.send(true) {
-                                                tracing::error!(
-                                                    "Impossible to send oneshot msg: {}",
-                                                    e
-                                                );
-                                            }
+
+                                        // The receiver may be dropped (ex. timeout)
+                                        for ack in acks.into_iter() {
+                                            let _ = ack.send(true);
                                         }
                                     }
                                     Err(e) => {
@@ -599,13 +878,8 @@ impl InternalRelay {
                                             relay.url(),
                                             e.to_string()
                                         );
-                                        if let Some(sender) = oneshot_sender {
-                                            if let Err(e) = sender.send(false) {
-                                                tracing::error!(
-                                                    "Impossible to send oneshot msg: {}",
-                                                    e
-                                                );
-                                            }
+                                        for ack in acks.into_iter() {
+                                            let _ = ack.send(false);
                                         }
                                         break;
                                     }
@@ -705,8 +979,18 @@ impl InternalRelay {
                             }
                         }
 
+                        // Shape to relay limitation
+                        #[cfg(feature = "nip11")]
+                        let msg = match relay.shape_incoming(msg).await {
+                            Some(msg) => msg,
+                            None => return Ok(false),
+                        };
+
                         match relay.handle_relay_message(msg).await {
                             Ok(Some(msg)) => {
+                                // Send to subscription stream, before the auto-close can remove it
+                                relay.send_stream_item(&msg).await;
+
                                 // Send notification
                                 relay
                                     .send_notification(RelayNotification::Message {
@@ -716,7 +1000,8 @@ impl InternalRelay {
 
                                 match msg {
                                     RelayMessage::Notice { message } => {
-                                        tracing::warn!("Notice from {}: {message}", relay.url)
+                                        tracing::warn!("Notice from {}: {message}", relay.url);
+                                        relay.stats.new_notice();
                                     }
                                     RelayMessage::Ok {
                                         event_id,
@@ -724,6 +1009,71 @@ impl InternalRelay {
                                         message,
                                     } => {
                                         tracing::debug!("Received OK from {} for event {event_id}: status={status}, message={message}", relay.url);
+                                        if status || !auth::is_auth_required(&message) {
+                                            relay.stats.new_ok(status);
+                                        }
+
+                                        #[cfg(not(target_arch = "wasm32"))]
+                                        relay
+                                            .rate_limit_reply(Priority::Event, status, &message)
+                                            .await;
+                                    }
+                                    RelayMessage::EndOfStoredEvents(subscription_id) => {
+                                        relay.stats.new_eose(&subscription_id).await;
+
+                                        #[cfg(not(target_arch = "wasm32"))]
+                                        relay.rate_limit_reply(Priority::Req, true, "").await;
+                                    }
+                                    RelayMessage::Auth { challenge } => {
+                                        tracing::debug!(
+                                            "Received auth challenge from {}",
+                                            relay.url
+                                        );
+                                        if relay.opts.get_automatic_authentication() {
+                                            let relay = relay.clone();
+                                            let _ = thread::spawn(async move {
+                                                if let Err(e) = relay.authenticate(challenge).await
+                                                {
+                                                    tracing::error!(
+                                                        "Impossible to authenticate to {}: {e}",
+                                                        relay.url
+                                                    );
+                                                    relay
+                                                        .authentication_failed(e.to_string())
+                                                        .await;
+                                                }
+                                            });
+                                        } else {
+                                            relay
+                                                .authentication_failed(String::from(
+                                                    "automatic authentication disabled",
+                                                ))
+                                                .await;
+                                        }
+                                    }
+                                    RelayMessage::Closed {
+                                        subscription_id,
+                                        message,
+                                    } => {
+                                        tracing::debug!("Subscription {subscription_id} closed by {}: {message}", relay.url);
+                                        relay.stats.subscription_closed(&subscription_id);
+                                        if !auth::is_auth_required(&message) {
+                                            relay.stats.new_closed();
+                                        }
+
+                                        #[cfg(not(target_arch = "wasm32"))]
+                                        relay
+                                            .rate_limit_reply(Priority::Req, false, &message)
+                                            .await;
+                                        if relay.is_auth_retry_enabled()
+                                            && auth::is_auth_required(&message)
+                                            && relay.subscription(&subscription_id).await.is_some()
+                                        {
+                                            // Re-subscribe after authentication
+                                            let mut pending =
+                                                relay.auth_pending_subscriptions.lock().await;
+                                            pending.insert(subscription_id);
+                                        }
                                     }
                                     _ => (),
                                 }
@@ -791,7 +1141,7 @@ impl InternalRelay {
                     }
 
                     #[cfg(target_arch = "wasm32")]
-                    while let Some(msg) = ws_rx.next().await {
+                    while let Some(Ok(msg)) = ws_rx.next().await {
                         let data: Vec<u8> = msg.as_ref().to_vec();
                         match func(&relay, data).await {
                             Ok(exit) => {
@@ -808,6 +1158,16 @@ impl InternalRelay {
 
                     tracing::debug!("Exited from Message Thread of {}", relay.url);
 
+                    // A connection closed soon after being established counts as a failed attempt
+                    let connected_for: u64 = Timestamp::now()
+                        .as_u64()
+                        .saturating_sub(relay.stats.connected_at().as_u64());
+                    if connected_for < MIN_STABLE_CONNECTION_SECS {
+                        relay.failures.fetch_add(1, Ordering::SeqCst);
+                    } else {
+                        relay.failures.store(0, Ordering::SeqCst);
+                    }
+
                     if let Err(err) = relay.disconnect().await {
                         tracing::error!("Impossible to disconnect {}: {}", relay.url, err);
                     }
@@ -828,6 +1188,7 @@ impl InternalRelay {
                 }
             }
             Err(err) => {
+                self.failures.fetch_add(1, Ordering::SeqCst);
                 self.set_status(RelayStatus::Disconnected).await;
                 tracing::error!("Impossible to connect to {}: {}", url, err);
             }
@@ -908,12 +1269,18 @@ impl InternalRelay {
                     );
                 }
 
+                // Get subscription stream, if not already received from another relay
+                let stream: Option<Arc<SubscriptionSink>> = self
+                    .stream(&SubscriptionId::new(&subscription_id))
+                    .await
+                    .filter(|sink| !sink.has_seen(&partial_event.id));
+
                 // Check if event was already saved
-                if self
+                let saved: bool = self
                     .database
                     .has_event_already_been_saved(&partial_event.id)
-                    .await?
-                {
+                    .await?;
+                if saved && stream.is_none() {
                     tracing::trace!("Event {} already saved into database", partial_event.id);
                     return Ok(None);
                 }
@@ -929,12 +1296,22 @@ impl InternalRelay {
                 // Verify event
                 event.verify()?;
 
-                // Save event
-                self.database.save_event(&event).await?;
-
                 // Box event
                 let event: Box<Event> = Box::new(event);
 
+                // Send to subscription stream
+                if let Some(stream) = stream {
+                    stream.event(self.url(), event.clone());
+                }
+
+                // Already saved: sent only to subscription stream
+                if saved {
+                    return Ok(None);
+                }
+
+                // Save event
+                self.database.save_event(&event).await?;
+
                 // Check if seen
                 if !seen {
                     // Send notification
@@ -954,20 +1331,258 @@ impl InternalRelay {
         }
     }
 
-    fn send_relay_event(
-        &self,
-        relay_msg: RelayEvent,
-        sender: Option<oneshot::Sender<bool>>,
-    ) -> Result<(), Error> {
-        self.relay_sender
-            .try_send((relay_msg, sender))
+    /// Replace the chunk IDs of split subscriptions and send the queued `REQ`s
+    #[cfg(feature = "nip11")]
+    async fn shape_incoming(&self, msg: RawRelayMessage) -> Option<RawRelayMessage> {
+        let limitation: Limitation = self.limitation().await;
+        let (msg, msgs) = {
+            let mut shaper = self.shaper.lock().await;
+            shaper.incoming(&limitation, msg)
+        };
+
+        if !msgs.is_empty() {
+            if let Err(e) = self.queue_batch(msgs, false) {
+                tracing::error!("Impossible to send queued messages to {}: {e}", self.url);
+            }
+        }
+
+        msg
+    }
+
+    /// Check messages against relay limitation (NIP-11) and shape `REQ`s and `CLOSE`s
+    #[cfg(feature = "nip11")]
+    async fn shape_outgoing(&self, msgs: Vec<ClientMessage>) -> Result<Vec<ClientMessage>, Error> {
+        let limitation: Limitation = self.limitation().await;
+
+        // Check if authentication is required and can't be done automatically
+        if limitation.auth_required == Some(true)
+            && !self.is_authenticated()
+            && !(self.opts.get_automatic_authentication() && self.authenticator().is_some())
+            && msgs.iter().any(|msg| msg.is_event() || msg.is_req())
+        {
+            return Err(Error::AuthenticationRequired);
+        }
+
+        let max_size: Option<usize> = limitation::positive(limitation.max_message_length);
+        let min_pow: Option<u8> = limitation
+            .min_pow_difficulty
+            .and_then(|difficulty| u8::try_from(difficulty).ok())
+            .filter(|difficulty| *difficulty > 0);
+
+        for msg in msgs.iter() {
+            if let ClientMessage::Event(event) = msg {
+                // Check if payment is required
+                if limitation.payment_required == Some(true) && !self.opts.get_paid() {
+                    return Err(Error::PaymentRequired);
+                }
+
+                // Check POW
+                if let Some(min) = min_pow {
+                    if !event.id().check_pow(min) {
+                        return Err(Error::PowDifficultyTooLow { min });
+                    }
+                }
+            }
+
+            // Check message size
+            if let Some(max_size) = max_size {
+                let size: usize = msg.as_json().as_bytes().len();
+                if size > max_size {
+                    return Err(Error::MessageTooLarge { size, max_size });
+                }
+            }
+        }
+
+        let mut shaper = self.shaper.lock().await;
+        Ok(shaper.outgoing(&limitation, msgs))
+    }
+
+    /// Back off if the relay replied with `rate-limited`
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn rate_limit_reply(&self, priority: Priority, accepted: bool, message: &str) {
+        let mut limiter = self.rate_limiter.lock().await;
+        if accepted {
+            limiter.accepted(priority);
+        } else if ratelimit::is_rate_limited(message) {
+            let backoff: Duration = limiter.rate_limited(priority, Instant::now());
+            tracing::warn!(
+                "Rate limited by {}: {priority:?} messages paused for {backoff:?}",
+                self.url
+            );
+        }
+    }
+
+    /// Send `EOSE` and `CLOSED` to subscription stream
+    async fn send_stream_item(&self, msg: &RelayMessage) {
+        match msg {
+            RelayMessage::EndOfStoredEvents(subscription_id) => {
+                if let Some(stream) = self.stream(subscription_id).await {
+                    stream.send(SubscriptionItem::Eose {
+                        relay_url: self.url(),
+                    });
+                }
+            }
+            RelayMessage::Closed {
+                subscription_id,
+                message,
+            } => {
+                // Keep the stream if the subscription will be sent again after authentication
+                if self.is_auth_retry_enabled() && auth::is_auth_required(message) {
+                    return;
+                }
+
+                if let Some(stream) = self.stream(subscription_id).await {
+                    stream.send(SubscriptionItem::Closed {
+                        relay_url: self.url(),
+                        message: message.clone(),
+                    });
+                }
+                self.remove_stream(subscription_id).await;
+            }
+            _ => (),
+        }
+    }
+
+    /// Stop waiting for the authentication: the messages rejected with `auth-required` fail
+    async fn authentication_failed(&self, message: String) {
+        self.authentication_failed.store(true, Ordering::SeqCst);
+        self.send_notification(RelayNotification::AuthenticationFailed { message })
+            .await;
+    }
+
+    /// Answer to an `AUTH` challenge and, if succeeded, re-subscribe the subscriptions closed with `auth-required`
+    async fn authenticate(&self, challenge: String) -> Result<(), Error> {
+        let authenticator = self
+            .authenticator()
+            .ok_or(AuthenticationError::AuthenticatorNotConfigured)?;
+
+        // Check policy
+        if !authenticator.allow(&self.url).await {
+            return Err(Error::Authentication(AuthenticationError::NotAllowed));
+        }
+
+        // Sign event
+        let event: Event = authenticator.authenticate(challenge, self.url()).await?;
+        let id: EventId = event.id();
+
+        // Subscribe to notifications before sending the AUTH, to avoid missing fast responses
+        let mut notifications = self.internal_notification_sender.subscribe();
+
+        // Send AUTH message
+        self.send_msg(ClientMessage::auth(event), RelaySendOptions::default())
+            .await?;
+
+        // Wait for OK
+        time::timeout(Some(DEFAULT_SEND_TIMEOUT), async {
+            while let Ok(notification) = notifications.recv().await {
+                match notification {
+                    RelayNotification::Message {
+                        message:
+                            RelayMessage::Ok {
+                                event_id,
+                                status,
+                                message,
+                            },
+                    } => {
+                        if event_id == id {
+                            return if status {
+                                Ok(())
+                            } else {
+                                Err(Error::Authentication(AuthenticationError::Rejected(
+                                    message,
+                                )))
+                            };
+                        }
+                    }
+                    RelayNotification::RelayStatus { status } => {
+                        if status.is_disconnected() {
+                            return Err(Error::NotConnectedStatusChanged);
+                        }
+                    }
+                    RelayNotification::Stop | RelayNotification::Shutdown => {
+                        return Err(Error::NotConnected);
+                    }
+                    _ => (),
+                }
+            }
+
+            Err(Error::OneShotRecvError)
+        })
+        .await
+        .ok_or(Error::Timeout)??;
+
+        tracing::info!("Authenticated to {}", self.url);
+        self.authenticated.store(true, Ordering::SeqCst);
+        self.authentication_failed.store(false, Ordering::SeqCst);
+        self.send_notification(RelayNotification::Authenticated)
+            .await;
+
+        // Re-subscribe
+        let pending: HashSet<SubscriptionId> = {
+            let mut pending = self.auth_pending_subscriptions.lock().await;
+            std::mem::take(&mut *pending)
+        };
+        for id in pending.into_iter() {
+            if let Some(filters) = self.subscription(&id).await {
+                self.send_msg(
+                    ClientMessage::req(id, filters),
+                    RelaySendOptions::default().skip_send_confirmation(true),
+                )
+                .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn send_relay_event(&self, relay_msg: RelayEvent) -> Result<(), Error> {
+        self.send_queue
+            .push(Priority::Control, (relay_msg, Vec::new()))
             .map_err(|_| Error::MessageNotSent)
     }
 
+    /// Queue the messages, grouped by priority (each priority is rate limited by its own bucket)
+    ///
+    /// If `confirm`, return the receivers of the acknowledgement of each message, in the same order.
+    fn queue_batch(
+        &self,
+        msgs: Vec<ClientMessage>,
+        confirm: bool,
+    ) -> Result<Vec<oneshot::Receiver<bool>>, Error> {
+        let mut receivers: Vec<oneshot::Receiver<bool>> = Vec::new();
+        let mut groups: Vec<(Priority, Vec<ClientMessage>, Vec<oneshot::Sender<bool>>)> =
+            Vec::new();
+
+        for msg in msgs.into_iter() {
+            let priority: Priority = Priority::from_msg(&msg);
+            match groups.last_mut() {
+                Some((p, ..)) if *p == priority => {}
+                _ => groups.push((priority, Vec::new(), Vec::new())),
+            }
+
+            if let Some((_, msgs, acks)) = groups.last_mut() {
+                msgs.push(msg);
+                if confirm {
+                    let (tx, rx) = oneshot::channel::<bool>();
+                    acks.push(tx);
+                    receivers.push(rx);
+                }
+            }
+        }
+
+        for (priority, msgs, acks) in groups.into_iter() {
+            self.send_queue
+                .push(priority, (RelayEvent::Batch(msgs), acks))
+                .map_err(|_| Error::MessageNotSent)?;
+        }
+
+        Ok(receivers)
+    }
+
     async fn disconnect(&self) -> Result<(), Error> {
         let status = self.status().await;
         if !status.is_disconnected() {
-            self.send_relay_event(RelayEvent::Close, None)?;
+            self.send_relay_event(RelayEvent::Close)?;
         }
         Ok(())
     }
@@ -976,7 +1591,7 @@ impl InternalRelay {
         self.schedule_for_stop(true);
         let status = self.status().await;
         if !status.is_disconnected() {
-            self.send_relay_event(RelayEvent::Stop, None)?;
+            self.send_relay_event(RelayEvent::Stop)?;
         }
         self.send_notification(RelayNotification::Stop).await;
         Ok(())
@@ -984,9 +1599,10 @@ impl InternalRelay {
 
     pub async fn terminate(&self) -> Result<(), Error> {
         self.schedule_for_termination(true);
+        self.streams.write().await.clear();
         let status = self.status().await;
         if !status.is_disconnected() {
-            self.send_relay_event(RelayEvent::Terminate, None)?;
+            self.send_relay_event(RelayEvent::Terminate)?;
         }
         self.send_notification(RelayNotification::Shutdown).await;
         Ok(())
@@ -1001,6 +1617,39 @@ impl InternalRelay {
         msgs: Vec<ClientMessage>,
         opts: RelaySendOptions,
     ) -> Result<(), Error> {
+        let msgs: Vec<ClientMessage> = self.prepare_batch(msgs, opts).await?;
+
+        // All messages queued or dropped
+        if msgs.is_empty() {
+            return Ok(());
+        }
+
+        if opts.skip_send_confirmation {
+            self.queue_batch(msgs, false)?;
+            Ok(())
+        } else {
+            let receivers: Vec<oneshot::Receiver<bool>> = self.queue_batch(msgs, true)?;
+            time::timeout(Some(opts.timeout), async {
+                for receiver in receivers.into_iter() {
+                    match receiver.await {
+                        Ok(true) => (),
+                        Ok(false) => return Err(Error::MessageNotSent),
+                        Err(_) => return Err(Error::OneShotRecvError),
+                    }
+                }
+                Ok(())
+            })
+            .await
+            .ok_or(Error::RecvTimeout)?
+        }
+    }
+
+    /// Check the messages and shape them to the relay limitation (NIP-11)
+    async fn prepare_batch(
+        &self,
+        msgs: Vec<ClientMessage>,
+        opts: RelaySendOptions,
+    ) -> Result<Vec<ClientMessage>, Error> {
         if !self.opts.flags.has_write() && msgs.iter().any(|msg| msg.is_event()) {
             return Err(Error::WriteDisabled);
         }
@@ -1017,25 +1666,11 @@ impl InternalRelay {
             return Err(Error::NotConnected);
         }
 
-        if opts.skip_send_confirmation {
-            self.send_relay_event(RelayEvent::Batch(msgs), None)
-        } else {
-            let (tx, rx) = oneshot::channel::<bool>();
-            self.send_relay_event(RelayEvent::Batch(msgs), Some(tx))?;
-            match time::timeout(Some(opts.timeout), rx).await {
-                Some(result) => match result {
-                    Ok(val) => {
-                        if val {
-                            Ok(())
-                        } else {
-                            Err(Error::MessageNotSent)
-                        }
-                    }
-                    Err(_) => Err(Error::OneShotRecvError),
-                },
-                _ => Err(Error::RecvTimeout),
-            }
-        }
+        // Shape messages to relay limitation
+        #[cfg(feature = "nip11")]
+        let msgs: Vec<ClientMessage> = self.shape_outgoing(msgs).await?;
+
+        Ok(msgs)
     }
 
     pub async fn send_event(&self, event: Event, opts: RelaySendOptions) -> Result<EventId, Error> {
@@ -1049,6 +1684,49 @@ impl InternalRelay {
         events: Vec<Event>,
         opts: RelaySendOptions,
     ) -> Result<(), Error> {
+        let events_len: usize = events.len();
+        let outcomes: HashMap<EventId, PublishOutcome> = self.publish(events, opts).await?;
+
+        let mut published: Vec<EventId> = Vec::new();
+        let mut not_published: HashMap<EventId, String> = HashMap::new();
+        for (event_id, outcome) in outcomes.into_iter() {
+            match outcome {
+                PublishOutcome::Accepted { .. } => published.push(event_id),
+                PublishOutcome::Rejected { message, .. } | PublishOutcome::Failed(message) => {
+                    if events_len == 1 {
+                        return Err(Error::EventNotPublished(message));
+                    }
+                    not_published.insert(event_id, message);
+                }
+                PublishOutcome::Timeout => {
+                    if events_len == 1 {
+                        return Err(Error::Timeout);
+                    }
+                    not_published.insert(event_id, Error::Timeout.to_string());
+                }
+            }
+        }
+
+        if !published.is_empty() && not_published.is_empty() {
+            Ok(())
+        } else if !published.is_empty() && !not_published.is_empty() {
+            Err(Error::PartialPublish {
+                published,
+                not_published,
+            })
+        } else {
+            Err(Error::EventsNotPublished(not_published))
+        }
+    }
+
+    /// Send events and wait for the `OK` messages
+    ///
+    /// Return the [`PublishOutcome`] of every event.
+    pub async fn publish(
+        &self,
+        events: Vec<Event>,
+        opts: RelaySendOptions,
+    ) -> Result<HashMap<EventId, PublishOutcome>, Error> {
         if events.is_empty() {
             return Err(Error::BatchEventEmpty);
         }
@@ -1057,19 +1735,37 @@ impl InternalRelay {
         let mut msgs: Vec<ClientMessage> = Vec::with_capacity(events_len);
         let mut missing: HashSet<EventId> = HashSet::with_capacity(events_len);
 
+        // Keep events to send them again if rejected with `auth-required`
+        let retry_after_auth: bool = self.is_auth_retry_enabled();
+        let mut to_retry: HashMap<EventId, Event> = HashMap::new();
+        let mut waiting_auth: Vec<(Event, String)> = Vec::new();
+
         for event in events.into_iter() {
             missing.insert(event.id());
+            if retry_after_auth {
+                to_retry.insert(event.id(), event.clone());
+            }
             msgs.push(ClientMessage::event(event));
         }
 
-        // Batch send messages
-        self.batch_msg(msgs, opts).await?;
+        // Subscribe to notifications before sending messages, to avoid missing fast responses
+        let mut notifications = self.internal_notification_sender.subscribe();
+
+        // Queue messages, with the send acknowledgement of every event
+        let msgs: Vec<ClientMessage> = self.prepare_batch(msgs, opts).await?;
+        let ids: Vec<EventId> = msgs
+            .iter()
+            .filter_map(|msg| match msg {
+                ClientMessage::Event(event) => Some(event.id()),
+                _ => None,
+            })
+            .collect();
+        let mut sent: HashMap<EventId, oneshot::Receiver<bool>> =
+            ids.into_iter().zip(self.queue_batch(msgs, true)?).collect();
 
         // Hanlde responses
-        time::timeout(Some(opts.timeout), async {
-            let mut published: HashSet<EventId> = HashSet::new();
-            let mut not_published: HashMap<EventId, String> = HashMap::new();
-            let mut notifications = self.internal_notification_sender.subscribe();
+        let mut outcomes: HashMap<EventId, PublishOutcome> = HashMap::with_capacity(events_len);
+        let res: Option<Result<(), String>> = time::timeout(Some(opts.timeout), async {
             while let Ok(notification) = notifications.recv().await {
                 match notification {
                     RelayNotification::Message {
@@ -1080,27 +1776,54 @@ impl InternalRelay {
                                 message,
                             },
                     } => {
-                        if missing.remove(&event_id) {
-                            if events_len == 1 {
-                                if status {
-                                    return Ok(());
-                                } else {
-                                    return Err(Error::EventNotPublished(message));
+                        // Wait for authentication, then send the event again (only once)
+                        if !status && auth::is_auth_required(&message) {
+                            if let Some(event) = to_retry.remove(&event_id) {
+                                if self.is_authenticated() {
+                                    // Authenticated in the meantime
+                                    self.batch_msg(vec![ClientMessage::event(event)], opts)
+                                        .await
+                                        .map_err(|e| e.to_string())?;
+                                    continue;
+                                } else if self.is_auth_retry_enabled() {
+                                    tracing::debug!(
+                                        "Event {event_id} requires authentication to {}",
+                                        self.url
+                                    );
+                                    waiting_auth.push((event, message));
+                                    continue;
                                 }
                             }
+                        }
 
-                            if status {
-                                published.insert(event_id);
-                            } else {
-                                not_published.insert(event_id, message);
-                            }
+                        if missing.remove(&event_id) {
+                            outcomes.insert(event_id, PublishOutcome::from_ok(status, message));
                         }
                     }
                     RelayNotification::RelayStatus { status } => {
                         if opts.skip_disconnected && status.is_disconnected() {
-                            return Err(Error::EventNotPublished(String::from(
-                                "relay not connected (status changed)",
-                            )));
+                            return Err(String::from("relay not connected (status changed)"));
+                        }
+                    }
+                    RelayNotification::Authenticated => {
+                        if !waiting_auth.is_empty() {
+                            let msgs: Vec<ClientMessage> = waiting_auth
+                                .drain(..)
+                                .map(|(event, ..)| ClientMessage::event(event))
+                                .collect();
+                            self.batch_msg(msgs, opts)
+                                .await
+                                .map_err(|e| e.to_string())?;
+                        }
+                    }
+                    RelayNotification::AuthenticationFailed { .. } => {
+                        // The rejections are final
+                        to_retry.clear();
+                        for (event, message) in waiting_auth.drain(..) {
+                            if missing.remove(&event.id()) {
+                                outcomes
+                                    .insert(event.id(), PublishOutcome::from_ok(false, message));
+                            }
                         }
                     }
                     _ => (),
@@ -1111,19 +1834,28 @@ impl InternalRelay {
                 }
             }
 
-            if !published.is_empty() && not_published.is_empty() {
-                Ok(())
-            } else if !published.is_empty() && !not_published.is_empty() {
-                Err(Error::PartialPublish {
-                    published: published.into_iter().collect(),
-                    not_published,
-                })
-            } else {
-                Err(Error::EventsNotPublished(not_published))
-            }
+            Ok(())
         })
-        .await
-        .ok_or(Error::Timeout)?
+        .await;
+
+        // Events without `OK`
+        for event_id in missing.into_iter() {
+            let outcome: PublishOutcome = match &res {
+                Some(Ok(())) | None => match sent.get_mut(&event_id).map(|rx| rx.try_recv()) {
+                    Some(Ok(false)) | Some(Err(TryRecvError::Closed)) => {
+                        PublishOutcome::Failed(String::from("message not sent"))
+                    }
+                    Some(Err(TryRecvError::Empty)) => {
+                        PublishOutcome::Failed(String::from("message not sent in time"))
+                    }
+                    Some(Ok(true)) | None => PublishOutcome::Timeout,
+                },
+                Some(Err(error)) => PublishOutcome::Failed(error.clone()),
+            };
+            outcomes.insert(event_id, outcome);
+        }
+
+        Ok(outcomes)
     }
 
     async fn resubscribe_all(&self, opts: RelaySendOptions) -> Result<(), Error> {
@@ -1167,12 +1899,23 @@ impl InternalRelay {
             return Err(Error::FiltersEmpty);
         }
 
+        // Subscribe to notifications before sending the REQ, to avoid missing fast responses
+        let mut notifications = self.internal_notification_sender.subscribe();
+
+        // Check before sending the REQ, the authentication may complete before the `CLOSED`
+        let mut retry_after_auth: bool = self.is_auth_retry_enabled();
+
         // Compose and send message
         let msg: ClientMessage = ClientMessage::req(id.clone(), filters.clone());
         self.send_msg(msg, opts.send_opts).await?;
 
         // Check if auto-close condition is set
         if let Some(opts) = opts.auto_close {
+            self.auto_closing_subscriptions
+                .lock()
+                .await
+                .insert(id.clone());
+
             let this = self.clone();
             thread::spawn(async move {
                 let sub_id = id.clone();
@@ -1180,8 +1923,8 @@ impl InternalRelay {
                 let res = time::timeout(opts.timeout, async move {
                     let mut counter = 0;
                     let mut received_eose: bool = false;
+                    let mut waiting_auth: bool = false;
 
-                    let mut notifications = relay.internal_notification_sender.subscribe();
                     while let Ok(notification) = notifications.recv().await {
                         match notification {
                             RelayNotification::Message { message, .. } => match message {
@@ -1215,8 +1958,54 @@ impl InternalRelay {
                                         }
                                     }
                                 }
+                                RelayMessage::Closed {
+                                    subscription_id,
+                                    message,
+                                } => {
+                                    if subscription_id.eq(&id) {
+                                        // Wait for authentication, then subscribe again (only once)
+                                        if retry_after_auth && auth::is_auth_required(&message) {
+                                            retry_after_auth = false;
+                                            if relay.is_authenticated() {
+                                                // Authenticated in the meantime
+                                                if let Err(e) =
+                                                    relay.subscribe_again(&id, &filters).await
+                                                {
+                                                    tracing::error!(
+                                                        "Impossible to subscribe again to {}: {e}",
+                                                        relay.url
+                                                    );
+                                                    return false;
+                                                }
+                                                continue;
+                                            } else if relay.is_auth_retry_enabled() {
+                                                waiting_auth = true;
+                                                continue;
+                                            }
+                                        }
+
+                                        return false; // No need to send CLOSE msg
+                                    }
+                                }
                                 _ => (),
                             },
+                            RelayNotification::AuthenticationFailed { .. } => {
+                                if waiting_auth {
+                                    return false; // Already closed by relay
+                                }
+                            }
+                            RelayNotification::Authenticated => {
+                                if waiting_auth {
+                                    waiting_auth = false;
+                                    if let Err(e) = relay.subscribe_again(&id, &filters).await {
+                                        tracing::error!(
+                                            "Impossible to subscribe again to {}: {e}",
+                                            relay.url
+                                        );
+                                        return false;
+                                    }
+                                }
+                            }
                             RelayNotification::RelayStatus { status } => {
                                 if status.is_disconnected() {
                                     return false; // No need to send CLOSE msg
@@ -1263,7 +2052,13 @@ impl InternalRelay {
                     }
                 };
 
-                if to_close {
+                // Close subscription stream
+                this.remove_stream(&sub_id).await;
+
+                // Skip if already unsubscribed
+                let running: bool = this.auto_closing_subscriptions.lock().await.remove(&sub_id);
+
+                if to_close && running {
                     // Unsubscribe
                     this.send_msg(
                         ClientMessage::close(sub_id.clone()),
@@ -1284,6 +2079,12 @@ impl InternalRelay {
         Ok(())
     }
 
+    /// Send again the `REQ` of a subscription closed by relay
+    async fn subscribe_again(&self, id: &SubscriptionId, filters: &[Filter]) -> Result<(), Error> {
+        let msg = ClientMessage::req(id.clone(), filters.to_vec());
+        self.send_msg(msg, RelaySendOptions::default()).await
+    }
+
     pub async fn unsubscribe(
         &self,
         id: SubscriptionId,
@@ -1295,6 +2096,8 @@ impl InternalRelay {
 
         // Remove subscription
         self.remove_subscription(&id).await;
+        self.remove_stream(&id).await;
+        self.auto_closing_subscriptions.lock().await.remove(&id);
 
         // Send CLOSE message
         let msg: ClientMessage = ClientMessage::close(id);
@@ -1308,6 +2111,9 @@ impl InternalRelay {
 
         let subscriptions = self.subscriptions().await;
 
+        // Close all subscription streams
+        self.streams.write().await.clear();
+
         for id in subscriptions.into_keys() {
             // Remove subscription
             self.remove_subscription(&id).await;
@@ -1322,6 +2128,7 @@ impl InternalRelay {
 
     async fn handle_events_of<F>(
         &self,
+        mut notifications: broadcast::Receiver<RelayNotification>,
         id: SubscriptionId,
         timeout: Duration,
         opts: FilterOptions,
@@ -1340,9 +2147,20 @@ impl InternalRelay {
         let mut counter = 0;
         let mut received_eose: bool = false;
 
-        let mut notifications = self.internal_notification_sender.subscribe();
         time::timeout(Some(timeout), async {
-            while let Ok(notification) = notifications.recv().await {
+            loop {
+                let notification = match notifications.recv().await {
+                    Ok(notification) => notification,
+                    // The callback is too slow: keep going with the next notifications
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::warn!(
+                            "Query {id} to {} lagged: {skipped} notifications skipped",
+                            self.url
+                        );
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                };
                 match notification {
                     RelayNotification::Message { message, .. } => match message {
                         RelayMessage::Event {
@@ -1395,7 +2213,19 @@ impl InternalRelay {
 
         if let FilterOptions::WaitDurationAfterEOSE(duration) = opts {
             time::timeout(Some(duration), async {
-                while let Ok(notification) = notifications.recv().await {
+                loop {
+                    let notification = match notifications.recv().await {
+                        Ok(notification) => notification,
+                        // The callback is too slow: keep going with the next notifications
+                        Err(RecvError::Lagged(skipped)) => {
+                            tracing::warn!(
+                                "Query {id} to {} lagged: {skipped} notifications skipped",
+                                self.url
+                            );
+                            continue;
+                        }
+                        Err(RecvError::Closed) => break,
+                    };
                     match notification {
                         RelayNotification::Message {
                             message:
@@ -1429,6 +2259,7 @@ impl InternalRelay {
 
     pub(crate) async fn get_events_of_with_callback<F>(
         &self,
+        id: SubscriptionId,
         filters: Vec<Filter>,
         timeout: Duration,
         opts: FilterOptions,
@@ -1443,11 +2274,16 @@ impl InternalRelay {
             .timeout(Some(timeout));
         let subscribe_opts = SubscribeOptions::default().close_on(Some(auto_close_opts));
 
+        // Subscribe to notifications before sending the REQ
+        let notifications = self.internal_notification_sender.subscribe();
+
         // Subscribe with auto-close
-        let id: SubscriptionId = self.subscribe(filters, subscribe_opts).await?;
+        self.subscribe_with_id(id.clone(), filters, subscribe_opts)
+            .await?;
 
         // Handle events
-        self.handle_events_of(id, timeout, opts, callback).await?;
+        self.handle_events_of(notifications, id, timeout, opts, callback)
+            .await?;
 
         Ok(())
     }
@@ -1464,7 +2300,8 @@ impl InternalRelay {
             .await
             .unwrap_or_default();
         let events: Mutex<BTreeSet<Event>> = Mutex::new(stored_events.into_iter().collect());
-        self.get_events_of_with_callback(filters, timeout, opts, |event| async {
+        let id: SubscriptionId = SubscriptionId::generate();
+        self.get_events_of_with_callback(id, filters, timeout, opts, |event| async {
             let mut events = events.lock().await;
             events.insert(event);
         })
@@ -1472,6 +2309,140 @@ impl InternalRelay {
         Ok(events.into_inner().into_iter().rev().collect())
     }
 
+    /// Get all the events of filters, page by page, moving `until` back to the oldest event received
+    ///
+    /// Events are saved into the database.
+    pub async fn paginate(
+        &self,
+        filters: Vec<Filter>,
+        opts: PaginationOptions,
+    ) -> Result<(), Error> {
+        if !self.opts.flags.has_read() {
+            return Err(Error::ReadDisabled);
+        }
+
+        let page_size: usize = self.page_size(opts.page_size).await;
+
+        for filter in filters.into_iter() {
+            self.paginate_filter(filter, page_size, opts.timeout)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Get page size, capped by relay limitation (NIP-11)
+    async fn page_size(&self, page_size: usize) -> usize {
+        #[cfg(feature = "nip11")]
+        {
+            let document = self.document().await;
+            if let Some(max_limit) = document.limitation.and_then(|l| l.max_limit) {
+                if max_limit > 0 {
+                    return page_size.min(max_limit as usize);
+                }
+            }
+        }
+
+        page_size
+    }
+
+    async fn paginate_filter(
+        &self,
+        filter: Filter,
+        page_size: usize,
+        timeout: Duration,
+    ) -> Result<(), Error> {
+        let since: Timestamp = filter.since.unwrap_or_else(|| Timestamp::from(0));
+        let mut until: Timestamp = filter.until.unwrap_or_else(Timestamp::now);
+
+        // The `limit` of the filter is the max number of events of all the pages
+        let mut remaining: Option<usize> = filter.limit;
+
+        // IDs of the events received in all the pages
+        let mut seen: HashSet<EventId> = HashSet::new();
+
+        while until >= since {
+            let limit: usize = match remaining {
+                Some(0) => break,
+                Some(remaining) => remaining.min(page_size),
+                None => page_size,
+            };
+            let page: Filter = filter.clone().until(until).limit(limit);
+            let mut events: Vec<Event> = self.get_page(page, timeout).await?;
+            let full: bool = events.len() >= limit;
+
+            // Discard the events out of range (relays that ignore `since` or `until`)
+            events.retain(|e| e.created_at() <= until && e.created_at() >= since);
+
+            let oldest: Timestamp = match events.iter().map(|e| e.created_at()).min() {
+                Some(oldest) => oldest,
+                None => break, // Nothing more
+            };
+
+            let new: usize = events.iter().filter(|e| seen.insert(e.id())).count();
+
+            if new == 0 {
+                // A full page of events with `created_at` equal to `until` (more than `page_size`):
+                // skip the timestamp. Otherwise, the relay has nothing more.
+                if full && oldest == until && until.as_u64() > 0 {
+                    until = until - 1u64;
+                    continue;
+                }
+                break;
+            }
+
+            tracing::debug!(
+                "Received {} new events until {until} from {}",
+                new,
+                self.url
+            );
+
+            if let Some(remaining) = remaining.as_mut() {
+                *remaining = remaining.saturating_sub(new);
+            }
+
+            until = oldest;
+        }
+
+        Ok(())
+    }
+
+    /// Get all the events received for filter (stored ones included), until `EOSE`, `CLOSED` or timeout
+    async fn get_page(&self, filter: Filter, timeout: Duration) -> Result<Vec<Event>, Error> {
+        let id: SubscriptionId = SubscriptionId::generate();
+
+        // Stream also the events already saved into the database
+        let (sink, mut stream) = SubscriptionSink::new(id.clone());
+        self.add_stream(id.clone(), sink).await;
+
+        let auto_close = SubscribeAutoCloseOptions::default()
+            .filter(FilterOptions::ExitOnEOSE)
+            .timeout(Some(timeout));
+        let opts = SubscribeOptions::default().close_on(Some(auto_close));
+        if let Err(e) = self.subscribe_with_id(id.clone(), vec![filter], opts).await {
+            self.remove_stream(&id).await;
+            return Err(e);
+        }
+
+        let mut events: Vec<Event> = Vec::new();
+        while let Some(item) = stream.next().await {
+            match item {
+                SubscriptionItem::Event { event, .. } => events.push(*event),
+                SubscriptionItem::Eose { .. } => break,
+                SubscriptionItem::Closed { message, .. } => {
+                    tracing::warn!("Page closed by {}: {message}", self.url);
+                    break;
+                }
+                SubscriptionItem::Lagged { skipped } => {
+                    self.remove_stream(&id).await;
+                    return Err(Error::Lagged(skipped));
+                }
+            }
+        }
+
+        Ok(events)
+    }
+
     pub async fn count_events_of(
         &self,
         filters: Vec<Filter>,
@@ -1479,12 +2450,12 @@ impl InternalRelay {
     ) -> Result<usize, Error> {
         let id = SubscriptionId::generate();
         let send_opts = RelaySendOptions::default().skip_send_confirmation(true);
+        let mut notifications = self.internal_notification_sender.subscribe();
         self.send_msg(ClientMessage::count(id.clone(), filters), send_opts)
             .await?;
 
         let mut count = 0;
 
-        let mut notifications = self.internal_notification_sender.subscribe();
         time::timeout(Some(timeout), async {
             while let Ok(notification) = notifications.recv().await {
                 if let RelayNotification::Message {
@@ -1547,11 +2518,12 @@ impl InternalRelay {
         let sub_id = SubscriptionId::generate();
         let send_opts = RelaySendOptions::default().skip_send_confirmation(true);
         let open_msg = ClientMessage::neg_open(&mut negentropy, &sub_id, filter)?;
-        self.send_msg(open_msg, send_opts).await?;
 
         let mut notifications = self.internal_notification_sender.subscribe();
         let mut temp_notifications = self.internal_notification_sender.subscribe();
 
+        self.send_msg(open_msg, send_opts).await?;
+
         // Check if negentropy it's supported
         time::timeout(Some(opts.initial_timeout), async {
             while let Ok(notification) = temp_notifications.recv().await {
@@ -1788,3 +2760,362 @@ impl InternalRelay {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Reverse;
+
+    use async_trait::async_trait;
+    use nostr::{EventBuilder, Kind};
+    use nostr_database::{MemoryDatabase, MemoryDatabaseOptions};
+
+    use super::*;
+    use crate::{mock, DuplexTransport, Relay, RelayPool, RelayPoolOptions};
+
+    /// Pool with a database storing the events
+    fn pool_with_database() -> RelayPool {
+        let database = MemoryDatabase::with_opts(MemoryDatabaseOptions {
+            events: true,
+            ..Default::default()
+        });
+        RelayPool::with_database(RelayPoolOptions::default(), database)
+    }
+
+    /// 25 events, with 3 events for each timestamp, sorted from the newest
+    fn paginated_events() -> Vec<Event> {
+        let keys = Keys::generate();
+        let mut events: Vec<Event> = (0..25)
+            .map(|i| {
+                EventBuilder::text_note(i.to_string(), [])
+                    .custom_created_at(Timestamp::from(1_000 + i / 3))
+                    .to_event(&keys)
+                    .unwrap()
+            })
+            .collect();
+        events.sort_by_key(|e| Reverse(e.created_at()));
+        events
+    }
+
+    /// Relay that honor `limit` and, if `honor_until`, `until`
+    async fn paginated_pool(honor_until: bool) -> RelayPool {
+        let events: Vec<Event> = paginated_events();
+        let transport = mock::spawn(move |_, msg| match msg {
+            ClientMessage::Req {
+                subscription_id,
+                filters,
+            } => {
+                let filter = &filters[0];
+                let until = filter.until.unwrap();
+                let limit = filter.limit.unwrap();
+                let mut msgs: Vec<RelayMessage> = events
+                    .iter()
+                    .filter(|e| !honor_until || e.created_at() <= until)
+                    .take(limit)
+                    .map(|e| RelayMessage::event(subscription_id.clone(), e.clone()))
+                    .collect();
+                msgs.push(RelayMessage::eose(subscription_id));
+                msgs
+            }
+            _ => Vec::new(),
+        });
+        let pool = pool_with_database();
+        mock::add_relays(&pool, transport, ["ws://relay.local"]).await;
+        pool
+    }
+
+    #[tokio::test]
+    async fn test_paginate() {
+        let pool = paginated_pool(true).await;
+
+        let filter = Filter::new().kind(Kind::TextNote);
+        let opts = PaginationOptions::new().page_size(4);
+        pool.paginate(vec![filter.clone()], opts).await.unwrap();
+
+        let count = pool.database().count(vec![filter]).await.unwrap();
+        assert_eq!(count, 25);
+    }
+
+    #[tokio::test]
+    async fn test_paginate_limit() {
+        let pool = paginated_pool(true).await;
+
+        // The limit of the filter is the total number of events
+        let filter = Filter::new().kind(Kind::TextNote).limit(10);
+        let opts = PaginationOptions::new().page_size(4);
+        pool.paginate(vec![filter], opts).await.unwrap();
+
+        let count = pool
+            .database()
+            .count(vec![Filter::new().kind(Kind::TextNote)])
+            .await
+            .unwrap();
+        assert_eq!(count, 10);
+    }
+
+    #[tokio::test]
+    async fn test_paginate_relay_ignoring_until() {
+        let pool = paginated_pool(false).await;
+
+        // Always the same page: must not loop forever
+        let filter = Filter::new().kind(Kind::TextNote);
+        let opts = PaginationOptions::new().page_size(4);
+        time::timeout(
+            Some(Duration::from_secs(5)),
+            pool.paginate(vec![filter.clone()], opts),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+        let count = pool.database().count(vec![filter]).await.unwrap();
+        assert_eq!(count, 4);
+    }
+
+    #[tokio::test]
+    async fn test_paginate_all_relays_failed() {
+        // No relay side: the connection always fails
+        let (transport, listener) = DuplexTransport::new();
+        drop(listener);
+
+        let pool = pool_with_database();
+        mock::add_relays(&pool, transport, ["ws://relay.local"]).await;
+
+        let filter = Filter::new().kind(Kind::TextNote);
+        let opts = PaginationOptions::new().timeout(Duration::from_secs(1));
+        assert!(pool.paginate(vec![filter], opts).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_dead_after_dropped_connections() {
+        let (transport, mut listener) = DuplexTransport::new();
+        let policy = ReconnectPolicy::new()
+            .initial_delay(Duration::from_millis(50))
+            .jitter(0.0)
+            .max_attempts(Some(2));
+        let opts = RelayOptions::new()
+            .transport(transport)
+            .reconnect_policy(policy);
+        let relay = Relay::with_opts(Url::parse("ws://relay.local").unwrap(), opts);
+        relay.connect(Some(Duration::from_secs(1))).await;
+
+        // The relay accepts the connection and immediately close it
+        time::timeout(Some(Duration::from_secs(5)), async {
+            while relay.status().await != RelayStatus::Dead {
+                if let Some(Some(connection)) =
+                    time::timeout(Some(Duration::from_millis(100)), listener.accept()).await
+                {
+                    drop(connection);
+                }
+            }
+        })
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_connectivity_changed_without_policy() {
+        let (transport, mut listener) = DuplexTransport::new();
+        let opts = RelayOptions::new().transport(transport).retry_sec(60);
+        let relay = Relay::with_opts(Url::parse("ws://relay.local").unwrap(), opts);
+        relay.connect(Some(Duration::from_secs(1))).await;
+
+        let connection = listener.accept().await.unwrap();
+        drop(connection);
+        while relay.status().await != RelayStatus::Disconnected {
+            thread::sleep(Duration::from_millis(10)).await;
+        }
+
+        // Reconnect now, not after the retry interval
+        relay.connectivity_changed(true).await;
+        let connection = time::timeout(Some(Duration::from_secs(2)), listener.accept())
+            .await
+            .unwrap();
+        assert!(connection.is_some());
+    }
+
+    #[derive(Debug)]
+    struct TestAuthenticator {
+        keys: Keys,
+        allow: bool,
+    }
+
+    #[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+    #[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+    impl RelayAuthenticator for TestAuthenticator {
+        async fn allow(&self, _relay_url: &Url) -> bool {
+            self.allow
+        }
+
+        async fn authenticate(
+            &self,
+            challenge: String,
+            relay_url: Url,
+        ) -> Result<Event, AuthenticationError> {
+            EventBuilder::auth(challenge, relay_url)
+                .to_event(&self.keys)
+                .map_err(AuthenticationError::signer)
+        }
+    }
+
+    /// Relay requiring authentication for `EVENT`s and `REQ`s, accepting the `AUTH` if `accept_auth`
+    async fn auth_relay(accept_auth: bool, allow_auth: bool) -> Relay {
+        let authenticated = Arc::new(AtomicBool::new(false));
+        let transport = mock::spawn(move |_, msg| match msg {
+            ClientMessage::Auth(event) => {
+                authenticated.store(accept_auth, Ordering::SeqCst);
+                let message = if accept_auth {
+                    ""
+                } else {
+                    "restricted: not allowed"
+                };
+                vec![RelayMessage::ok(event.id(), accept_auth, message)]
+            }
+            ClientMessage::Event(event) => {
+                if authenticated.load(Ordering::SeqCst) {
+                    vec![RelayMessage::ok(event.id(), true, "")]
+                } else {
+                    vec![
+                        RelayMessage::auth("challenge"),
+                        RelayMessage::ok(event.id(), false, "auth-required: sign in"),
+                    ]
+                }
+            }
+            ClientMessage::Req {
+                subscription_id, ..
+            } => {
+                if authenticated.load(Ordering::SeqCst) {
+                    vec![RelayMessage::eose(subscription_id)]
+                } else {
+                    vec![
+                        RelayMessage::auth("challenge"),
+                        RelayMessage::closed(subscription_id, "auth-required: sign in"),
+                    ]
+                }
+            }
+            _ => Vec::new(),
+        });
+
+        let opts = RelayOptions::new()
+            .transport(transport)
+            .automatic_authentication(true);
+        let relay = Relay::with_opts(Url::parse("ws://relay.local").unwrap(), opts);
+        relay.set_authenticator(Some(Arc::new(TestAuthenticator {
+            keys: Keys::generate(),
+            allow: allow_auth,
+        })));
+        relay.connect(Some(Duration::from_secs(1))).await;
+        relay
+    }
+
+    /// Publish a note, failing if waiting more than 2 secs (the send timeout is 10 secs)
+    async fn publish_note(relay: &Relay) -> PublishOutcome {
+        let event = EventBuilder::text_note("Hello", [])
+            .to_event(&Keys::generate())
+            .unwrap();
+        let opts = RelaySendOptions::new().timeout(Some(Duration::from_secs(10)));
+        let mut outcomes = time::timeout(
+            Some(Duration::from_secs(2)),
+            relay.publish(vec![event.clone()], opts),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        outcomes.remove(&event.id()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_event_sent_again_after_auth() {
+        let relay = auth_relay(true, true).await;
+        assert!(publish_note(&relay).await.is_accepted());
+        assert!(relay.is_authenticated());
+    }
+
+    #[tokio::test]
+    async fn test_event_auth_not_allowed() {
+        let relay = auth_relay(true, false).await;
+        match publish_note(&relay).await {
+            PublishOutcome::Rejected { message, .. } => {
+                assert_eq!(message, "auth-required: sign in")
+            }
+            outcome => panic!("Unexpected outcome: {outcome:?}"),
+        }
+        assert!(!relay.is_authenticated());
+    }
+
+    #[tokio::test]
+    async fn test_event_auth_rejected() {
+        let relay = auth_relay(false, true).await;
+        let outcome = publish_note(&relay).await;
+        assert!(matches!(outcome, PublishOutcome::Rejected { .. }));
+        assert!(!relay.is_authenticated());
+
+        // Authentication already failed: no wait
+        let outcome = publish_note(&relay).await;
+        assert!(matches!(outcome, PublishOutcome::Rejected { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_event_auth_disabled() {
+        let relay = auth_relay(true, true).await;
+        relay.opts().update_automatic_authentication(false);
+        let outcome = publish_note(&relay).await;
+        assert!(matches!(outcome, PublishOutcome::Rejected { .. }));
+        assert!(!relay.is_authenticated());
+    }
+
+    #[tokio::test]
+    async fn test_subscription_sent_again_after_auth() {
+        let relay = auth_relay(true, true).await;
+        let filter = Filter::new().kind(Kind::TextNote);
+        let events = relay
+            .get_events_of(
+                vec![filter],
+                Duration::from_secs(2),
+                FilterOptions::ExitOnEOSE,
+            )
+            .await
+            .unwrap();
+        assert!(events.is_empty());
+        assert!(relay.is_authenticated());
+    }
+
+    #[tokio::test]
+    async fn test_batch_event_timeout() {
+        // The relay never answers to the events without content
+        let transport = mock::spawn(|_, msg| match msg {
+            ClientMessage::Event(event) if !event.content().is_empty() => {
+                vec![RelayMessage::ok(event.id(), true, "")]
+            }
+            _ => Vec::new(),
+        });
+        let opts = RelayOptions::new().transport(transport);
+        let relay = Relay::with_opts(Url::parse("ws://relay.local").unwrap(), opts);
+        relay.connect(Some(Duration::from_secs(1))).await;
+
+        let keys = Keys::generate();
+        let published = EventBuilder::text_note("Hello", [])
+            .to_event(&keys)
+            .unwrap();
+        let timed_out = EventBuilder::text_note("", []).to_event(&keys).unwrap();
+        let opts = RelaySendOptions::new().timeout(Some(Duration::from_secs(1)));
+
+        // The outcome of the other events isn't lost
+        let res = relay
+            .batch_event(vec![published.clone(), timed_out.clone()], opts)
+            .await;
+        match res {
+            Err(Error::PartialPublish {
+                published: ids,
+                not_published,
+            }) => {
+                assert_eq!(ids, vec![published.id()]);
+                assert_eq!(not_published.len(), 1);
+                assert!(not_published.contains_key(&timed_out.id()));
+            }
+            res => panic!("Unexpected result: {res:?}"),
+        }
+
+        let res = relay.batch_event(vec![timed_out], opts).await;
+        assert!(matches!(res, Err(Error::Timeout)));
+    }
+}
@@ -0,0 +1,378 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Relay limitation (NIP-11)
+
+use std::cmp;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use nostr::nips::nip11::Limitation;
+use nostr::{ClientMessage, Filter, RawRelayMessage, SubscriptionId};
+
+/// Shape `REQ` and `CLOSE` messages to the relay [`Limitation`]
+///
+/// Filter limits are clamped to `max_limit`, subscriptions with more than `max_filters` filters are split in many `REQ`s
+/// and, when `max_subscriptions` is reached, `REQ`s are queued until a subscription is closed.
+#[derive(Debug, Default)]
+pub(crate) struct RequestShaper {
+    /// Subscriptions sent to relay
+    active: HashSet<SubscriptionId>,
+    /// `REQ`s waiting for a free subscription slot
+    queue: VecDeque<(SubscriptionId, Vec<Filter>)>,
+    /// Split subscriptions: subscription ID -> chunk IDs
+    chunks: HashMap<SubscriptionId, Vec<SubscriptionId>>,
+    /// Chunk ID -> subscription ID
+    parents: HashMap<SubscriptionId, SubscriptionId>,
+    /// Chunks that not sent `EOSE` yet
+    pending_eose: HashSet<SubscriptionId>,
+}
+
+impl RequestShaper {
+    /// Clear the state (new connection)
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Shape outgoing messages
+    pub fn outgoing(
+        &mut self,
+        limitation: &Limitation,
+        msgs: Vec<ClientMessage>,
+    ) -> Vec<ClientMessage> {
+        let max_subscriptions: Option<usize> = positive(limitation.max_subscriptions);
+        let max_filters: Option<usize> = positive(limitation.max_filters);
+        let max_limit: Option<usize> = positive(limitation.max_limit);
+
+        let mut out: Vec<ClientMessage> = Vec::with_capacity(msgs.len());
+
+        for msg in msgs.into_iter() {
+            match msg {
+                ClientMessage::Req {
+                    subscription_id,
+                    mut filters,
+                } => {
+                    // Clamp limits
+                    if let Some(max_limit) = max_limit {
+                        for filter in filters.iter_mut() {
+                            if let Some(limit) = filter.limit {
+                                filter.limit = Some(cmp::min(limit, max_limit));
+                            }
+                        }
+                    }
+
+                    // Close the chunks of the previous `REQ` with the same ID
+                    if let Some(ids) = self.chunks.remove(&subscription_id) {
+                        for id in ids.into_iter() {
+                            if id == subscription_id {
+                                self.parents.remove(&id);
+                                self.pending_eose.remove(&id);
+                            } else {
+                                self.close(id, &mut out);
+                            }
+                        }
+                    }
+
+                    match max_filters {
+                        Some(max_filters) if filters.len() > max_filters => {
+                            let mut ids: Vec<SubscriptionId> = Vec::new();
+                            for (index, chunk) in filters.chunks(max_filters).enumerate() {
+                                // The first chunk keep the subscription ID
+                                let id: SubscriptionId = if index == 0 {
+                                    subscription_id.clone()
+                                } else {
+                                    SubscriptionId::generate()
+                                };
+                                self.parents.insert(id.clone(), subscription_id.clone());
+                                self.pending_eose.insert(id.clone());
+                                ids.push(id.clone());
+                                self.req(id, chunk.to_vec(), max_subscriptions, &mut out);
+                            }
+                            self.chunks.insert(subscription_id, ids);
+                        }
+                        _ => self.req(subscription_id, filters, max_subscriptions, &mut out),
+                    }
+                }
+                ClientMessage::Close(subscription_id) => {
+                    let ids: Vec<SubscriptionId> = self
+                        .chunks
+                        .remove(&subscription_id)
+                        .unwrap_or_else(|| vec![subscription_id]);
+                    for id in ids.into_iter() {
+                        self.close(id, &mut out);
+                    }
+                    self.dequeue(max_subscriptions, &mut out);
+                }
+                msg => out.push(msg),
+            }
+        }
+
+        out
+    }
+
+    /// Shape incoming message
+    ///
+    /// Return the message with the chunk IDs replaced by the subscription ID (`None` if must be ignored)
+    /// and the messages to send to relay (ex. queued `REQ`s).
+    pub fn incoming(
+        &mut self,
+        limitation: &Limitation,
+        msg: RawRelayMessage,
+    ) -> (Option<RawRelayMessage>, Vec<ClientMessage>) {
+        let mut out: Vec<ClientMessage> = Vec::new();
+
+        let msg: Option<RawRelayMessage> = match msg {
+            RawRelayMessage::Event {
+                subscription_id,
+                event,
+            } => {
+                let id = SubscriptionId::new(&subscription_id);
+                let subscription_id: String = match self.parents.get(&id) {
+                    Some(parent) => parent.to_string(),
+                    None => subscription_id,
+                };
+                Some(RawRelayMessage::Event {
+                    subscription_id,
+                    event,
+                })
+            }
+            RawRelayMessage::EndOfStoredEvents(subscription_id) => {
+                let id = SubscriptionId::new(&subscription_id);
+                match self.parents.get(&id).cloned() {
+                    Some(parent) => {
+                        self.pending_eose.remove(&id);
+
+                        // Wait for the `EOSE` of all the chunks
+                        let completed: bool = match self.chunks.get(&parent) {
+                            Some(ids) => ids.iter().all(|id| !self.pending_eose.contains(id)),
+                            None => true,
+                        };
+                        if completed {
+                            Some(RawRelayMessage::EndOfStoredEvents(parent.to_string()))
+                        } else {
+                            None
+                        }
+                    }
+                    None => Some(RawRelayMessage::EndOfStoredEvents(subscription_id)),
+                }
+            }
+            RawRelayMessage::Closed {
+                subscription_id,
+                message,
+            } => {
+                let id = SubscriptionId::new(&subscription_id);
+                self.active.remove(&id);
+
+                let subscription_id: String = match self.parents.remove(&id) {
+                    Some(parent) => {
+                        self.pending_eose.remove(&id);
+
+                        // A closed chunk close the whole subscription
+                        if let Some(ids) = self.chunks.remove(&parent) {
+                            for chunk in ids.into_iter().filter(|chunk| chunk != &id) {
+                                self.close(chunk, &mut out);
+                            }
+                        }
+
+                        parent.to_string()
+                    }
+                    None => subscription_id,
+                };
+
+                self.dequeue(positive(limitation.max_subscriptions), &mut out);
+
+                Some(RawRelayMessage::Closed {
+                    subscription_id,
+                    message,
+                })
+            }
+            msg => Some(msg),
+        };
+
+        (msg, out)
+    }
+
+    /// Send `REQ` or, if max subscriptions reached, queue it
+    fn req(
+        &mut self,
+        id: SubscriptionId,
+        filters: Vec<Filter>,
+        max_subscriptions: Option<usize>,
+        out: &mut Vec<ClientMessage>,
+    ) {
+        self.queue.retain(|(queued, ..)| queued != &id);
+
+        let full: bool = match max_subscriptions {
+            Some(max) => !self.active.contains(&id) && self.active.len() >= max,
+            None => false,
+        };
+
+        if full {
+            tracing::debug!("Max subscriptions reached: REQ {id} queued");
+            self.queue.push_back((id, filters));
+        } else {
+            self.active.insert(id.clone());
+            out.push(ClientMessage::req(id, filters));
+        }
+    }
+
+    /// Send `CLOSE` or, if queued, remove the `REQ` from queue
+    fn close(&mut self, id: SubscriptionId, out: &mut Vec<ClientMessage>) {
+        self.parents.remove(&id);
+        self.pending_eose.remove(&id);
+
+        let len: usize = self.queue.len();
+        self.queue.retain(|(queued, ..)| queued != &id);
+
+        // Queued `REQ` never sent to relay
+        if self.queue.len() == len {
+            self.active.remove(&id);
+            out.push(ClientMessage::close(id));
+        }
+    }
+
+    /// Send the queued `REQ`s until max subscriptions is reached
+    fn dequeue(&mut self, max_subscriptions: Option<usize>, out: &mut Vec<ClientMessage>) {
+        loop {
+            if let Some(max) = max_subscriptions {
+                if self.active.len() >= max {
+                    break;
+                }
+            }
+
+            match self.queue.pop_front() {
+                Some((id, filters)) => {
+                    tracing::debug!("Sending queued REQ {id}");
+                    self.active.insert(id.clone());
+                    out.push(ClientMessage::req(id, filters));
+                }
+                None => break,
+            }
+        }
+    }
+}
+
+pub(super) fn positive(value: Option<i32>) -> Option<usize> {
+    value
+        .and_then(|value| usize::try_from(value).ok())
+        .filter(|value| *value > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::{Kind, Timestamp};
+
+    use super::*;
+
+    fn limitation() -> Limitation {
+        Limitation {
+            max_subscriptions: Some(2),
+            max_filters: Some(2),
+            max_limit: Some(100),
+            ..Default::default()
+        }
+    }
+
+    fn reqs(msgs: &[ClientMessage]) -> Vec<(SubscriptionId, Vec<Filter>)> {
+        msgs.iter()
+            .filter_map(|msg| match msg {
+                ClientMessage::Req {
+                    subscription_id,
+                    filters,
+                } => Some((subscription_id.clone(), filters.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_clamp_limit() {
+        let mut shaper = RequestShaper::default();
+        let id = SubscriptionId::new("test");
+        let filters = vec![
+            Filter::new().kind(Kind::TextNote).limit(1000),
+            Filter::new().kind(Kind::Metadata),
+        ];
+        let out = shaper.outgoing(&limitation(), vec![ClientMessage::req(id.clone(), filters)]);
+        let reqs = reqs(&out);
+        assert_eq!(reqs.len(), 1);
+        assert_eq!(reqs[0].0, id);
+        assert_eq!(reqs[0].1[0].limit, Some(100));
+        assert_eq!(reqs[0].1[1].limit, None);
+    }
+
+    #[test]
+    fn test_split_filters() {
+        let mut shaper = RequestShaper::default();
+        let limitation = Limitation {
+            max_filters: Some(2),
+            ..Default::default()
+        };
+        let id = SubscriptionId::new("test");
+        let filters: Vec<Filter> = (0..5)
+            .map(|i| Filter::new().since(Timestamp::from(i)))
+            .collect();
+        let out = shaper.outgoing(&limitation, vec![ClientMessage::req(id.clone(), filters)]);
+        let reqs = reqs(&out);
+        assert_eq!(reqs.len(), 3);
+        assert_eq!(reqs[0].0, id);
+        assert!(reqs.iter().all(|(_, filters)| filters.len() <= 2));
+
+        // EOSE only when all the chunks sent it
+        for (index, (chunk, ..)) in reqs.iter().enumerate() {
+            let (msg, _) = shaper.incoming(
+                &limitation,
+                RawRelayMessage::EndOfStoredEvents(chunk.to_string()),
+            );
+            if index < reqs.len() - 1 {
+                assert!(msg.is_none());
+            } else {
+                assert_eq!(
+                    msg,
+                    Some(RawRelayMessage::EndOfStoredEvents(id.to_string()))
+                );
+            }
+        }
+
+        // CLOSE all the chunks
+        let out = shaper.outgoing(&limitation, vec![ClientMessage::close(id)]);
+        assert_eq!(out.len(), 3);
+        assert!(out.iter().all(|msg| msg.is_close()));
+    }
+
+    #[test]
+    fn test_queue_subscriptions() {
+        let mut shaper = RequestShaper::default();
+        let limitation = limitation();
+        let msgs: Vec<ClientMessage> = (0..3)
+            .map(|i| ClientMessage::req(SubscriptionId::new(i.to_string()), vec![Filter::new()]))
+            .collect();
+        let out = shaper.outgoing(&limitation, msgs);
+        assert_eq!(reqs(&out).len(), 2);
+
+        // Closed by relay: send the queued REQ
+        let (msg, out) = shaper.incoming(
+            &limitation,
+            RawRelayMessage::Closed {
+                subscription_id: String::from("0"),
+                message: String::from("error: shutting down"),
+            },
+        );
+        assert!(msg.is_some());
+        let reqs = reqs(&out);
+        assert_eq!(reqs.len(), 1);
+        assert_eq!(reqs[0].0, SubscriptionId::new("2"));
+
+        // Queued REQ is removed without sending CLOSE
+        let msgs = vec![ClientMessage::req(
+            SubscriptionId::new("3"),
+            vec![Filter::new()],
+        )];
+        assert!(shaper.outgoing(&limitation, msgs).is_empty());
+        let out = shaper.outgoing(
+            &limitation,
+            vec![ClientMessage::close(SubscriptionId::new("3"))],
+        );
+        assert!(out.is_empty());
+    }
+}
@@ -8,7 +8,7 @@ use std::cmp;
 use std::collections::HashMap;
 #[cfg(not(target_arch = "wasm32"))]
 use std::net::SocketAddr;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
 
 use async_wsocket::futures_util::Future;
@@ -19,23 +19,43 @@ use nostr::{ClientMessage, Event, EventId, Filter, RelayMessage, SubscriptionId,
 use nostr_database::{DynNostrDatabase, MemoryDatabase};
 use tokio::sync::broadcast;
 
+pub mod auth;
 pub mod flags;
 mod internal;
+#[cfg(feature = "nip11")]
+mod limitation;
 pub mod limits;
 pub mod options;
+pub mod publish;
+mod queue;
+#[cfg(not(target_arch = "wasm32"))]
+mod ratelimit;
+pub mod score;
 pub mod stats;
 mod status;
+pub mod stream;
+pub mod transport;
 
+use self::auth::SharedAuthenticator;
+pub use self::auth::{AuthenticationError, RelayAuthenticator};
 pub use self::flags::{AtomicRelayServiceFlags, RelayServiceFlags};
 pub use self::internal::Error;
 use self::internal::InternalRelay;
 pub use self::limits::RelayLimits;
 pub use self::options::{
-    FilterOptions, NegentropyDirection, NegentropyOptions, RelayOptions, RelaySendOptions,
-    SubscribeAutoCloseOptions, SubscribeOptions,
+    FilterOptions, NegentropyDirection, NegentropyOptions, PaginationOptions, RateLimit,
+    ReconnectPolicy, RelayOptions, RelaySendOptions, SubscribeAutoCloseOptions, SubscribeOptions,
 };
+pub use self::publish::PublishOutcome;
+pub use self::score::RelayScore;
 pub use self::stats::RelayConnectionStats;
 pub use self::status::RelayStatus;
+use self::stream::SubscriptionSink;
+pub use self::stream::{EventStream, SubscriptionItem, SubscriptionStream};
+pub use self::transport::{
+    DefaultWebSocketTransport, DuplexConnection, DuplexListener, DuplexTransport, TransportError,
+    WebSocketTransport,
+};
 use crate::pool::RelayPoolNotification;
 
 /// Relay Notification
@@ -58,6 +78,13 @@ pub enum RelayNotification {
         /// Relay Status
         status: RelayStatus,
     },
+    /// Authenticated to relay (NIP-42)
+    Authenticated,
+    /// Authentication to relay failed or not allowed (NIP-42)
+    AuthenticationFailed {
+        /// Error message
+        message: String,
+    },
     /// Stop
     Stop,
     /// Shutdown
@@ -104,8 +131,17 @@ impl Relay {
 
     /// Create new `Relay` with **custom** `options` and/or `database`
     pub fn custom(url: Url, database: Arc<DynNostrDatabase>, opts: RelayOptions) -> Self {
+        Self::with_authenticator(url, database, opts, Arc::new(RwLock::new(None)))
+    }
+
+    pub(crate) fn with_authenticator(
+        url: Url,
+        database: Arc<DynNostrDatabase>,
+        opts: RelayOptions,
+        authenticator: SharedAuthenticator,
+    ) -> Self {
         Self {
-            inner: AtomicDestructor::new(InternalRelay::new(url, database, opts)),
+            inner: AtomicDestructor::new(InternalRelay::new(url, database, opts, authenticator)),
         }
     }
 
@@ -135,6 +171,18 @@ impl Relay {
         self.inner.is_connected().await
     }
 
+    /// Check if the client is authenticated to the relay (NIP-42)
+    pub fn is_authenticated(&self) -> bool {
+        self.inner.is_authenticated()
+    }
+
+    /// Set the [`RelayAuthenticator`] used for automatic authentication
+    ///
+    /// If the relay is part of a pool, the authenticator is shared with all the relays of the pool.
+    pub fn set_authenticator(&self, authenticator: Option<Arc<dyn RelayAuthenticator>>) {
+        self.inner.set_authenticator(authenticator)
+    }
+
     /// Get [`RelayInformationDocument`]
     #[cfg(feature = "nip11")]
     pub async fn document(&self) -> RelayInformationDocument {
@@ -161,6 +209,11 @@ impl Relay {
         self.inner.stats()
     }
 
+    /// Get [`RelayScore`]
+    pub async fn score(&self) -> RelayScore {
+        RelayScore::new(&self.inner.stats()).await
+    }
+
     /// Get queue len
     pub fn queue(&self) -> usize {
         self.inner.queue()
@@ -186,6 +239,15 @@ impl Relay {
         self.inner.connect(connection_timeout).await
     }
 
+    /// Handle a network connectivity change signaled by the app
+    ///
+    /// When offline, the connection attempts are paused.
+    /// When back online, the reconnection backoff is reset, the relay reconnects after a random delay
+    /// (see [`ReconnectPolicy`]) and, if [`RelayStatus::Dead`], is revived.
+    pub async fn connectivity_changed(&self, online: bool) {
+        self.inner.connectivity_changed(online).await
+    }
+
     /// Disconnect from relay and set status to 'Stopped'
     pub async fn stop(&self) -> Result<(), Error> {
         self.inner.stop().await
@@ -224,6 +286,17 @@ impl Relay {
         self.inner.batch_event(events, opts).await
     }
 
+    /// Send multiple [`Event`] at once and wait for the `OK` messages
+    ///
+    /// Return the [`PublishOutcome`] of every event (accepted, rejected, timeout, ...).
+    pub async fn publish(
+        &self,
+        events: Vec<Event>,
+        opts: RelaySendOptions,
+    ) -> Result<HashMap<EventId, PublishOutcome>, Error> {
+        self.inner.publish(events, opts).await
+    }
+
     /// Subscribe to filters
     ///
     /// Internally generate a new random [SubscriptionId]. Check `subscribe_with_id` method to use a custom [SubscriptionId].
@@ -257,6 +330,38 @@ impl Relay {
         self.inner.subscribe_with_id(id, filters, opts).await
     }
 
+    /// Subscribe and get the [`SubscriptionStream`] of the subscription
+    ///
+    /// The stream ends when the subscription is closed (unsubscribed, auto-closed or closed by relay).
+    pub async fn subscribe_stream(
+        &self,
+        filters: Vec<Filter>,
+        opts: SubscribeOptions,
+    ) -> Result<SubscriptionStream, Error> {
+        self.subscribe_stream_with_id(SubscriptionId::generate(), filters, opts)
+            .await
+    }
+
+    /// Subscribe with custom [SubscriptionId] and get the [`SubscriptionStream`] of the subscription
+    pub async fn subscribe_stream_with_id(
+        &self,
+        id: SubscriptionId,
+        filters: Vec<Filter>,
+        opts: SubscribeOptions,
+    ) -> Result<SubscriptionStream, Error> {
+        let (sink, stream) = SubscriptionSink::new(id.clone());
+        self.inner.add_stream(id.clone(), sink).await;
+        if let Err(e) = self
+            .inner
+            .subscribe_with_id(id.clone(), filters, opts)
+            .await
+        {
+            self.inner.remove_stream(&id).await;
+            return Err(e);
+        }
+        Ok(stream)
+    }
+
     /// Unsubscribe
     pub async fn unsubscribe(
         &self,
@@ -274,6 +379,7 @@ impl Relay {
     /// Get events of filters with custom callback
     pub(crate) async fn get_events_of_with_callback<F>(
         &self,
+        id: SubscriptionId,
         filters: Vec<Filter>,
         timeout: Duration,
         opts: FilterOptions,
@@ -283,7 +389,7 @@ impl Relay {
         F: Future<Output = ()>,
     {
         self.inner
-            .get_events_of_with_callback(filters, timeout, opts, callback)
+            .get_events_of_with_callback(id, filters, timeout, opts, callback)
             .await
     }
 
@@ -308,6 +414,20 @@ impl Relay {
         self.inner.count_events_of(filters, timeout).await
     }
 
+    /// Get all the events of filters, page by page
+    ///
+    /// Every page move `until` back to the oldest event received, until the relay return nothing new.
+    /// The `limit` of filters is replaced by the page size.
+    ///
+    /// Events are saved into the database.
+    pub async fn paginate(
+        &self,
+        filters: Vec<Filter>,
+        opts: PaginationOptions,
+    ) -> Result<(), Error> {
+        self.inner.paginate(filters, opts).await
+    }
+
     /// Negentropy reconciliation
     ///
     /// Use events stored in database
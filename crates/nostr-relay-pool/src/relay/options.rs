@@ -10,7 +10,10 @@ use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
+use nostr::secp256k1::rand::Rng;
+
 use super::flags::{AtomicRelayServiceFlags, RelayServiceFlags};
+use super::transport::WebSocketTransport;
 use crate::RelayLimits;
 
 /// Default send timeout
@@ -32,7 +35,17 @@ pub struct RelayOptions {
     reconnect: Arc<AtomicBool>,
     retry_sec: Arc<AtomicU64>,
     adjust_retry_sec: Arc<AtomicBool>,
+    pub(super) reconnect_policy: Option<ReconnectPolicy>,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(super) req_rate_limit: Option<RateLimit>,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(super) event_rate_limit: Option<RateLimit>,
+    automatic_authentication: Arc<AtomicBool>,
+    inherit_subscriptions: bool,
+    #[cfg(feature = "nip11")]
+    paid: Arc<AtomicBool>,
     pub(super) limits: RelayLimits,
+    pub(super) transport: Option<Arc<dyn WebSocketTransport>>,
 }
 
 impl Default for RelayOptions {
@@ -45,7 +58,17 @@ impl Default for RelayOptions {
             reconnect: Arc::new(AtomicBool::new(true)),
             retry_sec: Arc::new(AtomicU64::new(DEFAULT_RETRY_SEC)),
             adjust_retry_sec: Arc::new(AtomicBool::new(true)),
+            reconnect_policy: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            req_rate_limit: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            event_rate_limit: None,
+            automatic_authentication: Arc::new(AtomicBool::new(false)),
+            inherit_subscriptions: true,
+            #[cfg(feature = "nip11")]
+            paid: Arc::new(AtomicBool::new(false)),
             limits: RelayLimits::default(),
+            transport: None,
         }
     }
 }
@@ -57,12 +80,23 @@ impl RelayOptions {
     }
 
     /// Set proxy
+    ///
+    /// Ignored if a custom [`WebSocketTransport`] is set.
     #[cfg(not(target_arch = "wasm32"))]
     pub fn proxy(mut self, proxy: Option<SocketAddr>) -> Self {
         self.proxy = proxy;
         self
     }
 
+    /// Set custom WebSocket transport (default: [`DefaultWebSocketTransport`](super::transport::DefaultWebSocketTransport))
+    pub fn transport<T>(mut self, transport: T) -> Self
+    where
+        T: WebSocketTransport + 'static,
+    {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
     /// Set Relay Service Flags
     pub fn flags(mut self, flags: RelayServiceFlags) -> Self {
         self.flags = AtomicRelayServiceFlags::new(flags);
@@ -177,6 +211,83 @@ impl RelayOptions {
             .store(adjust_retry_sec, Ordering::SeqCst);
     }
 
+    /// Set reconnection policy (default: none)
+    ///
+    /// If set, replace `retry_sec` and `adjust_retry_sec`.
+    pub fn reconnect_policy(mut self, policy: ReconnectPolicy) -> Self {
+        self.reconnect_policy = Some(policy);
+        self
+    }
+
+    /// Limit the rate of `REQ`, `CLOSE`, `COUNT` and negentropy messages (default: unlimited)
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn req_rate_limit(mut self, limit: Option<RateLimit>) -> Self {
+        self.req_rate_limit = limit;
+        self
+    }
+
+    /// Limit the rate of `EVENT` messages (default: unlimited)
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn event_rate_limit(mut self, limit: Option<RateLimit>) -> Self {
+        self.event_rate_limit = limit;
+        self
+    }
+
+    /// Automatically authenticate to relay when an `AUTH` challenge is received (default: false)
+    ///
+    /// Requires a [`RelayAuthenticator`](super::auth::RelayAuthenticator).
+    /// `REQ` and `EVENT` messages rejected with `auth-required` are sent again after the authentication.
+    pub fn automatic_authentication(self, enable: bool) -> Self {
+        Self {
+            automatic_authentication: Arc::new(AtomicBool::new(enable)),
+            ..self
+        }
+    }
+
+    pub(crate) fn get_automatic_authentication(&self) -> bool {
+        self.automatic_authentication.load(Ordering::SeqCst)
+    }
+
+    /// Set automatic_authentication option
+    pub fn update_automatic_authentication(&self, enable: bool) {
+        self.automatic_authentication
+            .store(enable, Ordering::SeqCst);
+    }
+
+    /// Inherit the pool subscriptions when connected by the pool (default: true)
+    ///
+    /// The relay subscribes to the subscriptions for all the relays and to the ones targeted to its URL.
+    pub fn inherit_subscriptions(mut self, inherit: bool) -> Self {
+        self.inherit_subscriptions = inherit;
+        self
+    }
+
+    pub(crate) fn get_inherit_subscriptions(&self) -> bool {
+        self.inherit_subscriptions
+    }
+
+    /// Mark the relay as paid (default: false)
+    ///
+    /// If the relay information document (NIP-11) states that payment is required, events are refused unless the relay is marked as paid.
+    #[cfg(feature = "nip11")]
+    pub fn paid(self, paid: bool) -> Self {
+        Self {
+            paid: Arc::new(AtomicBool::new(paid)),
+            ..self
+        }
+    }
+
+    #[cfg(feature = "nip11")]
+    pub(crate) fn get_paid(&self) -> bool {
+        self.paid.load(Ordering::SeqCst)
+    }
+
+    /// Set paid option
+    #[cfg(feature = "nip11")]
+    pub fn update_paid(&self, paid: bool) {
+        self.paid.store(paid, Ordering::SeqCst);
+    }
+
     /// Set custom limits
     pub fn limits(mut self, limits: RelayLimits) -> Self {
         self.limits = limits;
@@ -184,6 +295,124 @@ impl RelayOptions {
     }
 }
 
+/// Reconnection policy
+///
+/// The delay between connection attempts grows exponentially with the consecutive failures,
+/// with a random jitter to not reconnect all the relays at the same time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReconnectPolicy {
+    pub(super) initial_delay: Duration,
+    pub(super) max_delay: Duration,
+    pub(super) multiplier: f64,
+    pub(super) jitter: f64,
+    pub(super) max_attempts: Option<usize>,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_secs(DEFAULT_RETRY_SEC),
+            max_delay: Duration::from_secs(600),
+            multiplier: 2.0,
+            jitter: 0.3,
+            max_attempts: None,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// New default policy
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Delay after the first failure (default: 10 secs)
+    pub fn initial_delay(mut self, delay: Duration) -> Self {
+        self.initial_delay = delay;
+        self
+    }
+
+    /// Max delay (default: 10 min)
+    pub fn max_delay(mut self, delay: Duration) -> Self {
+        self.max_delay = delay;
+        self
+    }
+
+    /// Delay multiplier at every consecutive failure (default: 2.0)
+    pub fn multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier.max(1.0);
+        self
+    }
+
+    /// Random jitter, as fraction of the delay (default: 0.3)
+    ///
+    /// Are allowed values between `0.0` and `1.0`.
+    pub fn jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Max consecutive failed attempts before marking the relay as [`RelayStatus::Dead`](super::RelayStatus::Dead) (default: unlimited)
+    pub fn max_attempts(mut self, max_attempts: Option<usize>) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Delay before the next attempt, without jitter
+    pub(super) fn delay(&self, failures: usize) -> Duration {
+        let exp: i32 = i32::try_from(failures).unwrap_or(i32::MAX);
+        let delay: f64 = self.initial_delay.as_secs_f64() * self.multiplier.powi(exp);
+        if delay.is_finite() && delay < self.max_delay.as_secs_f64() {
+            Duration::from_secs_f64(delay)
+        } else {
+            self.max_delay
+        }
+    }
+
+    /// Random delay before reconnecting after a connectivity change
+    pub(super) fn wake_up_delay<R>(&self, rng: &mut R) -> Duration
+    where
+        R: Rng,
+    {
+        let factor: f64 = rng.gen_range(0.0..=self.jitter);
+        self.initial_delay.mul_f64(factor)
+    }
+
+    /// Apply random jitter to the delay
+    pub(super) fn apply_jitter<R>(&self, delay: Duration, rng: &mut R) -> Duration
+    where
+        R: Rng,
+    {
+        if self.jitter > 0.0 {
+            let factor: f64 = rng.gen_range(-self.jitter..=self.jitter);
+            delay.mul_f64(1.0 + factor)
+        } else {
+            delay
+        }
+    }
+}
+
+/// Token bucket rate limit
+///
+/// Every message takes a token, the bucket is refilled at `per_second` tokens per second up to `burst` tokens.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub(super) burst: u32,
+    pub(super) per_second: f64,
+}
+
+impl RateLimit {
+    /// New rate limit
+    ///
+    /// `burst` is at least `1` and `per_second` at least `0.001`.
+    pub fn new(burst: u32, per_second: f64) -> Self {
+        Self {
+            burst: burst.max(1),
+            per_second: per_second.max(0.001),
+        }
+    }
+}
+
 /// [`Relay`](super::Relay) send options
 #[derive(Debug, Clone, Copy)]
 pub struct RelaySendOptions {
@@ -308,6 +537,43 @@ impl NegentropyDirection {
     }
 }
 
+/// Pagination options
+#[derive(Debug, Clone, Copy)]
+pub struct PaginationOptions {
+    pub(super) page_size: usize,
+    pub(super) timeout: Duration,
+}
+
+impl Default for PaginationOptions {
+    fn default() -> Self {
+        Self {
+            page_size: 500,
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+impl PaginationOptions {
+    /// New default [`PaginationOptions`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Max number of events requested for each page (default: 500)
+    ///
+    /// Capped by the `max_limit` of the relay information document (NIP-11).
+    pub fn page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size;
+        self
+    }
+
+    /// Timeout for each page (default: 10 secs)
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
 /// Negentropy reconciliation options
 #[derive(Debug, Clone, Copy)]
 pub struct NegentropyOptions {
@@ -344,3 +610,30 @@ impl NegentropyOptions {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use nostr::secp256k1::rand;
+
+    use super::*;
+
+    #[test]
+    fn test_reconnect_policy_delay() {
+        let policy = ReconnectPolicy::new()
+            .initial_delay(Duration::from_secs(1))
+            .max_delay(Duration::from_secs(60))
+            .jitter(0.5);
+
+        assert_eq!(policy.delay(0), Duration::from_secs(1));
+        assert_eq!(policy.delay(3), Duration::from_secs(8));
+        assert_eq!(policy.delay(10), Duration::from_secs(60));
+        assert_eq!(policy.delay(usize::MAX), Duration::from_secs(60));
+
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            let delay = policy.apply_jitter(Duration::from_secs(10), &mut rng);
+            assert!(delay >= Duration::from_secs(5));
+            assert!(delay <= Duration::from_secs(15));
+        }
+    }
+}
@@ -0,0 +1,68 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Publish outcome
+
+use nostr::message::relay::MachineReadablePrefix;
+
+/// Outcome of publishing an event to a relay
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PublishOutcome {
+    /// Accepted by relay
+    Accepted {
+        /// Machine-readable prefix (ex. `duplicate`)
+        reason: Option<MachineReadablePrefix>,
+        /// `OK` message
+        message: String,
+    },
+    /// Rejected by relay
+    Rejected {
+        /// Machine-readable prefix (ex. `blocked`, `pow`, `auth-required`)
+        reason: Option<MachineReadablePrefix>,
+        /// `OK` message
+        message: String,
+    },
+    /// `OK` message not received in time
+    Timeout,
+    /// Event not sent or relay disconnected before the `OK` message
+    Failed(String),
+}
+
+impl PublishOutcome {
+    /// Compose outcome from `OK` message
+    pub fn from_ok<S>(status: bool, message: S) -> Self
+    where
+        S: Into<String>,
+    {
+        let message: String = message.into();
+        let reason: Option<MachineReadablePrefix> = MachineReadablePrefix::parse(&message);
+        if status {
+            Self::Accepted { reason, message }
+        } else {
+            Self::Rejected { reason, message }
+        }
+    }
+
+    /// Check if the event has been accepted by relay
+    pub fn is_accepted(&self) -> bool {
+        matches!(self, Self::Accepted { .. })
+    }
+
+    /// Machine-readable prefix of the `OK` message
+    pub fn reason(&self) -> Option<&MachineReadablePrefix> {
+        match self {
+            Self::Accepted { reason, .. } | Self::Rejected { reason, .. } => reason.as_ref(),
+            Self::Timeout | Self::Failed(..) => None,
+        }
+    }
+
+    /// Message (`OK` message or error)
+    pub fn message(&self) -> Option<&str> {
+        match self {
+            Self::Accepted { message, .. } | Self::Rejected { message, .. } => Some(message),
+            Self::Failed(error) => Some(error),
+            Self::Timeout => None,
+        }
+    }
+}
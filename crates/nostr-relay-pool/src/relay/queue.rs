@@ -0,0 +1,348 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Relay send queue
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Duration;
+
+use nostr::ClientMessage;
+use tokio::sync::{Mutex as AsyncMutex, MutexGuard, Notify};
+
+/// Send priority
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Priority {
+    /// Control messages (ping, close, `AUTH`, ...)
+    Control = 0,
+    /// `REQ`, `CLOSE`, `COUNT` and negentropy messages
+    Req = 1,
+    /// `EVENT`
+    Event = 2,
+}
+
+impl Priority {
+    /// Priority of a message
+    pub fn from_msg(msg: &ClientMessage) -> Self {
+        match msg {
+            ClientMessage::Auth(..) => Self::Control,
+            ClientMessage::Event(..) => Self::Event,
+            _ => Self::Req,
+        }
+    }
+}
+
+/// Priority-aware send queue
+///
+/// Items are received by priority and, with the same priority, in FIFO order.
+#[derive(Debug)]
+pub(crate) struct SendQueue<T> {
+    queues: Mutex<[VecDeque<T>; 3]>,
+    capacity: usize,
+    notify: Notify,
+    consumer: AsyncMutex<()>,
+}
+
+impl<T> SendQueue<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            queues: Mutex::new([VecDeque::new(), VecDeque::new(), VecDeque::new()]),
+            capacity,
+            notify: Notify::new(),
+            consumer: AsyncMutex::new(()),
+        }
+    }
+
+    fn with_queues<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut [VecDeque<T>; 3]) -> R,
+    {
+        match self.queues.lock() {
+            Ok(mut queues) => f(&mut queues),
+            Err(e) => f(&mut e.into_inner()),
+        }
+    }
+
+    /// Number of queued items
+    pub fn len(&self) -> usize {
+        self.with_queues(|queues| queues.iter().map(|queue| queue.len()).sum())
+    }
+
+    /// Max number of queued items
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Push item at the end of its priority queue
+    ///
+    /// Return the item back if the queue is full.
+    pub fn push(&self, priority: Priority, item: T) -> Result<(), T> {
+        self.with_queues(|queues| {
+            let len: usize = queues.iter().map(|queue| queue.len()).sum();
+            if len >= self.capacity {
+                return Err(item);
+            }
+            queues[priority as usize].push_back(item);
+            Ok(())
+        })?;
+        self.notify.notify_one();
+        Ok(())
+    }
+
+    /// Push back item at the front of its priority queue (ex. partially sent batch)
+    ///
+    /// Used by the consumer, so it doesn't notify.
+    pub fn push_front(&self, priority: Priority, item: T) {
+        self.with_queues(|queues| queues[priority as usize].push_front(item));
+    }
+
+    /// Take the item with the highest priority, if any
+    #[cfg(any(target_arch = "wasm32", test))]
+    pub fn pop(&self) -> Option<(Priority, T)> {
+        self.with_queues(|queues| {
+            for (priority, queue) in [Priority::Control, Priority::Req, Priority::Event]
+                .into_iter()
+                .zip(queues.iter_mut())
+            {
+                if let Some(item) = queue.pop_front() {
+                    return Some((priority, item));
+                }
+            }
+            None
+        })
+    }
+
+    /// Take the item with the highest priority, skipping the priorities not available now
+    ///
+    /// If no item can be taken, return how long to wait for the first available priority (`None` if the queue is empty).
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn pop_available<F>(&self, mut available: F) -> Result<(Priority, T), Option<Duration>>
+    where
+        F: FnMut(Priority) -> Result<(), Duration>,
+    {
+        self.with_queues(|queues| {
+            let mut wait: Option<Duration> = None;
+            for (priority, queue) in [Priority::Control, Priority::Req, Priority::Event]
+                .into_iter()
+                .zip(queues.iter_mut())
+            {
+                if queue.is_empty() {
+                    continue;
+                }
+
+                match available(priority) {
+                    Ok(()) => {
+                        if let Some(item) = queue.pop_front() {
+                            return Ok((priority, item));
+                        }
+                    }
+                    Err(w) => wait = Some(wait.map_or(w, |wait| wait.min(w))),
+                }
+            }
+            Err(wait)
+        })
+    }
+
+    /// Wait for the item with the highest priority
+    #[cfg(any(target_arch = "wasm32", test))]
+    pub async fn recv(&self) -> (Priority, T) {
+        loop {
+            if let Some(item) = self.pop() {
+                return item;
+            }
+            self.notify.notified().await;
+        }
+    }
+
+    /// Wait for a new item
+    pub async fn notified(&self) {
+        self.notify.notified().await
+    }
+
+    /// Lock the queue consumer (only one consumer at a time)
+    pub async fn consumer(&self) -> MutexGuard<'_, ()> {
+        self.consumer.lock().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use nostr::{Event, EventBuilder, Filter, Keys, RelayMessage, SubscriptionId};
+
+    use super::*;
+    use crate::relay::PublishOutcome;
+    use crate::{DuplexTransport, RateLimit, Relay, RelayOptions, RelaySendOptions};
+
+    #[test]
+    fn test_priority() {
+        let keys = Keys::generate();
+        let event = EventBuilder::text_note("Hello", [])
+            .to_event(&keys)
+            .unwrap();
+        let req = ClientMessage::req(SubscriptionId::generate(), vec![Filter::new()]);
+
+        assert_eq!(
+            Priority::from_msg(&ClientMessage::event(event.clone())),
+            Priority::Event
+        );
+        assert_eq!(Priority::from_msg(&req), Priority::Req);
+        assert_eq!(
+            Priority::from_msg(&ClientMessage::auth(event)),
+            Priority::Control
+        );
+    }
+
+    #[tokio::test]
+    async fn test_send_queue() {
+        let queue: SendQueue<u8> = SendQueue::new(4);
+        queue.push(Priority::Event, 1).unwrap();
+        queue.push(Priority::Event, 2).unwrap();
+        queue.push(Priority::Req, 3).unwrap();
+        queue.push(Priority::Control, 4).unwrap();
+        assert_eq!(queue.push(Priority::Control, 5), Err(5));
+        assert_eq!(queue.len(), 4);
+
+        assert_eq!(queue.recv().await, (Priority::Control, 4));
+        assert_eq!(queue.recv().await, (Priority::Req, 3));
+
+        // Partially sent
+        let (priority, item) = queue.recv().await;
+        assert_eq!(item, 1);
+        queue.push_front(priority, item);
+
+        assert_eq!(queue.recv().await, (Priority::Event, 1));
+        assert_eq!(queue.recv().await, (Priority::Event, 2));
+        assert!(queue.pop().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_req_not_starved_by_events() {
+        let (transport, mut listener) = DuplexTransport::new();
+        let opts = RelayOptions::new()
+            .transport(transport)
+            .event_rate_limit(Some(RateLimit::new(2, 10.0)));
+        let relay = Relay::with_opts("ws://relay.local".parse().unwrap(), opts);
+        relay.connect(Some(Duration::from_secs(1))).await;
+
+        let mut connection = listener.accept().await.unwrap();
+
+        let keys = Keys::generate();
+        let events: Vec<Event> = (0..10)
+            .map(|i| {
+                EventBuilder::text_note(i.to_string(), [])
+                    .to_event(&keys)
+                    .unwrap()
+            })
+            .collect();
+        let opts = RelaySendOptions::new().skip_send_confirmation(true);
+        relay
+            .batch_msg(events.into_iter().map(ClientMessage::event).collect(), opts)
+            .await
+            .unwrap();
+        let id = SubscriptionId::generate();
+        relay
+            .send_msg(ClientMessage::req(id.clone(), vec![Filter::new()]), opts)
+            .await
+            .unwrap();
+
+        // The REQ is sent before the rate limited events
+        let mut events: usize = 0;
+        while let Some(msg) = connection.recv().await {
+            match msg {
+                ClientMessage::Event(..) => events += 1,
+                ClientMessage::Req { .. } => break,
+                _ => (),
+            }
+        }
+        assert!(events < 10);
+
+        connection.send(RelayMessage::eose(id)).unwrap();
+        while events < 10 {
+            if let Some(ClientMessage::Event(..)) = connection.recv().await {
+                events += 1;
+            }
+        }
+    }
+    #[tokio::test]
+    async fn test_events_not_blocked_by_paused_reqs() {
+        let (transport, mut listener) = DuplexTransport::new();
+        let opts = RelayOptions::new()
+            .transport(transport)
+            .req_rate_limit(Some(RateLimit::new(1, 0.001)));
+        let relay = Relay::with_opts("ws://relay.local".parse().unwrap(), opts);
+        relay.connect(Some(Duration::from_secs(1))).await;
+
+        let mut connection = listener.accept().await.unwrap();
+
+        // The second REQ waits for the REQ bucket
+        let opts = RelaySendOptions::new().skip_send_confirmation(true);
+        for _ in 0..2 {
+            let req = ClientMessage::req(SubscriptionId::generate(), vec![Filter::new()]);
+            relay.send_msg(req, opts).await.unwrap();
+        }
+
+        // The event is sent and confirmed anyway
+        let keys = Keys::generate();
+        let event = EventBuilder::text_note("Hello", [])
+            .to_event(&keys)
+            .unwrap();
+        let opts = RelaySendOptions::new().timeout(Some(Duration::from_secs(2)));
+        relay
+            .send_msg(ClientMessage::event(event), opts)
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            connection.recv().await,
+            Some(ClientMessage::Req { .. })
+        ));
+        assert!(matches!(
+            connection.recv().await,
+            Some(ClientMessage::Event(..))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_partially_sent_batch_confirmed() {
+        let (transport, mut listener) = DuplexTransport::new();
+        let opts = RelayOptions::new()
+            .transport(transport)
+            .event_rate_limit(Some(RateLimit::new(2, 0.001)));
+        let relay = Relay::with_opts("ws://relay.local".parse().unwrap(), opts);
+        relay.connect(Some(Duration::from_secs(1))).await;
+
+        let mut connection = listener.accept().await.unwrap();
+        tokio::spawn(async move {
+            while let Some(msg) = connection.recv().await {
+                if let ClientMessage::Event(event) = msg {
+                    connection
+                        .send(RelayMessage::ok(event.id(), true, ""))
+                        .unwrap();
+                }
+            }
+        });
+
+        // Only the first 2 events can be sent
+        let keys = Keys::generate();
+        let events: Vec<Event> = (0..4)
+            .map(|i| {
+                EventBuilder::text_note(i.to_string(), [])
+                    .to_event(&keys)
+                    .unwrap()
+            })
+            .collect();
+        let opts = RelaySendOptions::new().timeout(Some(Duration::from_secs(1)));
+        let outcomes = relay.publish(events.clone(), opts).await.unwrap();
+
+        assert!(outcomes[&events[0].id()].is_accepted());
+        assert!(outcomes[&events[1].id()].is_accepted());
+        assert_eq!(
+            outcomes[&events[2].id()],
+            PublishOutcome::Failed(String::from("message not sent in time"))
+        );
+    }
+}
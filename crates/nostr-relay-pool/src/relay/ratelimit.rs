@@ -0,0 +1,212 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Rate limit
+
+use std::cmp;
+use std::time::{Duration, Instant};
+
+use super::options::RateLimit;
+use super::queue::Priority;
+
+const RATE_LIMITED_PREFIX: &str = "rate-limited:";
+
+/// Backoff after the first `rate-limited` reply
+const RATE_LIMITED_INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+/// Max backoff after consecutive `rate-limited` replies
+const RATE_LIMITED_MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Check if the `CLOSED` or `OK` message has been rejected because of rate limit
+pub(crate) fn is_rate_limited(message: &str) -> bool {
+    message.starts_with(RATE_LIMITED_PREFIX)
+}
+
+/// Token bucket, paused when the relay reply with `rate-limited`
+#[derive(Debug)]
+struct TokenBucket {
+    limit: Option<RateLimit>,
+    tokens: f64,
+    updated_at: Instant,
+    paused_until: Option<Instant>,
+    backoff: Duration,
+}
+
+impl TokenBucket {
+    fn new(limit: Option<RateLimit>, now: Instant) -> Self {
+        Self {
+            limit,
+            tokens: limit.map(|limit| limit.burst as f64).unwrap_or_default(),
+            updated_at: now,
+            paused_until: None,
+            backoff: RATE_LIMITED_INITIAL_BACKOFF,
+        }
+    }
+
+    fn take(&mut self, max: usize, now: Instant) -> Result<usize, Duration> {
+        if let Some(until) = self.paused_until {
+            if now < until {
+                return Err(until - now);
+            }
+            self.paused_until = None;
+        }
+
+        match self.limit {
+            Some(limit) => {
+                // Refill
+                let elapsed: f64 = now.saturating_duration_since(self.updated_at).as_secs_f64();
+                self.tokens = (self.tokens + elapsed * limit.per_second).min(limit.burst as f64);
+                self.updated_at = now;
+
+                if self.tokens < 1.0 {
+                    let missing: f64 = 1.0 - self.tokens;
+                    return Err(Duration::from_secs_f64(missing / limit.per_second));
+                }
+
+                let taken: usize = cmp::min(self.tokens as usize, max);
+                self.tokens -= taken as f64;
+                Ok(taken)
+            }
+            None => Ok(max),
+        }
+    }
+
+    fn rate_limited(&mut self, now: Instant) -> Duration {
+        let backoff: Duration = self.backoff;
+        self.paused_until = Some(now + backoff);
+        self.backoff = cmp::min(backoff * 2, RATE_LIMITED_MAX_BACKOFF);
+        backoff
+    }
+
+    fn reset_backoff(&mut self) {
+        self.backoff = RATE_LIMITED_INITIAL_BACKOFF;
+    }
+}
+
+/// Rate limiter of `REQ`s and `EVENT`s
+///
+/// Control messages are never limited.
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    reqs: TokenBucket,
+    events: TokenBucket,
+}
+
+impl RateLimiter {
+    pub fn new(reqs: Option<RateLimit>, events: Option<RateLimit>) -> Self {
+        let now = Instant::now();
+        Self {
+            reqs: TokenBucket::new(reqs, now),
+            events: TokenBucket::new(events, now),
+        }
+    }
+
+    fn bucket(&mut self, priority: Priority) -> Option<&mut TokenBucket> {
+        match priority {
+            Priority::Control => None,
+            Priority::Req => Some(&mut self.reqs),
+            Priority::Event => Some(&mut self.events),
+        }
+    }
+
+    /// Check if at least a message can be sent now, without taking tokens
+    ///
+    /// Return how long to wait if not.
+    pub fn available(&mut self, priority: Priority, now: Instant) -> Result<(), Duration> {
+        self.take(priority, 0, now).map(|_| ())
+    }
+
+    /// Take up to `max` tokens
+    ///
+    /// Return the number of messages that can be sent now or, if none, how long to wait.
+    pub fn take(
+        &mut self,
+        priority: Priority,
+        max: usize,
+        now: Instant,
+    ) -> Result<usize, Duration> {
+        match self.bucket(priority) {
+            Some(bucket) => bucket.take(max, now),
+            None => Ok(max),
+        }
+    }
+
+    /// Relay replied with `rate-limited`: pause the sending and increase the backoff
+    ///
+    /// Return the pause duration.
+    pub fn rate_limited(&mut self, priority: Priority, now: Instant) -> Duration {
+        match self.bucket(priority) {
+            Some(bucket) => bucket.rate_limited(now),
+            None => Duration::ZERO,
+        }
+    }
+
+    /// Relay accepted the message: reset the backoff
+    pub fn accepted(&mut self, priority: Priority) {
+        if let Some(bucket) = self.bucket(priority) {
+            bucket.reset_backoff();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket() {
+        let now = Instant::now();
+        let mut limiter = RateLimiter::new(None, Some(RateLimit::new(3, 2.0)));
+
+        // Not limited
+        assert_eq!(limiter.take(Priority::Control, 100, now), Ok(100));
+        assert_eq!(limiter.take(Priority::Req, 100, now), Ok(100));
+
+        // Burst
+        assert_eq!(limiter.take(Priority::Event, 100, now), Ok(3));
+        assert_eq!(
+            limiter.take(Priority::Event, 1, now),
+            Err(Duration::from_millis(500))
+        );
+
+        // Other buckets are still available
+        assert_eq!(limiter.available(Priority::Req, now), Ok(()));
+        assert!(limiter.available(Priority::Event, now).is_err());
+
+        // Refill
+        let now = now + Duration::from_secs(1);
+        assert_eq!(limiter.available(Priority::Event, now), Ok(()));
+        assert_eq!(limiter.take(Priority::Event, 100, now), Ok(2));
+    }
+
+    #[test]
+    fn test_rate_limited_backoff() {
+        let now = Instant::now();
+        let mut limiter = RateLimiter::new(None, None);
+
+        assert_eq!(
+            limiter.rate_limited(Priority::Req, now),
+            RATE_LIMITED_INITIAL_BACKOFF
+        );
+        assert_eq!(
+            limiter.take(Priority::Req, 1, now),
+            Err(RATE_LIMITED_INITIAL_BACKOFF)
+        );
+        assert_eq!(limiter.take(Priority::Event, 1, now), Ok(1));
+
+        // Consecutive replies double the backoff
+        let now = now + RATE_LIMITED_INITIAL_BACKOFF;
+        assert_eq!(limiter.take(Priority::Req, 1, now), Ok(1));
+        assert_eq!(
+            limiter.rate_limited(Priority::Req, now),
+            RATE_LIMITED_INITIAL_BACKOFF * 2
+        );
+
+        // Reset
+        limiter.accepted(Priority::Req);
+        assert_eq!(
+            limiter.rate_limited(Priority::Req, now),
+            RATE_LIMITED_INITIAL_BACKOFF
+        );
+    }
+}
@@ -0,0 +1,166 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Relay score
+
+use std::time::Duration;
+
+use nostr::Timestamp;
+
+use super::stats::RelayConnectionStats;
+
+/// Min. number of samples before a rate affect the score
+const MIN_SAMPLES: usize = 5;
+/// `EOSE` latency that halve the read score
+const EOSE_LATENCY_HALF_SCORE: Duration = Duration::from_secs(5);
+
+/// Relay health score
+///
+/// Scores go from `0.0` (unusable) to `1.0` (healthy) and are computed from the [`RelayConnectionStats`].
+/// The rates affect the scores only after some samples.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RelayScore {
+    /// Read score (uptime, `CLOSED` and `NOTICE` rates and `EOSE` latency)
+    pub read: f64,
+    /// Write score (uptime, `OK` acceptance and `NOTICE` rates)
+    pub write: f64,
+    /// Uptime of the last connection attempts
+    pub uptime: f64,
+    /// Rate of events accepted by relay
+    pub ok_acceptance_rate: Option<f64>,
+    /// Rate of `REQ`s closed by relay
+    pub closed_rate: Option<f64>,
+    /// Rate of `NOTICE`s per `REQ` and `EVENT` sent
+    pub notice_rate: Option<f64>,
+    /// Average time between `REQ` and `EOSE`
+    pub eose_latency: Option<Duration>,
+    /// Disabled for reads until
+    pub read_disabled_until: Option<Timestamp>,
+    /// Disabled for writes until
+    pub write_disabled_until: Option<Timestamp>,
+}
+
+impl RelayScore {
+    pub(crate) async fn new(stats: &RelayConnectionStats) -> Self {
+        // Not penalize relays never tried
+        let uptime: f64 = stats.recent_uptime().unwrap_or(1.0);
+
+        let ok_acceptance_rate: Option<f64> = stats.ok_acceptance_rate();
+        let closed_rate: Option<f64> = stats.closed_rate();
+        let notice_rate: Option<f64> = stats.notice_rate();
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let eose_latency: Option<Duration> = stats.eose_latency().await;
+        #[cfg(target_arch = "wasm32")]
+        let eose_latency: Option<Duration> = None;
+
+        let oks: usize = stats.events_accepted() + stats.events_rejected();
+        let reqs: usize = stats.reqs();
+
+        let ok: f64 = factor(ok_acceptance_rate, oks);
+        let not_closed: f64 = factor(closed_rate.map(|rate| 1.0 - rate), reqs);
+        let not_notice: f64 = factor(notice_rate.map(|rate| 1.0 - rate), oks + reqs);
+        let latency: f64 = match eose_latency {
+            Some(eose_latency) => {
+                let half: f64 = EOSE_LATENCY_HALF_SCORE.as_secs_f64();
+                half / (half + eose_latency.as_secs_f64())
+            }
+            None => 1.0,
+        };
+
+        Self {
+            read: uptime * not_closed * not_notice * latency,
+            write: uptime * ok * not_notice,
+            uptime,
+            ok_acceptance_rate,
+            closed_rate,
+            notice_rate,
+            eose_latency,
+            read_disabled_until: stats.read_disabled_until(),
+            write_disabled_until: stats.write_disabled_until(),
+        }
+    }
+}
+
+/// Rate factor, `1.0` if not enough samples
+fn factor(rate: Option<f64>, samples: usize) -> f64 {
+    match rate {
+        Some(rate) if samples >= MIN_SAMPLES => rate,
+        _ => 1.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_relay_score() {
+        let stats = RelayConnectionStats::new();
+
+        // New relay
+        let score = RelayScore::new(&stats).await;
+        assert_eq!(score.read, 1.0);
+        assert_eq!(score.write, 1.0);
+
+        // Not enough samples
+        stats.new_ok(false);
+        let score = RelayScore::new(&stats).await;
+        assert_eq!(score.write, 1.0);
+        assert_eq!(score.ok_acceptance_rate, Some(0.0));
+
+        // Half of the events rejected
+        stats.new_ok(false);
+        for _ in 0..4 {
+            stats.new_ok(true);
+        }
+        stats.new_ok(false);
+        stats.new_ok(false);
+        let score = RelayScore::new(&stats).await;
+        assert_eq!(score.write, 0.5);
+        assert_eq!(score.read, 1.0);
+
+        // All the REQs closed
+        for i in 0..5 {
+            let id = nostr::SubscriptionId::new(i.to_string());
+            stats.new_req(id.clone());
+            stats.subscription_closed(&id);
+            stats.new_closed();
+        }
+        let score = RelayScore::new(&stats).await;
+        assert_eq!(score.read, 0.0);
+
+        // New chance for reads: writes unchanged
+        stats.reset_read_score().await;
+        let score = RelayScore::new(&stats).await;
+        assert_eq!(score.read, 1.0);
+        assert_eq!(score.write, 0.5);
+
+        // New chance for writes
+        stats.reset_write_score();
+        let score = RelayScore::new(&stats).await;
+        assert_eq!(score.write, 1.0);
+    }
+
+    #[tokio::test]
+    async fn test_relay_score_recent_uptime() {
+        let stats = RelayConnectionStats::new();
+
+        // Relay down for a long time
+        for _ in 0..100 {
+            stats.new_attempt();
+        }
+        let score = RelayScore::new(&stats).await;
+        assert_eq!(score.uptime, 0.0);
+
+        // Back up: only the last attempts count
+        for _ in 0..20 {
+            stats.new_attempt();
+            stats.new_success();
+        }
+        let score = RelayScore::new(&stats).await;
+        assert_eq!(score.uptime, 1.0);
+        assert!(stats.uptime() < 0.2);
+    }
+}
@@ -5,18 +5,22 @@
 //! Relay Stats
 
 #[cfg(not(target_arch = "wasm32"))]
+use std::collections::HashMap;
 use std::collections::VecDeque;
 #[cfg(not(target_arch = "wasm32"))]
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, MutexGuard};
 #[cfg(not(target_arch = "wasm32"))]
 use std::time::{Duration, Instant};
 
-use nostr::Timestamp;
+use nostr::{SubscriptionId, Timestamp};
 #[cfg(not(target_arch = "wasm32"))]
 use tokio::sync::RwLock;
 
+/// Number of connection attempts used to calculate the recent uptime
+const RECENT_ATTEMPTS: usize = 20;
+
 /// Ping Stats
 #[cfg(not(target_arch = "wasm32"))]
 #[derive(Debug, Clone)]
@@ -87,8 +91,22 @@ pub struct RelayConnectionStats {
     bytes_received: Arc<AtomicUsize>,
     connected_at: Arc<AtomicU64>,
     first_connection_timestamp: Arc<AtomicU64>,
+    /// Outcome of the last connection attempts, most recent first
+    recent_attempts: Arc<Mutex<VecDeque<bool>>>,
     #[cfg(not(target_arch = "wasm32"))]
     latencies: Arc<RwLock<VecDeque<Duration>>>,
+    events_accepted: Arc<AtomicUsize>,
+    events_rejected: Arc<AtomicUsize>,
+    reqs: Arc<AtomicUsize>,
+    closed: Arc<AtomicUsize>,
+    notices: Arc<AtomicUsize>,
+    /// `REQ`s waiting for `EOSE`
+    #[cfg(not(target_arch = "wasm32"))]
+    eose_pending: Arc<Mutex<HashMap<SubscriptionId, Instant>>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    eose_latencies: Arc<RwLock<VecDeque<Duration>>>,
+    read_disabled_until: Arc<AtomicU64>,
+    write_disabled_until: Arc<AtomicU64>,
     #[cfg(not(target_arch = "wasm32"))]
     pub(crate) ping: PingStats,
 }
@@ -109,8 +127,20 @@ impl RelayConnectionStats {
             bytes_received: Arc::new(AtomicUsize::new(0)),
             connected_at: Arc::new(AtomicU64::new(0)),
             first_connection_timestamp: Arc::new(AtomicU64::new(0)),
+            recent_attempts: Arc::new(Mutex::new(VecDeque::with_capacity(RECENT_ATTEMPTS))),
             #[cfg(not(target_arch = "wasm32"))]
             latencies: Arc::new(RwLock::new(VecDeque::new())),
+            events_accepted: Arc::new(AtomicUsize::new(0)),
+            events_rejected: Arc::new(AtomicUsize::new(0)),
+            reqs: Arc::new(AtomicUsize::new(0)),
+            closed: Arc::new(AtomicUsize::new(0)),
+            notices: Arc::new(AtomicUsize::new(0)),
+            #[cfg(not(target_arch = "wasm32"))]
+            eose_pending: Arc::new(Mutex::new(HashMap::new())),
+            #[cfg(not(target_arch = "wasm32"))]
+            eose_latencies: Arc::new(RwLock::new(VecDeque::new())),
+            read_disabled_until: Arc::new(AtomicU64::new(0)),
+            write_disabled_until: Arc::new(AtomicU64::new(0)),
             #[cfg(not(target_arch = "wasm32"))]
             ping: PingStats::default(),
         }
@@ -137,6 +167,25 @@ impl RelayConnectionStats {
         }
     }
 
+    /// Uptime of the last connection attempts
+    ///
+    /// Return `None` if no connection has been attempted.
+    pub fn recent_uptime(&self) -> Option<f64> {
+        let attempts = self.recent_attempts();
+        if attempts.is_empty() {
+            return None;
+        }
+        let success: usize = attempts.iter().filter(|success| **success).count();
+        Some(success as f64 / attempts.len() as f64)
+    }
+
+    fn recent_attempts(&self) -> MutexGuard<'_, VecDeque<bool>> {
+        match self.recent_attempts.lock() {
+            Ok(attempts) => attempts,
+            Err(e) => e.into_inner(),
+        }
+    }
+
     /// Bytes sent
     pub fn bytes_sent(&self) -> usize {
         self.bytes_sent.load(Ordering::SeqCst)
@@ -165,13 +214,107 @@ impl RelayConnectionStats {
         sum.checked_div(latencies.len() as u32)
     }
 
+    /// Number of events accepted by relay (`OK` with `true` status)
+    pub fn events_accepted(&self) -> usize {
+        self.events_accepted.load(Ordering::SeqCst)
+    }
+
+    /// Number of events rejected by relay (`OK` with `false` status)
+    pub fn events_rejected(&self) -> usize {
+        self.events_rejected.load(Ordering::SeqCst)
+    }
+
+    /// Rate of events accepted by relay
+    ///
+    /// Return `None` if no `OK` has been received.
+    pub fn ok_acceptance_rate(&self) -> Option<f64> {
+        let accepted: usize = self.events_accepted();
+        let total: usize = accepted + self.events_rejected();
+        if total > 0 {
+            Some(accepted as f64 / total as f64)
+        } else {
+            None
+        }
+    }
+
+    /// Number of `REQ`s sent
+    pub fn reqs(&self) -> usize {
+        self.reqs.load(Ordering::SeqCst)
+    }
+
+    /// Number of subscriptions closed by relay (`CLOSED`)
+    pub fn closed(&self) -> usize {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    /// Number of `NOTICE`s received
+    pub fn notices(&self) -> usize {
+        self.notices.load(Ordering::SeqCst)
+    }
+
+    /// Rate of `REQ`s closed by relay
+    ///
+    /// Return `None` if no `REQ` has been sent.
+    pub fn closed_rate(&self) -> Option<f64> {
+        let reqs: usize = self.reqs();
+        if reqs > 0 {
+            Some(f64::min(self.closed() as f64 / reqs as f64, 1.0))
+        } else {
+            None
+        }
+    }
+
+    /// Rate of `NOTICE`s per `REQ` and `EVENT` sent
+    ///
+    /// Return `None` if no `REQ` and `EVENT` has been sent.
+    pub fn notice_rate(&self) -> Option<f64> {
+        let total: usize = self.reqs() + self.events_accepted() + self.events_rejected();
+        if total > 0 {
+            Some(f64::min(self.notices() as f64 / total as f64, 1.0))
+        } else {
+            None
+        }
+    }
+
+    /// Average time between `REQ` and `EOSE`
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn eose_latency(&self) -> Option<Duration> {
+        let latencies = self.eose_latencies.read().await;
+        let sum: Duration = latencies.iter().sum();
+        sum.checked_div(latencies.len() as u32)
+    }
+
+    /// Get UNIX timestamp until which the relay is disabled for reads
+    pub fn read_disabled_until(&self) -> Option<Timestamp> {
+        match self.read_disabled_until.load(Ordering::SeqCst) {
+            0 => None,
+            until => Some(Timestamp::from(until)),
+        }
+    }
+
+    /// Get UNIX timestamp until which the relay is disabled for writes
+    pub fn write_disabled_until(&self) -> Option<Timestamp> {
+        match self.write_disabled_until.load(Ordering::SeqCst) {
+            0 => None,
+            until => Some(Timestamp::from(until)),
+        }
+    }
+
     pub(crate) fn new_attempt(&self) {
         self.attempts.fetch_add(1, Ordering::SeqCst);
+
+        let mut attempts = self.recent_attempts();
+        attempts.truncate(RECENT_ATTEMPTS - 1);
+        attempts.push_front(false);
     }
 
     pub(crate) fn new_success(&self) {
         self.success.fetch_add(1, Ordering::SeqCst);
 
+        if let Some(success) = self.recent_attempts().front_mut() {
+            *success = true;
+        }
+
         let now: u64 = Timestamp::now().as_u64();
 
         self.connected_at.store(now, Ordering::SeqCst);
@@ -199,4 +342,98 @@ impl RelayConnectionStats {
         }
         latencies.push_front(latency)
     }
+
+    pub(crate) fn new_ok(&self, status: bool) {
+        if status {
+            self.events_accepted.fetch_add(1, Ordering::SeqCst);
+        } else {
+            self.events_rejected.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    pub(crate) fn new_req(&self, id: SubscriptionId) {
+        self.reqs.fetch_add(1, Ordering::SeqCst);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        match self.eose_pending.lock() {
+            Ok(mut pending) => pending.insert(id, Instant::now()),
+            Err(e) => e.into_inner().insert(id, Instant::now()),
+        };
+
+        #[cfg(target_arch = "wasm32")]
+        let _ = id;
+    }
+
+    pub(crate) async fn new_eose(&self, id: &SubscriptionId) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let sent_at: Option<Instant> = match self.eose_pending.lock() {
+                Ok(mut pending) => pending.remove(id),
+                Err(e) => e.into_inner().remove(id),
+            };
+
+            if let Some(sent_at) = sent_at {
+                let mut latencies = self.eose_latencies.write().await;
+                if latencies.len() >= 20 {
+                    latencies.pop_back();
+                }
+                latencies.push_front(sent_at.elapsed());
+            }
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        let _ = id;
+    }
+
+    /// Subscription closed (`CLOSE` sent or `CLOSED` received): stop waiting for `EOSE`
+    pub(crate) fn subscription_closed(&self, id: &SubscriptionId) {
+        #[cfg(not(target_arch = "wasm32"))]
+        match self.eose_pending.lock() {
+            Ok(mut pending) => pending.remove(id),
+            Err(e) => e.into_inner().remove(id),
+        };
+
+        #[cfg(target_arch = "wasm32")]
+        let _ = id;
+    }
+
+    pub(crate) fn new_closed(&self) {
+        self.closed.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub(crate) fn new_notice(&self) {
+        self.notices.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub(crate) fn set_read_disabled_until(&self, until: Option<Timestamp>) {
+        let until: u64 = until.map(|t| t.as_u64()).unwrap_or_default();
+        self.read_disabled_until.store(until, Ordering::SeqCst);
+    }
+
+    pub(crate) fn set_write_disabled_until(&self, until: Option<Timestamp>) {
+        let until: u64 = until.map(|t| t.as_u64()).unwrap_or_default();
+        self.write_disabled_until.store(until, Ordering::SeqCst);
+    }
+
+    /// Reset the counters used by the read [`RelayScore`](super::score::RelayScore)
+    ///
+    /// Called when a relay disabled for reads get a new chance.
+    /// The `NOTICE`s, shared by reads and writes, are reset too.
+    pub(crate) async fn reset_read_score(&self) {
+        self.reqs.store(0, Ordering::SeqCst);
+        self.closed.store(0, Ordering::SeqCst);
+        self.notices.store(0, Ordering::SeqCst);
+        #[cfg(not(target_arch = "wasm32"))]
+        self.eose_latencies.write().await.clear();
+    }
+
+    /// Reset the counters used by the write [`RelayScore`](super::score::RelayScore)
+    ///
+    /// Called when a relay disabled for writes get a new chance.
+    /// The `NOTICE`s, shared by reads and writes, are reset too.
+    pub(crate) fn reset_write_score(&self) {
+        self.events_accepted.store(0, Ordering::SeqCst);
+        self.events_rejected.store(0, Ordering::SeqCst);
+        self.notices.store(0, Ordering::SeqCst);
+    }
 }
@@ -23,6 +23,10 @@ pub enum RelayStatus {
     Stopped,
     /// Relay completely disconnected
     Terminated,
+    /// Relay given up after too many failed connection attempts
+    ///
+    /// See [`ReconnectPolicy`](super::options::ReconnectPolicy).
+    Dead,
 }
 
 impl fmt::Display for RelayStatus {
@@ -35,13 +39,17 @@ impl fmt::Display for RelayStatus {
             Self::Disconnected => write!(f, "Disconnected"),
             Self::Stopped => write!(f, "Stopped"),
             Self::Terminated => write!(f, "Terminated"),
+            Self::Dead => write!(f, "Dead"),
         }
     }
 }
 
 impl RelayStatus {
-    /// Check if is `disconnected`, `stopped` or `terminated`
+    /// Check if is `disconnected`, `stopped`, `terminated` or `dead`
     pub(crate) fn is_disconnected(&self) -> bool {
-        matches!(self, Self::Disconnected | Self::Stopped | Self::Terminated)
+        matches!(
+            self,
+            Self::Disconnected | Self::Stopped | Self::Terminated | Self::Dead
+        )
     }
 }
@@ -0,0 +1,554 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Subscription and event streams
+
+use std::num::NonZeroUsize;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll};
+use std::{future, mem};
+
+use async_wsocket::futures_util::Stream;
+use lru::LruCache;
+use nostr::{Event, EventId, SubscriptionId, Url};
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+
+/// Subscription stream item
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SubscriptionItem {
+    /// Received an [`Event`] (deduplicated across relays)
+    Event {
+        /// Relay url
+        relay_url: Url,
+        /// Event
+        event: Box<Event>,
+    },
+    /// Relay sent all the stored events
+    Eose {
+        /// Relay url
+        relay_url: Url,
+    },
+    /// Subscription closed by relay
+    Closed {
+        /// Relay url
+        relay_url: Url,
+        /// Reason
+        message: String,
+    },
+    /// Stream not consumed fast enough: the items received meanwhile have been dropped
+    Lagged {
+        /// Number of dropped items
+        skipped: u64,
+    },
+}
+
+/// Max number of [`SubscriptionItem`]s waiting in a [`SubscriptionStream`]
+const SUBSCRIPTION_STREAM_SIZE: usize = 1024;
+/// Max number of [`Event`]s waiting in an [`EventStream`]
+const EVENT_STREAM_SIZE: usize = 1024;
+/// Max number of [`EventId`]s remembered to deduplicate the events across relays
+const SUBSCRIPTION_SEEN_SIZE: usize = 10_000;
+
+/// State shared by the [`SubscriptionSink`] and the [`SubscriptionStream`]
+#[derive(Debug)]
+struct SinkState {
+    /// Recently sent events
+    seen: LruCache<EventId, ()>,
+    /// Items dropped since the last sent item
+    lagged: u64,
+}
+
+/// Sending side of a [`SubscriptionStream`], shared by the relays of the subscription
+///
+/// The stream ends when all the relays drop it.
+/// The relays never wait for the stream: when it's full, the items are dropped
+/// and a [`SubscriptionItem::Lagged`] is sent as soon as there is room again.
+#[derive(Debug)]
+pub(crate) struct SubscriptionSink {
+    tx: Sender<SubscriptionItem>,
+    state: Arc<Mutex<SinkState>>,
+}
+
+fn lock(state: &Mutex<SinkState>) -> MutexGuard<'_, SinkState> {
+    match state.lock() {
+        Ok(state) => state,
+        Err(e) => e.into_inner(),
+    }
+}
+
+impl SubscriptionSink {
+    pub fn new(id: SubscriptionId) -> (Arc<Self>, SubscriptionStream) {
+        let (tx, rx) = mpsc::channel(SUBSCRIPTION_STREAM_SIZE);
+        let state = Arc::new(Mutex::new(SinkState {
+            seen: LruCache::new(NonZeroUsize::new(SUBSCRIPTION_SEEN_SIZE).expect("non-zero size")),
+            lagged: 0,
+        }));
+        let sink = Self {
+            tx,
+            state: state.clone(),
+        };
+        (Arc::new(sink), SubscriptionStream { id, rx, state })
+    }
+
+    /// Check if the stream has been dropped
+    pub fn is_closed(&self) -> bool {
+        self.tx.is_closed()
+    }
+
+    /// Check if the [`EventId`] has already been sent to the stream
+    pub fn has_seen(&self, event_id: &EventId) -> bool {
+        lock(&self.state).seen.contains(event_id)
+    }
+
+    /// Send event, if not already sent by another relay
+    pub fn event(&self, relay_url: Url, event: Box<Event>) {
+        let mut state = lock(&self.state);
+        let event_id: EventId = event.id();
+        if state.seen.put(event_id, ()).is_some() {
+            return;
+        }
+
+        // Dropped: another relay can send it again
+        if !self.try_send(&mut state, SubscriptionItem::Event { relay_url, event }) {
+            state.seen.pop(&event_id);
+        }
+    }
+
+    pub fn send(&self, item: SubscriptionItem) {
+        let mut state = lock(&self.state);
+        self.try_send(&mut state, item);
+    }
+
+    /// Send the item, preceded by the number of dropped items (if any)
+    ///
+    /// Return `false` if the item has been dropped.
+    fn try_send(&self, state: &mut SinkState, item: SubscriptionItem) -> bool {
+        if state.lagged > 0 {
+            let lagged = SubscriptionItem::Lagged {
+                skipped: state.lagged,
+            };
+            match self.tx.try_send(lagged) {
+                Ok(()) => state.lagged = 0,
+                Err(TrySendError::Full(..)) => {
+                    state.lagged += 1;
+                    return false;
+                }
+                Err(TrySendError::Closed(..)) => return false,
+            }
+        }
+
+        match self.tx.try_send(item) {
+            Ok(()) => true,
+            Err(TrySendError::Full(..)) => {
+                state.lagged += 1;
+                false
+            }
+            Err(TrySendError::Closed(..)) => false,
+        }
+    }
+}
+
+/// Stream of the [`SubscriptionItem`]s of a subscription
+///
+/// The stream ends when the subscription is closed: unsubscribed, auto-closed or closed by all the relays.
+/// Dropping the stream doesn't close the subscription.
+///
+/// The relays don't wait for a slow consumer: the items received while the stream is full
+/// are dropped and reported with a [`SubscriptionItem::Lagged`].
+#[derive(Debug)]
+pub struct SubscriptionStream {
+    id: SubscriptionId,
+    rx: Receiver<SubscriptionItem>,
+    state: Arc<Mutex<SinkState>>,
+}
+
+impl SubscriptionStream {
+    /// Subscription ID
+    pub fn id(&self) -> &SubscriptionId {
+        &self.id
+    }
+
+    /// Wait for the next [`SubscriptionItem`]
+    ///
+    /// Return `None` when the subscription is closed.
+    pub async fn next(&mut self) -> Option<SubscriptionItem> {
+        future::poll_fn(|cx| self.poll_item(cx)).await
+    }
+
+    /// Poll the next item, or the number of dropped items when all the received ones have been consumed
+    fn poll_item(&mut self, cx: &mut Context<'_>) -> Poll<Option<SubscriptionItem>> {
+        match self.rx.poll_recv(cx) {
+            Poll::Ready(Some(item)) => Poll::Ready(Some(item)),
+            res => {
+                // `Pending` also when the task has exhausted its budget: check if it's really empty
+                if let Ok(item) = self.rx.try_recv() {
+                    return Poll::Ready(Some(item));
+                }
+
+                let mut state = lock(&self.state);
+                if state.lagged > 0 {
+                    let skipped: u64 = mem::take(&mut state.lagged);
+                    Poll::Ready(Some(SubscriptionItem::Lagged { skipped }))
+                } else {
+                    res
+                }
+            }
+        }
+    }
+}
+
+impl Stream for SubscriptionStream {
+    type Item = SubscriptionItem;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.poll_item(cx)
+    }
+}
+
+/// Stream of [`Event`]s, deduplicated across relays
+///
+/// The stream ends when all the relays have sent the events (or timeout).
+/// Dropping the stream stops the queries and closes the subscriptions.
+///
+/// Up to 1024 events are buffered: when the buffer is full, the queries wait for the stream to be consumed.
+#[derive(Debug)]
+pub struct EventStream {
+    rx: Receiver<Event>,
+}
+
+impl EventStream {
+    pub(crate) fn new() -> (Sender<Event>, Self) {
+        let (tx, rx) = mpsc::channel(EVENT_STREAM_SIZE);
+        (tx, Self { rx })
+    }
+
+    /// Wait for the next [`Event`]
+    ///
+    /// Return `None` when all the relays have sent the events.
+    pub async fn next(&mut self) -> Option<Event> {
+        self.rx.recv().await
+    }
+}
+
+impl Stream for EventStream {
+    type Item = Event;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::time::Duration;
+
+    use nostr::{ClientMessage, EventBuilder, Filter, Keys, Kind, RelayMessage};
+
+    use super::*;
+    use crate::{mock, DuplexTransport, FilterOptions, RelayPool, SubscribeOptions};
+
+    #[tokio::test]
+    async fn test_subscription_sink() {
+        let keys = Keys::generate();
+        let url = Url::parse("ws://relay.local").unwrap();
+        let (sink, mut stream) = SubscriptionSink::new(SubscriptionId::generate());
+        let events: Vec<Event> = (0..=SUBSCRIPTION_STREAM_SIZE)
+            .map(|i| {
+                EventBuilder::text_note(i.to_string(), [])
+                    .to_event(&keys)
+                    .unwrap()
+            })
+            .collect();
+        for event in events.iter() {
+            sink.event(url.clone(), Box::new(event.clone()));
+        }
+
+        // The last event has been dropped: it can be sent again, the others not
+        let last: &Event = events.last().unwrap();
+        assert!(sink.has_seen(&events[0].id()));
+        assert!(!sink.has_seen(&last.id()));
+
+        for _ in 0..SUBSCRIPTION_STREAM_SIZE {
+            assert!(matches!(
+                stream.next().await,
+                Some(SubscriptionItem::Event { .. })
+            ));
+        }
+        sink.event(url.clone(), Box::new(events[0].clone()));
+        sink.event(url, Box::new(last.clone()));
+        assert_eq!(
+            stream.next().await,
+            Some(SubscriptionItem::Lagged { skipped: 1 })
+        );
+        match stream.next().await {
+            Some(SubscriptionItem::Event { event, .. }) => assert_eq!(event.id(), last.id()),
+            item => panic!("Unexpected item: {item:?}"),
+        }
+
+        drop(sink);
+        assert_eq!(stream.next().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_subscription_stream() {
+        let keys = Keys::generate();
+        let event = EventBuilder::text_note("Hello", [])
+            .to_event(&keys)
+            .unwrap();
+
+        // Both relays send the same event, then EOSE and CLOSED
+        let relay_event = event.clone();
+        let transport = mock::spawn(move |_, msg| match msg {
+            ClientMessage::Req {
+                subscription_id, ..
+            } => vec![
+                RelayMessage::event(subscription_id.clone(), relay_event.clone()),
+                RelayMessage::eose(subscription_id.clone()),
+                RelayMessage::closed(subscription_id, "error: shutting down"),
+            ],
+            _ => Vec::new(),
+        });
+        let pool = mock::pool(transport, ["ws://relay1.local", "ws://relay2.local"]).await;
+
+        let filter = Filter::new().kind(Kind::TextNote);
+        let mut stream = pool
+            .subscribe_stream(vec![filter], SubscribeOptions::default())
+            .await;
+
+        let mut events = 0;
+        let mut eose = 0;
+        let mut closed = 0;
+        while let Some(item) = tokio::time::timeout(Duration::from_secs(5), stream.next())
+            .await
+            .unwrap()
+        {
+            match item {
+                SubscriptionItem::Event { event: e, .. } => {
+                    assert_eq!(e.id(), event.id());
+                    events += 1;
+                }
+                SubscriptionItem::Eose { .. } => eose += 1,
+                SubscriptionItem::Closed { .. } => closed += 1,
+                SubscriptionItem::Lagged { .. } => panic!("Unexpected lag"),
+            }
+        }
+
+        assert_eq!(events, 1);
+        assert_eq!(eose, 2);
+        assert_eq!(closed, 2);
+    }
+
+    #[tokio::test]
+    async fn test_subscription_stream_without_relays() {
+        let keys = Keys::generate();
+        let event = EventBuilder::text_note("Hello", [])
+            .to_event(&keys)
+            .unwrap();
+
+        let filter = Filter::new().kind(Kind::TextNote);
+        let pool = RelayPool::default();
+        let mut stream = pool
+            .subscribe_stream(vec![filter], SubscribeOptions::default())
+            .await;
+
+        // The stream is still open when the relay is added
+        let relay_event = event.clone();
+        let transport = mock::spawn(move |_, msg| match msg {
+            ClientMessage::Req {
+                subscription_id, ..
+            } => vec![
+                RelayMessage::event(subscription_id.clone(), relay_event.clone()),
+                RelayMessage::eose(subscription_id),
+            ],
+            _ => Vec::new(),
+        });
+        mock::add_relays(&pool, transport, ["ws://relay.local"]).await;
+
+        let item = tokio::time::timeout(Duration::from_secs(5), stream.next())
+            .await
+            .unwrap();
+        match item {
+            Some(SubscriptionItem::Event { event: e, .. }) => assert_eq!(e.id(), event.id()),
+            item => panic!("Unexpected item: {item:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_subscription_stream_full() {
+        let keys = Keys::generate();
+        let events: Vec<Event> = (0..SUBSCRIPTION_STREAM_SIZE * 2)
+            .map(|i| {
+                EventBuilder::text_note(i.to_string(), [])
+                    .to_event(&keys)
+                    .unwrap()
+            })
+            .collect();
+        let count = events.len();
+
+        let transport = mock::spawn(move |_, msg| match msg {
+            ClientMessage::Req {
+                subscription_id, ..
+            } => {
+                let mut msgs: Vec<RelayMessage> = events
+                    .iter()
+                    .map(|e| RelayMessage::event(subscription_id.clone(), e.clone()))
+                    .collect();
+                msgs.push(RelayMessage::eose(subscription_id));
+                msgs
+            }
+            _ => Vec::new(),
+        });
+        let pool = mock::pool(transport, ["ws://relay.local"]).await;
+
+        let filter = Filter::new().kind(Kind::TextNote);
+        let mut stream = pool
+            .subscribe_stream(vec![filter], SubscribeOptions::default())
+            .await;
+
+        // Slow consumer: the relay doesn't wait, the items exceeding the stream size are dropped
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        let mut ids = HashSet::new();
+        loop {
+            let item = tokio::time::timeout(Duration::from_secs(1), stream.next())
+                .await
+                .unwrap()
+                .unwrap();
+            match item {
+                SubscriptionItem::Event { event, .. } => assert!(ids.insert(event.id())),
+                SubscriptionItem::Lagged { skipped } => {
+                    // Dropped events and EOSE
+                    assert_eq!(skipped as usize, count - SUBSCRIPTION_STREAM_SIZE + 1);
+                    break;
+                }
+                item => panic!("Unexpected item: {item:?}"),
+            }
+        }
+        assert_eq!(ids.len(), SUBSCRIPTION_STREAM_SIZE);
+    }
+
+    #[tokio::test]
+    async fn test_event_stream() {
+        let keys = Keys::generate();
+        let a = EventBuilder::text_note("A", []).to_event(&keys).unwrap();
+        let b = EventBuilder::text_note("B", []).to_event(&keys).unwrap();
+
+        // Relay 1 send A and B, relay 2 send only B
+        let transport = mock::spawn(move |url, msg| match msg {
+            ClientMessage::Req {
+                subscription_id, ..
+            } => {
+                let events = if url.as_str().contains("relay1") {
+                    vec![a.clone(), b.clone()]
+                } else {
+                    vec![b.clone()]
+                };
+                let mut msgs: Vec<RelayMessage> = events
+                    .into_iter()
+                    .map(|e| RelayMessage::event(subscription_id.clone(), e))
+                    .collect();
+                msgs.push(RelayMessage::eose(subscription_id));
+                msgs
+            }
+            _ => Vec::new(),
+        });
+        let pool = mock::pool(transport, ["ws://relay1.local", "ws://relay2.local"]).await;
+
+        let filter = Filter::new().kind(Kind::TextNote);
+        let mut stream = pool
+            .stream_events_of(
+                vec![filter],
+                Duration::from_secs(5),
+                FilterOptions::ExitOnEOSE,
+            )
+            .await
+            .unwrap();
+
+        let mut ids = HashSet::new();
+        while let Some(event) = stream.next().await {
+            assert!(ids.insert(event.id()));
+        }
+        assert_eq!(ids.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_event_stream_slow_consumer() {
+        let keys = Keys::generate();
+        let events: Vec<Event> = (0..EVENT_STREAM_SIZE + EVENT_STREAM_SIZE / 2)
+            .map(|i| {
+                EventBuilder::text_note(i.to_string(), [])
+                    .to_event(&keys)
+                    .unwrap()
+            })
+            .collect();
+        let count: usize = events.len();
+
+        let transport = mock::spawn(move |_, msg| match msg {
+            ClientMessage::Req {
+                subscription_id, ..
+            } => {
+                let mut msgs: Vec<RelayMessage> = events
+                    .iter()
+                    .map(|e| RelayMessage::event(subscription_id.clone(), e.clone()))
+                    .collect();
+                msgs.push(RelayMessage::eose(subscription_id));
+                msgs
+            }
+            _ => Vec::new(),
+        });
+        let pool = mock::pool(transport, ["ws://relay.local"]).await;
+
+        let filter = Filter::new().kind(Kind::TextNote);
+        let mut stream = pool
+            .stream_events_of(
+                vec![filter],
+                Duration::from_secs(5),
+                FilterOptions::ExitOnEOSE,
+            )
+            .await
+            .unwrap();
+
+        // Not consumed while the relay sends more events than the buffer size
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        let mut ids = HashSet::new();
+        while let Some(event) = stream.next().await {
+            assert!(ids.insert(event.id()));
+        }
+        assert_eq!(ids.len(), count);
+    }
+
+    #[tokio::test]
+    async fn test_event_stream_dropped() {
+        let (transport, mut listener) = DuplexTransport::new();
+        let pool = RelayPool::default();
+        mock::add_relays(&pool, transport, ["ws://relay.local"]).await;
+        let mut connection = listener.accept().await.unwrap();
+
+        // The relay never answers
+        let filter = Filter::new().kind(Kind::TextNote);
+        let stream = pool
+            .stream_events_of(
+                vec![filter],
+                Duration::from_secs(1),
+                FilterOptions::ExitOnEOSE,
+            )
+            .await
+            .unwrap();
+        let id = mock::recv_req(&mut connection).await;
+
+        drop(stream);
+
+        let msg = tokio::time::timeout(Duration::from_millis(500), connection.recv())
+            .await
+            .unwrap();
+        assert_eq!(msg, Some(ClientMessage::close(id)));
+
+        // No other CLOSE after the query timeout
+        let msg = tokio::time::timeout(Duration::from_secs(2), connection.recv()).await;
+        assert!(msg.is_err());
+    }
+}
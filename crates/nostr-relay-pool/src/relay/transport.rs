@@ -0,0 +1,275 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! WebSocket transport
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use async_wsocket::futures_util::{self, Sink, SinkExt, Stream, StreamExt};
+use async_wsocket::WsMessage;
+use nostr::{ClientMessage, JsonUtil, RelayMessage, Url};
+use nostr_database::AsyncTraitDeps;
+use thiserror::Error;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+/// Transport error
+#[derive(Debug, Error)]
+pub enum TransportError {
+    /// Backend error
+    #[error(transparent)]
+    Backend(Box<dyn std::error::Error + Send + Sync>),
+    /// Connection closed
+    #[error("connection closed")]
+    ConnectionClosed,
+}
+
+impl TransportError {
+    /// Create a new backend error
+    ///
+    /// Shorthand for `Error::Backend(Box::new(error))`.
+    #[inline]
+    pub fn backend<E>(error: E) -> Self
+    where
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        Self::Backend(Box::new(error))
+    }
+}
+
+/// WebSocket sink
+#[cfg(not(target_arch = "wasm32"))]
+pub type BoxSink = Pin<Box<dyn Sink<WsMessage, Error = TransportError> + Send>>;
+/// WebSocket sink
+#[cfg(target_arch = "wasm32")]
+pub type BoxSink = Pin<Box<dyn Sink<WsMessage, Error = TransportError>>>;
+
+/// WebSocket stream
+#[cfg(not(target_arch = "wasm32"))]
+pub type BoxStream = Pin<Box<dyn Stream<Item = Result<WsMessage, TransportError>> + Send>>;
+/// WebSocket stream
+#[cfg(target_arch = "wasm32")]
+pub type BoxStream = Pin<Box<dyn Stream<Item = Result<WsMessage, TransportError>>>>;
+
+/// WebSocket transport
+///
+/// Used by [`Relay`](super::Relay) to open the connection (ex. custom TLS config, Tor client, in-memory transport).
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait WebSocketTransport: AsyncTraitDeps {
+    /// Check if the transport support ping/pong messages
+    fn support_ping(&self) -> bool;
+
+    /// Connect to relay
+    async fn connect(
+        &self,
+        url: &Url,
+        timeout: Option<Duration>,
+    ) -> Result<(BoxSink, BoxStream), TransportError>;
+}
+
+/// Default WebSocket transport (`async-wsocket`)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultWebSocketTransport {
+    #[cfg(not(target_arch = "wasm32"))]
+    proxy: Option<SocketAddr>,
+}
+
+impl DefaultWebSocketTransport {
+    /// New default transport
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set proxy
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn proxy(mut self, proxy: Option<SocketAddr>) -> Self {
+        self.proxy = proxy;
+        self
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl WebSocketTransport for DefaultWebSocketTransport {
+    fn support_ping(&self) -> bool {
+        cfg!(not(target_arch = "wasm32"))
+    }
+
+    async fn connect(
+        &self,
+        url: &Url,
+        timeout: Option<Duration>,
+    ) -> Result<(BoxSink, BoxStream), TransportError> {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let (tx, rx) = async_wsocket::native::connect(url, self.proxy, timeout)
+                .await
+                .map_err(TransportError::backend)?;
+            let tx = tx.sink_map_err(TransportError::backend);
+            let rx = rx.map(|msg| msg.map_err(TransportError::backend));
+            Ok((Box::pin(tx), Box::pin(rx)))
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            let (tx, rx) = async_wsocket::wasm::connect(url, timeout)
+                .await
+                .map_err(TransportError::backend)?;
+            let tx = tx.sink_map_err(TransportError::backend);
+            let rx = rx.map(Ok);
+            Ok((Box::pin(tx), Box::pin(rx)))
+        }
+    }
+}
+
+/// In-process duplex transport
+///
+/// Every connection is handed to the [`DuplexListener`], to play the relay side without sockets (ex. tests).
+#[derive(Debug, Clone)]
+pub struct DuplexTransport {
+    listener: UnboundedSender<DuplexConnection>,
+}
+
+impl DuplexTransport {
+    /// New duplex transport and the listener of its connections
+    pub fn new() -> (Self, DuplexListener) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        (Self { listener: tx }, DuplexListener { connections: rx })
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl WebSocketTransport for DuplexTransport {
+    fn support_ping(&self) -> bool {
+        false
+    }
+
+    async fn connect(
+        &self,
+        url: &Url,
+        _timeout: Option<Duration>,
+    ) -> Result<(BoxSink, BoxStream), TransportError> {
+        let (client_tx, relay_rx) = mpsc::unbounded_channel::<WsMessage>();
+        let (relay_tx, client_rx) = mpsc::unbounded_channel::<WsMessage>();
+
+        let connection = DuplexConnection {
+            url: url.clone(),
+            tx: relay_tx,
+            rx: relay_rx,
+        };
+        self.listener
+            .send(connection)
+            .map_err(|_| TransportError::ConnectionClosed)?;
+
+        let tx = futures_util::sink::unfold(client_tx, |tx, msg: WsMessage| async move {
+            tx.send(msg).map_err(|_| TransportError::ConnectionClosed)?;
+            Ok::<_, TransportError>(tx)
+        });
+        let rx = futures_util::stream::unfold(client_rx, |mut rx| async move {
+            rx.recv().await.map(|msg| (Ok(msg), rx))
+        });
+        Ok((Box::pin(tx), Box::pin(rx)))
+    }
+}
+
+/// Listener of [`DuplexTransport`] connections
+#[derive(Debug)]
+pub struct DuplexListener {
+    connections: UnboundedReceiver<DuplexConnection>,
+}
+
+impl DuplexListener {
+    /// Wait for the next connection
+    ///
+    /// Return `None` if all the [`DuplexTransport`] clones have been dropped.
+    pub async fn accept(&mut self) -> Option<DuplexConnection> {
+        self.connections.recv().await
+    }
+}
+
+/// Relay side of a [`DuplexTransport`] connection
+///
+/// Drop it to close the connection.
+#[derive(Debug)]
+pub struct DuplexConnection {
+    url: Url,
+    tx: UnboundedSender<WsMessage>,
+    rx: UnboundedReceiver<WsMessage>,
+}
+
+impl DuplexConnection {
+    /// Url requested by the client
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Send [`RelayMessage`] to the client
+    pub fn send(&self, msg: RelayMessage) -> Result<(), TransportError> {
+        self.tx
+            .send(WsMessage::Text(msg.as_json()))
+            .map_err(|_| TransportError::ConnectionClosed)
+    }
+
+    /// Receive the next [`ClientMessage`]
+    ///
+    /// Return `None` when the client closes the connection.
+    pub async fn recv(&mut self) -> Option<ClientMessage> {
+        while let Some(msg) = self.rx.recv().await {
+            if let WsMessage::Text(json) = msg {
+                match ClientMessage::from_json(json) {
+                    Ok(msg) => return Some(msg),
+                    Err(e) => tracing::error!("Invalid client message: {e}"),
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::{EventBuilder, Keys};
+
+    use super::*;
+    use crate::relay::{Relay, RelayOptions, RelaySendOptions};
+
+    #[tokio::test]
+    async fn test_duplex_transport() {
+        let (transport, mut listener) = DuplexTransport::new();
+        let url = Url::parse("ws://duplex.local").unwrap();
+
+        let relay = Relay::with_opts(url.clone(), RelayOptions::new().transport(transport));
+        relay.connect(Some(Duration::from_secs(1))).await;
+        assert!(relay.is_connected().await);
+
+        let mut connection = listener.accept().await.unwrap();
+        assert_eq!(connection.url(), &url);
+
+        // Accept all the events
+        tokio::spawn(async move {
+            while let Some(msg) = connection.recv().await {
+                if let ClientMessage::Event(event) = msg {
+                    connection
+                        .send(RelayMessage::ok(event.id(), true, ""))
+                        .unwrap();
+                }
+            }
+        });
+
+        let keys = Keys::generate();
+        let event = EventBuilder::text_note("Hello", [])
+            .to_event(&keys)
+            .unwrap();
+        let event_id = relay
+            .send_event(event.clone(), RelaySendOptions::new())
+            .await
+            .unwrap();
+        assert_eq!(event_id, event.id());
+    }
+}
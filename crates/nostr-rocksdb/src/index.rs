@@ -0,0 +1,895 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! RocksDB persistent indexes
+//!
+//! Every index key ends with `reverse created_at (8 bytes) + event id (32 bytes)`,
+//! so that a forward iteration over a prefix returns the newest events first.
+
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::Arc;
+
+use nostr::hashes::siphash24::Hash as SipHash24;
+use nostr::hashes::Hash;
+use nostr::nips::nip01::Coordinate;
+use nostr::{Event, EventId, Filter, Kind, PublicKey, SingleLetterTag, Timestamp};
+use nostr_database::search::{self, query_tokens, term_frequencies, term_score};
+use nostr_database::{DatabaseError, FlatBufferDecode};
+use rocksdb::{
+    BoundColumnFamily, Direction, IteratorMode, OptimisticTransactionDB, WriteBatchWithTransaction,
+};
+
+use crate::{
+    AUTHOR_INDEX_CF, AUTHOR_KIND_INDEX_CF, COORDINATE_INDEX_CF, CREATED_AT_INDEX_CF,
+    DELETED_COORDINATES_CF, DELETED_IDS_CF, EVENTS_CF, KIND_INDEX_CF, SEARCH_INDEX_CF,
+    TAG_INDEX_CF,
+};
+
+const EVENT_ID_SIZE: usize = 32;
+const TIMESTAMP_SIZE: usize = 8;
+const INDEX_SUFFIX_SIZE: usize = TIMESTAMP_SIZE + EVENT_ID_SIZE;
+const HASH_SIZE: usize = 8;
+
+/// Index entry, ordered by `created_at` DESC and then by event ID
+pub(crate) type IndexEntry = (Reverse<Timestamp>, EventId);
+
+type WriteBatch = WriteBatchWithTransaction<true>;
+
+pub(crate) fn cf_handle<'a>(
+    db: &'a OptimisticTransactionDB,
+    name: &str,
+) -> Result<Arc<BoundColumnFamily<'a>>, DatabaseError> {
+    db.cf_handle(name).ok_or(DatabaseError::NotFound)
+}
+
+#[inline]
+fn hash<S>(value: S) -> [u8; HASH_SIZE]
+where
+    S: AsRef<str>,
+{
+    let mut inner: [u8; HASH_SIZE] = [0u8; HASH_SIZE];
+    let hash = SipHash24::hash(value.as_ref().as_bytes());
+    inner.copy_from_slice(&hash[..HASH_SIZE]);
+    inner
+}
+
+#[inline]
+fn reverse_timestamp(timestamp: Timestamp) -> [u8; TIMESTAMP_SIZE] {
+    (u64::MAX - timestamp.as_u64()).to_be_bytes()
+}
+
+fn author_prefix(author: &PublicKey) -> Vec<u8> {
+    author.to_bytes().to_vec()
+}
+
+fn kind_prefix(kind: Kind) -> Vec<u8> {
+    kind.as_u64().to_be_bytes().to_vec()
+}
+
+fn author_kind_prefix(author: &PublicKey, kind: Kind) -> Vec<u8> {
+    let mut prefix: Vec<u8> = author_prefix(author);
+    prefix.extend_from_slice(&kind.as_u64().to_be_bytes());
+    prefix
+}
+
+fn tag_prefix<S>(tag: &SingleLetterTag, value: S) -> Vec<u8>
+where
+    S: AsRef<str>,
+{
+    let mut prefix: Vec<u8> = Vec::with_capacity(1 + HASH_SIZE);
+    prefix.push(tag.as_char() as u8);
+    prefix.extend_from_slice(&hash(value));
+    prefix
+}
+
+fn coordinate_key<S>(kind: Kind, author: &PublicKey, identifier: S) -> Vec<u8>
+where
+    S: AsRef<str>,
+{
+    let mut key: Vec<u8> = kind_prefix(kind);
+    key.extend_from_slice(&author.to_bytes());
+    key.extend_from_slice(&hash(identifier));
+    key
+}
+
+fn index_key(prefix: &[u8], created_at: Timestamp, event_id: &EventId) -> Vec<u8> {
+    let mut key: Vec<u8> = Vec::with_capacity(prefix.len() + INDEX_SUFFIX_SIZE);
+    key.extend_from_slice(prefix);
+    key.extend_from_slice(&reverse_timestamp(created_at));
+    key.extend_from_slice(event_id.as_bytes());
+    key
+}
+
+/// Search index prefix: `token + 0x00`
+fn search_prefix(token: &str) -> Vec<u8> {
+    let mut prefix: Vec<u8> = Vec::with_capacity(token.len() + 1);
+    prefix.extend_from_slice(token.as_bytes());
+    prefix.push(0);
+    prefix
+}
+
+/// Get the search index entries of an [`Event`], with the token occurrences as value
+fn search_keys(event: &Event) -> Vec<(Vec<u8>, [u8; 4])> {
+    match search::searchable_text(event.kind(), event.content()) {
+        Some(text) => term_frequencies(&text)
+            .into_iter()
+            .map(|(token, tf)| {
+                let key: Vec<u8> =
+                    index_key(&search_prefix(&token), event.created_at(), &event.id());
+                (key, tf.to_be_bytes())
+            })
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+/// Encode `created_at + event id` (used as value of the coordinate index)
+fn encode_entry(created_at: Timestamp, event_id: &EventId) -> Vec<u8> {
+    let mut value: Vec<u8> = Vec::with_capacity(INDEX_SUFFIX_SIZE);
+    value.extend_from_slice(&created_at.as_u64().to_be_bytes());
+    value.extend_from_slice(event_id.as_bytes());
+    value
+}
+
+fn decode_entry(value: &[u8]) -> Option<(Timestamp, EventId)> {
+    if value.len() != INDEX_SUFFIX_SIZE {
+        return None;
+    }
+    let mut created_at: [u8; TIMESTAMP_SIZE] = [0u8; TIMESTAMP_SIZE];
+    created_at.copy_from_slice(&value[..TIMESTAMP_SIZE]);
+    let created_at = Timestamp::from(u64::from_be_bytes(created_at));
+    let event_id = EventId::from_slice(&value[TIMESTAMP_SIZE..]).ok()?;
+    Some((created_at, event_id))
+}
+
+/// Decode the `reverse created_at + event id` suffix of an index key
+fn decode_index_key(key: &[u8]) -> Option<(Timestamp, EventId)> {
+    if key.len() < INDEX_SUFFIX_SIZE {
+        return None;
+    }
+    let suffix: &[u8] = &key[key.len() - INDEX_SUFFIX_SIZE..];
+    let mut reversed: [u8; TIMESTAMP_SIZE] = [0u8; TIMESTAMP_SIZE];
+    reversed.copy_from_slice(&suffix[..TIMESTAMP_SIZE]);
+    let created_at = Timestamp::from(u64::MAX - u64::from_be_bytes(reversed));
+    let event_id = EventId::from_slice(&suffix[TIMESTAMP_SIZE..]).ok()?;
+    Some((created_at, event_id))
+}
+
+/// Get all the `(column family, key)` index entries of an [`Event`]
+fn index_keys(event: &Event) -> Vec<(&'static str, Vec<u8>)> {
+    let id: EventId = event.id();
+    let author: &PublicKey = event.author_ref();
+    let created_at: Timestamp = event.created_at();
+    let kind: Kind = event.kind();
+
+    let mut keys: Vec<(&'static str, Vec<u8>)> = vec![
+        (CREATED_AT_INDEX_CF, index_key(&[], created_at, &id)),
+        (
+            AUTHOR_INDEX_CF,
+            index_key(&author_prefix(author), created_at, &id),
+        ),
+        (
+            KIND_INDEX_CF,
+            index_key(&kind_prefix(kind), created_at, &id),
+        ),
+        (
+            AUTHOR_KIND_INDEX_CF,
+            index_key(&author_kind_prefix(author, kind), created_at, &id),
+        ),
+    ];
+
+    for (tag, value) in event
+        .iter_tags()
+        .filter_map(|t| Some((t.single_letter_tag()?, t.content()?)))
+    {
+        keys.push((
+            TAG_INDEX_CF,
+            index_key(&tag_prefix(&tag, value.to_string()), created_at, &id),
+        ));
+    }
+
+    keys
+}
+
+pub(crate) fn get_event(
+    db: &OptimisticTransactionDB,
+    event_id: &EventId,
+) -> Result<Option<Event>, DatabaseError> {
+    let cf = cf_handle(db, EVENTS_CF)?;
+    match db
+        .get_pinned_cf(&cf, event_id.as_bytes())
+        .map_err(DatabaseError::backend)?
+    {
+        Some(value) => Ok(Some(Event::decode(&value).map_err(DatabaseError::backend)?)),
+        None => Ok(None),
+    }
+}
+
+pub(crate) fn has_event_id_been_deleted(
+    db: &OptimisticTransactionDB,
+    event_id: &EventId,
+) -> Result<bool, DatabaseError> {
+    let cf = cf_handle(db, DELETED_IDS_CF)?;
+    Ok(db
+        .get_pinned_cf(&cf, event_id.as_bytes())
+        .map_err(DatabaseError::backend)?
+        .is_some())
+}
+
+fn coordinate_deleted_at(
+    db: &OptimisticTransactionDB,
+    key: &[u8],
+) -> Result<Option<Timestamp>, DatabaseError> {
+    let cf = cf_handle(db, DELETED_COORDINATES_CF)?;
+    match db.get_pinned_cf(&cf, key).map_err(DatabaseError::backend)? {
+        Some(value) if value.len() == TIMESTAMP_SIZE => {
+            let mut deleted_at: [u8; TIMESTAMP_SIZE] = [0u8; TIMESTAMP_SIZE];
+            deleted_at.copy_from_slice(&value);
+            Ok(Some(Timestamp::from(u64::from_be_bytes(deleted_at))))
+        }
+        _ => Ok(None),
+    }
+}
+
+pub(crate) fn has_coordinate_been_deleted(
+    db: &OptimisticTransactionDB,
+    coordinate: &Coordinate,
+    timestamp: Timestamp,
+) -> Result<bool, DatabaseError> {
+    let key: Vec<u8> = coordinate_key(
+        coordinate.kind,
+        &coordinate.public_key,
+        &coordinate.identifier,
+    );
+    Ok(coordinate_deleted_at(db, &key)?.map_or(false, |t| t >= timestamp))
+}
+
+/// Iterate an index prefix, from `until` to `since`
+fn iter_prefix<'a>(
+    db: &'a OptimisticTransactionDB,
+    cf: &Arc<BoundColumnFamily<'a>>,
+    prefix: Vec<u8>,
+    since: Option<Timestamp>,
+    until: Option<Timestamp>,
+) -> impl Iterator<Item = (Timestamp, EventId)> + 'a {
+    let mut start: Vec<u8> = prefix.clone();
+    if let Some(until) = until {
+        start.extend_from_slice(&reverse_timestamp(until));
+    }
+
+    db.iterator_cf(cf, IteratorMode::From(&start, Direction::Forward))
+        .flatten()
+        .take_while(move |(key, _)| key.starts_with(&prefix))
+        .filter_map(|(key, _)| decode_index_key(&key))
+        .take_while(move |(created_at, _)| since.map_or(true, |since| *created_at >= since))
+}
+
+/// Full-text search
+///
+/// Return the events that contain **all** the `tokens` and match the filter,
+/// sorted by relevance.
+fn search(
+    db: &OptimisticTransactionDB,
+    filter: &Filter,
+    tokens: BTreeSet<String>,
+) -> Result<Vec<(f64, IndexEntry)>, DatabaseError> {
+    let cf = cf_handle(db, SEARCH_INDEX_CF)?;
+
+    // Collect postings of each token
+    let mut postings: Vec<HashMap<EventId, (Timestamp, u32)>> = Vec::with_capacity(tokens.len());
+    for token in tokens.iter() {
+        let prefix: Vec<u8> = search_prefix(token);
+        let mut start: Vec<u8> = prefix.clone();
+        if let Some(until) = filter.until {
+            start.extend_from_slice(&reverse_timestamp(until));
+        }
+
+        let mut ids: HashMap<EventId, (Timestamp, u32)> = HashMap::new();
+        for (key, value) in db
+            .iterator_cf(&cf, IteratorMode::From(&start, Direction::Forward))
+            .flatten()
+            .take_while(|(key, _)| key.starts_with(&prefix))
+        {
+            if let Some((created_at, id)) = decode_index_key(&key) {
+                if filter.since.map_or(false, |since| created_at < since) {
+                    break;
+                }
+                let mut tf: [u8; 4] = [0u8; 4];
+                if value.len() == tf.len() {
+                    tf.copy_from_slice(&value);
+                }
+                ids.insert(id, (created_at, u32::from_be_bytes(tf)));
+            }
+        }
+
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        postings.push(ids);
+    }
+
+    // Start from the rarest token
+    postings.sort_by_key(|ids| ids.len());
+
+    // Events must be decoded and matched only if there are other conditions to check
+    let covered: bool = filter.ids.is_none()
+        && filter.authors.is_none()
+        && filter.kinds.is_none()
+        && filter.generic_tags.is_empty();
+
+    let mut entries: Vec<(f64, IndexEntry)> = Vec::new();
+    if let Some(first) = postings.first() {
+        'outer: for (id, (created_at, _)) in first.iter() {
+            let mut score: f64 = 0.0;
+            for ids in postings.iter() {
+                match ids.get(id) {
+                    Some((_, tf)) => score += term_score(*tf, ids.len()),
+                    None => continue 'outer,
+                }
+            }
+
+            if !covered {
+                match get_event(db, id)? {
+                    Some(event) if filter.match_event(&event) => {}
+                    _ => continue,
+                }
+            }
+
+            entries.push((score, (Reverse(*created_at), *id)));
+        }
+    }
+
+    entries.sort_by(|(a_score, a), (b_score, b)| b_score.total_cmp(a_score).then_with(|| a.cmp(b)));
+
+    if let Some(limit) = filter.limit {
+        entries.truncate(limit);
+    }
+
+    Ok(entries)
+}
+
+/// Query the indexes
+///
+/// Results are sorted by `created_at` DESC (and by event ID for the same timestamp).
+/// Events matched by a `search` filter come first, sorted by relevance.
+pub(crate) fn query(
+    db: &OptimisticTransactionDB,
+    filters: Vec<Filter>,
+) -> Result<Vec<IndexEntry>, DatabaseError> {
+    let mut matching: BTreeSet<IndexEntry> = BTreeSet::new();
+    let mut scores: HashMap<EventId, f64> = HashMap::new();
+
+    for filter in filters.into_iter() {
+        if let (Some(since), Some(until)) = (filter.since, filter.until) {
+            if since > until {
+                continue;
+            }
+        }
+
+        // Full-text search
+        if let Some(tokens) = filter
+            .search
+            .as_deref()
+            .map(query_tokens)
+            .filter(|tokens| !tokens.is_empty())
+        {
+            for (score, entry) in search(db, &filter, tokens)?.into_iter() {
+                scores
+                    .entry(entry.1)
+                    .and_modify(|s| *s = s.max(score))
+                    .or_insert(score);
+                matching.insert(entry);
+            }
+            continue;
+        }
+
+        let limit: Option<usize> = filter.limit;
+        let mut entries: BTreeSet<IndexEntry> = BTreeSet::new();
+
+        // Lookup by ID
+        if let Some(ids) = &filter.ids {
+            for id in ids.iter() {
+                if let Some(event) = get_event(db, id)? {
+                    if filter.match_event(&event) {
+                        entries.insert((Reverse(event.created_at()), *id));
+                    }
+                }
+            }
+        } else {
+            // Choose the index to iterate
+            let authors = filter.authors.as_ref().filter(|a| !a.is_empty());
+            let kinds = filter.kinds.as_ref().filter(|k| !k.is_empty());
+            let tag = filter
+                .generic_tags
+                .iter()
+                .filter(|(_, values)| !values.is_empty())
+                .min_by_key(|(_, values)| values.len());
+
+            let (cf_name, prefixes): (&str, Vec<Vec<u8>>) = match (authors, kinds, tag) {
+                (Some(authors), Some(kinds), _) => (
+                    AUTHOR_KIND_INDEX_CF,
+                    authors
+                        .iter()
+                        .flat_map(|a| kinds.iter().map(move |k| author_kind_prefix(a, *k)))
+                        .collect(),
+                ),
+                (Some(authors), None, _) => {
+                    (AUTHOR_INDEX_CF, authors.iter().map(author_prefix).collect())
+                }
+                (None, _, Some((tag, values))) => (
+                    TAG_INDEX_CF,
+                    values
+                        .iter()
+                        .map(|v| tag_prefix(tag, v.to_string()))
+                        .collect(),
+                ),
+                (None, Some(kinds), None) => (
+                    KIND_INDEX_CF,
+                    kinds.iter().map(|k| kind_prefix(*k)).collect(),
+                ),
+                (None, None, None) => (CREATED_AT_INDEX_CF, vec![Vec::new()]),
+            };
+
+            // Events must be decoded and matched only if there are tags to check
+            let covered: bool = filter.generic_tags.is_empty();
+
+            let cf = cf_handle(db, cf_name)?;
+            for prefix in prefixes.into_iter() {
+                let iter = iter_prefix(db, &cf, prefix, filter.since, filter.until);
+                let mut found: usize = 0;
+                for (created_at, id) in iter {
+                    if limit.map_or(false, |limit| found >= limit) {
+                        break;
+                    }
+
+                    if !covered {
+                        match get_event(db, &id)? {
+                            Some(event) if filter.match_event(&event) => {}
+                            _ => continue,
+                        }
+                    }
+
+                    entries.insert((Reverse(created_at), id));
+                    found += 1;
+                }
+            }
+        }
+
+        match limit {
+            Some(limit) => matching.extend(entries.into_iter().take(limit)),
+            None => matching.extend(entries),
+        }
+    }
+
+    let mut matching: Vec<IndexEntry> = matching.into_iter().collect();
+
+    // Sort by relevance (stable sort: same relevance are still sorted by timestamp)
+    if !scores.is_empty() {
+        matching.sort_by(|(_, a), (_, b)| {
+            let a: f64 = scores.get(a).copied().unwrap_or_default();
+            let b: f64 = scores.get(b).copied().unwrap_or_default();
+            b.total_cmp(&a)
+        });
+    }
+
+    Ok(matching)
+}
+
+fn put_event(
+    db: &OptimisticTransactionDB,
+    batch: &mut WriteBatch,
+    event: &Event,
+    value: &[u8],
+) -> Result<(), DatabaseError> {
+    let id: EventId = event.id();
+
+    // Save event
+    let events_cf = cf_handle(db, EVENTS_CF)?;
+    batch.put_cf(&events_cf, id.as_bytes(), value);
+
+    // Save indexes
+    for (cf_name, key) in index_keys(event).into_iter() {
+        let cf = cf_handle(db, cf_name)?;
+        batch.put_cf(&cf, key, []);
+    }
+
+    let search_cf = cf_handle(db, SEARCH_INDEX_CF)?;
+    for (key, tf) in search_keys(event).into_iter() {
+        batch.put_cf(&search_cf, key, tf);
+    }
+
+    if event.kind().is_parameterized_replaceable() {
+        if let Some(identifier) = event.identifier() {
+            let cf = cf_handle(db, COORDINATE_INDEX_CF)?;
+            let key: Vec<u8> = coordinate_key(event.kind(), event.author_ref(), identifier);
+            batch.put_cf(&cf, key, encode_entry(event.created_at(), &id));
+        }
+    }
+
+    Ok(())
+}
+
+/// Remove event and indexes, and mark event ID as deleted
+fn discard_event(
+    db: &OptimisticTransactionDB,
+    batch: &mut WriteBatch,
+    event_id: &EventId,
+) -> Result<(), DatabaseError> {
+    if let Some(event) = get_event(db, event_id)? {
+        let events_cf = cf_handle(db, EVENTS_CF)?;
+        batch.delete_cf(&events_cf, event_id.as_bytes());
+
+        for (cf_name, key) in index_keys(&event).into_iter() {
+            let cf = cf_handle(db, cf_name)?;
+            batch.delete_cf(&cf, key);
+        }
+
+        let search_cf = cf_handle(db, SEARCH_INDEX_CF)?;
+        for (key, _) in search_keys(&event).into_iter() {
+            batch.delete_cf(&search_cf, key);
+        }
+
+        if event.kind().is_parameterized_replaceable() {
+            if let Some(identifier) = event.identifier() {
+                let cf = cf_handle(db, COORDINATE_INDEX_CF)?;
+                let key: Vec<u8> = coordinate_key(event.kind(), event.author_ref(), identifier);
+                if let Some(value) = db
+                    .get_pinned_cf(&cf, &key)
+                    .map_err(DatabaseError::backend)?
+                {
+                    if decode_entry(&value).map_or(false, |(_, id)| &id == event_id) {
+                        batch.delete_cf(&cf, key);
+                    }
+                }
+            }
+        }
+    }
+
+    let deleted_cf = cf_handle(db, DELETED_IDS_CF)?;
+    batch.put_cf(&deleted_cf, event_id.as_bytes(), []);
+
+    Ok(())
+}
+
+/// Discard events (see [`discard_event`])
+pub(crate) fn discard_events<'a, I>(
+    db: &OptimisticTransactionDB,
+    ids: I,
+) -> Result<(), DatabaseError>
+where
+    I: IntoIterator<Item = &'a EventId>,
+{
+    let mut batch = WriteBatch::default();
+    for id in ids.into_iter() {
+        discard_event(db, &mut batch, id)?;
+    }
+    db.write(batch).map_err(DatabaseError::backend)
+}
+
+/// Index and save [`Event`]
+///
+/// Follow the same rules of [`DatabaseIndexes`](nostr_database::DatabaseIndexes):
+/// replaceable and parameterized replaceable events replace the older ones,
+/// deletion events remove the referenced events of the same author.
+///
+/// Return `true` if the event has been stored.
+pub(crate) fn save_event(
+    db: &OptimisticTransactionDB,
+    event: &Event,
+    value: &[u8],
+) -> Result<bool, DatabaseError> {
+    // Check if it's expired or ephemeral
+    if event.is_expired() || event.is_ephemeral() {
+        return Ok(false);
+    }
+
+    let event_id: EventId = event.id();
+
+    // Check if was already added or deleted
+    if get_event(db, &event_id)?.is_some() || has_event_id_been_deleted(db, &event_id)? {
+        return Ok(false);
+    }
+
+    let author: &PublicKey = event.author_ref();
+    let created_at: Timestamp = event.created_at();
+    let kind: Kind = event.kind();
+
+    let mut batch = WriteBatch::default();
+    let mut to_store: bool = true;
+    let mut to_discard: HashSet<EventId> = HashSet::new();
+
+    if kind.is_replaceable() {
+        let cf = cf_handle(db, AUTHOR_KIND_INDEX_CF)?;
+        for (ts, id) in iter_prefix(db, &cf, author_kind_prefix(author, kind), None, None) {
+            if ts > created_at {
+                to_store = false;
+            } else {
+                to_discard.insert(id);
+            }
+        }
+    } else if kind.is_parameterized_replaceable() {
+        match event.identifier() {
+            Some(identifier) => {
+                let key: Vec<u8> = coordinate_key(kind, author, identifier);
+                if coordinate_deleted_at(db, &key)?.map_or(false, |t| t >= created_at) {
+                    to_store = false;
+                } else {
+                    let cf = cf_handle(db, COORDINATE_INDEX_CF)?;
+                    if let Some(value) = db
+                        .get_pinned_cf(&cf, &key)
+                        .map_err(DatabaseError::backend)?
+                    {
+                        if let Some((ts, id)) = decode_entry(&value) {
+                            if ts > created_at {
+                                to_store = false;
+                            } else {
+                                to_discard.insert(id);
+                            }
+                        }
+                    }
+                }
+            }
+            None => to_store = false,
+        }
+    } else if kind == Kind::EventDeletion {
+        // Check `e` tags
+        for id in event.event_ids() {
+            if let Some(ev) = get_event(db, id)? {
+                if ev.author_ref() == author && ev.created_at() <= created_at {
+                    to_discard.insert(*id);
+                }
+            }
+        }
+
+        // Check `a` tags
+        let deleted_coordinates_cf = cf_handle(db, DELETED_COORDINATES_CF)?;
+        for coordinate in event.coordinates() {
+            if &coordinate.public_key == author {
+                // Save deleted coordinate at certain timestamp
+                let key: Vec<u8> = coordinate_key(coordinate.kind, author, &coordinate.identifier);
+                if coordinate_deleted_at(db, &key)?.map_or(true, |t| t < created_at) {
+                    batch.put_cf(
+                        &deleted_coordinates_cf,
+                        key,
+                        created_at.as_u64().to_be_bytes(),
+                    );
+                }
+
+                let filter: Filter = Filter::from(coordinate).until(created_at);
+                to_discard.extend(query(db, vec![filter])?.into_iter().map(|(_, id)| id));
+            }
+        }
+    }
+
+    // Discard events no longer needed
+    for id in to_discard.iter() {
+        discard_event(db, &mut batch, id)?;
+    }
+
+    // Save event
+    if to_store {
+        put_event(db, &mut batch, event, value)?;
+    }
+
+    // Write batch changes
+    db.write(batch).map_err(DatabaseError::backend)?;
+
+    Ok(to_store)
+}
+
+/// Populate the search index of events saved before it was introduced
+pub(crate) fn build_search<'a, I>(
+    db: &OptimisticTransactionDB,
+    events: I,
+) -> Result<(), DatabaseError>
+where
+    I: Iterator<Item = &'a Event>,
+{
+    let mut batch = WriteBatch::default();
+    let search_cf = cf_handle(db, SEARCH_INDEX_CF)?;
+    for event in events {
+        for (key, tf) in search_keys(event).into_iter() {
+            batch.put_cf(&search_cf, key, tf);
+        }
+    }
+    db.write(batch).map_err(DatabaseError::backend)
+}
+
+/// Populate indexes of events saved before the persistent indexes were introduced
+///
+/// Can be called for consecutive chunks of events: the coordinate and deleted coordinate
+/// entries are merged with the ones written by the previous chunks.
+/// The replaced and deleted events are indexed too (see [`collect_discarded`]).
+pub(crate) fn build(db: &OptimisticTransactionDB, events: &[Event]) -> Result<(), DatabaseError> {
+    let mut batch = WriteBatch::default();
+    let mut coordinates: HashMap<Vec<u8>, (Timestamp, EventId)> = HashMap::new();
+    let mut deleted_coordinates: HashMap<Vec<u8>, Timestamp> = HashMap::new();
+
+    let deleted_coordinates_cf = cf_handle(db, DELETED_COORDINATES_CF)?;
+    let coordinate_cf = cf_handle(db, COORDINATE_INDEX_CF)?;
+    let search_cf = cf_handle(db, SEARCH_INDEX_CF)?;
+
+    for event in events.iter() {
+        for (cf_name, key) in index_keys(event).into_iter() {
+            let cf = cf_handle(db, cf_name)?;
+            batch.put_cf(&cf, key, []);
+        }
+
+        for (key, tf) in search_keys(event).into_iter() {
+            batch.put_cf(&search_cf, key, tf);
+        }
+
+        // Keep only the latest event of each coordinate
+        if event.kind().is_parameterized_replaceable() {
+            if let Some(identifier) = event.identifier() {
+                let key: Vec<u8> = coordinate_key(event.kind(), event.author_ref(), identifier);
+                let entry: (Timestamp, EventId) = (event.created_at(), event.id());
+                let latest: Option<(Timestamp, EventId)> = match coordinates.get(&key) {
+                    Some(latest) => Some(*latest),
+                    None => db
+                        .get_pinned_cf(&coordinate_cf, &key)
+                        .map_err(DatabaseError::backend)?
+                        .and_then(|value| decode_entry(&value)),
+                };
+                if latest.map_or(true, |latest| is_newer(entry, latest)) {
+                    coordinates.insert(key, entry);
+                }
+            }
+        }
+
+        // Persist deleted coordinates
+        if event.kind() == Kind::EventDeletion {
+            for coordinate in event.coordinates() {
+                if &coordinate.public_key == event.author_ref() {
+                    let key: Vec<u8> = coordinate_key(
+                        coordinate.kind,
+                        &coordinate.public_key,
+                        &coordinate.identifier,
+                    );
+                    let deleted_at: Option<Timestamp> = match deleted_coordinates.get(&key) {
+                        Some(deleted_at) => Some(*deleted_at),
+                        None => coordinate_deleted_at(db, &key)?,
+                    };
+                    if deleted_at.map_or(true, |t| t < event.created_at()) {
+                        deleted_coordinates.insert(key, event.created_at());
+                    }
+                }
+            }
+        }
+    }
+
+    for (key, (created_at, event_id)) in coordinates.into_iter() {
+        batch.put_cf(&coordinate_cf, key, encode_entry(created_at, &event_id));
+    }
+
+    for (key, deleted_at) in deleted_coordinates.into_iter() {
+        batch.put_cf(
+            &deleted_coordinates_cf,
+            key,
+            deleted_at.as_u64().to_be_bytes(),
+        );
+    }
+
+    db.write(batch).map_err(DatabaseError::backend)
+}
+
+/// Check if `a` takes precedence over `b` (same order of the index keys)
+fn is_newer(a: (Timestamp, EventId), b: (Timestamp, EventId)) -> bool {
+    a.0 > b.0 || (a.0 == b.0 && a.1 < b.1)
+}
+
+/// Collect the events that must be discarded after [`build`]
+///
+/// Only read the indexes, so the result doesn't depend on the order of the events.
+pub(crate) fn collect_discarded(
+    db: &OptimisticTransactionDB,
+    event: &Event,
+    to_discard: &mut HashSet<EventId>,
+) -> Result<(), DatabaseError> {
+    let event_id: EventId = event.id();
+    let author: &PublicKey = event.author_ref();
+    let created_at: Timestamp = event.created_at();
+    let kind: Kind = event.kind();
+
+    if event.is_expired() || event.is_ephemeral() {
+        to_discard.insert(event_id);
+    } else if kind.is_replaceable() {
+        let cf = cf_handle(db, AUTHOR_KIND_INDEX_CF)?;
+        let mut entries = iter_prefix(db, &cf, author_kind_prefix(author, kind), None, None);
+        if entries.next().map_or(false, |(_, id)| id != event_id) {
+            to_discard.insert(event_id);
+        }
+    } else if kind.is_parameterized_replaceable() {
+        match event.identifier() {
+            Some(identifier) => {
+                let key: Vec<u8> = coordinate_key(kind, author, identifier);
+                let cf = cf_handle(db, COORDINATE_INDEX_CF)?;
+                let latest: Option<(Timestamp, EventId)> = db
+                    .get_pinned_cf(&cf, &key)
+                    .map_err(DatabaseError::backend)?
+                    .and_then(|value| decode_entry(&value));
+                if latest.map_or(false, |(_, id)| id != event_id)
+                    || coordinate_deleted_at(db, &key)?.map_or(false, |t| t >= created_at)
+                {
+                    to_discard.insert(event_id);
+                }
+            }
+            None => {
+                to_discard.insert(event_id);
+            }
+        }
+    } else if kind == Kind::EventDeletion {
+        // Check `e` tags
+        for id in event.event_ids() {
+            if let Some(ev) = get_event(db, id)? {
+                if ev.author_ref() == author && ev.created_at() <= created_at {
+                    to_discard.insert(*id);
+                }
+            }
+        }
+
+        // Check `a` tags
+        for coordinate in event.coordinates() {
+            if &coordinate.public_key == author {
+                let filter: Filter = Filter::from(coordinate).until(created_at);
+                to_discard.extend(query(db, vec![filter])?.into_iter().map(|(_, id)| id));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::{Keys, SingleLetterTag};
+
+    use super::*;
+
+    #[test]
+    fn test_index_key() {
+        let id = EventId::all_zeros();
+        let key = index_key(b"prefix", Timestamp::from(1_000), &id);
+        assert!(key.starts_with(b"prefix"));
+        assert_eq!(key.len(), 6 + INDEX_SUFFIX_SIZE);
+        assert_eq!(decode_index_key(&key), Some((Timestamp::from(1_000), id)));
+        assert_eq!(decode_index_key(&key[..INDEX_SUFFIX_SIZE - 1]), None);
+
+        // Newest first
+        let old = index_key(b"prefix", Timestamp::from(1_000), &id);
+        let new = index_key(b"prefix", Timestamp::from(2_000), &id);
+        assert!(new < old);
+    }
+
+    #[test]
+    fn test_entry() {
+        let id = EventId::all_zeros();
+        let value = encode_entry(Timestamp::from(1_000), &id);
+        assert_eq!(decode_entry(&value), Some((Timestamp::from(1_000), id)));
+        assert_eq!(decode_entry(&value[1..]), None);
+    }
+
+    #[test]
+    fn test_prefixes() {
+        let keys = Keys::generate();
+        let author = keys.public_key();
+
+        let prefix = author_kind_prefix(&author, Kind::TextNote);
+        assert!(prefix.starts_with(&author_prefix(&author)));
+        assert_ne!(prefix, author_kind_prefix(&author, Kind::Metadata));
+
+        // Search tokens can't be prefix of other tokens
+        assert!(!search_prefix("nostrich").starts_with(&search_prefix("nostr")));
+
+        let tag = SingleLetterTag::lowercase(nostr::Alphabet::T);
+        assert_eq!(tag_prefix(&tag, "nostr"), tag_prefix(&tag, "nostr"));
+        assert_ne!(tag_prefix(&tag, "nostr"), tag_prefix(&tag, "bitcoin"));
+
+        let a = coordinate_key(Kind::ParameterizedReplaceable(30000), &author, "a");
+        let b = coordinate_key(Kind::ParameterizedReplaceable(30000), &author, "b");
+        assert_ne!(a, b);
+    }
+}
@@ -20,25 +20,44 @@ use async_trait::async_trait;
 use nostr::nips::nip01::Coordinate;
 use nostr::{Event, EventId, Filter, Timestamp, Url};
 use nostr_database::{
-    Backend, DatabaseError, DatabaseIndexes, EventIndexResult, FlatBufferBuilder, FlatBufferDecode,
-    FlatBufferEncode, NostrDatabase, Order, TempEvent,
+    Backend, DatabaseError, FlatBufferBuilder, FlatBufferDecode, FlatBufferEncode, NostrDatabase,
+    Order,
 };
 use rocksdb::{
-    BoundColumnFamily, ColumnFamilyDescriptor, DBCompactionStyle, DBCompressionType, IteratorMode,
-    OptimisticTransactionDB, Options, WriteBatchWithTransaction,
+    BoundColumnFamily, ColumnFamilyDescriptor, DBCompactionStyle, DBCompressionType, Direction,
+    IteratorMode, OptimisticTransactionDB, Options, WriteBatchWithTransaction,
 };
 use tokio::sync::RwLock;
 
+mod index;
 mod ops;
 
+use self::index::IndexEntry;
+
 const EVENTS_CF: &str = "events";
 const EVENTS_SEEN_BY_RELAYS_CF: &str = "event-seen-by-relays";
+const CREATED_AT_INDEX_CF: &str = "created-at-index";
+const AUTHOR_INDEX_CF: &str = "author-index";
+const KIND_INDEX_CF: &str = "kind-index";
+const AUTHOR_KIND_INDEX_CF: &str = "author-kind-index";
+const TAG_INDEX_CF: &str = "tag-index";
+const COORDINATE_INDEX_CF: &str = "coordinate-index";
+const SEARCH_INDEX_CF: &str = "search-index";
+const DELETED_IDS_CF: &str = "deleted-ids";
+const DELETED_COORDINATES_CF: &str = "deleted-coordinates";
+const OUTBOX_CF: &str = "outbox";
+
+/// Key of the indexes version (stored in the default column family)
+const INDEXES_VERSION_KEY: &[u8] = b"indexes-version";
+/// Latest indexes version
+const INDEXES_VERSION: u8 = 2;
+/// Number of events processed per write batch while building the indexes
+const MIGRATION_BATCH_SIZE: usize = 1000;
 
 /// RocksDB Nostr Database
 #[derive(Debug, Clone)]
 pub struct RocksDatabase {
     db: Arc<OptimisticTransactionDB>,
-    indexes: DatabaseIndexes,
     fbb: Arc<RwLock<FlatBufferBuilder<'static>>>,
 }
 
@@ -66,6 +85,16 @@ fn column_families() -> Vec<ColumnFamilyDescriptor> {
     vec![
         ColumnFamilyDescriptor::new(EVENTS_CF, default_opts()),
         ColumnFamilyDescriptor::new(EVENTS_SEEN_BY_RELAYS_CF, relay_urls_opts),
+        ColumnFamilyDescriptor::new(CREATED_AT_INDEX_CF, default_opts()),
+        ColumnFamilyDescriptor::new(AUTHOR_INDEX_CF, default_opts()),
+        ColumnFamilyDescriptor::new(KIND_INDEX_CF, default_opts()),
+        ColumnFamilyDescriptor::new(AUTHOR_KIND_INDEX_CF, default_opts()),
+        ColumnFamilyDescriptor::new(TAG_INDEX_CF, default_opts()),
+        ColumnFamilyDescriptor::new(COORDINATE_INDEX_CF, default_opts()),
+        ColumnFamilyDescriptor::new(SEARCH_INDEX_CF, default_opts()),
+        ColumnFamilyDescriptor::new(DELETED_IDS_CF, default_opts()),
+        ColumnFamilyDescriptor::new(DELETED_COORDINATES_CF, default_opts()),
+        ColumnFamilyDescriptor::new(OUTBOX_CF, default_opts()),
     ]
 }
 
@@ -99,47 +128,106 @@ impl RocksDatabase {
 
         let this = Self {
             db: Arc::new(db),
-            indexes: DatabaseIndexes::new(),
             fbb: Arc::new(RwLock::new(FlatBufferBuilder::with_capacity(70_000))),
         };
 
-        this.build_indexes().await?;
+        this.migrate().await?;
 
         Ok(this)
     }
 
-    fn cf_handle(&self, name: &str) -> Result<Arc<BoundColumnFamily>, DatabaseError> {
-        self.db.cf_handle(name).ok_or(DatabaseError::NotFound)
+    fn cf_handle(&self, name: &str) -> Result<Arc<BoundColumnFamily<'_>>, DatabaseError> {
+        index::cf_handle(&self.db, name)
     }
 
+    /// Build the persistent indexes of a database created with an older version
     #[tracing::instrument(skip_all)]
-    async fn build_indexes(&self) -> Result<(), DatabaseError> {
-        let cf = self.cf_handle(EVENTS_CF)?;
-        let events = self
+    async fn migrate(&self) -> Result<(), DatabaseError> {
+        let version: Option<u8> = self
             .db
-            .full_iterator_cf(&cf, IteratorMode::Start)
-            .flatten()
-            .filter_map(|(_, value)| TempEvent::decode(&value).ok())
-            .collect();
+            .get_pinned(INDEXES_VERSION_KEY)
+            .map_err(DatabaseError::backend)?
+            .and_then(|v| v.first().copied());
+
+        if version == Some(INDEXES_VERSION) {
+            return Ok(());
+        }
+
+        if version == Some(1) {
+            // Version 1 has all the indexes except the search one
+            tracing::info!("Building search index");
+            let count: usize =
+                self.for_each_chunk(|events| index::build_search(&self.db, events.iter()))?;
+            tracing::info!("Search index built for {} events", count);
+        } else {
+            tracing::info!("Building indexes");
+            let count: usize = self.for_each_chunk(|events| index::build(&self.db, events))?;
+
+            if count > 0 {
+                // Check which events must be discarded
+                let mut to_discard: HashSet<EventId> = HashSet::new();
+                self.for_each_chunk(|events| {
+                    for event in events.iter() {
+                        index::collect_discarded(&self.db, event, &mut to_discard)?;
+                    }
+                    Ok(())
+                })?;
+
+                // Discard events
+                let to_discard: Vec<EventId> = to_discard.into_iter().collect();
+                for ids in to_discard.chunks(MIGRATION_BATCH_SIZE) {
+                    index::discard_events(&self.db, ids.iter())?;
+                }
+
+                tracing::info!(
+                    "Indexes built for {} events ({} discarded)",
+                    count,
+                    to_discard.len()
+                );
+            }
+        }
+
+        self.db
+            .put(INDEXES_VERSION_KEY, [INDEXES_VERSION])
+            .map_err(DatabaseError::backend)
+    }
 
-        // Build indexes
-        let to_discard: HashSet<EventId> = self.indexes.bulk_index(events).await;
+    /// Decode the stored events and pass them to `f` in chunks of [`MIGRATION_BATCH_SIZE`]
+    ///
+    /// Return the number of decoded events.
+    fn for_each_chunk<F>(&self, mut f: F) -> Result<usize, DatabaseError>
+    where
+        F: FnMut(&[Event]) -> Result<(), DatabaseError>,
+    {
+        let cf = self.cf_handle(EVENTS_CF)?;
+        let mut chunk: Vec<Event> = Vec::with_capacity(MIGRATION_BATCH_SIZE);
+        let mut count: usize = 0;
 
-        // Discard events
-        if !to_discard.is_empty() {
-            // Prepare write batch
-            let mut batch = WriteBatchWithTransaction::default();
+        for (_, value) in self.db.full_iterator_cf(&cf, IteratorMode::Start).flatten() {
+            if let Ok(event) = Event::decode(&value) {
+                chunk.push(event);
+            }
 
-            // Discard events no longer needed
-            for event_id in to_discard.into_iter() {
-                batch.delete_cf(&cf, event_id);
+            if chunk.len() >= MIGRATION_BATCH_SIZE {
+                f(&chunk)?;
+                count += chunk.len();
+                chunk.clear();
             }
+        }
 
-            // Write batch changes
-            self.db.write(batch).map_err(DatabaseError::backend)?;
+        if !chunk.is_empty() {
+            f(&chunk)?;
+            count += chunk.len();
         }
 
-        Ok(())
+        Ok(count)
+    }
+
+    async fn query_indexes(&self, filters: Vec<Filter>) -> Result<Vec<IndexEntry>, DatabaseError> {
+        let this = self.clone();
+        tokio::task::spawn_blocking(move || index::query(&this.db, filters))
+            .await
+            .map_err(DatabaseError::backend)?
     }
 }
 
@@ -153,44 +241,17 @@ impl NostrDatabase for RocksDatabase {
 
     #[tracing::instrument(skip_all, level = "trace")]
     async fn save_event(&self, event: &Event) -> Result<bool, Self::Err> {
-        // Index event
-        let EventIndexResult {
-            to_store,
-            to_discard,
-        } = self.indexes.index_event(event).await;
-
-        if to_store {
-            // Acquire FlatBuffers Builder
-            let mut fbb = self.fbb.write().await;
-
-            tokio::task::block_in_place(|| {
-                // Get Column Families
-                let events_cf = self.cf_handle(EVENTS_CF)?;
-
-                // Serialize key and value
-                let id = event.id();
-                let key: &[u8] = id.as_bytes();
-                let value: &[u8] = event.encode(&mut fbb);
-
-                // Prepare write batch
-                let mut batch = WriteBatchWithTransaction::default();
-
-                // Save event
-                batch.put_cf(&events_cf, key, value);
-
-                // Discard events no longer needed
-                for event_id in to_discard.into_iter() {
-                    batch.delete_cf(&events_cf, event_id);
-                }
+        // Acquire FlatBuffers Builder
+        // The lock is held until the end, to index events one at a time
+        let mut fbb = self.fbb.write().await;
 
-                // Write batch changes
-                self.db.write(batch).map_err(DatabaseError::backend)
-            })?;
+        tokio::task::block_in_place(|| {
+            // Serialize value
+            let value: &[u8] = event.encode(&mut fbb);
 
-            Ok(true)
-        } else {
-            Ok(false)
-        }
+            // Index and save event
+            index::save_event(&self.db, event, value)
+        })
     }
 
     #[tracing::instrument(skip_all, level = "trace")]
@@ -198,32 +259,21 @@ impl NostrDatabase for RocksDatabase {
         // Acquire FlatBuffers Builder
         let mut fbb = self.fbb.write().await;
 
-        // Prepare write batch
-        let mut batch = WriteBatchWithTransaction::default();
-
-        let events = self.indexes.bulk_import(events).await;
-
-        // Get Column Family
-        let events_cf = self.cf_handle(EVENTS_CF)?;
-
-        for event in events.into_iter() {
-            // Serialize key and value
-            let id = event.id;
-            let key: &[u8] = id.as_bytes();
-            let value: &[u8] = event.encode(&mut fbb);
-
-            // Save event
-            batch.put_cf(&events_cf, key, value);
-        }
+        tokio::task::block_in_place(|| {
+            for event in events.into_iter() {
+                // Serialize value
+                let value: &[u8] = event.encode(&mut fbb);
 
-        // Write batch changes
-        self.db.write(batch).map_err(DatabaseError::backend)?;
+                // Index and save event
+                index::save_event(&self.db, &event, value)?;
+            }
 
-        Ok(())
+            Ok(())
+        })
     }
 
     async fn has_event_already_been_saved(&self, event_id: &EventId) -> Result<bool, Self::Err> {
-        if self.indexes.has_event_id_been_deleted(event_id).await {
+        if index::has_event_id_been_deleted(&self.db, event_id)? {
             Ok(true)
         } else {
             let cf = self.cf_handle(EVENTS_CF)?;
@@ -237,7 +287,7 @@ impl NostrDatabase for RocksDatabase {
     }
 
     async fn has_event_id_been_deleted(&self, event_id: &EventId) -> Result<bool, Self::Err> {
-        Ok(self.indexes.has_event_id_been_deleted(event_id).await)
+        index::has_event_id_been_deleted(&self.db, event_id)
     }
 
     async fn has_coordinate_been_deleted(
@@ -245,10 +295,7 @@ impl NostrDatabase for RocksDatabase {
         coordinate: &Coordinate,
         timestamp: Timestamp,
     ) -> Result<bool, Self::Err> {
-        Ok(self
-            .indexes
-            .has_coordinate_been_deleted(coordinate, timestamp)
-            .await)
+        index::has_coordinate_been_deleted(&self.db, coordinate, timestamp)
     }
 
     async fn event_id_seen(&self, event_id: EventId, relay_url: Url) -> Result<(), Self::Err> {
@@ -299,12 +346,12 @@ impl NostrDatabase for RocksDatabase {
 
     #[tracing::instrument(skip_all, level = "trace")]
     async fn count(&self, filters: Vec<Filter>) -> Result<usize, Self::Err> {
-        Ok(self.indexes.count(filters).await)
+        Ok(self.query_indexes(filters).await?.len())
     }
 
     #[tracing::instrument(skip_all, level = "trace")]
     async fn query(&self, filters: Vec<Filter>, order: Order) -> Result<Vec<Event>, Self::Err> {
-        let ids: Vec<EventId> = self.indexes.query(filters, order).await;
+        let ids: Vec<EventId> = self.event_ids_by_filters(filters, order).await?;
 
         let this = self.clone();
         tokio::task::spawn_blocking(move || {
@@ -335,38 +382,530 @@ impl NostrDatabase for RocksDatabase {
         filters: Vec<Filter>,
         order: Order,
     ) -> Result<Vec<EventId>, Self::Err> {
-        Ok(self.indexes.query(filters, order).await)
+        let entries = self.query_indexes(filters).await?;
+        Ok(match order {
+            Order::Asc => entries.into_iter().rev().map(|(_, id)| id).collect(),
+            Order::Desc => entries.into_iter().map(|(_, id)| id).collect(),
+        })
     }
 
     async fn negentropy_items(
         &self,
         filter: Filter,
     ) -> Result<Vec<(EventId, Timestamp)>, Self::Err> {
-        Ok(self.indexes.negentropy_items(filter).await)
+        let entries = self.query_indexes(vec![filter]).await?;
+        Ok(entries
+            .into_iter()
+            .map(|(created_at, id)| (id, created_at.0))
+            .collect())
+    }
+
+    async fn outbox_add(&self, event: &Event, relays: HashSet<Url>) -> Result<(), Self::Err> {
+        let mut fbb = self.fbb.write().await;
+        let value: &[u8] = event.encode(&mut fbb);
+        let cf = self.cf_handle(OUTBOX_CF)?;
+        for relay_url in relays.iter() {
+            self.db
+                .put_cf(&cf, outbox_key(relay_url, &event.id()), value)
+                .map_err(DatabaseError::backend)?;
+        }
+        Ok(())
+    }
+
+    async fn outbox_remove(&self, event_id: EventId, relay_url: Url) -> Result<(), Self::Err> {
+        let cf = self.cf_handle(OUTBOX_CF)?;
+        let mut batch = WriteBatchWithTransaction::<true>::default();
+        batch.delete_cf(&cf, outbox_key(&relay_url, &event_id));
+        self.db.write(batch).map_err(DatabaseError::backend)
+    }
+
+    async fn outbox(&self, relay_url: Url) -> Result<Vec<Event>, Self::Err> {
+        let cf = self.cf_handle(OUTBOX_CF)?;
+        let prefix: Vec<u8> = outbox_prefix(&relay_url);
+        let mut events: Vec<Event> = self
+            .db
+            .iterator_cf(&cf, IteratorMode::From(&prefix, Direction::Forward))
+            .flatten()
+            .take_while(|(key, _)| key.starts_with(&prefix))
+            .map(|(_, value)| Event::decode(&value).map_err(DatabaseError::backend))
+            .collect::<Result<_, _>>()?;
+        events.sort_by_key(|e| e.created_at());
+        Ok(events)
     }
 
     async fn delete(&self, filter: Filter) -> Result<(), Self::Err> {
-        match self.indexes.delete(filter).await {
-            Some(ids) => {
-                let events_cf = self.cf_handle(EVENTS_CF)?;
+        // Acquire FlatBuffers Builder, to not interleave with events indexing
+        let _fbb = self.fbb.write().await;
 
-                // Prepare write batch
-                let mut batch = WriteBatchWithTransaction::default();
+        let entries = self.query_indexes(vec![filter]).await?;
 
-                for id in ids.into_iter() {
-                    batch.delete_cf(&events_cf, id);
-                }
+        tokio::task::block_in_place(|| {
+            index::discard_events(&self.db, entries.iter().map(|(_, id)| id))
+        })
+    }
 
-                // Write batch changes
-                self.db.write(batch).map_err(DatabaseError::backend)?;
+    async fn wipe(&self) -> Result<(), Self::Err> {
+        Err(DatabaseError::NotSupported)
+    }
+}
 
-                Ok(())
+/// Outbox key prefix: `<relay url>\0`
+fn outbox_prefix(relay_url: &Url) -> Vec<u8> {
+    let url: &str = relay_url.as_str();
+    let mut prefix: Vec<u8> = Vec::with_capacity(url.len() + 1 + 32);
+    prefix.extend_from_slice(url.as_bytes());
+    prefix.push(0);
+    prefix
+}
+
+/// Outbox key: `<relay url>\0<event id>`
+fn outbox_key(relay_url: &Url, event_id: &EventId) -> Vec<u8> {
+    let mut key: Vec<u8> = outbox_prefix(relay_url);
+    key.extend_from_slice(event_id.as_bytes());
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::{EventBuilder, Keys, Kind, Tag};
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn note(keys: &Keys, content: &str, created_at: u64, tags: Vec<Tag>) -> Event {
+        EventBuilder::text_note(content, tags)
+            .custom_created_at(Timestamp::from(created_at))
+            .to_event(keys)
+            .unwrap()
+    }
+
+    fn ids(events: Vec<Event>) -> Vec<EventId> {
+        events.into_iter().map(|e| e.id()).collect()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_query() {
+        let dir = TempDir::new().unwrap();
+        let db = RocksDatabase::open(dir.path()).await.unwrap();
+
+        let alice = Keys::generate();
+        let bob = Keys::generate();
+        let a = note(
+            &alice,
+            "Hello world",
+            1_000,
+            vec![Tag::Hashtag(String::from("nostr"))],
+        );
+        let b = note(&alice, "Second note", 2_000, vec![]);
+        let c = note(
+            &bob,
+            "Bob note",
+            3_000,
+            vec![Tag::public_key(alice.public_key())],
+        );
+        for event in [&a, &b, &c] {
+            assert!(db.save_event(event).await.unwrap());
+        }
+
+        // Already saved
+        assert!(!db.save_event(&a).await.unwrap());
+
+        let all = Filter::new().kind(Kind::TextNote);
+        assert_eq!(
+            ids(db.query(vec![all.clone()], Order::Desc).await.unwrap()),
+            vec![c.id(), b.id(), a.id()]
+        );
+        assert_eq!(
+            ids(db.query(vec![all.clone()], Order::Asc).await.unwrap()),
+            vec![a.id(), b.id(), c.id()]
+        );
+        assert_eq!(db.count(vec![Filter::new()]).await.unwrap(), 3);
+
+        // Author, author and kind, limit, since and until
+        let filter = Filter::new().author(alice.public_key());
+        assert_eq!(db.count(vec![filter.clone()]).await.unwrap(), 2);
+        let filter = filter.kind(Kind::TextNote).limit(1);
+        assert_eq!(
+            ids(db.query(vec![filter], Order::Desc).await.unwrap()),
+            vec![b.id()]
+        );
+        let filter = all
+            .clone()
+            .since(Timestamp::from(1_500))
+            .until(Timestamp::from(2_500));
+        assert_eq!(
+            ids(db.query(vec![filter], Order::Desc).await.unwrap()),
+            vec![b.id()]
+        );
+        let filter = all
+            .clone()
+            .since(Timestamp::from(2_500))
+            .until(Timestamp::from(1_500));
+        assert_eq!(db.count(vec![filter]).await.unwrap(), 0);
+
+        // IDs and tags
+        let filter = Filter::new().id(b.id());
+        assert_eq!(
+            ids(db.query(vec![filter], Order::Desc).await.unwrap()),
+            vec![b.id()]
+        );
+        let filter = Filter::new().id(b.id()).author(bob.public_key());
+        assert_eq!(db.count(vec![filter]).await.unwrap(), 0);
+        let filter = Filter::new().hashtag("nostr");
+        assert_eq!(
+            ids(db.query(vec![filter], Order::Desc).await.unwrap()),
+            vec![a.id()]
+        );
+        let filter = Filter::new()
+            .pubkey(alice.public_key())
+            .author(alice.public_key());
+        assert_eq!(db.count(vec![filter]).await.unwrap(), 0);
+
+        // Many filters
+        let filters = vec![
+            Filter::new().id(a.id()),
+            Filter::new().author(bob.public_key()),
+        ];
+        assert_eq!(
+            ids(db.query(filters, Order::Desc).await.unwrap()),
+            vec![c.id(), a.id()]
+        );
+
+        // Negentropy items
+        let items = db
+            .negentropy_items(Filter::new().author(bob.public_key()))
+            .await
+            .unwrap();
+        assert_eq!(items, vec![(c.id(), c.created_at())]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_search() {
+        let dir = TempDir::new().unwrap();
+        let db = RocksDatabase::open(dir.path()).await.unwrap();
+
+        let keys = Keys::generate();
+        let a = note(&keys, "Nostr is a protocol", 1_000, vec![]);
+        let b = note(&keys, "Nostr nostr nostr protocol", 2_000, vec![]);
+        let c = note(&keys, "Something else", 3_000, vec![]);
+        for event in [&a, &b, &c] {
+            db.save_event(event).await.unwrap();
+        }
+
+        // All the tokens, sorted by relevance
+        let filter = Filter::new().search("nostr protocol");
+        assert_eq!(
+            ids(db.query(vec![filter], Order::Desc).await.unwrap()),
+            vec![b.id(), a.id()]
+        );
+        let filter = Filter::new().search("nostr else");
+        assert_eq!(db.count(vec![filter]).await.unwrap(), 0);
+
+        // With other conditions
+        let filter = Filter::new().search("nostr").until(Timestamp::from(1_500));
+        assert_eq!(
+            ids(db.query(vec![filter], Order::Desc).await.unwrap()),
+            vec![a.id()]
+        );
+        let filter = Filter::new()
+            .search("nostr")
+            .author(Keys::generate().public_key());
+        assert_eq!(db.count(vec![filter]).await.unwrap(), 0);
+
+        // Removed from the search index
+        db.delete(Filter::new().id(b.id())).await.unwrap();
+        let filter = Filter::new().search("nostr");
+        assert_eq!(
+            ids(db.query(vec![filter], Order::Desc).await.unwrap()),
+            vec![a.id()]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_replaceable() {
+        let dir = TempDir::new().unwrap();
+        let db = RocksDatabase::open(dir.path()).await.unwrap();
+
+        let keys = Keys::generate();
+        let old = EventBuilder::new(Kind::Metadata, "{}", [])
+            .custom_created_at(Timestamp::from(1_000))
+            .to_event(&keys)
+            .unwrap();
+        let new = EventBuilder::new(Kind::Metadata, "{}", [])
+            .custom_created_at(Timestamp::from(2_000))
+            .to_event(&keys)
+            .unwrap();
+        assert!(db.save_event(&old).await.unwrap());
+        assert!(db.save_event(&new).await.unwrap());
+        assert!(db.has_event_id_been_deleted(&old.id()).await.unwrap());
+
+        let filter = Filter::new().kind(Kind::Metadata);
+        assert_eq!(
+            ids(db.query(vec![filter], Order::Desc).await.unwrap()),
+            vec![new.id()]
+        );
+
+        // Parameterized replaceable
+        let kind = Kind::ParameterizedReplaceable(30000);
+        let old = EventBuilder::new(kind, "", [Tag::Identifier(String::from("a"))])
+            .custom_created_at(Timestamp::from(1_000))
+            .to_event(&keys)
+            .unwrap();
+        let other = EventBuilder::new(kind, "", [Tag::Identifier(String::from("b"))])
+            .custom_created_at(Timestamp::from(1_000))
+            .to_event(&keys)
+            .unwrap();
+        let new = EventBuilder::new(kind, "", [Tag::Identifier(String::from("a"))])
+            .custom_created_at(Timestamp::from(2_000))
+            .to_event(&keys)
+            .unwrap();
+        assert!(db.save_event(&new).await.unwrap());
+        assert!(db.save_event(&other).await.unwrap());
+        assert!(!db.save_event(&old).await.unwrap());
+
+        let filter = Filter::new().kind(kind);
+        assert_eq!(
+            ids(db.query(vec![filter], Order::Desc).await.unwrap()),
+            vec![new.id(), other.id()]
+        );
+        let filter = Filter::new().kind(kind).identifier("a");
+        assert_eq!(
+            ids(db.query(vec![filter], Order::Desc).await.unwrap()),
+            vec![new.id()]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_deletion() {
+        let dir = TempDir::new().unwrap();
+        let db = RocksDatabase::open(dir.path()).await.unwrap();
+
+        let keys = Keys::generate();
+        let event = note(&keys, "To delete", 1_000, vec![]);
+        assert!(db.save_event(&event).await.unwrap());
+
+        // Deletion by another author is ignored
+        let other = EventBuilder::new(Kind::EventDeletion, "", [Tag::event(event.id())])
+            .to_event(&Keys::generate())
+            .unwrap();
+        assert!(db.save_event(&other).await.unwrap());
+        assert!(!db.has_event_id_been_deleted(&event.id()).await.unwrap());
+
+        let deletion = EventBuilder::new(Kind::EventDeletion, "", [Tag::event(event.id())])
+            .custom_created_at(Timestamp::from(2_000))
+            .to_event(&keys)
+            .unwrap();
+        assert!(db.save_event(&deletion).await.unwrap());
+        assert!(db.has_event_id_been_deleted(&event.id()).await.unwrap());
+        assert!(!db.save_event(&event).await.unwrap());
+        let filter = Filter::new().kind(Kind::TextNote);
+        assert_eq!(db.count(vec![filter]).await.unwrap(), 0);
+
+        // Coordinate
+        let kind = Kind::ParameterizedReplaceable(30000);
+        let coordinate = Coordinate::new(kind, keys.public_key()).identifier("a");
+        let old = EventBuilder::new(kind, "", [Tag::Identifier(String::from("a"))])
+            .custom_created_at(Timestamp::from(1_000))
+            .to_event(&keys)
+            .unwrap();
+        assert!(db.save_event(&old).await.unwrap());
+        let deletion = EventBuilder::new(
+            Kind::EventDeletion,
+            "",
+            [Tag::A {
+                coordinate: coordinate.clone(),
+                relay_url: None,
+            }],
+        )
+        .custom_created_at(Timestamp::from(2_000))
+        .to_event(&keys)
+        .unwrap();
+        assert!(db.save_event(&deletion).await.unwrap());
+        assert!(db.has_event_id_been_deleted(&old.id()).await.unwrap());
+        assert!(db
+            .has_coordinate_been_deleted(&coordinate, Timestamp::from(1_500))
+            .await
+            .unwrap());
+        assert!(!db
+            .has_coordinate_been_deleted(&coordinate, Timestamp::from(2_500))
+            .await
+            .unwrap());
+
+        // Older than the deletion
+        let older = EventBuilder::new(kind, "", [Tag::Identifier(String::from("a"))])
+            .custom_created_at(Timestamp::from(1_500))
+            .to_event(&keys)
+            .unwrap();
+        assert!(!db.save_event(&older).await.unwrap());
+        let newer = EventBuilder::new(kind, "", [Tag::Identifier(String::from("a"))])
+            .custom_created_at(Timestamp::from(2_500))
+            .to_event(&keys)
+            .unwrap();
+        assert!(db.save_event(&newer).await.unwrap());
+    }
+
+    /// Store events without indexes, as an older version did
+    async fn store_raw(path: &Path, events: &[Event], version: Option<u8>) {
+        let db = RocksDatabase::open(path).await.unwrap();
+        let cf = db.cf_handle(EVENTS_CF).unwrap();
+        let mut fbb = FlatBufferBuilder::new();
+        for event in events.iter() {
+            db.db
+                .put_cf(&cf, event.id().as_bytes(), event.encode(&mut fbb))
+                .unwrap();
+        }
+        match version {
+            Some(version) => db.db.put(INDEXES_VERSION_KEY, [version]).unwrap(),
+            None => {
+                let mut batch = WriteBatchWithTransaction::<true>::default();
+                batch.delete(INDEXES_VERSION_KEY);
+                db.db.write(batch).unwrap();
             }
-            None => Err(DatabaseError::NotSupported),
         }
     }
 
-    async fn wipe(&self) -> Result<(), Self::Err> {
-        Err(DatabaseError::NotSupported)
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_build_indexes() {
+        let dir = TempDir::new().unwrap();
+        let keys = Keys::generate();
+        let a = note(
+            &keys,
+            "Hello world",
+            1_000,
+            vec![Tag::Hashtag(String::from("nostr"))],
+        );
+        let old = EventBuilder::new(Kind::Metadata, "{}", [])
+            .custom_created_at(Timestamp::from(1_000))
+            .to_event(&keys)
+            .unwrap();
+        let new = EventBuilder::new(Kind::Metadata, "{}", [])
+            .custom_created_at(Timestamp::from(2_000))
+            .to_event(&keys)
+            .unwrap();
+
+        // Events stored without indexes (older version)
+        store_raw(dir.path(), &[a.clone(), old.clone(), new.clone()], None).await;
+
+        let db = RocksDatabase::open(dir.path()).await.unwrap();
+        let filters = [
+            Filter::new().author(keys.public_key()).kind(Kind::TextNote),
+            Filter::new().hashtag("nostr"),
+            Filter::new().search("world"),
+        ];
+        for filter in filters.into_iter() {
+            assert_eq!(
+                ids(db.query(vec![filter], Order::Desc).await.unwrap()),
+                vec![a.id()]
+            );
+        }
+
+        // Replaced event discarded
+        let filter = Filter::new().kind(Kind::Metadata);
+        assert_eq!(
+            ids(db.query(vec![filter], Order::Desc).await.unwrap()),
+            vec![new.id()]
+        );
+        assert!(db.has_event_id_been_deleted(&old.id()).await.unwrap());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_build_indexes_batches() {
+        let dir = TempDir::new().unwrap();
+        let keys = Keys::generate();
+
+        // More events than a single migration batch
+        let mut events: Vec<Event> = (0..MIGRATION_BATCH_SIZE as u64 + 500)
+            .map(|i| note(&keys, "Hello world", 1_000 + i, vec![]))
+            .collect();
+        let deleted = events[0].clone();
+        let deletion = EventBuilder::new(Kind::EventDeletion, "", [Tag::event(deleted.id())])
+            .custom_created_at(Timestamp::from(5_000))
+            .to_event(&keys)
+            .unwrap();
+        events.push(deletion.clone());
+
+        let kind = Kind::ParameterizedReplaceable(30000);
+        let mut replaced: Vec<Event> = Vec::new();
+        for created_at in 1..=5 {
+            replaced.push(
+                EventBuilder::new(kind, "", [Tag::Identifier(String::from("a"))])
+                    .custom_created_at(Timestamp::from(created_at * 1_000))
+                    .to_event(&keys)
+                    .unwrap(),
+            );
+        }
+        let latest = replaced.pop().unwrap();
+        events.extend(replaced.iter().cloned());
+        events.push(latest.clone());
+
+        store_raw(dir.path(), &events, None).await;
+
+        let db = RocksDatabase::open(dir.path()).await.unwrap();
+        let filter = Filter::new().author(keys.public_key()).kind(Kind::TextNote);
+        assert_eq!(
+            db.count(vec![filter]).await.unwrap(),
+            MIGRATION_BATCH_SIZE + 499
+        );
+        assert!(db.has_event_id_been_deleted(&deleted.id()).await.unwrap());
+        assert!(db.event_by_id(deleted.id()).await.is_err());
+
+        let filter = Filter::new().kind(kind);
+        assert_eq!(
+            ids(db.query(vec![filter], Order::Desc).await.unwrap()),
+            vec![latest.id()]
+        );
+        for event in replaced.iter() {
+            assert!(db.has_event_id_been_deleted(&event.id()).await.unwrap());
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_build_search_index() {
+        let dir = TempDir::new().unwrap();
+        let keys = Keys::generate();
+
+        // Version 1 has all the indexes except the search one
+        let events: Vec<Event> = (0..MIGRATION_BATCH_SIZE as u64 + 500)
+            .map(|i| note(&keys, &format!("Hello world {i}"), 1_000 + i, vec![]))
+            .collect();
+        store_raw(dir.path(), &events, Some(1)).await;
+
+        let db = RocksDatabase::open(dir.path()).await.unwrap();
+        let filter = Filter::new().search("world");
+        assert_eq!(
+            db.count(vec![filter]).await.unwrap(),
+            MIGRATION_BATCH_SIZE + 500
+        );
+        let filter = Filter::new().search("1499");
+        assert_eq!(
+            ids(db.query(vec![filter], Order::Desc).await.unwrap()),
+            vec![events[1499].id()]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_outbox() {
+        let dir = TempDir::new().unwrap();
+        let db = RocksDatabase::open(dir.path()).await.unwrap();
+
+        let keys = Keys::generate();
+        let a = note(&keys, "A", 2_000, vec![]);
+        let b = note(&keys, "B", 1_000, vec![]);
+        let relay1 = Url::parse("wss://relay1.example.com").unwrap();
+        let relay2 = Url::parse("wss://relay2.example.com").unwrap();
+        db.outbox_add(&a, [relay1.clone(), relay2.clone()].into_iter().collect())
+            .await
+            .unwrap();
+        db.outbox_add(&b, [relay1.clone()].into_iter().collect())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            ids(db.outbox(relay1.clone()).await.unwrap()),
+            vec![b.id(), a.id()]
+        );
+        db.outbox_remove(a.id(), relay1.clone()).await.unwrap();
+        assert_eq!(ids(db.outbox(relay1).await.unwrap()), vec![b.id()]);
+        assert_eq!(ids(db.outbox(relay2).await.unwrap()), vec![a.id()]);
     }
 }
@@ -0,0 +1,39 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+use std::time::Duration;
+
+use nostr_sdk::prelude::*;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    tracing_subscriber::fmt::init();
+
+    let public_key =
+        PublicKey::from_bech32("npub1acg6thl5psv62405rljzkj8spesceyfz2c32udakc2ak0dmvfeyse9p35c")?;
+
+    let opts = Options::new().gossip(true).max_gossip_relays(10);
+    let client = ClientBuilder::new().opts(opts).build();
+
+    // Relays used to discover the relay lists
+    client.add_relay("wss://relay.damus.io").await?;
+    client.add_relay("wss://purplepag.es").await?;
+
+    client.connect().await;
+
+    // Text notes are requested to the author's write relays
+    let filter = Filter::new()
+        .author(public_key)
+        .kind(Kind::TextNote)
+        .limit(10);
+    let events = client
+        .get_events_of(vec![filter], Some(Duration::from_secs(10)))
+        .await?;
+
+    for event in events.into_iter() {
+        println!("{}", event.as_json());
+    }
+
+    Ok(())
+}
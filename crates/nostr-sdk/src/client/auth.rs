@@ -0,0 +1,72 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Client authentication (NIP-42)
+
+use std::fmt;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use nostr::{Event, EventBuilder, PublicKey, UnsignedEvent, Url};
+use nostr_relay_pool::relay::{AuthenticationError, RelayAuthenticator};
+use nostr_signer::DynNostrSigner;
+use tokio::sync::RwLock;
+
+/// Authentication policy
+///
+/// Allow or deny the automatic authentication with a relay.
+pub trait AuthenticationPolicy: fmt::Debug + Send + Sync {
+    /// Check if the authentication with the relay is allowed
+    fn allow(&self, relay_url: &Url) -> bool;
+}
+
+/// Sign the authentication events with the [`Client`](super::Client) signer
+#[derive(Debug, Clone)]
+pub(crate) struct ClientAuthenticator {
+    signer: Arc<RwLock<Option<Arc<DynNostrSigner>>>>,
+    policy: Option<Arc<dyn AuthenticationPolicy>>,
+}
+
+impl ClientAuthenticator {
+    pub fn new(
+        signer: Arc<RwLock<Option<Arc<DynNostrSigner>>>>,
+        policy: Option<Arc<dyn AuthenticationPolicy>>,
+    ) -> Self {
+        Self { signer, policy }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl RelayAuthenticator for ClientAuthenticator {
+    async fn allow(&self, relay_url: &Url) -> bool {
+        match &self.policy {
+            Some(policy) => policy.allow(relay_url),
+            None => true,
+        }
+    }
+
+    async fn authenticate(
+        &self,
+        challenge: String,
+        relay_url: Url,
+    ) -> Result<Event, AuthenticationError> {
+        let signer: Arc<DynNostrSigner> = self
+            .signer
+            .read()
+            .await
+            .clone()
+            .ok_or(AuthenticationError::AuthenticatorNotConfigured)?;
+        let public_key: PublicKey = signer
+            .public_key()
+            .await
+            .map_err(AuthenticationError::signer)?;
+        let unsigned: UnsignedEvent =
+            EventBuilder::auth(challenge, relay_url).to_unsigned_event(public_key);
+        signer
+            .sign_event(unsigned)
+            .await
+            .map_err(AuthenticationError::signer)
+    }
+}
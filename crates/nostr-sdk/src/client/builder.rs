@@ -8,7 +8,7 @@ use std::sync::Arc;
 
 use nostr_database::memory::MemoryDatabase;
 use nostr_database::{DynNostrDatabase, IntoNostrDatabase};
-use nostr_signer::NostrSigner;
+use nostr_signer::{DynNostrSigner, IntoNostrSigner};
 #[cfg(feature = "nip57")]
 use nostr_zapper::{DynNostrZapper, IntoNostrZapper};
 
@@ -18,7 +18,7 @@ use crate::{Client, Options};
 #[derive(Debug, Clone)]
 pub struct ClientBuilder {
     /// Nostr Signer
-    pub signer: Option<NostrSigner>,
+    pub signer: Option<Arc<DynNostrSigner>>,
     /// Nostr Zapper
     #[cfg(feature = "nip57")]
     pub zapper: Option<Arc<DynNostrZapper>>,
@@ -60,9 +60,9 @@ impl ClientBuilder {
     /// ```
     pub fn signer<S>(mut self, signer: S) -> Self
     where
-        S: Into<NostrSigner>,
+        S: IntoNostrSigner,
     {
-        self.signer = Some(signer.into());
+        self.signer = Some(signer.into_nostr_signer());
         self
     }
 
@@ -0,0 +1,835 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Gossip (NIP-65 outbox model)
+//!
+//! <https://github.com/nostr-protocol/nips/blob/master/65.md>
+
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_utility::thread;
+use nostr::nips::nip65;
+use nostr::{
+    Alphabet, Event, EventId, Filter, GenericTagValue, Kind, PublicKey, RelayMetadata,
+    SingleLetterTag, SubscriptionId, Timestamp, Url,
+};
+use nostr_database::Order;
+#[cfg(feature = "nip59")]
+use nostr_relay_pool::RelayServiceFlags;
+use nostr_relay_pool::{
+    EventStream, FilterOptions, PublishReport, Relay, RelayOptions, RelayPool, RelaySendOptions,
+    SubscribeOptions, SubscriptionStream,
+};
+use tokio::sync::{Mutex, RwLock};
+
+use super::{Client, Error};
+
+/// Relay lists are fetched again after this interval
+const RELAY_LIST_TTL: Duration = Duration::from_secs(60 * 60);
+/// Timeout for relay lists discovery
+const RELAY_LIST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Connection timeout for gossip relays, if not set in client options
+const DEFAULT_CONNECTION_TIMEOUT: Duration = Duration::from_secs(5);
+/// Max number of relays selected for each public key
+const MAX_RELAYS_PER_PUBLIC_KEY: usize = 2;
+
+#[derive(Debug, Clone)]
+struct RelayList {
+    read: BTreeSet<Url>,
+    write: BTreeSet<Url>,
+    /// Timestamp of the relay list event
+    created_at: Timestamp,
+    /// Last time the relay list was checked
+    checked_at: Timestamp,
+}
+
+impl RelayList {
+    fn new(checked_at: Timestamp) -> Self {
+        Self {
+            read: BTreeSet::new(),
+            write: BTreeSet::new(),
+            created_at: Timestamp::from(0),
+            checked_at,
+        }
+    }
+
+    fn update(&mut self, event: &Event) {
+        if event.created_at() <= self.created_at {
+            return;
+        }
+
+        self.read.clear();
+        self.write.clear();
+        self.created_at = event.created_at();
+
+        for (url, metadata) in nip65::extract_relay_list(event).into_iter() {
+            if let Ok(url) = Url::try_from(url) {
+                match metadata {
+                    Some(RelayMetadata::Read) => {
+                        self.read.insert(url);
+                    }
+                    Some(RelayMetadata::Write) => {
+                        self.write.insert(url);
+                    }
+                    None => {
+                        self.read.insert(url.clone());
+                        self.write.insert(url);
+                    }
+                }
+            }
+        }
+    }
+
+    fn relays(&self, metadata: &RelayMetadata) -> &BTreeSet<Url> {
+        match metadata {
+            RelayMetadata::Read => &self.read,
+            RelayMetadata::Write => &self.write,
+        }
+    }
+
+    fn is_outdated(&self, now: Timestamp) -> bool {
+        self.checked_at + RELAY_LIST_TTL < now
+    }
+}
+
+/// Gossip state
+///
+/// Cache of the relay lists and of the relays added by the outbox model.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Gossip {
+    relay_lists: Arc<RwLock<HashMap<PublicKey, RelayList>>>,
+    /// Relays added by gossip, with last usage
+    ephemeral: Arc<RwLock<HashMap<Url, Timestamp>>>,
+}
+
+impl Gossip {
+    /// Get the relays that aren't managed by gossip
+    pub async fn default_relays(&self, pool: &RelayPool) -> HashSet<Url> {
+        let ephemeral = self.ephemeral.read().await;
+        pool.relays()
+            .await
+            .into_keys()
+            .filter(|url| !ephemeral.contains_key(url))
+            .collect()
+    }
+
+    /// Get the public keys with a missing or outdated relay list
+    async fn outdated_public_keys(&self, public_keys: BTreeSet<PublicKey>) -> BTreeSet<PublicKey> {
+        let now = Timestamp::now();
+        let relay_lists = self.relay_lists.read().await;
+        public_keys
+            .into_iter()
+            .filter(|public_key| match relay_lists.get(public_key) {
+                Some(list) => list.is_outdated(now),
+                None => true,
+            })
+            .collect()
+    }
+
+    /// Discover the relay lists (`kind:10002`) of public keys
+    ///
+    /// Relay lists are requested to the default relays and loaded from the database.
+    pub async fn update_relay_lists(
+        &self,
+        pool: &RelayPool,
+        default_relays: &HashSet<Url>,
+        public_keys: BTreeSet<PublicKey>,
+    ) {
+        let outdated: BTreeSet<PublicKey> = self.outdated_public_keys(public_keys).await;
+
+        if outdated.is_empty() {
+            return;
+        }
+
+        let filter: Filter = Filter::new()
+            .kind(Kind::RelayList)
+            .authors(outdated.iter().copied());
+
+        // Fetched events are saved into the database
+        let mut events: Vec<Event> = Vec::new();
+        if !default_relays.is_empty() {
+            match pool
+                .get_events_from(
+                    default_relays.iter().cloned(),
+                    vec![filter.clone()],
+                    RELAY_LIST_TIMEOUT,
+                    FilterOptions::ExitOnEOSE,
+                )
+                .await
+            {
+                Ok(list) => events.extend(list),
+                Err(e) => tracing::error!("Impossible to fetch relay lists: {e}"),
+            }
+        }
+
+        if let Ok(list) = pool.database().query(vec![filter], Order::Desc).await {
+            events.extend(list);
+        }
+
+        let now = Timestamp::now();
+        let mut relay_lists = self.relay_lists.write().await;
+
+        // Public keys without relay list are marked as checked too
+        for public_key in outdated.into_iter() {
+            relay_lists
+                .entry(public_key)
+                .and_modify(|list| list.checked_at = now)
+                .or_insert_with(|| RelayList::new(now));
+        }
+
+        for event in events.iter().filter(|e| e.kind() == Kind::RelayList) {
+            if let Some(list) = relay_lists.get_mut(&event.author()) {
+                list.update(event);
+            }
+        }
+    }
+
+    /// Get all the read or write relays of a public key
+    pub async fn relays(&self, public_key: &PublicKey, metadata: &RelayMetadata) -> BTreeSet<Url> {
+        let relay_lists = self.relay_lists.read().await;
+        relay_lists
+            .get(public_key)
+            .map(|list| list.relays(metadata).clone())
+            .unwrap_or_default()
+    }
+
+    /// Select the read or write relays for each public key
+    ///
+    /// Relays shared with other public keys and the default relays are preferred.
+    /// The number of relays not in `default_relays` is bounded by `max_relays`.
+    ///
+    /// Return the selected relays and the public keys without any relay.
+    pub async fn select(
+        &self,
+        public_keys: &BTreeSet<PublicKey>,
+        metadata: &RelayMetadata,
+        default_relays: &HashSet<Url>,
+        gossip_relays: &mut HashSet<Url>,
+        max_relays: usize,
+    ) -> (HashMap<Url, BTreeSet<PublicKey>>, BTreeSet<PublicKey>) {
+        let relay_lists = self.relay_lists.read().await;
+
+        let mut popularity: HashMap<&Url, usize> = HashMap::new();
+        for public_key in public_keys.iter() {
+            if let Some(list) = relay_lists.get(public_key) {
+                for url in list.relays(metadata).iter() {
+                    *popularity.entry(url).or_default() += 1;
+                }
+            }
+        }
+
+        let mut selected: HashMap<Url, BTreeSet<PublicKey>> = HashMap::new();
+        let mut missing: BTreeSet<PublicKey> = BTreeSet::new();
+
+        for public_key in public_keys.iter() {
+            let mut candidates: Vec<&Url> = match relay_lists.get(public_key) {
+                Some(list) => list.relays(metadata).iter().collect(),
+                None => Vec::new(),
+            };
+
+            candidates.sort_by_key(|url| {
+                let known: bool = default_relays.contains(*url) || gossip_relays.contains(*url);
+                (
+                    !known,
+                    Reverse(popularity.get(*url).copied().unwrap_or_default()),
+                )
+            });
+
+            let mut count: usize = 0;
+            for url in candidates.into_iter() {
+                if count >= MAX_RELAYS_PER_PUBLIC_KEY {
+                    break;
+                }
+
+                if !default_relays.contains(url) && !gossip_relays.contains(url) {
+                    if gossip_relays.len() >= max_relays {
+                        continue;
+                    }
+                    gossip_relays.insert(url.clone());
+                }
+
+                selected.entry(url.clone()).or_default().insert(*public_key);
+                count += 1;
+            }
+
+            if count == 0 {
+                missing.insert(*public_key);
+            }
+        }
+
+        (selected, missing)
+    }
+
+    /// Split filters by relay
+    ///
+    /// Filters with authors are sent to the authors' write relays, filters with `p` tags
+    /// to the tagged users' read relays. Everything else goes to the default relays.
+    pub async fn route(
+        &self,
+        filters: Vec<Filter>,
+        default_relays: &HashSet<Url>,
+        max_relays: usize,
+    ) -> HashMap<Url, Vec<Filter>> {
+        let mut routes: HashMap<Url, Vec<Filter>> = HashMap::new();
+        let mut gossip_relays: HashSet<Url> = HashSet::new();
+
+        for filter in filters.into_iter() {
+            if let Some(authors) = filter.authors.as_ref().filter(|a| !a.is_empty()) {
+                let authors: BTreeSet<PublicKey> = authors.iter().copied().collect();
+                let (selected, missing) = self
+                    .select(
+                        &authors,
+                        &RelayMetadata::Write,
+                        default_relays,
+                        &mut gossip_relays,
+                        max_relays,
+                    )
+                    .await;
+
+                for (url, authors) in selected.into_iter() {
+                    let mut filter: Filter = filter.clone();
+                    filter.authors = Some(authors.into_iter().collect());
+                    routes.entry(url).or_default().push(filter);
+                }
+
+                if !missing.is_empty() {
+                    let mut filter: Filter = filter.clone();
+                    filter.authors = Some(missing.into_iter().collect());
+                    for url in default_relays.iter() {
+                        routes.entry(url.clone()).or_default().push(filter.clone());
+                    }
+                }
+            } else if let Some(pubkeys) = tagged_public_keys(&filter) {
+                let (selected, missing) = self
+                    .select(
+                        &pubkeys,
+                        &RelayMetadata::Read,
+                        default_relays,
+                        &mut gossip_relays,
+                        max_relays,
+                    )
+                    .await;
+
+                for (url, pubkeys) in selected.into_iter() {
+                    let filter: Filter = with_tagged_public_keys(filter.clone(), pubkeys);
+                    routes.entry(url).or_default().push(filter);
+                }
+
+                if !missing.is_empty() {
+                    let filter: Filter = with_tagged_public_keys(filter.clone(), missing);
+                    for url in default_relays.iter() {
+                        routes.entry(url.clone()).or_default().push(filter.clone());
+                    }
+                }
+            } else {
+                for url in default_relays.iter() {
+                    routes.entry(url.clone()).or_default().push(filter.clone());
+                }
+            }
+        }
+
+        routes
+    }
+
+    /// Add and connect the relays not already in the pool, then evict the least recently used
+    /// gossip relays exceeding `max_relays`
+    ///
+    /// Disconnected default relays aren't reconnected.
+    /// Gossip relays in `urls` or with active subscriptions are never evicted.
+    pub async fn prepare_relays(
+        &self,
+        pool: &RelayPool,
+        urls: &HashSet<Url>,
+        opts: RelayOptions,
+        connection_timeout: Option<Duration>,
+        max_relays: usize,
+    ) -> Result<(), Error> {
+        let now = Timestamp::now();
+        let relays: HashMap<Url, Relay> = pool.relays().await;
+
+        let mut ephemeral = self.ephemeral.write().await;
+
+        let mut to_connect: Vec<Relay> = Vec::new();
+        for url in urls.iter() {
+            match relays.get(url) {
+                Some(relay) => {
+                    // The connection of the default relays is managed by the user
+                    if let Some(last_used) = ephemeral.get_mut(url) {
+                        *last_used = now;
+
+                        if !relay.is_connected().await {
+                            to_connect.push(relay.clone());
+                        }
+                    }
+                }
+                None => {
+                    pool.add_relay(url, opts.clone()).await?;
+                    ephemeral.insert(url.clone(), now);
+                    to_connect.push(pool.relay(url).await?);
+                }
+            }
+        }
+
+        // Evict least recently used relays
+        if ephemeral.len() > max_relays {
+            let mut candidates: Vec<(Url, Timestamp)> = Vec::new();
+            for (url, last_used) in ephemeral.iter() {
+                if urls.contains(url) {
+                    continue;
+                }
+
+                if let Some(relay) = relays.get(url) {
+                    if !relay.subscriptions().await.is_empty() {
+                        continue;
+                    }
+                }
+
+                candidates.push((url.clone(), *last_used));
+            }
+
+            candidates.sort_by_key(|(.., last_used)| *last_used);
+
+            for (url, ..) in candidates.into_iter() {
+                if ephemeral.len() <= max_relays {
+                    break;
+                }
+
+                ephemeral.remove(&url);
+                pool.remove_relay(url).await?;
+            }
+        }
+
+        drop(ephemeral);
+
+        // Connect
+        let timeout: Duration = connection_timeout.unwrap_or(DEFAULT_CONNECTION_TIMEOUT);
+        let mut handles = Vec::with_capacity(to_connect.len());
+        for relay in to_connect.into_iter() {
+            let handle = thread::spawn(async move {
+                relay.connect(Some(timeout)).await;
+            });
+            handles.push(handle);
+        }
+
+        for handle in handles.into_iter().flatten() {
+            if let Err(e) = handle.join().await {
+                tracing::error!("Impossible to join thread: {e}")
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Add flags to gossip relays (ex. to write to a relay added for reads)
+    ///
+    /// The relays not managed by gossip are left untouched.
+    #[cfg(feature = "nip59")]
+    pub async fn add_flags(&self, pool: &RelayPool, urls: &HashSet<Url>, flags: RelayServiceFlags) {
+        let ephemeral = self.ephemeral.read().await;
+        for url in urls.iter().filter(|url| ephemeral.contains_key(*url)) {
+            if let Ok(relay) = pool.relay(url).await {
+                relay.flags().add(flags);
+            }
+        }
+    }
+
+    /// Stop tracking a relay as gossip relay
+    pub async fn remove_ephemeral(&self, url: &Url) -> bool {
+        let mut ephemeral = self.ephemeral.write().await;
+        ephemeral.remove(url).is_some()
+    }
+}
+
+impl Client {
+    /// Discover relay lists, then route filters and prepare the relays
+    pub(super) async fn gossip_routes(
+        &self,
+        filters: Vec<Filter>,
+    ) -> Result<HashMap<Url, Vec<Filter>>, Error> {
+        let default_relays: HashSet<Url> = self.gossip.default_relays(&self.pool).await;
+        let public_keys: BTreeSet<PublicKey> = filters_public_keys(&filters);
+        self.gossip
+            .update_relay_lists(&self.pool, &default_relays, public_keys)
+            .await;
+
+        let max_relays: usize = self.opts.get_max_gossip_relays();
+        let routes: HashMap<Url, Vec<Filter>> = self
+            .gossip
+            .route(filters, &default_relays, max_relays)
+            .await;
+
+        let urls: HashSet<Url> = routes.keys().cloned().collect();
+        self.gossip
+            .prepare_relays(
+                &self.pool,
+                &urls,
+                self.relay_opts(),
+                self.opts.connection_timeout,
+                max_relays,
+            )
+            .await?;
+
+        Ok(routes)
+    }
+
+    pub(super) async fn gossip_get_events_of(
+        &self,
+        filters: Vec<Filter>,
+        timeout: Duration,
+        opts: FilterOptions,
+    ) -> Result<Vec<Event>, Error> {
+        let stored_events: Vec<Event> = self
+            .database()
+            .query(filters.clone(), Order::Desc)
+            .await
+            .unwrap_or_default();
+
+        let routes: HashMap<Url, Vec<Filter>> = self.gossip_routes(filters).await?;
+
+        // Compose IDs and Events collections
+        let ids: Arc<Mutex<HashSet<EventId>>> =
+            Arc::new(Mutex::new(stored_events.iter().map(|e| e.id()).collect()));
+        let events: Arc<Mutex<BTreeSet<Event>>> =
+            Arc::new(Mutex::new(stored_events.into_iter().collect()));
+
+        let mut handles = Vec::with_capacity(routes.len());
+        for (url, filters) in routes.into_iter() {
+            let relay: Relay = self.pool.relay(&url).await?;
+            let ids = ids.clone();
+            let events = events.clone();
+            let handle = thread::spawn(async move {
+                match relay.get_events_of(filters, timeout, opts).await {
+                    Ok(list) => {
+                        let mut ids = ids.lock().await;
+                        let mut events = events.lock().await;
+                        for event in list.into_iter() {
+                            if ids.insert(event.id()) {
+                                events.insert(event);
+                            }
+                        }
+                    }
+                    Err(e) => tracing::error!("Failed to get events from {url}: {e}"),
+                }
+            });
+            handles.push(handle);
+        }
+
+        for handle in handles.into_iter().flatten() {
+            if let Err(e) = handle.join().await {
+                tracing::error!("Impossible to join thread: {e}")
+            }
+        }
+
+        Ok(events
+            .lock_owned()
+            .await
+            .clone()
+            .into_iter()
+            .rev()
+            .collect())
+    }
+
+    pub(super) async fn gossip_stream_events_of(
+        &self,
+        filters: Vec<Filter>,
+        timeout: Duration,
+        opts: FilterOptions,
+    ) -> Result<EventStream, Error> {
+        let routes: HashMap<Url, Vec<Filter>> = self.gossip_routes(filters).await?;
+        Ok(self
+            .pool
+            .stream_events_targeted(routes, timeout, opts)
+            .await?)
+    }
+
+    pub(super) async fn gossip_subscribe_with_id(
+        &self,
+        id: SubscriptionId,
+        filters: Vec<Filter>,
+        opts: SubscribeOptions,
+    ) {
+        let routes: HashMap<Url, Vec<Filter>> = match self.gossip_routes(filters).await {
+            Ok(routes) => routes,
+            Err(e) => {
+                tracing::error!("Impossible to route subscription {id}: {e}");
+                return;
+            }
+        };
+
+        for (url, filters) in routes.into_iter() {
+            match self.pool.relay(&url).await {
+                Ok(relay) => {
+                    if let Err(e) = relay.subscribe_with_id(id.clone(), filters, opts).await {
+                        tracing::error!("Impossible to subscribe to {url}: {e}");
+                    }
+                }
+                Err(e) => tracing::error!("{e}"),
+            }
+        }
+    }
+
+    pub(super) async fn gossip_subscribe_stream_with_id(
+        &self,
+        id: SubscriptionId,
+        filters: Vec<Filter>,
+        opts: SubscribeOptions,
+    ) -> SubscriptionStream {
+        let routes: HashMap<Url, Vec<Filter>> = match self.gossip_routes(filters).await {
+            Ok(routes) => routes,
+            Err(e) => {
+                tracing::error!("Impossible to route subscription {id}: {e}");
+                HashMap::new()
+            }
+        };
+
+        self.pool.subscribe_stream_targeted(id, routes, opts).await
+    }
+
+    pub(super) async fn gossip_send_event(
+        &self,
+        event: Event,
+        opts: RelaySendOptions,
+    ) -> Result<PublishReport, Error> {
+        let default_relays: HashSet<Url> = self.gossip.default_relays(&self.pool).await;
+
+        // Lists (ex. contact list) aren't routed to the tagged users
+        let tagged: BTreeSet<PublicKey> =
+            if event.kind().is_replaceable() || event.kind().is_parameterized_replaceable() {
+                BTreeSet::new()
+            } else {
+                event
+                    .public_keys()
+                    .copied()
+                    .filter(|p| *p != event.author())
+                    .collect()
+            };
+
+        let mut public_keys: BTreeSet<PublicKey> = tagged.clone();
+        public_keys.insert(event.author());
+        self.gossip
+            .update_relay_lists(&self.pool, &default_relays, public_keys)
+            .await;
+
+        // Author's write relays
+        let max_relays: usize = self.opts.get_max_gossip_relays();
+        let mut urls: HashSet<Url> = default_relays.clone();
+        let mut gossip_relays: HashSet<Url> = HashSet::new();
+        for url in self
+            .gossip
+            .relays(&event.author(), &RelayMetadata::Write)
+            .await
+            .into_iter()
+        {
+            if gossip_relays.len() < max_relays || default_relays.contains(&url) {
+                if !default_relays.contains(&url) {
+                    gossip_relays.insert(url.clone());
+                }
+                urls.insert(url);
+            }
+        }
+
+        // Tagged users' read relays
+        let (selected, ..) = self
+            .gossip
+            .select(
+                &tagged,
+                &RelayMetadata::Read,
+                &default_relays,
+                &mut gossip_relays,
+                max_relays,
+            )
+            .await;
+        urls.extend(selected.into_keys());
+
+        self.gossip
+            .prepare_relays(
+                &self.pool,
+                &urls,
+                self.relay_opts(),
+                self.opts.connection_timeout,
+                max_relays,
+            )
+            .await?;
+
+        Ok(self.pool.send_event_to(urls, event, opts).await?)
+    }
+}
+
+/// Get the authors and `p` tags public keys of filters
+pub(crate) fn filters_public_keys(filters: &[Filter]) -> BTreeSet<PublicKey> {
+    let mut public_keys: BTreeSet<PublicKey> = BTreeSet::new();
+    for filter in filters.iter() {
+        if let Some(authors) = &filter.authors {
+            public_keys.extend(authors.iter().copied());
+        }
+
+        if let Some(pubkeys) = tagged_public_keys(filter) {
+            public_keys.extend(pubkeys);
+        }
+    }
+    public_keys
+}
+
+fn tagged_public_keys(filter: &Filter) -> Option<BTreeSet<PublicKey>> {
+    let values = filter
+        .generic_tags
+        .get(&SingleLetterTag::lowercase(Alphabet::P))?;
+    let public_keys: BTreeSet<PublicKey> = values
+        .iter()
+        .filter_map(|value| match value {
+            GenericTagValue::Pubkey(public_key) => Some(*public_key),
+            GenericTagValue::String(value) => PublicKey::from_hex(value).ok(),
+            _ => None,
+        })
+        .collect();
+
+    if public_keys.is_empty() {
+        None
+    } else {
+        Some(public_keys)
+    }
+}
+
+fn with_tagged_public_keys(mut filter: Filter, public_keys: BTreeSet<PublicKey>) -> Filter {
+    filter.generic_tags.insert(
+        SingleLetterTag::lowercase(Alphabet::P),
+        public_keys
+            .into_iter()
+            .map(GenericTagValue::Pubkey)
+            .collect(),
+    );
+    filter
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::Keys;
+    use nostr_relay_pool::RelayStatus;
+
+    use super::*;
+    use crate::mock;
+
+    fn url(host: &str) -> Url {
+        Url::parse(&format!("ws://{host}.local")).unwrap()
+    }
+
+    async fn set_relay_list(
+        gossip: &Gossip,
+        public_key: PublicKey,
+        read: Vec<Url>,
+        write: Vec<Url>,
+    ) {
+        let mut list = RelayList::new(Timestamp::now());
+        list.read = read.into_iter().collect();
+        list.write = write.into_iter().collect();
+        gossip.relay_lists.write().await.insert(public_key, list);
+    }
+
+    #[tokio::test]
+    async fn test_select() {
+        let gossip = Gossip::default();
+        let pk1 = Keys::generate().public_key();
+        let pk2 = Keys::generate().public_key();
+        let pk3 = Keys::generate().public_key();
+        let (a, b, c, d) = (url("a"), url("b"), url("c"), url("d"));
+        set_relay_list(&gossip, pk1, vec![], vec![a.clone(), b.clone(), c]).await;
+        set_relay_list(&gossip, pk2, vec![], vec![b.clone(), d]).await;
+
+        let public_keys: BTreeSet<PublicKey> = [pk1, pk2, pk3].into_iter().collect();
+        let default_relays: HashSet<Url> = [a.clone()].into_iter().collect();
+        let mut gossip_relays: HashSet<Url> = HashSet::new();
+        let (selected, missing) = gossip
+            .select(
+                &public_keys,
+                &RelayMetadata::Write,
+                &default_relays,
+                &mut gossip_relays,
+                1,
+            )
+            .await;
+
+        // Default relay first, then the most popular one; `d` exceeds `max_relays`
+        assert_eq!(selected.len(), 2);
+        assert_eq!(selected[&a], [pk1].into_iter().collect());
+        assert_eq!(selected[&b], [pk1, pk2].into_iter().collect());
+        assert_eq!(missing, [pk3].into_iter().collect());
+        assert_eq!(gossip_relays, [b].into_iter().collect());
+
+        // Read relays are unknown
+        let mut gossip_relays: HashSet<Url> = HashSet::new();
+        let (selected, missing) = gossip
+            .select(
+                &public_keys,
+                &RelayMetadata::Read,
+                &default_relays,
+                &mut gossip_relays,
+                1,
+            )
+            .await;
+        assert!(selected.is_empty());
+        assert_eq!(missing, public_keys);
+    }
+
+    #[tokio::test]
+    async fn test_route() {
+        let gossip = Gossip::default();
+        let pk1 = Keys::generate().public_key();
+        let pk2 = Keys::generate().public_key();
+        let pk3 = Keys::generate().public_key();
+        let (a, b, c) = (url("a"), url("b"), url("c"));
+        set_relay_list(&gossip, pk1, vec![c.clone()], vec![b.clone()]).await;
+        set_relay_list(&gossip, pk2, vec![c.clone()], vec![]).await;
+
+        let default_relays: HashSet<Url> = [a.clone()].into_iter().collect();
+        let authors = Filter::new().kind(Kind::TextNote).authors([pk1, pk3]);
+        let mentions = Filter::new().kind(Kind::TextNote).pubkey(pk2);
+        let other = Filter::new().kind(Kind::Metadata);
+        let routes = gossip
+            .route(
+                vec![authors.clone(), mentions.clone(), other.clone()],
+                &default_relays,
+                10,
+            )
+            .await;
+
+        assert_eq!(routes.len(), 3);
+        // Authors without relay list and filters without public keys go to the default relays
+        let note = Filter::new().kind(Kind::TextNote);
+        assert_eq!(routes[&a], vec![note.clone().author(pk3), other]);
+        assert_eq!(routes[&b], vec![note.author(pk1)]);
+        assert_eq!(routes[&c], vec![mentions]);
+    }
+
+    #[tokio::test]
+    async fn test_prepare_relays() {
+        let keys = Keys::generate();
+        let (a, b, c) = (url("a"), url("b"), url("c"));
+        let (transport, _) = mock::spawn(|_, _| true);
+        let client = mock::client(&keys, &transport, [&a, &b]).await;
+        client.disconnect_relay(&b).await.unwrap();
+        thread::sleep(Duration::from_millis(100)).await;
+
+        let urls: HashSet<Url> = [a.clone(), b.clone(), c.clone()].into_iter().collect();
+        let opts = RelayOptions::new().transport(transport);
+        client
+            .gossip
+            .prepare_relays(&client.pool, &urls, opts, Some(Duration::from_secs(1)), 10)
+            .await
+            .unwrap();
+
+        // The default relay disconnected by the user isn't reconnected
+        let relays = client.relays().await;
+        assert_eq!(relays.len(), 3);
+        assert_eq!(relays[&a].status().await, RelayStatus::Connected);
+        assert_eq!(relays[&b].status().await, RelayStatus::Terminated);
+        assert_eq!(relays[&c].status().await, RelayStatus::Connected);
+        assert_eq!(
+            client.gossip.default_relays(&client.pool).await,
+            [a, b].into_iter().collect()
+        );
+    }
+}
@@ -0,0 +1,628 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Relay-based groups (NIP-29)
+
+use std::collections::{HashMap, VecDeque};
+#[cfg(feature = "nip11")]
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use nostr::prelude::*;
+use nostr_relay_pool::{PublishReport, SubscribeAutoCloseOptions};
+use tokio::sync::RwLock;
+
+use super::{Client, Error};
+
+/// Number of recent events referenced in the `previous` tag
+const PREVIOUS_REFERENCES: usize = 3;
+
+/// Group state, as signed by the relay
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GroupState {
+    /// Metadata
+    pub metadata: Option<GroupMetadata>,
+    /// Admins
+    pub admins: Vec<GroupAdmin>,
+    /// Members
+    pub members: Vec<PublicKey>,
+}
+
+#[derive(Debug, Default)]
+struct InternalGroupState {
+    state: GroupState,
+    updated_at: HashMap<Kind, Timestamp>,
+    recent: VecDeque<EventId>,
+}
+
+impl InternalGroupState {
+    /// Check if the event is newer than the current version of the state `kind`
+    fn update(&mut self, kind: Kind, created_at: Timestamp) -> bool {
+        match self.updated_at.get(&kind) {
+            Some(updated_at) if *updated_at > created_at => false,
+            _ => {
+                self.updated_at.insert(kind, created_at);
+                true
+            }
+        }
+    }
+
+    fn track(&mut self, id: EventId) {
+        if !self.recent.contains(&id) {
+            if self.recent.len() >= MAX_PREVIOUS {
+                self.recent.pop_front();
+            }
+            self.recent.push_back(id);
+        }
+    }
+}
+
+/// Session of a relay-based group (NIP-29)
+///
+/// Keep the group state (metadata, admins and members) signed by the relay and
+/// the recent events of the group, referenced in the `previous` tag of the sent events.
+///
+/// Events received from the relays (ex. with [`Client::handle_notifications`]) must be
+/// passed to [`GroupSession::handle_event`] to keep the state updated.
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/29.md>
+#[derive(Debug, Clone)]
+pub struct GroupSession {
+    client: Client,
+    relay_url: Url,
+    group_id: String,
+    relay_public_key: PublicKey,
+    state: Arc<RwLock<InternalGroupState>>,
+}
+
+impl Client {
+    /// Open a session of a relay-based group (NIP-29)
+    ///
+    /// Add and connect the relay, then fetch the group state.
+    /// The relay public key is taken from the NIP-11 document of the relay:
+    /// if not available, [`Error::GroupRelayPublicKeyNotFound`] is returned,
+    /// since the group state can't be verified.
+    /// Use [`Client::group_with_relay_public_key`] if the relay public key is known.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/29.md>
+    pub async fn group<S>(&self, relay_url: Url, group_id: S) -> Result<GroupSession, Error>
+    where
+        S: Into<String>,
+    {
+        self.add_relay(relay_url.clone()).await?;
+        self.connect_relay(relay_url.clone()).await?;
+
+        #[cfg(feature = "nip11")]
+        let relay_public_key: Option<PublicKey> = {
+            let relay = self.relay(relay_url.clone()).await?;
+            let mut document = relay.document().await;
+
+            // The document may be not fetched yet
+            if document.pubkey.is_none() {
+                #[cfg(not(target_arch = "wasm32"))]
+                let proxy = relay.proxy();
+                #[cfg(target_arch = "wasm32")]
+                let proxy = None;
+                match RelayInformationDocument::get(relay_url.clone(), proxy).await {
+                    Ok(doc) => document = doc,
+                    Err(e) => tracing::warn!(
+                        "Impossible to get information document from {relay_url}: {e}"
+                    ),
+                }
+            }
+
+            document
+                .pubkey
+                .and_then(|public_key| PublicKey::from_str(&public_key).ok())
+        };
+        #[cfg(not(feature = "nip11"))]
+        let relay_public_key: Option<PublicKey> = None;
+
+        match relay_public_key {
+            Some(relay_public_key) => {
+                self.open_group(relay_url, group_id.into(), relay_public_key)
+                    .await
+            }
+            None => Err(Error::GroupRelayPublicKeyNotFound(relay_url)),
+        }
+    }
+
+    /// Open a session of a relay-based group (NIP-29), signed by a known relay public key
+    ///
+    /// Add and connect the relay, then fetch the group state.
+    /// The group state is accepted only if signed by `relay_public_key`.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/29.md>
+    pub async fn group_with_relay_public_key<S>(
+        &self,
+        relay_url: Url,
+        group_id: S,
+        relay_public_key: PublicKey,
+    ) -> Result<GroupSession, Error>
+    where
+        S: Into<String>,
+    {
+        self.add_relay(relay_url.clone()).await?;
+        self.connect_relay(relay_url.clone()).await?;
+        self.open_group(relay_url, group_id.into(), relay_public_key)
+            .await
+    }
+
+    async fn open_group(
+        &self,
+        relay_url: Url,
+        group_id: String,
+        relay_public_key: PublicKey,
+    ) -> Result<GroupSession, Error> {
+        let session = GroupSession {
+            client: self.clone(),
+            relay_url,
+            group_id,
+            relay_public_key,
+            state: Arc::new(RwLock::new(InternalGroupState::default())),
+        };
+        session.refresh(None).await?;
+        Ok(session)
+    }
+}
+
+impl GroupSession {
+    /// Relay URL
+    pub fn relay_url(&self) -> &Url {
+        &self.relay_url
+    }
+
+    /// Group ID
+    pub fn group_id(&self) -> &str {
+        &self.group_id
+    }
+
+    /// Relay public key
+    pub fn relay_public_key(&self) -> PublicKey {
+        self.relay_public_key
+    }
+
+    /// Get group state
+    pub async fn state(&self) -> GroupState {
+        self.state.read().await.state.clone()
+    }
+
+    /// Check if the public key is a member of the group
+    pub async fn is_member(&self, public_key: &PublicKey) -> bool {
+        let state = self.state.read().await;
+        state.state.members.contains(public_key)
+            || state
+                .state
+                .admins
+                .iter()
+                .any(|admin| admin.public_key == *public_key)
+    }
+
+    /// Check if the public key is an admin of the group
+    pub async fn is_admin(&self, public_key: &PublicKey) -> bool {
+        let state = self.state.read().await;
+        state
+            .state
+            .admins
+            .iter()
+            .any(|admin| admin.public_key == *public_key)
+    }
+
+    /// Fetch the group state and the recent events of the group from the relay
+    ///
+    /// If timeout is set to `None`, the default from [`Options`](super::Options) will be used.
+    pub async fn refresh(&self, timeout: Option<Duration>) -> Result<(), Error> {
+        let mut events: Vec<Event> = self
+            .client
+            .get_events_from(
+                [self.relay_url.clone()],
+                vec![
+                    self.state_filter(),
+                    Filter::new()
+                        .group(self.group_id.clone())
+                        .limit(MAX_PREVIOUS),
+                ],
+                timeout,
+            )
+            .await?;
+
+        events.sort_by_key(|event| (event.created_at(), event.id()));
+        for event in events.iter() {
+            self.handle_event(&self.relay_url, event).await;
+        }
+
+        Ok(())
+    }
+
+    /// Subscribe to the group state and to the new events of the group
+    pub async fn subscribe(
+        &self,
+        opts: Option<SubscribeAutoCloseOptions>,
+    ) -> Result<SubscriptionId, Error> {
+        let filters: Vec<Filter> = vec![
+            self.state_filter(),
+            Filter::new()
+                .group(self.group_id.clone())
+                .since(Timestamp::now()),
+        ];
+        self.client
+            .subscribe_to([self.relay_url.clone()], filters, opts)
+            .await
+    }
+
+    /// Handle an event received from a relay
+    ///
+    /// Update the group state with the newer metadata, admins and members signed by the relay
+    /// and track the events of the group. Moderation events of the admins adding or removing users
+    /// are applied to the members, until the relay publish the new members list.
+    ///
+    /// Return `false` if the event doesn't belong to the group or if it's not received from the group relay.
+    pub async fn handle_event(&self, relay_url: &Url, event: &Event) -> bool {
+        if *relay_url != self.relay_url
+            || nip29::extract_group_id(event) != Some(self.group_id.as_str())
+        {
+            return false;
+        }
+
+        let kind: Kind = event.kind();
+        let is_state: bool = matches!(
+            kind,
+            Kind::GroupMetadata | Kind::GroupAdmins | Kind::GroupMembers
+        );
+
+        // The state must be signed by the relay
+        if is_state && event.author() != self.relay_public_key {
+            return false;
+        }
+
+        let mut state = self.state.write().await;
+
+        match kind {
+            Kind::GroupMetadata => match GroupMetadata::try_from(event) {
+                Ok(metadata) if state.update(kind, event.created_at()) => {
+                    state.state.metadata = Some(metadata);
+                }
+                Ok(..) => {}
+                Err(e) => tracing::warn!("Invalid group metadata {}: {e}", event.id()),
+            },
+            Kind::GroupAdmins => match GroupAdmins::try_from(event) {
+                Ok(admins) if state.update(kind, event.created_at()) => {
+                    state.state.admins = admins.admins;
+                }
+                Ok(..) => {}
+                Err(e) => tracing::warn!("Invalid group admins {}: {e}", event.id()),
+            },
+            Kind::GroupMembers => match GroupMembers::try_from(event) {
+                Ok(members) if state.update(kind, event.created_at()) => {
+                    state.state.members = members.members;
+                }
+                Ok(..) => {}
+                Err(e) => tracing::warn!("Invalid group members {}: {e}", event.id()),
+            },
+            _ => {
+                if nip29::is_moderation(&kind) {
+                    // Moderation allowed only to the admins (or the relay)
+                    let author: PublicKey = event.author();
+                    let authorized: bool = self.relay_public_key == author
+                        || state
+                            .state
+                            .admins
+                            .iter()
+                            .any(|admin| admin.public_key == author);
+                    if let (true, Ok(moderation)) = (authorized, GroupModeration::try_from(event)) {
+                        let newer: bool = state
+                            .updated_at
+                            .get(&Kind::GroupMembers)
+                            .map_or(true, |updated_at| event.created_at() >= *updated_at);
+                        match moderation.action {
+                            GroupAction::AddUser { public_key, .. } if newer => {
+                                if !state.state.members.contains(&public_key) {
+                                    state.state.members.push(public_key);
+                                }
+                            }
+                            GroupAction::RemoveUser { public_key } if newer => {
+                                state.state.members.retain(|p| *p != public_key);
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+
+                state.track(event.id());
+            }
+        }
+
+        true
+    }
+
+    /// Send a join request
+    pub async fn join<S>(&self, reason: S) -> Result<PublishReport, Error>
+    where
+        S: Into<String>,
+    {
+        let builder = EventBuilder::group_join_request(self.group_id.clone(), reason.into());
+        self.send_builder(builder).await
+    }
+
+    /// Send a leave request
+    pub async fn leave<S>(&self, reason: S) -> Result<PublishReport, Error>
+    where
+        S: Into<String>,
+    {
+        let builder = EventBuilder::group_leave_request(self.group_id.clone(), reason.into());
+        self.send_builder(builder).await
+    }
+
+    /// Send an event to the group (ex. a chat message of kind `9`)
+    ///
+    /// The `h` tag and the `previous` tag, referencing the recent events of the group, are added.
+    pub async fn send<S, I>(&self, kind: Kind, content: S, tags: I) -> Result<PublishReport, Error>
+    where
+        S: Into<String>,
+        I: IntoIterator<Item = Tag>,
+    {
+        let mut tags: Vec<Tag> = tags
+            .into_iter()
+            .filter(|tag| !matches!(tag, Tag::Group(..)))
+            .collect();
+        tags.insert(0, Tag::Group(self.group_id.clone()));
+        tags.extend(nip29::previous_tag(self.previous().await));
+
+        let builder = EventBuilder::new(kind, content, tags);
+        self.send_builder(builder).await
+    }
+
+    /// Send a moderation event (the user must be an admin with the required permission)
+    pub async fn moderate<S>(&self, action: GroupAction, reason: S) -> Result<PublishReport, Error>
+    where
+        S: Into<String>,
+    {
+        let moderation = GroupModeration {
+            reason: reason.into(),
+            previous: self.previous().await,
+            ..GroupModeration::new(self.group_id.clone(), action)
+        };
+        let builder = EventBuilder::group_moderation(moderation);
+        self.send_builder(builder).await
+    }
+
+    fn state_filter(&self) -> Filter {
+        Filter::new()
+            .kinds([Kind::GroupMetadata, Kind::GroupAdmins, Kind::GroupMembers])
+            .identifier(self.group_id.clone())
+            .author(self.relay_public_key)
+    }
+
+    /// References of the newest events of the group
+    async fn previous(&self) -> Vec<String> {
+        let state = self.state.read().await;
+        state
+            .recent
+            .iter()
+            .rev()
+            .take(PREVIOUS_REFERENCES)
+            .map(nip29::previous_reference)
+            .collect()
+    }
+
+    async fn send_builder(&self, builder: EventBuilder) -> Result<PublishReport, Error> {
+        let event: Event = self.client.sign_event_builder(builder).await?;
+        let report: PublishReport = self
+            .client
+            .send_event_to([self.relay_url.clone()], event.clone())
+            .await?;
+
+        // Track the event only if accepted by the relay
+        if report.accepted(&event.id()).contains(&&self.relay_url) {
+            self.handle_event(&self.relay_url, &event).await;
+        }
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock;
+
+    const GROUP_ID: &str = "group";
+
+    fn relay_url() -> Url {
+        Url::parse("ws://relay.local").unwrap()
+    }
+
+    /// Group state signed by the relay
+    fn state_events(relay_keys: &Keys, admin: &Keys, members: Vec<PublicKey>) -> Vec<Event> {
+        let mut metadata = GroupMetadata::new(GROUP_ID);
+        metadata.name = Some(String::from("Group"));
+        let admins = GroupAdmins {
+            group_id: GROUP_ID.to_string(),
+            admins: vec![GroupAdmin {
+                public_key: admin.public_key(),
+                roles: vec![String::from("add-user")],
+            }],
+        };
+        let members = GroupMembers {
+            group_id: GROUP_ID.to_string(),
+            members,
+        };
+        vec![
+            EventBuilder::group_metadata(metadata),
+            EventBuilder::group_admins(admins),
+            EventBuilder::group_members(members),
+        ]
+        .into_iter()
+        .map(|builder| builder.to_event(relay_keys).unwrap())
+        .collect()
+    }
+
+    /// Session with known relay public key (without fetching the NIP-11 document)
+    fn new_session(client: &Client, relay_keys: &Keys) -> GroupSession {
+        GroupSession {
+            client: client.clone(),
+            relay_url: relay_url(),
+            group_id: GROUP_ID.to_string(),
+            relay_public_key: relay_keys.public_key(),
+            state: Arc::new(RwLock::new(InternalGroupState::default())),
+        }
+    }
+
+    fn add_user(admin: &Keys, public_key: PublicKey) -> Event {
+        let action = GroupAction::AddUser {
+            public_key,
+            roles: Vec::new(),
+        };
+        EventBuilder::group_moderation(GroupModeration::new(GROUP_ID, action))
+            .to_event(admin)
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_refresh() {
+        let relay_keys = Keys::generate();
+        let admin = Keys::generate();
+        let member = Keys::generate();
+        let url = relay_url();
+
+        let (transport, relays) = mock::spawn(|_, _| true);
+        for event in state_events(&relay_keys, &admin, vec![member.public_key()]) {
+            relays.insert(&url, event);
+        }
+
+        // Fake members list, not signed by the relay
+        let fake = Keys::generate();
+        let fake_members = GroupMembers {
+            group_id: GROUP_ID.to_string(),
+            members: vec![fake.public_key()],
+        };
+        relays.insert(
+            &url,
+            EventBuilder::group_members(fake_members)
+                .custom_created_at(Timestamp::now() + 60u64)
+                .to_event(&fake)
+                .unwrap(),
+        );
+
+        let client = mock::client(&member, &transport, [&url]).await;
+        let session = new_session(&client, &relay_keys);
+        session.refresh(None).await.unwrap();
+
+        let state = session.state().await;
+        assert_eq!(state.metadata.unwrap().name.as_deref(), Some("Group"));
+        assert!(session.is_admin(&admin.public_key()).await);
+        assert!(session.is_member(&member.public_key()).await);
+        assert!(!session.is_member(&fake.public_key()).await);
+    }
+
+    #[tokio::test]
+    async fn test_group_with_relay_public_key() {
+        let relay_keys = Keys::generate();
+        let admin = Keys::generate();
+        let member = Keys::generate();
+        let url = relay_url();
+
+        let (transport, relays) = mock::spawn(|_, _| true);
+        for event in state_events(&relay_keys, &admin, vec![member.public_key()]) {
+            relays.insert(&url, event);
+        }
+
+        // State signed by another key
+        let fake = Keys::generate();
+        let fake_metadata = GroupMetadata::new(GROUP_ID);
+        relays.insert(
+            &url,
+            EventBuilder::group_metadata(fake_metadata)
+                .custom_created_at(Timestamp::now() + 60u64)
+                .to_event(&fake)
+                .unwrap(),
+        );
+
+        let client = mock::client(&member, &transport, [&url]).await;
+        let session = client
+            .group_with_relay_public_key(url, GROUP_ID, relay_keys.public_key())
+            .await
+            .unwrap();
+        assert_eq!(session.relay_public_key(), relay_keys.public_key());
+        let state = session.state().await;
+        assert_eq!(state.metadata.unwrap().name.as_deref(), Some("Group"));
+        assert!(session.is_member(&member.public_key()).await);
+    }
+
+    #[tokio::test]
+    async fn test_group_relay_public_key_not_found() {
+        // No information document available
+        let url = Url::parse("ws://127.0.0.1:1").unwrap();
+        let keys = Keys::generate();
+        let (transport, _) = mock::spawn(|_, _| true);
+        let client = mock::client(&keys, &transport, [&url]).await;
+        match client.group(url.clone(), GROUP_ID).await {
+            Err(Error::GroupRelayPublicKeyNotFound(u)) => assert_eq!(u, url),
+            res => panic!("unexpected result: {res:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handle_event_from_other_relay() {
+        let relay_keys = Keys::generate();
+        let admin = Keys::generate();
+        let client = Client::new(&admin);
+        let session = new_session(&client, &relay_keys);
+
+        let other = Url::parse("ws://other.local").unwrap();
+        for event in state_events(&relay_keys, &admin, Vec::new()) {
+            assert!(!session.handle_event(&other, &event).await);
+        }
+        assert_eq!(session.state().await, GroupState::default());
+    }
+
+    #[tokio::test]
+    async fn test_moderation_only_from_admins() {
+        let relay_keys = Keys::generate();
+        let admin = Keys::generate();
+        let user = Keys::generate();
+        let url = relay_url();
+        let client = Client::new(&user);
+        let session = new_session(&client, &relay_keys);
+
+        for event in state_events(&relay_keys, &admin, Vec::new()) {
+            assert!(session.handle_event(&url, &event).await);
+        }
+
+        // Not an admin
+        let event = add_user(&user, user.public_key());
+        assert!(session.handle_event(&url, &event).await);
+        assert!(!session.is_member(&user.public_key()).await);
+
+        let event = add_user(&admin, user.public_key());
+        assert!(session.handle_event(&url, &event).await);
+        assert!(session.is_member(&user.public_key()).await);
+    }
+
+    #[tokio::test]
+    async fn test_send_track_only_accepted() {
+        let relay_keys = Keys::generate();
+        let user = Keys::generate();
+        let url = relay_url();
+
+        // Reject the events
+        let (transport, _) = mock::spawn(|_, _| false);
+        let client = mock::client(&user, &transport, [&url]).await;
+        let session = new_session(&client, &relay_keys);
+        assert!(session.send(Kind::from(9), "Hello", []).await.is_err());
+        assert!(session.previous().await.is_empty());
+
+        // Accept the events
+        let (transport, relays) = mock::spawn(|_, _| true);
+        let client = mock::client(&user, &transport, [&url]).await;
+        let session = new_session(&client, &relay_keys);
+        let report = session.send(Kind::from(9), "Hello", []).await.unwrap();
+        assert_eq!(
+            session.previous().await,
+            vec![nip29::previous_reference(&report.id())]
+        );
+        assert_eq!(relays.events(&url).len(), 1);
+    }
+}
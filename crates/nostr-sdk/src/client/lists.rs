@@ -0,0 +1,281 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Lists (NIP-51)
+
+use std::cmp;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_utility::thread;
+use nostr::nips::nip51::{self, ListItems};
+use nostr::prelude::*;
+use nostr_relay_pool::{FilterOptions, PublishReport, Relay};
+use nostr_signer::prelude::*;
+
+use super::{Client, Error};
+
+impl Client {
+    /// Get a list or set (NIP-51) of the signer public key, with the private items decrypted
+    ///
+    /// The `identifier` (`d` tag) is used only for sets. Return `None` if the list doesn't exist
+    /// and an error if no relay sent the stored events (the list may exist).
+    /// The items can be parsed with the NIP-51 types (ex. `MuteList::from_tags(items.iter())`).
+    ///
+    /// If timeout is set to `None`, the default from [`Options`](super::Options) will be used.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/51.md>
+    pub async fn get_list(
+        &self,
+        kind: Kind,
+        identifier: Option<&str>,
+        timeout: Option<Duration>,
+    ) -> Result<Option<ListItems>, Error> {
+        let signer: Arc<DynNostrSigner> = self.signer().await?;
+        let list = self.fetch_list(&signer, kind, identifier, timeout).await?;
+        Ok(list.map(|(.., items)| items))
+    }
+
+    /// Fetch a list or set (NIP-51) of the signer public key, modify it and republish it
+    ///
+    /// The private items are encrypted to the signer public key with NIP44 or,
+    /// if not supported by the signer, with NIP04.
+    /// Updates made by this client are serialized, so concurrent calls don't overwrite each other.
+    /// Fail if no relay sent the stored events, to not replace an existing list with a new one.
+    ///
+    /// If timeout is set to `None`, the default from [`Options`](super::Options) will be used.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/51.md>
+    pub async fn update_list<F>(
+        &self,
+        kind: Kind,
+        identifier: Option<&str>,
+        timeout: Option<Duration>,
+        f: F,
+    ) -> Result<PublishReport, Error>
+    where
+        F: FnOnce(&mut ListItems),
+    {
+        let _guard = self.lists_lock.lock().await;
+
+        let signer: Arc<DynNostrSigner> = self.signer().await?;
+        let (prev, mut items) = match self.fetch_list(&signer, kind, identifier, timeout).await? {
+            Some((event, items)) => (Some(event), items),
+            None => (None, ListItems::default()),
+        };
+
+        f(&mut items);
+
+        // Sets are identified by the `d` tag
+        let ListItems {
+            public: mut tags,
+            private,
+        } = items;
+        tags.retain(|tag| !matches!(tag, Tag::Identifier(..)));
+        if kind.is_parameterized_replaceable() {
+            tags.insert(
+                0,
+                Tag::Identifier(identifier.unwrap_or_default().to_string()),
+            );
+        }
+
+        let content: String = if private.is_empty() {
+            String::new()
+        } else {
+            let public_key: PublicKey = signer.public_key().await?;
+            let json: String = nip51::private_items_to_json(&private);
+            match signer.nip44_encrypt(public_key, json.clone()).await {
+                Ok(content) => content,
+                Err(SignerError::NotSupported | SignerError::FeatureDisabled) => {
+                    signer.nip04_encrypt(public_key, json).await?
+                }
+                Err(e) => return Err(e.into()),
+            }
+        };
+
+        // Make sure to replace the previous version
+        let mut created_at: Timestamp = Timestamp::now();
+        if let Some(prev) = prev {
+            created_at = cmp::max(created_at, prev.created_at() + 1_u64);
+        }
+
+        let builder = EventBuilder::new(kind, content, tags).custom_created_at(created_at);
+        let event: Event = signer.sign_event_builder(builder).await?;
+        self.send_event(event).await
+    }
+
+    /// Get the newest version of a list, with the private items decrypted
+    async fn fetch_list(
+        &self,
+        signer: &Arc<DynNostrSigner>,
+        kind: Kind,
+        identifier: Option<&str>,
+        timeout: Option<Duration>,
+    ) -> Result<Option<(Event, ListItems)>, Error> {
+        let public_key: PublicKey = signer.public_key().await?;
+        let mut filter: Filter = Filter::new().author(public_key).kind(kind);
+        if kind.is_parameterized_replaceable() {
+            filter = filter.identifier(identifier.unwrap_or_default());
+        }
+
+        let events: Vec<Event> = self.get_list_events(filter, timeout).await?;
+        let event: Event = match events
+            .into_iter()
+            .max_by_key(|event| (event.created_at(), event.id()))
+        {
+            Some(event) => event,
+            None => return Ok(None),
+        };
+
+        let content: &str = event.content();
+        let private: Vec<Tag> = if content.is_empty() {
+            Vec::new()
+        } else {
+            let json: String = if nip51::is_nip04_content(content) {
+                signer
+                    .nip04_decrypt(public_key, content.to_string())
+                    .await?
+            } else {
+                signer
+                    .nip44_decrypt(public_key, content.to_string())
+                    .await?
+            };
+            nip51::private_items_from_json(json)?
+        };
+
+        let items = ListItems {
+            public: event.iter_tags().cloned().collect(),
+            private,
+        };
+
+        Ok(Some((event, items)))
+    }
+
+    /// Get the list events from the read relays (from the outbox relays with gossip)
+    ///
+    /// Return an error if no relay sent the stored events (`EOSE`): an empty result
+    /// must mean that the list doesn't exist, not that the relays failed.
+    async fn get_list_events(
+        &self,
+        filter: Filter,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<Event>, Error> {
+        let timeout: Duration = timeout.unwrap_or(self.opts.timeout);
+
+        let targets: HashMap<Url, Vec<Filter>> = if self.opts.get_gossip() {
+            self.gossip_routes(vec![filter]).await?
+        } else {
+            self.pool
+                .relays()
+                .await
+                .into_iter()
+                .filter(|(_, relay)| relay.flags().has_read())
+                .map(|(url, _)| (url, vec![filter.clone()]))
+                .collect()
+        };
+
+        let mut handles = Vec::with_capacity(targets.len());
+        for (url, filters) in targets.into_iter() {
+            let relay: Relay = self.pool.relay(&url).await?;
+            let handle = thread::spawn(async move {
+                match relay
+                    .get_events_of(filters, timeout, FilterOptions::ExitOnEOSE)
+                    .await
+                {
+                    Ok(events) => Some(events),
+                    Err(e) => {
+                        tracing::error!("Failed to get list from {url}: {e}");
+                        None
+                    }
+                }
+            });
+            handles.push(handle);
+        }
+
+        let mut events: Vec<Event> = Vec::new();
+        let mut eose: bool = false;
+        for handle in handles.into_iter().flatten() {
+            match handle.join().await {
+                Ok(Some(list)) => {
+                    eose = true;
+                    events.extend(list);
+                }
+                Ok(None) => {}
+                Err(e) => tracing::error!("Impossible to join thread: {e}"),
+            }
+        }
+
+        if eose {
+            Ok(events)
+        } else {
+            Err(Error::ListNotFetched)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr_relay_pool::DuplexTransport;
+
+    use super::*;
+    use crate::mock;
+
+    #[tokio::test]
+    async fn test_update_list() {
+        let keys = Keys::generate();
+        let url = Url::parse("ws://relay.local").unwrap();
+        let (transport, relays) = mock::spawn(|_, _| true);
+        let client = mock::client(&keys, &transport, [&url]).await;
+
+        let kind = Kind::MuteList;
+        assert!(client.get_list(kind, None, None).await.unwrap().is_none());
+
+        client
+            .update_list(kind, None, None, |items| {
+                items.public.push(Tag::Hashtag(String::from("nostr")));
+                items.private.push(Tag::Hashtag(String::from("secret")));
+            })
+            .await
+            .unwrap();
+        client
+            .update_list(kind, None, None, |items| {
+                items.public.push(Tag::Hashtag(String::from("rust")));
+            })
+            .await
+            .unwrap();
+        assert_eq!(relays.events(&url).len(), 2);
+
+        let items = client.get_list(kind, None, None).await.unwrap().unwrap();
+        assert_eq!(
+            items.public,
+            vec![
+                Tag::Hashtag(String::from("nostr")),
+                Tag::Hashtag(String::from("rust"))
+            ]
+        );
+        assert_eq!(items.private, vec![Tag::Hashtag(String::from("secret"))]);
+    }
+
+    #[tokio::test]
+    async fn test_update_list_without_eose() {
+        // No relay side: the connection always fails
+        let (transport, listener) = DuplexTransport::new();
+        drop(listener);
+
+        let keys = Keys::generate();
+        let url = Url::parse("ws://relay.local").unwrap();
+        let client = mock::client(&keys, &transport, [&url]).await;
+
+        let res = client
+            .update_list(
+                Kind::MuteList,
+                None,
+                Some(Duration::from_secs(1)),
+                |items| items.public.push(Tag::Hashtag(String::from("nostr"))),
+            )
+            .await;
+        assert!(matches!(res, Err(Error::ListNotFetched)));
+    }
+}
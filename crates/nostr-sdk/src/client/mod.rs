@@ -4,11 +4,15 @@
 
 //! Client
 
-use std::collections::HashMap;
+#[cfg(feature = "nip11")]
+use std::cmp;
+use std::collections::{HashMap, HashSet};
 use std::future::Future;
 use std::sync::Arc;
 use std::time::Duration;
 
+#[cfg(feature = "nip59")]
+use lru::LruCache;
 use nostr::event::builder::Error as EventBuilderError;
 use nostr::prelude::*;
 use nostr::types::metadata::Error as MetadataError;
@@ -16,20 +20,32 @@ use nostr_database::DynNostrDatabase;
 use nostr_relay_pool::pool::{self, Error as RelayPoolError, RelayPool};
 use nostr_relay_pool::relay::Error as RelayError;
 use nostr_relay_pool::{
-    FilterOptions, NegentropyOptions, Relay, RelayOptions, RelayPoolNotification, RelaySendOptions,
-    SubscribeAutoCloseOptions, SubscribeOptions,
+    EventStream, FilterOptions, NegentropyOptions, PaginationOptions, PublishReport, Relay,
+    RelayOptions, RelayPoolNotification, RelaySendOptions, SubscribeAutoCloseOptions,
+    SubscribeOptions, SubscriptionStream,
 };
 use nostr_signer::prelude::*;
 #[cfg(feature = "nip57")]
 use nostr_zapper::{DynNostrZapper, IntoNostrZapper, ZapperError};
-use tokio::sync::{broadcast, RwLock};
+use tokio::sync::{broadcast, Mutex, RwLock};
 
+mod auth;
 pub mod builder;
+mod gossip;
+mod groups;
+mod lists;
 pub mod options;
+#[cfg(feature = "nip59")]
+mod private_msg;
+mod thread;
 #[cfg(feature = "nip57")]
 mod zapper;
 
+pub use self::auth::AuthenticationPolicy;
+use self::auth::ClientAuthenticator;
 pub use self::builder::ClientBuilder;
+use self::gossip::Gossip;
+pub use self::groups::{GroupSession, GroupState};
 pub use self::options::Options;
 #[cfg(feature = "nip57")]
 pub use self::zapper::{ZapDetails, ZapEntity};
@@ -45,7 +61,7 @@ pub enum Error {
     RelayPool(#[from] RelayPoolError),
     /// Signer error
     #[error(transparent)]
-    Signer(#[from] nostr_signer::Error),
+    Signer(#[from] SignerError),
     /// Zapper error
     #[cfg(feature = "nip57")]
     #[error(transparent)]
@@ -63,10 +79,17 @@ pub enum Error {
     #[cfg(feature = "nip57")]
     #[error("zapper not configured")]
     ZapperNotConfigured,
+    /// NIP51 error
+    #[error(transparent)]
+    NIP51(#[from] nostr::nips::nip51::Error),
     /// NIP57 error
     #[cfg(feature = "nip57")]
     #[error(transparent)]
     NIP57(#[from] nostr::nips::nip57::Error),
+    /// NIP59 error
+    #[cfg(feature = "nip59")]
+    #[error(transparent)]
+    NIP59(#[from] nostr::nips::nip59::Error),
     /// LNURL Pay
     #[cfg(feature = "nip57")]
     #[error(transparent)]
@@ -80,15 +103,28 @@ pub enum Error {
     /// Metadata not found
     #[error("metadata not found")]
     MetadataNotFound,
+    /// List not fetched
+    #[error("list not fetched: no relay sent the stored events")]
+    ListNotFetched,
+    /// Inbox relays not found (NIP17)
+    #[error("inbox relays not found for {0}")]
+    InboxRelaysNotFound(PublicKey),
+    /// Group relay public key not found (NIP29)
+    #[error("group relay public key not found for {0}")]
+    GroupRelayPublicKeyNotFound(Url),
 }
 
 /// Nostr client
 #[derive(Debug, Clone)]
 pub struct Client {
     pool: RelayPool,
-    signer: Arc<RwLock<Option<NostrSigner>>>,
+    signer: Arc<RwLock<Option<Arc<DynNostrSigner>>>>,
     #[cfg(feature = "nip57")]
     zapper: Arc<RwLock<Option<Arc<DynNostrZapper>>>>,
+    gossip: Gossip,
+    lists_lock: Arc<Mutex<()>>,
+    #[cfg(feature = "nip59")]
+    unwrapped_gifts: Arc<Mutex<LruCache<EventId, UnwrappedGift>>>,
     opts: Options,
 }
 
@@ -112,7 +148,7 @@ impl Client {
     /// ```
     pub fn new<S>(signer: S) -> Self
     where
-        S: Into<NostrSigner>,
+        S: IntoNostrSigner,
     {
         Self::with_opts(signer, Options::default())
     }
@@ -131,18 +167,30 @@ impl Client {
     /// ```
     pub fn with_opts<S>(signer: S, opts: Options) -> Self
     where
-        S: Into<NostrSigner>,
+        S: IntoNostrSigner,
     {
         ClientBuilder::new().signer(signer).opts(opts).build()
     }
 
     /// Compose [`Client`] from [`ClientBuilder`]
     pub fn from_builder(builder: ClientBuilder) -> Self {
+        let pool = RelayPool::with_database(builder.opts.pool, builder.database);
+        let signer = Arc::new(RwLock::new(builder.signer));
+
+        // Sign NIP-42 authentication events with the client signer
+        let authenticator =
+            ClientAuthenticator::new(signer.clone(), builder.opts.authentication_policy.clone());
+        pool.set_authenticator(Some(Arc::new(authenticator)));
+
         Self {
-            pool: RelayPool::with_database(builder.opts.pool, builder.database),
-            signer: Arc::new(RwLock::new(builder.signer)),
+            pool,
+            signer,
             #[cfg(feature = "nip57")]
             zapper: Arc::new(RwLock::new(builder.zapper)),
+            gossip: Gossip::default(),
+            lists_lock: Arc::new(Mutex::new(())),
+            #[cfg(feature = "nip59")]
+            unwrapped_gifts: Arc::new(Mutex::new(private_msg::unwrapped_gifts_cache())),
             opts: builder.opts,
         }
     }
@@ -152,18 +200,33 @@ impl Client {
         self.opts.update_difficulty(difficulty);
     }
 
+    /// Check if signer is configured
+    pub async fn has_signer(&self) -> bool {
+        let signer = self.signer.read().await;
+        signer.is_some()
+    }
+
     /// Get current nostr signer
     ///
     /// Rise error if it not set.
-    pub async fn signer(&self) -> Result<NostrSigner, Error> {
+    pub async fn signer(&self) -> Result<Arc<DynNostrSigner>, Error> {
         let signer = self.signer.read().await;
         signer.clone().ok_or(Error::SignerNotConfigured)
     }
 
     /// Set nostr signer
-    pub async fn set_signer(&self, signer: Option<NostrSigner>) {
+    pub async fn set_signer<S>(&self, signer: S)
+    where
+        S: IntoNostrSigner,
+    {
         let mut s = self.signer.write().await;
-        *s = signer;
+        *s = Some(signer.into_nostr_signer());
+    }
+
+    /// Unset nostr signer
+    pub async fn unset_signer(&self) {
+        let mut s = self.signer.write().await;
+        *s = None;
     }
 
     /// Check if `zapper` is configured
@@ -273,19 +336,29 @@ impl Client {
         U: TryIntoUrl,
         pool::Error: From<<U as TryIntoUrl>::Err>,
     {
+        let opts: RelayOptions = self.relay_opts();
+        self.add_relay_with_opts(url, opts).await
+    }
+
+    /// Compose [`RelayOptions`] from client [`Options`]
+    fn relay_opts(&self) -> RelayOptions {
         let opts: RelayOptions = RelayOptions::new();
 
         // Set proxy
         #[cfg(not(target_arch = "wasm32"))]
         let opts: RelayOptions = opts.proxy(self.opts.proxy);
 
-        // Set min POW difficulty and limits
+        // Set min POW difficulty, limits and automatic authentication
         let opts: RelayOptions = opts
             .pow(self.opts.get_min_pow_difficulty())
-            .limits(self.opts.relay_limits);
+            .limits(self.opts.relay_limits)
+            .automatic_authentication(self.opts.get_automatic_authentication());
 
-        // Add relay
-        self.add_relay_with_opts(url, opts).await
+        // Set reconnection policy
+        match self.opts.reconnect_policy {
+            Some(policy) => opts.reconnect_policy(policy),
+            None => opts,
+        }
     }
 
     /// Add new relay with custom [`RelayOptions`]
@@ -319,6 +392,16 @@ impl Client {
         U: TryIntoUrl,
         pool::Error: From<<U as TryIntoUrl>::Err>,
     {
+        let url: Url = url.try_into_url().map_err(pool::Error::from)?;
+        self.add_relay_url(url, opts).await
+    }
+
+    async fn add_relay_url(&self, url: Url, opts: RelayOptions) -> Result<bool, Error> {
+        // Replace the relay previously added by gossip
+        if self.gossip.remove_ephemeral(&url).await {
+            self.pool.remove_relay(url.clone()).await?;
+        }
+
         Ok(self.pool.add_relay(url, opts).await?)
     }
 
@@ -452,6 +535,14 @@ impl Client {
         Ok(self.pool.disconnect().await?)
     }
 
+    /// Handle a network connectivity change signaled by the app
+    ///
+    /// When offline, the connection attempts are paused.
+    /// When back online, the reconnection backoff is reset and the relays reconnect (also the dead ones).
+    pub async fn connectivity_changed(&self, online: bool) {
+        self.pool.connectivity_changed(online).await
+    }
+
     /// Get pool subscriptions
     pub async fn subscriptions(&self) -> HashMap<SubscriptionId, Vec<Filter>> {
         self.pool.subscriptions().await
@@ -462,6 +553,13 @@ impl Client {
         self.pool.subscription(id).await
     }
 
+    /// Get the relays targeted by a subscription
+    ///
+    /// Return `None` if the subscription doesn't exist or isn't targeted to specific relays.
+    pub async fn subscription_relays(&self, id: &SubscriptionId) -> Option<HashSet<Url>> {
+        self.pool.subscription_relays(id).await
+    }
+
     /// Subscribe to filters
     ///
     /// This method create a new subscription. None of the previous subscriptions will be edited/closed when you call this!
@@ -503,11 +601,9 @@ impl Client {
         filters: Vec<Filter>,
         opts: Option<SubscribeAutoCloseOptions>,
     ) -> SubscriptionId {
-        let send_opts: RelaySendOptions = self.opts.get_wait_for_subscription();
-        let opts: SubscribeOptions = SubscribeOptions::default()
-            .close_on(opts)
-            .send_opts(send_opts);
-        self.pool.subscribe(filters, opts).await
+        let id: SubscriptionId = SubscriptionId::generate();
+        self.subscribe_with_id(id.clone(), filters, opts).await;
+        id
     }
 
     /// Subscribe to filters with custom [SubscriptionId]
@@ -553,9 +649,147 @@ impl Client {
         let opts: SubscribeOptions = SubscribeOptions::default()
             .close_on(opts)
             .send_opts(send_opts);
+
+        if self.opts.get_gossip() {
+            return self.gossip_subscribe_with_id(id, filters, opts).await;
+        }
+
         self.pool.subscribe_with_id(id, filters, opts).await
     }
 
+    /// Subscribe to filters to **specific relays**
+    ///
+    /// The subscription is sent again to the relays when they reconnect.
+    ///
+    /// # Auto-closing subscription
+    ///
+    /// It's possible to automatically close a subscription by configuring the [SubscribeAutoCloseOptions].
+    ///
+    /// Note: auto-closing subscriptions aren't saved in subscriptions map!
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use nostr_sdk::prelude::*;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Keys::generate();
+    /// #   let client = Client::new(&my_keys);
+    /// let subscription = Filter::new()
+    ///     .pubkeys(vec![my_keys.public_key()])
+    ///     .since(Timestamp::now());
+    ///
+    /// let sub_id = client
+    ///     .subscribe_to(["wss://relay.damus.io"], vec![subscription], None)
+    ///     .await
+    ///     .unwrap();
+    /// println!("Subscription ID: {sub_id}");
+    /// # }
+    /// ```
+    pub async fn subscribe_to<I, U>(
+        &self,
+        urls: I,
+        filters: Vec<Filter>,
+        opts: Option<SubscribeAutoCloseOptions>,
+    ) -> Result<SubscriptionId, Error>
+    where
+        I: IntoIterator<Item = U>,
+        U: TryIntoUrl,
+        pool::Error: From<<U as TryIntoUrl>::Err>,
+    {
+        let id: SubscriptionId = SubscriptionId::generate();
+        self.subscribe_with_id_to(urls, id.clone(), filters, opts)
+            .await?;
+        Ok(id)
+    }
+
+    /// Subscribe to filters with custom [SubscriptionId] to **specific relays**
+    ///
+    /// # Auto-closing subscription
+    ///
+    /// It's possible to automatically close a subscription by configuring the [SubscribeAutoCloseOptions].
+    ///
+    /// Note: auto-closing subscriptions aren't saved in subscriptions map!
+    pub async fn subscribe_with_id_to<I, U>(
+        &self,
+        urls: I,
+        id: SubscriptionId,
+        filters: Vec<Filter>,
+        opts: Option<SubscribeAutoCloseOptions>,
+    ) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = U>,
+        U: TryIntoUrl,
+        pool::Error: From<<U as TryIntoUrl>::Err>,
+    {
+        let send_opts: RelaySendOptions = self.opts.get_wait_for_subscription();
+        let opts: SubscribeOptions = SubscribeOptions::default()
+            .close_on(opts)
+            .send_opts(send_opts);
+        Ok(self
+            .pool
+            .subscribe_with_id_to(urls, id, filters, opts)
+            .await?)
+    }
+
+    /// Subscribe to filters and get the [`SubscriptionStream`] of the subscription
+    ///
+    /// Events are deduplicated across relays.
+    /// The stream ends when the subscription is closed (unsubscribed, auto-closed or closed by all the relays).
+    /// Without relays, the stream waits for the relays connected later.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use nostr_sdk::prelude::*;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Keys::generate();
+    /// #   let client = Client::new(&my_keys);
+    /// let filter = Filter::new().kind(Kind::TextNote).limit(10);
+    /// let mut stream = client.subscribe_stream(vec![filter], None).await;
+    /// while let Some(item) = stream.next().await {
+    ///     match item {
+    ///         SubscriptionItem::Event { event, .. } => println!("{}", event.as_json()),
+    ///         SubscriptionItem::Eose { relay_url } => println!("EOSE from {relay_url}"),
+    ///         SubscriptionItem::Closed { relay_url, message } => {
+    ///             println!("Closed by {relay_url}: {message}")
+    ///         }
+    ///         SubscriptionItem::Lagged { skipped } => println!("{skipped} items dropped"),
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub async fn subscribe_stream(
+        &self,
+        filters: Vec<Filter>,
+        opts: Option<SubscribeAutoCloseOptions>,
+    ) -> SubscriptionStream {
+        self.subscribe_stream_with_id(SubscriptionId::generate(), filters, opts)
+            .await
+    }
+
+    /// Subscribe to filters with custom [SubscriptionId] and get the [`SubscriptionStream`] of the subscription
+    pub async fn subscribe_stream_with_id(
+        &self,
+        id: SubscriptionId,
+        filters: Vec<Filter>,
+        opts: Option<SubscribeAutoCloseOptions>,
+    ) -> SubscriptionStream {
+        let send_opts: RelaySendOptions = self.opts.get_wait_for_subscription();
+        let opts: SubscribeOptions = SubscribeOptions::default()
+            .close_on(opts)
+            .send_opts(send_opts);
+
+        if self.opts.get_gossip() {
+            return self
+                .gossip_subscribe_stream_with_id(id, filters, opts)
+                .await;
+        }
+
+        self.pool.subscribe_stream_with_id(id, filters, opts).await
+    }
+
     /// Unsubscribe
     pub async fn unsubscribe(&self, id: SubscriptionId) {
         let opts: RelaySendOptions = self.opts.get_wait_for_subscription();
@@ -615,6 +849,11 @@ impl Client {
             Some(t) => t,
             None => self.opts.timeout,
         };
+
+        if self.opts.get_gossip() {
+            return self.gossip_get_events_of(filters, timeout, opts).await;
+        }
+
         Ok(self.pool.get_events_of(filters, timeout, opts).await?)
     }
 
@@ -641,6 +880,69 @@ impl Client {
             .await?)
     }
 
+    /// Stream events of filters
+    ///
+    /// Like [`Client::get_events_of`], but the events are sent as soon as received (deduplicated, without any ordering).
+    /// Dropping the stream stops the queries.
+    ///
+    /// If timeout is set to `None`, the default from [`Options`] will be used.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    ///
+    /// use nostr_sdk::prelude::*;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Keys::generate();
+    /// #   let client = Client::new(&my_keys);
+    /// let filter = Filter::new().author(my_keys.public_key()).kind(Kind::TextNote);
+    /// let mut stream = client
+    ///     .stream_events_of(vec![filter], Some(Duration::from_secs(10)))
+    ///     .await
+    ///     .unwrap();
+    /// while let Some(event) = stream.next().await {
+    ///     println!("{}", event.as_json());
+    /// }
+    /// # }
+    /// ```
+    pub async fn stream_events_of(
+        &self,
+        filters: Vec<Filter>,
+        timeout: Option<Duration>,
+    ) -> Result<EventStream, Error> {
+        let timeout: Duration = timeout.unwrap_or(self.opts.timeout);
+        let opts: FilterOptions = FilterOptions::ExitOnEOSE;
+
+        if self.opts.get_gossip() {
+            return self.gossip_stream_events_of(filters, timeout, opts).await;
+        }
+
+        Ok(self.pool.stream_events_of(filters, timeout, opts).await?)
+    }
+
+    /// Stream events of filters from specific relays
+    ///
+    /// If no relay is specified, will be streamed only the events of database.
+    pub async fn stream_events_from<I, U>(
+        &self,
+        urls: I,
+        filters: Vec<Filter>,
+        timeout: Option<Duration>,
+    ) -> Result<EventStream, Error>
+    where
+        I: IntoIterator<Item = U>,
+        U: TryIntoUrl,
+        pool::Error: From<<U as TryIntoUrl>::Err>,
+    {
+        let timeout: Duration = timeout.unwrap_or(self.opts.timeout);
+        Ok(self
+            .pool
+            .stream_events_from(urls, filters, timeout, FilterOptions::ExitOnEOSE)
+            .await?)
+    }
+
     /// Send client message to **all relays**
     pub async fn send_msg(&self, msg: ClientMessage) -> Result<(), Error> {
         let opts: RelaySendOptions = self.opts.get_wait_for_send();
@@ -686,8 +988,15 @@ impl Client {
     ///
     /// This method will wait for the `OK` message from the relay.
     /// If you not want to wait for the `OK` message, use `send_msg` method instead.
-    pub async fn send_event(&self, event: Event) -> Result<EventId, Error> {
+    ///
+    /// Return the [`PublishReport`] with the outcome of every relay.
+    pub async fn send_event(&self, event: Event) -> Result<PublishReport, Error> {
         let opts: RelaySendOptions = self.opts.get_wait_for_send();
+
+        if self.opts.get_gossip() {
+            return self.gossip_send_event(event, opts).await;
+        }
+
         Ok(self.pool.send_event(event, opts).await?)
     }
 
@@ -696,7 +1005,7 @@ impl Client {
         &self,
         events: Vec<Event>,
         opts: RelaySendOptions,
-    ) -> Result<(), Error> {
+    ) -> Result<PublishReport, Error> {
         Ok(self.pool.batch_event(events, opts).await?)
     }
 
@@ -704,7 +1013,7 @@ impl Client {
     ///
     /// This method will wait for the `OK` message from the relay.
     /// If you not want to wait for the `OK` message, use `send_msg` method instead.
-    pub async fn send_event_to<I, U>(&self, urls: I, event: Event) -> Result<EventId, Error>
+    pub async fn send_event_to<I, U>(&self, urls: I, event: Event) -> Result<PublishReport, Error>
     where
         I: IntoIterator<Item = U>,
         U: TryIntoUrl,
@@ -720,7 +1029,7 @@ impl Client {
         urls: I,
         events: Vec<Event>,
         opts: RelaySendOptions,
-    ) -> Result<(), Error>
+    ) -> Result<PublishReport, Error>
     where
         I: IntoIterator<Item = U>,
         U: TryIntoUrl,
@@ -735,6 +1044,11 @@ impl Client {
 
         let public_key = signer.public_key().await?;
         let difficulty: u8 = self.opts.get_difficulty();
+
+        // Pre-mine the POW required by relays (NIP-11)
+        #[cfg(feature = "nip11")]
+        let difficulty: u8 = cmp::max(difficulty, self.relays_min_pow_difficulty().await);
+
         let unsigned = if difficulty > 0 {
             builder.to_unsigned_pow_event(public_key, difficulty)
         } else {
@@ -744,10 +1058,29 @@ impl Client {
         Ok(signer.sign_event(unsigned).await?)
     }
 
+    /// Get the highest min. POW difficulty required by write relays (NIP-11)
+    #[cfg(feature = "nip11")]
+    async fn relays_min_pow_difficulty(&self) -> u8 {
+        let mut difficulty: u8 = 0;
+        for relay in self.relays().await.into_values() {
+            if relay.flags().has_write() {
+                let document = relay.document().await;
+                if let Some(min) = document
+                    .limitation
+                    .and_then(|l| l.min_pow_difficulty)
+                    .and_then(|d| u8::try_from(d).ok())
+                {
+                    difficulty = cmp::max(difficulty, min);
+                }
+            }
+        }
+        difficulty
+    }
+
     /// Take an [`EventBuilder`], sign it by using the [`NostrSigner`] and broadcast to **all relays**.
     ///
     /// Rise an error if the [`NostrSigner`] is not set.
-    pub async fn send_event_builder(&self, builder: EventBuilder) -> Result<EventId, Error> {
+    pub async fn send_event_builder(&self, builder: EventBuilder) -> Result<PublishReport, Error> {
         let event: Event = self.sign_event_builder(builder).await?;
         self.send_event(event).await
     }
@@ -759,7 +1092,7 @@ impl Client {
         &self,
         urls: I,
         builder: EventBuilder,
-    ) -> Result<EventId, Error>
+    ) -> Result<PublishReport, Error>
     where
         I: IntoIterator<Item = U>,
         U: TryIntoUrl,
@@ -808,7 +1141,7 @@ impl Client {
     /// ```
     pub async fn set_metadata(&self, metadata: &Metadata) -> Result<EventId, Error> {
         let builder = EventBuilder::metadata(metadata);
-        self.send_event_builder(builder).await
+        Ok(self.send_event_builder(builder).await?.id())
     }
 
     /// Set relay list (NIP65)
@@ -819,7 +1152,7 @@ impl Client {
         I: IntoIterator<Item = (UncheckedUrl, Option<RelayMetadata>)>,
     {
         let builder = EventBuilder::relay_list(relays);
-        self.send_event_builder(builder).await
+        Ok(self.send_event_builder(builder).await?.id())
     }
 
     /// Publish text note
@@ -846,7 +1179,7 @@ impl Client {
         I: IntoIterator<Item = Tag>,
     {
         let builder = EventBuilder::text_note(content, tags);
-        self.send_event_builder(builder).await
+        Ok(self.send_event_builder(builder).await?.id())
     }
 
     /// Set contact list
@@ -857,7 +1190,7 @@ impl Client {
         I: IntoIterator<Item = Contact>,
     {
         let builder = EventBuilder::contact_list(list);
-        self.send_event_builder(builder).await
+        Ok(self.send_event_builder(builder).await?.id())
     }
 
     async fn get_contact_list_filters(&self) -> Result<Vec<Filter>, Error> {
@@ -1003,7 +1336,7 @@ impl Client {
 
         let builder: EventBuilder = EventBuilder::new(Kind::EncryptedDirectMessage, content, tags);
 
-        self.send_event_builder(builder).await
+        Ok(self.send_event_builder(builder).await?.id())
     }
 
     /// Repost
@@ -1013,7 +1346,7 @@ impl Client {
         relay_url: Option<UncheckedUrl>,
     ) -> Result<EventId, Error> {
         let builder = EventBuilder::repost(event, relay_url);
-        self.send_event_builder(builder).await
+        Ok(self.send_event_builder(builder).await?.id())
     }
 
     /// Delete event
@@ -1024,7 +1357,7 @@ impl Client {
         T: Into<EventIdOrCoordinate>,
     {
         let builder = EventBuilder::delete([id]);
-        self.send_event_builder(builder).await
+        Ok(self.send_event_builder(builder).await?.id())
     }
 
     /// Like event
@@ -1103,7 +1436,7 @@ impl Client {
         S: Into<String>,
     {
         let builder = EventBuilder::reaction(event, reaction);
-        self.send_event_builder(builder).await
+        Ok(self.send_event_builder(builder).await?.id())
     }
 
     /// Create new channel
@@ -1111,7 +1444,7 @@ impl Client {
     /// <https://github.com/nostr-protocol/nips/blob/master/28.md>
     pub async fn new_channel(&self, metadata: &Metadata) -> Result<EventId, Error> {
         let builder = EventBuilder::channel(metadata);
-        self.send_event_builder(builder).await
+        Ok(self.send_event_builder(builder).await?.id())
     }
 
     /// Update channel metadata
@@ -1124,7 +1457,7 @@ impl Client {
         metadata: &Metadata,
     ) -> Result<EventId, Error> {
         let builder = EventBuilder::channel_metadata(channel_id, relay_url, metadata);
-        self.send_event_builder(builder).await
+        Ok(self.send_event_builder(builder).await?.id())
     }
 
     /// Send message to channel
@@ -1140,7 +1473,7 @@ impl Client {
         S: Into<String>,
     {
         let builder = EventBuilder::channel_msg(channel_id, relay_url, msg);
-        self.send_event_builder(builder).await
+        Ok(self.send_event_builder(builder).await?.id())
     }
 
     /// Hide channel message
@@ -1155,7 +1488,7 @@ impl Client {
         S: Into<String>,
     {
         let builder = EventBuilder::hide_channel_msg(message_id, reason);
-        self.send_event_builder(builder).await
+        Ok(self.send_event_builder(builder).await?.id())
     }
 
     /// Mute channel user
@@ -1170,7 +1503,7 @@ impl Client {
         S: Into<String>,
     {
         let builder = EventBuilder::mute_channel_user(pubkey, reason);
-        self.send_event_builder(builder).await
+        Ok(self.send_event_builder(builder).await?.id())
     }
 
     /// Create an auth event
@@ -1181,7 +1514,7 @@ impl Client {
         S: Into<String>,
     {
         let builder = EventBuilder::auth(challenge, relay);
-        self.send_event_builder(builder).await
+        Ok(self.send_event_builder(builder).await?.id())
     }
 
     /// Create zap receipt event
@@ -1198,7 +1531,7 @@ impl Client {
         S: Into<String>,
     {
         let builder = EventBuilder::zap_receipt(bolt11, preimage, zap_request);
-        self.send_event_builder(builder).await
+        Ok(self.send_event_builder(builder).await?.id())
     }
 
     /// Send a Zap!
@@ -1228,17 +1561,14 @@ impl Client {
         expiration: Option<Timestamp>,
     ) -> Result<(), Error> {
         // Compose rumor
-        let signer: NostrSigner = self.signer().await?;
+        let signer: Arc<DynNostrSigner> = self.signer().await?;
         let public_key: PublicKey = signer.public_key().await?;
         let rumor = rumor.to_unsigned_event(public_key);
 
-        // Compose seal
-        let content: String = signer.nip44_encrypt(receiver, rumor.as_json()).await?;
-        let seal: EventBuilder = EventBuilder::new(Kind::Seal, content, []);
-        let seal: Event = self.sign_event_builder(seal).await?;
-
         // Compose gift wrap
-        let gift_wrap: Event = EventBuilder::gift_wrap_from_seal(&receiver, &seal, expiration)?;
+        let gift_wrap: Event = self
+            .gift_wrap_event(&signer, receiver, &rumor, expiration)
+            .await?;
 
         // Send event
         self.send_event(gift_wrap).await?;
@@ -1246,6 +1576,27 @@ impl Client {
         Ok(())
     }
 
+    /// Seal the rumor with the signer and gift wrap it
+    #[cfg(feature = "nip59")]
+    async fn gift_wrap_event(
+        &self,
+        signer: &Arc<DynNostrSigner>,
+        receiver: PublicKey,
+        rumor: &UnsignedEvent,
+        expiration: Option<Timestamp>,
+    ) -> Result<Event, Error> {
+        // Compose seal
+        let content: String = signer.nip44_encrypt(receiver, rumor.as_json()).await?;
+        let seal: EventBuilder =
+            EventBuilder::new(Kind::Seal, content, []).custom_created_at(Timestamp::tweaked());
+        let seal: Event = signer.sign_event_builder(seal).await?;
+
+        // Compose gift wrap
+        Ok(EventBuilder::gift_wrap_from_seal(
+            &receiver, &seal, expiration,
+        )?)
+    }
+
     /// Send GiftWrapper Sealed Direct message
     #[cfg(feature = "nip59")]
     pub async fn send_sealed_msg<S>(
@@ -1273,7 +1624,21 @@ impl Client {
         S: Into<String>,
     {
         let builder = EventBuilder::file_metadata(description, metadata);
-        self.send_event_builder(builder).await
+        Ok(self.send_event_builder(builder).await?.id())
+    }
+
+    /// Get all the events of filters, page by page
+    ///
+    /// Relays cap the number of events returned for a `REQ`, so every page move `until` back to the oldest event received,
+    /// until the relays return nothing new. The `limit` of filters is replaced by the page size.
+    ///
+    /// Events are saved into the database: use [`Client::database`] to query them.
+    pub async fn paginate(
+        &self,
+        filters: Vec<Filter>,
+        opts: PaginationOptions,
+    ) -> Result<(), Error> {
+        Ok(self.pool.paginate(filters, opts).await?)
     }
 
     /// Negentropy reconciliation
@@ -6,12 +6,14 @@
 
 #[cfg(not(target_arch = "wasm32"))]
 use std::net::SocketAddr;
-use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
 use nostr_relay_pool::relay::options::DEFAULT_SEND_TIMEOUT;
-use nostr_relay_pool::{RelayLimits, RelayPoolOptions, RelaySendOptions};
+use nostr_relay_pool::{ReconnectPolicy, RelayLimits, RelayPoolOptions, RelaySendOptions};
+
+use super::AuthenticationPolicy;
 
 /// Options
 #[derive(Debug, Clone)]
@@ -28,6 +30,14 @@ pub struct Options {
     ///
     /// If the relay made just 1 attempt, the relay will not be skipped
     skip_disconnected_relays: Arc<AtomicBool>,
+    /// Automatically authenticate to relays (NIP-42) (default: false)
+    automatic_authentication: Arc<AtomicBool>,
+    /// Allow or deny the automatic authentication with a relay (default: allow all)
+    pub(crate) authentication_policy: Option<Arc<dyn AuthenticationPolicy>>,
+    /// Gossip (NIP-65 outbox model) (default: false)
+    gossip: Arc<AtomicBool>,
+    /// Max number of relays added by gossip (default: 20)
+    max_gossip_relays: Arc<AtomicUsize>,
     /// Timeout (default: 60)
     ///
     /// Used in `get_events_of`, `req_events_of` and similar as default timeout.
@@ -43,6 +53,8 @@ pub struct Options {
     pub proxy: Option<SocketAddr>,
     /// Default limits for new added relays
     pub relay_limits: RelayLimits,
+    /// Default reconnection policy for new added relays
+    pub reconnect_policy: Option<ReconnectPolicy>,
     /// Pool Options
     pub pool: RelayPoolOptions,
 }
@@ -56,12 +68,17 @@ impl Default for Options {
             min_pow_difficulty: Arc::new(AtomicU8::new(0)),
             req_filters_chunk_size: Arc::new(AtomicU8::new(10)),
             skip_disconnected_relays: Arc::new(AtomicBool::new(true)),
+            automatic_authentication: Arc::new(AtomicBool::new(false)),
+            authentication_policy: None,
+            gossip: Arc::new(AtomicBool::new(false)),
+            max_gossip_relays: Arc::new(AtomicUsize::new(20)),
             timeout: Duration::from_secs(60),
             connection_timeout: None,
             send_timeout: Some(DEFAULT_SEND_TIMEOUT),
             #[cfg(not(target_arch = "wasm32"))]
             proxy: None,
             relay_limits: RelayLimits::default(),
+            reconnect_policy: None,
             pool: RelayPoolOptions::default(),
         }
     }
@@ -167,6 +184,60 @@ impl Options {
         self.skip_disconnected_relays.load(Ordering::SeqCst)
     }
 
+    /// Automatically authenticate to relays when an `AUTH` challenge is received (default: false)
+    ///
+    /// The authentication event is signed with the client signer.
+    /// `REQ` and `EVENT` messages rejected with `auth-required` are sent again after the authentication.
+    pub fn automatic_authentication(self, enable: bool) -> Self {
+        Self {
+            automatic_authentication: Arc::new(AtomicBool::new(enable)),
+            ..self
+        }
+    }
+
+    pub(crate) fn get_automatic_authentication(&self) -> bool {
+        self.automatic_authentication.load(Ordering::SeqCst)
+    }
+
+    /// Set authentication policy, to allow or deny the automatic authentication with a relay
+    pub fn authentication_policy<P>(mut self, policy: P) -> Self
+    where
+        P: AuthenticationPolicy + 'static,
+    {
+        self.authentication_policy = Some(Arc::new(policy));
+        self
+    }
+
+    /// Enable gossip (NIP-65 outbox model) (default: false)
+    ///
+    /// Requests for authors' events are sent to the authors' write relays and
+    /// events are delivered to the read relays of the tagged users.
+    /// The relay lists are discovered through the added relays and cached in the database.
+    pub fn gossip(self, enable: bool) -> Self {
+        Self {
+            gossip: Arc::new(AtomicBool::new(enable)),
+            ..self
+        }
+    }
+
+    pub(crate) fn get_gossip(&self) -> bool {
+        self.gossip.load(Ordering::SeqCst)
+    }
+
+    /// Max number of relays connected by gossip, in addition to the added ones (default: 20)
+    ///
+    /// The least recently used relays are disconnected when the limit is exceeded.
+    pub fn max_gossip_relays(self, max: usize) -> Self {
+        Self {
+            max_gossip_relays: Arc::new(AtomicUsize::new(max)),
+            ..self
+        }
+    }
+
+    pub(crate) fn get_max_gossip_relays(&self) -> usize {
+        self.max_gossip_relays.load(Ordering::SeqCst)
+    }
+
     /// Set default timeout
     pub fn timeout(self, timeout: Duration) -> Self {
         Self { timeout, ..self }
@@ -201,6 +272,12 @@ impl Options {
         self
     }
 
+    /// Set reconnection policy for new added relays
+    pub fn reconnect_policy(mut self, policy: ReconnectPolicy) -> Self {
+        self.reconnect_policy = Some(policy);
+        self
+    }
+
     /// Set pool options
     pub fn pool(self, opts: RelayPoolOptions) -> Self {
         Self { pool: opts, ..self }
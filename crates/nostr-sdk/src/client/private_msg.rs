@@ -0,0 +1,470 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Private Direct messages (NIP-17)
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+use std::time::Duration;
+
+use lru::LruCache;
+use nostr::nips::nip59;
+use nostr::prelude::*;
+use nostr_database::{DatabaseError, Order};
+use nostr_relay_pool::pool::Error as RelayPoolError;
+use nostr_relay_pool::{PublishReport, RelayOptions, RelayServiceFlags, SubscribeAutoCloseOptions};
+use nostr_signer::prelude::*;
+
+use super::{Client, Error};
+
+/// Max number of unwrapped gift wraps kept in memory
+const MAX_UNWRAPPED_GIFTS: usize = 10_000;
+
+/// Cache of the last unwrapped gift wraps
+pub(super) fn unwrapped_gifts_cache() -> LruCache<EventId, UnwrappedGift> {
+    match NonZeroUsize::new(MAX_UNWRAPPED_GIFTS) {
+        Some(size) => LruCache::new(size),
+        None => LruCache::unbounded(),
+    }
+}
+
+impl Client {
+    /// Publish the inbox relays, where to receive the Private Direct messages (NIP17)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/17.md>
+    pub async fn set_inbox_relays<I>(&self, relays: I) -> Result<PublishReport, Error>
+    where
+        I: IntoIterator<Item = Url>,
+    {
+        let builder = EventBuilder::inbox_relays(relays.into_iter().map(UncheckedUrl::from));
+        self.send_event_builder(builder).await
+    }
+
+    /// Get the inbox relays of a public key (NIP17)
+    ///
+    /// Invalid relay URLs are skipped.
+    /// If timeout is set to `None`, the default from [`Options`](super::Options) will be used.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/17.md>
+    pub async fn inbox_relays(
+        &self,
+        public_key: PublicKey,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<Url>, Error> {
+        let filter: Filter = Filter::new().author(public_key).kind(Kind::InboxRelays);
+        let events: Vec<Event> = self.get_events_of(vec![filter], timeout).await?;
+        Ok(events
+            .iter()
+            .max_by_key(|event| (event.created_at(), event.id()))
+            .map(|event| {
+                nip17::extract_inbox_relays(event)
+                    .filter_map(|url| Url::try_from(url.clone()).ok())
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    /// Send Private Direct message to a chat room (NIP17)
+    ///
+    /// The message is gift wrapped to every receiver and to the user, and sent to their inbox relays.
+    /// The inbox relays are added to the pool for writes and managed like the gossip relays.
+    /// If a receiver has no inbox relays, its gift wrap is not sent (never broadcast to the relays of the pool)
+    /// and [`Error::InboxRelaysNotFound`] is returned for it. Without inbox relays, the copy of the user
+    /// is sent to the relays of the pool.
+    ///
+    /// Return the [`PublishReport`] of every member of the room or, if the gift wrap can't be sent
+    /// to a member, the error (the other members are not affected).
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/17.md>
+    pub async fn send_private_msg<I, S>(
+        &self,
+        receivers: I,
+        message: S,
+        subject: Option<S>,
+    ) -> Result<HashMap<PublicKey, Result<PublishReport, Error>>, Error>
+    where
+        I: IntoIterator<Item = PublicKey>,
+        S: Into<String>,
+    {
+        let signer: Arc<DynNostrSigner> = self.signer().await?;
+        let public_key: PublicKey = signer.public_key().await?;
+
+        // Compose rumor
+        let receivers: Vec<PublicKey> = receivers
+            .into_iter()
+            .filter(|p| *p != public_key)
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        let rumor: UnsignedEvent = EventBuilder::private_msg_rumor(receivers, message, subject)
+            .to_unsigned_event(public_key);
+        let room: ChatRoom = ChatRoom::from_rumor(&rumor);
+
+        let mut reports: HashMap<PublicKey, Result<PublishReport, Error>> = HashMap::new();
+        for member in room.members().iter() {
+            let res = self
+                .send_private_msg_to(&signer, public_key, *member, &rumor)
+                .await;
+            reports.insert(*member, res);
+        }
+
+        Ok(reports)
+    }
+
+    /// Gift wrap the rumor to a member of the chat room and send it to its inbox relays
+    async fn send_private_msg_to(
+        &self,
+        signer: &Arc<DynNostrSigner>,
+        public_key: PublicKey,
+        member: PublicKey,
+        rumor: &UnsignedEvent,
+    ) -> Result<PublishReport, Error> {
+        let urls: Vec<Url> = self.inbox_relays(member, None).await?;
+        if urls.is_empty() && member != public_key {
+            return Err(Error::InboxRelaysNotFound(member));
+        }
+
+        let gift_wrap: Event = self.gift_wrap_event(signer, member, rumor, None).await?;
+
+        // Keep the copy of the user
+        if member == public_key {
+            let mut unwrapped_gifts = self.unwrapped_gifts.lock().await;
+            unwrapped_gifts.put(
+                gift_wrap.id(),
+                UnwrappedGift {
+                    sender: public_key,
+                    rumor: rumor.clone(),
+                },
+            );
+        }
+
+        let res = if urls.is_empty() {
+            // Copy of the user
+            self.send_event(gift_wrap).await
+        } else {
+            let opts: RelayOptions = self.relay_opts().read(false).inherit_subscriptions(false);
+            let set: HashSet<Url> = urls.iter().cloned().collect();
+            self.gossip
+                .prepare_relays(
+                    &self.pool,
+                    &set,
+                    opts,
+                    self.opts.connection_timeout,
+                    self.opts.get_max_gossip_relays(),
+                )
+                .await?;
+            self.gossip
+                .add_flags(&self.pool, &set, RelayServiceFlags::WRITE)
+                .await;
+            self.send_event_to(urls, gift_wrap).await
+        };
+
+        match res {
+            Ok(report) => Ok(report),
+            Err(Error::RelayPool(RelayPoolError::EventNotPublished(report))) => Ok(report),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Subscribe to the gift wraps sent to the user (NIP17)
+    ///
+    /// Subscribe to the inbox relays of the user or, if not set, to all relays.
+    /// The gift wraps can be unwrapped with [`Client::unwrap_gift_wrap`].
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/17.md>
+    pub async fn subscribe_private_msgs(
+        &self,
+        opts: Option<SubscribeAutoCloseOptions>,
+    ) -> Result<SubscriptionId, Error> {
+        let public_key: PublicKey = self.signer().await?.public_key().await?;
+
+        // The `created_at` of gift wraps is tweaked, so `since` can't be used
+        let filter: Filter = Filter::new().kind(Kind::GiftWrap).pubkey(public_key);
+
+        let urls: Vec<Url> = self.inbox_relays(public_key, None).await?;
+        if urls.is_empty() {
+            return Ok(self.subscribe(vec![filter], opts).await);
+        }
+
+        let opts_relay: RelayOptions = self.relay_opts().write(false);
+        let set: HashSet<Url> = urls.iter().cloned().collect();
+        self.gossip
+            .prepare_relays(
+                &self.pool,
+                &set,
+                opts_relay,
+                self.opts.connection_timeout,
+                self.opts.get_max_gossip_relays(),
+            )
+            .await?;
+        self.gossip
+            .add_flags(&self.pool, &set, RelayServiceFlags::READ)
+            .await;
+        self.subscribe_to(urls, vec![filter], opts).await
+    }
+
+    /// Unwrap Gift Wrap event (NIP59)
+    ///
+    /// Decrypt the `seal` and the `rumor` with the signer and verify that the author of the `seal`
+    /// matches the author of the `rumor`.
+    /// The last unwrapped rumors are kept in memory, to not decrypt them again.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/59.md>
+    pub async fn unwrap_gift_wrap(&self, gift_wrap: &Event) -> Result<UnwrappedGift, Error> {
+        if let Some(unwrapped) = self.unwrapped_gifts.lock().await.get(&gift_wrap.id()) {
+            return Ok(unwrapped.clone());
+        }
+
+        if gift_wrap.kind() != Kind::GiftWrap {
+            return Err(nip59::Error::NotGiftWrap.into());
+        }
+
+        let signer: Arc<DynNostrSigner> = self.signer().await?;
+
+        // Decrypt and verify seal
+        let seal: String = signer
+            .nip44_decrypt(gift_wrap.author(), gift_wrap.content().to_string())
+            .await?;
+        let seal: Event = Event::from_json(seal).map_err(nip59::Error::from)?;
+        seal.verify().map_err(nip59::Error::from)?;
+
+        // Decrypt rumor
+        let rumor: String = signer
+            .nip44_decrypt(seal.author(), seal.content().to_string())
+            .await?;
+        let rumor: UnsignedEvent = UnsignedEvent::from_json(rumor).map_err(nip59::Error::from)?;
+
+        // Check that the rumor has not been forged by someone else
+        if rumor.pubkey != seal.author() {
+            return Err(nip59::Error::SenderMismatch.into());
+        }
+
+        let unwrapped = UnwrappedGift {
+            sender: seal.author(),
+            rumor,
+        };
+
+        let mut unwrapped_gifts = self.unwrapped_gifts.lock().await;
+        unwrapped_gifts.put(gift_wrap.id(), unwrapped.clone());
+
+        Ok(unwrapped)
+    }
+
+    /// Get the Private Direct messages of a chat room, sorted by creation time (NIP17)
+    ///
+    /// Include the messages of the gift wraps stored in the **database** and, if the database
+    /// doesn't store events, the last messages sent and unwrapped by this client.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/17.md>
+    pub async fn private_msgs(&self, room: &ChatRoom) -> Result<Vec<UnsignedEvent>, Error> {
+        let mut msgs: Vec<UnsignedEvent> = self
+            .unwrap_stored_gift_wraps()
+            .await?
+            .into_iter()
+            .filter(|rumor| ChatRoom::from_rumor(rumor) == *room)
+            .collect();
+        msgs.sort_by_key(|rumor| (rumor.created_at, rumor.id));
+        Ok(msgs)
+    }
+
+    /// Get the chat rooms of the user, with the newest message (NIP17)
+    ///
+    /// Include the messages of the gift wraps stored in the **database** and, if the database
+    /// doesn't store events, the last messages sent and unwrapped by this client.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/17.md>
+    pub async fn chat_rooms(&self) -> Result<BTreeMap<ChatRoom, UnsignedEvent>, Error> {
+        let mut rooms: BTreeMap<ChatRoom, UnsignedEvent> = BTreeMap::new();
+        for rumor in self.unwrap_stored_gift_wraps().await?.into_iter() {
+            let room: ChatRoom = ChatRoom::from_rumor(&rumor);
+            match rooms.get(&room) {
+                Some(newest) if (newest.created_at, newest.id) >= (rumor.created_at, rumor.id) => {}
+                _ => {
+                    rooms.insert(room, rumor);
+                }
+            }
+        }
+        Ok(rooms)
+    }
+
+    /// Unwrap the gift wraps of the user stored in the database (if it stores events)
+    /// and return the deduplicated messages of the user, received or sent
+    async fn unwrap_stored_gift_wraps(&self) -> Result<Vec<UnsignedEvent>, Error> {
+        let public_key: PublicKey = self.signer().await?.public_key().await?;
+        let filter: Filter = Filter::new().kind(Kind::GiftWrap).pubkey(public_key);
+        let gift_wraps: Vec<Event> = match self.database().query(vec![filter], Order::Desc).await {
+            Ok(events) => events,
+            Err(DatabaseError::FeatureDisabled) => Vec::new(),
+            Err(e) => return Err(RelayPoolError::from(e).into()),
+        };
+
+        let mut rumors: Vec<UnsignedEvent> = Vec::with_capacity(gift_wraps.len());
+        for gift_wrap in gift_wraps.iter() {
+            match self.unwrap_gift_wrap(gift_wrap).await {
+                Ok(unwrapped) => rumors.push(unwrapped.rumor),
+                Err(e) => tracing::warn!("Impossible to unwrap {}: {e}", gift_wrap.id()),
+            }
+        }
+
+        // Messages not stored in the database
+        let unwrapped_gifts = self.unwrapped_gifts.lock().await;
+        rumors.extend(
+            unwrapped_gifts
+                .iter()
+                .map(|(.., unwrapped)| unwrapped.rumor.clone()),
+        );
+
+        let mut ids: HashSet<EventId> = HashSet::new();
+        Ok(rumors
+            .into_iter()
+            .filter(|rumor| rumor.kind == Kind::SealedDirect)
+            .filter(|rumor| ChatRoom::from_rumor(rumor).contains(&public_key))
+            .filter(|rumor| ids.insert(rumor.id))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr_database::{
+        DynNostrDatabase, IntoNostrDatabase, MemoryDatabase, MemoryDatabaseOptions,
+    };
+    use nostr_relay_pool::DuplexTransport;
+
+    use super::*;
+    use crate::{mock, ClientBuilder};
+
+    fn relay_url() -> Url {
+        Url::parse("ws://relay.local").unwrap()
+    }
+
+    fn inbox_url() -> Url {
+        Url::parse("ws://inbox.local").unwrap()
+    }
+
+    fn inbox_relays(keys: &Keys, url: &Url) -> Event {
+        EventBuilder::inbox_relays([UncheckedUrl::from(url.to_string())])
+            .to_event(keys)
+            .unwrap()
+    }
+
+    /// Gift wraps sent to the public key stored by the relay
+    fn stored_gift_wraps(relays: &mock::MockRelays, url: &Url, keys: &Keys) -> Vec<Event> {
+        let filter = Filter::new().kind(Kind::GiftWrap).pubkey(keys.public_key());
+        relays
+            .events(url)
+            .into_iter()
+            .filter(|e| filter.match_event(e))
+            .collect()
+    }
+
+    /// Client with custom database
+    async fn client_with(
+        keys: &Keys,
+        database: Arc<DynNostrDatabase>,
+        transport: &DuplexTransport,
+    ) -> Client {
+        let client = ClientBuilder::new().signer(keys).database(database).build();
+        let opts = RelayOptions::new().transport(transport.clone());
+        client.add_relay_with_opts(relay_url(), opts).await.unwrap();
+        client.connect().await;
+        client
+    }
+
+    #[tokio::test]
+    async fn test_send_private_msg() {
+        let alice = Keys::generate();
+        let bob = Keys::generate();
+        let (transport, relays) = mock::spawn(|_, _| true);
+        relays.insert(&relay_url(), inbox_relays(&bob, &inbox_url()));
+
+        // The inbox relay is already in the pool, to use the mock transport
+        let client = mock::client(&alice, &transport, [&relay_url(), &inbox_url()]).await;
+        let reports = client
+            .send_private_msg([bob.public_key()], "Hello", None)
+            .await
+            .unwrap();
+        assert_eq!(reports.len(), 2);
+        assert!(reports
+            .values()
+            .all(|report| report.as_ref().unwrap().is_success()));
+
+        // Gift wrap sent to the inbox relay of bob and copy of alice sent to the pool relays
+        let gift_wraps: Vec<Event> = stored_gift_wraps(&relays, &inbox_url(), &bob);
+        assert_eq!(gift_wraps.len(), 1);
+        assert_eq!(stored_gift_wraps(&relays, &relay_url(), &alice).len(), 1);
+
+        let bob_client = mock::client(&bob, &transport, [&inbox_url()]).await;
+        let unwrapped = bob_client.unwrap_gift_wrap(&gift_wraps[0]).await.unwrap();
+        assert_eq!(unwrapped.sender, alice.public_key());
+        assert_eq!(unwrapped.rumor.content, "Hello");
+
+        let rooms = client.chat_rooms().await.unwrap();
+        assert_eq!(rooms.len(), 1);
+        let (room, newest) = rooms.into_iter().next().unwrap();
+        assert_eq!(newest.content, "Hello");
+        assert_eq!(client.private_msgs(&room).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_send_private_msg_errors() {
+        let alice = Keys::generate();
+        let bob = Keys::generate();
+        let carol = Keys::generate();
+
+        // No relays: every gift wrap fails, without aborting the others
+        let client = Client::new(&alice);
+        let reports = client
+            .send_private_msg([bob.public_key(), carol.public_key()], "Hello", None)
+            .await
+            .unwrap();
+        assert_eq!(reports.len(), 3);
+        assert!(reports.values().all(|report| report.is_err()));
+
+        // Carol has no inbox relays: her gift wrap is not broadcast to the pool relays
+        let (transport, relays) = mock::spawn(|_, _| true);
+        relays.insert(&relay_url(), inbox_relays(&bob, &inbox_url()));
+        let client = mock::client(&alice, &transport, [&relay_url(), &inbox_url()]).await;
+        let reports = client
+            .send_private_msg([bob.public_key(), carol.public_key()], "Hello", None)
+            .await
+            .unwrap();
+        assert!(reports[&bob.public_key()].as_ref().unwrap().is_success());
+        assert!(matches!(
+            reports[&carol.public_key()],
+            Err(Error::InboxRelaysNotFound(public_key)) if public_key == carol.public_key()
+        ));
+        assert!(stored_gift_wraps(&relays, &relay_url(), &carol).is_empty());
+        assert!(stored_gift_wraps(&relays, &inbox_url(), &carol).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_private_msgs_from_database() {
+        let alice = Keys::generate();
+        let bob = Keys::generate();
+        let (transport, _) = mock::spawn(|_, _| true);
+
+        let database = MemoryDatabase::with_opts(MemoryDatabaseOptions {
+            events: true,
+            ..Default::default()
+        })
+        .into_nostr_database();
+        let client = client_with(&alice, database.clone(), &transport).await;
+        client
+            .send_private_msg([bob.public_key()], "Hello", None)
+            .await
+            .unwrap();
+
+        // The messages are unwrapped again from the database after a restart
+        drop(client);
+        let client = client_with(&alice, database, &transport).await;
+        let rooms = client.chat_rooms().await.unwrap();
+        assert_eq!(rooms.len(), 1);
+        let room = rooms.keys().next().unwrap();
+        let msgs = client.private_msgs(room).await.unwrap();
+        assert_eq!(msgs.len(), 1);
+        assert_eq!(msgs[0].content, "Hello");
+    }
+}
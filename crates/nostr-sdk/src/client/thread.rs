@@ -0,0 +1,267 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Threads (NIP-10)
+
+use std::collections::HashSet;
+use std::time::Duration;
+
+use nostr::prelude::*;
+use nostr_relay_pool::{FilterOptions, RelayOptions};
+
+use super::{Client, Error};
+
+/// Max number of ancestors fetched while walking up a thread
+const MAX_THREAD_DEPTH: usize = 100;
+
+impl Client {
+    /// Get the thread of an event (NIP-10)
+    ///
+    /// Walk up the ancestors of the event until the root, then get all the replies of the root.
+    /// Events are searched in the **local database** first, then in the **relays**.
+    /// The ancestors not found are fetched from the relays of the `e` tag hints,
+    /// added to the pool for reads only and managed like the gossip relays.
+    ///
+    /// If timeout is set to `None`, the default from [`Options`](super::Options) will be used.
+    pub async fn get_thread(
+        &self,
+        id: EventId,
+        timeout: Option<Duration>,
+    ) -> Result<ThreadTree, Error> {
+        let event: Event = self
+            .get_thread_event(
+                &ThreadReference {
+                    event_id: id,
+                    relay_url: None,
+                },
+                timeout,
+            )
+            .await?;
+
+        // Walk up the ancestors
+        let relations: ThreadRelations = ThreadRelations::from_event(&event);
+        let mut visited: HashSet<EventId> = HashSet::from([event.id()]);
+        let mut ancestors: Vec<Event> = Vec::new();
+        let mut current: Event = event;
+        let root: Event = loop {
+            let parent: ThreadReference = match ThreadRelations::from_event(&current).parent() {
+                Some(parent) if ancestors.len() < MAX_THREAD_DEPTH => parent.clone(),
+                _ => break current,
+            };
+
+            // Loop
+            if !visited.insert(parent.event_id) {
+                break current;
+            }
+
+            match self.get_thread_event(&parent, timeout).await {
+                Ok(parent) => {
+                    ancestors.push(current);
+                    current = parent;
+                }
+                Err(e) => {
+                    ancestors.push(current);
+
+                    // Missing ancestor: get the root directly
+                    match relations.root {
+                        Some(root) if !visited.contains(&root.event_id) => {
+                            break self.get_thread_event(&root, timeout).await?
+                        }
+                        _ => return Err(e),
+                    }
+                }
+            }
+        };
+
+        // Get replies
+        let filter: Filter = Filter::new().kind(Kind::TextNote).event(root.id());
+        let replies: Vec<Event> = self.get_events_of(vec![filter], timeout).await?;
+
+        Ok(ThreadTree::new(root, ancestors.into_iter().chain(replies)))
+    }
+
+    /// Get an event of a thread from database, relays or, if missing, from the relay hint
+    async fn get_thread_event(
+        &self,
+        reference: &ThreadReference,
+        timeout: Option<Duration>,
+    ) -> Result<Event, Error> {
+        let id: EventId = reference.event_id;
+
+        if let Ok(event) = self.database().event_by_id(id).await {
+            return Ok(event);
+        }
+
+        let filter: Filter = Filter::new().id(id);
+        let events: Vec<Event> = self.get_events_of(vec![filter.clone()], timeout).await?;
+        if let Some(event) = events.into_iter().next() {
+            return Ok(event);
+        }
+
+        // Try with relay hint
+        if let Some(url) = reference
+            .relay_url
+            .clone()
+            .and_then(|url| Url::try_from(url).ok())
+        {
+            let opts: RelayOptions = self.relay_opts().write(false).inherit_subscriptions(false);
+            self.gossip
+                .prepare_relays(
+                    &self.pool,
+                    &HashSet::from([url.clone()]),
+                    opts,
+                    self.opts.connection_timeout,
+                    self.opts.get_max_gossip_relays(),
+                )
+                .await?;
+
+            let timeout: Duration = timeout.unwrap_or(self.opts.timeout);
+            match self
+                .pool
+                .get_events_from([&url], vec![filter], timeout, FilterOptions::ExitOnEOSE)
+                .await
+            {
+                Ok(events) => {
+                    if let Some(event) = events.into_iter().next() {
+                        return Ok(event);
+                    }
+                }
+                Err(e) => tracing::error!("Failed to get event {id} from {url}: {e}"),
+            }
+        }
+
+        Err(Error::EventNotFound(id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr_relay_pool::RelayStatus;
+
+    use super::*;
+    use crate::{mock, ClientBuilder, Options};
+
+    fn relay_url() -> Url {
+        Url::parse("ws://relay.local").unwrap()
+    }
+
+    fn hint_url() -> Url {
+        Url::parse("ws://hint.local").unwrap()
+    }
+
+    fn reply(
+        keys: &Keys,
+        content: &str,
+        reply_to: &Event,
+        root: &Event,
+        hint: Option<&Url>,
+    ) -> Event {
+        let hint: Option<UncheckedUrl> = hint.map(|url| UncheckedUrl::from(url.to_string()));
+        EventBuilder::text_note_reply(content, reply_to, Some(root), hint)
+            .to_event(keys)
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_get_thread_relay_hint() {
+        let keys = Keys::generate();
+        let (transport, relays) = mock::spawn(|_, _| true);
+        let root = EventBuilder::text_note("Root", []).to_event(&keys).unwrap();
+        let a = reply(&keys, "A", &root, &root, None);
+        let b = reply(&keys, "B", &a, &root, Some(&hint_url()));
+        relays.insert(&relay_url(), root.clone());
+        relays.insert(&relay_url(), b.clone());
+        relays.insert(&hint_url(), a.clone());
+
+        // The hint relay is a disconnected gossip relay, to use the mock transport:
+        // the queries not targeted go only to the default relays, so only the relay hint fallback connects it again
+        let client = ClientBuilder::new()
+            .signer(&keys)
+            .opts(Options::new().gossip(true))
+            .build();
+        let opts = RelayOptions::new().transport(transport.clone());
+        client.add_relay_with_opts(relay_url(), opts).await.unwrap();
+        client.pool().connect(Some(Duration::from_secs(1))).await;
+        let opts = RelayOptions::new().transport(transport).write(false);
+        client
+            .gossip
+            .prepare_relays(
+                &client.pool,
+                &HashSet::from([hint_url()]),
+                opts,
+                Some(Duration::from_secs(1)),
+                10,
+            )
+            .await
+            .unwrap();
+        client.disconnect_relay(hint_url()).await.unwrap();
+
+        let tree = client
+            .get_thread(b.id(), Some(Duration::from_secs(1)))
+            .await
+            .unwrap();
+        assert_eq!(tree.root.event.id(), root.id());
+        assert_eq!(tree.count(), 3);
+        assert!(tree.orphans.is_empty());
+        assert_eq!(tree.find(&a.id()).unwrap().replies[0].event.id(), b.id());
+
+        let hint = client.relay(hint_url()).await.unwrap();
+        assert_eq!(hint.status().await, RelayStatus::Connected);
+    }
+
+    #[tokio::test]
+    async fn test_get_thread_missing_ancestor() {
+        let keys = Keys::generate();
+        let (transport, relays) = mock::spawn(|_, _| true);
+        let root = EventBuilder::text_note("Root", []).to_event(&keys).unwrap();
+        let a = reply(&keys, "A", &root, &root, None);
+        let b = reply(&keys, "B", &a, &root, None);
+        relays.insert(&relay_url(), root.clone());
+        relays.insert(&relay_url(), b.clone());
+
+        // A not found anywhere: get the root directly
+        let client = mock::client(&keys, &transport, [&relay_url()]).await;
+        let tree = client
+            .get_thread(b.id(), Some(Duration::from_secs(1)))
+            .await
+            .unwrap();
+        assert_eq!(tree.root.event.id(), root.id());
+        assert_eq!(tree.orphans.len(), 1);
+        assert_eq!(tree.orphans[0].event.id(), b.id());
+
+        // Neither the parent nor the root found
+        let c = reply(&keys, "C", &a, &a, None);
+        relays.insert(&relay_url(), c.clone());
+        let res = client
+            .get_thread(c.id(), Some(Duration::from_secs(1)))
+            .await;
+        assert!(matches!(res, Err(Error::EventNotFound(id)) if id == a.id()));
+    }
+
+    #[tokio::test]
+    async fn test_get_thread_max_depth() {
+        let keys = Keys::generate();
+        let (transport, relays) = mock::spawn(|_, _| true);
+        let root = EventBuilder::text_note("Root", []).to_event(&keys).unwrap();
+        let mut chain: Vec<Event> = vec![root.clone()];
+        for i in 0..MAX_THREAD_DEPTH + 2 {
+            let event = reply(&keys, &i.to_string(), chain.last().unwrap(), &root, None);
+            chain.push(event);
+        }
+        for event in chain.iter() {
+            relays.insert(&relay_url(), event.clone());
+        }
+
+        // Stop walking up after the max depth
+        let client = mock::client(&keys, &transport, [&relay_url()]).await;
+        let last: &Event = chain.last().unwrap();
+        let tree = client
+            .get_thread(last.id(), Some(Duration::from_secs(1)))
+            .await
+            .unwrap();
+        let top: &Event = &chain[chain.len() - 1 - MAX_THREAD_DEPTH];
+        assert_eq!(tree.root.event.id(), top.id());
+        assert_eq!(tree.count(), MAX_THREAD_DEPTH + 1);
+    }
+}
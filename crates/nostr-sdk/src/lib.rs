@@ -24,13 +24,15 @@ pub use nostr_database::{self as database, NostrDatabase, NostrDatabaseExt, Prof
 #[cfg(all(target_arch = "wasm32", feature = "indexeddb"))]
 pub use nostr_indexeddb::{IndexedDBError, WebDatabase};
 pub use nostr_relay_pool::{
-    self as pool, AtomicRelayServiceFlags, FilterOptions, NegentropyDirection, NegentropyOptions,
-    Relay, RelayConnectionStats, RelayOptions, RelayPool, RelayPoolNotification, RelayPoolOptions,
-    RelaySendOptions, RelayServiceFlags, RelayStatus, SubscribeAutoCloseOptions, SubscribeOptions,
+    self as pool, AtomicRelayServiceFlags, EventStream, FilterOptions, NegentropyDirection,
+    NegentropyOptions, PublishOutcome, PublishReport, RateLimit, ReconnectPolicy, Relay,
+    RelayConnectionStats, RelayOptions, RelayPool, RelayPoolNotification, RelayPoolOptions,
+    RelayScore, RelaySendOptions, RelayServiceFlags, RelayStatus, SubscribeAutoCloseOptions,
+    SubscribeOptions, SubscriptionItem, SubscriptionStream,
 };
 #[cfg(feature = "rocksdb")]
 pub use nostr_rocksdb::RocksDatabase;
-pub use nostr_signer::{self as signer, NostrSigner, NostrSignerType};
+pub use nostr_signer::{self as signer, NostrSigner, SignerBackend, SignerError};
 #[cfg(feature = "sqlite")]
 pub use nostr_sqlite::{Error as SQLiteError, SQLiteDatabase};
 #[cfg(all(target_arch = "wasm32", feature = "webln"))]
@@ -48,9 +50,11 @@ use tokio::runtime::Runtime;
 pub use tokio::task::spawn_blocking;
 
 pub mod client;
+#[cfg(test)]
+mod mock;
 pub mod prelude;
 
-pub use self::client::{Client, ClientBuilder, Options};
+pub use self::client::{AuthenticationPolicy, Client, ClientBuilder, Options};
 
 #[cfg(feature = "blocking")]
 static RUNTIME: Lazy<Runtime> = Lazy::new(|| Runtime::new().expect("Can't start Tokio runtime"));
@@ -0,0 +1,103 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Mock relays for tests
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use nostr::{ClientMessage, Event, Keys, RelayMessage, Url};
+use nostr_relay_pool::{DuplexTransport, RelayOptions};
+
+use crate::Client;
+
+/// Events stored by the mock relays, by relay URL
+#[derive(Debug, Clone, Default)]
+pub(crate) struct MockRelays {
+    events: Arc<Mutex<HashMap<Url, Vec<Event>>>>,
+}
+
+impl MockRelays {
+    /// Store an event in the relay
+    pub fn insert(&self, url: &Url, event: Event) {
+        let mut events = self.events.lock().unwrap();
+        events.entry(url.clone()).or_default().push(event);
+    }
+
+    /// Events stored by the relay
+    pub fn events(&self, url: &Url) -> Vec<Event> {
+        let events = self.events.lock().unwrap();
+        events.get(url).cloned().unwrap_or_default()
+    }
+}
+
+/// Spawn the mock relays, all using the same transport
+///
+/// The `EVENT`s are stored if `accept` returns `true` and the `REQ`s are answered
+/// with the stored events matching the filters, followed by `EOSE`.
+pub(crate) fn spawn<F>(accept: F) -> (DuplexTransport, MockRelays)
+where
+    F: Fn(&Url, &Event) -> bool + Send + Sync + 'static,
+{
+    let relays = MockRelays::default();
+    let (transport, mut listener) = DuplexTransport::new();
+    let accept = Arc::new(accept);
+    let store = relays.clone();
+    tokio::spawn(async move {
+        while let Some(mut connection) = listener.accept().await {
+            let accept = accept.clone();
+            let store = store.clone();
+            tokio::spawn(async move {
+                while let Some(msg) = connection.recv().await {
+                    let url: Url = connection.url().clone();
+                    let msgs: Vec<RelayMessage> = match msg {
+                        ClientMessage::Event(event) => {
+                            if accept(&url, &event) {
+                                store.insert(&url, *event.clone());
+                                vec![RelayMessage::ok(event.id(), true, "")]
+                            } else {
+                                vec![RelayMessage::ok(event.id(), false, "blocked: not allowed")]
+                            }
+                        }
+                        ClientMessage::Req {
+                            subscription_id,
+                            filters,
+                        } => {
+                            let mut msgs: Vec<RelayMessage> = store
+                                .events(&url)
+                                .into_iter()
+                                .filter(|e| filters.iter().any(|f| f.match_event(e)))
+                                .map(|e| RelayMessage::event(subscription_id.clone(), e))
+                                .collect();
+                            msgs.push(RelayMessage::eose(subscription_id));
+                            msgs
+                        }
+                        _ => Vec::new(),
+                    };
+                    for msg in msgs.into_iter() {
+                        if connection.send(msg).is_err() {
+                            return;
+                        }
+                    }
+                }
+            });
+        }
+    });
+    (transport, relays)
+}
+
+/// Client connected to the relays, all using the same transport
+pub(crate) async fn client<'a, I>(keys: &Keys, transport: &DuplexTransport, urls: I) -> Client
+where
+    I: IntoIterator<Item = &'a Url>,
+{
+    let client = Client::new(keys);
+    for url in urls.into_iter() {
+        let opts = RelayOptions::new().transport(transport.clone());
+        client.add_relay_with_opts(url, opts).await.unwrap();
+    }
+    client.pool().connect(Some(Duration::from_secs(1))).await;
+    client
+}
@@ -0,0 +1,92 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Keys signer
+
+use nostr::prelude::*;
+
+use crate::{NostrSigner, SignerBackend, SignerError};
+
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+impl NostrSigner for Keys {
+    type Err = SignerError;
+
+    fn backend(&self) -> SignerBackend {
+        SignerBackend::Keys
+    }
+
+    async fn public_key(&self) -> Result<PublicKey, Self::Err> {
+        Ok(Keys::public_key(self))
+    }
+
+    async fn sign_event(&self, unsigned: UnsignedEvent) -> Result<Event, Self::Err> {
+        unsigned.sign(self).map_err(SignerError::nostr)
+    }
+
+    #[allow(unused_variables)]
+    async fn nip04_encrypt(
+        &self,
+        public_key: PublicKey,
+        content: String,
+    ) -> Result<String, Self::Err> {
+        #[cfg(feature = "nip04")]
+        {
+            let secret_key: &SecretKey = self.secret_key().map_err(SignerError::nostr)?;
+            nip04::encrypt(secret_key, &public_key, content).map_err(SignerError::nostr)
+        }
+
+        #[cfg(not(feature = "nip04"))]
+        Err(SignerError::FeatureDisabled)
+    }
+
+    #[allow(unused_variables)]
+    async fn nip04_decrypt(
+        &self,
+        public_key: PublicKey,
+        encrypted_content: String,
+    ) -> Result<String, Self::Err> {
+        #[cfg(feature = "nip04")]
+        {
+            let secret_key: &SecretKey = self.secret_key().map_err(SignerError::nostr)?;
+            nip04::decrypt(secret_key, &public_key, encrypted_content).map_err(SignerError::nostr)
+        }
+
+        #[cfg(not(feature = "nip04"))]
+        Err(SignerError::FeatureDisabled)
+    }
+
+    #[allow(unused_variables)]
+    async fn nip44_encrypt(
+        &self,
+        public_key: PublicKey,
+        content: String,
+    ) -> Result<String, Self::Err> {
+        #[cfg(feature = "nip44")]
+        {
+            let secret_key: &SecretKey = self.secret_key().map_err(SignerError::nostr)?;
+            nip44::encrypt(secret_key, &public_key, content, nip44::Version::default())
+                .map_err(SignerError::nostr)
+        }
+
+        #[cfg(not(feature = "nip44"))]
+        Err(SignerError::FeatureDisabled)
+    }
+
+    #[allow(unused_variables)]
+    async fn nip44_decrypt(
+        &self,
+        public_key: PublicKey,
+        payload: String,
+    ) -> Result<String, Self::Err> {
+        #[cfg(feature = "nip44")]
+        {
+            let secret_key: &SecretKey = self.secret_key().map_err(SignerError::nostr)?;
+            nip44::decrypt(secret_key, &public_key, payload).map_err(SignerError::nostr)
+        }
+
+        #[cfg(not(feature = "nip44"))]
+        Err(SignerError::FeatureDisabled)
+    }
+}
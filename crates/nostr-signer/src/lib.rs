@@ -4,297 +4,255 @@
 
 //! Nostr Signer
 
-#![forbid(unsafe_code)]
 #![warn(missing_docs)]
 #![warn(rustdoc::bare_urls)]
 
-use std::fmt;
+use core::fmt;
+use std::sync::Arc;
 
-use nostr::key;
+pub extern crate nostr;
+
+pub use async_trait::async_trait;
 use nostr::prelude::*;
-use thiserror::Error;
 
+pub mod error;
+mod keys;
+#[cfg(all(feature = "nip07", target_arch = "wasm32"))]
+mod nip07;
 #[cfg(feature = "nip46")]
 pub mod nip46;
 pub mod prelude;
 
+pub use self::error::SignerError;
 #[cfg(feature = "nip46")]
 pub use self::nip46::Nip46Signer;
 
-/// Nostr Signer error
-#[derive(Debug, Error)]
-pub enum Error {
-    /// Keys error
-    #[error(transparent)]
-    Keys(#[from] key::Error),
-    /// Unsigned event error
-    #[error(transparent)]
-    Unsigned(#[from] unsigned::Error),
-    /// NIP04 error
-    #[cfg(feature = "nip04")]
-    #[error(transparent)]
-    NIP04(#[from] nip04::Error),
-    /// NIP07 error
-    #[cfg(all(feature = "nip07", target_arch = "wasm32"))]
-    #[error(transparent)]
-    NIP07(#[from] nip07::Error),
-    /// NIP44 error
-    #[cfg(feature = "nip44")]
-    #[error(transparent)]
-    NIP44(#[from] nip44::Error),
-    /// NIP46 error
-    #[cfg(feature = "nip46")]
-    #[error(transparent)]
-    NIP46(#[from] nip46::Error),
-    /// Response not match to the request
-    #[cfg(feature = "nip46")]
-    #[error("response not match to the request")]
-    ResponseNotMatchRequest,
-    /// Not supported yet
-    #[error("{0}")]
-    Unsupported(String),
-}
-
-#[cfg(feature = "nip44")]
-impl Error {
-    fn unsupported<S>(message: S) -> Self
-    where
-        S: Into<String>,
-    {
-        Self::Unsupported(message.into())
-    }
-}
-
-/// Nostr Signer Type
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub enum NostrSignerType {
+/// Backend
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SignerBackend {
     /// Keys
     Keys,
-    /// NIP07
-    #[cfg(all(feature = "nip07", target_arch = "wasm32"))]
-    NIP07,
-    /// NIP46
-    #[cfg(feature = "nip46")]
-    NIP46,
+    /// Nostr Browser Extension (NIP07)
+    BrowserExtension,
+    /// Nostr Connect (NIP46)
+    NostrConnect,
+    /// Custom
+    Custom(String),
 }
 
-// TODO: better display
-impl fmt::Display for NostrSignerType {
+impl fmt::Display for SignerBackend {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::Keys => write!(f, "Keys"),
-            #[cfg(all(feature = "nip07", target_arch = "wasm32"))]
-            Self::NIP07 => write!(f, "Nostr Browser Extension"),
-            #[cfg(feature = "nip46")]
-            Self::NIP46 => write!(f, "Nostr Connect"),
+            Self::BrowserExtension => write!(f, "Nostr Browser Extension"),
+            Self::NostrConnect => write!(f, "Nostr Connect"),
+            Self::Custom(name) => write!(f, "{name}"),
         }
     }
 }
 
-/// Nostr signer
-#[derive(Debug, Clone)]
-pub enum NostrSigner {
-    /// Private Keys
-    Keys(Keys),
-    /// NIP07 signer
-    #[cfg(all(feature = "nip07", target_arch = "wasm32"))]
-    NIP07(Nip07Signer),
-    /// NIP46 signer
-    #[cfg(feature = "nip46")]
-    NIP46(Box<Nip46Signer>),
+/// A type-erased [`NostrSigner`].
+pub type DynNostrSigner = dyn NostrSigner<Err = SignerError>;
+
+/// A type that can be type-erased into `Arc<dyn NostrSigner>`.
+pub trait IntoNostrSigner {
+    #[doc(hidden)]
+    fn into_nostr_signer(self) -> Arc<DynNostrSigner>;
 }
 
-impl NostrSigner {
-    /// Create a new [NIP07] instance and compose [NostrSigner]
-    #[cfg(all(feature = "nip07", target_arch = "wasm32"))]
-    pub fn nip07() -> Result<Self, Error> {
-        let instance = Nip07Signer::new()?;
-        Ok(Self::NIP07(instance))
+impl IntoNostrSigner for Arc<DynNostrSigner> {
+    fn into_nostr_signer(self) -> Arc<DynNostrSigner> {
+        self
     }
+}
 
-    /// Compose [NostrSigner] with [Nip46Signer]
-    #[cfg(feature = "nip46")]
-    pub fn nip46(signer: Nip46Signer) -> Self {
-        Self::NIP46(Box::new(signer))
+impl<T> IntoNostrSigner for T
+where
+    T: NostrSigner + Sized + 'static,
+{
+    fn into_nostr_signer(self) -> Arc<DynNostrSigner> {
+        Arc::new(EraseNostrSignerError(self))
     }
+}
 
-    /// Get Nostr Signer Type
-    pub fn r#type(&self) -> NostrSignerType {
-        match self {
-            Self::Keys(..) => NostrSignerType::Keys,
-            #[cfg(all(feature = "nip07", target_arch = "wasm32"))]
-            Self::NIP07(..) => NostrSignerType::NIP07,
-            #[cfg(feature = "nip46")]
-            Self::NIP46(..) => NostrSignerType::NIP46,
-        }
+impl IntoNostrSigner for &Keys {
+    fn into_nostr_signer(self) -> Arc<DynNostrSigner> {
+        self.clone().into_nostr_signer()
     }
+}
 
-    /// Get signer public key
-    pub async fn public_key(&self) -> Result<PublicKey, Error> {
-        match self {
-            Self::Keys(keys) => Ok(keys.public_key()),
-            #[cfg(all(feature = "nip07", target_arch = "wasm32"))]
-            Self::NIP07(s) => Ok(s.get_public_key().await?),
-            #[cfg(feature = "nip46")]
-            Self::NIP46(s) => Ok(s.signer_public_key().await?),
-        }
+// Turns a given `Arc<T>` into `Arc<DynNostrSigner>` by attaching the
+// NostrSigner impl vtable of `EraseNostrSignerError<T>`.
+impl<T> IntoNostrSigner for Arc<T>
+where
+    T: NostrSigner + 'static,
+{
+    fn into_nostr_signer(self) -> Arc<DynNostrSigner> {
+        let ptr: *const T = Arc::into_raw(self);
+        let ptr_erased = ptr as *const EraseNostrSignerError<T>;
+        // SAFETY: EraseNostrSignerError is repr(transparent) so T and
+        //         EraseNostrSignerError<T> have the same layout and ABI
+        unsafe { Arc::from_raw(ptr_erased) }
     }
+}
+
+/// Nostr Signer
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait NostrSigner: AsyncTraitDeps {
+    /// Error
+    type Err: From<SignerError> + Into<SignerError>;
+
+    /// Name of the signer backend (ex. Keys, Nostr Connect, ...)
+    fn backend(&self) -> SignerBackend;
+
+    /// Get signer public key
+    async fn public_key(&self) -> Result<PublicKey, Self::Err>;
+
+    /// Sign an [UnsignedEvent]
+    async fn sign_event(&self, unsigned: UnsignedEvent) -> Result<Event, Self::Err>;
 
     /// Sign an [EventBuilder]
-    pub async fn sign_event_builder(&self, builder: EventBuilder) -> Result<Event, Error> {
+    async fn sign_event_builder(&self, builder: EventBuilder) -> Result<Event, Self::Err> {
         let public_key: PublicKey = self.public_key().await?;
         let unsigned: UnsignedEvent = builder.to_unsigned_event(public_key);
         self.sign_event(unsigned).await
     }
 
-    /// Sign an [UnsignedEvent]
-    pub async fn sign_event(&self, unsigned: UnsignedEvent) -> Result<Event, Error> {
-        match self {
-            Self::Keys(keys) => Ok(unsigned.sign(keys)?),
-            #[cfg(all(feature = "nip07", target_arch = "wasm32"))]
-            Self::NIP07(nip07) => Ok(nip07.sign_event(unsigned).await?),
-            #[cfg(feature = "nip46")]
-            Self::NIP46(nip46) => {
-                let res = nip46
-                    .send_req_to_signer(nostr::nips::nip46::Request::SignEvent(unsigned), None)
-                    .await?;
-                if let nostr::nips::nip46::Response::SignEvent(event) = res {
-                    Ok(event)
-                } else {
-                    Err(Error::ResponseNotMatchRequest)
-                }
-            }
-        }
-    }
-
     /// NIP04 encrypt
-    #[cfg(feature = "nip04")]
-    pub async fn nip04_encrypt<T>(&self, public_key: PublicKey, content: T) -> Result<String, Error>
-    where
-        T: AsRef<[u8]>,
-    {
-        let content: &[u8] = content.as_ref();
-        match self {
-            Self::Keys(keys) => Ok(nip04::encrypt(keys.secret_key()?, &public_key, content)?),
-            #[cfg(all(feature = "nip07", target_arch = "wasm32"))]
-            Self::NIP07(signer) => Ok(signer.nip04_encrypt(public_key, content).await?),
-            #[cfg(feature = "nip46")]
-            Self::NIP46(signer) => {
-                let req = nostr::nips::nip46::Request::Nip04Encrypt {
-                    public_key,
-                    text: String::from_utf8_lossy(content).to_string(),
-                };
-                let res: nostr::nips::nip46::Response =
-                    signer.send_req_to_signer(req, None).await?;
-                if let nostr::nips::nip46::Response::Nip04Encrypt(ciphertext) = res {
-                    Ok(ciphertext)
-                } else {
-                    Err(Error::ResponseNotMatchRequest)
-                }
-            }
-        }
-    }
+    async fn nip04_encrypt(
+        &self,
+        public_key: PublicKey,
+        content: String,
+    ) -> Result<String, Self::Err>;
 
     /// NIP04 decrypt
-    #[cfg(feature = "nip04")]
-    pub async fn nip04_decrypt<T>(
+    async fn nip04_decrypt(
         &self,
         public_key: PublicKey,
-        encrypted_content: T,
-    ) -> Result<String, Error>
-    where
-        T: AsRef<str>,
-    {
-        let encrypted_content: &str = encrypted_content.as_ref();
-        match self {
-            Self::Keys(keys) => Ok(nip04::decrypt(
-                keys.secret_key()?,
-                &public_key,
-                encrypted_content,
-            )?),
-            #[cfg(all(feature = "nip07", target_arch = "wasm32"))]
-            Self::NIP07(signer) => Ok(signer.nip04_decrypt(public_key, encrypted_content).await?),
-            #[cfg(feature = "nip46")]
-            Self::NIP46(signer) => {
-                let req = nostr::nips::nip46::Request::Nip04Decrypt {
-                    public_key,
-                    text: encrypted_content.to_string(),
-                };
-                let res: nostr::nips::nip46::Response =
-                    signer.send_req_to_signer(req, None).await?;
-                if let nostr::nips::nip46::Response::Nip04Decrypt(content) = res {
-                    Ok(content)
-                } else {
-                    Err(Error::ResponseNotMatchRequest)
-                }
-            }
-        }
+        encrypted_content: String,
+    ) -> Result<String, Self::Err>;
+
+    /// NIP44 encrypt
+    async fn nip44_encrypt(
+        &self,
+        public_key: PublicKey,
+        content: String,
+    ) -> Result<String, Self::Err>;
+
+    /// NIP44 decrypt
+    async fn nip44_decrypt(
+        &self,
+        public_key: PublicKey,
+        payload: String,
+    ) -> Result<String, Self::Err>;
+}
+
+#[repr(transparent)]
+struct EraseNostrSignerError<T>(T);
+
+impl<T: fmt::Debug> fmt::Debug for EraseNostrSignerError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
     }
+}
 
-    /// NIP44 encryption with [NostrSigner]
-    #[cfg(feature = "nip44")]
-    pub async fn nip44_encrypt<T>(&self, public_key: PublicKey, content: T) -> Result<String, Error>
-    where
-        T: AsRef<[u8]>,
-    {
-        let content: &[u8] = content.as_ref();
-        match self {
-            Self::Keys(keys) => Ok(nip44::encrypt(
-                keys.secret_key()?,
-                &public_key,
-                content,
-                nip44::Version::default(),
-            )?),
-            #[cfg(all(feature = "nip07", target_arch = "wasm32"))]
-            Self::NIP07(signer) => Ok(signer.nip44_encrypt(public_key, content).await?),
-            #[cfg(feature = "nip46")]
-            Self::NIP46(..) => Err(Error::unsupported(
-                "NIP44 encryption not supported with NIP46 signer yet!",
-            )),
-        }
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<T: NostrSigner> NostrSigner for EraseNostrSignerError<T> {
+    type Err = SignerError;
+
+    fn backend(&self) -> SignerBackend {
+        self.0.backend()
     }
 
-    /// NIP44 decryption with [NostrSigner]
-    #[cfg(feature = "nip44")]
-    pub async fn nip44_decrypt<T>(&self, public_key: PublicKey, payload: T) -> Result<String, Error>
-    where
-        T: AsRef<[u8]>,
-    {
-        let payload: &[u8] = payload.as_ref();
-        match self {
-            Self::Keys(keys) => Ok(nip44::decrypt(keys.secret_key()?, &public_key, payload)?),
-            #[cfg(all(feature = "nip07", target_arch = "wasm32"))]
-            Self::NIP07(signer) => Ok(signer.nip44_decrypt(public_key, payload).await?),
-            #[cfg(feature = "nip46")]
-            Self::NIP46(..) => Err(Error::unsupported(
-                "NIP44 decryption not supported with NIP46 signer yet!",
-            )),
-        }
+    async fn public_key(&self) -> Result<PublicKey, Self::Err> {
+        self.0.public_key().await.map_err(Into::into)
     }
-}
 
-impl From<Keys> for NostrSigner {
-    fn from(keys: Keys) -> Self {
-        Self::Keys(keys)
+    async fn sign_event(&self, unsigned: UnsignedEvent) -> Result<Event, Self::Err> {
+        self.0.sign_event(unsigned).await.map_err(Into::into)
     }
-}
 
-impl From<&Keys> for NostrSigner {
-    fn from(keys: &Keys) -> Self {
-        Self::Keys(keys.clone())
+    async fn sign_event_builder(&self, builder: EventBuilder) -> Result<Event, Self::Err> {
+        self.0.sign_event_builder(builder).await.map_err(Into::into)
     }
-}
 
-#[cfg(all(feature = "nip07", target_arch = "wasm32"))]
-impl From<Nip07Signer> for NostrSigner {
-    fn from(nip07: Nip07Signer) -> Self {
-        Self::NIP07(nip07)
+    async fn nip04_encrypt(
+        &self,
+        public_key: PublicKey,
+        content: String,
+    ) -> Result<String, Self::Err> {
+        self.0
+            .nip04_encrypt(public_key, content)
+            .await
+            .map_err(Into::into)
     }
-}
 
-#[cfg(feature = "nip46")]
-impl From<Nip46Signer> for NostrSigner {
-    fn from(nip46: Nip46Signer) -> Self {
-        Self::nip46(nip46)
+    async fn nip04_decrypt(
+        &self,
+        public_key: PublicKey,
+        encrypted_content: String,
+    ) -> Result<String, Self::Err> {
+        self.0
+            .nip04_decrypt(public_key, encrypted_content)
+            .await
+            .map_err(Into::into)
+    }
+
+    async fn nip44_encrypt(
+        &self,
+        public_key: PublicKey,
+        content: String,
+    ) -> Result<String, Self::Err> {
+        self.0
+            .nip44_encrypt(public_key, content)
+            .await
+            .map_err(Into::into)
+    }
+
+    async fn nip44_decrypt(
+        &self,
+        public_key: PublicKey,
+        payload: String,
+    ) -> Result<String, Self::Err> {
+        self.0
+            .nip44_decrypt(public_key, payload)
+            .await
+            .map_err(Into::into)
     }
 }
+
+/// Alias for `Send` on non-wasm, empty trait (implemented by everything) on
+/// wasm.
+#[cfg(not(target_arch = "wasm32"))]
+pub trait SendOutsideWasm: Send {}
+#[cfg(not(target_arch = "wasm32"))]
+impl<T: Send> SendOutsideWasm for T {}
+
+/// Alias for `Send` on non-wasm, empty trait (implemented by everything) on
+/// wasm.
+#[cfg(target_arch = "wasm32")]
+pub trait SendOutsideWasm {}
+#[cfg(target_arch = "wasm32")]
+impl<T> SendOutsideWasm for T {}
+
+/// Alias for `Sync` on non-wasm, empty trait (implemented by everything) on
+/// wasm.
+#[cfg(not(target_arch = "wasm32"))]
+pub trait SyncOutsideWasm: Sync {}
+#[cfg(not(target_arch = "wasm32"))]
+impl<T: Sync> SyncOutsideWasm for T {}
+
+/// Alias for `Sync` on non-wasm, empty trait (implemented by everything) on
+/// wasm.
+#[cfg(target_arch = "wasm32")]
+pub trait SyncOutsideWasm {}
+#[cfg(target_arch = "wasm32")]
+impl<T> SyncOutsideWasm for T {}
+
+/// Super trait that is used for our signer traits, this trait will differ if
+/// it's used on WASM. WASM targets will not require `Send` and `Sync` to have
+/// implemented, while other targets will.
+pub trait AsyncTraitDeps: std::fmt::Debug + SendOutsideWasm + SyncOutsideWasm {}
+impl<T: std::fmt::Debug + SendOutsideWasm + SyncOutsideWasm> AsyncTraitDeps for T {}
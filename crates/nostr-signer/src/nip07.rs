@@ -0,0 +1,76 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Nostr Browser Extension signer (NIP07)
+//!
+//! <https://github.com/nostr-protocol/nips/blob/master/07.md>
+
+use nostr::nips::nip07::{self, Nip07Signer};
+use nostr::{Event, PublicKey, UnsignedEvent};
+
+use crate::{NostrSigner, SignerBackend, SignerError};
+
+// `nip07::Error` may contain a `JsValue`, that isn't `Send` and `Sync`
+fn backend_error(e: nip07::Error) -> SignerError {
+    SignerError::Backend(e.to_string().into())
+}
+
+#[async_trait::async_trait(?Send)]
+impl NostrSigner for Nip07Signer {
+    type Err = SignerError;
+
+    fn backend(&self) -> SignerBackend {
+        SignerBackend::BrowserExtension
+    }
+
+    async fn public_key(&self) -> Result<PublicKey, Self::Err> {
+        self.get_public_key().await.map_err(backend_error)
+    }
+
+    async fn sign_event(&self, unsigned: UnsignedEvent) -> Result<Event, Self::Err> {
+        Nip07Signer::sign_event(self, unsigned)
+            .await
+            .map_err(backend_error)
+    }
+
+    async fn nip04_encrypt(
+        &self,
+        public_key: PublicKey,
+        content: String,
+    ) -> Result<String, Self::Err> {
+        Nip07Signer::nip04_encrypt(self, public_key, content)
+            .await
+            .map_err(backend_error)
+    }
+
+    async fn nip04_decrypt(
+        &self,
+        public_key: PublicKey,
+        encrypted_content: String,
+    ) -> Result<String, Self::Err> {
+        Nip07Signer::nip04_decrypt(self, public_key, encrypted_content)
+            .await
+            .map_err(backend_error)
+    }
+
+    async fn nip44_encrypt(
+        &self,
+        public_key: PublicKey,
+        content: String,
+    ) -> Result<String, Self::Err> {
+        Nip07Signer::nip44_encrypt(self, public_key, content)
+            .await
+            .map_err(backend_error)
+    }
+
+    async fn nip44_decrypt(
+        &self,
+        public_key: PublicKey,
+        payload: String,
+    ) -> Result<String, Self::Err> {
+        Nip07Signer::nip44_decrypt(self, public_key, payload)
+            .await
+            .map_err(backend_error)
+    }
+}
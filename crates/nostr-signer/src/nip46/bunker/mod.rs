@@ -0,0 +1,752 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Nostr Connect bunker (remote signer)
+//!
+//! Listen for NIP46 requests and answer them with the bunker keys.
+
+use std::collections::{BTreeSet, HashMap};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use async_utility::thread;
+use nostr::nips::nip44::{self, Version};
+use nostr::nips::nip46::{Message, NostrConnectURI, Request};
+use nostr::prelude::*;
+use nostr::serde_json::json;
+use nostr_relay_pool::{
+    RelayOptions, RelayPool, RelayPoolNotification, RelayPoolOptions, RelaySendOptions,
+    SubscribeOptions,
+};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{Mutex, RwLock, Semaphore};
+
+pub mod permission;
+pub mod storage;
+
+pub use self::permission::Permission;
+#[cfg(not(target_arch = "wasm32"))]
+pub use self::storage::FileBunkerStorage;
+pub use self::storage::{BunkerStorage, ConnectedApp, MemoryBunkerStorage};
+use super::Error;
+use crate::AsyncTraitDeps;
+
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(10);
+/// Max number of requests handled concurrently
+const MAX_CONCURRENT_REQUESTS: usize = 32;
+/// Max number of connections of new apps waiting for the [`NostrConnectAuthorizer`]
+const MAX_PENDING_CONNECTIONS: usize = 4;
+/// Min interval between the requests of an app not connected
+const UNKNOWN_APP_REQUEST_INTERVAL: Duration = Duration::from_secs(5);
+/// Max number of apps not connected tracked in the [`UNKNOWN_APP_REQUEST_INTERVAL`]
+const MAX_UNKNOWN_APPS: usize = 1000;
+
+/// Nostr Connect authorizer
+///
+/// Decide which apps can connect to the bunker and what they are allowed to do.
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait NostrConnectAuthorizer: AsyncTraitDeps {
+    /// Approve the connection of a new app
+    ///
+    /// Return the permissions to grant or `None` to reject the connection.
+    async fn approve_connection(&self, app: PublicKey) -> Option<BTreeSet<Permission>>;
+
+    /// Approve a request not covered by the permissions of the app (default: `false`)
+    async fn approve_request(&self, app: &ConnectedApp, req: &Request) -> bool {
+        let _ = (app, req);
+        false
+    }
+}
+
+/// NIP46 Bunker
+#[derive(Debug, Clone)]
+pub struct Nip46Bunker {
+    keys: Keys,
+    pool: RelayPool,
+    subscription_id: SubscriptionId,
+    authorizer: Arc<dyn NostrConnectAuthorizer>,
+    storage: Arc<dyn BunkerStorage>,
+    apps: Arc<RwLock<HashMap<PublicKey, ConnectedApp>>>,
+    secret: Option<String>,
+    /// The secret has been used by an app
+    secret_used: Arc<AtomicBool>,
+    requests: Arc<Semaphore>,
+    pending_connections: Arc<AtomicUsize>,
+    /// Last request of the apps not connected
+    unknown_apps: Arc<Mutex<HashMap<PublicKey, Timestamp>>>,
+}
+
+impl Nip46Bunker {
+    /// New NIP46 bunker
+    ///
+    /// Connected apps are kept in memory. Use [`Nip46Bunker::with_storage`] to persist them.
+    pub async fn new<I, A>(keys: Keys, relays: I, authorizer: A) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = Url>,
+        A: NostrConnectAuthorizer + 'static,
+    {
+        Self::with_storage(keys, relays, authorizer, MemoryBunkerStorage::default()).await
+    }
+
+    /// New NIP46 bunker with custom [`BunkerStorage`]
+    pub async fn with_storage<I, A, S>(
+        keys: Keys,
+        relays: I,
+        authorizer: A,
+        storage: S,
+    ) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = Url>,
+        A: NostrConnectAuthorizer + 'static,
+        S: BunkerStorage + 'static,
+    {
+        // Compose pool
+        let pool = RelayPool::new(RelayPoolOptions::default());
+        for url in relays.into_iter() {
+            pool.add_relay(url, RelayOptions::default()).await?;
+        }
+        pool.connect(Some(CONNECTION_TIMEOUT)).await;
+
+        Self::with_pool(keys, pool, authorizer, storage).await
+    }
+
+    async fn with_pool<A, S>(
+        keys: Keys,
+        pool: RelayPool,
+        authorizer: A,
+        storage: S,
+    ) -> Result<Self, Error>
+    where
+        A: NostrConnectAuthorizer + 'static,
+        S: BunkerStorage + 'static,
+    {
+        // Load connected apps
+        let apps: HashMap<PublicKey, ConnectedApp> = storage
+            .load()
+            .await?
+            .into_iter()
+            .map(|app| (app.public_key, app))
+            .collect();
+
+        Ok(Self {
+            keys,
+            pool,
+            subscription_id: SubscriptionId::generate(),
+            authorizer: Arc::new(authorizer),
+            storage: Arc::new(storage),
+            apps: Arc::new(RwLock::new(apps)),
+            secret: None,
+            secret_used: Arc::new(AtomicBool::new(false)),
+            requests: Arc::new(Semaphore::new(MAX_CONCURRENT_REQUESTS)),
+            pending_connections: Arc::new(AtomicUsize::new(0)),
+            unknown_apps: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    /// Require a secret in the `connect` requests of the new apps
+    ///
+    /// The connections with a missing or wrong secret are rejected, without asking to the [`NostrConnectAuthorizer`].
+    /// The secret can be used only once: after the first app connected, the new apps are rejected.
+    pub fn secret<S>(mut self, secret: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.secret = Some(secret.into());
+        self
+    }
+
+    /// Get bunker [`PublicKey`]
+    pub fn public_key(&self) -> PublicKey {
+        self.keys.public_key()
+    }
+
+    /// Get bunker relays
+    pub async fn relays(&self) -> Vec<Url> {
+        self.pool.relays().await.into_keys().collect()
+    }
+
+    /// Get connected apps
+    pub async fn apps(&self) -> Vec<ConnectedApp> {
+        self.apps.read().await.values().cloned().collect()
+    }
+
+    /// Connect to an app that shared a Nostr Connect URI
+    ///
+    /// The app is authorized with the provided permissions, without asking to the [`NostrConnectAuthorizer`].
+    pub async fn connect_app(
+        &self,
+        uri: &NostrConnectURI,
+        permissions: BTreeSet<Permission>,
+    ) -> Result<(), Error> {
+        // Add the app relay, if missing
+        let url: Url = uri.relay_url.clone();
+        if self
+            .pool
+            .add_relay(url.clone(), RelayOptions::default())
+            .await?
+        {
+            self.pool
+                .connect_relay(url, Some(CONNECTION_TIMEOUT))
+                .await?;
+
+            // Re-subscribe, if already serving, to include the new relay
+            if self
+                .pool
+                .subscription(&self.subscription_id)
+                .await
+                .is_some()
+            {
+                self.subscribe().await;
+            }
+        }
+
+        self.save_app(ConnectedApp::new(uri.public_key, permissions))
+            .await?;
+
+        // Send `connect` request to the app
+        let msg = Message::request(Request::Connect {
+            public_key: self.keys.public_key(),
+            secret: None,
+        });
+        let event: Event = self.encrypt_msg(uri.public_key, msg, false)?;
+        self.send_event(event).await
+    }
+
+    /// Disconnect an app and revoke its permissions
+    pub async fn remove_app(&self, public_key: &PublicKey) -> Result<(), Error> {
+        let mut apps = self.apps.write().await;
+        if apps.remove(public_key).is_some() {
+            self.storage.store(apps.values().cloned().collect()).await?;
+        }
+        Ok(())
+    }
+
+    /// Listen for requests and answer them
+    ///
+    /// Requests are handled concurrently (up to 32), so an app waiting for the [`NostrConnectAuthorizer`]
+    /// doesn't block the others. The requests of the apps not connected are rate limited.
+    /// Return when the bunker is shutdown.
+    pub async fn serve(&self) -> Result<(), Error> {
+        let mut notifications = self.pool.notifications();
+
+        self.subscribe().await;
+
+        loop {
+            let notification = match notifications.recv().await {
+                Ok(notification) => notification,
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!("Bunker lagged: {skipped} notifications skipped");
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            };
+
+            match notification {
+                RelayPoolNotification::Event {
+                    subscription_id,
+                    event,
+                    ..
+                } => {
+                    if subscription_id != self.subscription_id || event.kind() != Kind::NostrConnect
+                    {
+                        continue;
+                    }
+
+                    let author: PublicKey = event.author();
+                    let connected: bool = self.apps.read().await.contains_key(&author);
+                    if !connected && !self.allow_unknown_app(author).await {
+                        tracing::warn!("Too many NIP46 requests from {author}, skipping");
+                        continue;
+                    }
+
+                    // Wait for a free slot
+                    let permit = match self.requests.clone().acquire_owned().await {
+                        Ok(permit) => permit,
+                        Err(..) => break,
+                    };
+
+                    let bunker = self.clone();
+                    let res = thread::spawn(async move {
+                        let _permit = permit;
+                        if let Err(e) = bunker.handle_event(&event).await {
+                            tracing::error!(
+                                "Impossible to handle NIP46 request {}: {e}",
+                                event.id()
+                            );
+                        }
+                    });
+                    if let Err(e) = res {
+                        tracing::error!("Impossible to spawn NIP46 request handler: {e}");
+                    }
+                }
+                RelayPoolNotification::Shutdown => break,
+                _ => (),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check if a request of an app not connected can be handled
+    ///
+    /// Allow one request per app every [`UNKNOWN_APP_REQUEST_INTERVAL`].
+    async fn allow_unknown_app(&self, public_key: PublicKey) -> bool {
+        let now: Timestamp = Timestamp::now();
+        let mut apps = self.unknown_apps.lock().await;
+        apps.retain(|_, last| *last + UNKNOWN_APP_REQUEST_INTERVAL > now);
+
+        if apps.contains_key(&public_key) || apps.len() >= MAX_UNKNOWN_APPS {
+            return false;
+        }
+
+        apps.insert(public_key, now);
+        true
+    }
+
+    /// Completely shutdown
+    pub async fn shutdown(self) -> Result<(), Error> {
+        Ok(self.pool.shutdown().await?)
+    }
+
+    async fn subscribe(&self) {
+        let filter = Filter::new()
+            .pubkey(self.keys.public_key())
+            .kind(Kind::NostrConnect)
+            .since(Timestamp::now());
+        self.pool
+            .subscribe_with_id(
+                self.subscription_id.clone(),
+                vec![filter],
+                SubscribeOptions::default(),
+            )
+            .await;
+    }
+
+    async fn save_app(&self, app: ConnectedApp) -> Result<(), Error> {
+        let mut apps = self.apps.write().await;
+        apps.insert(app.public_key, app);
+        self.storage.store(apps.values().cloned().collect()).await?;
+        Ok(())
+    }
+
+    /// Encrypt the message with NIP44 or NIP04
+    fn encrypt_msg(
+        &self,
+        public_key: PublicKey,
+        msg: Message,
+        nip44: bool,
+    ) -> Result<Event, Error> {
+        let secret_key = self.keys.secret_key()?;
+        let content: String = if nip44 {
+            nip44::encrypt(secret_key, &public_key, msg.as_json(), Version::default())?
+        } else {
+            nip04::encrypt(secret_key, &public_key, msg.as_json())?
+        };
+        let event: Event =
+            EventBuilder::new(Kind::NostrConnect, content, [Tag::public_key(public_key)])
+                .to_event(&self.keys)?;
+        Ok(event)
+    }
+
+    async fn send_event(&self, event: Event) -> Result<(), Error> {
+        self.pool.send_event(event, RelaySendOptions::new()).await?;
+        Ok(())
+    }
+
+    async fn handle_event(&self, event: &Event) -> Result<(), Error> {
+        match self.response(event).await? {
+            Some(res) => self.send_event(res).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Handle the request and compose the response event
+    ///
+    /// The response is encrypted with the same NIP (NIP44 or NIP04) of the request.
+    async fn response(&self, event: &Event) -> Result<Option<Event>, Error> {
+        let secret_key = self.keys.secret_key()?;
+        let content: &str = event.content();
+        let nip44: bool = !content.contains("?iv=");
+        let msg: String = if nip44 {
+            nip44::decrypt(secret_key, event.author_ref(), content)?
+        } else {
+            nip04::decrypt(secret_key, event.author_ref(), content)?
+        };
+        let msg: Message = Message::from_json(msg)?;
+
+        // Responses are only expected after `connect_app`: nothing to do
+        if !msg.is_request() {
+            return Ok(None);
+        }
+
+        let res: Message = match msg.to_request() {
+            Ok(req) => match self.handle_request(event.author(), msg.id(), req).await? {
+                Some(res) => res,
+                None => return Ok(None),
+            },
+            Err(e) => Message::response(msg.id(), None, Some(e.to_string())),
+        };
+
+        Ok(Some(self.encrypt_msg(event.author(), res, nip44)?))
+    }
+
+    /// Check the secret and ask to the [`NostrConnectAuthorizer`] to approve the connection of a new app
+    ///
+    /// Return the error to send to the app if the connection is not approved.
+    async fn connect_new_app(
+        &self,
+        public_key: PublicKey,
+        secret: Option<String>,
+    ) -> Result<Option<&'static str>, Error> {
+        // Claim the secret: only one app can use it
+        let claimed: bool = match &self.secret {
+            Some(expected) => {
+                if secret.as_ref() != Some(expected)
+                    || self.secret_used.swap(true, Ordering::SeqCst)
+                {
+                    return Ok(Some("invalid secret"));
+                }
+                true
+            }
+            None => false,
+        };
+
+        if self.pending_connections.fetch_add(1, Ordering::SeqCst) >= MAX_PENDING_CONNECTIONS {
+            self.pending_connections.fetch_sub(1, Ordering::SeqCst);
+            if claimed {
+                self.secret_used.store(false, Ordering::SeqCst);
+            }
+            return Ok(Some("too many pending connections"));
+        }
+
+        let permissions: Option<BTreeSet<Permission>> =
+            self.authorizer.approve_connection(public_key).await;
+        self.pending_connections.fetch_sub(1, Ordering::SeqCst);
+
+        let res: Result<Option<&'static str>, Error> = match permissions {
+            Some(permissions) => self
+                .save_app(ConnectedApp::new(public_key, permissions))
+                .await
+                .map(|()| None),
+            None => Ok(Some("connection rejected")),
+        };
+
+        // Release the secret if the app is not connected
+        if claimed && !matches!(res, Ok(None)) {
+            self.secret_used.store(false, Ordering::SeqCst);
+        }
+
+        res
+    }
+
+    async fn handle_request(
+        &self,
+        public_key: PublicKey,
+        id: String,
+        req: Request,
+    ) -> Result<Option<Message>, Error> {
+        match req {
+            Request::Connect { secret, .. } => {
+                let connected: bool = self.apps.read().await.contains_key(&public_key);
+                if !connected {
+                    if let Some(error) = self.connect_new_app(public_key, secret).await? {
+                        return Ok(Some(error_response(id, error)));
+                    }
+                }
+
+                Ok(Some(Message::Response {
+                    id,
+                    result: Some(json!("ack")),
+                    error: None,
+                }))
+            }
+            Request::Disconnect => {
+                self.remove_app(&public_key).await?;
+                Ok(Some(Message::Response {
+                    id,
+                    result: Some(json!("ack")),
+                    error: None,
+                }))
+            }
+            req => {
+                let app: Option<ConnectedApp> = self.apps.read().await.get(&public_key).cloned();
+                let app: ConnectedApp = match app {
+                    Some(app) => app,
+                    None => return Ok(Some(error_response(id, "app not connected"))),
+                };
+
+                if !app.is_allowed(&req) && !self.authorizer.approve_request(&app, &req).await {
+                    return Ok(Some(error_response(id, "permission denied")));
+                }
+
+                match req.generate_response(&self.keys) {
+                    Ok(res) => Ok(res.map(|res| Message::response(id, Some(res), None))),
+                    Err(e) => Ok(Some(error_response(id, e.to_string()))),
+                }
+            }
+        }
+    }
+}
+
+fn error_response<S>(id: String, error: S) -> Message
+where
+    S: Into<String>,
+{
+    Message::response(id, None, Some(error.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future;
+
+    use nostr_relay_pool::DuplexTransport;
+
+    use super::*;
+
+    /// Approve every connection without permissions, except the pending app, never answered
+    #[derive(Debug)]
+    struct TestAuthorizer {
+        pending: Option<PublicKey>,
+    }
+
+    #[async_trait]
+    impl NostrConnectAuthorizer for TestAuthorizer {
+        async fn approve_connection(&self, app: PublicKey) -> Option<BTreeSet<Permission>> {
+            if self.pending == Some(app) {
+                future::pending::<()>().await;
+            }
+            Some(BTreeSet::new())
+        }
+    }
+
+    async fn new_bunker(pool: RelayPool, pending: Option<PublicKey>) -> Nip46Bunker {
+        Nip46Bunker::with_pool(
+            Keys::generate(),
+            pool,
+            TestAuthorizer { pending },
+            MemoryBunkerStorage::default(),
+        )
+        .await
+        .unwrap()
+    }
+
+    fn request(app: &Keys, bunker: PublicKey, req: Request, nip44: bool) -> Event {
+        let msg = Message::request(req);
+        let secret_key = app.secret_key().unwrap();
+        let content: String = if nip44 {
+            nip44::encrypt(secret_key, &bunker, msg.as_json(), Version::default()).unwrap()
+        } else {
+            nip04::encrypt(secret_key, &bunker, msg.as_json()).unwrap()
+        };
+        EventBuilder::new(Kind::NostrConnect, content, [Tag::public_key(bunker)])
+            .to_event(app)
+            .unwrap()
+    }
+
+    fn connect(app: &Keys, bunker: PublicKey, secret: Option<&str>) -> Event {
+        let req = Request::Connect {
+            public_key: bunker,
+            secret: secret.map(|s| s.to_string()),
+        };
+        request(app, bunker, req, true)
+    }
+
+    /// Decrypt the response with the same NIP of the request
+    fn response(app: &Keys, event: &Event, nip44: bool) -> Message {
+        let secret_key = app.secret_key().unwrap();
+        let msg: String = if nip44 {
+            nip44::decrypt(secret_key, event.author_ref(), event.content()).unwrap()
+        } else {
+            nip04::decrypt(secret_key, event.author_ref(), event.content()).unwrap()
+        };
+        Message::from_json(msg).unwrap()
+    }
+
+    fn is_ack(msg: &Message) -> bool {
+        matches!(msg, Message::Response { result: Some(result), error: None, .. } if *result == json!("ack"))
+    }
+
+    #[tokio::test]
+    async fn test_connect_secret() {
+        let bunker = new_bunker(RelayPool::default(), None)
+            .await
+            .secret("secret");
+        let app = Keys::generate();
+
+        let event = connect(&app, bunker.public_key(), Some("wrong"));
+        let res = bunker.response(&event).await.unwrap().unwrap();
+        match response(&app, &res, true) {
+            Message::Response { error, .. } => assert_eq!(error.as_deref(), Some("invalid secret")),
+            msg => panic!("Unexpected message: {msg:?}"),
+        }
+        assert!(bunker.apps().await.is_empty());
+
+        let event = connect(&app, bunker.public_key(), Some("secret"));
+        let res = bunker.response(&event).await.unwrap().unwrap();
+        assert!(is_ack(&response(&app, &res, true)));
+        assert_eq!(bunker.apps().await.len(), 1);
+
+        // The secret can be used only once
+        let other = Keys::generate();
+        let event = connect(&other, bunker.public_key(), Some("secret"));
+        let res = bunker.response(&event).await.unwrap().unwrap();
+        match response(&other, &res, true) {
+            Message::Response { error, .. } => assert_eq!(error.as_deref(), Some("invalid secret")),
+            msg => panic!("Unexpected message: {msg:?}"),
+        }
+        assert_eq!(bunker.apps().await.len(), 1);
+
+        // Already connected
+        let event = connect(&app, bunker.public_key(), Some("secret"));
+        let res = bunker.response(&event).await.unwrap().unwrap();
+        assert!(is_ack(&response(&app, &res, true)));
+    }
+
+    #[tokio::test]
+    async fn test_pending_connections() {
+        let app = Keys::generate();
+        let bunker = new_bunker(RelayPool::default(), Some(app.public_key())).await;
+
+        // Connections waiting for the authorizer forever
+        for _ in 0..MAX_PENDING_CONNECTIONS {
+            let bunker = bunker.clone();
+            let event = connect(&app, bunker.public_key(), None);
+            tokio::spawn(async move { bunker.response(&event).await });
+        }
+        while bunker.pending_connections.load(Ordering::SeqCst) < MAX_PENDING_CONNECTIONS {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let other = Keys::generate();
+        let event = connect(&other, bunker.public_key(), None);
+        let res = bunker.response(&event).await.unwrap().unwrap();
+        match response(&other, &res, true) {
+            Message::Response { error, .. } => {
+                assert_eq!(error.as_deref(), Some("too many pending connections"))
+            }
+            msg => panic!("Unexpected message: {msg:?}"),
+        }
+        assert!(bunker.apps().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unknown_apps_rate_limit() {
+        let bunker = new_bunker(RelayPool::default(), None).await;
+        let app = Keys::generate();
+
+        assert!(bunker.allow_unknown_app(app.public_key()).await);
+        assert!(!bunker.allow_unknown_app(app.public_key()).await);
+        assert!(
+            bunker
+                .allow_unknown_app(Keys::generate().public_key())
+                .await
+        );
+    }
+
+    #[tokio::test]
+    async fn test_nip04_and_nip44_requests() {
+        let bunker = new_bunker(RelayPool::default(), None).await;
+        let app = Keys::generate();
+
+        let event = connect(&app, bunker.public_key(), None);
+        bunker.response(&event).await.unwrap().unwrap();
+
+        for nip44 in [true, false] {
+            let event = request(&app, bunker.public_key(), Request::GetPublicKey, nip44);
+            let res = bunker.response(&event).await.unwrap().unwrap();
+            assert_eq!(res.content().contains("?iv="), !nip44);
+            assert_eq!(res.public_keys().next(), Some(&app.public_key()));
+            match response(&app, &res, nip44) {
+                Message::Response { result, error, .. } => {
+                    assert_eq!(error, None);
+                    assert_eq!(result, Some(json!(bunker.public_key())));
+                }
+                msg => panic!("Unexpected message: {msg:?}"),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_disconnect() {
+        let bunker = new_bunker(RelayPool::default(), None).await;
+        let app = Keys::generate();
+
+        let event = connect(&app, bunker.public_key(), None);
+        bunker.response(&event).await.unwrap().unwrap();
+        assert_eq!(bunker.apps().await.len(), 1);
+
+        // Not allowed
+        let unsigned = EventBuilder::text_note("Hello", []).to_unsigned_event(app.public_key());
+        let event = request(
+            &app,
+            bunker.public_key(),
+            Request::SignEvent(unsigned),
+            true,
+        );
+        let res = bunker.response(&event).await.unwrap().unwrap();
+        match response(&app, &res, true) {
+            Message::Response { error, .. } => {
+                assert_eq!(error.as_deref(), Some("permission denied"))
+            }
+            msg => panic!("Unexpected message: {msg:?}"),
+        }
+
+        let event = request(&app, bunker.public_key(), Request::Disconnect, true);
+        let res = bunker.response(&event).await.unwrap().unwrap();
+        assert!(is_ack(&response(&app, &res, true)));
+        assert!(bunker.apps().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_serve_concurrent_requests() {
+        let (transport, mut listener) = DuplexTransport::new();
+        let pool = RelayPool::default();
+        let opts = RelayOptions::new().transport(transport);
+        pool.add_relay("ws://relay.local", opts).await.unwrap();
+        pool.connect(Some(Duration::from_secs(1))).await;
+
+        // The connection of the app 1 wait for the authorizer forever
+        let app1 = Keys::generate();
+        let app2 = Keys::generate();
+        let bunker = new_bunker(pool, Some(app1.public_key())).await;
+        let serve = bunker.clone();
+        tokio::spawn(async move { serve.serve().await });
+
+        let mut connection = listener.accept().await.unwrap();
+        let subscription_id = loop {
+            if let Some(ClientMessage::Req {
+                subscription_id, ..
+            }) = connection.recv().await
+            {
+                break subscription_id;
+            }
+        };
+
+        for app in [&app1, &app2] {
+            let event = connect(app, bunker.public_key(), None);
+            connection
+                .send(RelayMessage::event(subscription_id.clone(), event))
+                .unwrap();
+        }
+
+        // Only the app 2 receive the response
+        let res = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                if let Some(ClientMessage::Event(event)) = connection.recv().await {
+                    connection
+                        .send(RelayMessage::ok(event.id(), true, ""))
+                        .unwrap();
+                    break event;
+                }
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(res.public_keys().next(), Some(&app2.public_key()));
+        assert!(is_ack(&response(&app2, &res, true)));
+    }
+}
@@ -0,0 +1,176 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Nostr Connect permissions
+
+use std::fmt;
+use std::str::FromStr;
+
+use nostr::nips::nip46::Request;
+use nostr::Kind;
+use serde::de::Error as DeserializerError;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use thiserror::Error;
+
+/// Permission error
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum Error {
+    /// Unknown permission
+    #[error("unknown permission: {0}")]
+    Unknown(String),
+    /// Invalid kind
+    #[error("invalid kind: {0}")]
+    InvalidKind(String),
+}
+
+/// Permission granted to a connected app
+///
+/// Serialized as in the NIP46 `perms` list (ex. `sign_event:1`, `nip44_encrypt`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Permission {
+    /// Sign events (`sign_event`), optionally only of a specific kind (`sign_event:<kind>`)
+    SignEvent(Option<Kind>),
+    /// NIP04 encryption
+    Nip04Encrypt,
+    /// NIP04 decryption
+    Nip04Decrypt,
+    /// NIP44 encryption
+    Nip44Encrypt,
+    /// NIP44 decryption
+    Nip44Decrypt,
+    /// NIP26 delegation
+    Delegate,
+    /// Schnorr signature
+    SignSchnorr,
+}
+
+impl Permission {
+    /// Check if the permission allows the [`Request`]
+    ///
+    /// Requests that don't require permissions (`connect`, `describe`, ...) always return `false`.
+    pub fn allows(&self, req: &Request) -> bool {
+        match (self, req) {
+            (Self::SignEvent(None), Request::SignEvent(..)) => true,
+            (Self::SignEvent(Some(kind)), Request::SignEvent(unsigned)) => unsigned.kind == *kind,
+            (Self::Nip04Encrypt, Request::Nip04Encrypt { .. }) => true,
+            (Self::Nip04Decrypt, Request::Nip04Decrypt { .. }) => true,
+            (Self::Nip44Encrypt, Request::Nip44Encrypt { .. }) => true,
+            (Self::Nip44Decrypt, Request::Nip44Decrypt { .. }) => true,
+            (Self::Delegate, Request::Delegate { .. }) => true,
+            (Self::SignSchnorr, Request::SignSchnorr(..)) => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SignEvent(None) => write!(f, "sign_event"),
+            Self::SignEvent(Some(kind)) => write!(f, "sign_event:{kind}"),
+            Self::Nip04Encrypt => write!(f, "nip04_encrypt"),
+            Self::Nip04Decrypt => write!(f, "nip04_decrypt"),
+            Self::Nip44Encrypt => write!(f, "nip44_encrypt"),
+            Self::Nip44Decrypt => write!(f, "nip44_decrypt"),
+            Self::Delegate => write!(f, "delegate"),
+            Self::SignSchnorr => write!(f, "sign_schnorr"),
+        }
+    }
+}
+
+impl FromStr for Permission {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sign_event" => Ok(Self::SignEvent(None)),
+            "nip04_encrypt" => Ok(Self::Nip04Encrypt),
+            "nip04_decrypt" => Ok(Self::Nip04Decrypt),
+            "nip44_encrypt" => Ok(Self::Nip44Encrypt),
+            "nip44_decrypt" => Ok(Self::Nip44Decrypt),
+            "delegate" => Ok(Self::Delegate),
+            "sign_schnorr" => Ok(Self::SignSchnorr),
+            s => match s.strip_prefix("sign_event:") {
+                Some(kind) => {
+                    let kind: u64 = kind
+                        .parse()
+                        .map_err(|_| Error::InvalidKind(kind.to_string()))?;
+                    Ok(Self::SignEvent(Some(Kind::from(kind))))
+                }
+                None => Err(Error::Unknown(s.to_string())),
+            },
+        }
+    }
+}
+
+impl Serialize for Permission {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Permission {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let permission: String = String::deserialize(deserializer)?;
+        Self::from_str(&permission).map_err(DeserializerError::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::{EventBuilder, Keys};
+
+    use super::*;
+
+    #[test]
+    fn test_parse_permission() {
+        for permission in [
+            Permission::SignEvent(None),
+            Permission::SignEvent(Some(Kind::TextNote)),
+            Permission::Nip04Encrypt,
+            Permission::Nip04Decrypt,
+            Permission::Nip44Encrypt,
+            Permission::Nip44Decrypt,
+            Permission::Delegate,
+            Permission::SignSchnorr,
+        ] {
+            assert_eq!(
+                Permission::from_str(&permission.to_string()),
+                Ok(permission)
+            );
+        }
+
+        assert_eq!(
+            Permission::from_str("sign_event:1"),
+            Ok(Permission::SignEvent(Some(Kind::TextNote)))
+        );
+        assert_eq!(
+            Permission::from_str("sign_event:abc"),
+            Err(Error::InvalidKind(String::from("abc")))
+        );
+        assert_eq!(
+            Permission::from_str("get_relays"),
+            Err(Error::Unknown(String::from("get_relays")))
+        );
+    }
+
+    #[test]
+    fn test_permission_allows() {
+        let keys = Keys::generate();
+        let unsigned = EventBuilder::text_note("Test", []).to_unsigned_event(keys.public_key());
+        let req = Request::SignEvent(unsigned);
+
+        assert!(Permission::SignEvent(None).allows(&req));
+        assert!(Permission::SignEvent(Some(Kind::TextNote)).allows(&req));
+        assert!(!Permission::SignEvent(Some(Kind::Metadata)).allows(&req));
+        assert!(!Permission::Nip04Encrypt.allows(&req));
+        assert!(!Permission::SignEvent(None).allows(&Request::GetPublicKey));
+    }
+}
@@ -0,0 +1,135 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Connected apps storage
+
+use std::collections::BTreeSet;
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs;
+#[cfg(not(target_arch = "wasm32"))]
+use std::io::ErrorKind;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use nostr::nips::nip46::Request;
+#[cfg(not(target_arch = "wasm32"))]
+use nostr::serde_json;
+use nostr::{PublicKey, Timestamp};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use super::Permission;
+use crate::{AsyncTraitDeps, SignerError};
+
+/// App connected to the bunker
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConnectedApp {
+    /// App public key
+    pub public_key: PublicKey,
+    /// Granted permissions
+    pub permissions: BTreeSet<Permission>,
+    /// Connection timestamp
+    pub connected_at: Timestamp,
+}
+
+impl ConnectedApp {
+    /// New connected app
+    pub fn new(public_key: PublicKey, permissions: BTreeSet<Permission>) -> Self {
+        Self {
+            public_key,
+            permissions,
+            connected_at: Timestamp::now(),
+        }
+    }
+
+    /// Check if the [`Request`] is allowed by the granted permissions
+    ///
+    /// `describe`, `get_public_key`, `connect` and `disconnect` are always allowed.
+    pub fn is_allowed(&self, req: &Request) -> bool {
+        match req {
+            Request::Describe
+            | Request::GetPublicKey
+            | Request::Connect { .. }
+            | Request::Disconnect => true,
+            req => self.permissions.iter().any(|p| p.allows(req)),
+        }
+    }
+}
+
+/// Bunker storage
+///
+/// Persist the connected apps, so they don't have to be authorized again after a restart.
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait BunkerStorage: AsyncTraitDeps {
+    /// Load connected apps
+    async fn load(&self) -> Result<Vec<ConnectedApp>, SignerError>;
+
+    /// Store connected apps (replace the previous ones)
+    async fn store(&self, apps: Vec<ConnectedApp>) -> Result<(), SignerError>;
+}
+
+/// In-memory bunker storage
+///
+/// Connected apps are lost on restart.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryBunkerStorage {
+    apps: Arc<Mutex<Vec<ConnectedApp>>>,
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl BunkerStorage for MemoryBunkerStorage {
+    async fn load(&self) -> Result<Vec<ConnectedApp>, SignerError> {
+        Ok(self.apps.lock().await.clone())
+    }
+
+    async fn store(&self, apps: Vec<ConnectedApp>) -> Result<(), SignerError> {
+        *self.apps.lock().await = apps;
+        Ok(())
+    }
+}
+
+/// JSON file bunker storage
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone)]
+pub struct FileBunkerStorage {
+    path: PathBuf,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FileBunkerStorage {
+    /// New JSON file storage
+    ///
+    /// The file is created at the first store.
+    pub fn new<P>(path: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self { path: path.into() }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[async_trait]
+impl BunkerStorage for FileBunkerStorage {
+    async fn load(&self) -> Result<Vec<ConnectedApp>, SignerError> {
+        match fs::read(&self.path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(SignerError::backend),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(SignerError::backend(e)),
+        }
+    }
+
+    async fn store(&self, apps: Vec<ConnectedApp>) -> Result<(), SignerError> {
+        let json: Vec<u8> = serde_json::to_vec_pretty(&apps).map_err(SignerError::backend)?;
+
+        // Write to a temporary file first, to not corrupt the storage if the write fails
+        let tmp: PathBuf = self.path.with_extension("tmp");
+        fs::write(&tmp, json).map_err(SignerError::backend)?;
+        fs::rename(tmp, &self.path).map_err(SignerError::backend)
+    }
+}
@@ -20,6 +20,12 @@ use nostr_relay_pool::{
 use thiserror::Error;
 use tokio::sync::Mutex;
 
+use crate::{NostrSigner, SignerBackend, SignerError};
+
+pub mod bunker;
+
+pub use self::bunker::Nip46Bunker;
+
 const TIMEOUT: Duration = Duration::from_secs(10);
 
 /// Nostr Connect error
@@ -37,12 +43,21 @@ pub enum Error {
     /// NIP04 error
     #[error(transparent)]
     NIP04(#[from] nip04::Error),
+    /// NIP44 error
+    #[error(transparent)]
+    NIP44(#[from] nip44::Error),
     /// NIP46 error
     #[error(transparent)]
     NIP46(#[from] nip46::Error),
     /// Relay
     #[error(transparent)]
     Relay(#[from] nostr_relay_pool::relay::Error),
+    /// Relay pool
+    #[error(transparent)]
+    Pool(#[from] nostr_relay_pool::pool::Error),
+    /// Signer error
+    #[error(transparent)]
+    Signer(#[from] SignerError),
     /// Generic NIP46 error
     #[error("generic error")]
     Generic,
@@ -52,11 +67,23 @@ pub enum Error {
     /// Signer public key not found
     #[error("signer public key not found")]
     SignerPublicKeyNotFound,
+    /// Response not match to the request
+    #[error("response not match to the request")]
+    ResponseNotMatchRequest,
     /// Request timeout
     #[error("timeout")]
     Timeout,
 }
 
+impl From<Error> for SignerError {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::Signer(e) => e,
+            e => Self::backend(e),
+        }
+    }
+}
+
 /// NIP46 Signer
 #[derive(Debug, Clone)]
 pub struct Nip46Signer {
@@ -156,8 +183,8 @@ impl Nip46Signer {
                         let msg: String =
                             nip04::decrypt(secret_key, event.author_ref(), event.content())?;
                         let msg = Message::from_json(msg)?;
-                        if let Ok(Request::Connect(pk)) = msg.to_request() {
-                            return Ok(pk);
+                        if let Ok(Request::Connect { public_key, .. }) = msg.to_request() {
+                            return Ok(public_key);
                         }
                     }
                 }
@@ -242,6 +269,12 @@ impl Nip46Signer {
                                         Request::Nip04Decrypt { .. } => Response::Nip04Decrypt(
                                             serde_json::from_value(result.to_owned())?,
                                         ),
+                                        Request::Nip44Encrypt { .. } => Response::Nip44Encrypt(
+                                            serde_json::from_value(result.to_owned())?,
+                                        ),
+                                        Request::Nip44Decrypt { .. } => Response::Nip44Decrypt(
+                                            serde_json::from_value(result.to_owned())?,
+                                        ),
                                         Request::SignSchnorr { .. } => Response::SignSchnorr(
                                             serde_json::from_value(result.to_owned())?,
                                         ),
@@ -275,3 +308,96 @@ impl Nip46Signer {
         Ok(self.relay.terminate().await?)
     }
 }
+
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+impl NostrSigner for Nip46Signer {
+    type Err = Error;
+
+    fn backend(&self) -> SignerBackend {
+        SignerBackend::NostrConnect
+    }
+
+    async fn public_key(&self) -> Result<PublicKey, Self::Err> {
+        self.signer_public_key().await
+    }
+
+    async fn sign_event(&self, unsigned: UnsignedEvent) -> Result<Event, Self::Err> {
+        let res: Response = self
+            .send_req_to_signer(Request::SignEvent(unsigned), None)
+            .await?;
+        if let Response::SignEvent(event) = res {
+            Ok(event)
+        } else {
+            Err(Error::ResponseNotMatchRequest)
+        }
+    }
+
+    async fn nip04_encrypt(
+        &self,
+        public_key: PublicKey,
+        content: String,
+    ) -> Result<String, Self::Err> {
+        let req = Request::Nip04Encrypt {
+            public_key,
+            text: content,
+        };
+        let res: Response = self.send_req_to_signer(req, None).await?;
+        if let Response::Nip04Encrypt(ciphertext) = res {
+            Ok(ciphertext)
+        } else {
+            Err(Error::ResponseNotMatchRequest)
+        }
+    }
+
+    async fn nip04_decrypt(
+        &self,
+        public_key: PublicKey,
+        encrypted_content: String,
+    ) -> Result<String, Self::Err> {
+        let req = Request::Nip04Decrypt {
+            public_key,
+            text: encrypted_content,
+        };
+        let res: Response = self.send_req_to_signer(req, None).await?;
+        if let Response::Nip04Decrypt(content) = res {
+            Ok(content)
+        } else {
+            Err(Error::ResponseNotMatchRequest)
+        }
+    }
+
+    async fn nip44_encrypt(
+        &self,
+        public_key: PublicKey,
+        content: String,
+    ) -> Result<String, Self::Err> {
+        let req = Request::Nip44Encrypt {
+            public_key,
+            text: content,
+        };
+        let res: Response = self.send_req_to_signer(req, None).await?;
+        if let Response::Nip44Encrypt(payload) = res {
+            Ok(payload)
+        } else {
+            Err(Error::ResponseNotMatchRequest)
+        }
+    }
+
+    async fn nip44_decrypt(
+        &self,
+        public_key: PublicKey,
+        payload: String,
+    ) -> Result<String, Self::Err> {
+        let req = Request::Nip44Decrypt {
+            public_key,
+            text: payload,
+        };
+        let res: Response = self.send_req_to_signer(req, None).await?;
+        if let Response::Nip44Decrypt(content) = res {
+            Ok(content)
+        } else {
+            Err(Error::ResponseNotMatchRequest)
+        }
+    }
+}
@@ -0,0 +1,36 @@
+-- Event fields used by the on-disk indexes
+ALTER TABLE events ADD COLUMN pubkey BLOB;
+ALTER TABLE events ADD COLUMN created_at INTEGER;
+ALTER TABLE events ADD COLUMN kind INTEGER;
+ALTER TABLE events ADD COLUMN identifier TEXT; -- `d` tag
+
+CREATE INDEX IF NOT EXISTS events_created_at_index ON events(created_at);
+CREATE INDEX IF NOT EXISTS events_kind_created_at_index ON events(kind,created_at);
+CREATE INDEX IF NOT EXISTS events_pubkey_kind_created_at_index ON events(pubkey,kind,created_at);
+CREATE INDEX IF NOT EXISTS events_coordinate_index ON events(kind,pubkey,identifier);
+
+-- Single-letter tags
+CREATE TABLE IF NOT EXISTS event_tags (
+    event_id BLOB NOT NULL,
+    name TEXT NOT NULL,
+    value TEXT NOT NULL,
+    created_at INTEGER NOT NULL
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS event_tags_unique_index ON event_tags(event_id,name,value);
+CREATE INDEX IF NOT EXISTS event_tags_name_value_index ON event_tags(name,value,created_at);
+
+-- Deleted events and coordinates
+CREATE TABLE IF NOT EXISTS deleted_ids (
+    event_id BLOB PRIMARY KEY NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS deleted_coordinates (
+    kind INTEGER NOT NULL,
+    pubkey BLOB NOT NULL,
+    identifier TEXT NOT NULL,
+    deleted_at INTEGER NOT NULL,
+    PRIMARY KEY(kind,pubkey,identifier)
+);
+
+PRAGMA user_version = 2; -- Schema version
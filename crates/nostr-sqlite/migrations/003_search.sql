@@ -0,0 +1,5 @@
+-- Full-text search (NIP-50)
+-- The `rowid` matches the one of the `events` table
+CREATE VIRTUAL TABLE IF NOT EXISTS events_fts USING fts5(content, tokenize = 'unicode61 remove_diacritics 2');
+
+PRAGMA user_version = 3; -- Schema version
@@ -0,0 +1,10 @@
+-- Events waiting to be delivered to relays
+CREATE TABLE IF NOT EXISTS outbox (
+    relay_url TEXT NOT NULL,
+    event_id BLOB NOT NULL,
+    event BLOB NOT NULL,
+    created_at INTEGER NOT NULL,
+    PRIMARY KEY(relay_url,event_id)
+);
+
+PRAGMA user_version = 4; -- Schema version
@@ -32,6 +32,9 @@ pub enum Error {
     /// Flatbuffers error
     #[error(transparent)]
     Flatbuffers(#[from] flatbuffers::Error),
+    /// Event ID error
+    #[error(transparent)]
+    EventId(#[from] nostr::event::id::Error),
     /// Url error
     #[error(transparent)]
     Url(#[from] nostr::types::url::ParseError),
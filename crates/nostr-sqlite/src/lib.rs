@@ -8,7 +8,7 @@
 #![warn(missing_docs)]
 #![warn(rustdoc::bare_urls)]
 
-use std::collections::{BTreeSet, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::path::Path;
 use std::sync::Arc;
 
@@ -28,23 +28,37 @@ use tokio::sync::RwLock;
 
 mod error;
 mod migration;
+mod options;
+mod query;
 
 pub use self::error::Error;
 use self::migration::STARTUP_SQL;
+pub use self::options::SQLiteDatabaseOptions;
 
 const BATCH_SIZE: usize = 100;
 
 /// SQLite Nostr Database
+///
+/// Queries are answered by the on-disk indexes, unless the in-memory indexes are enabled
+/// (see [`SQLiteDatabaseOptions::in_memory_indexes`]).
 #[derive(Debug, Clone)]
 pub struct SQLiteDatabase {
     db: Pool,
-    indexes: DatabaseIndexes,
+    indexes: Option<DatabaseIndexes>,
     fbb: Arc<RwLock<FlatBufferBuilder<'static>>>,
 }
 
 impl SQLiteDatabase {
     /// Open SQLite store
     pub async fn open<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        Self::open_with_opts(path, SQLiteDatabaseOptions::default()).await
+    }
+
+    /// Open SQLite store with custom options
+    pub async fn open_with_opts<P>(path: P, opts: SQLiteDatabaseOptions) -> Result<Self, Error>
     where
         P: AsRef<Path>,
     {
@@ -57,12 +71,18 @@ impl SQLiteDatabase {
 
         let this = Self {
             db: pool,
-            indexes: DatabaseIndexes::new(),
+            indexes: if opts.in_memory_indexes {
+                Some(DatabaseIndexes::new())
+            } else {
+                None
+            },
             fbb: Arc::new(RwLock::new(FlatBufferBuilder::with_capacity(70_000))),
         };
 
-        // Build indexes
-        this.build_indexes(&conn).await?;
+        // Build in-memory indexes
+        if let Some(indexes) = &this.indexes {
+            this.build_indexes(&conn, indexes).await?;
+        }
 
         Ok(this)
     }
@@ -72,7 +92,7 @@ impl SQLiteDatabase {
     }
 
     #[tracing::instrument(skip_all)]
-    async fn build_indexes(&self, conn: &Object) -> Result<(), Error> {
+    async fn build_indexes(&self, conn: &Object, indexes: &DatabaseIndexes) -> Result<(), Error> {
         let events = conn
             .interact(move |conn| {
                 let mut stmt = conn.prepare_cached("SELECT event FROM events;")?;
@@ -88,30 +108,48 @@ impl SQLiteDatabase {
             .await??;
 
         // Build indexes
-        let to_discard: Vec<EventId> = self.indexes.bulk_index(events).await.into_iter().collect();
+        let to_discard: Vec<EventId> = indexes.bulk_index(events).await.into_iter().collect();
 
         // Discard events
         if !to_discard.is_empty() {
             let conn = self.acquire().await?;
             conn.interact(move |conn| {
-                for chunk in to_discard.chunks(BATCH_SIZE) {
-                    let delete_query = format!(
-                        "DELETE FROM events WHERE {};",
-                        chunk
-                            .iter()
-                            .map(|id| format!("event_id = '{id}'"))
-                            .collect::<Vec<_>>()
-                            .join(" OR ")
-                    );
-                    conn.execute(&delete_query, [])?;
-                }
-
+                let tx = conn.transaction()?;
+                query::discard_events(&tx, &to_discard)?;
+                tx.commit()?;
                 Ok::<(), Error>(())
             })
             .await??;
         }
         Ok(())
     }
+
+    async fn fetch_events(&self, ids: Vec<EventId>) -> Result<Vec<Event>, Error> {
+        let conn = self.acquire().await?;
+        conn.interact(move |conn| {
+            let mut events: HashMap<EventId, Event> = HashMap::with_capacity(ids.len());
+            for chunk in ids.chunks(BATCH_SIZE) {
+                let mut stmt = conn.prepare_cached(&format!(
+                    "SELECT event FROM events WHERE {};",
+                    chunk
+                        .iter()
+                        .map(|id| format!("event_id = '{id}'"))
+                        .collect::<Vec<_>>()
+                        .join(" OR ")
+                ))?;
+                let mut rows = stmt.query([])?;
+                while let Ok(Some(row)) = rows.next() {
+                    let buf: Vec<u8> = row.get(0)?;
+                    let event: Event = Event::decode(&buf)?;
+                    events.insert(event.id(), event);
+                }
+            }
+
+            // Keep the order of the IDs
+            Ok(ids.iter().filter_map(|id| events.remove(id)).collect())
+        })
+        .await?
+    }
 }
 
 #[async_trait]
@@ -124,55 +162,46 @@ impl NostrDatabase for SQLiteDatabase {
 
     #[tracing::instrument(skip_all, level = "trace")]
     async fn save_event(&self, event: &Event) -> Result<bool, Self::Err> {
-        // Index event
-        let EventIndexResult {
-            to_store,
-            to_discard,
-        } = self.indexes.index_event(event).await;
+        // Index event in memory
+        let index_result: Option<EventIndexResult> = match &self.indexes {
+            Some(indexes) => Some(indexes.index_event(event).await),
+            None => None,
+        };
 
-        if !to_discard.is_empty() {
-            let conn = self.acquire().await?;
-            let to_discard: Vec<EventId> = to_discard.into_iter().collect();
-            conn.interact(move |conn| {
-                for chunk in to_discard.chunks(BATCH_SIZE) {
-                    let delete_query = format!(
-                        "DELETE FROM events WHERE {};",
-                        chunk
-                            .iter()
-                            .map(|id| format!("event_id = '{id}'"))
-                            .collect::<Vec<_>>()
-                            .join(" OR ")
-                    );
-                    conn.execute(&delete_query, [])?;
-                }
+        // Acquire FlatBuffers Builder and encode
+        let value: Vec<u8> = {
+            let mut fbb = self.fbb.write().await;
+            event.encode(&mut fbb).to_vec()
+        };
 
-                Ok::<(), Error>(())
-            })
-            .await??;
-        }
+        // Save event
+        let event: Event = event.clone();
+        let conn = self.acquire().await?;
+        conn.interact(move |conn| {
+            let tx = conn.transaction()?;
 
-        if to_store {
-            // Acquire FlatBuffers Builder
-            let mut fbb = self.fbb.write().await;
+            // Use the in-memory index result or index using the on-disk indexes
+            let EventIndexResult {
+                to_store,
+                to_discard,
+            } = match index_result {
+                Some(res) => res,
+                None => query::index_event(&tx, &event)?,
+            };
 
-            // Encode
-            let event_id: EventId = event.id();
-            let value: Vec<u8> = event.encode(&mut fbb).to_vec();
+            if !to_discard.is_empty() {
+                query::discard_events(&tx, &to_discard)?;
+            }
 
-            // Save event
-            let conn = self.acquire().await?;
-            conn.interact(move |conn| {
-                let mut stmt = conn.prepare_cached(
-                    "INSERT OR IGNORE INTO events (event_id, event) VALUES (?, ?);",
-                )?;
-                stmt.execute((event_id.to_hex(), value))
-            })
-            .await??;
+            if to_store {
+                query::insert_event(&tx, &event, value)?;
+            }
 
-            Ok(true)
-        } else {
-            Ok(false)
-        }
+            tx.commit()?;
+
+            Ok(to_store)
+        })
+        .await?
     }
 
     #[tracing::instrument(skip_all, level = "trace")]
@@ -184,15 +213,17 @@ impl NostrDatabase for SQLiteDatabase {
         let mut fbb = self.fbb.write().await;
 
         // Events to store
-        let events = self.indexes.bulk_import(events).await;
+        let (events, indexed): (BTreeSet<Event>, bool) = match &self.indexes {
+            Some(indexes) => (indexes.bulk_import(events).await, true),
+            None => (events, false),
+        };
 
         // Encode
-        let events: Vec<(EventId, Vec<u8>)> = events
+        let events: Vec<(Event, Vec<u8>)> = events
             .into_iter()
             .map(move |e| {
-                let event_id: EventId = e.id();
                 let value: Vec<u8> = e.encode(&mut fbb).to_vec();
-                (event_id, value)
+                (e, value)
             })
             .collect();
 
@@ -200,14 +231,28 @@ impl NostrDatabase for SQLiteDatabase {
         conn.interact(move |conn| {
             let tx = conn.transaction()?;
 
-            for (event_id, value) in events.into_iter() {
-                tx.execute(
-                    "INSERT OR IGNORE INTO events (event_id, event) VALUES (?, ?);",
-                    (event_id.to_hex(), value),
-                )?;
+            for (event, value) in events.into_iter() {
+                if indexed {
+                    query::insert_event(&tx, &event, value)?;
+                } else {
+                    let EventIndexResult {
+                        to_store,
+                        to_discard,
+                    } = query::index_event(&tx, &event)?;
+
+                    if !to_discard.is_empty() {
+                        query::discard_events(&tx, &to_discard)?;
+                    }
+
+                    if to_store {
+                        query::insert_event(&tx, &event, value)?;
+                    }
+                }
             }
 
-            tx.commit()
+            tx.commit()?;
+
+            Ok::<(), Error>(())
         })
         .await??;
 
@@ -215,24 +260,19 @@ impl NostrDatabase for SQLiteDatabase {
     }
 
     async fn has_event_already_been_saved(&self, event_id: &EventId) -> Result<bool, Self::Err> {
-        if self.indexes.has_event_id_been_deleted(event_id).await {
-            Ok(true)
-        } else {
-            let conn = self.acquire().await?;
-            let event_id: String = event_id.to_hex();
-            conn.interact(move |conn| {
-                let mut stmt = conn.prepare_cached(
-                    "SELECT EXISTS(SELECT 1 FROM events WHERE event_id = ? LIMIT 1);",
-                )?;
-                let mut rows = stmt.query([event_id])?;
-                let exists: u8 = match rows.next()? {
-                    Some(row) => row.get(0)?,
-                    None => 0,
-                };
-                Ok(exists == 1)
-            })
-            .await?
+        if let Some(indexes) = &self.indexes {
+            if indexes.has_event_id_been_deleted(event_id).await {
+                return Ok(true);
+            }
         }
+
+        let conn = self.acquire().await?;
+        let event_id: EventId = *event_id;
+        conn.interact(move |conn| {
+            Ok(query::has_event(conn, &event_id)?
+                || query::has_event_id_been_deleted(conn, &event_id)?)
+        })
+        .await?
     }
 
     async fn has_event_already_been_seen(&self, event_id: &EventId) -> Result<bool, Self::Err> {
@@ -253,7 +293,15 @@ impl NostrDatabase for SQLiteDatabase {
     }
 
     async fn has_event_id_been_deleted(&self, event_id: &EventId) -> Result<bool, Self::Err> {
-        Ok(self.indexes.has_event_id_been_deleted(event_id).await)
+        match &self.indexes {
+            Some(indexes) => Ok(indexes.has_event_id_been_deleted(event_id).await),
+            None => {
+                let conn = self.acquire().await?;
+                let event_id: EventId = *event_id;
+                conn.interact(move |conn| query::has_event_id_been_deleted(conn, &event_id))
+                    .await?
+            }
+        }
     }
 
     async fn has_coordinate_been_deleted(
@@ -261,10 +309,19 @@ impl NostrDatabase for SQLiteDatabase {
         coordinate: &Coordinate,
         timestamp: Timestamp,
     ) -> Result<bool, Self::Err> {
-        Ok(self
-            .indexes
-            .has_coordinate_been_deleted(coordinate, timestamp)
-            .await)
+        match &self.indexes {
+            Some(indexes) => Ok(indexes
+                .has_coordinate_been_deleted(coordinate, timestamp)
+                .await),
+            None => {
+                let conn = self.acquire().await?;
+                let coordinate: Coordinate = coordinate.clone();
+                conn.interact(move |conn| {
+                    query::has_coordinate_been_deleted(conn, &coordinate, timestamp)
+                })
+                .await?
+            }
+        }
     }
 
     async fn event_id_seen(&self, event_id: EventId, relay_url: Url) -> Result<(), Self::Err> {
@@ -315,33 +372,29 @@ impl NostrDatabase for SQLiteDatabase {
 
     #[tracing::instrument(skip_all, level = "trace")]
     async fn count(&self, filters: Vec<Filter>) -> Result<usize, Self::Err> {
-        Ok(self.indexes.count(filters).await)
+        match &self.indexes {
+            Some(indexes) => Ok(indexes.count(filters).await),
+            None => {
+                let conn = self.acquire().await?;
+                conn.interact(move |conn| query::count(conn, filters))
+                    .await?
+            }
+        }
     }
 
     #[tracing::instrument(skip_all, level = "trace")]
     async fn query(&self, filters: Vec<Filter>, order: Order) -> Result<Vec<Event>, Self::Err> {
-        let conn = self.acquire().await?;
-        let ids: Vec<EventId> = self.indexes.query(filters, order).await;
-        conn.interact(move |conn| {
-            let mut events = Vec::with_capacity(ids.len());
-            for chunk in ids.chunks(BATCH_SIZE) {
-                let mut stmt = conn.prepare_cached(&format!(
-                    "SELECT event FROM events WHERE {};",
-                    chunk
-                        .iter()
-                        .map(|id| format!("event_id = '{id}'"))
-                        .collect::<Vec<_>>()
-                        .join(" OR ")
-                ))?;
-                let mut rows = stmt.query([])?;
-                while let Ok(Some(row)) = rows.next() {
-                    let buf: Vec<u8> = row.get(0)?;
-                    events.push(Event::decode(&buf)?);
-                }
+        match &self.indexes {
+            Some(indexes) => {
+                let ids: Vec<EventId> = indexes.query(filters, order).await;
+                self.fetch_events(ids).await
             }
-            Ok(events)
-        })
-        .await?
+            None => {
+                let conn = self.acquire().await?;
+                conn.interact(move |conn| query::query(conn, filters, order))
+                    .await?
+            }
+        }
     }
 
     async fn event_ids_by_filters(
@@ -349,45 +402,120 @@ impl NostrDatabase for SQLiteDatabase {
         filters: Vec<Filter>,
         order: Order,
     ) -> Result<Vec<EventId>, Self::Err> {
-        Ok(self.indexes.query(filters, order).await)
+        match &self.indexes {
+            Some(indexes) => Ok(indexes.query(filters, order).await),
+            None => {
+                let conn = self.acquire().await?;
+                conn.interact(move |conn| query::event_ids(conn, filters, order))
+                    .await?
+            }
+        }
     }
 
     async fn negentropy_items(
         &self,
         filter: Filter,
     ) -> Result<Vec<(EventId, Timestamp)>, Self::Err> {
-        Ok(self.indexes.negentropy_items(filter).await)
+        match &self.indexes {
+            Some(indexes) => Ok(indexes.negentropy_items(filter).await),
+            None => {
+                let conn = self.acquire().await?;
+                conn.interact(move |conn| query::negentropy_items(conn, filter))
+                    .await?
+            }
+        }
     }
 
-    async fn delete(&self, filter: Filter) -> Result<(), Self::Err> {
-        match self.indexes.delete(filter).await {
-            Some(ids) => {
-                let conn = self.acquire().await?;
-                let ids: Vec<EventId> = ids.into_iter().collect();
-                conn.interact(move |conn| {
-                    for chunk in ids.chunks(BATCH_SIZE) {
-                        let delete_query = format!(
-                            "DELETE FROM events WHERE {};",
-                            chunk
-                                .iter()
-                                .map(|id| format!("event_id = '{id}'"))
-                                .collect::<Vec<_>>()
-                                .join(" OR ")
-                        );
-                        conn.execute(&delete_query, [])?;
-                    }
+    async fn outbox_add(&self, event: &Event, relays: HashSet<Url>) -> Result<(), Self::Err> {
+        // Acquire FlatBuffers Builder and encode
+        let value: Vec<u8> = {
+            let mut fbb = self.fbb.write().await;
+            event.encode(&mut fbb).to_vec()
+        };
 
-                    Ok::<(), Error>(())
-                })
-                .await??;
+        let event_id: String = event.id().to_hex();
+        let created_at: i64 = event.created_at().as_u64() as i64;
+        let conn = self.acquire().await?;
+        conn.interact(move |conn| {
+            let tx = conn.transaction()?;
+            {
+                let mut stmt = tx.prepare_cached(
+                    "INSERT OR IGNORE INTO outbox (relay_url, event_id, event, created_at) VALUES (?, ?, ?, ?);",
+                )?;
+                for relay_url in relays.into_iter() {
+                    stmt.execute((relay_url.to_string(), &event_id, &value, created_at))?;
+                }
+            }
+            tx.commit()?;
+            Ok::<(), Error>(())
+        })
+        .await??;
+        Ok(())
+    }
+
+    async fn outbox_remove(&self, event_id: EventId, relay_url: Url) -> Result<(), Self::Err> {
+        let conn = self.acquire().await?;
+        conn.interact(move |conn| {
+            let mut stmt =
+                conn.prepare_cached("DELETE FROM outbox WHERE relay_url = ? AND event_id = ?;")?;
+            stmt.execute((relay_url.to_string(), event_id.to_hex()))
+        })
+        .await??;
+        Ok(())
+    }
+
+    async fn outbox(&self, relay_url: Url) -> Result<Vec<Event>, Self::Err> {
+        let conn = self.acquire().await?;
+        conn.interact(move |conn| {
+            let mut stmt = conn.prepare_cached(
+                "SELECT event FROM outbox WHERE relay_url = ? ORDER BY created_at ASC;",
+            )?;
+            let mut rows = stmt.query([relay_url.to_string()])?;
+            let mut events = Vec::new();
+            while let Some(row) = rows.next()? {
+                let buf: Vec<u8> = row.get(0)?;
+                events.push(Event::decode(&buf)?);
             }
+            Ok(events)
+        })
+        .await?
+    }
+
+    async fn delete(&self, filter: Filter) -> Result<(), Self::Err> {
+        // Get IDs of the events to delete (`None` means all events)
+        let ids: Option<Vec<EventId>> = match &self.indexes {
+            Some(indexes) => indexes
+                .delete(filter)
+                .await
+                .map(|ids| ids.into_iter().collect()),
+            None if filter.is_empty() => None,
             None => {
                 let conn = self.acquire().await?;
-                conn.interact(move |conn| conn.execute("DELETE FROM events;", []))
-                    .await??;
+                Some(
+                    conn.interact(move |conn| query::event_ids(conn, vec![filter], Order::Desc))
+                        .await??,
+                )
             }
         };
 
+        let conn = self.acquire().await?;
+        conn.interact(move |conn| {
+            let tx = conn.transaction()?;
+            match ids {
+                Some(ids) => query::discard_events(&tx, &ids)?,
+                None => {
+                    tx.execute("DELETE FROM events_fts;", [])?;
+                    tx.execute("DELETE FROM event_tags;", [])?;
+                    tx.execute("DELETE FROM events;", [])?;
+                    tx.execute("DELETE FROM deleted_ids;", [])?;
+                    tx.execute("DELETE FROM deleted_coordinates;", [])?;
+                }
+            }
+            tx.commit()?;
+            Ok::<(), Error>(())
+        })
+        .await??;
+
         Ok(())
     }
 
@@ -409,8 +537,315 @@ impl NostrDatabase for SQLiteDatabase {
 
         migration::run(&conn).await?;
 
-        self.indexes.clear().await;
+        if let Some(indexes) = &self.indexes {
+            indexes.clear().await;
+        }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use nostr::{EventBuilder, Keys, Kind, Tag};
+    use tempfile::TempDir;
+
+    use super::*;
+
+    async fn open(dir: &TempDir, in_memory_indexes: bool) -> SQLiteDatabase {
+        let opts = SQLiteDatabaseOptions::new().in_memory_indexes(in_memory_indexes);
+        SQLiteDatabase::open_with_opts(dir.path().join("nostr.db"), opts)
+            .await
+            .unwrap()
+    }
+
+    fn note(keys: &Keys, content: &str, created_at: u64, tags: Vec<Tag>) -> Event {
+        EventBuilder::text_note(content, tags)
+            .custom_created_at(Timestamp::from(created_at))
+            .to_event(keys)
+            .unwrap()
+    }
+
+    fn ids(events: Vec<Event>) -> Vec<EventId> {
+        events.into_iter().map(|e| e.id()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_query() {
+        let dir = TempDir::new().unwrap();
+        let db = open(&dir, false).await;
+
+        let alice = Keys::generate();
+        let bob = Keys::generate();
+        let a = note(
+            &alice,
+            "Hello world",
+            1_000,
+            vec![Tag::Hashtag(String::from("nostr"))],
+        );
+        let b = note(&alice, "Second note", 2_000, vec![]);
+        let c = note(
+            &bob,
+            "Bob note",
+            3_000,
+            vec![Tag::public_key(alice.public_key())],
+        );
+        for event in [&a, &b, &c] {
+            assert!(db.save_event(event).await.unwrap());
+        }
+
+        // Already saved
+        assert!(!db.save_event(&a).await.unwrap());
+
+        let all = Filter::new().kind(Kind::TextNote);
+        assert_eq!(
+            ids(db.query(vec![all.clone()], Order::Desc).await.unwrap()),
+            vec![c.id(), b.id(), a.id()]
+        );
+        assert_eq!(
+            ids(db.query(vec![all.clone()], Order::Asc).await.unwrap()),
+            vec![a.id(), b.id(), c.id()]
+        );
+        assert_eq!(db.count(vec![all.clone()]).await.unwrap(), 3);
+
+        // Author, limit, since and until
+        let filter = Filter::new().author(alice.public_key());
+        assert_eq!(db.count(vec![filter.clone()]).await.unwrap(), 2);
+        let filter = all.clone().limit(1);
+        assert_eq!(
+            ids(db.query(vec![filter], Order::Desc).await.unwrap()),
+            vec![c.id()]
+        );
+        let filter = all
+            .clone()
+            .since(Timestamp::from(1_500))
+            .until(Timestamp::from(2_500));
+        assert_eq!(
+            ids(db.query(vec![filter], Order::Desc).await.unwrap()),
+            vec![b.id()]
+        );
+
+        // IDs and tags
+        let filter = Filter::new().id(b.id());
+        assert_eq!(
+            ids(db.query(vec![filter], Order::Desc).await.unwrap()),
+            vec![b.id()]
+        );
+        let filter = Filter::new().hashtag("nostr");
+        assert_eq!(
+            ids(db.query(vec![filter], Order::Desc).await.unwrap()),
+            vec![a.id()]
+        );
+        let filter = Filter::new().pubkey(alice.public_key());
+        assert_eq!(
+            ids(db.query(vec![filter], Order::Desc).await.unwrap()),
+            vec![c.id()]
+        );
+
+        // Many filters
+        let filters = vec![
+            Filter::new().id(a.id()),
+            Filter::new().author(bob.public_key()),
+        ];
+        assert_eq!(
+            ids(db.query(filters, Order::Desc).await.unwrap()),
+            vec![c.id(), a.id()]
+        );
+
+        // Full-text search
+        let filter = Filter::new().search("world");
+        assert_eq!(
+            ids(db.query(vec![filter], Order::Desc).await.unwrap()),
+            vec![a.id()]
+        );
+
+        // Event by ID
+        assert_eq!(db.event_by_id(c.id()).await.unwrap(), c);
+    }
+
+    #[tokio::test]
+    async fn test_replaceable() {
+        let dir = TempDir::new().unwrap();
+        let db = open(&dir, false).await;
+
+        let keys = Keys::generate();
+        let old = EventBuilder::new(Kind::Metadata, "{}", [])
+            .custom_created_at(Timestamp::from(1_000))
+            .to_event(&keys)
+            .unwrap();
+        let new = EventBuilder::new(Kind::Metadata, "{}", [])
+            .custom_created_at(Timestamp::from(2_000))
+            .to_event(&keys)
+            .unwrap();
+        assert!(db.save_event(&new).await.unwrap());
+        assert!(!db.save_event(&old).await.unwrap());
+
+        let filter = Filter::new().kind(Kind::Metadata);
+        assert_eq!(
+            ids(db.query(vec![filter], Order::Desc).await.unwrap()),
+            vec![new.id()]
+        );
+
+        // Parameterized replaceable
+        let kind = Kind::ParameterizedReplaceable(30000);
+        let old = EventBuilder::new(kind, "", [Tag::Identifier(String::from("a"))])
+            .custom_created_at(Timestamp::from(1_000))
+            .to_event(&keys)
+            .unwrap();
+        let other = EventBuilder::new(kind, "", [Tag::Identifier(String::from("b"))])
+            .custom_created_at(Timestamp::from(1_000))
+            .to_event(&keys)
+            .unwrap();
+        let new = EventBuilder::new(kind, "", [Tag::Identifier(String::from("a"))])
+            .custom_created_at(Timestamp::from(2_000))
+            .to_event(&keys)
+            .unwrap();
+        for event in [&old, &other, &new] {
+            assert!(db.save_event(event).await.unwrap());
+        }
+
+        let filter = Filter::new().kind(kind);
+        assert_eq!(
+            ids(db.query(vec![filter], Order::Desc).await.unwrap()),
+            vec![new.id(), other.id()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_deletion() {
+        let dir = TempDir::new().unwrap();
+        let db = open(&dir, false).await;
+
+        let keys = Keys::generate();
+        let event = note(&keys, "To delete", 1_000, vec![]);
+        assert!(db.save_event(&event).await.unwrap());
+
+        let deletion = EventBuilder::new(Kind::EventDeletion, "", [Tag::event(event.id())])
+            .custom_created_at(Timestamp::from(2_000))
+            .to_event(&keys)
+            .unwrap();
+        assert!(db.save_event(&deletion).await.unwrap());
+
+        assert!(db.has_event_id_been_deleted(&event.id()).await.unwrap());
+        assert!(db.has_event_already_been_saved(&event.id()).await.unwrap());
+        assert!(!db.save_event(&event).await.unwrap());
+        let filter = Filter::new().kind(Kind::TextNote);
+        assert_eq!(db.count(vec![filter]).await.unwrap(), 0);
+
+        // Coordinate
+        let kind = Kind::ParameterizedReplaceable(30000);
+        let coordinate = Coordinate::new(kind, keys.public_key()).identifier("a");
+        let deletion = EventBuilder::new(
+            Kind::EventDeletion,
+            "",
+            [Tag::A {
+                coordinate: coordinate.clone(),
+                relay_url: None,
+            }],
+        )
+        .custom_created_at(Timestamp::from(2_000))
+        .to_event(&keys)
+        .unwrap();
+        assert!(db.save_event(&deletion).await.unwrap());
+        assert!(db
+            .has_coordinate_been_deleted(&coordinate, Timestamp::from(1_500))
+            .await
+            .unwrap());
+        assert!(!db
+            .has_coordinate_been_deleted(&coordinate, Timestamp::from(2_500))
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_delete() {
+        let dir = TempDir::new().unwrap();
+        let db = open(&dir, false).await;
+
+        let alice = Keys::generate();
+        let bob = Keys::generate();
+        let a = note(&alice, "Alice", 1_000, vec![]);
+        let b = note(&bob, "Bob", 1_000, vec![]);
+        db.bulk_import([a.clone(), b.clone()].into_iter().collect())
+            .await
+            .unwrap();
+
+        db.delete(Filter::new().author(alice.public_key()))
+            .await
+            .unwrap();
+        let filter = Filter::new().kind(Kind::TextNote);
+        assert_eq!(
+            ids(db.query(vec![filter], Order::Desc).await.unwrap()),
+            vec![b.id()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delete_all() {
+        for in_memory_indexes in [false, true] {
+            let dir = TempDir::new().unwrap();
+            let db = open(&dir, in_memory_indexes).await;
+
+            let keys = Keys::generate();
+            let event = note(&keys, "To delete", 1_000, vec![]);
+            let deletion = EventBuilder::new(Kind::EventDeletion, "", [Tag::event(event.id())])
+                .custom_created_at(Timestamp::from(2_000))
+                .to_event(&keys)
+                .unwrap();
+            let coordinate =
+                Coordinate::new(Kind::ParameterizedReplaceable(30000), keys.public_key())
+                    .identifier("a");
+            let deletion_coordinate = EventBuilder::new(
+                Kind::EventDeletion,
+                "",
+                [Tag::A {
+                    coordinate: coordinate.clone(),
+                    relay_url: None,
+                }],
+            )
+            .custom_created_at(Timestamp::from(2_000))
+            .to_event(&keys)
+            .unwrap();
+            for event in [&event, &deletion, &deletion_coordinate] {
+                assert!(db.save_event(event).await.unwrap());
+            }
+            assert!(db.has_event_id_been_deleted(&event.id()).await.unwrap());
+
+            db.delete(Filter::new()).await.unwrap();
+
+            assert_eq!(db.count(vec![Filter::new()]).await.unwrap(), 0);
+            assert!(!db.has_event_id_been_deleted(&event.id()).await.unwrap());
+            assert!(!db
+                .has_coordinate_been_deleted(&coordinate, Timestamp::from(1_500))
+                .await
+                .unwrap());
+
+            // Still cleared after reopening
+            drop(db);
+            let db = open(&dir, in_memory_indexes).await;
+            assert!(!db.has_event_id_been_deleted(&event.id()).await.unwrap());
+            assert!(db.save_event(&event).await.unwrap());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_indexes() {
+        let dir = TempDir::new().unwrap();
+        let keys = Keys::generate();
+        let a = note(&keys, "A", 1_000, vec![]);
+        let b = note(&keys, "B", 2_000, vec![]);
+
+        let db = open(&dir, false).await;
+        db.save_event(&a).await.unwrap();
+        db.save_event(&b).await.unwrap();
+        drop(db);
+
+        // Indexes built from the stored events
+        let db = open(&dir, true).await;
+        let filter = Filter::new().author(keys.public_key());
+        assert_eq!(
+            db.query(vec![filter], Order::Desc).await.unwrap(),
+            vec![b, a]
+        );
+    }
+}
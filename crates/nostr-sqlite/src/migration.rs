@@ -5,13 +5,16 @@
 use std::cmp::Ordering;
 
 use deadpool_sqlite::Object;
+use nostr::Event;
+use nostr_database::FlatBufferDecode;
 use rusqlite::Connection;
 use thiserror::Error;
 
 use super::Error;
+use crate::query;
 
 /// Latest database version
-pub const DB_VERSION: usize = 1;
+pub const DB_VERSION: usize = 4;
 
 /// Startup DB Pragmas
 pub const STARTUP_SQL: &str = r##"
@@ -55,18 +58,18 @@ pub(crate) async fn run(conn: &Object) -> Result<(), Error> {
 
                 // for initialized but out-of-date schemas, proceed to
                 // upgrade sequentially until we are current.
-                // if curr_version == 1 {
-                // curr_version = mig_1_to_2(conn)?;
-                // }
-                //
-                // if curr_version == 2 {
-                // curr_version = mig_2_to_3(conn)?;
-                // }
-                //
-                // if curr_version == 3 {
-                // curr_version = mig_3_to_4(conn)?;
-                // }
-                //
+                if curr_version == 1 {
+                    curr_version = mig_1_to_2(conn)?;
+                }
+
+                if curr_version == 2 {
+                    curr_version = mig_2_to_3(conn)?;
+                }
+
+                if curr_version == 3 {
+                    curr_version = mig_3_to_4(conn)?;
+                }
+
                 // if curr_version == 4 {
                 // curr_version = mig_4_to_5(conn)?;
                 // }
@@ -109,8 +112,128 @@ fn mig_init(conn: &mut Connection) -> Result<usize, Error> {
     Ok(1)
 }
 
-// fn mig_1_to_2(conn: &mut Connection) -> Result<usize, Error> {
-// conn.execute_batch(include_str!("../../migrations/002_notifications.sql"))?;
-// tracing::info!("database schema upgraded v1 -> v2");
-// Ok(2)
-// }
+fn mig_1_to_2(conn: &mut Connection) -> Result<usize, Error> {
+    let tx = conn.transaction()?;
+    tx.execute_batch(include_str!("../migrations/002_indexes.sql"))?;
+
+    // Populate indexes of the already stored events
+    {
+        let mut stmt = tx.prepare("SELECT event FROM events;")?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let buf: Vec<u8> = row.get(0)?;
+            let event: Event = Event::decode(&buf)?;
+            let event_id: String = event.id().to_hex();
+            let created_at: i64 = event.created_at().as_u64() as i64;
+            tx.execute(
+                "UPDATE events SET pubkey = ?, created_at = ?, kind = ?, identifier = ? WHERE event_id = ?;",
+                (
+                    event.author_ref().to_hex(),
+                    created_at,
+                    event.kind().as_u64() as i64,
+                    event.identifier(),
+                    &event_id,
+                ),
+            )?;
+            query::insert_tags(&tx, &event_id, &event, created_at)?;
+        }
+    }
+
+    tx.commit()?;
+    tracing::info!("database schema upgraded v1 -> v2");
+    Ok(2)
+}
+
+fn mig_2_to_3(conn: &mut Connection) -> Result<usize, Error> {
+    let tx = conn.transaction()?;
+    tx.execute_batch(include_str!("../migrations/003_search.sql"))?;
+
+    // Populate search index of the already stored events
+    {
+        let mut stmt = tx.prepare("SELECT event FROM events;")?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let buf: Vec<u8> = row.get(0)?;
+            let event: Event = Event::decode(&buf)?;
+            query::insert_search(&tx, &event.id().to_hex(), &event)?;
+        }
+    }
+
+    tx.commit()?;
+    tracing::info!("database schema upgraded v2 -> v3");
+    Ok(3)
+}
+
+fn mig_3_to_4(conn: &mut Connection) -> Result<usize, Error> {
+    conn.execute_batch(include_str!("../migrations/004_outbox.sql"))?;
+    tracing::info!("database schema upgraded v3 -> v4");
+    Ok(4)
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::{EventBuilder, Filter, Keys, Kind, Tag};
+    use nostr_database::{FlatBufferBuilder, FlatBufferEncode, NostrDatabase, Order};
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::SQLiteDatabase;
+
+    #[tokio::test]
+    async fn test_migrate_from_v1() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("nostr.db");
+
+        // Database with the v1 schema and an event
+        let keys = Keys::generate();
+        let event = EventBuilder::text_note("Hello world", [Tag::Hashtag(String::from("nostr"))])
+            .to_event(&keys)
+            .unwrap();
+        {
+            let mut conn = Connection::open(&path).unwrap();
+            assert_eq!(mig_init(&mut conn).unwrap(), 1);
+            let mut fbb = FlatBufferBuilder::new();
+            conn.execute(
+                "INSERT INTO events (event_id, event) VALUES (?, ?);",
+                (event.id().to_hex(), event.encode(&mut fbb).to_vec()),
+            )
+            .unwrap();
+        }
+
+        let db = SQLiteDatabase::open(&path).await.unwrap();
+
+        // Indexes populated from the stored event
+        let filters = [
+            Filter::new().author(keys.public_key()),
+            Filter::new().kind(Kind::TextNote),
+            Filter::new().hashtag("nostr"),
+            Filter::new().search("world"),
+        ];
+        for filter in filters.into_iter() {
+            assert_eq!(
+                db.query(vec![filter], Order::Desc).await.unwrap(),
+                vec![event.clone()]
+            );
+        }
+
+        let mut conn = Connection::open(&path).unwrap();
+        assert_eq!(curr_db_version(&mut conn).unwrap(), DB_VERSION);
+    }
+
+    #[tokio::test]
+    async fn test_newer_db_version() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("nostr.db");
+        {
+            let conn = Connection::open(&path).unwrap();
+            conn.execute_batch(&format!("PRAGMA user_version = {};", DB_VERSION + 1))
+                .unwrap();
+        }
+
+        let res = SQLiteDatabase::open(&path).await;
+        assert!(matches!(
+            res,
+            Err(Error::Migration(MigrationError::NewerDbVersion { .. }))
+        ));
+    }
+}
@@ -0,0 +1,28 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! SQLite database options
+
+/// SQLite database options
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct SQLiteDatabaseOptions {
+    /// Load all events into the in-memory indexes at startup (default: false)
+    ///
+    /// Queries will be answered by the in-memory indexes instead of the on-disk ones:
+    /// faster queries, but slower startup and a RAM usage that grows with the database size.
+    pub in_memory_indexes: bool,
+}
+
+impl SQLiteDatabaseOptions {
+    /// New default database options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enable/disable in-memory indexes (default: false)
+    pub fn in_memory_indexes(mut self, enable: bool) -> Self {
+        self.in_memory_indexes = enable;
+        self
+    }
+}
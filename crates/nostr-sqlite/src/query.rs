@@ -0,0 +1,538 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! SQL queries
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use nostr::nips::nip01::Coordinate;
+use nostr::{Event, EventId, Filter, Kind, Timestamp};
+use nostr_database::search::{self, query_tokens};
+use nostr_database::{EventIndexResult, FlatBufferDecode, Order};
+use rusqlite::types::Value;
+use rusqlite::{params_from_iter, Connection, OptionalExtension, Transaction};
+
+use crate::error::Error;
+use crate::BATCH_SIZE;
+
+/// Row key: search relevance (`0` if not a search), `created_at` (desc) and event ID (asc)
+type RowKey = (i64, Reverse<u64>, String);
+
+/// Query results, sorted by [`RowKey`]
+#[derive(Default)]
+struct QueryResult {
+    rows: BTreeMap<RowKey, Option<Vec<u8>>>,
+    keys: HashMap<String, RowKey>,
+}
+
+impl QueryResult {
+    /// Insert row (if an event is matched by more filters, the best key is kept)
+    fn insert(&mut self, key: RowKey, event: Option<Vec<u8>>) {
+        if let Some(old) = self.keys.get(&key.2) {
+            if old <= &key {
+                return;
+            }
+            self.rows.remove(old);
+        }
+        self.keys.insert(key.2.clone(), key.clone());
+        self.rows.insert(key, event);
+    }
+
+    fn len(&self) -> usize {
+        self.rows.len()
+    }
+}
+
+/// Build the FTS5 `MATCH` expression of a NIP-50 search
+fn search_to_sql(query: &str) -> Option<String> {
+    let tokens: Vec<String> = query_tokens(query)
+        .into_iter()
+        .map(|token| format!("\"{}\"", token.replace('"', "\"\"")))
+        .collect();
+    if tokens.is_empty() {
+        None
+    } else {
+        Some(tokens.join(" "))
+    }
+}
+
+/// `FROM` and `WHERE` clauses of a [`Filter`]
+struct SqlFilter {
+    clauses: String,
+    params: Vec<Value>,
+    /// Full-text search: the `fts.rank` column is available
+    search: bool,
+}
+
+/// Build the `FROM` and `WHERE` clauses of a [`Filter`]
+///
+/// Return `None` if the filter can't match any event.
+fn filter_to_sql(filter: &Filter) -> Option<SqlFilter> {
+    if let (Some(since), Some(until)) = (filter.since, filter.until) {
+        if since > until {
+            return None;
+        }
+    }
+
+    let mut conditions: Vec<String> = Vec::new();
+    let mut params: Vec<Value> = Vec::new();
+
+    let (from, search): (&str, bool) = match filter.search.as_deref().and_then(search_to_sql) {
+        Some(search) => {
+            params.push(Value::Text(search));
+            ("FROM events JOIN (SELECT rowid AS fts_rowid, rank FROM events_fts WHERE events_fts MATCH ?) AS fts ON events.rowid = fts.fts_rowid", true)
+        }
+        None => ("FROM events", false),
+    };
+
+    if let Some(ids) = &filter.ids {
+        if !ids.is_empty() {
+            conditions.push(format!("event_id IN ({})", placeholders(ids.len())));
+            params.extend(ids.iter().map(|id| Value::Text(id.to_hex())));
+        }
+    }
+
+    if let Some(authors) = &filter.authors {
+        if !authors.is_empty() {
+            conditions.push(format!("pubkey IN ({})", placeholders(authors.len())));
+            params.extend(authors.iter().map(|p| Value::Text(p.to_hex())));
+        }
+    }
+
+    if let Some(kinds) = &filter.kinds {
+        if !kinds.is_empty() {
+            conditions.push(format!("kind IN ({})", placeholders(kinds.len())));
+            params.extend(kinds.iter().map(|k| Value::Integer(k.as_u64() as i64)));
+        }
+    }
+
+    if let Some(since) = filter.since {
+        conditions.push(String::from("created_at >= ?"));
+        params.push(Value::Integer(since.as_u64() as i64));
+    }
+
+    if let Some(until) = filter.until {
+        conditions.push(String::from("created_at <= ?"));
+        params.push(Value::Integer(until.as_u64() as i64));
+    }
+
+    for (tag, values) in filter.generic_tags.iter() {
+        if values.is_empty() {
+            continue;
+        }
+
+        conditions.push(format!(
+            "event_id IN (SELECT event_id FROM event_tags WHERE name = ? AND value IN ({}))",
+            placeholders(values.len())
+        ));
+        params.push(Value::Text(tag.to_string()));
+        params.extend(values.iter().map(|v| Value::Text(v.to_string())));
+    }
+
+    let clauses: String = if conditions.is_empty() {
+        from.to_string()
+    } else {
+        format!("{from} WHERE {}", conditions.join(" AND "))
+    };
+
+    Some(SqlFilter {
+        clauses,
+        params,
+        search,
+    })
+}
+
+fn placeholders(len: usize) -> String {
+    vec!["?"; len].join(",")
+}
+
+fn query_rows(
+    conn: &Connection,
+    filters: Vec<Filter>,
+    with_event: bool,
+) -> Result<QueryResult, Error> {
+    let columns: &str = if with_event {
+        "event_id, created_at, event"
+    } else {
+        "event_id, created_at"
+    };
+
+    let mut result: QueryResult = QueryResult::default();
+
+    // Empty filter match all events
+    let filters: Vec<Filter> = if filters.iter().any(|f| f.is_empty()) {
+        vec![Filter::new()]
+    } else {
+        filters
+    };
+
+    for filter in filters.into_iter() {
+        let SqlFilter {
+            clauses,
+            mut params,
+            search,
+        } = match filter_to_sql(&filter) {
+            Some(res) => res,
+            None => continue,
+        };
+
+        // Sort by relevance
+        let rank: &str = if search { "fts.rank" } else { "0" };
+
+        let limit: &str = match filter.limit {
+            Some(limit) => {
+                params.push(Value::Integer(limit as i64));
+                "LIMIT ?"
+            }
+            None => "",
+        };
+
+        let sql: String = format!(
+            "SELECT {columns}, {rank} AS relevance {clauses} ORDER BY relevance ASC, created_at DESC, event_id ASC {limit};"
+        );
+        let mut stmt = conn.prepare_cached(&sql)?;
+        let mut rows = stmt.query(params_from_iter(params.iter()))?;
+        while let Some(row) = rows.next()? {
+            let event_id: String = row.get(0)?;
+            let created_at: i64 = row.get(1)?;
+            let event: Option<Vec<u8>> = if with_event { Some(row.get(2)?) } else { None };
+            // BM25 rank: lower is better
+            let relevance: f64 = row.get(if with_event { 3 } else { 2 })?;
+            let relevance: i64 = (relevance * 1_000_000.0) as i64;
+            result.insert((relevance, Reverse(created_at as u64), event_id), event);
+        }
+    }
+
+    Ok(result)
+}
+
+fn sort<T>(iter: impl DoubleEndedIterator<Item = T>, order: Order) -> Vec<T> {
+    match order {
+        Order::Asc => iter.rev().collect(),
+        Order::Desc => iter.collect(),
+    }
+}
+
+/// Query events
+pub(crate) fn query(
+    conn: &Connection,
+    filters: Vec<Filter>,
+    order: Order,
+) -> Result<Vec<Event>, Error> {
+    let rows: QueryResult = query_rows(conn, filters, true)?;
+    let mut events: Vec<Event> = Vec::with_capacity(rows.len());
+    for buf in sort(rows.rows.into_values(), order).into_iter().flatten() {
+        events.push(Event::decode(&buf)?);
+    }
+    Ok(events)
+}
+
+/// Query event IDs
+pub(crate) fn event_ids(
+    conn: &Connection,
+    filters: Vec<Filter>,
+    order: Order,
+) -> Result<Vec<EventId>, Error> {
+    let rows: QueryResult = query_rows(conn, filters, false)?;
+    sort(rows.rows.into_keys(), order)
+        .into_iter()
+        .map(|(_, _, id)| Ok(EventId::from_hex(id)?))
+        .collect()
+}
+
+/// Count events
+pub(crate) fn count(conn: &Connection, filters: Vec<Filter>) -> Result<usize, Error> {
+    // Single filter without limit: let SQLite count
+    if let [filter] = filters.as_slice() {
+        if filter.limit.is_none() {
+            let SqlFilter {
+                clauses, params, ..
+            } = match filter_to_sql(filter) {
+                Some(res) => res,
+                None => return Ok(0),
+            };
+            let sql: String = format!("SELECT COUNT(*) {clauses};");
+            let mut stmt = conn.prepare_cached(&sql)?;
+            let count: i64 = stmt.query_row(params_from_iter(params.iter()), |row| row.get(0))?;
+            return Ok(count as usize);
+        }
+    }
+
+    Ok(query_rows(conn, filters, false)?.len())
+}
+
+/// Get negentropy items
+pub(crate) fn negentropy_items(
+    conn: &Connection,
+    filter: Filter,
+) -> Result<Vec<(EventId, Timestamp)>, Error> {
+    query_rows(conn, vec![filter], false)?
+        .rows
+        .into_keys()
+        .map(|(_, Reverse(created_at), id)| {
+            Ok((EventId::from_hex(id)?, Timestamp::from(created_at)))
+        })
+        .collect()
+}
+
+/// Check if an event is stored
+pub(crate) fn has_event(conn: &Connection, event_id: &EventId) -> Result<bool, Error> {
+    let mut stmt =
+        conn.prepare_cached("SELECT EXISTS(SELECT 1 FROM events WHERE event_id = ? LIMIT 1);")?;
+    let exists: bool = stmt.query_row([event_id.to_hex()], |row| row.get(0))?;
+    Ok(exists)
+}
+
+/// Check if an event ID has been deleted
+pub(crate) fn has_event_id_been_deleted(
+    conn: &Connection,
+    event_id: &EventId,
+) -> Result<bool, Error> {
+    let mut stmt = conn
+        .prepare_cached("SELECT EXISTS(SELECT 1 FROM deleted_ids WHERE event_id = ? LIMIT 1);")?;
+    let exists: bool = stmt.query_row([event_id.to_hex()], |row| row.get(0))?;
+    Ok(exists)
+}
+
+/// Check if a [`Coordinate`] has been deleted at or after [`Timestamp`]
+pub(crate) fn has_coordinate_been_deleted(
+    conn: &Connection,
+    coordinate: &Coordinate,
+    timestamp: Timestamp,
+) -> Result<bool, Error> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT deleted_at FROM deleted_coordinates WHERE kind = ? AND pubkey = ? AND identifier = ?;",
+    )?;
+    let deleted_at: Option<i64> = stmt
+        .query_row(
+            (
+                coordinate.kind.as_u64() as i64,
+                coordinate.public_key.to_hex(),
+                &coordinate.identifier,
+            ),
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(deleted_at.map_or(false, |t| t as u64 >= timestamp.as_u64()))
+}
+
+fn ids_by_statement<P>(tx: &Transaction, sql: &str, params: P) -> Result<Vec<String>, Error>
+where
+    P: rusqlite::Params,
+{
+    let mut stmt = tx.prepare_cached(sql)?;
+    let mut rows = stmt.query(params)?;
+    let mut ids: Vec<String> = Vec::new();
+    while let Some(row) = rows.next()? {
+        ids.push(row.get(0)?);
+    }
+    Ok(ids)
+}
+
+/// Apply replaceable, parameterized replaceable and deletion semantics
+///
+/// Mirror the behaviour of [`DatabaseIndexes`](nostr_database::DatabaseIndexes).
+pub(crate) fn index_event(tx: &Transaction, event: &Event) -> Result<EventIndexResult, Error> {
+    // Check if it's expired or ephemeral
+    if event.is_expired() || event.is_ephemeral() {
+        return Ok(EventIndexResult::default());
+    }
+
+    let event_id: EventId = event.id();
+
+    // Check if was already added or deleted
+    if has_event(tx, &event_id)? || has_event_id_been_deleted(tx, &event_id)? {
+        return Ok(EventIndexResult::default());
+    }
+
+    let pubkey: String = event.author_ref().to_hex();
+    let created_at: i64 = event.created_at().as_u64() as i64;
+    let kind: Kind = event.kind();
+
+    let mut to_discard: HashSet<EventId> = HashSet::new();
+    let mut should_insert: bool = true;
+
+    if kind.is_replaceable() {
+        let mut stmt = tx.prepare_cached(
+            "SELECT event_id, created_at FROM events WHERE pubkey = ? AND kind = ?;",
+        )?;
+        let mut rows = stmt.query((&pubkey, kind.as_u64() as i64))?;
+        while let Some(row) = rows.next()? {
+            let id: String = row.get(0)?;
+            let timestamp: i64 = row.get(1)?;
+            if timestamp > created_at {
+                should_insert = false;
+            } else {
+                to_discard.insert(EventId::from_hex(id)?);
+            }
+        }
+    } else if kind.is_parameterized_replaceable() {
+        match event.identifier() {
+            Some(identifier) => {
+                let coordinate: Coordinate =
+                    Coordinate::new(kind, event.author()).identifier(identifier);
+                if has_coordinate_been_deleted(tx, &coordinate, event.created_at())? {
+                    return Ok(EventIndexResult::default());
+                }
+
+                let mut stmt = tx.prepare_cached(
+                    "SELECT event_id, created_at FROM events WHERE kind = ? AND pubkey = ? AND identifier = ?;",
+                )?;
+                let mut rows = stmt.query((kind.as_u64() as i64, &pubkey, identifier))?;
+                while let Some(row) = rows.next()? {
+                    let id: String = row.get(0)?;
+                    let timestamp: i64 = row.get(1)?;
+                    if timestamp > created_at {
+                        should_insert = false;
+                    } else {
+                        to_discard.insert(EventId::from_hex(id)?);
+                    }
+                }
+            }
+            None => should_insert = false,
+        }
+    } else if kind == Kind::EventDeletion {
+        // Check `e` tags
+        for id in event.event_ids() {
+            let ids = ids_by_statement(
+                tx,
+                "SELECT event_id FROM events WHERE event_id = ? AND pubkey = ? AND created_at <= ?;",
+                (id.to_hex(), &pubkey, created_at),
+            )?;
+            for id in ids.into_iter() {
+                to_discard.insert(EventId::from_hex(id)?);
+            }
+        }
+
+        // Check `a` tags
+        for coordinate in event.coordinates() {
+            if coordinate.public_key == event.author() {
+                // Save deleted coordinate at certain timestamp
+                tx.execute(
+                    "INSERT INTO deleted_coordinates (kind, pubkey, identifier, deleted_at) VALUES (?, ?, ?, ?)
+                    ON CONFLICT(kind, pubkey, identifier) DO UPDATE SET deleted_at = MAX(deleted_at, excluded.deleted_at);",
+                    (
+                        coordinate.kind.as_u64() as i64,
+                        &pubkey,
+                        &coordinate.identifier,
+                        created_at,
+                    ),
+                )?;
+
+                let ids = if coordinate.identifier.is_empty() {
+                    ids_by_statement(
+                        tx,
+                        "SELECT event_id FROM events WHERE kind = ? AND pubkey = ? AND created_at <= ?;",
+                        (coordinate.kind.as_u64() as i64, &pubkey, created_at),
+                    )?
+                } else {
+                    ids_by_statement(
+                        tx,
+                        "SELECT event_id FROM events WHERE kind = ? AND pubkey = ? AND identifier = ? AND created_at <= ?;",
+                        (
+                            coordinate.kind.as_u64() as i64,
+                            &pubkey,
+                            &coordinate.identifier,
+                            created_at,
+                        ),
+                    )?
+                };
+                for id in ids.into_iter() {
+                    to_discard.insert(EventId::from_hex(id)?);
+                }
+            }
+        }
+    }
+
+    Ok(EventIndexResult {
+        to_store: should_insert,
+        to_discard,
+    })
+}
+
+/// Remove events (and their tags) and mark them as deleted
+pub(crate) fn discard_events<'a, I>(tx: &Transaction, ids: I) -> Result<(), Error>
+where
+    I: IntoIterator<Item = &'a EventId>,
+{
+    let ids: Vec<Value> = ids.into_iter().map(|id| Value::Text(id.to_hex())).collect();
+    for chunk in ids.chunks(BATCH_SIZE) {
+        let placeholders: String = placeholders(chunk.len());
+        tx.execute(
+            &format!("DELETE FROM events_fts WHERE rowid IN (SELECT rowid FROM events WHERE event_id IN ({placeholders}));"),
+            params_from_iter(chunk.iter()),
+        )?;
+        tx.execute(
+            &format!("DELETE FROM event_tags WHERE event_id IN ({placeholders});"),
+            params_from_iter(chunk.iter()),
+        )?;
+        tx.execute(
+            &format!("DELETE FROM events WHERE event_id IN ({placeholders});"),
+            params_from_iter(chunk.iter()),
+        )?;
+    }
+
+    let mut stmt = tx.prepare_cached("INSERT OR IGNORE INTO deleted_ids (event_id) VALUES (?);")?;
+    for id in ids.iter() {
+        stmt.execute([id])?;
+    }
+
+    Ok(())
+}
+
+/// Insert event and its single-letter tags
+pub(crate) fn insert_event(tx: &Transaction, event: &Event, value: Vec<u8>) -> Result<(), Error> {
+    let event_id: String = event.id().to_hex();
+    let created_at: i64 = event.created_at().as_u64() as i64;
+
+    let mut stmt = tx.prepare_cached(
+        "INSERT OR IGNORE INTO events (event_id, event, pubkey, created_at, kind, identifier) VALUES (?, ?, ?, ?, ?, ?);",
+    )?;
+    let inserted: usize = stmt.execute((
+        &event_id,
+        value,
+        event.author_ref().to_hex(),
+        created_at,
+        event.kind().as_u64() as i64,
+        event.identifier(),
+    ))?;
+
+    if inserted > 0 {
+        insert_tags(tx, &event_id, event, created_at)?;
+        insert_search(tx, &event_id, event)?;
+    }
+
+    Ok(())
+}
+
+/// Insert single-letter tags
+pub(crate) fn insert_tags(
+    conn: &Connection,
+    event_id: &str,
+    event: &Event,
+    created_at: i64,
+) -> Result<(), Error> {
+    let mut stmt = conn.prepare_cached(
+        "INSERT OR IGNORE INTO event_tags (event_id, name, value, created_at) VALUES (?, ?, ?, ?);",
+    )?;
+    for (name, value) in event
+        .iter_tags()
+        .filter_map(|t| Some((t.single_letter_tag()?, t.content()?)))
+    {
+        stmt.execute((event_id, name.to_string(), value.to_string(), created_at))?;
+    }
+    Ok(())
+}
+
+/// Insert the searchable text into the full-text search index
+pub(crate) fn insert_search(conn: &Connection, event_id: &str, event: &Event) -> Result<(), Error> {
+    if let Some(text) = search::searchable_text(event.kind(), event.content()) {
+        let mut stmt = conn.prepare_cached(
+            "INSERT INTO events_fts (rowid, content) SELECT rowid, ? FROM events WHERE event_id = ?;",
+        )?;
+        stmt.execute((text.as_ref(), event_id))?;
+    }
+    Ok(())
+}
@@ -23,11 +23,15 @@ use crate::nips::nip01::Coordinate;
 #[cfg(feature = "nip04")]
 use crate::nips::nip04;
 use crate::nips::nip15::{ProductData, StallData};
+use crate::nips::nip29::{GroupAdmins, GroupMembers, GroupMetadata, GroupModeration};
 #[cfg(all(feature = "std", feature = "nip44"))]
 use crate::nips::nip44::{self, Version};
 #[cfg(all(feature = "std", feature = "nip46"))]
 use crate::nips::nip46::Message as NostrConnectMessage;
 use crate::nips::nip51::{ArticlesCuration, Bookmarks, Emojis, Interests, MuteList};
+use crate::nips::nip52::{
+    Calendar, CalendarEventRsvp, DateBasedCalendarEvent, TimeBasedCalendarEvent,
+};
 use crate::nips::nip53::LiveEvent;
 #[cfg(feature = "nip57")]
 use crate::nips::nip57::ZapRequestData;
@@ -744,6 +748,118 @@ impl EventBuilder {
         ))
     }
 
+    /// Group metadata (NIP29)
+    ///
+    /// Signed by the relay of the group.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/29.md>
+    pub fn group_metadata(metadata: GroupMetadata) -> Self {
+        let tags: Vec<Tag> = metadata.into();
+        Self::new(Kind::GroupMetadata, "", tags)
+    }
+
+    /// Group admins (NIP29)
+    ///
+    /// Signed by the relay of the group.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/29.md>
+    pub fn group_admins(admins: GroupAdmins) -> Self {
+        let tags: Vec<Tag> = admins.into();
+        Self::new(Kind::GroupAdmins, "", tags)
+    }
+
+    /// Group members (NIP29)
+    ///
+    /// Signed by the relay of the group.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/29.md>
+    pub fn group_members(members: GroupMembers) -> Self {
+        let tags: Vec<Tag> = members.into();
+        Self::new(Kind::GroupMembers, "", tags)
+    }
+
+    /// Request to join a group (NIP29)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/29.md>
+    pub fn group_join_request<S>(group_id: S, reason: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::new(
+            Kind::GroupJoinRequest,
+            reason,
+            [Tag::Group(group_id.into())],
+        )
+    }
+
+    /// Request to leave a group (NIP29)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/29.md>
+    pub fn group_leave_request<S>(group_id: S, reason: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::new(
+            Kind::GroupLeaveRequest,
+            reason,
+            [Tag::Group(group_id.into())],
+        )
+    }
+
+    /// Group moderation event (NIP29)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/29.md>
+    pub fn group_moderation(moderation: GroupModeration) -> Self {
+        let kind: Kind = moderation.kind();
+        let reason: String = moderation.reason.clone();
+        let tags: Vec<Tag> = moderation.into();
+        Self::new(kind, reason, tags)
+    }
+
+    /// Date-Based Calendar Event
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/52.md>
+    pub fn date_based_calendar_event<S>(event: DateBasedCalendarEvent, description: S) -> Self
+    where
+        S: Into<String>,
+    {
+        let tags: Vec<Tag> = event.into();
+        Self::new(Kind::DateBasedCalendarEvent, description, tags)
+    }
+
+    /// Time-Based Calendar Event
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/52.md>
+    pub fn time_based_calendar_event<S>(event: TimeBasedCalendarEvent, description: S) -> Self
+    where
+        S: Into<String>,
+    {
+        let tags: Vec<Tag> = event.into();
+        Self::new(Kind::TimeBasedCalendarEvent, description, tags)
+    }
+
+    /// Calendar
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/52.md>
+    pub fn calendar<S>(calendar: Calendar, description: S) -> Self
+    where
+        S: Into<String>,
+    {
+        let tags: Vec<Tag> = calendar.into();
+        Self::new(Kind::Calendar, description, tags)
+    }
+
+    /// Calendar Event RSVP
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/52.md>
+    pub fn calendar_event_rsvp<S>(rsvp: CalendarEventRsvp, note: S) -> Self
+    where
+        S: Into<String>,
+    {
+        let tags: Vec<Tag> = rsvp.into();
+        Self::new(Kind::CalendarEventRsvp, note, tags)
+    }
+
     /// Live Event
     ///
     /// <https://github.com/nostr-protocol/nips/blob/master/53.md>
@@ -1256,6 +1372,35 @@ impl EventBuilder {
         Self::new(Kind::SealedDirect, message, [Tag::public_key(receiver)])
     }
 
+    /// Private Direct message rumor
+    ///
+    /// The rumor must be gift wrapped to every receiver and to the author.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/17.md>
+    #[cfg(feature = "nip59")]
+    pub fn private_msg_rumor<I, S>(receivers: I, message: S, subject: Option<S>) -> Self
+    where
+        I: IntoIterator<Item = PublicKey>,
+        S: Into<String>,
+    {
+        let mut tags: Vec<Tag> = receivers.into_iter().map(Tag::public_key).collect();
+        if let Some(subject) = subject {
+            tags.push(Tag::Subject(subject.into()));
+        }
+        Self::new(Kind::SealedDirect, message, tags)
+    }
+
+    /// Inbox relays, where to receive the Private Direct messages
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/17.md>
+    pub fn inbox_relays<I>(relays: I) -> Self
+    where
+        I: IntoIterator<Item = UncheckedUrl>,
+    {
+        let tags = relays.into_iter().map(Tag::Relay);
+        Self::new(Kind::InboxRelays, "", tags)
+    }
+
     /// Mute list
     ///
     /// <https://github.com/nostr-protocol/nips/blob/master/51.md>
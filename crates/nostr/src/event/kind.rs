@@ -122,6 +122,10 @@ pub enum Kind {
     ///
     /// <https://github.com/nostr-protocol/nips/blob/master/51.md>
     Emojis,
+    /// Inbox Relays (NIP17)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/17.md>
+    InboxRelays,
     /// Follow Sets
     ///
     /// <https://github.com/nostr-protocol/nips/blob/master/51.md>
@@ -178,6 +182,40 @@ pub enum Kind {
     GiftWrap,
     /// GiftWrapped Sealed Direct message
     SealedDirect,
+    /// Group Add User (NIP29)
+    GroupAddUser,
+    /// Group Remove User (NIP29)
+    GroupRemoveUser,
+    /// Group Edit Metadata (NIP29)
+    GroupEditMetadata,
+    /// Group Add Permission (NIP29)
+    GroupAddPermission,
+    /// Group Remove Permission (NIP29)
+    GroupRemovePermission,
+    /// Group Delete Event (NIP29)
+    GroupDeleteEvent,
+    /// Group Edit Status (NIP29)
+    GroupEditStatus,
+    /// Group Create (NIP29)
+    GroupCreate,
+    /// Group Join Request (NIP29)
+    GroupJoinRequest,
+    /// Group Leave Request (NIP29)
+    GroupLeaveRequest,
+    /// Group Metadata (NIP29)
+    GroupMetadata,
+    /// Group Admins (NIP29)
+    GroupAdmins,
+    /// Group Members (NIP29)
+    GroupMembers,
+    /// Date-Based Calendar Event (NIP52)
+    DateBasedCalendarEvent,
+    /// Time-Based Calendar Event (NIP52)
+    TimeBasedCalendarEvent,
+    /// Calendar (NIP52)
+    Calendar,
+    /// Calendar Event RSVP (NIP52)
+    CalendarEventRsvp,
     /// Long-form Text Note (NIP23)
     LongFormTextNote,
     /// Application-specific Data (NIP78)
@@ -343,6 +381,7 @@ impl From<u64> for Kind {
             10009 => Self::SimpleGroups,
             10015 => Self::Interests,
             10030 => Self::Emojis,
+            10050 => Self::InboxRelays,
             10002 => Self::RelayList,
             22242 => Self::Authentication,
             23194 => Self::WalletConnectRequest,
@@ -363,6 +402,23 @@ impl From<u64> for Kind {
             13 => Self::Seal,
             1059 => Self::GiftWrap,
             14 => Self::SealedDirect,
+            9000 => Self::GroupAddUser,
+            9001 => Self::GroupRemoveUser,
+            9002 => Self::GroupEditMetadata,
+            9003 => Self::GroupAddPermission,
+            9004 => Self::GroupRemovePermission,
+            9005 => Self::GroupDeleteEvent,
+            9006 => Self::GroupEditStatus,
+            9007 => Self::GroupCreate,
+            9021 => Self::GroupJoinRequest,
+            9022 => Self::GroupLeaveRequest,
+            39000 => Self::GroupMetadata,
+            39001 => Self::GroupAdmins,
+            39002 => Self::GroupMembers,
+            31922 => Self::DateBasedCalendarEvent,
+            31923 => Self::TimeBasedCalendarEvent,
+            31924 => Self::Calendar,
+            31925 => Self::CalendarEventRsvp,
             30017 => Self::SetStall,
             30018 => Self::SetProduct,
             30023 => Self::LongFormTextNote,
@@ -422,6 +478,7 @@ impl From<Kind> for u64 {
             Kind::SimpleGroups => 10009,
             Kind::Interests => 10015,
             Kind::Emojis => 10030,
+            Kind::InboxRelays => 10050,
             Kind::RelayList => 10002,
             Kind::Authentication => 22242,
             Kind::WalletConnectRequest => 23194,
@@ -442,6 +499,23 @@ impl From<Kind> for u64 {
             Kind::Seal => 13,
             Kind::GiftWrap => 1059,
             Kind::SealedDirect => 14,
+            Kind::GroupAddUser => 9000,
+            Kind::GroupRemoveUser => 9001,
+            Kind::GroupEditMetadata => 9002,
+            Kind::GroupAddPermission => 9003,
+            Kind::GroupRemovePermission => 9004,
+            Kind::GroupDeleteEvent => 9005,
+            Kind::GroupEditStatus => 9006,
+            Kind::GroupCreate => 9007,
+            Kind::GroupJoinRequest => 9021,
+            Kind::GroupLeaveRequest => 9022,
+            Kind::GroupMetadata => 39000,
+            Kind::GroupAdmins => 39001,
+            Kind::GroupMembers => 39002,
+            Kind::DateBasedCalendarEvent => 31922,
+            Kind::TimeBasedCalendarEvent => 31923,
+            Kind::Calendar => 31924,
+            Kind::CalendarEventRsvp => 31925,
             Kind::SetStall => 30017,
             Kind::SetProduct => 30018,
             Kind::LongFormTextNote => 30023,
@@ -412,6 +412,12 @@ pub enum TagKind {
     Ends,
     /// Status
     Status,
+    /// Start timezone (NIP52)
+    StartTzid,
+    /// End timezone (NIP52)
+    EndTzid,
+    /// Location (NIP52)
+    Location,
     /// Current participants
     CurrentParticipants,
     /// Total participants
@@ -470,6 +476,9 @@ impl fmt::Display for TagKind {
             Self::Starts => write!(f, "starts"),
             Self::Ends => write!(f, "ends"),
             Self::Status => write!(f, "status"),
+            Self::StartTzid => write!(f, "start_tzid"),
+            Self::EndTzid => write!(f, "end_tzid"),
+            Self::Location => write!(f, "location"),
             Self::CurrentParticipants => write!(f, "current_participants"),
             Self::TotalParticipants => write!(f, "total_participants"),
             Self::Method => write!(f, "method"),
@@ -521,6 +530,9 @@ where
             "starts" => Self::Starts,
             "ends" => Self::Ends,
             "status" => Self::Status,
+            "start_tzid" => Self::StartTzid,
+            "end_tzid" => Self::EndTzid,
+            "location" => Self::Location,
             "current_participants" => Self::CurrentParticipants,
             "total_participants" => Self::TotalParticipants,
             "method" => Self::Method,
@@ -568,6 +580,8 @@ pub enum Tag {
     Hashtag(String),
     Geohash(String),
     Identifier(String),
+    /// Group (NIP29)
+    Group(String),
     ExternalIdentity(Identity),
     A {
         coordinate: Coordinate,
@@ -724,6 +738,10 @@ impl Tag {
                     character: Alphabet::D,
                     uppercase: false,
                 }) => Ok(Self::Identifier(tag_1.to_owned())),
+                TagKind::SingleLetter(SingleLetterTag {
+                    character: Alphabet::H,
+                    uppercase: false,
+                }) => Ok(Self::Group(tag_1.to_owned())),
                 TagKind::SingleLetter(SingleLetterTag {
                     character: Alphabet::K,
                     uppercase: false,
@@ -956,12 +974,19 @@ impl Tag {
                 TagKind::SingleLetter(SingleLetterTag {
                     character: Alphabet::P,
                     ..
-                }) => Ok(Self::PubKeyLiveEvent {
-                    public_key: PublicKey::from_str(tag_1)?,
-                    relay_url: (!tag_2.is_empty()).then_some(UncheckedUrl::from(tag_2)),
-                    marker: LiveEventMarker::from_str(tag_3)?,
-                    proof: Signature::from_str(tag_4).ok(),
-                }),
+                }) => match LiveEventMarker::from_str(tag_3) {
+                    Ok(marker) => Ok(Self::PubKeyLiveEvent {
+                        public_key: PublicKey::from_str(tag_1)?,
+                        relay_url: (!tag_2.is_empty()).then_some(UncheckedUrl::from(tag_2)),
+                        marker,
+                        proof: Signature::from_str(tag_4).ok(),
+                    }),
+                    // Ex. NIP29 admin roles
+                    Err(..) => Ok(Self::Generic(
+                        tag_kind,
+                        tag[1..].iter().map(|s| s.as_ref().to_owned()).collect(),
+                    )),
+                },
                 _ => Ok(Self::Generic(
                     tag_kind,
                     tag[1..].iter().map(|s| s.as_ref().to_owned()).collect(),
@@ -1062,6 +1087,10 @@ impl Tag {
                 character: Alphabet::D,
                 uppercase: false,
             }),
+            Self::Group(..) => TagKind::SingleLetter(SingleLetterTag {
+                character: Alphabet::H,
+                uppercase: false,
+            }),
             Self::ExternalIdentity(..) => TagKind::SingleLetter(SingleLetterTag {
                 character: Alphabet::I,
                 uppercase: false,
@@ -1158,6 +1187,7 @@ impl Tag {
             Self::Hashtag(val) => Some(val.into_generic_tag_value()),
             Self::Geohash(val, ..) => Some(val.into_generic_tag_value()),
             Self::Identifier(val, ..) => Some(val.into_generic_tag_value()),
+            Self::Group(val) => Some(val.into_generic_tag_value()),
             Self::ExternalIdentity(..) => None,
             Self::A { coordinate, .. } => Some(coordinate.clone().into_generic_tag_value()),
             Self::Kind(kind) => Some(kind.to_string().into_generic_tag_value()),
@@ -1283,6 +1313,7 @@ impl From<Tag> for Vec<String> {
             Tag::Hashtag(t) => vec![tag_kind.to_string(), t],
             Tag::Geohash(g) => vec![tag_kind.to_string(), g],
             Tag::Identifier(d) => vec![tag_kind.to_string(), d],
+            Tag::Group(h) => vec![tag_kind.to_string(), h],
             Tag::A {
                 coordinate,
                 relay_url,
@@ -1687,6 +1718,8 @@ mod tests {
             Tag::Identifier("test".to_string()).as_vec()
         );
 
+        assert_eq!(vec!["h", "abcd"], Tag::Group("abcd".to_string()).as_vec());
+
         assert_eq!(
             vec![
                 "p",
@@ -1983,6 +2016,11 @@ mod tests {
             Tag::Identifier("test".to_string())
         );
 
+        assert_eq!(
+            Tag::parse(vec!["h", "abcd"]).unwrap(),
+            Tag::Group("abcd".to_string())
+        );
+
         assert_eq!(
             Tag::parse(vec!["r", "https://example.com",]).unwrap(),
             Tag::Reference(String::from("https://example.com"),)
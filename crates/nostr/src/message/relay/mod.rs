@@ -82,6 +82,103 @@ impl<'de> Deserialize<'de> for NegentropyErrorCode {
     }
 }
 
+/// Machine-readable prefix of `OK` and `CLOSED` messages (NIP01 and NIP42)
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MachineReadablePrefix {
+    /// Event already stored by relay
+    Duplicate,
+    /// Proof of work
+    Pow,
+    /// Pubkey or IP blocked
+    Blocked,
+    /// Too many messages
+    RateLimited,
+    /// Invalid event or filter
+    Invalid,
+    /// Relay error
+    Error,
+    /// Authentication required (NIP42)
+    AuthRequired,
+    /// Authenticated but not allowed (NIP42)
+    Restricted,
+    /// Ephemeral event not delivered, no one was listening
+    Mute,
+    /// Other
+    Other(String),
+}
+
+impl MachineReadablePrefix {
+    /// Parse the prefix of the `OK` or `CLOSED` message (ex. `blocked: you are banned`)
+    ///
+    /// Return `None` if the message has no prefix.
+    pub fn parse(message: &str) -> Option<Self> {
+        let (prefix, _) = message.split_once(':')?;
+        let prefix: &str = prefix.trim();
+        if prefix.is_empty() || prefix.contains(char::is_whitespace) {
+            return None;
+        }
+        Some(Self::from(prefix))
+    }
+}
+
+impl fmt::Display for MachineReadablePrefix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Duplicate => write!(f, "duplicate"),
+            Self::Pow => write!(f, "pow"),
+            Self::Blocked => write!(f, "blocked"),
+            Self::RateLimited => write!(f, "rate-limited"),
+            Self::Invalid => write!(f, "invalid"),
+            Self::Error => write!(f, "error"),
+            Self::AuthRequired => write!(f, "auth-required"),
+            Self::Restricted => write!(f, "restricted"),
+            Self::Mute => write!(f, "mute"),
+            Self::Other(prefix) => write!(f, "{prefix}"),
+        }
+    }
+}
+
+impl<S> From<S> for MachineReadablePrefix
+where
+    S: Into<String>,
+{
+    fn from(prefix: S) -> Self {
+        let prefix: String = prefix.into();
+        match prefix.as_str() {
+            "duplicate" => Self::Duplicate,
+            "pow" => Self::Pow,
+            "blocked" => Self::Blocked,
+            "rate-limited" => Self::RateLimited,
+            "invalid" => Self::Invalid,
+            "error" => Self::Error,
+            "auth-required" => Self::AuthRequired,
+            "restricted" => Self::Restricted,
+            "mute" => Self::Mute,
+            _ => Self::Other(prefix),
+        }
+    }
+}
+
+impl Serialize for MachineReadablePrefix {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for MachineReadablePrefix {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = Value::deserialize(deserializer)?;
+        let prefix: String = serde_json::from_value(value).map_err(serde::de::Error::custom)?;
+        Ok(Self::from(prefix))
+    }
+}
+
 /// Messages sent by relays, received by clients
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum RelayMessage {
@@ -542,4 +639,36 @@ mod tests {
 
         assert_eq!(msg, RelayMessage::from_json(SAMPLE_EVENT).unwrap());
     }
+
+    #[test]
+    fn test_machine_readable_prefix() {
+        assert_eq!(
+            MachineReadablePrefix::parse("blocked: you are banned"),
+            Some(MachineReadablePrefix::Blocked)
+        );
+        assert_eq!(
+            MachineReadablePrefix::parse("rate-limited: slow down"),
+            Some(MachineReadablePrefix::RateLimited)
+        );
+        assert_eq!(
+            MachineReadablePrefix::parse("mute: no one was listening"),
+            Some(MachineReadablePrefix::Mute)
+        );
+        assert_eq!(
+            MachineReadablePrefix::parse("payment-required: pay first"),
+            Some(MachineReadablePrefix::Other(String::from(
+                "payment-required"
+            )))
+        );
+        assert_eq!(MachineReadablePrefix::parse(""), None);
+        assert_eq!(MachineReadablePrefix::parse("event stored"), None);
+        assert_eq!(
+            MachineReadablePrefix::parse("see https://example.com"),
+            None
+        );
+        assert_eq!(
+            MachineReadablePrefix::AuthRequired.to_string(),
+            "auth-required"
+        );
+    }
 }
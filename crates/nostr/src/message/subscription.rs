@@ -673,6 +673,45 @@ impl Filter {
         )
     }
 
+    /// Add group (`h` tag)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/29.md>
+    #[inline]
+    pub fn group<S>(self, group_id: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.custom_tag(SingleLetterTag::lowercase(Alphabet::H), [group_id.into()])
+    }
+
+    /// Add groups (`h` tag)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/29.md>
+    #[inline]
+    pub fn groups<I, S>(self, group_ids: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.custom_tag(
+            SingleLetterTag::lowercase(Alphabet::H),
+            group_ids.into_iter().map(|s| s.into()),
+        )
+    }
+
+    /// Remove groups (`h` tag)
+    #[inline]
+    pub fn remove_groups<I, S>(self, group_ids: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.remove_custom_tag(
+            SingleLetterTag::lowercase(Alphabet::H),
+            group_ids.into_iter().map(|s| s.into()),
+        )
+    }
+
     /// Add search field
     #[inline]
     pub fn search<S>(mut self, value: S) -> Self
@@ -897,7 +936,21 @@ where
                                 values.retain(|v| matches!(v, GenericTagValue::Pubkey(_)))
                             }
                             Alphabet::E => {
-                                values.retain(|v| matches!(v, GenericTagValue::EventId(_)))
+                                // Event IDs may also be valid public keys
+                                values = values
+                                    .into_iter()
+                                    .filter_map(|v| match v {
+                                        GenericTagValue::EventId(id) => {
+                                            Some(GenericTagValue::EventId(id))
+                                        }
+                                        GenericTagValue::Pubkey(public_key) => {
+                                            EventId::from_slice(&public_key.to_bytes())
+                                                .ok()
+                                                .map(GenericTagValue::EventId)
+                                        }
+                                        _ => None,
+                                    })
+                                    .collect();
                             }
                             _ => {}
                         }
@@ -1080,6 +1133,15 @@ mod tests {
         let json = r##"{"aa":["..."],"search":"test"}"##;
         let filter = Filter::from_json(json).unwrap();
         assert_eq!(filter, Filter::new().search("test"));
+
+        // Event ID that is also a valid public key
+        let json =
+            r##"{"#e":["379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe"]}"##;
+        let filter = Filter::from_json(json).unwrap();
+        let event_id =
+            EventId::from_hex("379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe")
+                .unwrap();
+        assert_eq!(filter, Filter::new().event(event_id));
     }
 
     #[test]
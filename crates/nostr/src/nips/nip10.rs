@@ -0,0 +1,381 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! NIP10
+//!
+//! <https://github.com/nostr-protocol/nips/blob/master/10.md>
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec::Vec;
+
+use crate::{Event, EventId, Marker, PublicKey, Tag, UncheckedUrl};
+
+/// Event referenced by an `e` tag
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ThreadReference {
+    /// Event ID
+    pub event_id: EventId,
+    /// Relay hint
+    pub relay_url: Option<UncheckedUrl>,
+}
+
+/// Thread relations of an event
+///
+/// Support both marked and deprecated positional `e` tags.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ThreadRelations {
+    /// Root of the thread
+    pub root: Option<ThreadReference>,
+    /// Event to which the event is replying, if not a direct reply to the root
+    pub reply: Option<ThreadReference>,
+    /// Mentioned events
+    pub mentions: Vec<ThreadReference>,
+    /// Public keys involved in the thread (`p` tags)
+    pub public_keys: Vec<PublicKey>,
+}
+
+impl ThreadRelations {
+    /// Parse thread relations from event tags
+    pub fn from_event(event: &Event) -> Self {
+        let mut references: Vec<(ThreadReference, Option<&Marker>)> = Vec::new();
+        let mut public_keys: Vec<PublicKey> = Vec::new();
+
+        for tag in event.iter_tags() {
+            match tag {
+                Tag::Event {
+                    event_id,
+                    relay_url,
+                    marker,
+                } => {
+                    let reference = ThreadReference {
+                        event_id: *event_id,
+                        relay_url: relay_url.clone(),
+                    };
+                    references.push((reference, marker.as_ref()));
+                }
+                Tag::PublicKey {
+                    public_key,
+                    uppercase: false,
+                    ..
+                } => {
+                    if !public_keys.contains(public_key) {
+                        public_keys.push(*public_key);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut relations = Self {
+            public_keys,
+            ..Default::default()
+        };
+
+        if references.iter().any(|(.., marker)| marker.is_some()) {
+            // Marked `e` tags
+            for (reference, marker) in references.into_iter() {
+                match marker {
+                    Some(Marker::Root) if relations.root.is_none() => {
+                        relations.root = Some(reference)
+                    }
+                    Some(Marker::Reply) if relations.reply.is_none() => {
+                        relations.reply = Some(reference)
+                    }
+                    _ => relations.mentions.push(reference),
+                }
+            }
+
+            // Replies to the root may have both the `root` and `reply` markers
+            if relations.root == relations.reply {
+                relations.reply = None;
+            }
+        } else {
+            // Positional `e` tags (deprecated): first is the root, last the reply and the others are mentions
+            let mut references = references.into_iter().map(|(reference, ..)| reference);
+            relations.root = references.next();
+            relations.reply = references.next_back();
+            relations.mentions = references.collect();
+        }
+
+        relations
+    }
+
+    /// Event to which the event is replying (the reply or, if missing, the root)
+    pub fn parent(&self) -> Option<&ThreadReference> {
+        self.reply.as_ref().or(self.root.as_ref())
+    }
+
+    /// Check if the event is a reply
+    pub fn is_reply(&self) -> bool {
+        self.parent().is_some()
+    }
+
+    /// Root of the thread, if the event is a reply
+    ///
+    /// If the root isn't specified, return the parent.
+    pub fn root_id(&self) -> Option<EventId> {
+        self.root
+            .as_ref()
+            .or(self.reply.as_ref())
+            .map(|r| r.event_id)
+    }
+}
+
+/// Event of a [`ThreadTree`], with its replies
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThreadNode {
+    /// Event
+    pub event: Event,
+    /// Replies, sorted by creation time
+    pub replies: Vec<ThreadNode>,
+}
+
+impl ThreadNode {
+    /// Number of events (this one and all the replies)
+    pub fn count(&self) -> usize {
+        1 + self.replies.iter().map(|node| node.count()).sum::<usize>()
+    }
+
+    /// Find an event in the subtree
+    pub fn find(&self, id: &EventId) -> Option<&ThreadNode> {
+        if self.event.id() == *id {
+            return Some(self);
+        }
+        self.replies.iter().find_map(|node| node.find(id))
+    }
+}
+
+/// Reply tree of a thread
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThreadTree {
+    /// Root of the thread
+    pub root: ThreadNode,
+    /// Replies of the thread whose parent is missing, with their replies
+    pub orphans: Vec<ThreadNode>,
+}
+
+impl ThreadTree {
+    /// Build the reply tree of the `root` event
+    ///
+    /// The events that aren't replies of the thread are skipped.
+    pub fn new<I>(root: Event, events: I) -> Self
+    where
+        I: IntoIterator<Item = Event>,
+    {
+        let root_id: EventId = root.id();
+
+        // Group the replies by parent
+        let mut ids: BTreeSet<EventId> = BTreeSet::new();
+        let mut children: BTreeMap<EventId, Vec<Event>> = BTreeMap::new();
+        let mut orphans: Vec<Event> = Vec::new();
+        let mut events: Vec<(Event, ThreadRelations)> = events
+            .into_iter()
+            .filter(|event| event.id() != root_id)
+            .map(|event| {
+                let relations = ThreadRelations::from_event(&event);
+                (event, relations)
+            })
+            .collect();
+        events.retain(|(event, ..)| ids.insert(event.id()));
+        for (event, relations) in events.into_iter() {
+            let parent: EventId = match relations.parent() {
+                Some(parent) => parent.event_id,
+                None => continue,
+            };
+
+            if parent != root_id && !ids.contains(&parent) {
+                if relations.root_id() == Some(root_id) {
+                    orphans.push(event);
+                }
+                continue;
+            }
+
+            children.entry(parent).or_default().push(event);
+        }
+
+        let mut visited: BTreeSet<EventId> = BTreeSet::new();
+        let root: ThreadNode = build_node(root, &mut children, &mut visited);
+        let mut orphans: Vec<ThreadNode> = orphans
+            .into_iter()
+            .map(|event| build_node(event, &mut children, &mut visited))
+            .collect();
+        orphans.sort_by_key(|node| (node.event.created_at(), node.event.id()));
+
+        Self { root, orphans }
+    }
+
+    /// Number of events of the thread (root, replies and orphans)
+    pub fn count(&self) -> usize {
+        self.root.count() + self.orphans.iter().map(|node| node.count()).sum::<usize>()
+    }
+
+    /// Find an event in the thread
+    pub fn find(&self, id: &EventId) -> Option<&ThreadNode> {
+        self.root
+            .find(id)
+            .or_else(|| self.orphans.iter().find_map(|node| node.find(id)))
+    }
+}
+
+fn build_node(
+    event: Event,
+    children: &mut BTreeMap<EventId, Vec<Event>>,
+    visited: &mut BTreeSet<EventId>,
+) -> ThreadNode {
+    visited.insert(event.id());
+
+    let mut replies: Vec<ThreadNode> = Vec::new();
+    if let Some(events) = children.remove(&event.id()) {
+        for reply in events.into_iter() {
+            // Skip loops
+            if !visited.contains(&reply.id()) {
+                replies.push(build_node(reply, children, visited));
+            }
+        }
+    }
+    replies.sort_by_key(|node| (node.event.created_at(), node.event.id()));
+
+    ThreadNode { event, replies }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EventBuilder, Keys, Kind};
+
+    fn note(keys: &Keys, content: &str, tags: Vec<Tag>) -> Event {
+        EventBuilder::new(Kind::TextNote, content, tags)
+            .to_event(keys)
+            .unwrap()
+    }
+
+    fn e(event: &Event, marker: Option<Marker>) -> Tag {
+        Tag::Event {
+            event_id: event.id(),
+            relay_url: Some(UncheckedUrl::from("wss://relay.damus.io")),
+            marker,
+        }
+    }
+
+    #[test]
+    fn test_marked_relations() {
+        let keys = Keys::generate();
+        let root = note(&keys, "root", Vec::new());
+        assert_eq!(
+            ThreadRelations::from_event(&root),
+            ThreadRelations::default()
+        );
+
+        // Direct reply to the root
+        let reply = EventBuilder::text_note_reply("reply", &root, None, None)
+            .to_event(&keys)
+            .unwrap();
+        let relations = ThreadRelations::from_event(&reply);
+        assert_eq!(relations.root_id(), Some(root.id()));
+        assert_eq!(relations.reply, None);
+        assert_eq!(relations.parent().unwrap().event_id, root.id());
+        assert_eq!(relations.public_keys, vec![keys.public_key()]);
+
+        // Nested reply, with mention
+        let mention = note(&keys, "mention", Vec::new());
+        let nested = note(
+            &keys,
+            "nested",
+            vec![
+                e(&root, Some(Marker::Root)),
+                e(&mention, Some(Marker::Mention)),
+                e(&reply, Some(Marker::Reply)),
+            ],
+        );
+        let relations = ThreadRelations::from_event(&nested);
+        assert_eq!(relations.root_id(), Some(root.id()));
+        assert_eq!(relations.parent().unwrap().event_id, reply.id());
+        assert_eq!(
+            relations.parent().unwrap().relay_url,
+            Some(UncheckedUrl::from("wss://relay.damus.io"))
+        );
+        assert_eq!(relations.mentions.len(), 1);
+        assert_eq!(relations.mentions[0].event_id, mention.id());
+    }
+
+    #[test]
+    fn test_positional_relations() {
+        let keys = Keys::generate();
+        let root = note(&keys, "root", Vec::new());
+        let mention = note(&keys, "mention", Vec::new());
+        let reply = note(&keys, "reply", vec![e(&root, None)]);
+
+        // One `e` tag: reply to the root
+        let relations = ThreadRelations::from_event(&reply);
+        assert_eq!(relations.root_id(), Some(root.id()));
+        assert_eq!(relations.reply, None);
+        assert_eq!(relations.parent().unwrap().event_id, root.id());
+
+        // Two `e` tags: root and reply
+        let nested = note(&keys, "nested", vec![e(&root, None), e(&reply, None)]);
+        let relations = ThreadRelations::from_event(&nested);
+        assert_eq!(relations.root_id(), Some(root.id()));
+        assert_eq!(relations.parent().unwrap().event_id, reply.id());
+        assert!(relations.mentions.is_empty());
+
+        // Many `e` tags: root, mentions and reply
+        let nested = note(
+            &keys,
+            "nested",
+            vec![e(&root, None), e(&mention, None), e(&reply, None)],
+        );
+        let relations = ThreadRelations::from_event(&nested);
+        assert_eq!(relations.root_id(), Some(root.id()));
+        assert_eq!(relations.parent().unwrap().event_id, reply.id());
+        assert_eq!(relations.mentions[0].event_id, mention.id());
+    }
+
+    #[test]
+    fn test_thread_tree() {
+        let keys = Keys::generate();
+        let root = note(&keys, "root", Vec::new());
+        let reply1 = note(&keys, "reply 1", vec![e(&root, Some(Marker::Root))]);
+        let reply2 = note(&keys, "reply 2", vec![e(&root, None)]);
+        let nested = note(
+            &keys,
+            "nested",
+            vec![
+                e(&root, Some(Marker::Root)),
+                e(&reply1, Some(Marker::Reply)),
+            ],
+        );
+        let missing = note(&keys, "missing", vec![e(&root, Some(Marker::Root))]);
+        let orphan = note(
+            &keys,
+            "orphan",
+            vec![
+                e(&root, Some(Marker::Root)),
+                e(&missing, Some(Marker::Reply)),
+            ],
+        );
+        let unrelated = note(&keys, "unrelated", Vec::new());
+
+        let tree = ThreadTree::new(
+            root.clone(),
+            vec![
+                nested.clone(),
+                reply2.clone(),
+                reply1.clone(),
+                orphan.clone(),
+                unrelated.clone(),
+                reply1.clone(),
+                root.clone(),
+            ],
+        );
+
+        assert_eq!(tree.root.event, root);
+        assert_eq!(tree.root.replies.len(), 2);
+        assert_eq!(tree.count(), 5);
+        assert_eq!(tree.find(&reply1.id()).unwrap().replies[0].event, nested);
+        assert!(tree.find(&reply2.id()).unwrap().replies.is_empty());
+        assert_eq!(tree.orphans.len(), 1);
+        assert_eq!(tree.orphans[0].event, orphan);
+        assert!(tree.find(&unrelated.id()).is_none());
+    }
+}
@@ -0,0 +1,148 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! NIP17
+//!
+//! <https://github.com/nostr-protocol/nips/blob/master/17.md>
+
+use alloc::collections::BTreeSet;
+
+use crate::{Event, Kind, PublicKey, Tag, UncheckedUrl, UnsignedEvent};
+
+/// Chat room, identified by the set of `pubkey` + `p` tags of its messages
+///
+/// Adding or removing a member creates a new room.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ChatRoom {
+    members: BTreeSet<PublicKey>,
+}
+
+impl ChatRoom {
+    /// Compose chat room from its members
+    pub fn new<I>(members: I) -> Self
+    where
+        I: IntoIterator<Item = PublicKey>,
+    {
+        Self {
+            members: members.into_iter().collect(),
+        }
+    }
+
+    /// Chat room of a message (author and `p` tags)
+    pub fn from_rumor(rumor: &UnsignedEvent) -> Self {
+        let mut members: BTreeSet<PublicKey> = BTreeSet::new();
+        members.insert(rumor.pubkey);
+        for tag in rumor.tags.iter() {
+            if let Tag::PublicKey {
+                public_key,
+                uppercase: false,
+                ..
+            } = tag
+            {
+                members.insert(*public_key);
+            }
+        }
+        Self { members }
+    }
+
+    /// Members (including the user)
+    pub fn members(&self) -> &BTreeSet<PublicKey> {
+        &self.members
+    }
+
+    /// Check if the public key is a member of the room
+    pub fn contains(&self, public_key: &PublicKey) -> bool {
+        self.members.contains(public_key)
+    }
+
+    /// Members except the public key (ex. the receivers of a message sent by the user)
+    pub fn others(&self, public_key: &PublicKey) -> impl Iterator<Item = &PublicKey> {
+        let public_key: PublicKey = *public_key;
+        self.members.iter().filter(move |p| **p != public_key)
+    }
+}
+
+/// Subject of a message, if any
+///
+/// The subject of a chat room is the one of the newest message with a subject.
+pub fn subject(rumor: &UnsignedEvent) -> Option<&str> {
+    rumor.tags.iter().find_map(|tag| match tag {
+        Tag::Subject(subject) => Some(subject.as_str()),
+        _ => None,
+    })
+}
+
+/// Extract the relays of an inbox relays event (kind `10050`)
+///
+/// Return an empty iterator if the event kind doesn't match.
+pub fn extract_inbox_relays(event: &Event) -> impl Iterator<Item = &UncheckedUrl> {
+    let is_inbox: bool = event.kind() == Kind::InboxRelays;
+    event.iter_tags().filter_map(move |tag| match tag {
+        Tag::Relay(url) if is_inbox => Some(url),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::String;
+    use alloc::vec::Vec;
+
+    use super::*;
+    use crate::{EventBuilder, Keys};
+
+    #[test]
+    fn test_chat_room() {
+        let alice = Keys::generate();
+        let bob = Keys::generate().public_key();
+        let carol = Keys::generate().public_key();
+
+        let rumor = EventBuilder::new(
+            Kind::SealedDirect,
+            "Hello",
+            [
+                Tag::public_key(bob),
+                Tag::public_key(carol),
+                Tag::Subject(String::from("Party")),
+            ],
+        )
+        .to_unsigned_event(alice.public_key());
+        let room = ChatRoom::from_rumor(&rumor);
+        assert_eq!(room, ChatRoom::new([carol, alice.public_key(), bob]));
+        assert!(room.contains(&bob));
+        assert_eq!(room.others(&alice.public_key()).count(), 2);
+        assert_eq!(subject(&rumor), Some("Party"));
+
+        // A reply of bob to the same members is in the same room
+        let reply = EventBuilder::new(
+            Kind::SealedDirect,
+            "Hi",
+            [Tag::public_key(alice.public_key()), Tag::public_key(carol)],
+        )
+        .to_unsigned_event(bob);
+        assert_eq!(ChatRoom::from_rumor(&reply), room);
+        assert_eq!(subject(&reply), None);
+    }
+
+    #[test]
+    fn test_extract_inbox_relays() {
+        let keys = Keys::generate();
+        let relays = [
+            UncheckedUrl::from("wss://inbox.nostr.wine"),
+            UncheckedUrl::from("wss://relay.damus.io"),
+        ];
+        let event = EventBuilder::inbox_relays(relays.clone())
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(
+            extract_inbox_relays(&event).cloned().collect::<Vec<_>>(),
+            relays
+        );
+
+        let event = EventBuilder::blocked_relays(relays)
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(extract_inbox_relays(&event).count(), 0);
+    }
+}
@@ -0,0 +1,798 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! NIP29
+//!
+//! <https://github.com/nostr-protocol/nips/blob/master/29.md>
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::RangeInclusive;
+use core::str::FromStr;
+
+use crate::{key, Event, EventId, Kind, PublicKey, Tag, TagKind, UncheckedUrl};
+
+/// Kinds of the moderation events
+pub const GROUP_MODERATION_RANGE: RangeInclusive<u64> = 9_000..=9_020;
+
+/// Max number of `previous` references
+pub const MAX_PREVIOUS: usize = 50;
+
+/// NIP29 error
+#[derive(Debug, PartialEq, Eq)]
+pub enum Error {
+    /// Key error
+    Key(key::Error),
+    /// Unexpected kind
+    WrongKind(Kind),
+    /// Missing tag
+    MissingTag(TagKind),
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Key(e) => write!(f, "Key: {e}"),
+            Self::WrongKind(kind) => write!(f, "Wrong kind: {kind}"),
+            Self::MissingTag(kind) => write!(f, "Missing '{kind}' tag"),
+        }
+    }
+}
+
+impl From<key::Error> for Error {
+    fn from(e: key::Error) -> Self {
+        Self::Key(e)
+    }
+}
+
+fn check_kind(event: &Event, kinds: &[Kind]) -> Result<(), Error> {
+    if kinds.contains(&event.kind()) {
+        Ok(())
+    } else {
+        Err(Error::WrongKind(event.kind()))
+    }
+}
+
+/// Check if the kind is a group moderation event (`9000-9020`)
+pub fn is_moderation(kind: &Kind) -> bool {
+    GROUP_MODERATION_RANGE.contains(&kind.as_u64())
+}
+
+/// Extract the group ID of an event (`h` tag or, for the group state events, `d` tag)
+pub fn extract_group_id(event: &Event) -> Option<&str> {
+    let state: bool = matches!(
+        event.kind(),
+        Kind::GroupMetadata | Kind::GroupAdmins | Kind::GroupMembers
+    );
+    event.iter_tags().find_map(|tag| match tag {
+        Tag::Group(id) if !state => Some(id.as_str()),
+        Tag::Identifier(id) if state => Some(id.as_str()),
+        _ => None,
+    })
+}
+
+/// Extract the `previous` references (first 8 chars of the IDs of recent events of the group)
+pub fn extract_previous(event: &Event) -> Vec<String> {
+    event
+        .iter_tags()
+        .filter_map(|tag| match tag {
+            Tag::Generic(TagKind::Custom(kind), values) if kind == "previous" => Some(values),
+            _ => None,
+        })
+        .flatten()
+        .cloned()
+        .collect()
+}
+
+/// Compose `previous` tag (max [`MAX_PREVIOUS`] references)
+pub fn previous_tag<I, S>(previous: I) -> Option<Tag>
+where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+{
+    let previous: Vec<String> = previous
+        .into_iter()
+        .take(MAX_PREVIOUS)
+        .map(|s| s.into())
+        .collect();
+    if previous.is_empty() {
+        None
+    } else {
+        Some(Tag::Generic(
+            TagKind::Custom(String::from("previous")),
+            previous,
+        ))
+    }
+}
+
+/// Reference of an event for the `previous` tag
+pub fn previous_reference(id: &EventId) -> String {
+    id.to_hex()[..8].to_string()
+}
+
+/// Public key and extra values of a `p` tag (ex. roles or permissions)
+fn public_key_with_values(tag: &Tag) -> Result<Option<(PublicKey, Vec<String>)>, Error> {
+    let values: Vec<String> = match tag {
+        Tag::PublicKey {
+            uppercase: false, ..
+        }
+        | Tag::PubKeyReport(..)
+        | Tag::PubKeyLiveEvent { .. } => tag.as_vec(),
+        Tag::Generic(kind, ..) if kind.to_string() == "p" => tag.as_vec(),
+        _ => return Ok(None),
+    };
+
+    let mut values = values.into_iter().skip(1);
+    let public_key: PublicKey = match values.next() {
+        Some(public_key) => PublicKey::from_str(&public_key)?,
+        None => return Ok(None),
+    };
+
+    Ok(Some((
+        public_key,
+        values.filter(|v| !v.is_empty()).collect(),
+    )))
+}
+
+fn public_key_tag(public_key: PublicKey, values: Vec<String>) -> Tag {
+    if values.is_empty() {
+        Tag::public_key(public_key)
+    } else {
+        let mut values = values;
+        values.insert(0, public_key.to_string());
+        Tag::Generic(TagKind::from("p"), values)
+    }
+}
+
+fn custom(kind: &str, value: String) -> Tag {
+    Tag::Generic(TagKind::Custom(kind.to_string()), vec![value])
+}
+
+fn flag(kind: &str) -> Tag {
+    Tag::Generic(TagKind::Custom(kind.to_string()), Vec::new())
+}
+
+/// Group metadata (kind `39000`, signed by the relay)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GroupMetadata {
+    /// Group ID
+    pub id: String,
+    /// Name
+    pub name: Option<String>,
+    /// Picture
+    pub picture: Option<UncheckedUrl>,
+    /// About
+    pub about: Option<String>,
+    /// Only members can read the group messages
+    pub private: bool,
+    /// Join requests are ignored (invite only)
+    pub closed: bool,
+}
+
+impl GroupMetadata {
+    /// New group metadata
+    pub fn new<S>(id: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            id: id.into(),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<GroupMetadata> for Vec<Tag> {
+    fn from(metadata: GroupMetadata) -> Self {
+        let GroupMetadata {
+            id,
+            name,
+            picture,
+            about,
+            private,
+            closed,
+        } = metadata;
+
+        let mut tags: Vec<Tag> = vec![Tag::Identifier(id)];
+
+        if let Some(name) = name {
+            tags.push(Tag::Name(name));
+        }
+
+        if let Some(picture) = picture {
+            tags.push(custom("picture", picture.to_string()));
+        }
+
+        if let Some(about) = about {
+            tags.push(custom("about", about));
+        }
+
+        tags.push(flag(if private { "private" } else { "public" }));
+        tags.push(flag(if closed { "closed" } else { "open" }));
+
+        tags
+    }
+}
+
+impl TryFrom<&Event> for GroupMetadata {
+    type Error = Error;
+
+    fn try_from(event: &Event) -> Result<Self, Self::Error> {
+        check_kind(event, &[Kind::GroupMetadata])?;
+
+        let mut metadata = Self::default();
+        let mut id: Option<String> = None;
+        for tag in event.iter_tags() {
+            match tag {
+                Tag::Identifier(d) => id = Some(d.clone()),
+                Tag::Name(name) => metadata.name = Some(name.clone()),
+                Tag::Generic(TagKind::Custom(kind), values) => {
+                    let value: Option<&String> = values.first();
+                    match (kind.as_str(), value) {
+                        ("picture", Some(url)) => metadata.picture = Some(UncheckedUrl::from(url)),
+                        ("about", Some(about)) => metadata.about = Some(about.clone()),
+                        ("private", ..) => metadata.private = true,
+                        ("closed", ..) => metadata.closed = true,
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        metadata.id = id.ok_or(Error::MissingTag(TagKind::from("d")))?;
+
+        Ok(metadata)
+    }
+}
+
+/// Group admin
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupAdmin {
+    /// Public key
+    pub public_key: PublicKey,
+    /// Roles or permissions (ex. `add-user`, `delete-event`)
+    pub roles: Vec<String>,
+}
+
+/// Group admins (kind `39001`, signed by the relay)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GroupAdmins {
+    /// Group ID
+    pub group_id: String,
+    /// Admins
+    pub admins: Vec<GroupAdmin>,
+}
+
+impl From<GroupAdmins> for Vec<Tag> {
+    fn from(GroupAdmins { group_id, admins }: GroupAdmins) -> Self {
+        let mut tags: Vec<Tag> = Vec::with_capacity(1 + admins.len());
+        tags.push(Tag::Identifier(group_id));
+        tags.extend(
+            admins
+                .into_iter()
+                .map(|admin| public_key_tag(admin.public_key, admin.roles)),
+        );
+        tags
+    }
+}
+
+impl TryFrom<&Event> for GroupAdmins {
+    type Error = Error;
+
+    fn try_from(event: &Event) -> Result<Self, Self::Error> {
+        check_kind(event, &[Kind::GroupAdmins])?;
+
+        let mut admins: Vec<GroupAdmin> = Vec::new();
+        for tag in event.iter_tags() {
+            if let Some((public_key, roles)) = public_key_with_values(tag)? {
+                admins.push(GroupAdmin { public_key, roles });
+            }
+        }
+
+        Ok(Self {
+            group_id: extract_group_id(event)
+                .ok_or(Error::MissingTag(TagKind::from("d")))?
+                .to_string(),
+            admins,
+        })
+    }
+}
+
+/// Group members (kind `39002`, signed by the relay)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GroupMembers {
+    /// Group ID
+    pub group_id: String,
+    /// Members
+    pub members: Vec<PublicKey>,
+}
+
+impl From<GroupMembers> for Vec<Tag> {
+    fn from(GroupMembers { group_id, members }: GroupMembers) -> Self {
+        let mut tags: Vec<Tag> = Vec::with_capacity(1 + members.len());
+        tags.push(Tag::Identifier(group_id));
+        tags.extend(members.into_iter().map(Tag::public_key));
+        tags
+    }
+}
+
+impl TryFrom<&Event> for GroupMembers {
+    type Error = Error;
+
+    fn try_from(event: &Event) -> Result<Self, Self::Error> {
+        check_kind(event, &[Kind::GroupMembers])?;
+
+        let mut members: Vec<PublicKey> = Vec::new();
+        for tag in event.iter_tags() {
+            if let Some((public_key, ..)) = public_key_with_values(tag)? {
+                members.push(public_key);
+            }
+        }
+
+        Ok(Self {
+            group_id: extract_group_id(event)
+                .ok_or(Error::MissingTag(TagKind::from("d")))?
+                .to_string(),
+            members,
+        })
+    }
+}
+
+/// Request to join or leave a group (kinds `9021` and `9022`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GroupRequest {
+    /// Join request
+    Join {
+        /// Group ID
+        group_id: String,
+        /// Reason
+        reason: String,
+    },
+    /// Leave request
+    Leave {
+        /// Group ID
+        group_id: String,
+        /// Reason
+        reason: String,
+    },
+}
+
+impl GroupRequest {
+    /// Group ID
+    pub fn group_id(&self) -> &str {
+        match self {
+            Self::Join { group_id, .. } | Self::Leave { group_id, .. } => group_id,
+        }
+    }
+}
+
+impl TryFrom<&Event> for GroupRequest {
+    type Error = Error;
+
+    fn try_from(event: &Event) -> Result<Self, Self::Error> {
+        check_kind(event, &[Kind::GroupJoinRequest, Kind::GroupLeaveRequest])?;
+
+        let group_id: String = extract_group_id(event)
+            .ok_or(Error::MissingTag(TagKind::from("h")))?
+            .to_string();
+        let reason: String = event.content().to_string();
+
+        if event.kind() == Kind::GroupJoinRequest {
+            Ok(Self::Join { group_id, reason })
+        } else {
+            Ok(Self::Leave { group_id, reason })
+        }
+    }
+}
+
+/// Moderation action
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GroupAction {
+    /// Add user (kind `9000`)
+    AddUser {
+        /// Public key
+        public_key: PublicKey,
+        /// Roles
+        roles: Vec<String>,
+    },
+    /// Remove user (kind `9001`)
+    RemoveUser {
+        /// Public key
+        public_key: PublicKey,
+    },
+    /// Edit metadata (kind `9002`)
+    EditMetadata {
+        /// Name
+        name: Option<String>,
+        /// Picture
+        picture: Option<UncheckedUrl>,
+        /// About
+        about: Option<String>,
+    },
+    /// Add permissions to an admin (kind `9003`)
+    AddPermission {
+        /// Public key
+        public_key: PublicKey,
+        /// Permissions
+        permissions: Vec<String>,
+    },
+    /// Remove permissions from an admin (kind `9004`)
+    RemovePermission {
+        /// Public key
+        public_key: PublicKey,
+        /// Permissions
+        permissions: Vec<String>,
+    },
+    /// Delete event (kind `9005`)
+    DeleteEvent {
+        /// Event ID
+        event_id: EventId,
+    },
+    /// Edit group status (kind `9006`)
+    EditStatus {
+        /// Private or public
+        private: Option<bool>,
+        /// Closed or open
+        closed: Option<bool>,
+    },
+    /// Create group (kind `9007`)
+    CreateGroup,
+    /// Other moderation kind (`9000-9020`)
+    Other(Kind),
+}
+
+impl GroupAction {
+    /// Event kind
+    pub fn kind(&self) -> Kind {
+        match self {
+            Self::AddUser { .. } => Kind::GroupAddUser,
+            Self::RemoveUser { .. } => Kind::GroupRemoveUser,
+            Self::EditMetadata { .. } => Kind::GroupEditMetadata,
+            Self::AddPermission { .. } => Kind::GroupAddPermission,
+            Self::RemovePermission { .. } => Kind::GroupRemovePermission,
+            Self::DeleteEvent { .. } => Kind::GroupDeleteEvent,
+            Self::EditStatus { .. } => Kind::GroupEditStatus,
+            Self::CreateGroup => Kind::GroupCreate,
+            Self::Other(kind) => *kind,
+        }
+    }
+
+    fn into_tags(self) -> Vec<Tag> {
+        match self {
+            Self::AddUser { public_key, roles } => vec![public_key_tag(public_key, roles)],
+            Self::RemoveUser { public_key } => vec![Tag::public_key(public_key)],
+            Self::EditMetadata {
+                name,
+                picture,
+                about,
+            } => {
+                let mut tags: Vec<Tag> = Vec::new();
+                if let Some(name) = name {
+                    tags.push(Tag::Name(name));
+                }
+                if let Some(picture) = picture {
+                    tags.push(custom("picture", picture.to_string()));
+                }
+                if let Some(about) = about {
+                    tags.push(custom("about", about));
+                }
+                tags
+            }
+            Self::AddPermission {
+                public_key,
+                permissions,
+            }
+            | Self::RemovePermission {
+                public_key,
+                permissions,
+            } => vec![public_key_tag(public_key, permissions)],
+            Self::DeleteEvent { event_id } => vec![Tag::event(event_id)],
+            Self::EditStatus { private, closed } => {
+                let mut tags: Vec<Tag> = Vec::new();
+                if let Some(private) = private {
+                    tags.push(flag(if private { "private" } else { "public" }));
+                }
+                if let Some(closed) = closed {
+                    tags.push(flag(if closed { "closed" } else { "open" }));
+                }
+                tags
+            }
+            Self::CreateGroup | Self::Other(..) => Vec::new(),
+        }
+    }
+}
+
+/// Moderation event (kinds `9000-9020`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupModeration {
+    /// Group ID
+    pub group_id: String,
+    /// Action
+    pub action: GroupAction,
+    /// Reason
+    pub reason: String,
+    /// References of recent events of the group (see [`previous_reference`])
+    pub previous: Vec<String>,
+}
+
+impl GroupModeration {
+    /// New moderation event
+    pub fn new<S>(group_id: S, action: GroupAction) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            group_id: group_id.into(),
+            action,
+            reason: String::new(),
+            previous: Vec::new(),
+        }
+    }
+
+    /// Event kind
+    pub fn kind(&self) -> Kind {
+        self.action.kind()
+    }
+}
+
+impl From<GroupModeration> for Vec<Tag> {
+    fn from(moderation: GroupModeration) -> Self {
+        let GroupModeration {
+            group_id,
+            action,
+            previous,
+            ..
+        } = moderation;
+
+        let mut tags: Vec<Tag> = vec![Tag::Group(group_id)];
+        tags.extend(action.into_tags());
+        tags.extend(previous_tag(previous));
+        tags
+    }
+}
+
+impl TryFrom<&Event> for GroupModeration {
+    type Error = Error;
+
+    fn try_from(event: &Event) -> Result<Self, Self::Error> {
+        let kind: Kind = event.kind();
+        if !is_moderation(&kind) {
+            return Err(Error::WrongKind(kind));
+        }
+
+        let group_id: String = extract_group_id(event)
+            .ok_or(Error::MissingTag(TagKind::from("h")))?
+            .to_string();
+
+        let mut public_keys: Vec<(PublicKey, Vec<String>)> = Vec::new();
+        for tag in event.iter_tags() {
+            if let Some(p) = public_key_with_values(tag)? {
+                public_keys.push(p);
+            }
+        }
+        let mut public_keys = public_keys.into_iter();
+        let mut public_key = || {
+            public_keys
+                .next()
+                .ok_or(Error::MissingTag(TagKind::from("p")))
+        };
+
+        let custom_value = |name: &str| {
+            event.iter_tags().find_map(|tag| match tag {
+                Tag::Generic(TagKind::Custom(kind), values) if kind == name => {
+                    values.first().cloned()
+                }
+                _ => None,
+            })
+        };
+        let has_flag = |name: &str| {
+            event
+                .iter_tags()
+                .any(|tag| matches!(tag, Tag::Generic(TagKind::Custom(kind), ..) if kind == name))
+        };
+
+        let action: GroupAction = match kind {
+            Kind::GroupAddUser => {
+                let (public_key, roles) = public_key()?;
+                GroupAction::AddUser { public_key, roles }
+            }
+            Kind::GroupRemoveUser => GroupAction::RemoveUser {
+                public_key: public_key()?.0,
+            },
+            Kind::GroupEditMetadata => GroupAction::EditMetadata {
+                name: event.iter_tags().find_map(|tag| match tag {
+                    Tag::Name(name) => Some(name.clone()),
+                    _ => None,
+                }),
+                picture: custom_value("picture").map(UncheckedUrl::from),
+                about: custom_value("about"),
+            },
+            Kind::GroupAddPermission => {
+                let (public_key, permissions) = public_key()?;
+                GroupAction::AddPermission {
+                    public_key,
+                    permissions,
+                }
+            }
+            Kind::GroupRemovePermission => {
+                let (public_key, permissions) = public_key()?;
+                GroupAction::RemovePermission {
+                    public_key,
+                    permissions,
+                }
+            }
+            Kind::GroupDeleteEvent => GroupAction::DeleteEvent {
+                event_id: event
+                    .event_ids()
+                    .next()
+                    .copied()
+                    .ok_or(Error::MissingTag(TagKind::from("e")))?,
+            },
+            Kind::GroupEditStatus => GroupAction::EditStatus {
+                private: if has_flag("private") {
+                    Some(true)
+                } else if has_flag("public") {
+                    Some(false)
+                } else {
+                    None
+                },
+                closed: if has_flag("closed") {
+                    Some(true)
+                } else if has_flag("open") {
+                    Some(false)
+                } else {
+                    None
+                },
+            },
+            Kind::GroupCreate => GroupAction::CreateGroup,
+            kind => GroupAction::Other(kind),
+        };
+
+        Ok(Self {
+            group_id,
+            action,
+            reason: event.content().to_string(),
+            previous: extract_previous(event),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EventBuilder, JsonUtil, Keys};
+
+    #[test]
+    fn test_group_state() {
+        let relay = Keys::generate();
+        let alice = Keys::generate().public_key();
+        let bob = Keys::generate().public_key();
+        let carol = Keys::generate().public_key();
+        let dave = Keys::generate().public_key();
+
+        let metadata = GroupMetadata {
+            name: Some(String::from("Rust")),
+            about: Some(String::from("Rust nostr developers")),
+            closed: true,
+            ..GroupMetadata::new("abcd")
+        };
+        let event = EventBuilder::group_metadata(metadata.clone())
+            .to_event(&relay)
+            .unwrap();
+        assert_eq!(extract_group_id(&event), Some("abcd"));
+        assert_eq!(GroupMetadata::try_from(&event).unwrap(), metadata);
+
+        // Admins with many roles
+        let admins = GroupAdmins {
+            group_id: String::from("abcd"),
+            admins: vec![
+                GroupAdmin {
+                    public_key: alice,
+                    roles: vec![
+                        String::from("admin"),
+                        String::from("add-user"),
+                        String::from("delete-event"),
+                        String::from("remove-user"),
+                    ],
+                },
+                GroupAdmin {
+                    public_key: bob,
+                    roles: vec![String::from("moderator")],
+                },
+                GroupAdmin {
+                    public_key: carol,
+                    roles: vec![String::from("moderator"), String::from("delete-event")],
+                },
+                GroupAdmin {
+                    public_key: dave,
+                    roles: vec![
+                        String::from("moderator"),
+                        String::from("add-user"),
+                        String::from("remove-user"),
+                    ],
+                },
+            ],
+        };
+        let event = EventBuilder::group_admins(admins.clone())
+            .to_event(&relay)
+            .unwrap();
+        let event = Event::from_json(event.as_json()).unwrap();
+        assert_eq!(GroupAdmins::try_from(&event).unwrap(), admins);
+
+        let members = GroupMembers {
+            group_id: String::from("abcd"),
+            members: vec![alice, bob],
+        };
+        let event = EventBuilder::group_members(members.clone())
+            .to_event(&relay)
+            .unwrap();
+        assert_eq!(GroupMembers::try_from(&event).unwrap(), members);
+        assert_eq!(
+            GroupAdmins::try_from(&event).unwrap_err(),
+            Error::WrongKind(Kind::GroupMembers)
+        );
+    }
+
+    #[test]
+    fn test_group_requests_and_moderation() {
+        let keys = Keys::generate();
+        let bob = Keys::generate().public_key();
+
+        let event = EventBuilder::group_join_request("abcd", "Hi!")
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(
+            GroupRequest::try_from(&event).unwrap(),
+            GroupRequest::Join {
+                group_id: String::from("abcd"),
+                reason: String::from("Hi!")
+            }
+        );
+
+        let previous = vec![previous_reference(&event.id())];
+        let moderation = GroupModeration {
+            reason: String::from("Welcome"),
+            previous: previous.clone(),
+            ..GroupModeration::new(
+                "abcd",
+                GroupAction::AddUser {
+                    public_key: bob,
+                    roles: Vec::new(),
+                },
+            )
+        };
+        let event = EventBuilder::group_moderation(moderation.clone())
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(event.kind(), Kind::GroupAddUser);
+        assert_eq!(extract_previous(&event), previous);
+        assert_eq!(GroupModeration::try_from(&event).unwrap(), moderation);
+
+        let moderation = GroupModeration::new(
+            "abcd",
+            GroupAction::EditStatus {
+                private: Some(true),
+                closed: None,
+            },
+        );
+        let event = EventBuilder::group_moderation(moderation.clone())
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(GroupModeration::try_from(&event).unwrap(), moderation);
+
+        // Unknown moderation kind
+        let event = EventBuilder::new(Kind::from(9015), "", [Tag::Group(String::from("abcd"))])
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(
+            GroupModeration::try_from(&event).unwrap().action,
+            GroupAction::Other(Kind::from(9015))
+        );
+    }
+}
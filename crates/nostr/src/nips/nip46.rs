@@ -22,8 +22,8 @@ use bitcoin::secp256k1::{self, Message as Secp256k1Message, Secp256k1, Signing};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 
-use super::nip04;
 use super::nip26::{self, sign_delegation_with_ctx, Conditions};
+use super::{nip04, nip44};
 use crate::event::unsigned::{self, UnsignedEvent};
 use crate::key::{self, Keys};
 use crate::types::url::form_urlencoded::byte_serialize;
@@ -47,6 +47,8 @@ pub enum Error {
     NIP04(nip04::Error),
     /// NIP26 error
     NIP26(nip26::Error),
+    /// NIP44 error
+    NIP44(nip44::Error),
     /// Unsigned event error
     UnsignedEvent(unsigned::Error),
     /// Invalid request
@@ -73,6 +75,7 @@ impl fmt::Display for Error {
             Self::Secp256k1(e) => write!(f, "Secp256k1: {e}"),
             Self::NIP04(e) => write!(f, "NIP04: {e}"),
             Self::NIP26(e) => write!(f, "NIP26: {e}"),
+            Self::NIP44(e) => write!(f, "NIP44: {e}"),
             Self::UnsignedEvent(e) => write!(f, "{e}"),
             Self::InvalidRequest => write!(f, "Invalid request"),
             Self::InvalidParamsLength => write!(f, "Too many/few params"),
@@ -119,6 +122,12 @@ impl From<nip26::Error> for Error {
     }
 }
 
+impl From<nip44::Error> for Error {
+    fn from(e: nip44::Error) -> Self {
+        Self::NIP44(e)
+    }
+}
+
 impl From<unsigned::Error> for Error {
     fn from(e: unsigned::Error) -> Self {
         Self::UnsignedEvent(e)
@@ -135,7 +144,12 @@ pub enum Request {
     /// Sign [`UnsignedEvent`]
     SignEvent(UnsignedEvent),
     /// Connect
-    Connect(PublicKey),
+    Connect {
+        /// Remote signer public key
+        public_key: PublicKey,
+        /// Optional secret
+        secret: Option<String>,
+    },
     /// Disconnect
     Disconnect,
     /// Delegate
@@ -159,6 +173,20 @@ pub enum Request {
         /// Ciphertext
         text: String,
     },
+    /// Encrypt text (NIP44)
+    Nip44Encrypt {
+        /// Pubkey
+        public_key: PublicKey,
+        /// Plain text
+        text: String,
+    },
+    /// Decrypt (NIP44)
+    Nip44Decrypt {
+        /// Pubkey
+        public_key: PublicKey,
+        /// Payload
+        text: String,
+    },
     /// Sign Schnorr
     SignSchnorr(String),
 }
@@ -170,11 +198,13 @@ impl Request {
             Self::Describe => "describe".to_string(),
             Self::GetPublicKey => "get_public_key".to_string(),
             Self::SignEvent(_) => "sign_event".to_string(),
-            Self::Connect(_) => "connect".to_string(),
+            Self::Connect { .. } => "connect".to_string(),
             Self::Disconnect => "disconnect".to_string(),
             Self::Delegate { .. } => "delegate".to_string(),
             Self::Nip04Encrypt { .. } => "nip04_encrypt".to_string(),
             Self::Nip04Decrypt { .. } => "nip04_decrypt".to_string(),
+            Self::Nip44Encrypt { .. } => "nip44_encrypt".to_string(),
+            Self::Nip44Decrypt { .. } => "nip44_decrypt".to_string(),
             Self::SignSchnorr(_) => "sign_schnorr".to_string(),
         }
     }
@@ -185,7 +215,10 @@ impl Request {
             Self::Describe => Vec::new(),
             Self::GetPublicKey => Vec::new(),
             Self::SignEvent(event) => vec![json!(event)],
-            Self::Connect(pubkey) => vec![json!(pubkey)],
+            Self::Connect { public_key, secret } => match secret {
+                Some(secret) => vec![json!(public_key), json!(secret)],
+                None => vec![json!(public_key)],
+            },
             Self::Disconnect => Vec::new(),
             Self::Delegate {
                 public_key,
@@ -193,6 +226,8 @@ impl Request {
             } => vec![json!(public_key), json!(conditions)],
             Self::Nip04Encrypt { public_key, text } => vec![json!(public_key), json!(text)],
             Self::Nip04Decrypt { public_key, text } => vec![json!(public_key), json!(text)],
+            Self::Nip44Encrypt { public_key, text } => vec![json!(public_key), json!(text)],
+            Self::Nip44Decrypt { public_key, text } => vec![json!(public_key), json!(text)],
             Self::SignSchnorr(value) => vec![json!(value)],
         }
     }
@@ -224,6 +259,8 @@ impl Request {
                 String::from("delegate"),
                 String::from("nip04_encrypt"),
                 String::from("nip04_decrypt"),
+                String::from("nip44_encrypt"),
+                String::from("nip44_decrypt"),
                 String::from("sign_schnorr"),
             ])),
             Self::GetPublicKey => Some(Response::GetPublicKey(keys.public_key())),
@@ -231,7 +268,7 @@ impl Request {
                 let signed_event = unsigned_event.sign_with_ctx(secp, rng, keys)?;
                 Some(Response::SignEvent(signed_event))
             }
-            Self::Connect(_) => None,
+            Self::Connect { .. } => None,
             Self::Disconnect => None,
             Self::Delegate {
                 public_key,
@@ -257,6 +294,20 @@ impl Request {
                 let decrypted_content = nip04::decrypt(keys.secret_key()?, &public_key, text)?;
                 Some(Response::Nip04Decrypt(decrypted_content))
             }
+            Self::Nip44Encrypt { public_key, text } => {
+                let payload = nip44::encrypt_with_rng(
+                    rng,
+                    keys.secret_key()?,
+                    &public_key,
+                    text,
+                    nip44::Version::default(),
+                )?;
+                Some(Response::Nip44Encrypt(payload))
+            }
+            Self::Nip44Decrypt { public_key, text } => {
+                let content = nip44::decrypt(keys.secret_key()?, &public_key, text)?;
+                Some(Response::Nip44Decrypt(content))
+            }
             Self::SignSchnorr(value) => {
                 let hash = Sha256Hash::hash(value.as_bytes());
                 let message = Secp256k1Message::from(hash);
@@ -296,6 +347,10 @@ pub enum Response {
     Nip04Encrypt(String),
     /// Decrypted content (NIP04)
     Nip04Decrypt(String),
+    /// Encrypted content (NIP44)
+    Nip44Encrypt(String),
+    /// Decrypted content (NIP44)
+    Nip44Decrypt(String),
     /// Sign Schnorr
     SignSchnorr(Signature),
 }
@@ -357,6 +412,8 @@ impl Message {
                 Response::Delegate(delegation_result) => json!(delegation_result),
                 Response::Nip04Encrypt(encrypted_content) => json!(encrypted_content),
                 Response::Nip04Decrypt(decrypted_content) => json!(decrypted_content),
+                Response::Nip44Encrypt(payload) => json!(payload),
+                Response::Nip44Decrypt(content) => json!(content),
                 Response::SignSchnorr(sig) => json!(sig),
             }),
             error: error.map(|e| e.into()),
@@ -395,12 +452,18 @@ impl Message {
                     }
                 }
                 "connect" => {
-                    if params.len() != 1 {
+                    // The optional requested permissions are ignored
+                    if params.is_empty() || params.len() > 3 {
                         return Err(Error::InvalidParamsLength);
                     }
 
-                    let pubkey: PublicKey = serde_json::from_value(params[0].to_owned())?;
-                    Ok(Request::Connect(pubkey))
+                    let public_key: PublicKey = serde_json::from_value(params[0].to_owned())?;
+                    let secret: Option<String> = params
+                        .get(1)
+                        .and_then(|secret| secret.as_str())
+                        .filter(|secret| !secret.is_empty())
+                        .map(|secret| secret.to_string());
+                    Ok(Request::Connect { public_key, secret })
                 }
                 "disconnect" => Ok(Request::Disconnect),
                 "delegate" => {
@@ -433,6 +496,26 @@ impl Message {
                         text: serde_json::from_value(params[1].clone())?,
                     })
                 }
+                "nip44_encrypt" => {
+                    if params.len() != 2 {
+                        return Err(Error::InvalidParamsLength);
+                    }
+
+                    Ok(Request::Nip44Encrypt {
+                        public_key: serde_json::from_value(params[0].clone())?,
+                        text: serde_json::from_value(params[1].clone())?,
+                    })
+                }
+                "nip44_decrypt" => {
+                    if params.len() != 2 {
+                        return Err(Error::InvalidParamsLength);
+                    }
+
+                    Ok(Request::Nip44Decrypt {
+                        public_key: serde_json::from_value(params[0].clone())?,
+                        text: serde_json::from_value(params[1].clone())?,
+                    })
+                }
                 "sign_schnorr" => {
                     if params.len() != 1 {
                         return Err(Error::InvalidParamsLength);
@@ -712,4 +795,59 @@ mod tests {
         let app_name = "Example";
         assert_eq!(uri, NostrConnectURI::new(pubkey, relay_url, app_name));
     }
+
+    #[test]
+    fn test_nip44_request() {
+        let keys = Keys::generate();
+        let app_keys = Keys::generate();
+
+        let req = Request::Nip44Encrypt {
+            public_key: app_keys.public_key(),
+            text: String::from("Hello"),
+        };
+        let msg = Message::request(req.clone());
+        let msg = Message::from_json(msg.as_json()).unwrap();
+        assert_eq!(msg.to_request().unwrap(), req);
+
+        let payload = match req.generate_response(&keys).unwrap() {
+            Some(Response::Nip44Encrypt(payload)) => payload,
+            res => panic!("unexpected response: {res:?}"),
+        };
+
+        let req = Request::Nip44Decrypt {
+            public_key: keys.public_key(),
+            text: payload,
+        };
+        assert_eq!(
+            req.generate_response(&app_keys).unwrap(),
+            Some(Response::Nip44Decrypt(String::from("Hello")))
+        );
+    }
+
+    #[test]
+    fn test_connect_request() {
+        let keys = Keys::generate();
+
+        let req = Request::Connect {
+            public_key: keys.public_key(),
+            secret: Some(String::from("secret")),
+        };
+        let msg = Message::request(req.clone());
+        let msg = Message::from_json(msg.as_json()).unwrap();
+        assert_eq!(msg.to_request().unwrap(), req);
+
+        // Without secret and with requested permissions
+        let json = format!(
+            r#"{{"id":"1","method":"connect","params":["{}","","sign_event:1"]}}"#,
+            keys.public_key()
+        );
+        let msg = Message::from_json(json).unwrap();
+        assert_eq!(
+            msg.to_request().unwrap(),
+            Request::Connect {
+                public_key: keys.public_key(),
+                secret: None,
+            }
+        );
+    }
 }
@@ -5,13 +5,104 @@
 //!
 //! <https://github.com/nostr-protocol/nips/blob/master/51.md>
 
-use alloc::string::String;
+use alloc::string::{String, ToString};
 use alloc::vec::Vec;
+use core::fmt;
 
 use super::nip01::Coordinate;
-use crate::{EventId, PublicKey, Tag, UncheckedUrl, Url};
+use crate::event::tag;
+use crate::{Event, EventId, Kind, PublicKey, Tag, TagKind, UncheckedUrl, Url};
+
+/// NIP51 error
+#[derive(Debug)]
+pub enum Error {
+    /// Tag error
+    Tag(tag::Error),
+    /// JSON error
+    Json(serde_json::Error),
+    /// Unexpected kind
+    WrongKind(Kind),
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tag(e) => write!(f, "Tag: {e}"),
+            Self::Json(e) => write!(f, "Json: {e}"),
+            Self::WrongKind(kind) => write!(f, "Wrong kind: {kind}"),
+        }
+    }
+}
+
+impl From<tag::Error> for Error {
+    fn from(e: tag::Error) -> Self {
+        Self::Tag(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Json(e)
+    }
+}
+
+fn check_kind(event: &Event, kinds: &[Kind]) -> Result<(), Error> {
+    if kinds.contains(&event.kind()) {
+        Ok(())
+    } else {
+        Err(Error::WrongKind(event.kind()))
+    }
+}
+
+/// Public and private items of a list or set
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ListItems {
+    /// Public items (event tags)
+    pub public: Vec<Tag>,
+    /// Private items (encrypted in the event content)
+    pub private: Vec<Tag>,
+}
+
+impl ListItems {
+    /// Iterate both public and private items
+    pub fn iter(&self) -> impl Iterator<Item = &Tag> {
+        self.public.iter().chain(self.private.iter())
+    }
+}
+
+/// Serialize private items, to be encrypted in the event content
+pub fn private_items_to_json<'a, I>(tags: I) -> String
+where
+    I: IntoIterator<Item = &'a Tag>,
+{
+    let tags: Vec<Vec<String>> = tags.into_iter().map(|t| t.as_vec()).collect();
+    serde_json::json!(tags).to_string()
+}
+
+/// Deserialize decrypted private items
+pub fn private_items_from_json<S>(json: S) -> Result<Vec<Tag>, Error>
+where
+    S: AsRef<str>,
+{
+    let tags: Vec<Vec<String>> = serde_json::from_str(json.as_ref())?;
+    tags.into_iter()
+        .map(|t| Tag::parse(t).map_err(Error::from))
+        .collect()
+}
+
+/// Check if the private items are encrypted with NIP04 (deprecated) instead of NIP44
+pub fn is_nip04_content<S>(content: S) -> bool
+where
+    S: AsRef<str>,
+{
+    content.as_ref().contains("?iv=")
+}
 
 /// Things the user doesn't want to see in their feeds
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct MuteList {
     /// Public Keys
     pub public_keys: Vec<PublicKey>,
@@ -44,7 +135,80 @@ impl From<MuteList> for Vec<Tag> {
     }
 }
 
+impl MuteList {
+    /// Compose from tags, skipping the unrelated ones
+    pub fn from_tags<'a, I>(tags: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Tag>,
+    {
+        let mut list = Self::default();
+        for tag in tags.into_iter() {
+            match tag {
+                Tag::PublicKey {
+                    public_key,
+                    uppercase: false,
+                    ..
+                } => list.public_keys.push(*public_key),
+                Tag::Hashtag(hashtag) => list.hashtags.push(hashtag.clone()),
+                Tag::Event { event_id, .. } => list.event_ids.push(*event_id),
+                Tag::Word(word) => list.words.push(word.clone()),
+                _ => {}
+            }
+        }
+        list
+    }
+}
+
+impl TryFrom<&Event> for MuteList {
+    type Error = Error;
+
+    fn try_from(event: &Event) -> Result<Self, Self::Error> {
+        check_kind(event, &[Kind::MuteList])?;
+        Ok(Self::from_tags(event.iter_tags()))
+    }
+}
+
+/// Events the user intends to showcase in their profile page
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PinnedNotes {
+    /// Event IDs
+    pub event_ids: Vec<EventId>,
+}
+
+impl From<PinnedNotes> for Vec<Tag> {
+    fn from(PinnedNotes { event_ids }: PinnedNotes) -> Self {
+        event_ids.into_iter().map(Tag::event).collect()
+    }
+}
+
+impl PinnedNotes {
+    /// Compose from tags, skipping the unrelated ones
+    pub fn from_tags<'a, I>(tags: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Tag>,
+    {
+        let event_ids = tags
+            .into_iter()
+            .filter_map(|tag| match tag {
+                Tag::Event { event_id, .. } => Some(*event_id),
+                _ => None,
+            })
+            .collect();
+        Self { event_ids }
+    }
+}
+
+impl TryFrom<&Event> for PinnedNotes {
+    type Error = Error;
+
+    fn try_from(event: &Event) -> Result<Self, Self::Error> {
+        check_kind(event, &[Kind::PinList])?;
+        Ok(Self::from_tags(event.iter_tags()))
+    }
+}
+
 /// Uncategorized, "global" list of things a user wants to save
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct Bookmarks {
     /// Event IDs
     pub event_ids: Vec<EventId>,
@@ -77,7 +241,204 @@ impl From<Bookmarks> for Vec<Tag> {
     }
 }
 
+impl Bookmarks {
+    /// Compose from tags, skipping the unrelated ones
+    pub fn from_tags<'a, I>(tags: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Tag>,
+    {
+        let mut list = Self::default();
+        for tag in tags.into_iter() {
+            match tag {
+                Tag::Event { event_id, .. } => list.event_ids.push(*event_id),
+                Tag::A { coordinate, .. } => list.coordinate.push(coordinate.clone()),
+                Tag::Hashtag(hashtag) => list.hashtags.push(hashtag.clone()),
+                Tag::Url(url) => list.urls.push(url.clone()),
+                _ => {}
+            }
+        }
+        list
+    }
+}
+
+impl TryFrom<&Event> for Bookmarks {
+    type Error = Error;
+
+    /// Bookmarks or bookmark set
+    fn try_from(event: &Event) -> Result<Self, Self::Error> {
+        check_kind(event, &[Kind::Bookmarks, Kind::BookmarkSets])?;
+        Ok(Self::from_tags(event.iter_tags()))
+    }
+}
+
+/// Communities the user belongs to
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Communities {
+    /// Community definitions
+    pub coordinates: Vec<Coordinate>,
+}
+
+impl From<Communities> for Vec<Tag> {
+    fn from(Communities { coordinates }: Communities) -> Self {
+        coordinates.into_iter().map(Tag::from).collect()
+    }
+}
+
+impl Communities {
+    /// Compose from tags, skipping the unrelated ones
+    pub fn from_tags<'a, I>(tags: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Tag>,
+    {
+        Self {
+            coordinates: coordinates(tags),
+        }
+    }
+}
+
+impl TryFrom<&Event> for Communities {
+    type Error = Error;
+
+    fn try_from(event: &Event) -> Result<Self, Self::Error> {
+        check_kind(event, &[Kind::Communities])?;
+        Ok(Self::from_tags(event.iter_tags()))
+    }
+}
+
+/// NIP28 chat channels the user is in
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PublicChats {
+    /// Channel definitions (kind 40)
+    pub event_ids: Vec<EventId>,
+}
+
+impl From<PublicChats> for Vec<Tag> {
+    fn from(PublicChats { event_ids }: PublicChats) -> Self {
+        event_ids.into_iter().map(Tag::event).collect()
+    }
+}
+
+impl PublicChats {
+    /// Compose from tags, skipping the unrelated ones
+    pub fn from_tags<'a, I>(tags: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Tag>,
+    {
+        let PinnedNotes { event_ids } = PinnedNotes::from_tags(tags);
+        Self { event_ids }
+    }
+}
+
+impl TryFrom<&Event> for PublicChats {
+    type Error = Error;
+
+    fn try_from(event: &Event) -> Result<Self, Self::Error> {
+        check_kind(event, &[Kind::PublicChats])?;
+        Ok(Self::from_tags(event.iter_tags()))
+    }
+}
+
+/// Relays of blocked relays, search relays or relay set
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Relays {
+    /// Relay URLs
+    pub relays: Vec<UncheckedUrl>,
+}
+
+impl From<Relays> for Vec<Tag> {
+    fn from(Relays { relays }: Relays) -> Self {
+        relays.into_iter().map(Tag::Relay).collect()
+    }
+}
+
+impl Relays {
+    /// Compose from tags, skipping the unrelated ones
+    pub fn from_tags<'a, I>(tags: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Tag>,
+    {
+        let relays = tags
+            .into_iter()
+            .filter_map(|tag| match tag {
+                Tag::Relay(url) => Some(url.clone()),
+                _ => None,
+            })
+            .collect();
+        Self { relays }
+    }
+}
+
+impl TryFrom<&Event> for Relays {
+    type Error = Error;
+
+    /// Blocked relays, search relays or relay set
+    fn try_from(event: &Event) -> Result<Self, Self::Error> {
+        check_kind(
+            event,
+            &[Kind::BlockedRelays, Kind::SearchRelays, Kind::RelaySets],
+        )?;
+        Ok(Self::from_tags(event.iter_tags()))
+    }
+}
+
+/// NIP29 groups the user is in
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SimpleGroups {
+    /// Group IDs, with the relay of the group
+    pub groups: Vec<(String, UncheckedUrl)>,
+    /// Relays in use
+    pub relays: Vec<UncheckedUrl>,
+}
+
+impl From<SimpleGroups> for Vec<Tag> {
+    fn from(SimpleGroups { groups, relays }: SimpleGroups) -> Self {
+        let mut tags = Vec::with_capacity(groups.len() + relays.len());
+
+        tags.extend(groups.into_iter().map(|(id, url)| {
+            Tag::Generic(
+                TagKind::Custom(String::from("group")),
+                vec![id, url.to_string()],
+            )
+        }));
+        tags.extend(relays.into_iter().map(|url| Tag::RelayMetadata(url, None)));
+
+        tags
+    }
+}
+
+impl SimpleGroups {
+    /// Compose from tags, skipping the unrelated ones
+    pub fn from_tags<'a, I>(tags: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Tag>,
+    {
+        let mut list = Self::default();
+        for tag in tags.into_iter() {
+            match tag {
+                Tag::Generic(TagKind::Custom(kind), values) if kind == "group" => {
+                    if let [id, url, ..] = values.as_slice() {
+                        list.groups.push((id.clone(), UncheckedUrl::from(url)));
+                    }
+                }
+                Tag::RelayMetadata(url, ..) => list.relays.push(url.clone()),
+                _ => {}
+            }
+        }
+        list
+    }
+}
+
+impl TryFrom<&Event> for SimpleGroups {
+    type Error = Error;
+
+    fn try_from(event: &Event) -> Result<Self, Self::Error> {
+        check_kind(event, &[Kind::SimpleGroups])?;
+        Ok(Self::from_tags(event.iter_tags()))
+    }
+}
+
 /// Topics a user may be interested in and pointers
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct Interests {
     /// Hashtags
     pub hashtags: Vec<String>,
@@ -101,7 +462,36 @@ impl From<Interests> for Vec<Tag> {
     }
 }
 
+impl Interests {
+    /// Compose from tags, skipping the unrelated ones
+    pub fn from_tags<'a, I>(tags: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Tag>,
+    {
+        let mut list = Self::default();
+        for tag in tags.into_iter() {
+            match tag {
+                Tag::Hashtag(hashtag) => list.hashtags.push(hashtag.clone()),
+                Tag::A { coordinate, .. } => list.coordinate.push(coordinate.clone()),
+                _ => {}
+            }
+        }
+        list
+    }
+}
+
+impl TryFrom<&Event> for Interests {
+    type Error = Error;
+
+    /// Interests or interest set
+    fn try_from(event: &Event) -> Result<Self, Self::Error> {
+        check_kind(event, &[Kind::Interests, Kind::InterestSets])?;
+        Ok(Self::from_tags(event.iter_tags()))
+    }
+}
+
 /// User preferred emojis and pointers to emoji sets
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct Emojis {
     /// Emojis
     pub emojis: Vec<(String, UncheckedUrl)>,
@@ -124,7 +514,79 @@ impl From<Emojis> for Vec<Tag> {
     }
 }
 
+impl Emojis {
+    /// Compose from tags, skipping the unrelated ones
+    pub fn from_tags<'a, I>(tags: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Tag>,
+    {
+        let mut list = Self::default();
+        for tag in tags.into_iter() {
+            match tag {
+                Tag::Emoji { shortcode, url } => list.emojis.push((shortcode.clone(), url.clone())),
+                Tag::A { coordinate, .. } => list.coordinate.push(coordinate.clone()),
+                _ => {}
+            }
+        }
+        list
+    }
+}
+
+impl TryFrom<&Event> for Emojis {
+    type Error = Error;
+
+    /// Emojis or emoji set
+    fn try_from(event: &Event) -> Result<Self, Self::Error> {
+        check_kind(event, &[Kind::Emojis, Kind::EmojiSets])?;
+        Ok(Self::from_tags(event.iter_tags()))
+    }
+}
+
+/// Categorized groups of users a client may choose to check out in different circumstances
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FollowSet {
+    /// Public Keys
+    pub public_keys: Vec<PublicKey>,
+}
+
+impl From<FollowSet> for Vec<Tag> {
+    fn from(FollowSet { public_keys }: FollowSet) -> Self {
+        public_keys.into_iter().map(Tag::public_key).collect()
+    }
+}
+
+impl FollowSet {
+    /// Compose from tags, skipping the unrelated ones
+    pub fn from_tags<'a, I>(tags: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Tag>,
+    {
+        let public_keys = tags
+            .into_iter()
+            .filter_map(|tag| match tag {
+                Tag::PublicKey {
+                    public_key,
+                    uppercase: false,
+                    ..
+                } => Some(*public_key),
+                _ => None,
+            })
+            .collect();
+        Self { public_keys }
+    }
+}
+
+impl TryFrom<&Event> for FollowSet {
+    type Error = Error;
+
+    fn try_from(event: &Event) -> Result<Self, Self::Error> {
+        check_kind(event, &[Kind::FollowSets])?;
+        Ok(Self::from_tags(event.iter_tags()))
+    }
+}
+
 /// Groups of articles picked by users as interesting and/or belonging to the same category
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct ArticlesCuration {
     /// Coordinates
     pub coordinate: Vec<Coordinate>,
@@ -147,3 +609,237 @@ impl From<ArticlesCuration> for Vec<Tag> {
         tags
     }
 }
+
+impl ArticlesCuration {
+    /// Compose from tags, skipping the unrelated ones
+    pub fn from_tags<'a, I>(tags: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Tag>,
+    {
+        let mut list = Self::default();
+        for tag in tags.into_iter() {
+            match tag {
+                Tag::A { coordinate, .. } => list.coordinate.push(coordinate.clone()),
+                Tag::Event { event_id, .. } => list.event_ids.push(*event_id),
+                _ => {}
+            }
+        }
+        list
+    }
+}
+
+impl TryFrom<&Event> for ArticlesCuration {
+    type Error = Error;
+
+    fn try_from(event: &Event) -> Result<Self, Self::Error> {
+        check_kind(event, &[Kind::ArticlesCurationSets])?;
+        Ok(Self::from_tags(event.iter_tags()))
+    }
+}
+
+/// Groups of videos picked by users as interesting and/or belonging to the same category
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VideosCuration {
+    /// Coordinates
+    pub coordinates: Vec<Coordinate>,
+}
+
+impl From<VideosCuration> for Vec<Tag> {
+    fn from(VideosCuration { coordinates }: VideosCuration) -> Self {
+        coordinates.into_iter().map(Tag::from).collect()
+    }
+}
+
+impl VideosCuration {
+    /// Compose from tags, skipping the unrelated ones
+    pub fn from_tags<'a, I>(tags: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Tag>,
+    {
+        Self {
+            coordinates: coordinates(tags),
+        }
+    }
+}
+
+impl TryFrom<&Event> for VideosCuration {
+    type Error = Error;
+
+    fn try_from(event: &Event) -> Result<Self, Self::Error> {
+        check_kind(event, &[Kind::VideosCurationSets])?;
+        Ok(Self::from_tags(event.iter_tags()))
+    }
+}
+
+/// Groups of artifacts of a software release (release artifact sets)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReleaseArtifacts {
+    /// File metadata events (kind `1063`)
+    pub event_ids: Vec<EventId>,
+    /// Software application events
+    pub coordinates: Vec<Coordinate>,
+}
+
+impl From<ReleaseArtifacts> for Vec<Tag> {
+    fn from(
+        ReleaseArtifacts {
+            event_ids,
+            coordinates,
+        }: ReleaseArtifacts,
+    ) -> Self {
+        let mut tags = Vec::with_capacity(event_ids.len() + coordinates.len());
+
+        tags.extend(event_ids.into_iter().map(Tag::event));
+        tags.extend(coordinates.into_iter().map(Tag::from));
+
+        tags
+    }
+}
+
+impl ReleaseArtifacts {
+    /// Compose from tags, skipping the unrelated ones
+    pub fn from_tags<'a, I>(tags: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Tag>,
+    {
+        let mut list = Self::default();
+        for tag in tags.into_iter() {
+            match tag {
+                Tag::Event { event_id, .. } => list.event_ids.push(*event_id),
+                Tag::A { coordinate, .. } => list.coordinates.push(coordinate.clone()),
+                _ => {}
+            }
+        }
+        list
+    }
+}
+
+impl TryFrom<&Event> for ReleaseArtifacts {
+    type Error = Error;
+
+    fn try_from(event: &Event) -> Result<Self, Self::Error> {
+        check_kind(event, &[Kind::ReleaseArtifactSets])?;
+        Ok(Self::from_tags(event.iter_tags()))
+    }
+}
+
+fn coordinates<'a, I>(tags: I) -> Vec<Coordinate>
+where
+    I: IntoIterator<Item = &'a Tag>,
+{
+    tags.into_iter()
+        .filter_map(|tag| match tag {
+            Tag::A { coordinate, .. } => Some(coordinate.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EventBuilder, Keys};
+
+    #[test]
+    fn test_mute_list_roundtrip() {
+        let keys = Keys::generate();
+        let list = MuteList {
+            public_keys: vec![keys.public_key()],
+            hashtags: vec![String::from("nostr")],
+            event_ids: vec![EventId::all_zeros()],
+            words: vec![String::from("gm")],
+        };
+        let event = EventBuilder::mute_list(list.clone())
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(MuteList::try_from(&event).unwrap(), list);
+
+        // Wrong kind
+        assert!(matches!(
+            Bookmarks::try_from(&event),
+            Err(Error::WrongKind(Kind::MuteList))
+        ));
+    }
+
+    #[test]
+    fn test_sets() {
+        let keys = Keys::generate();
+        let list = Bookmarks {
+            event_ids: vec![EventId::all_zeros()],
+            urls: vec![Url::parse("https://rust-nostr.org").unwrap()],
+            ..Default::default()
+        };
+        let mut tags: Vec<Tag> = list.clone().into();
+        tags.push(Tag::Identifier(String::from("test")));
+        let event = EventBuilder::new(Kind::BookmarkSets, "", tags)
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(event.identifier(), Some("test"));
+        assert_eq!(Bookmarks::try_from(&event).unwrap(), list);
+
+        let relays = Relays {
+            relays: vec![UncheckedUrl::from("wss://relay.damus.io")],
+        };
+        let event = EventBuilder::relay_sets(relays.relays.clone())
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(Relays::try_from(&event).unwrap(), relays);
+
+        let artifacts = ReleaseArtifacts {
+            event_ids: vec![EventId::all_zeros()],
+            coordinates: vec![
+                Coordinate::new(Kind::from(32267), keys.public_key()).identifier("com.example.app")
+            ],
+        };
+        let mut tags: Vec<Tag> = artifacts.clone().into();
+        tags.push(Tag::Identifier(String::from("com.example.app@1.0.0")));
+        let event = EventBuilder::new(Kind::ReleaseArtifactSets, "", tags)
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(ReleaseArtifacts::try_from(&event).unwrap(), artifacts);
+    }
+
+    #[test]
+    fn test_simple_groups() {
+        let groups = SimpleGroups {
+            groups: vec![(
+                String::from("abcd"),
+                UncheckedUrl::from("wss://groups.nostr.com"),
+            )],
+            relays: vec![UncheckedUrl::from("wss://groups.nostr.com")],
+        };
+        let tags: Vec<Tag> = groups.clone().into();
+        assert_eq!(
+            tags[0].as_vec(),
+            vec!["group", "abcd", "wss://groups.nostr.com"]
+        );
+        assert_eq!(SimpleGroups::from_tags(&tags), groups);
+    }
+
+    #[test]
+    fn test_private_items() {
+        let items = ListItems {
+            public: vec![Tag::Hashtag(String::from("nostr"))],
+            private: vec![
+                Tag::Word(String::from("gm")),
+                Tag::event(EventId::all_zeros()),
+            ],
+        };
+        let json = private_items_to_json(&items.private);
+        assert_eq!(
+            json,
+            r#"[["word","gm"],["e","0000000000000000000000000000000000000000000000000000000000000000"]]"#
+        );
+        assert_eq!(private_items_from_json(json).unwrap(), items.private);
+
+        let list = MuteList::from_tags(items.iter());
+        assert_eq!(list.hashtags, vec![String::from("nostr")]);
+        assert_eq!(list.words, vec![String::from("gm")]);
+        assert_eq!(list.event_ids, vec![EventId::all_zeros()]);
+
+        assert!(is_nip04_content("abcd?iv=efgh"));
+        assert!(!is_nip04_content(
+            "AgAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA"
+        ));
+    }
+}
@@ -0,0 +1,901 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! NIP52
+//!
+//! <https://github.com/nostr-protocol/nips/blob/master/52.md>
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::num::ParseIntError;
+use core::str::FromStr;
+
+use crate::event::id;
+use crate::nips::nip01::{self, Coordinate};
+use crate::{key, EventId, PublicKey, Tag, TagKind, Timestamp, UncheckedUrl};
+
+/// NIP52 Error
+#[derive(Debug)]
+pub enum Error {
+    /// Key error
+    Key(key::Error),
+    /// Event ID error
+    EventId(id::Error),
+    /// NIP01 error
+    NIP01(nip01::Error),
+    /// Parse Int error
+    ParseInt(ParseIntError),
+    /// Invalid date (`YYYY-MM-DD`)
+    InvalidDate(String),
+    /// Unknown [`RsvpStatus`]
+    UnknownRsvpStatus(String),
+    /// Unknown [`FreeBusy`]
+    UnknownFreeBusy(String),
+    /// Missing tag
+    MissingTag(TagKind),
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Key(e) => write!(f, "Key: {e}"),
+            Self::EventId(e) => write!(f, "Event ID: {e}"),
+            Self::NIP01(e) => write!(f, "NIP01: {e}"),
+            Self::ParseInt(e) => write!(f, "Parse Int: {e}"),
+            Self::InvalidDate(d) => write!(f, "Invalid date: {d}"),
+            Self::UnknownRsvpStatus(s) => write!(f, "Unknown RSVP status: {s}"),
+            Self::UnknownFreeBusy(s) => write!(f, "Unknown free/busy: {s}"),
+            Self::MissingTag(kind) => write!(f, "Missing '{kind}' tag"),
+        }
+    }
+}
+
+impl From<key::Error> for Error {
+    fn from(e: key::Error) -> Self {
+        Self::Key(e)
+    }
+}
+
+impl From<id::Error> for Error {
+    fn from(e: id::Error) -> Self {
+        Self::EventId(e)
+    }
+}
+
+impl From<nip01::Error> for Error {
+    fn from(e: nip01::Error) -> Self {
+        Self::NIP01(e)
+    }
+}
+
+impl From<ParseIntError> for Error {
+    fn from(e: ParseIntError) -> Self {
+        Self::ParseInt(e)
+    }
+}
+
+/// Calendar date (`YYYY-MM-DD`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CalendarDate {
+    year: u16,
+    month: u8,
+    day: u8,
+}
+
+impl CalendarDate {
+    /// New calendar date
+    pub fn new(year: u16, month: u8, day: u8) -> Result<Self, Error> {
+        let leap: bool = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+        let days: u8 = match month {
+            1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+            4 | 6 | 9 | 11 => 30,
+            2 if leap => 29,
+            2 => 28,
+            _ => 0,
+        };
+
+        if day == 0 || day > days {
+            return Err(Error::InvalidDate(format!("{year:04}-{month:02}-{day:02}")));
+        }
+
+        Ok(Self { year, month, day })
+    }
+
+    /// Year
+    pub fn year(&self) -> u16 {
+        self.year
+    }
+
+    /// Month (`1-12`)
+    pub fn month(&self) -> u8 {
+        self.month
+    }
+
+    /// Day of the month (`1-31`)
+    pub fn day(&self) -> u8 {
+        self.day
+    }
+}
+
+impl fmt::Display for CalendarDate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+impl FromStr for CalendarDate {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidDate(s.to_string());
+        let mut ymd = s.split('-');
+        match (ymd.next(), ymd.next(), ymd.next(), ymd.next()) {
+            (Some(year), Some(month), Some(day), None)
+                if year.len() == 4 && month.len() == 2 && day.len() == 2 =>
+            {
+                Self::new(
+                    year.parse().map_err(|_| invalid())?,
+                    month.parse().map_err(|_| invalid())?,
+                    day.parse().map_err(|_| invalid())?,
+                )
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Calendar event participant
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CalendarEventParticipant {
+    /// Public key
+    pub public_key: PublicKey,
+    /// Relay URL
+    pub relay_url: Option<UncheckedUrl>,
+    /// Role in the meeting (ex. `speaker`)
+    pub role: Option<String>,
+}
+
+impl From<CalendarEventParticipant> for Tag {
+    fn from(participant: CalendarEventParticipant) -> Self {
+        let CalendarEventParticipant {
+            public_key,
+            relay_url,
+            role,
+        } = participant;
+
+        // Keep the position of the role
+        let relay_url: Option<UncheckedUrl> = match (relay_url, &role) {
+            (None, Some(..)) => Some(UncheckedUrl::empty()),
+            (relay_url, ..) => relay_url,
+        };
+
+        Self::PublicKey {
+            public_key,
+            relay_url,
+            alias: role,
+            uppercase: false,
+        }
+    }
+}
+
+/// Date-Based Calendar Event (kind `31922`)
+///
+/// All-day or multi-day event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DateBasedCalendarEvent {
+    /// Unique event ID
+    pub id: String,
+    /// Title
+    pub title: String,
+    /// Start date
+    pub start: CalendarDate,
+    /// End date (exclusive)
+    pub end: Option<CalendarDate>,
+    /// Locations (ex. address or URL)
+    pub locations: Vec<String>,
+    /// Geohash
+    pub geohash: Option<String>,
+    /// Participants
+    pub participants: Vec<CalendarEventParticipant>,
+    /// Hashtags
+    pub hashtags: Vec<String>,
+    /// References (URLs)
+    pub references: Vec<String>,
+}
+
+impl DateBasedCalendarEvent {
+    /// New date-based calendar event
+    pub fn new<S>(id: S, title: S, start: CalendarDate) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            id: id.into(),
+            title: title.into(),
+            start,
+            end: None,
+            locations: Vec::new(),
+            geohash: None,
+            participants: Vec::new(),
+            hashtags: Vec::new(),
+            references: Vec::new(),
+        }
+    }
+}
+
+impl From<DateBasedCalendarEvent> for Vec<Tag> {
+    fn from(event: DateBasedCalendarEvent) -> Self {
+        let DateBasedCalendarEvent {
+            id,
+            title,
+            start,
+            end,
+            locations,
+            geohash,
+            participants,
+            hashtags,
+            references,
+        } = event;
+
+        let mut tags: Vec<Tag> = vec![
+            Tag::Identifier(id),
+            Tag::Title(title),
+            generic(TagKind::Custom(String::from("start")), start.to_string()),
+        ];
+
+        if let Some(end) = end {
+            tags.push(generic(
+                TagKind::Custom(String::from("end")),
+                end.to_string(),
+            ));
+        }
+
+        push_common_tags(
+            &mut tags,
+            locations,
+            geohash,
+            participants,
+            hashtags,
+            references,
+        );
+
+        tags
+    }
+}
+
+impl TryFrom<Vec<Tag>> for DateBasedCalendarEvent {
+    type Error = Error;
+
+    fn try_from(tags: Vec<Tag>) -> Result<Self, Self::Error> {
+        let tags = CalendarEventTags::parse(tags)?;
+        Ok(Self {
+            id: tags.id()?,
+            title: tags.title()?,
+            start: CalendarDate::from_str(&tags.start()?)?,
+            end: tags
+                .end
+                .as_deref()
+                .map(CalendarDate::from_str)
+                .transpose()?,
+            locations: tags.locations,
+            geohash: tags.geohash,
+            participants: tags.participants,
+            hashtags: tags.hashtags,
+            references: tags.references,
+        })
+    }
+}
+
+/// Time-Based Calendar Event (kind `31923`)
+///
+/// Event that begins and ends at specific moments in time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeBasedCalendarEvent {
+    /// Unique event ID
+    pub id: String,
+    /// Title
+    pub title: String,
+    /// Start
+    pub start: Timestamp,
+    /// End (exclusive)
+    pub end: Option<Timestamp>,
+    /// Time zone of the start (IANA Time Zone Database, ex. `America/Costa_Rica`)
+    pub start_tzid: Option<String>,
+    /// Time zone of the end, if different from the start one
+    pub end_tzid: Option<String>,
+    /// Locations (ex. address or URL)
+    pub locations: Vec<String>,
+    /// Geohash
+    pub geohash: Option<String>,
+    /// Participants
+    pub participants: Vec<CalendarEventParticipant>,
+    /// Hashtags
+    pub hashtags: Vec<String>,
+    /// References (URLs)
+    pub references: Vec<String>,
+}
+
+impl TimeBasedCalendarEvent {
+    /// New time-based calendar event
+    pub fn new<S>(id: S, title: S, start: Timestamp) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            id: id.into(),
+            title: title.into(),
+            start,
+            end: None,
+            start_tzid: None,
+            end_tzid: None,
+            locations: Vec::new(),
+            geohash: None,
+            participants: Vec::new(),
+            hashtags: Vec::new(),
+            references: Vec::new(),
+        }
+    }
+}
+
+impl From<TimeBasedCalendarEvent> for Vec<Tag> {
+    fn from(event: TimeBasedCalendarEvent) -> Self {
+        let TimeBasedCalendarEvent {
+            id,
+            title,
+            start,
+            end,
+            start_tzid,
+            end_tzid,
+            locations,
+            geohash,
+            participants,
+            hashtags,
+            references,
+        } = event;
+
+        let mut tags: Vec<Tag> = vec![
+            Tag::Identifier(id),
+            Tag::Title(title),
+            generic(TagKind::Custom(String::from("start")), start.to_string()),
+        ];
+
+        if let Some(end) = end {
+            tags.push(generic(
+                TagKind::Custom(String::from("end")),
+                end.to_string(),
+            ));
+        }
+
+        if let Some(start_tzid) = start_tzid {
+            tags.push(generic(TagKind::StartTzid, start_tzid));
+        }
+
+        if let Some(end_tzid) = end_tzid {
+            tags.push(generic(TagKind::EndTzid, end_tzid));
+        }
+
+        push_common_tags(
+            &mut tags,
+            locations,
+            geohash,
+            participants,
+            hashtags,
+            references,
+        );
+
+        tags
+    }
+}
+
+impl TryFrom<Vec<Tag>> for TimeBasedCalendarEvent {
+    type Error = Error;
+
+    fn try_from(tags: Vec<Tag>) -> Result<Self, Self::Error> {
+        let tags = CalendarEventTags::parse(tags)?;
+        Ok(Self {
+            id: tags.id()?,
+            title: tags.title()?,
+            start: Timestamp::from_str(&tags.start()?)?,
+            end: tags.end.as_deref().map(Timestamp::from_str).transpose()?,
+            start_tzid: tags.start_tzid,
+            end_tzid: tags.end_tzid,
+            locations: tags.locations,
+            geohash: tags.geohash,
+            participants: tags.participants,
+            hashtags: tags.hashtags,
+            references: tags.references,
+        })
+    }
+}
+
+/// Calendar (kind `31924`)
+///
+/// Collection of calendar events.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Calendar {
+    /// Unique calendar ID
+    pub id: String,
+    /// Title
+    pub title: String,
+    /// Calendar events
+    pub events: Vec<(Coordinate, Option<UncheckedUrl>)>,
+}
+
+impl Calendar {
+    /// New calendar
+    pub fn new<S>(id: S, title: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            id: id.into(),
+            title: title.into(),
+            events: Vec::new(),
+        }
+    }
+}
+
+impl From<Calendar> for Vec<Tag> {
+    fn from(calendar: Calendar) -> Self {
+        let Calendar { id, title, events } = calendar;
+
+        let mut tags: Vec<Tag> = Vec::with_capacity(2 + events.len());
+        tags.push(Tag::Identifier(id));
+        tags.push(Tag::Title(title));
+        tags.extend(events.into_iter().map(|(coordinate, relay_url)| Tag::A {
+            coordinate,
+            relay_url,
+        }));
+        tags
+    }
+}
+
+impl TryFrom<Vec<Tag>> for Calendar {
+    type Error = Error;
+
+    fn try_from(tags: Vec<Tag>) -> Result<Self, Self::Error> {
+        let tags = CalendarEventTags::parse(tags)?;
+        Ok(Self {
+            id: tags.id()?,
+            title: tags.title()?,
+            events: tags.coordinates,
+        })
+    }
+}
+
+/// RSVP status
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RsvpStatus {
+    /// Accepted
+    Accepted,
+    /// Declined
+    Declined,
+    /// Tentative
+    Tentative,
+}
+
+impl fmt::Display for RsvpStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Accepted => write!(f, "accepted"),
+            Self::Declined => write!(f, "declined"),
+            Self::Tentative => write!(f, "tentative"),
+        }
+    }
+}
+
+impl FromStr for RsvpStatus {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "accepted" => Ok(Self::Accepted),
+            "declined" => Ok(Self::Declined),
+            "tentative" => Ok(Self::Tentative),
+            s => Err(Error::UnknownRsvpStatus(s.to_string())),
+        }
+    }
+}
+
+/// Free or busy during the calendar event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FreeBusy {
+    /// Free
+    Free,
+    /// Busy
+    Busy,
+}
+
+impl fmt::Display for FreeBusy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Free => write!(f, "free"),
+            Self::Busy => write!(f, "busy"),
+        }
+    }
+}
+
+impl FromStr for FreeBusy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "free" => Ok(Self::Free),
+            "busy" => Ok(Self::Busy),
+            s => Err(Error::UnknownFreeBusy(s.to_string())),
+        }
+    }
+}
+
+/// Calendar Event RSVP (kind `31925`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CalendarEventRsvp {
+    /// Unique RSVP ID
+    pub id: String,
+    /// Coordinate of the calendar event
+    pub coordinate: Coordinate,
+    /// Relay URL of the calendar event
+    pub relay_url: Option<UncheckedUrl>,
+    /// ID of a specific revision of the calendar event
+    pub event_id: Option<EventId>,
+    /// Status
+    pub status: RsvpStatus,
+    /// Free or busy (omitted if the status is [`RsvpStatus::Declined`])
+    pub free_busy: Option<FreeBusy>,
+    /// Author of the calendar event
+    pub author: Option<PublicKey>,
+}
+
+impl CalendarEventRsvp {
+    /// New calendar event RSVP
+    pub fn new<S>(id: S, coordinate: Coordinate, status: RsvpStatus) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            id: id.into(),
+            coordinate,
+            relay_url: None,
+            event_id: None,
+            status,
+            free_busy: None,
+            author: None,
+        }
+    }
+}
+
+impl From<CalendarEventRsvp> for Vec<Tag> {
+    fn from(rsvp: CalendarEventRsvp) -> Self {
+        let CalendarEventRsvp {
+            id,
+            coordinate,
+            relay_url,
+            event_id,
+            status,
+            free_busy,
+            author,
+        } = rsvp;
+
+        let mut tags: Vec<Tag> = vec![
+            Tag::Identifier(id),
+            Tag::A {
+                coordinate,
+                relay_url,
+            },
+        ];
+
+        if let Some(event_id) = event_id {
+            tags.push(Tag::event(event_id));
+        }
+
+        tags.push(generic(TagKind::Status, status.to_string()));
+
+        if let Some(free_busy) = free_busy {
+            if status != RsvpStatus::Declined {
+                tags.push(generic(
+                    TagKind::Custom(String::from("fb")),
+                    free_busy.to_string(),
+                ));
+            }
+        }
+
+        if let Some(author) = author {
+            tags.push(Tag::public_key(author));
+        }
+
+        tags
+    }
+}
+
+impl TryFrom<Vec<Tag>> for CalendarEventRsvp {
+    type Error = Error;
+
+    fn try_from(tags: Vec<Tag>) -> Result<Self, Self::Error> {
+        let tags = CalendarEventTags::parse(tags)?;
+        let (coordinate, relay_url) = tags
+            .coordinates
+            .first()
+            .cloned()
+            .ok_or(Error::MissingTag(TagKind::from("a")))?;
+        Ok(Self {
+            id: tags.id()?,
+            coordinate,
+            relay_url,
+            event_id: tags.event_id,
+            status: RsvpStatus::from_str(
+                tags.status
+                    .as_deref()
+                    .ok_or(Error::MissingTag(TagKind::Status))?,
+            )?,
+            free_busy: tags
+                .free_busy
+                .as_deref()
+                .map(FreeBusy::from_str)
+                .transpose()?,
+            author: tags.participants.first().map(|p| p.public_key),
+        })
+    }
+}
+
+fn generic(kind: TagKind, value: String) -> Tag {
+    Tag::Generic(kind, vec![value])
+}
+
+fn push_common_tags(
+    tags: &mut Vec<Tag>,
+    locations: Vec<String>,
+    geohash: Option<String>,
+    participants: Vec<CalendarEventParticipant>,
+    hashtags: Vec<String>,
+    references: Vec<String>,
+) {
+    tags.extend(
+        locations
+            .into_iter()
+            .map(|location| generic(TagKind::Location, location)),
+    );
+
+    if let Some(geohash) = geohash {
+        tags.push(Tag::Geohash(geohash));
+    }
+
+    tags.extend(participants.into_iter().map(Tag::from));
+    tags.extend(hashtags.into_iter().map(Tag::Hashtag));
+    tags.extend(references.into_iter().map(Tag::Reference));
+}
+
+/// Values of the tags of the NIP52 events
+///
+/// Tags are matched by their string representation, since some of them
+/// are parsed as other variants (ex. `status` as NIP53 status).
+#[derive(Default)]
+struct CalendarEventTags {
+    id: Option<String>,
+    title: Option<String>,
+    name: Option<String>,
+    start: Option<String>,
+    end: Option<String>,
+    start_tzid: Option<String>,
+    end_tzid: Option<String>,
+    locations: Vec<String>,
+    geohash: Option<String>,
+    participants: Vec<CalendarEventParticipant>,
+    hashtags: Vec<String>,
+    references: Vec<String>,
+    coordinates: Vec<(Coordinate, Option<UncheckedUrl>)>,
+    event_id: Option<EventId>,
+    status: Option<String>,
+    free_busy: Option<String>,
+}
+
+impl CalendarEventTags {
+    fn parse(tags: Vec<Tag>) -> Result<Self, Error> {
+        let mut values = Self::default();
+
+        for tag in tags.into_iter() {
+            let tag: Vec<String> = tag.as_vec();
+            let (kind, value) = match (tag.first(), tag.get(1)) {
+                (Some(kind), Some(value)) => (kind.as_str(), value.clone()),
+                _ => continue,
+            };
+            let relay_url: Option<UncheckedUrl> = tag
+                .get(2)
+                .filter(|url| !url.is_empty())
+                .map(UncheckedUrl::from);
+
+            match kind {
+                "d" => values.id = Some(value),
+                "title" => values.title = Some(value),
+                "name" => values.name = Some(value),
+                "start" => values.start = Some(value),
+                "end" => values.end = Some(value),
+                "start_tzid" => values.start_tzid = Some(value),
+                "end_tzid" => values.end_tzid = Some(value),
+                "location" => values.locations.push(value),
+                "g" => values.geohash = Some(value),
+                "p" => values.participants.push(CalendarEventParticipant {
+                    public_key: PublicKey::from_str(&value)?,
+                    relay_url,
+                    role: tag.get(3).filter(|role| !role.is_empty()).cloned(),
+                }),
+                "t" => values.hashtags.push(value),
+                "r" => values.references.push(value),
+                "a" => values
+                    .coordinates
+                    .push((Coordinate::from_str(&value)?, relay_url)),
+                "e" => values.event_id = Some(EventId::from_hex(value)?),
+                "status" => values.status = Some(value),
+                "fb" => values.free_busy = Some(value),
+                _ => {}
+            }
+        }
+
+        Ok(values)
+    }
+
+    fn id(&self) -> Result<String, Error> {
+        self.id.clone().ok_or(Error::MissingTag(TagKind::from("d")))
+    }
+
+    /// Title, or the deprecated `name`
+    fn title(&self) -> Result<String, Error> {
+        self.title
+            .clone()
+            .or_else(|| self.name.clone())
+            .ok_or(Error::MissingTag(TagKind::Title))
+    }
+
+    fn start(&self) -> Result<String, Error> {
+        self.start
+            .clone()
+            .ok_or(Error::MissingTag(TagKind::Custom(String::from("start"))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Event, EventBuilder, JsonUtil, Keys, Kind};
+
+    fn parse_tags(event: &Event) -> Vec<Tag> {
+        // Tags as received from relays
+        Event::from_json(event.as_json())
+            .unwrap()
+            .iter_tags()
+            .cloned()
+            .collect()
+    }
+
+    #[test]
+    fn test_calendar_date() {
+        let date = CalendarDate::from_str("2024-02-29").unwrap();
+        assert_eq!(date, CalendarDate::new(2024, 2, 29).unwrap());
+        assert_eq!(date.to_string(), "2024-02-29");
+
+        assert!(CalendarDate::from_str("2023-02-29").is_err());
+        assert!(CalendarDate::from_str("2024-13-01").is_err());
+        assert!(CalendarDate::from_str("2024-1-01").is_err());
+        assert!(CalendarDate::from_str("1706745600").is_err());
+    }
+
+    #[test]
+    fn test_calendar_events() {
+        let keys = Keys::generate();
+        let speaker = Keys::generate().public_key();
+
+        let mut event = DateBasedCalendarEvent::new(
+            "rust-conf",
+            "Rust Conf",
+            CalendarDate::new(2024, 9, 10).unwrap(),
+        );
+        event.end = Some(CalendarDate::new(2024, 9, 13).unwrap());
+        event.locations = vec![String::from("Montreal")];
+        event.participants = vec![CalendarEventParticipant {
+            public_key: speaker,
+            relay_url: None,
+            role: Some(String::from("speaker")),
+        }];
+        event.hashtags = vec![String::from("rust")];
+        let e = EventBuilder::date_based_calendar_event(event.clone(), "Annual conference")
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(e.kind(), Kind::DateBasedCalendarEvent);
+        assert_eq!(
+            DateBasedCalendarEvent::try_from(parse_tags(&e)).unwrap(),
+            event
+        );
+
+        let mut event =
+            TimeBasedCalendarEvent::new("meetup", "Nostr meetup", Timestamp::from(1_706_787_000));
+        event.end = Some(Timestamp::from(1_706_794_200));
+        event.start_tzid = Some(String::from("Europe/Rome"));
+        event.locations = vec![
+            String::from("Milan"),
+            String::from("https://meet.example.com"),
+        ];
+        event.geohash = Some(String::from("u0nd"));
+        event.participants = vec![
+            CalendarEventParticipant {
+                public_key: speaker,
+                relay_url: Some(UncheckedUrl::from("wss://relay.damus.io")),
+                role: Some(String::from("Host")),
+            },
+            CalendarEventParticipant {
+                public_key: keys.public_key(),
+                relay_url: None,
+                role: None,
+            },
+        ];
+        let e = EventBuilder::time_based_calendar_event(event.clone(), "")
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(e.kind(), Kind::TimeBasedCalendarEvent);
+        assert_eq!(
+            TimeBasedCalendarEvent::try_from(parse_tags(&e)).unwrap(),
+            event
+        );
+
+        // Missing start
+        let tags = vec![
+            Tag::Identifier(String::from("abcd")),
+            Tag::Title(String::from("Test")),
+        ];
+        assert!(matches!(
+            TimeBasedCalendarEvent::try_from(tags).unwrap_err(),
+            Error::MissingTag(..)
+        ));
+
+        // Calendar
+        let coordinate =
+            Coordinate::new(Kind::TimeBasedCalendarEvent, keys.public_key()).identifier("meetup");
+        let mut calendar = Calendar::new("nostr", "Nostr events");
+        calendar.events = vec![(coordinate, None)];
+        let e = EventBuilder::calendar(calendar.clone(), "")
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(e.kind(), Kind::Calendar);
+        assert_eq!(Calendar::try_from(parse_tags(&e)).unwrap(), calendar);
+    }
+
+    #[test]
+    fn test_calendar_event_rsvp() {
+        let keys = Keys::generate();
+        let author = Keys::generate().public_key();
+        let coordinate = Coordinate::new(Kind::TimeBasedCalendarEvent, author).identifier("meetup");
+
+        let mut rsvp = CalendarEventRsvp::new("rsvp", coordinate.clone(), RsvpStatus::Accepted);
+        rsvp.free_busy = Some(FreeBusy::Busy);
+        rsvp.author = Some(author);
+        let e = EventBuilder::calendar_event_rsvp(rsvp.clone(), "See you there")
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(e.kind(), Kind::CalendarEventRsvp);
+        assert_eq!(CalendarEventRsvp::try_from(parse_tags(&e)).unwrap(), rsvp);
+
+        // Free/busy is omitted when declined
+        let mut rsvp = CalendarEventRsvp::new("rsvp", coordinate, RsvpStatus::Declined);
+        rsvp.free_busy = Some(FreeBusy::Free);
+        let tags: Vec<Tag> = rsvp.into();
+        let rsvp = CalendarEventRsvp::try_from(tags).unwrap();
+        assert_eq!(rsvp.status, RsvpStatus::Declined);
+        assert_eq!(rsvp.free_busy, None);
+
+        let tags = vec![
+            Tag::Identifier(String::from("rsvp")),
+            Tag::A {
+                coordinate: Coordinate::new(Kind::TimeBasedCalendarEvent, author),
+                relay_url: None,
+            },
+            Tag::Generic(TagKind::Status, vec![String::from("maybe")]),
+        ];
+        assert!(matches!(
+            CalendarEventRsvp::try_from(tags).unwrap_err(),
+            Error::UnknownRsvpStatus(..)
+        ));
+    }
+}
@@ -31,6 +31,8 @@ pub enum Error {
     NIP44(nip44::Error),
     /// Not Gift Wrap event
     NotGiftWrap,
+    /// The author of the `seal` doesn't match the author of the `rumor`
+    SenderMismatch,
 }
 
 #[cfg(feature = "std")]
@@ -44,6 +46,7 @@ impl fmt::Display for Error {
             Self::Unsigned(e) => write!(f, "Unsigned event: {e}"),
             Self::NIP44(e) => write!(f, "NIP44: {e}"),
             Self::NotGiftWrap => write!(f, "Not Gift Wrap event"),
+            Self::SenderMismatch => write!(f, "Seal and rumor authors mismatch"),
         }
     }
 }
@@ -84,7 +87,7 @@ pub struct UnwrappedGift {
 impl UnwrappedGift {
     /// Unwrap Gift Wrap event
     ///
-    /// Internally verify the `seal` event
+    /// Internally verify the `seal` event and that its author matches the `rumor` author
     #[cfg(feature = "std")]
     pub fn from_gift_wrap(receiver_keys: &Keys, gift_wrap: &Event) -> Result<Self, Error> {
         Self::from_gift_wrap_with_ctx(&SECP256K1, receiver_keys, gift_wrap)
@@ -92,7 +95,7 @@ impl UnwrappedGift {
 
     /// Unwrap Gift Wrap event
     ///
-    /// Internally verify the `seal` event
+    /// Internally verify the `seal` event and that its author matches the `rumor` author
     pub fn from_gift_wrap_with_ctx<C>(
         secp: &Secp256k1<C>,
         receiver_keys: &Keys,
@@ -115,10 +118,16 @@ impl UnwrappedGift {
 
         // Decrypt rumor
         let rumor: String = nip44::decrypt(secret_key, seal.author_ref(), seal.content())?;
+        let rumor: UnsignedEvent = UnsignedEvent::from_json(rumor)?;
+
+        // Check that the rumor has not been forged by someone else
+        if rumor.pubkey != seal.author() {
+            return Err(Error::SenderMismatch);
+        }
 
         Ok(UnwrappedGift {
             sender: seal.author(),
-            rumor: UnsignedEvent::from_json(rumor)?,
+            rumor,
         })
     }
 }
@@ -167,6 +176,17 @@ mod tests {
         );
         assert!(extract_rumor(&sender_keys, &event).is_err());
 
+        // Rumor with a forged author
+        let forged: UnsignedEvent =
+            EventBuilder::text_note("Test", []).to_unsigned_event(receiver_keys.public_key());
+        let event: Event =
+            EventBuilder::gift_wrap(&sender_keys, &receiver_keys.public_key(), forged, None)
+                .unwrap();
+        assert_eq!(
+            extract_rumor(&receiver_keys, &event).unwrap_err(),
+            Error::SenderMismatch
+        );
+
         let event: Event = EventBuilder::text_note("", [])
             .to_event(&sender_keys)
             .unwrap();
@@ -35,13 +35,16 @@ pub use crate::nips::nip05::{self, *};
 pub use crate::nips::nip06::{self, *};
 #[cfg(all(feature = "nip07", target_arch = "wasm32"))]
 pub use crate::nips::nip07::{self, *};
+pub use crate::nips::nip10::{self, *};
 #[cfg(all(feature = "std", feature = "nip11"))]
 pub use crate::nips::nip11::{self, *};
 pub use crate::nips::nip13::{self, *};
 pub use crate::nips::nip15::{self, *};
+pub use crate::nips::nip17::{self, *};
 pub use crate::nips::nip19::{self, *};
 pub use crate::nips::nip21::{self, *};
 pub use crate::nips::nip26::{self, *};
+pub use crate::nips::nip29::{self, *};
 #[cfg(feature = "nip44")]
 pub use crate::nips::nip44::{self, *};
 #[cfg(all(feature = "std", feature = "nip46"))]
@@ -51,6 +54,7 @@ pub use crate::nips::nip47::{self, *};
 pub use crate::nips::nip48::{self, *};
 #[cfg(feature = "nip49")]
 pub use crate::nips::nip49::{self, *};
+pub use crate::nips::nip52::{self, *};
 pub use crate::nips::nip53::{self, *};
 #[cfg(feature = "nip57")]
 pub use crate::nips::nip57::{self, *};
@@ -0,0 +1,30 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+use std::time::Duration;
+
+use nwc::prelude::*;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    tracing_subscriber::fmt::init();
+
+    let relay_url = Url::parse("wss://relay.damus.io")?;
+
+    // Wallet service backed by an in-memory wallet with 100k sats
+    let keys = Keys::generate();
+    let backend = MockLightningBackend::new(100_000_000);
+    let service = NostrWalletConnectService::new(keys, [relay_url.clone()], backend).await?;
+
+    // New connection, with a daily budget of 10k sats
+    let connection = WalletConnection::generate()
+        .budget(Budget::new(10_000_000).renewal(Duration::from_secs(60 * 60 * 24)));
+    println!("{}", connection.uri(service.public_key(), relay_url, None));
+    service.add_connection(connection);
+
+    // Handle requests
+    service.serve().await?;
+
+    Ok(())
+}
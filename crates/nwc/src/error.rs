@@ -4,7 +4,8 @@
 
 //! NWC error
 
-use nostr::nips::nip47;
+use nostr::nips::{nip04, nip47};
+use nostr::{event, key};
 use nostr_zapper::ZapperError;
 use thiserror::Error;
 
@@ -14,12 +15,24 @@ pub enum Error {
     /// Zapper error
     #[error(transparent)]
     Zapper(#[from] ZapperError),
+    /// Keys error
+    #[error(transparent)]
+    Keys(#[from] key::Error),
+    /// Event builder error
+    #[error(transparent)]
+    EventBuilder(#[from] event::builder::Error),
+    /// NIP04 error
+    #[error(transparent)]
+    NIP04(#[from] nip04::Error),
     /// NIP47 error
     #[error(transparent)]
     NIP47(#[from] nip47::Error),
     /// Relay
     #[error(transparent)]
     Relay(#[from] nostr_relay_pool::relay::Error),
+    /// Relay pool
+    #[error(transparent)]
+    Pool(#[from] nostr_relay_pool::pool::Error),
     /// Request timeout
     #[error("timeout")]
     Timeout,
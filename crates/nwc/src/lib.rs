@@ -2,7 +2,7 @@
 // Copyright (c) 2023-2024 Rust Nostr Developers
 // Distributed under the MIT software license
 
-//! NWC client and zapper backend for Nostr apps
+//! NWC client, service and zapper backend for Nostr apps
 
 #![forbid(unsafe_code)]
 #![warn(missing_docs)]
@@ -32,9 +32,11 @@ use nostr_zapper::{async_trait, NostrZapper, ZapperBackend};
 pub mod error;
 pub mod options;
 pub mod prelude;
+pub mod service;
 
 pub use self::error::Error;
 pub use self::options::NostrWalletConnectOptions;
+pub use self::service::NostrWalletConnectService;
 
 const TIMEOUT: Duration = Duration::from_secs(10);
 
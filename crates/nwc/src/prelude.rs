@@ -11,4 +11,5 @@
 pub use nostr::prelude::*;
 pub use nostr_zapper::prelude::*;
 
+pub use crate::service::*;
 pub use crate::*;
@@ -0,0 +1,94 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Lightning backend
+
+use nostr::nips::nip47::{
+    ErrorCode, GetBalanceResponseResult, GetInfoResponseResult, ListTransactionsRequestParams,
+    LookupInvoiceRequestParams, LookupInvoiceResponseResult, MakeInvoiceRequestParams,
+    MakeInvoiceResponseResult, Method, NIP47Error, PayInvoiceRequestParams,
+    PayInvoiceResponseResult, PayKeysendRequestParams, PayKeysendResponseResult,
+};
+use nostr_zapper::{async_trait, AsyncTraitDeps};
+
+/// Lightning backend of the [`NostrWalletConnectService`](super::NostrWalletConnectService)
+///
+/// Methods not implemented return [`ErrorCode::NotImplemented`].
+/// The `multi_pay_invoice` and `multi_pay_keysend` requests are split by the service in single payments.
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait LightningBackend: AsyncTraitDeps {
+    /// Supported methods (published in the info event)
+    fn methods(&self) -> Vec<Method>;
+
+    /// Get the amount of a bolt11 invoice, in millisatoshis (`None` for amount-less invoices)
+    ///
+    /// Required to enforce the connection budgets.
+    async fn invoice_amount(&self, invoice: &str) -> Result<Option<u64>, NIP47Error> {
+        let _ = invoice;
+        Err(not_implemented())
+    }
+
+    /// Pay invoice
+    async fn pay_invoice(
+        &self,
+        params: PayInvoiceRequestParams,
+    ) -> Result<PayInvoiceResponseResult, NIP47Error> {
+        let _ = params;
+        Err(not_implemented())
+    }
+
+    /// Pay keysend
+    async fn pay_keysend(
+        &self,
+        params: PayKeysendRequestParams,
+    ) -> Result<PayKeysendResponseResult, NIP47Error> {
+        let _ = params;
+        Err(not_implemented())
+    }
+
+    /// Make invoice
+    async fn make_invoice(
+        &self,
+        params: MakeInvoiceRequestParams,
+    ) -> Result<MakeInvoiceResponseResult, NIP47Error> {
+        let _ = params;
+        Err(not_implemented())
+    }
+
+    /// Lookup invoice
+    async fn lookup_invoice(
+        &self,
+        params: LookupInvoiceRequestParams,
+    ) -> Result<LookupInvoiceResponseResult, NIP47Error> {
+        let _ = params;
+        Err(not_implemented())
+    }
+
+    /// List transactions
+    async fn list_transactions(
+        &self,
+        params: ListTransactionsRequestParams,
+    ) -> Result<Vec<LookupInvoiceResponseResult>, NIP47Error> {
+        let _ = params;
+        Err(not_implemented())
+    }
+
+    /// Get balance, in millisatoshis
+    async fn get_balance(&self) -> Result<GetBalanceResponseResult, NIP47Error> {
+        Err(not_implemented())
+    }
+
+    /// Get info
+    async fn get_info(&self) -> Result<GetInfoResponseResult, NIP47Error> {
+        Err(not_implemented())
+    }
+}
+
+fn not_implemented() -> NIP47Error {
+    NIP47Error {
+        code: ErrorCode::NotImplemented,
+        message: String::from("method not implemented"),
+    }
+}
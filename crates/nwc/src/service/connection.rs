@@ -0,0 +1,248 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! NWC service connection
+
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+use nostr::nips::nip47::{Method, NostrWalletConnectURI};
+use nostr::{Keys, PublicKey, SecretKey, Timestamp, Url};
+
+/// Connection budget
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Budget {
+    /// Max amount that can be spent in a period, in millisatoshis
+    pub max_amount: u64,
+    /// Budget renewal period (`None` means that the budget never renews)
+    pub renewal: Option<Duration>,
+}
+
+impl Budget {
+    /// New budget that never renews
+    pub fn new(max_amount: u64) -> Self {
+        Self {
+            max_amount,
+            renewal: None,
+        }
+    }
+
+    /// Renew budget every `period`
+    pub fn renewal(self, period: Duration) -> Self {
+        Self {
+            renewal: Some(period),
+            ..self
+        }
+    }
+}
+
+/// Wallet connection
+///
+/// Each connection has its own secret, shared with the app in the [`NostrWalletConnectURI`].
+#[derive(Debug, Clone)]
+pub struct WalletConnection {
+    secret: SecretKey,
+    public_key: PublicKey,
+    budget: Option<Budget>,
+    methods: Option<BTreeSet<Method>>,
+}
+
+impl WalletConnection {
+    /// New wallet connection from secret
+    pub fn new(secret: SecretKey) -> Self {
+        let keys: Keys = Keys::new(secret.clone());
+        Self {
+            secret,
+            public_key: keys.public_key(),
+            budget: None,
+            methods: None,
+        }
+    }
+
+    /// Generate new wallet connection with random secret
+    pub fn generate() -> Self {
+        let keys: Keys = Keys::generate();
+        Self {
+            secret: keys
+                .secret_key()
+                .cloned()
+                .expect("generated keys have secret key"),
+            public_key: keys.public_key(),
+            budget: None,
+            methods: None,
+        }
+    }
+
+    /// Set budget (default: unlimited)
+    pub fn budget(mut self, budget: Budget) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// Restrict the allowed methods (default: all the methods supported by the backend)
+    pub fn methods<I>(mut self, methods: I) -> Self
+    where
+        I: IntoIterator<Item = Method>,
+    {
+        self.methods = Some(methods.into_iter().collect());
+        self
+    }
+
+    /// Get connection [`PublicKey`]
+    ///
+    /// The app signs the requests with the connection secret, so this is the author of the requests.
+    pub fn public_key(&self) -> PublicKey {
+        self.public_key
+    }
+
+    /// Get connection budget
+    pub fn get_budget(&self) -> Option<Budget> {
+        self.budget
+    }
+
+    /// Check if the [`Method`] is allowed
+    pub fn is_allowed(&self, method: &Method) -> bool {
+        match &self.methods {
+            Some(methods) => methods.contains(method),
+            None => true,
+        }
+    }
+
+    /// Compose [`NostrWalletConnectURI`] to share with the app
+    pub fn uri(
+        &self,
+        service_public_key: PublicKey,
+        relay_url: Url,
+        lud16: Option<String>,
+    ) -> NostrWalletConnectURI {
+        NostrWalletConnectURI::new(service_public_key, relay_url, self.secret.clone(), lud16)
+    }
+}
+
+/// Amount reserved from the budget of a period
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Reservation {
+    amount: u64,
+    period: u64,
+}
+
+/// Budget spent in the current period
+#[derive(Debug, Clone)]
+pub(crate) struct BudgetTracker {
+    budget: Budget,
+    spent: u64,
+    /// Number of renewals
+    period: u64,
+    period_start: Timestamp,
+}
+
+impl BudgetTracker {
+    pub fn new(budget: Budget) -> Self {
+        Self {
+            budget,
+            spent: 0,
+            period: 0,
+            period_start: Timestamp::now(),
+        }
+    }
+
+    fn renew(&mut self, now: Timestamp) {
+        if let Some(period) = self.budget.renewal {
+            if now >= self.period_start + period {
+                self.spent = 0;
+                self.period += 1;
+                self.period_start = now;
+            }
+        }
+    }
+
+    /// Reserve `amount`, if there is enough budget left
+    pub fn reserve(&mut self, amount: u64, now: Timestamp) -> Option<Reservation> {
+        self.renew(now);
+        match self.spent.checked_add(amount) {
+            Some(spent) if spent <= self.budget.max_amount => {
+                self.spent = spent;
+                Some(Reservation {
+                    amount,
+                    period: self.period,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Release a reservation (ex. payment failed)
+    ///
+    /// If the budget has been renewed in the meantime, the reservation is already expired.
+    pub fn release(&mut self, reservation: Reservation) {
+        if reservation.period == self.period {
+            self.spent = self.spent.saturating_sub(reservation.amount);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_tracker() {
+        let now = Timestamp::from(1_700_000_000);
+        let mut tracker = BudgetTracker {
+            budget: Budget::new(10_000).renewal(Duration::from_secs(60)),
+            spent: 0,
+            period: 0,
+            period_start: now,
+        };
+
+        assert!(tracker.reserve(6_000, now).is_some());
+        assert!(tracker.reserve(6_000, now).is_none());
+        let reservation = tracker.reserve(4_000, now).unwrap();
+        assert!(tracker.reserve(1, now).is_none());
+
+        // Payment failed
+        tracker.release(reservation);
+        assert!(tracker.reserve(4_000, now).is_some());
+
+        // Renewed
+        assert!(tracker
+            .reserve(10_000, now + Duration::from_secs(60))
+            .is_some());
+        assert!(tracker.reserve(1, now + Duration::from_secs(61)).is_none());
+    }
+
+    #[test]
+    fn test_release_after_renewal() {
+        let now = Timestamp::from(1_700_000_000);
+        let mut tracker = BudgetTracker {
+            budget: Budget::new(10_000).renewal(Duration::from_secs(60)),
+            spent: 0,
+            period: 0,
+            period_start: now,
+        };
+
+        // Payment of the previous period failed after the renewal
+        let reservation = tracker.reserve(4_000, now).unwrap();
+        let renewed = now + Duration::from_secs(60);
+        assert!(tracker.reserve(10_000, renewed).is_some());
+        tracker.release(reservation);
+        assert!(tracker.reserve(1, renewed).is_none());
+    }
+
+    #[test]
+    fn test_budget_never_renew() {
+        let now = Timestamp::from(1_700_000_000);
+        let mut tracker = BudgetTracker {
+            budget: Budget::new(1_000),
+            spent: 0,
+            period: 0,
+            period_start: now,
+        };
+
+        assert!(tracker.reserve(1_000, now).is_some());
+        assert!(tracker
+            .reserve(1, now + Duration::from_secs(60 * 60 * 24 * 365))
+            .is_none());
+    }
+}
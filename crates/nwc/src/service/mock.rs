@@ -0,0 +1,391 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Mock lightning backend
+
+use std::sync::{Arc, Mutex};
+
+use nostr::hashes::sha256::Hash as Sha256Hash;
+use nostr::hashes::Hash;
+use nostr::nips::nip47::{
+    ErrorCode, GetBalanceResponseResult, GetInfoResponseResult, ListTransactionsRequestParams,
+    LookupInvoiceRequestParams, LookupInvoiceResponseResult, MakeInvoiceRequestParams,
+    MakeInvoiceResponseResult, Method, NIP47Error, PayInvoiceRequestParams,
+    PayInvoiceResponseResult, PayKeysendRequestParams, PayKeysendResponseResult, TransactionType,
+};
+use nostr::secp256k1::rand::{self, RngCore};
+use nostr::util::hex;
+use nostr::{serde_json, Timestamp};
+use nostr_zapper::async_trait;
+
+use super::LightningBackend;
+
+const MOCK_INVOICE_PREFIX: &str = "lnmock";
+const DEFAULT_EXPIRY: u64 = 60 * 60;
+
+#[derive(Debug, Default)]
+struct MockWallet {
+    balance: u64,
+    /// Incoming and outgoing transactions, with their preimage
+    transactions: Vec<(LookupInvoiceResponseResult, String)>,
+}
+
+/// Mock lightning backend
+///
+/// In-memory wallet, without a real lightning node, to test NWC and zap flows offline.
+///
+/// Invoices are fake (`lnmock<amount>_<payment hash>`) and paying an invoice made by the same backend settles it.
+#[derive(Debug, Clone, Default)]
+pub struct MockLightningBackend {
+    wallet: Arc<Mutex<MockWallet>>,
+}
+
+impl MockLightningBackend {
+    /// New mock backend with initial balance, in millisatoshis
+    pub fn new(balance: u64) -> Self {
+        Self {
+            wallet: Arc::new(Mutex::new(MockWallet {
+                balance,
+                transactions: Vec::new(),
+            })),
+        }
+    }
+
+    /// Get balance, in millisatoshis
+    pub fn balance(&self) -> u64 {
+        self.wallet.lock().expect("mock wallet").balance
+    }
+
+    fn parse_invoice(invoice: &str) -> Result<(u64, String), NIP47Error> {
+        invoice
+            .strip_prefix(MOCK_INVOICE_PREFIX)
+            .and_then(|s| s.split_once('_'))
+            .and_then(|(amount, payment_hash)| {
+                Some((amount.parse().ok()?, payment_hash.to_string()))
+            })
+            .ok_or_else(|| error(ErrorCode::Other, "invalid invoice"))
+    }
+
+    fn pay(
+        &self,
+        amount: u64,
+        invoice: Option<String>,
+        payment_hash: String,
+        preimage: String,
+    ) -> Result<(), NIP47Error> {
+        let mut guard = self.wallet.lock().expect("mock wallet");
+        let wallet: &mut MockWallet = &mut guard;
+
+        if wallet.balance < amount {
+            return Err(error(
+                ErrorCode::InsufficientBalance,
+                "insufficient balance",
+            ));
+        }
+
+        let now: u64 = Timestamp::now().as_u64();
+
+        // Settle incoming invoice, if made by this backend
+        if let Some((tx, preimage)) = wallet.transactions.iter_mut().find(|(tx, ..)| {
+            tx.transaction_type == Some(TransactionType::Incoming)
+                && tx.payment_hash == payment_hash
+        }) {
+            if tx.settled_at.is_some() {
+                return Err(error(ErrorCode::PaymentFailed, "invoice already paid"));
+            }
+
+            tx.settled_at = Some(now);
+            tx.preimage = Some(preimage.clone());
+            wallet.balance += amount;
+        }
+
+        wallet.balance -= amount;
+        wallet.transactions.push((
+            LookupInvoiceResponseResult {
+                transaction_type: Some(TransactionType::Outgoing),
+                invoice,
+                description: None,
+                description_hash: None,
+                preimage: Some(preimage.clone()),
+                payment_hash,
+                amount,
+                fees_paid: 0,
+                created_at: now,
+                expires_at: now,
+                settled_at: Some(now),
+                metadata: serde_json::Value::Null,
+            },
+            preimage,
+        ));
+
+        Ok(())
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl LightningBackend for MockLightningBackend {
+    fn methods(&self) -> Vec<Method> {
+        vec![
+            Method::PayInvoice,
+            Method::PayKeysend,
+            Method::MakeInvoice,
+            Method::LookupInvoice,
+            Method::ListTransactions,
+            Method::GetBalance,
+            Method::GetInfo,
+        ]
+    }
+
+    async fn invoice_amount(&self, invoice: &str) -> Result<Option<u64>, NIP47Error> {
+        let (amount, ..) = Self::parse_invoice(invoice)?;
+        Ok(if amount > 0 { Some(amount) } else { None })
+    }
+
+    async fn pay_invoice(
+        &self,
+        params: PayInvoiceRequestParams,
+    ) -> Result<PayInvoiceResponseResult, NIP47Error> {
+        let (amount, payment_hash) = Self::parse_invoice(&params.invoice)?;
+        let amount: u64 = match (amount, params.amount) {
+            (0, Some(amount)) => amount,
+            (0, None) => return Err(error(ErrorCode::Other, "amount required")),
+            (amount, ..) => amount,
+        };
+
+        // Use the preimage of the invoice, if made by this backend
+        let preimage: String = self
+            .wallet
+            .lock()
+            .expect("mock wallet")
+            .transactions
+            .iter()
+            .find(|(tx, ..)| tx.payment_hash == payment_hash)
+            .map(|(.., preimage)| preimage.clone())
+            .unwrap_or_else(|| random_preimage().0);
+
+        self.pay(amount, Some(params.invoice), payment_hash, preimage.clone())?;
+
+        Ok(PayInvoiceResponseResult { preimage })
+    }
+
+    async fn pay_keysend(
+        &self,
+        params: PayKeysendRequestParams,
+    ) -> Result<PayKeysendResponseResult, NIP47Error> {
+        let (preimage, payment_hash) = match params.preimage {
+            Some(preimage) => {
+                let bytes: Vec<u8> = hex::decode(&preimage)
+                    .map_err(|_| error(ErrorCode::Other, "invalid preimage"))?;
+                let payment_hash = Sha256Hash::hash(&bytes);
+                (preimage, payment_hash.to_string())
+            }
+            None => random_preimage(),
+        };
+
+        self.pay(params.amount, None, payment_hash, preimage.clone())?;
+
+        Ok(PayKeysendResponseResult { preimage })
+    }
+
+    async fn make_invoice(
+        &self,
+        params: MakeInvoiceRequestParams,
+    ) -> Result<MakeInvoiceResponseResult, NIP47Error> {
+        let (preimage, payment_hash) = random_preimage();
+        let invoice: String = format!("{MOCK_INVOICE_PREFIX}{}_{payment_hash}", params.amount);
+        let now: u64 = Timestamp::now().as_u64();
+
+        let mut wallet = self.wallet.lock().expect("mock wallet");
+        wallet.transactions.push((
+            LookupInvoiceResponseResult {
+                transaction_type: Some(TransactionType::Incoming),
+                invoice: Some(invoice.clone()),
+                description: params.description,
+                description_hash: params.description_hash,
+                preimage: None,
+                payment_hash: payment_hash.clone(),
+                amount: params.amount,
+                fees_paid: 0,
+                created_at: now,
+                expires_at: now + params.expiry.unwrap_or(DEFAULT_EXPIRY),
+                settled_at: None,
+                metadata: serde_json::Value::Null,
+            },
+            preimage,
+        ));
+
+        Ok(MakeInvoiceResponseResult {
+            invoice,
+            payment_hash,
+        })
+    }
+
+    async fn lookup_invoice(
+        &self,
+        params: LookupInvoiceRequestParams,
+    ) -> Result<LookupInvoiceResponseResult, NIP47Error> {
+        let wallet = self.wallet.lock().expect("mock wallet");
+        wallet
+            .transactions
+            .iter()
+            .map(|(tx, ..)| tx)
+            .find(|tx| {
+                (params.payment_hash.is_some()
+                    && params.payment_hash.as_ref() == Some(&tx.payment_hash))
+                    || (params.bolt11.is_some() && params.bolt11 == tx.invoice)
+            })
+            .cloned()
+            .ok_or_else(|| error(ErrorCode::NotFound, "invoice not found"))
+    }
+
+    async fn list_transactions(
+        &self,
+        params: ListTransactionsRequestParams,
+    ) -> Result<Vec<LookupInvoiceResponseResult>, NIP47Error> {
+        let wallet = self.wallet.lock().expect("mock wallet");
+        let mut transactions: Vec<LookupInvoiceResponseResult> = wallet
+            .transactions
+            .iter()
+            .map(|(tx, ..)| tx)
+            .filter(|tx| {
+                params.transaction_type.is_none() || tx.transaction_type == params.transaction_type
+            })
+            .filter(|tx| params.unpaid.unwrap_or_default() || tx.settled_at.is_some())
+            .filter(|tx| params.from.map_or(true, |from| tx.created_at >= from))
+            .filter(|tx| params.until.map_or(true, |until| tx.created_at <= until))
+            .cloned()
+            .collect();
+
+        // Newest first
+        transactions.reverse();
+
+        Ok(transactions
+            .into_iter()
+            .skip(params.offset.unwrap_or_default() as usize)
+            .take(params.limit.map_or(usize::MAX, |limit| limit as usize))
+            .collect())
+    }
+
+    async fn get_balance(&self) -> Result<GetBalanceResponseResult, NIP47Error> {
+        Ok(GetBalanceResponseResult {
+            balance: self.balance(),
+        })
+    }
+
+    async fn get_info(&self) -> Result<GetInfoResponseResult, NIP47Error> {
+        Ok(GetInfoResponseResult {
+            alias: String::from("mock"),
+            color: String::from("#000000"),
+            pubkey: String::new(),
+            network: String::from("regtest"),
+            block_height: 0,
+            block_hash: String::new(),
+            methods: self.methods().into_iter().map(|m| m.to_string()).collect(),
+        })
+    }
+}
+
+/// Generate random preimage and its payment hash
+fn random_preimage() -> (String, String) {
+    let mut preimage: [u8; 32] = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut preimage);
+    let payment_hash = Sha256Hash::hash(&preimage);
+    (hex::encode(preimage), payment_hash.to_string())
+}
+
+fn error<S>(code: ErrorCode, message: S) -> NIP47Error
+where
+    S: Into<String>,
+{
+    NIP47Error {
+        code,
+        message: message.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_mock_wallet() {
+        let backend = MockLightningBackend::new(10_000);
+
+        let params = MakeInvoiceRequestParams {
+            amount: 3_000,
+            description: Some(String::from("test")),
+            description_hash: None,
+            expiry: None,
+        };
+        let invoice = backend.make_invoice(params).await.unwrap();
+        assert_eq!(
+            backend.invoice_amount(&invoice.invoice).await.unwrap(),
+            Some(3_000)
+        );
+
+        let lookup = LookupInvoiceRequestParams {
+            payment_hash: Some(invoice.payment_hash.clone()),
+            bolt11: None,
+        };
+        let tx = backend.lookup_invoice(lookup.clone()).await.unwrap();
+        assert!(tx.settled_at.is_none());
+
+        // Paying an invoice of the same backend settles it
+        let params = PayInvoiceRequestParams {
+            id: None,
+            invoice: invoice.invoice.clone(),
+            amount: None,
+        };
+        let paid = backend.pay_invoice(params.clone()).await.unwrap();
+        assert_eq!(backend.balance(), 10_000);
+        let tx = backend.lookup_invoice(lookup).await.unwrap();
+        assert_eq!(tx.preimage, Some(paid.preimage));
+        assert!(tx.settled_at.is_some());
+
+        let err = backend.pay_invoice(params).await.unwrap_err();
+        assert_eq!(err.code, ErrorCode::PaymentFailed);
+
+        // Incoming settled and outgoing
+        let list = ListTransactionsRequestParams {
+            from: None,
+            until: None,
+            limit: None,
+            offset: None,
+            unpaid: None,
+            transaction_type: None,
+        };
+        let transactions = backend.list_transactions(list).await.unwrap();
+        assert_eq!(transactions.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_mock_keysend() {
+        let backend = MockLightningBackend::new(1_000);
+
+        let (preimage, payment_hash) = random_preimage();
+        let params = PayKeysendRequestParams {
+            id: None,
+            amount: 600,
+            pubkey: String::from("pubkey"),
+            preimage: Some(preimage.clone()),
+            tlv_records: Vec::new(),
+        };
+        let res = backend.pay_keysend(params.clone()).await.unwrap();
+        assert_eq!(res.preimage, preimage);
+        assert_eq!(backend.balance(), 400);
+
+        let lookup = LookupInvoiceRequestParams {
+            payment_hash: Some(payment_hash),
+            bolt11: None,
+        };
+        let tx = backend.lookup_invoice(lookup).await.unwrap();
+        assert_eq!(tx.transaction_type, Some(TransactionType::Outgoing));
+        assert_eq!(tx.amount, 600);
+
+        let err = backend.pay_keysend(params).await.unwrap_err();
+        assert_eq!(err.code, ErrorCode::InsufficientBalance);
+        assert_eq!(backend.balance(), 400);
+    }
+}
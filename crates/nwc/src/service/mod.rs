@@ -0,0 +1,648 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! NWC service
+//!
+//! Wallet side of NIP47: answer the requests of the connected apps with a [`LightningBackend`].
+
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use nostr::nips::nip04;
+use nostr::nips::nip47::{
+    ErrorCode, Method, NIP47Error, PayInvoiceRequestParams, PayInvoiceResponseResult,
+    PayKeysendRequestParams, PayKeysendResponseResult, Request, RequestParams, Response,
+    ResponseResult,
+};
+use nostr::{
+    Event, EventBuilder, EventId, Filter, JsonUtil, Keys, Kind, PublicKey, SubscriptionId, Tag,
+    Timestamp, Url,
+};
+use nostr_relay_pool::{
+    RelayPool, RelayPoolNotification, RelayPoolOptions, RelaySendOptions, SubscribeOptions,
+};
+
+pub mod backend;
+pub mod connection;
+pub mod mock;
+
+pub use self::backend::LightningBackend;
+pub use self::connection::{Budget, WalletConnection};
+use self::connection::{BudgetTracker, Reservation};
+pub use self::mock::MockLightningBackend;
+use crate::{Error, NostrWalletConnectOptions};
+
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug)]
+struct ConnectionState {
+    connection: WalletConnection,
+    budget: Option<BudgetTracker>,
+}
+
+/// Nostr Wallet Connect service
+#[derive(Debug, Clone)]
+pub struct NostrWalletConnectService {
+    keys: Keys,
+    pool: RelayPool,
+    subscription_id: SubscriptionId,
+    backend: Arc<dyn LightningBackend>,
+    connections: Arc<Mutex<HashMap<PublicKey, ConnectionState>>>,
+}
+
+impl NostrWalletConnectService {
+    /// New NWC service
+    pub async fn new<I, B>(keys: Keys, relays: I, backend: B) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = Url>,
+        B: LightningBackend + 'static,
+    {
+        Self::with_opts(keys, relays, backend, NostrWalletConnectOptions::default()).await
+    }
+
+    /// New NWC service with [`NostrWalletConnectOptions`]
+    pub async fn with_opts<I, B>(
+        keys: Keys,
+        relays: I,
+        backend: B,
+        opts: NostrWalletConnectOptions,
+    ) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = Url>,
+        B: LightningBackend + 'static,
+    {
+        let pool = RelayPool::new(RelayPoolOptions::default());
+        for url in relays.into_iter() {
+            pool.add_relay(url, opts.relay.clone()).await?;
+        }
+        pool.connect(Some(CONNECTION_TIMEOUT)).await;
+
+        Ok(Self {
+            keys,
+            pool,
+            subscription_id: SubscriptionId::generate(),
+            backend: Arc::new(backend),
+            connections: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    /// Get service [`PublicKey`]
+    pub fn public_key(&self) -> PublicKey {
+        self.keys.public_key()
+    }
+
+    /// Get supported methods
+    ///
+    /// `multi_pay_invoice` and `multi_pay_keysend` are supported if the backend supports the single payments.
+    pub fn methods(&self) -> Vec<Method> {
+        let mut methods: BTreeSet<Method> = self.backend.methods().into_iter().collect();
+        if methods.contains(&Method::PayInvoice) {
+            methods.insert(Method::MultiPayInvoice);
+        }
+        if methods.contains(&Method::PayKeysend) {
+            methods.insert(Method::MultiPayKeysend);
+        }
+        methods.into_iter().collect()
+    }
+
+    /// Add wallet connection
+    ///
+    /// If a connection with the same secret already exists, it's replaced and its budget reset.
+    pub fn add_connection(&self, connection: WalletConnection) {
+        let mut connections = self.connections.lock().expect("connections");
+        connections.insert(
+            connection.public_key(),
+            ConnectionState {
+                budget: connection.get_budget().map(BudgetTracker::new),
+                connection,
+            },
+        );
+    }
+
+    /// Remove wallet connection
+    pub fn remove_connection(&self, public_key: &PublicKey) {
+        let mut connections = self.connections.lock().expect("connections");
+        connections.remove(public_key);
+    }
+
+    /// Get wallet connections
+    pub fn connections(&self) -> Vec<WalletConnection> {
+        let connections = self.connections.lock().expect("connections");
+        connections
+            .values()
+            .map(|state| state.connection.clone())
+            .collect()
+    }
+
+    /// Publish info event (`kind:13194`), with the supported methods
+    pub async fn publish_info(&self) -> Result<EventId, Error> {
+        let methods: Vec<String> = self.methods().into_iter().map(|m| m.to_string()).collect();
+        let event: Event = EventBuilder::new(Kind::WalletConnectInfo, methods.join(" "), [])
+            .to_event(&self.keys)?;
+        Ok(self
+            .pool
+            .send_event(event, RelaySendOptions::new())
+            .await?
+            .id())
+    }
+
+    /// Publish the info event, listen for requests and answer them
+    ///
+    /// Requests are handled one at a time. Return when the service is shutdown.
+    pub async fn serve(&self) -> Result<(), Error> {
+        let mut notifications = self.pool.notifications();
+
+        self.publish_info().await?;
+
+        let filter = Filter::new()
+            .pubkey(self.keys.public_key())
+            .kind(Kind::WalletConnectRequest)
+            .since(Timestamp::now());
+        self.pool
+            .subscribe_with_id(
+                self.subscription_id.clone(),
+                vec![filter],
+                SubscribeOptions::default(),
+            )
+            .await;
+
+        while let Ok(notification) = notifications.recv().await {
+            match notification {
+                RelayPoolNotification::Event {
+                    subscription_id,
+                    event,
+                    ..
+                } => {
+                    if subscription_id == self.subscription_id
+                        && event.kind() == Kind::WalletConnectRequest
+                    {
+                        if let Err(e) = self.handle_event(&event).await {
+                            tracing::error!("Impossible to handle NWC request {}: {e}", event.id());
+                        }
+                    }
+                }
+                RelayPoolNotification::Shutdown => break,
+                _ => (),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Completely shutdown service
+    pub async fn shutdown(self) -> Result<(), Error> {
+        Ok(self.pool.shutdown().await?)
+    }
+
+    async fn handle_event(&self, event: &Event) -> Result<(), Error> {
+        // Expired requests must not be executed
+        if event.is_expired() {
+            tracing::warn!("Ignoring expired NWC request {}", event.id());
+            return Ok(());
+        }
+
+        let secret_key = self.keys.secret_key()?;
+        let content: String = nip04::decrypt(secret_key, event.author_ref(), event.content())?;
+        let req: Request = Request::from_json(content)?;
+
+        let connection: Option<WalletConnection> = self
+            .connections
+            .lock()
+            .expect("connections")
+            .get(event.author_ref())
+            .map(|state| state.connection.clone());
+
+        let responses: Vec<(Response, Option<String>)> = match connection {
+            Some(connection) => self.handle_request(&connection, req).await,
+            None => vec![(
+                response(
+                    req.method,
+                    Err(error(ErrorCode::Unauthorized, "no wallet connected")),
+                ),
+                None,
+            )],
+        };
+
+        for (res, identifier) in responses.into_iter() {
+            self.send_response(event, res, identifier).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn handle_request(
+        &self,
+        connection: &WalletConnection,
+        req: Request,
+    ) -> Vec<(Response, Option<String>)> {
+        let method: Method = req.method;
+
+        if !self.methods().contains(&method) {
+            let res = Err(error(ErrorCode::NotImplemented, "method not supported"));
+            return vec![(response(method, res), None)];
+        }
+
+        if !connection.is_allowed(&method) {
+            let res = Err(error(ErrorCode::Restricted, "method not allowed"));
+            return vec![(response(method, res), None)];
+        }
+
+        let res: Result<ResponseResult, NIP47Error> = match req.params {
+            RequestParams::PayInvoice(params) => self
+                .pay_invoice(connection, params)
+                .await
+                .map(ResponseResult::PayInvoice),
+            RequestParams::MultiPayInvoice(params) => {
+                let mut responses = Vec::with_capacity(params.invoices.len());
+                for params in params.invoices.into_iter() {
+                    let identifier: String =
+                        params.id.clone().unwrap_or_else(|| params.invoice.clone());
+                    let res = self
+                        .pay_invoice(connection, params)
+                        .await
+                        .map(ResponseResult::MultiPayInvoice);
+                    responses.push((response(method, res), Some(identifier)));
+                }
+                return responses;
+            }
+            RequestParams::PayKeysend(params) => self
+                .pay_keysend(connection, params)
+                .await
+                .map(ResponseResult::PayKeysend),
+            RequestParams::MultiPayKeysend(params) => {
+                let mut responses = Vec::with_capacity(params.keysends.len());
+                for params in params.keysends.into_iter() {
+                    let identifier: String =
+                        params.id.clone().unwrap_or_else(|| params.pubkey.clone());
+                    let res = self
+                        .pay_keysend(connection, params)
+                        .await
+                        .map(ResponseResult::MultiPayKeysend);
+                    responses.push((response(method, res), Some(identifier)));
+                }
+                return responses;
+            }
+            RequestParams::MakeInvoice(params) => self
+                .backend
+                .make_invoice(params)
+                .await
+                .map(ResponseResult::MakeInvoice),
+            RequestParams::LookupInvoice(params) => self
+                .backend
+                .lookup_invoice(params)
+                .await
+                .map(ResponseResult::LookupInvoice),
+            RequestParams::ListTransactions(params) => self
+                .backend
+                .list_transactions(params)
+                .await
+                .map(ResponseResult::ListTransactions),
+            RequestParams::GetBalance => self
+                .backend
+                .get_balance()
+                .await
+                .map(ResponseResult::GetBalance),
+            RequestParams::GetInfo => self.backend.get_info().await.map(|mut info| {
+                // Only the methods available for this connection
+                info.methods = self
+                    .methods()
+                    .into_iter()
+                    .filter(|m| connection.is_allowed(m))
+                    .map(|m| m.to_string())
+                    .collect();
+                ResponseResult::GetInfo(info)
+            }),
+        };
+
+        vec![(response(method, res), None)]
+    }
+
+    async fn pay_invoice(
+        &self,
+        connection: &WalletConnection,
+        params: PayInvoiceRequestParams,
+    ) -> Result<PayInvoiceResponseResult, NIP47Error> {
+        // The amount is needed only to enforce the budget
+        let amount: Option<u64> = match connection.get_budget() {
+            Some(..) => {
+                let amount: Option<u64> = self.backend.invoice_amount(&params.invoice).await?;
+                let amount: u64 = amount
+                    .or(params.amount)
+                    .ok_or_else(|| error(ErrorCode::Other, "amount required"))?;
+                Some(amount)
+            }
+            None => None,
+        };
+
+        let reservation: Option<Reservation> = self.reserve(connection, amount)?;
+        let res = self.backend.pay_invoice(params).await;
+        if res.is_err() {
+            self.release(connection, reservation);
+        }
+        res
+    }
+
+    async fn pay_keysend(
+        &self,
+        connection: &WalletConnection,
+        params: PayKeysendRequestParams,
+    ) -> Result<PayKeysendResponseResult, NIP47Error> {
+        let reservation: Option<Reservation> = self.reserve(connection, Some(params.amount))?;
+        let res = self.backend.pay_keysend(params).await;
+        if res.is_err() {
+            self.release(connection, reservation);
+        }
+        res
+    }
+
+    /// Reserve the amount from the connection budget
+    ///
+    /// Return `None` if the connection has no budget.
+    fn reserve(
+        &self,
+        connection: &WalletConnection,
+        amount: Option<u64>,
+    ) -> Result<Option<Reservation>, NIP47Error> {
+        let mut connections = self.connections.lock().expect("connections");
+        let budget: Option<&mut BudgetTracker> = connections
+            .get_mut(&connection.public_key())
+            .and_then(|state| state.budget.as_mut());
+        match (budget, amount) {
+            (Some(budget), Some(amount)) => match budget.reserve(amount, Timestamp::now()) {
+                Some(reservation) => Ok(Some(reservation)),
+                None => Err(error(ErrorCode::QuotaExceeded, "budget exceeded")),
+            },
+            _ => Ok(None),
+        }
+    }
+
+    /// Release the reservation of a failed payment
+    fn release(&self, connection: &WalletConnection, reservation: Option<Reservation>) {
+        let mut connections = self.connections.lock().expect("connections");
+        let budget: Option<&mut BudgetTracker> = connections
+            .get_mut(&connection.public_key())
+            .and_then(|state| state.budget.as_mut());
+        if let (Some(budget), Some(reservation)) = (budget, reservation) {
+            budget.release(reservation);
+        }
+    }
+
+    async fn send_response(
+        &self,
+        request: &Event,
+        res: Response,
+        identifier: Option<String>,
+    ) -> Result<(), Error> {
+        let content: String =
+            nip04::encrypt(self.keys.secret_key()?, request.author_ref(), res.as_json())?;
+
+        let mut tags: Vec<Tag> = vec![Tag::public_key(request.author()), Tag::event(request.id())];
+        if let Some(identifier) = identifier {
+            tags.push(Tag::Identifier(identifier));
+        }
+
+        let event: Event =
+            EventBuilder::new(Kind::WalletConnectResponse, content, tags).to_event(&self.keys)?;
+        self.pool.send_event(event, RelaySendOptions::new()).await?;
+        Ok(())
+    }
+}
+
+fn response(method: Method, res: Result<ResponseResult, NIP47Error>) -> Response {
+    match res {
+        Ok(result) => Response {
+            result_type: method,
+            error: None,
+            result: Some(result),
+        },
+        Err(e) => Response {
+            result_type: method,
+            error: Some(e),
+            result: None,
+        },
+    }
+}
+
+fn error<S>(code: ErrorCode, message: S) -> NIP47Error
+where
+    S: Into<String>,
+{
+    NIP47Error {
+        code,
+        message: message.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::nips::nip47::{
+        MakeInvoiceRequestParams, NostrWalletConnectURI, PayKeysendRequestParams,
+    };
+    use nostr::{ClientMessage, RelayMessage};
+    use nostr_relay_pool::{DuplexTransport, RelayOptions};
+    use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+    use super::*;
+
+    /// Service connected to a mock relay, with the receiver of the published events
+    async fn service(
+        backend: MockLightningBackend,
+    ) -> (NostrWalletConnectService, UnboundedReceiver<Event>) {
+        let (transport, mut listener) = DuplexTransport::new();
+        let opts = NostrWalletConnectOptions {
+            relay: RelayOptions::new().transport(transport),
+        };
+        let service =
+            NostrWalletConnectService::with_opts(Keys::generate(), [relay_url()], backend, opts)
+                .await
+                .unwrap();
+
+        let mut connection = listener.accept().await.unwrap();
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while let Some(msg) = connection.recv().await {
+                if let ClientMessage::Event(event) = msg {
+                    let _ = connection.send(RelayMessage::ok(event.id(), true, ""));
+                    let _ = tx.send(*event);
+                }
+            }
+        });
+
+        (service, rx)
+    }
+
+    fn relay_url() -> Url {
+        Url::parse("ws://relay.local").unwrap()
+    }
+
+    fn uri(
+        service: &NostrWalletConnectService,
+        connection: &WalletConnection,
+    ) -> NostrWalletConnectURI {
+        connection.uri(service.public_key(), relay_url(), None)
+    }
+
+    fn keysend(amount: u64) -> Request {
+        Request::pay_keysend(PayKeysendRequestParams {
+            id: None,
+            amount,
+            pubkey: String::from("pubkey"),
+            preimage: None,
+            tlv_records: Vec::new(),
+        })
+    }
+
+    /// Send request to the service and decrypt the response
+    async fn request(
+        service: &NostrWalletConnectService,
+        events: &mut UnboundedReceiver<Event>,
+        uri: &NostrWalletConnectURI,
+        req: Request,
+    ) -> Response {
+        let event: Event = req.to_event(uri).unwrap();
+        service.handle_event(&event).await.unwrap();
+
+        let res: Event = events.recv().await.unwrap();
+        assert_eq!(res.kind(), Kind::WalletConnectResponse);
+        let content: String =
+            nip04::decrypt(&uri.secret, &service.public_key(), res.content()).unwrap();
+        Response::from_json(content).unwrap()
+    }
+
+    fn error_code(res: Response) -> Option<ErrorCode> {
+        res.error.map(|e| e.code)
+    }
+
+    #[tokio::test]
+    async fn test_unknown_connection() {
+        let (service, mut events) = service(MockLightningBackend::new(10_000)).await;
+
+        let connection = WalletConnection::generate();
+        let uri = uri(&service, &connection);
+        let res = request(&service, &mut events, &uri, Request::get_balance()).await;
+        assert_eq!(error_code(res), Some(ErrorCode::Unauthorized));
+    }
+
+    #[tokio::test]
+    async fn test_restricted_methods() {
+        let (service, mut events) = service(MockLightningBackend::new(10_000)).await;
+
+        let connection =
+            WalletConnection::generate().methods([Method::GetBalance, Method::GetInfo]);
+        let uri = uri(&service, &connection);
+        service.add_connection(connection);
+
+        let res = request(&service, &mut events, &uri, Request::get_balance()).await;
+        assert_eq!(res.to_get_balance().unwrap().balance, 10_000);
+
+        let res = request(&service, &mut events, &uri, keysend(1_000)).await;
+        assert_eq!(error_code(res), Some(ErrorCode::Restricted));
+
+        let res = request(&service, &mut events, &uri, Request::get_info()).await;
+        let info = res.to_get_info().unwrap();
+        assert_eq!(info.methods.len(), 2);
+        assert!(info.methods.contains(&Method::GetBalance.to_string()));
+        assert!(info.methods.contains(&Method::GetInfo.to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_budget() {
+        let backend = MockLightningBackend::new(10_000);
+        let (service, mut events) = service(backend.clone()).await;
+
+        let connection = WalletConnection::generate().budget(Budget::new(8_000));
+        let uri = uri(&service, &connection);
+        service.add_connection(connection);
+
+        // Over budget
+        let res = request(&service, &mut events, &uri, keysend(12_000)).await;
+        assert_eq!(error_code(res), Some(ErrorCode::QuotaExceeded));
+        let res = request(&service, &mut events, &uri, keysend(8_000)).await;
+        assert!(res.to_pay_keysend().is_ok());
+        assert_eq!(backend.balance(), 2_000);
+
+        let res = request(&service, &mut events, &uri, keysend(1)).await;
+        assert_eq!(error_code(res), Some(ErrorCode::QuotaExceeded));
+        assert_eq!(backend.balance(), 2_000);
+    }
+
+    #[tokio::test]
+    async fn test_failed_payment_releases_budget() {
+        let backend = MockLightningBackend::new(5_000);
+        let (service, mut events) = service(backend.clone()).await;
+
+        let connection = WalletConnection::generate().budget(Budget::new(10_000));
+        let uri = uri(&service, &connection);
+        service.add_connection(connection);
+
+        let res = request(&service, &mut events, &uri, keysend(8_000)).await;
+        assert_eq!(error_code(res), Some(ErrorCode::InsufficientBalance));
+        let res = request(&service, &mut events, &uri, keysend(5_000)).await;
+        assert!(res.to_pay_keysend().is_ok());
+        assert_eq!(backend.balance(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_pay_invoice() {
+        let backend = MockLightningBackend::new(10_000);
+        let (service, mut events) = service(backend.clone()).await;
+
+        let connection = WalletConnection::generate().budget(Budget::new(5_000));
+        let uri = uri(&service, &connection);
+        service.add_connection(connection);
+
+        // Invoice made by the same backend: paying it settles it
+        let req = Request::make_invoice(MakeInvoiceRequestParams {
+            amount: 4_000,
+            description: None,
+            description_hash: None,
+            expiry: None,
+        });
+        let res = request(&service, &mut events, &uri, req).await;
+        let invoice = res.to_make_invoice().unwrap();
+
+        let req = Request::pay_invoice(PayInvoiceRequestParams {
+            id: None,
+            invoice: invoice.invoice.clone(),
+            amount: None,
+        });
+        let res = request(&service, &mut events, &uri, req.clone()).await;
+        assert!(res.to_pay_invoice().is_ok());
+        assert_eq!(backend.balance(), 10_000);
+
+        // The amount of the invoice is taken from the budget
+        let res = request(&service, &mut events, &uri, req).await;
+        assert_eq!(error_code(res), Some(ErrorCode::QuotaExceeded));
+    }
+
+    #[tokio::test]
+    async fn test_expired_request() {
+        let (service, mut events) = service(MockLightningBackend::new(10_000)).await;
+
+        let connection = WalletConnection::generate();
+        let uri = uri(&service, &connection);
+        service.add_connection(connection);
+
+        let keys = Keys::new(uri.secret.clone());
+        let content: String = nip04::encrypt(
+            &uri.secret,
+            &service.public_key(),
+            Request::get_balance().as_json(),
+        )
+        .unwrap();
+        let tags = [
+            Tag::public_key(service.public_key()),
+            Tag::Expiration(Timestamp::now() - Duration::from_secs(60)),
+        ];
+        let event = EventBuilder::new(Kind::WalletConnectRequest, content, tags)
+            .to_event(&keys)
+            .unwrap();
+        service.handle_event(&event).await.unwrap();
+
+        // Not answered
+        let res = tokio::time::timeout(Duration::from_millis(200), events.recv()).await;
+        assert!(res.is_err());
+    }
+}